@@ -0,0 +1,530 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::{Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+use ast_grep_config::{
+  CombinedScan, Metadata, RuleCollection, RuleConfig, SerializableRuleConfig, Severity,
+  SECURITY_RULESETS,
+};
+use ast_grep_language::SupportLang;
+use clap::{Args, ValueEnum};
+use ignore::WalkParallel;
+use serde::Deserialize;
+use serde_yaml::with::singleton_map_recursive;
+use serde_yaml::Value as YamlValue;
+
+use crate::config::{with_rule_stats, ProjectConfig};
+use crate::lang::SgLang;
+use crate::print::{Diff, PrintProcessor, Printer, SimpleFile};
+use crate::utils::ErrorContext as EC;
+use crate::utils::{filter_file_rule, Granularity, InputArgs, ParseCache};
+use crate::utils::{FileTrace, ScanTrace};
+use crate::utils::{Items, PathWorker, Worker};
+
+type NodeMatch<'a> = ast_grep_core::NodeMatch<'a, ast_grep_core::tree_sitter::StrDoc<SgLang>>;
+
+#[derive(Args)]
+pub struct AuditArg {
+  /// Fail if there is any finding of at least this severity.
+  ///
+  /// Findings of rules with `error` severity are high, `warning` medium,
+  /// and `info` or `hint` low.
+  #[clap(long, default_value = "high", value_name = "LEVEL")]
+  fail_on: AuditLevel,
+
+  /// Run only the project's security rules, not the builtin ones.
+  ///
+  /// Project rules are security rules if their metadata has a `cwe`
+  /// or `category: security`.
+  #[clap(long)]
+  no_builtin: bool,
+
+  /// input related options
+  #[clap(flatten)]
+  input: InputArgs,
+}
+
+/// The severity of a finding in an audit, by the severity of its rule.
+#[derive(Clone, Copy, ValueEnum, PartialEq, Eq, PartialOrd, Ord)]
+enum AuditLevel {
+  Low,
+  Medium,
+  High,
+}
+
+impl AuditLevel {
+  fn from_severity(severity: &Severity) -> Self {
+    match severity {
+      Severity::Error => Self::High,
+      Severity::Warning => Self::Medium,
+      _ => Self::Low,
+    }
+  }
+}
+
+impl fmt::Display for AuditLevel {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::High => write!(f, "high"),
+      Self::Medium => write!(f, "medium"),
+      Self::Low => write!(f, "low"),
+    }
+  }
+}
+
+pub fn run_audit(arg: AuditArg, project: Result<ProjectConfig>) -> Result<ExitCode> {
+  if arg.input.stdin {
+    return Err(anyhow::anyhow!(EC::StdInIsNotSupported("audit")));
+  }
+  let worker = AuditWorker::try_new(arg, project)?;
+  let printer = AuditPrinter::stdout(worker.builtin_count, worker.project_count, worker.fail_on);
+  worker.run_path(printer)
+}
+
+struct AuditWorker {
+  input: InputArgs,
+  fail_on: AuditLevel,
+  configs: RuleCollection<SgLang>,
+  trace: ScanTrace,
+  proj_dir: PathBuf,
+  builtin_count: usize,
+  project_count: usize,
+  fail_count: AtomicUsize,
+}
+
+impl AuditWorker {
+  fn try_new(arg: AuditArg, project: Result<ProjectConfig>) -> Result<Self> {
+    let mut proj_dir = PathBuf::from(".");
+    // without a project, the builtin rules still make a useful audit
+    let mut rules = match project {
+      Ok(project) => {
+        proj_dir = project.project_dir.clone();
+        let mut rules = project.read_rules()?;
+        rules.retain(|rule| is_security_rule(rule.metadata.as_ref()));
+        rules
+      }
+      Err(e) if arg.no_builtin => return Err(e),
+      Err(_) => vec![],
+    };
+    let project_count = rules.len();
+    let builtin_count = if arg.no_builtin {
+      0
+    } else {
+      // project rules take precedence over builtin rules of the same id
+      let ids: HashSet<_> = rules.iter().map(|r| r.id.clone()).collect();
+      let mut builtins = builtin_rules()?;
+      builtins.retain(|rule| !ids.contains(&rule.id));
+      let count = builtins.len();
+      rules.extend(builtins);
+      count
+    };
+    let (configs, rule_trace) = with_rule_stats(rules)?;
+    let absolute_proj_dir = proj_dir
+      .canonicalize()
+      .or_else(|_| std::env::current_dir())?;
+    Ok(Self {
+      input: arg.input,
+      fail_on: arg.fail_on,
+      configs,
+      trace: Granularity::Nothing.scan_trace(rule_trace),
+      proj_dir: absolute_proj_dir,
+      builtin_count,
+      project_count,
+      fail_count: AtomicUsize::new(0),
+    })
+  }
+}
+
+fn is_security_rule(metadata: Option<&Metadata>) -> bool {
+  let Some(metadata) = metadata else {
+    return false;
+  };
+  let category = metadata.get("category").and_then(YamlValue::as_str);
+  metadata.get("cwe").is_some() || category == Some("security")
+}
+
+/// The CWE id in a rule's metadata, e.g. `CWE-798` for both `cwe: CWE-798` and `cwe: 798`.
+fn cwe(metadata: Option<&Metadata>) -> Option<String> {
+  match metadata?.get("cwe")? {
+    YamlValue::String(cwe) => Some(cwe.clone()),
+    YamlValue::Number(n) => Some(format!("CWE-{n}")),
+    _ => None,
+  }
+}
+
+/// The rules of every bundled ruleset, compiled for each language of the ruleset.
+fn builtin_rules() -> Result<Vec<RuleConfig<SgLang>>> {
+  let mut rules = vec![];
+  for ruleset in SECURITY_RULESETS {
+    let mut langs = vec![];
+    for name in ruleset.languages {
+      let Ok(lang) = SupportLang::from_str(name) else {
+        continue;
+      };
+      if !langs.iter().any(|(l, _)| *l == lang) {
+        langs.push((lang, *name));
+      }
+    }
+    let context = || EC::ParseRule(format!("builtin:{}", ruleset.name).into());
+    for document in serde_yaml::Deserializer::from_str(ruleset.yaml) {
+      let value = YamlValue::deserialize(document).with_context(context)?;
+      for (_, name) in &langs {
+        let mut value = value.clone();
+        if let YamlValue::Mapping(map) = &mut value {
+          map.insert("language".into(), (*name).into());
+        }
+        let config: SerializableRuleConfig<SgLang> =
+          singleton_map_recursive::deserialize(value).with_context(context)?;
+        let rule = RuleConfig::try_from(config, &Default::default()).with_context(context)?;
+        rules.push(rule);
+      }
+    }
+  }
+  Ok(rules)
+}
+
+impl Worker for AuditWorker {
+  fn consume_items<P: Printer>(
+    &self,
+    items: Items<P::Processed>,
+    mut printer: P,
+  ) -> Result<ExitCode> {
+    printer.before_print()?;
+    for item in items {
+      printer.process(item)?;
+    }
+    printer.after_print()?;
+    let fail_count = self.fail_count.load(Ordering::Acquire);
+    if fail_count > 0 {
+      let level = self.fail_on.to_string();
+      Err(anyhow::anyhow!(EC::AuditFailure(fail_count, level)))
+    } else {
+      Ok(ExitCode::SUCCESS)
+    }
+  }
+}
+
+impl PathWorker for AuditWorker {
+  fn get_input(&self) -> &InputArgs {
+    &self.input
+  }
+  fn get_trace(&self) -> &FileTrace {
+    &self.trace.inner.file_trace
+  }
+  fn build_walk(&self) -> Result<WalkParallel> {
+    let mut langs = HashSet::new();
+    self.configs.for_each_rule(|rule| {
+      langs.insert(rule.language);
+    });
+    self.input.walk_langs(langs.into_iter())
+  }
+  fn produce_item<P: Printer>(
+    &self,
+    path: &Path,
+    processor: &P::Processor,
+  ) -> Result<Vec<P::Processed>> {
+    let items = filter_file_rule(path, &self.configs, &self.trace, &ParseCache::default())?;
+    let mut fail_count = 0usize;
+    let mut ret = vec![];
+    for grep in items {
+      // use path relative to project director
+      let abs_path = path.canonicalize()?;
+      let normalized_path = abs_path.strip_prefix(&self.proj_dir).unwrap_or(path);
+      let rules = self
+        .configs
+        .get_rule_from_lang(normalized_path, *grep.lang());
+      let combined = CombinedScan::new(rules);
+      let scanned = combined.scan(&grep, /* separate_fix*/ false);
+      for (rule, matches) in scanned.matches {
+        if AuditLevel::from_severity(&rule.severity) >= self.fail_on {
+          fail_count = fail_count.saturating_add(matches.len());
+        }
+        let file = SimpleFile::new(path.to_string_lossy(), grep.source());
+        ret.push(processor.print_rule(matches, file, rule)?);
+      }
+    }
+    self.fail_count.fetch_add(fail_count, Ordering::AcqRel);
+    Ok(ret)
+  }
+}
+
+struct Finding {
+  level: AuditLevel,
+  cwe: Option<String>,
+  path: String,
+  line: usize,
+  column: usize,
+  rule_id: String,
+  message: String,
+}
+
+struct AuditProcessor;
+
+impl PrintProcessor<Vec<Finding>> for AuditProcessor {
+  fn print_rule(
+    &self,
+    matches: Vec<NodeMatch>,
+    file: SimpleFile<Cow<str>, &str>,
+    rule: &RuleConfig<SgLang>,
+  ) -> Result<Vec<Finding>> {
+    let path = file.name().to_string();
+    let level = AuditLevel::from_severity(&rule.severity);
+    let cwe = cwe(rule.metadata.as_ref());
+    let findings = matches
+      .iter()
+      .map(|nm| {
+        let start = nm.start_pos();
+        Finding {
+          level,
+          cwe: cwe.clone(),
+          path: path.clone(),
+          line: start.line() + 1,
+          column: start.column(nm) + 1,
+          rule_id: rule.id.clone(),
+          message: rule.get_message(nm),
+        }
+      })
+      .collect();
+    Ok(findings)
+  }
+  // the audit scan does not separate fixes, so every match is printed by `print_rule`
+  fn print_matches(&self, _: Vec<NodeMatch>, _: &Path) -> Result<Vec<Finding>> {
+    Ok(vec![])
+  }
+  fn print_diffs(&self, _: Vec<Diff>, _: &Path) -> Result<Vec<Finding>> {
+    Ok(vec![])
+  }
+  fn print_rule_diffs(
+    &self,
+    _: Vec<(Diff, &RuleConfig<SgLang>)>,
+    _: &Path,
+  ) -> Result<Vec<Finding>> {
+    Ok(vec![])
+  }
+}
+
+/// Prints findings grouped by severity and CWE, and a summary of the audit.
+struct AuditPrinter<W: Write> {
+  writer: W,
+  findings: Vec<Finding>,
+  builtin_count: usize,
+  project_count: usize,
+  fail_on: AuditLevel,
+}
+
+impl AuditPrinter<Stdout> {
+  fn stdout(builtin_count: usize, project_count: usize, fail_on: AuditLevel) -> Self {
+    Self {
+      writer: std::io::stdout(),
+      findings: vec![],
+      builtin_count,
+      project_count,
+      fail_on,
+    }
+  }
+}
+
+impl<W: Write> Printer for AuditPrinter<W> {
+  type Processed = Vec<Finding>;
+  type Processor = AuditProcessor;
+
+  fn get_processor(&self) -> Self::Processor {
+    AuditProcessor
+  }
+  fn process(&mut self, processed: Self::Processed) -> Result<()> {
+    self.findings.extend(processed);
+    Ok(())
+  }
+  fn after_print(&mut self) -> Result<()> {
+    self.print_findings()?;
+    self.print_summary()?;
+    Ok(())
+  }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+  if count == 1 {
+    format!("{count} {noun}")
+  } else {
+    format!("{count} {noun}s")
+  }
+}
+
+/// The number of findings by CWE, most common first.
+fn count_by_cwe<'a>(findings: impl Iterator<Item = &'a Finding>) -> Vec<(&'a str, usize)> {
+  let mut counts = BTreeMap::new();
+  for finding in findings {
+    let cwe = finding.cwe.as_deref().unwrap_or("no CWE");
+    *counts.entry(cwe).or_insert(0) += 1;
+  }
+  let mut counts: Vec<_> = counts.into_iter().collect();
+  counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+  counts
+}
+
+impl<W: Write> AuditPrinter<W> {
+  fn print_findings(&mut self) -> Result<()> {
+    self.findings.sort_by(|a, b| {
+      let key = |f: &Finding| (f.path.clone(), f.line, f.column);
+      key(a).cmp(&key(b))
+    });
+    let writer = &mut self.writer;
+    for level in [AuditLevel::High, AuditLevel::Medium, AuditLevel::Low] {
+      let findings: Vec<_> = self.findings.iter().filter(|f| f.level == level).collect();
+      if findings.is_empty() {
+        continue;
+      }
+      let heading = level.to_string().to_uppercase();
+      writeln!(writer, "{heading}: {}", plural(findings.len(), "finding"))?;
+      for (cwe, count) in count_by_cwe(findings.iter().copied()) {
+        writeln!(writer, "  {cwe} ({count})")?;
+        let in_group = findings
+          .iter()
+          .filter(|f| f.cwe.as_deref().unwrap_or("no CWE") == cwe);
+        for f in in_group {
+          let location = format!("{}:{}:{}", f.path, f.line, f.column);
+          writeln!(writer, "    {location} {}: {}", f.rule_id, f.message)?;
+        }
+      }
+      writeln!(writer)?;
+    }
+    Ok(())
+  }
+
+  fn print_summary(&mut self) -> Result<()> {
+    let findings = &self.findings;
+    let writer = &mut self.writer;
+    let count = |level| findings.iter().filter(|f| f.level == level).count();
+    let files: HashSet<_> = findings.iter().map(|f| &f.path).collect();
+    writeln!(writer, "Security audit summary")?;
+    writeln!(
+      writer,
+      "  Rules: {} ({} builtin, {} project)",
+      self.builtin_count + self.project_count,
+      self.builtin_count,
+      self.project_count,
+    )?;
+    writeln!(
+      writer,
+      "  Findings: {} in {} ({} high, {} medium, {} low)",
+      findings.len(),
+      plural(files.len(), "file"),
+      count(AuditLevel::High),
+      count(AuditLevel::Medium),
+      count(AuditLevel::Low),
+    )?;
+    if !findings.is_empty() {
+      let weaknesses: Vec<_> = count_by_cwe(findings.iter())
+        .into_iter()
+        .map(|(cwe, count)| format!("{cwe} ({count})"))
+        .collect();
+      writeln!(writer, "  Weaknesses: {}", weaknesses.join(", "))?;
+    }
+    let failing = findings.iter().filter(|f| f.level >= self.fail_on).count();
+    let fail_on = self.fail_on;
+    if failing > 0 {
+      let found = plural(failing, "finding");
+      writeln!(
+        writer,
+        "  Result: failed, {found} of {fail_on} or higher severity"
+      )?;
+    } else {
+      writeln!(
+        writer,
+        "  Result: passed, no finding of {fail_on} or higher severity"
+      )?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn printer(findings: Vec<Finding>, fail_on: AuditLevel) -> String {
+    let mut printer = AuditPrinter {
+      writer: vec![],
+      findings: vec![],
+      builtin_count: 2,
+      project_count: 1,
+      fail_on,
+    };
+    printer.process(findings).expect("should process");
+    printer.after_print().expect("should print");
+    String::from_utf8(printer.writer).expect("should be utf8")
+  }
+
+  fn finding(level: AuditLevel, cwe: Option<&str>, line: usize) -> Finding {
+    Finding {
+      level,
+      cwe: cwe.map(String::from),
+      path: "a.js".into(),
+      line,
+      column: 1,
+      rule_id: "rule".into(),
+      message: "message".into(),
+    }
+  }
+
+  #[test]
+  fn test_builtin_rules() {
+    let rules = builtin_rules().expect("builtin rules should compile");
+    assert!(rules.iter().all(|r| cwe(r.metadata.as_ref()).is_some()));
+    let langs: HashSet<_> = rules.iter().map(|r| r.language.to_string()).collect();
+    assert_eq!(langs.len(), 5);
+  }
+
+  #[test]
+  fn test_security_rule() {
+    let metadata = |yaml: &str| serde_yaml::from_str::<Metadata>(yaml).expect("should parse");
+    assert!(is_security_rule(Some(&metadata("cwe: CWE-78"))));
+    assert!(is_security_rule(Some(&metadata("category: security"))));
+    assert!(!is_security_rule(Some(&metadata("category: style"))));
+    assert!(!is_security_rule(None));
+    assert_eq!(cwe(Some(&metadata("cwe: 78"))).as_deref(), Some("CWE-78"));
+  }
+
+  #[test]
+  fn test_print_report() {
+    let findings = vec![
+      finding(AuditLevel::Medium, Some("CWE-330"), 3),
+      finding(AuditLevel::High, Some("CWE-95"), 2),
+      finding(AuditLevel::High, None, 1),
+      finding(AuditLevel::High, Some("CWE-95"), 4),
+    ];
+    let report = printer(findings, AuditLevel::High);
+    let expected = "\
+HIGH: 3 findings
+  CWE-95 (2)
+    a.js:2:1 rule: message
+    a.js:4:1 rule: message
+  no CWE (1)
+    a.js:1:1 rule: message
+
+MEDIUM: 1 finding
+  CWE-330 (1)
+    a.js:3:1 rule: message
+
+Security audit summary
+  Rules: 3 (2 builtin, 1 project)
+  Findings: 4 in 1 file (3 high, 1 medium, 0 low)
+  Weaknesses: CWE-95 (2), CWE-330 (1), no CWE (1)
+  Result: failed, 3 findings of high or higher severity
+";
+    assert_eq!(report, expected);
+  }
+
+  #[test]
+  fn test_print_passed() {
+    let findings = vec![finding(AuditLevel::Low, Some("CWE-330"), 1)];
+    let report = printer(findings, AuditLevel::Medium);
+    assert!(report.contains("Result: passed, no finding of medium or higher severity"));
+  }
+}
@@ -1,10 +1,14 @@
 use crate::lang::{CustomLang, LanguageGlobs, SerializableInjection, SgLang};
-use crate::utils::{ErrorContext as EC, RuleOverwrite, RuleTrace};
+use crate::utils::{
+  hex_digest, ErrorContext as EC, ExitCodes, RemoteRuleset, RuleOverwrite, RuleTrace,
+};
 
 use anyhow::{Context, Result};
 use ast_grep_config::{
-  from_str, from_yaml_string, DeserializeEnv, GlobalRules, RuleCollection, RuleConfig,
+  diagnose_rule, from_str, from_yaml_string, DeserializeEnv, GlobalRules, PublicKey,
+  RuleCollection, RuleConfig, Severity,
 };
+use ast_grep_dynamic::LibraryPath;
 use ast_grep_language::config_file_type;
 use ignore::WalkBuilder;
 use serde::{Deserialize, Serialize};
@@ -52,8 +56,28 @@ pub struct AstGrepConfig {
   /// injection config for embedded languages
   #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub language_injections: Vec<SerializableInjection>,
+  /// severities of the findings that fail `ast-grep scan`, error by default
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub fail_on: Option<Vec<Severity>>,
+  /// severities of rules overwritten by rule id
+  #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+  pub severities: HashMap<String, Severity>,
+  /// exit codes of `ast-grep scan`
+  #[serde(default, skip_serializing_if = "ExitCodes::is_unset")]
+  pub exit_codes: ExitCodes,
+  /// rulesets published at URLs and pinned by digest, like `URL@sha256:<digest>`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub extends: Vec<String>,
+  /// minisign public keys, one of which must have signed each ruleset in `extends`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub trusted_keys: Vec<String>,
+  /// directory to cache fetched rulesets and scan results, relative to the project directory
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cache_dir: Option<PathBuf>,
 }
 
+pub const DEFAULT_CACHE_DIR: &str = ".ast-grep/cache";
+
 #[derive(Clone)]
 pub struct ProjectConfig {
   pub project_dir: PathBuf,
@@ -63,6 +87,19 @@ pub struct ProjectConfig {
   pub test_configs: Option<Vec<TestConfig>>,
   /// util rules directories
   pub util_dirs: Option<Vec<PathBuf>>,
+  /// severities of the findings that fail a scan
+  pub fail_on: Option<Vec<Severity>>,
+  /// severities of rules overwritten by rule id
+  pub severities: HashMap<String, Severity>,
+  pub exit_codes: ExitCodes,
+  /// remote rulesets used along with the rules in rule directories
+  pub extends: Vec<RemoteRuleset>,
+  /// keys that must have signed the extended rulesets, if any
+  pub trusted_keys: Vec<PublicKey>,
+  /// directory of fetched rulesets and scan results
+  pub cache_dir: PathBuf,
+  /// the config as read, before its parts are moved into the fields above
+  sg_config: AstGrepConfig,
 }
 
 impl ProjectConfig {
@@ -86,8 +123,91 @@ impl ProjectConfig {
     &self,
     rule_overwrite: RuleOverwrite,
   ) -> Result<(RuleCollection<SgLang>, RuleTrace)> {
+    let configs = self.read_rules()?;
+    let total_rule_count = configs.len();
+
+    let configs = rule_overwrite.process_configs(configs)?;
+    let collection = RuleCollection::try_new(configs).context(EC::GlobPattern)?;
+    let effective_rule_count = collection.total_rule_count();
+    let trace = RuleTrace {
+      file_trace: Default::default(),
+      effective_rule_count,
+      skipped_rule_count: total_rule_count - effective_rule_count,
+    };
+    Ok((collection, trace))
+  }
+
+  /// All rules in the rule directories and the extended rulesets, with the project's
+  /// utility rules. Rulesets not cached yet are fetched.
+  pub fn read_rules(&self) -> Result<Vec<RuleConfig<SgLang>>> {
     let global_rules = find_util_rules(self)?;
-    read_directory_yaml(self, global_rules, rule_overwrite)
+    let mut walkers: Vec<_> = self
+      .rule_dirs
+      .iter()
+      .map(|dir| {
+        let dir_path = self.project_dir.join(dir);
+        let walker = WalkBuilder::new(&dir_path);
+        (dir_path, walker)
+      })
+      .collect();
+    for ruleset in &self.extends {
+      let dir_path = ruleset.fetch(&self.cache_dir, &self.trusted_keys)?;
+      let mut walker = WalkBuilder::new(&dir_path);
+      // the cache directory is likely ignored by the project
+      walker.parents(false);
+      walkers.push((dir_path, walker));
+    }
+    read_directory_yaml(walkers, global_rules)
+  }
+
+  /// The text of the utility rule files in the util directories.
+  pub fn read_util_files(&self) -> Result<Vec<String>> {
+    let Some(mut walker) = build_util_walker(&self.project_dir, &self.util_dirs) else {
+      return Ok(vec![]);
+    };
+    let mut files = vec![];
+    let walker = walker.types(config_file_type()).build();
+    for dir in walker {
+      let config_file = dir.with_context(|| EC::WalkRuleDir(PathBuf::new()))?;
+      // file_type is None only if it is stdin, safe to panic here
+      if !config_file
+        .file_type()
+        .expect("file type should be available for non-stdin")
+        .is_file()
+      {
+        continue;
+      }
+      files.push(read_to_string(config_file.path())?);
+    }
+    Ok(files)
+  }
+
+  /// Everything in the config that decides the findings of a cached scan besides the rule
+  /// files: the config itself with custom languages, language globs and injections, and
+  /// the digests of the custom language parsers.
+  pub fn read_config_inputs(&self) -> Result<Vec<String>> {
+    // serde_json sorts the keys of a Value, so the text does not depend on HashMap order
+    let config = serde_json::to_value(&self.sg_config)?;
+    let mut parsers = vec![];
+    let custom_langs = self
+      .sg_config
+      .custom_languages
+      .iter()
+      .flat_map(HashMap::values);
+    for custom_lang in custom_langs {
+      let paths: Vec<_> = match &custom_lang.library_path {
+        LibraryPath::Single(path) => vec![path],
+        LibraryPath::Platform(paths) => paths.values().collect(),
+      };
+      for path in paths {
+        // parsers of other platforms are usually missing
+        if let Ok(parser) = std::fs::read(self.project_dir.join(path)) {
+          parsers.push(hex_digest([parser]));
+        }
+      }
+    }
+    parsers.sort();
+    Ok(std::iter::once(config.to_string()).chain(parsers).collect())
   }
 
   /// returns a Result of Result.
@@ -97,11 +217,35 @@ impl ProjectConfig {
     let Some((project_dir, mut sg_config)) = Self::discover_project(config_path)? else {
       return Ok(Err(anyhow::anyhow!(EC::ProjectNotExist)));
     };
+    let resolved = sg_config.clone();
+    let extends = sg_config
+      .extends
+      .iter()
+      .map(|s| s.parse())
+      .collect::<Result<_>>()
+      .context(EC::ParseConfiguration)?;
+    let trusted_keys = sg_config
+      .trusted_keys
+      .iter()
+      .map(|key| key.parse())
+      .collect::<Result<_, _>>()
+      .context(EC::ParseConfiguration)?;
+    let cache_dir = sg_config
+      .cache_dir
+      .take()
+      .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_DIR));
     let config = ProjectConfig {
+      cache_dir: project_dir.join(cache_dir),
+      extends,
+      trusted_keys,
       project_dir,
       rule_dirs: sg_config.rule_dirs.drain(..).collect(),
       test_configs: sg_config.test_configs.take(),
       util_dirs: sg_config.util_dirs.take(),
+      fail_on: sg_config.fail_on.take(),
+      severities: std::mem::take(&mut sg_config.severities),
+      exit_codes: sg_config.exit_codes,
+      sg_config: resolved,
     };
     // sg_config will not use rule dirs and test configs anymore
     register_custom_language(&config.project_dir, sg_config)?;
@@ -131,28 +275,8 @@ fn build_util_walker(base_dir: &Path, util_dirs: &Option<Vec<PathBuf>>) -> Optio
 }
 
 fn find_util_rules(config: &ProjectConfig) -> Result<GlobalRules> {
-  let ProjectConfig {
-    project_dir,
-    util_dirs,
-    ..
-  } = config;
-  let Some(mut walker) = build_util_walker(project_dir, util_dirs) else {
-    return Ok(GlobalRules::default());
-  };
   let mut utils = vec![];
-  let walker = walker.types(config_file_type()).build();
-  for dir in walker {
-    let config_file = dir.with_context(|| EC::WalkRuleDir(PathBuf::new()))?;
-    // file_type is None only if it is stdin, safe to panic here
-    if !config_file
-      .file_type()
-      .expect("file type should be available for non-stdin")
-      .is_file()
-    {
-      continue;
-    }
-    let path = config_file.path();
-    let file = read_to_string(path)?;
+  for file in config.read_util_files()? {
     let new_configs = from_str(&file)?;
     utils.push(new_configs);
   }
@@ -162,21 +286,12 @@ fn find_util_rules(config: &ProjectConfig) -> Result<GlobalRules> {
 }
 
 fn read_directory_yaml(
-  config: &ProjectConfig,
+  walkers: Vec<(PathBuf, WalkBuilder)>,
   global_rules: GlobalRules,
-  rule_overwrite: RuleOverwrite,
-) -> Result<(RuleCollection<SgLang>, RuleTrace)> {
+) -> Result<Vec<RuleConfig<SgLang>>> {
   let mut configs = vec![];
-  let ProjectConfig {
-    project_dir,
-    rule_dirs,
-    ..
-  } = config;
-  for dir in rule_dirs {
-    let dir_path = project_dir.join(dir);
-    let walker = WalkBuilder::new(&dir_path)
-      .types(config_file_type())
-      .build();
+  for (dir_path, mut walker) in walkers {
+    let walker = walker.types(config_file_type()).build();
     for dir in walker {
       let config_file = dir.with_context(|| EC::WalkRuleDir(dir_path.clone()))?;
       // file_type is None only if it is stdin, safe to panic here
@@ -206,17 +321,7 @@ fn read_directory_yaml(
   {
     return Err(anyhow::anyhow!(EC::DuplicateRuleId(duplicated_id.into())));
   }
-  let total_rule_count = configs.len();
-
-  let configs = rule_overwrite.process_configs(configs)?;
-  let collection = RuleCollection::try_new(configs).context(EC::GlobPattern)?;
-  let effective_rule_count = collection.total_rule_count();
-  let trace = RuleTrace {
-    file_trace: Default::default(),
-    effective_rule_count,
-    skipped_rule_count: total_rule_count - effective_rule_count,
-  };
-  Ok((collection, trace))
+  Ok(configs)
 }
 
 pub fn with_rule_stats(
@@ -243,7 +348,19 @@ pub fn read_rule_file(
   } else {
     from_yaml_string(&yaml, &Default::default())
   };
-  let mut rules = parsed.with_context(|| EC::ParseRule(path.to_path_buf()))?;
+  let mut rules = match parsed {
+    Ok(rules) => rules,
+    Err(e) => {
+      let globals = global_rules.cloned().unwrap_or_default();
+      let located = locate_rule_errors(&yaml, &globals);
+      // the located errors are shown first, in the order of the file
+      let e = located
+        .into_iter()
+        .rev()
+        .fold(anyhow::Error::from(e), |e, msg| e.context(msg));
+      return Err(e.context(EC::ParseRule(path.to_path_buf())));
+    }
+  };
   let default_id = path.file_stem().and_then(|s| s.to_str());
   let has_multiple = rules.len() > 1;
   for (i, rule) in rules.iter_mut().enumerate() {
@@ -259,6 +376,52 @@ pub fn read_rule_file(
   Ok(rules)
 }
 
+/// Messages of the errors in the rule documents of `yaml` like
+/// "`rule.all[1].kind` at line 7, column 7: Kind `foo` is invalid."
+fn locate_rule_errors(yaml: &str, globals: &GlobalRules) -> Vec<String> {
+  let mut located = vec![];
+  for (offset, document) in split_documents(yaml) {
+    let Err(diagnostics) = diagnose_rule::<SgLang>(document, &|_| globals.clone()) else {
+      continue;
+    };
+    for diagnostic in diagnostics {
+      let before = &yaml[..offset + diagnostic.span.start];
+      let line = before.matches('\n').count() + 1;
+      let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+      let cause = std::iter::successors(Some(&diagnostic.error as &dyn std::error::Error), |e| {
+        e.source()
+      })
+      .last()
+      .map(|e| e.to_string())
+      .unwrap_or_default();
+      let at = if diagnostic.path.is_empty() {
+        format!("At line {line}, column {column}")
+      } else {
+        format!("`{}` at line {line}, column {column}", diagnostic.path)
+      };
+      located.push(format!("{at}: {cause}"));
+    }
+  }
+  located
+}
+
+/// The YAML documents separated by `---` lines, with their byte offsets.
+fn split_documents(yaml: &str) -> Vec<(usize, &str)> {
+  let mut documents = vec![];
+  let mut start = 0;
+  let mut offset = 0;
+  for line in yaml.split_inclusive('\n') {
+    if line.trim_end() == "---" {
+      documents.push((start, &yaml[start..offset]));
+      start = offset + line.len();
+    }
+    offset += line.len();
+  }
+  documents.push((start, &yaml[start..]));
+  documents.retain(|(_, doc)| !doc.trim().is_empty());
+  documents
+}
+
 const CONFIG_FILE_YML: &str = "sgconfig.yml";
 const CONFIG_FILE_YAML: &str = "sgconfig.yaml";
 
@@ -92,6 +92,12 @@ impl SgLang {
     let types = langs.map(|lang| lang.augmented_file_type());
     lang_globs::merge_types(types)
   }
+
+  /// The file types of the languages and the `extra` file types.
+  pub fn file_types_with(langs: impl Iterator<Item = Self>, extra: Types) -> Types {
+    let types = langs.map(|lang| lang.augmented_file_type());
+    lang_globs::merge_types(types.chain(std::iter::once(extra)))
+  }
 }
 
 impl Display for SgLang {
@@ -177,6 +183,12 @@ impl Language for SgLang {
     }
   }
 
+  fn kind_names(&self) -> Option<Vec<String>> {
+    match self {
+      Builtin(b) => b.kind_names(),
+      Custom(c) => c.kind_names(),
+    }
+  }
   fn kind_to_id(&self, kind: &str) -> u16 {
     match self {
       Builtin(b) => b.kind_to_id(kind),
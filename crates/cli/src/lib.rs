@@ -1,3 +1,4 @@
+mod audit;
 mod completions;
 mod config;
 mod lang;
@@ -13,6 +14,7 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 use std::{path::PathBuf, process::ExitCode};
 
+use audit::{run_audit, AuditArg};
 use completions::{run_shell_completion, CompletionsArg};
 use config::ProjectConfig;
 use lsp::{run_language_server, LspArg};
@@ -51,9 +53,11 @@ enum Commands {
   /// Run one time search or rewrite in command line. (default command)
   Run(RunArg),
   /// Scan and rewrite code by configuration.
-  Scan(ScanArg),
+  Scan(Box<ScanArg>),
   /// Test ast-grep rules.
   Test(TestArg),
+  /// Scan the codebase with security rules and summarize findings by severity and CWE.
+  Audit(AuditArg),
   /// Create new ast-grep project or items like rules/tests.
   New(NewArg),
   /// Start language server.
@@ -135,8 +139,9 @@ pub fn main_with_args(args: impl Iterator<Item = String>) -> Result<ExitCode> {
   let project = project?; // unwrap here to report invalid project
   match app.command {
     Commands::Run(arg) => run_with_pattern(arg, project),
-    Commands::Scan(arg) => run_with_config(arg, project),
+    Commands::Scan(arg) => run_with_config(*arg, project),
     Commands::Test(arg) => run_test_rule(arg, project),
+    Commands::Audit(arg) => run_audit(arg, project),
     Commands::New(arg) => run_create_new(arg, project),
     Commands::Lsp(arg) => run_language_server(arg, project).map(|_| ExitCode::SUCCESS),
     Commands::Completions(arg) => run_shell_completion::<App>(arg),
@@ -232,6 +237,7 @@ mod test_cli {
     ok("run -p fubuki -j8");
     ok("run -p test --threads 12");
     ok("run -p test --files-with-matches");
+    ok("run -p test --watch dir");
     ok("run -p test -l rs -c config.yml"); // global config arg
     error("run test");
     error("run --debug-query test"); // missing lang
@@ -245,6 +251,9 @@ mod test_cli {
     error("run -p test --threads");
     error("run -p test --files-with-matches -r test -U");
     error("run -p test --files-with-matches --json");
+    error("run -p test --watch -i");
+    error("run -p test --watch --json");
+    error("run -p test -r Test --watch -U");
   }
 
   #[test]
@@ -258,6 +267,8 @@ mod test_cli {
     ok("scan dir1 dir2 dir3"); // multiple paths
     ok("scan -r test.yml --format github");
     ok("scan --format github");
+    ok("scan --format codeclimate");
+    ok("scan --format junit");
     ok("scan --interactive");
     ok("scan --follow");
     ok("scan --json --include-metadata");
@@ -272,6 +283,17 @@ mod test_cli {
     ok("scan --context 1");
     ok("scan --max-results=10");
     ok("scan --max-results 5");
+    ok("scan --watch");
+    ok("scan -r test.yml --watch dir");
+    ok("scan --baseline generate");
+    ok("scan --fail-on error,warning");
+    ok("scan --diff main -i");
+    ok("scan --cache --json");
+    ok("scan --documents --json");
+    ok("scan --diff HEAD~1 --format github dir");
+    ok("scan --fail-on warning --fail-on hint");
+    ok("scan --findings-exit-code 3 --error-exit-code 4");
+    ok("scan --baseline compare --baseline-file base.json --format codeclimate");
     error("scan --max-results=10 --interactive"); // conflict
     error("scan -i --json dir"); // conflict
     error("scan --report-style rich --json dir"); // conflict
@@ -285,7 +307,20 @@ mod test_cli {
     error("scan --include-metadata"); // requires json
     error("scan --threads");
     error("scan --files-with-matches -U");
+    error("scan --baseline update");
+    error("scan --fail-on severe");
+    error("scan --diff main --watch"); // conflict
+    error("scan --cache --stdin"); // conflict
+    error("scan --documents -U"); // conflict
+    error("scan --diff"); // requires base
+    error("scan --error-exit-code 256");
+    error("scan --baseline-file base.json"); // requires baseline
+    error("scan --baseline compare --watch"); // conflict
     error("scan --files-with-matches --json");
+    error("scan --watch --interactive");
+    error("scan --watch --format github");
+    error("scan --watch --stdin");
+    error("scan --watch --max-results 5");
   }
 
   #[test]
@@ -298,6 +333,16 @@ mod test_cli {
     error("test --update-all --skip-snapshot-tests");
   }
   #[test]
+  fn test_audit() {
+    ok("audit");
+    ok("audit dir1 dir2");
+    ok("audit --fail-on medium");
+    ok("audit --fail-on=low --no-builtin");
+    ok("audit --globs '*.js' -j 4");
+    error("audit --fail-on critical");
+    error("audit --fail-on");
+  }
+  #[test]
   fn test_new() {
     ok("new");
     ok("new project");
@@ -18,7 +18,8 @@ async fn run_language_server_impl(_arg: LspArg, project: Result<ProjectConfig>)
 
   // Create a rule finder closure that uses the CLI logic
   let rule_finder = move || {
-    let (collection, _trace) = project_config.find_rules(RuleOverwrite::default())?;
+    let (collection, _trace) = project_config
+      .find_rules(RuleOverwrite::default().with_project_severities(&project_config.severities))?;
     Ok(collection)
   };
 
@@ -221,6 +221,12 @@ fn create_new_project(arg: NewArg, project_dir: &Path) -> Result<ExitCode> {
     custom_languages: None,      // advanced feature, skip now
     language_globs: None,        // advanced feature, skip now
     language_injections: vec![], // advanced feature
+    fail_on: None,
+    severities: Default::default(),
+    exit_codes: Default::default(),
+    extends: vec![],
+    trusted_keys: vec![],
+    cache_dir: None,
   };
   let config_path = project_dir.join("sgconfig.yml");
   let f = File::create(config_path)?;
@@ -1,14 +1,17 @@
 use super::{Diff, NodeMatch, PrintProcessor, Printer};
 use crate::lang::SgLang;
-use ast_grep_config::{RuleConfig, Severity};
+use crate::utils::match_fingerprints;
+use ast_grep_config::{RuleConfig, Severity, SARIF_COLUMN_KIND, SARIF_SCHEMA, SARIF_VERSION};
 use clap::ValueEnum;
 
 use anyhow::Result;
 use codespan_reporting::files::SimpleFile;
+use serde::Serialize;
 use serde_sarif::sarif;
 use std::io::{Stdout, Write};
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 #[derive(PartialEq, Eq, Clone, ValueEnum)]
@@ -16,17 +19,25 @@ use std::path::{Path, PathBuf};
 pub enum Platform {
   GitHub,
   Sarif,
+  /// Code Climate issues, the Code Quality report of GitLab.
+  CodeClimate,
+  /// JUnit XML, with a test suite per rule and a failed test case per finding.
+  JUnit,
 }
 
 pub enum CloudOutput {
   GitHub(Vec<u8>),
   Sarif(Vec<sarif::Result>),
+  CodeClimate(Vec<CodeClimateIssue>),
+  JUnit(Vec<JUnitCase>),
 }
 
 pub struct CloudPrinter<W: Write> {
   writer: W,
   platform: Platform,
   sarif_results: Vec<sarif::Result>,
+  code_climate_issues: Vec<CodeClimateIssue>,
+  junit_cases: Vec<JUnitCase>,
 }
 
 impl<W: Write> CloudPrinter<W> {
@@ -35,6 +46,8 @@ impl<W: Write> CloudPrinter<W> {
       writer,
       platform,
       sarif_results: vec![],
+      code_climate_issues: vec![],
+      junit_cases: vec![],
     }
   }
 }
@@ -63,22 +76,47 @@ impl<W: Write> Printer for CloudPrinter<W> {
       CloudOutput::Sarif(results) => {
         self.sarif_results.extend(results);
       }
+      CloudOutput::CodeClimate(issues) => {
+        self.code_climate_issues.extend(issues);
+      }
+      CloudOutput::JUnit(cases) => {
+        self.junit_cases.extend(cases);
+      }
     }
     Ok(())
   }
 
   fn after_print(&mut self) -> Result<()> {
     if self.platform == Platform::Sarif {
-      let tool_component = sarif::ToolComponent::builder().name("ast-grep").build();
+      let tool_component = sarif::ToolComponent::builder()
+        .name("ast-grep")
+        .version(env!("CARGO_PKG_VERSION"))
+        .build();
       let tool = sarif::Tool::builder().driver(tool_component).build();
-      let mut run = sarif::Run::builder().tool(tool).build();
+      let mut run = sarif::Run::builder()
+        .tool(tool)
+        .column_kind(serde_json::json!(SARIF_COLUMN_KIND))
+        .build();
       run.results = Some(self.sarif_results.clone());
+      // version is the SARIF format version, the tool version is in the driver
       let sarif_log = sarif::Sarif::builder()
-        .version(serde_json::json!(env!("CARGO_PKG_VERSION")))
+        .schema(SARIF_SCHEMA)
+        .version(serde_json::json!(SARIF_VERSION))
         .runs(vec![run])
         .build();
       let json = serde_json::to_string_pretty(&sarif_log)?;
       writeln!(self.writer, "{}", json)?;
+    } else if self.platform == Platform::CodeClimate {
+      // files are scanned in parallel, sort issues to make the report stable
+      let issues = &mut self.code_climate_issues;
+      issues.sort_by(|a, b| {
+        let key = |i: &CodeClimateIssue| (i.location.path.clone(), i.location.lines.begin);
+        key(a).cmp(&key(b))
+      });
+      let json = serde_json::to_string_pretty(issues)?;
+      writeln!(self.writer, "{}", json)?;
+    } else if self.platform == Platform::JUnit {
+      write_junit_report(&mut self.writer, &mut self.junit_cases)?;
     }
     Ok(())
   }
@@ -112,6 +150,19 @@ impl PrintProcessor<CloudOutput> for CloudProcessor {
           .collect();
         Ok(CloudOutput::Sarif(results))
       }
+      Platform::CodeClimate => {
+        let path = file.name();
+        let issues = create_code_climate_issues(matches.iter(), path, rule);
+        Ok(CloudOutput::CodeClimate(issues))
+      }
+      Platform::JUnit => {
+        let path = file.name();
+        let cases = matches
+          .iter()
+          .map(|nm| create_junit_case(nm, path, rule))
+          .collect();
+        Ok(CloudOutput::JUnit(cases))
+      }
     }
   }
 
@@ -147,6 +198,23 @@ impl PrintProcessor<CloudOutput> for CloudProcessor {
           .collect();
         Ok(CloudOutput::Sarif(results))
       }
+      Platform::CodeClimate => {
+        let path = path.to_string_lossy();
+        let mut issues = vec![];
+        for (diff, rule) in &diffs {
+          let matches = std::iter::once(&diff.node_match);
+          issues.extend(create_code_climate_issues(matches, &path, rule));
+        }
+        Ok(CloudOutput::CodeClimate(issues))
+      }
+      Platform::JUnit => {
+        let path = path.to_string_lossy();
+        let cases = diffs
+          .iter()
+          .map(|(diff, rule)| create_junit_case(&diff.node_match, &path, rule))
+          .collect();
+        Ok(CloudOutput::JUnit(cases))
+      }
     }
   }
 }
@@ -236,6 +304,160 @@ fn create_sarif_result(
   result
 }
 
+/// An issue of the Code Climate spec, with the fields GitLab reads for Code Quality.
+/// See https://docs.gitlab.com/ci/testing/code_quality/#code-quality-report-format
+#[derive(Serialize)]
+pub struct CodeClimateIssue {
+  #[serde(rename = "type")]
+  kind: &'static str,
+  check_name: String,
+  description: String,
+  categories: [&'static str; 1],
+  severity: &'static str,
+  fingerprint: String,
+  location: CodeClimateLocation,
+}
+
+#[derive(Serialize)]
+struct CodeClimateLocation {
+  path: String,
+  lines: CodeClimateLines,
+}
+
+#[derive(Serialize)]
+struct CodeClimateLines {
+  begin: usize,
+  end: usize,
+}
+
+fn severity_to_code_climate(severity: &Severity) -> &'static str {
+  match severity {
+    Severity::Error => "critical",
+    Severity::Warning => "major",
+    Severity::Info => "minor",
+    Severity::Hint | Severity::Off => "info",
+  }
+}
+
+/// Issues of one rule's matches in one file, with the fingerprints of a baseline.
+fn create_code_climate_issues<'a, 'n: 'a>(
+  matches: impl Iterator<Item = &'a NodeMatch<'n>> + Clone,
+  path: &str,
+  rule: &RuleConfig<SgLang>,
+) -> Vec<CodeClimateIssue> {
+  let fingerprints = match_fingerprints(&rule.id, path, matches.clone());
+  matches
+    .zip(fingerprints)
+    .map(|(node_match, fingerprint)| CodeClimateIssue {
+      kind: "issue",
+      check_name: rule.id.clone(),
+      description: rule.get_message(node_match),
+      categories: ["Bug Risk"],
+      severity: severity_to_code_climate(&rule.severity),
+      fingerprint,
+      location: CodeClimateLocation {
+        path: path.to_string(),
+        lines: CodeClimateLines {
+          begin: node_match.start_pos().line() + 1,
+          end: node_match.end_pos().line() + 1,
+        },
+      },
+    })
+    .collect()
+}
+
+/// A finding as a failed test case of its rule's test suite.
+pub struct JUnitCase {
+  rule_id: String,
+  severity: &'static str,
+  path: String,
+  line: usize,
+  column: usize,
+  message: String,
+  text: String,
+}
+
+fn create_junit_case(node_match: &NodeMatch, path: &str, rule: &RuleConfig<SgLang>) -> JUnitCase {
+  let start_pos = node_match.start_pos();
+  let severity = match rule.severity {
+    Severity::Error => "error",
+    Severity::Warning => "warning",
+    Severity::Info => "info",
+    Severity::Hint => "hint",
+    Severity::Off => "off",
+  };
+  JUnitCase {
+    rule_id: rule.id.clone(),
+    severity,
+    path: path.to_string(),
+    line: start_pos.line() + 1,
+    column: start_pos.column(node_match) + 1,
+    message: rule.get_message(node_match),
+    text: node_match.text().to_string(),
+  }
+}
+
+/// Escape text for XML attributes and content. Control chars are not allowed in XML 1.0.
+fn escape_xml(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      '\t' | '\n' | '\r' => escaped.push(c),
+      c if c.is_control() => {}
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn write_junit_report<W: Write>(writer: &mut W, cases: &mut [JUnitCase]) -> Result<()> {
+  // files are scanned in parallel, sort cases to make the report stable
+  cases.sort_by(|a, b| (&a.path, a.line, a.column).cmp(&(&b.path, b.line, b.column)));
+  let mut suites: BTreeMap<&str, Vec<&JUnitCase>> = BTreeMap::new();
+  for case in cases.iter() {
+    suites.entry(&case.rule_id).or_default().push(case);
+  }
+  let total = cases.len();
+  writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+  writeln!(
+    writer,
+    r#"<testsuites name="ast-grep" tests="{total}" failures="{total}">"#
+  )?;
+  for (rule_id, cases) in suites {
+    let rule_id = escape_xml(rule_id);
+    let count = cases.len();
+    writeln!(
+      writer,
+      r#"  <testsuite name="{rule_id}" tests="{count}" failures="{count}">"#
+    )?;
+    for case in cases {
+      let path = escape_xml(&case.path);
+      let location = format!("{path}:{}:{}", case.line, case.column);
+      let message = escape_xml(&case.message);
+      writeln!(
+        writer,
+        r#"    <testcase name="{location}" classname="{rule_id}" file="{path}" line="{}">"#,
+        case.line
+      )?;
+      writeln!(
+        writer,
+        r#"      <failure message="{location}: {message}" type="{}">{location}: {message}"#,
+        case.severity
+      )?;
+      writeln!(writer, "{}</failure>", escape_xml(&case.text))?;
+      writeln!(writer, "    </testcase>")?;
+    }
+    writeln!(writer, "  </testsuite>")?;
+  }
+  writeln!(writer, "</testsuites>")?;
+  Ok(())
+}
+
 fn attach_sarif_fix(mut result: sarif::Result, path: &str, diff: Diff<'_>) -> sarif::Result {
   let range = diff.range;
   // Add fix information if replacement is available
@@ -301,6 +523,14 @@ mod test {
   fn make_sarif_test_printer() -> CloudPrinter<Buffer> {
     CloudPrinter::new(Buffer::no_color(), Platform::Sarif)
   }
+
+  fn make_code_climate_test_printer() -> CloudPrinter<Buffer> {
+    CloudPrinter::new(Buffer::no_color(), Platform::CodeClimate)
+  }
+
+  fn make_junit_test_printer() -> CloudPrinter<Buffer> {
+    CloudPrinter::new(Buffer::no_color(), Platform::JUnit)
+  }
   fn get_text(printer: &mut CloudPrinter<Buffer>) -> String {
     let buffer = &mut printer.writer;
     let bytes = buffer.as_slice();
@@ -420,15 +650,84 @@ severity: error
 
     // Verify it's valid JSON
     let sarif_log: sarif::Sarif = serde_json::from_str(&json_str).expect("should be valid SARIF");
-    assert_eq!(
-      sarif_log.version,
-      serde_json::json!(env!("CARGO_PKG_VERSION"))
-    );
+    assert_eq!(sarif_log.version, serde_json::json!("2.1.0"));
+    assert_eq!(sarif_log.schema.as_deref(), Some(SARIF_SCHEMA));
+    let driver = &sarif_log.runs[0].tool.driver;
+    assert_eq!(driver.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
     assert_eq!(sarif_log.runs.len(), 1);
 
     let run = &sarif_log.runs[0];
+    let column_kind = serde_json::json!("unicodeCodePoints");
+    assert_eq!(run.column_kind.as_ref(), Some(&column_kind));
     let results = run.results.as_ref().unwrap();
     assert_eq!(results.len(), 1);
     assert_eq!(results[0].rule_id.as_ref().unwrap(), "test");
   }
+
+  #[test]
+  fn test_code_climate_output() {
+    let mut printer = make_code_climate_test_printer();
+    let source = "let a = 123\nlet b = a\nlet c = a";
+    let grep = SgLang::from(SupportLang::Tsx).ast_grep(source);
+    let rule = make_rule("rule: { pattern: a }\nseverity: warning");
+    let matches: Vec<_> = grep.root().find_all(&rule.matcher).collect();
+    printer.before_print().unwrap();
+    let file = SimpleFile::new(Cow::Borrowed("test.ts"), source);
+    let buffer = printer
+      .get_processor()
+      .print_rule(matches, file, &rule)
+      .unwrap();
+    printer.process(buffer).unwrap();
+    printer.after_print().unwrap();
+    let json: serde_json::Value =
+      serde_json::from_str(&get_text(&mut printer)).expect("should be valid JSON");
+    let issues = json.as_array().expect("should be an array");
+    assert_eq!(issues.len(), 3);
+    let issue = &issues[1];
+    assert_eq!(issue["type"], "issue");
+    assert_eq!(issue["check_name"], "test");
+    assert_eq!(issue["description"], "test rule");
+    assert_eq!(issue["severity"], "major");
+    assert_eq!(issue["location"]["path"], "test.ts");
+    assert_eq!(issue["location"]["lines"]["begin"], 2);
+    assert_eq!(issue["location"]["lines"]["end"], 2);
+    let fingerprints: std::collections::HashSet<_> =
+      issues.iter().map(|i| i["fingerprint"].as_str()).collect();
+    assert_eq!(fingerprints.len(), 3);
+  }
+
+  #[test]
+  fn test_junit_output() {
+    let mut printer = make_junit_test_printer();
+    let source = "let a = 123\nlet b = a < 1";
+    let grep = SgLang::from(SupportLang::Tsx).ast_grep(source);
+    let rule = make_rule("rule: { pattern: a }\nseverity: warning");
+    let matches: Vec<_> = grep.root().find_all(&rule.matcher).collect();
+    printer.before_print().unwrap();
+    let file = SimpleFile::new(Cow::Borrowed("test.ts"), source);
+    let buffer = printer
+      .get_processor()
+      .print_rule(matches, file, &rule)
+      .unwrap();
+    printer.process(buffer).unwrap();
+    printer.after_print().unwrap();
+    let xml = get_text(&mut printer);
+    assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert!(xml.contains(r#"<testsuites name="ast-grep" tests="2" failures="2">"#));
+    assert!(xml.contains(r#"<testsuite name="test" tests="2" failures="2">"#));
+    assert!(
+      xml.contains(r#"<testcase name="test.ts:2:9" classname="test" file="test.ts" line="2">"#)
+    );
+    assert!(xml.contains(r#"<failure message="test.ts:1:5: test rule" type="warning">"#));
+    assert!(xml.trim_end().ends_with("</testsuites>"));
+  }
+
+  #[test]
+  fn test_escape_xml() {
+    assert_eq!(
+      escape_xml(r#"a < b && "c" > 'd'"#),
+      "a &lt; b &amp;&amp; &quot;c&quot; &gt; &apos;d&apos;"
+    );
+    assert_eq!(escape_xml("a\u{1b}b\n"), "ab\n");
+  }
 }
@@ -16,7 +16,7 @@ use crate::print::{
 };
 use crate::utils::ErrorContext as EC;
 use crate::utils::{filter_file_pattern, ContextArgs, InputArgs, MatchUnit, OutputArgs};
-use crate::utils::{DebugFormat, FileTrace, RunTrace};
+use crate::utils::{DebugFormat, FileTrace, ParseCache, RunTrace};
 use crate::utils::{Items, PathWorker, StdInWorker, Worker};
 
 fn lang_help() -> String {
@@ -115,6 +115,13 @@ pub struct RunArg {
   /// and to disable heading when piping to another program or redirected to files.
   #[clap(long, default_value = "auto", value_name = "WHEN")]
   heading: Heading,
+
+  /// Watch the paths and search files again when they change.
+  ///
+  /// ast-grep keeps running, prints the matches of every file once and then of every
+  /// changed file again. Changed files are reparsed incrementally. Stop watching with Ctrl+C.
+  #[clap(long, conflicts_with_all = ["interactive", "update_all", "json", "stdin"])]
+  watch: bool,
 }
 
 impl RunArg {
@@ -178,15 +185,27 @@ fn run_pattern_with_printer(arg: RunArg, printer: impl Printer + 'static) -> Res
   if arg.input.stdin {
     RunWithSpecificLang::new(arg, trace)?.run_std_in(printer)
   } else if arg.lang.is_some() {
-    RunWithSpecificLang::new(arg, trace)?.run_path(printer)
+    let worker = RunWithSpecificLang::new(arg, trace)?;
+    if worker.arg.watch {
+      worker.run_watch(printer)
+    } else {
+      worker.run_path(printer)
+    }
   } else {
-    RunWithInferredLang { arg, trace }.run_path(printer)
+    let cache = ParseCache::new(arg.watch);
+    let worker = RunWithInferredLang { arg, trace, cache };
+    if worker.arg.watch {
+      worker.run_watch(printer)
+    } else {
+      worker.run_path(printer)
+    }
   }
 }
 
 struct RunWithInferredLang {
   arg: RunArg,
   trace: RunTrace,
+  cache: ParseCache,
 }
 impl Worker for RunWithInferredLang {
   fn consume_items<P: Printer>(
@@ -211,6 +230,9 @@ impl PathWorker for RunWithInferredLang {
   fn build_walk(&self) -> Result<WalkParallel> {
     self.arg.input.walk()
   }
+  fn get_input(&self) -> &InputArgs {
+    &self.arg.input
+  }
   fn get_trace(&self) -> &FileTrace {
     &self.trace.inner
   }
@@ -234,7 +256,7 @@ impl PathWorker for RunWithInferredLang {
       })
       .collect::<Vec<_>>();
 
-    let items = filter_file_pattern(path, lang, Some(&matcher), &sub_matchers)?;
+    let items = filter_file_pattern(path, lang, Some(&matcher), &sub_matchers, &self.cache)?;
     let mut ret = Vec::with_capacity(items.len());
     let rewrite_str = self.arg.rewrite.as_ref();
 
@@ -262,6 +284,7 @@ struct RunWithSpecificLang {
   pattern: Pattern,
   rewrite: Option<Fixer>,
   stats: RunTrace,
+  cache: ParseCache,
 }
 
 impl RunWithSpecificLang {
@@ -275,11 +298,13 @@ impl RunWithSpecificLang {
     } else {
       None
     };
+    let cache = ParseCache::new(arg.watch);
     Ok(Self {
       arg,
       pattern: pattern_ret?,
       rewrite,
       stats,
+      cache,
     })
   }
 }
@@ -311,6 +336,9 @@ impl PathWorker for RunWithSpecificLang {
     let lang = self.arg.lang.expect("must present");
     self.arg.input.walk_lang(lang)
   }
+  fn get_input(&self) -> &InputArgs {
+    &self.arg.input
+  }
   fn get_trace(&self) -> &FileTrace {
     &self.stats.inner
   }
@@ -331,7 +359,7 @@ impl PathWorker for RunWithSpecificLang {
     } else {
       (None, vec![(lang, pattern.clone())])
     };
-    let filtered = filter_file_pattern(path, path_lang, root_matcher, &sub_matchers)?;
+    let filtered = filter_file_pattern(path, path_lang, root_matcher, &sub_matchers, &self.cache)?;
     let mut ret = Vec::with_capacity(filtered.len());
     for unit in filtered {
       let Some(processed) = match_one_file(processor, &unit, &self.rewrite)? else {
@@ -406,6 +434,7 @@ mod test {
       rewrite: None,
       lang: None,
       heading: Heading::Never,
+      watch: false,
       debug_query: None,
       strictness: None,
       input: InputArgs {
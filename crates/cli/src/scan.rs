@@ -3,21 +3,24 @@ use std::process::ExitCode;
 
 use anyhow::{Context, Result};
 use ast_grep_config::{from_yaml_string, CombinedScan, RuleCollection, RuleConfig, Severity};
-use ast_grep_core::{tree_sitter::StrDoc, NodeMatch};
-use ast_grep_language::SupportLang;
+use ast_grep_core::{tree_sitter::StrDoc, AstGrep, NodeMatch};
+use ast_grep_language::{Language, SupportLang};
 use clap::Args;
 use ignore::WalkParallel;
 
-use crate::config::{read_rule_file, with_rule_stats, ProjectConfig};
+use crate::config::{read_rule_file, with_rule_stats, ProjectConfig, DEFAULT_CACHE_DIR};
 use crate::lang::SgLang;
 use crate::print::{
   CloudPrinter, ColoredPrinter, Diff, FileNamePrinter, InteractivePrinter, JSONPrinter, Platform,
   PrintProcessor, Printer, ReportStyle, SimpleFile,
 };
+use crate::utils::DocumentKind;
 use crate::utils::RuleOverwrite;
-use crate::utils::{filter_file_rule, ContextArgs, InputArgs, OutputArgs, OverwriteArgs};
-use crate::utils::{ErrorContext as EC, MaxItemCounter};
-use crate::utils::{FileTrace, ScanTrace};
+use crate::utils::{filter_document_blocks, filter_file_content, read_file, rules_hash, ScanCache};
+use crate::utils::{is_secret_rule, FileTrace, Redacted, ScanTrace};
+use crate::utils::{Baseline, BaselineMode, ChangedLines, ParseCache, ScanProfiler};
+use crate::utils::{ContextArgs, InputArgs, OutputArgs, OverwriteArgs};
+use crate::utils::{ErrorContext as EC, ExitCodes, MaxItemCounter};
 use crate::utils::{Items, PathWorker, StdInWorker, Worker};
 
 use std::collections::HashSet;
@@ -41,7 +44,9 @@ pub struct ScanArg {
 
   /// Output warning/error messages in different formats.
   ///
-  /// Supported formats: GitHub Action, SARIF (Static Analysis Results Interchange Format).
+  /// Supported formats: GitHub Action, SARIF (Static Analysis Results Interchange Format),
+  /// Code Climate (GitLab Code Quality report),
+  /// JUnit XML (Jenkins, Azure Pipelines and other CI servers).
   #[clap(long, conflicts_with = "json", conflicts_with = "interactive")]
   format: Option<Platform>,
 
@@ -54,6 +59,14 @@ pub struct ScanArg {
   #[clap(long, requires = "json")]
   include_metadata: bool,
 
+  /// Mask the text of the findings of rules tagged `secret`, like `metadata: { tags: [secret] }`.
+  ///
+  /// The matched text and meta variables keep their first and last two characters in every
+  /// output format, including the messages, fixes and source lines shown, so that leaked
+  /// credentials are not copied into logs and reports.
+  #[clap(long, conflicts_with_all = ["interactive", "update_all"])]
+  redact_secrets: bool,
+
   /// severity related options
   #[clap(flatten)]
   overwrite: OverwriteArgs,
@@ -73,6 +86,90 @@ pub struct ScanArg {
   /// Useful for big codebase to fail scan/search fast.
   #[clap(long, conflicts_with = "interactive", value_name = "NUM")]
   max_results: Option<u16>,
+
+  /// Watch the paths and scan files again when they change.
+  ///
+  /// ast-grep keeps running, reports every file once and then every changed file again.
+  /// Changed files are reparsed incrementally, rules are read only once. Stop watching with Ctrl+C.
+  #[clap(long, conflicts_with_all = ["interactive", "json", "format", "stdin", "max_results"])]
+  watch: bool,
+
+  /// Record the current findings in a baseline file, or report only new findings.
+  ///
+  /// `generate` writes the fingerprints of all findings to the baseline file without reporting them.
+  /// `compare` suppresses the findings recorded in the baseline file, so that a rule can be
+  /// adopted on a large codebase and still fail CI on new findings only.
+  #[clap(
+    long,
+    value_name = "MODE",
+    conflicts_with_all = ["interactive", "update_all", "stdin", "watch"]
+  )]
+  baseline: Option<BaselineMode>,
+
+  /// The baseline file to generate or compare with.
+  #[clap(
+    long,
+    requires = "baseline",
+    value_name = "FILE",
+    default_value = "ast-grep-baseline.json"
+  )]
+  baseline_file: PathBuf,
+
+  /// Skip the files without findings when they were last scanned with the same rules and config.
+  ///
+  /// Files are compared by content, and are cached in `scan.json` of the project's `cacheDir`,
+  /// `.ast-grep/cache` by default. Files with findings are scanned again to report them.
+  #[clap(long, conflicts_with_all = ["stdin", "watch"])]
+  cache: bool,
+
+  /// Report only findings on the lines changed since the git ref BASE, like `main`.
+  ///
+  /// Changes are those of `git diff BASE` and untracked files. Findings spanning changed and
+  /// unchanged lines are reported. Unchanged files are not scanned.
+  #[clap(long, value_name = "BASE", conflicts_with_all = ["stdin", "watch"])]
+  diff: Option<String>,
+
+  /// Also scan the fenced code blocks of Markdown and MDX files and the code cells of notebooks.
+  ///
+  /// A block is scanned as the language of its tag, like ```js, and a Jupyter notebook's cells
+  /// as the notebook's language. Findings are reported at the lines of the document.
+  /// Fixes are not applied to documents.
+  #[clap(long, conflicts_with_all = ["interactive", "update_all", "stdin"])]
+  documents: bool,
+
+  /// Fail the scan if there are findings of the severities, separated by comma.
+  ///
+  /// Default is `error`, or `failOn` in sgconfig.yml.
+  #[clap(long, value_name = "SEVERITY", value_delimiter = ',', value_parser = parse_severity)]
+  fail_on: Vec<Severity>,
+
+  /// Exit with CODE if findings fail the scan. Default is 1.
+  #[clap(long, value_name = "CODE")]
+  findings_exit_code: Option<u8>,
+
+  /// Report what each rule cost after the scan, the slowest rule first.
+  ///
+  /// The report is printed to stderr with each rule's match time, its matches, the nodes of
+  /// its kinds visited and the share of them skipped by the prefilter since they lack a text
+  /// the rule requires. Timing every match slows the scan down a little.
+  #[clap(long, conflicts_with = "watch")]
+  profile: bool,
+
+  /// Exit with CODE if the scan fails to run, instead of the code of the error.
+  ///
+  /// This tells tool errors from findings in CI.
+  #[clap(long, value_name = "CODE")]
+  error_exit_code: Option<u8>,
+}
+
+fn parse_severity(severity: &str) -> Result<Severity, String> {
+  match severity {
+    "error" => Ok(Severity::Error),
+    "warning" => Ok(Severity::Warning),
+    "info" => Ok(Severity::Info),
+    "hint" => Ok(Severity::Hint),
+    _ => Err("expected one of error, warning, info or hint".into()),
+  }
 }
 
 impl ScanArg {
@@ -82,7 +179,22 @@ impl ScanArg {
   }
 }
 
-pub fn run_with_config(arg: ScanArg, project: Result<ProjectConfig>) -> Result<ExitCode> {
+pub fn run_with_config(mut arg: ScanArg, project: Result<ProjectConfig>) -> Result<ExitCode> {
+  let cli_codes = ExitCodes {
+    findings: arg.findings_exit_code,
+    error: arg.error_exit_code,
+  };
+  let (exit_codes, fail_on) = match &project {
+    Ok(config) => (cli_codes.or(config.exit_codes), config.fail_on.clone()),
+    Err(_) => (cli_codes, None),
+  };
+  if arg.fail_on.is_empty() {
+    arg.fail_on = fail_on.unwrap_or_else(|| vec![Severity::Error]);
+  }
+  exit_codes.apply(run_with_printer(arg, project))
+}
+
+fn run_with_printer(arg: ScanArg, project: Result<ProjectConfig>) -> Result<ExitCode> {
   let project_trace = arg.output.inspect.project_trace();
   project_trace.print_project(&project)?;
   let context = arg.context.get();
@@ -120,6 +232,9 @@ fn run_scan<P: Printer + 'static>(
     let worker = ScanStdin::try_new(arg)?;
     // TODO: report a soft error if rules have different languages
     worker.run_std_in(printer)
+  } else if arg.watch {
+    let worker = ScanWithConfig::try_new(arg, project)?;
+    worker.run_watch(printer)
   } else {
     let worker = ScanWithConfig::try_new(arg, project)?;
     worker.run_path(printer)
@@ -135,12 +250,27 @@ struct ScanWithConfig {
   // TODO: remove this
   error_count: AtomicUsize,
   max_item_counter: Option<MaxItemCounter>,
+  cache: ParseCache,
+  baseline: Option<Baseline>,
+  changes: Option<ChangedLines>,
+  scan_cache: Option<ScanCache>,
+  profiler: Option<ScanProfiler>,
 }
 impl ScanWithConfig {
   fn try_new(arg: ScanArg, project: Result<ProjectConfig>) -> Result<Self> {
-    let overwrite = RuleOverwrite::new(&arg.overwrite)?;
+    let mut overwrite = RuleOverwrite::new(&arg.overwrite)?;
+    if let Ok(project_config) = &project {
+      overwrite = overwrite.with_project_severities(&project_config.severities);
+    }
     let unused_suppression_rule = unused_suppression_rule_config(&arg, &overwrite);
     let mut proj_dir = PathBuf::from(".");
+    let mut cache_dir = PathBuf::from(DEFAULT_CACHE_DIR);
+    // inputs of the rules besides the rule configs
+    let mut rule_inputs = vec![format!("{:?}", unused_suppression_rule.severity)];
+    // the project's languages apply to `--rule` and `--inline-rules` too
+    if let (true, Ok(project_config)) = (arg.cache, &project) {
+      rule_inputs.extend(project_config.read_config_inputs()?);
+    }
     let (configs, rule_trace) = if let Some(path) = &arg.rule {
       let rules = read_rule_file(path, None)?;
       proj_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
@@ -153,6 +283,10 @@ impl ScanWithConfig {
       // NOTE: only query project here since -r does not need project
       let project_config = project?;
       proj_dir = project_config.project_dir.clone();
+      if arg.cache {
+        cache_dir = project_config.cache_dir.clone();
+        rule_inputs.extend(project_config.read_util_files()?);
+      }
       project_config.find_rules(overwrite)?
     };
     let trace = arg.output.inspect.scan_trace(rule_trace);
@@ -161,6 +295,23 @@ impl ScanWithConfig {
       .canonicalize()
       .or_else(|_| std::env::current_dir())?;
     let max_item_counter = arg.max_results.map(MaxItemCounter::new);
+    let cache = ParseCache::new(arg.watch);
+    let baseline = arg
+      .baseline
+      .map(|mode| Baseline::try_new(mode, arg.baseline_file.clone()))
+      .transpose()?;
+    let changes = arg
+      .diff
+      .as_deref()
+      .map(ChangedLines::from_git)
+      .transpose()?;
+    let scan_cache = if arg.cache {
+      let hash = rules_hash(&configs, &rule_inputs)?;
+      Some(ScanCache::load(cache_dir.join("scan.json"), hash))
+    } else {
+      None
+    };
+    let profiler = arg.profile.then(ScanProfiler::new);
     Ok(Self {
       arg,
       configs,
@@ -169,6 +320,11 @@ impl ScanWithConfig {
       proj_dir: absolute_proj_dir,
       error_count: AtomicUsize::new(0),
       max_item_counter,
+      cache,
+      baseline,
+      changes,
+      scan_cache,
+      profiler,
     })
   }
 }
@@ -184,6 +340,15 @@ impl Worker for ScanWithConfig {
     }
     printer.after_print()?;
     self.trace.print()?;
+    if let Some(profiler) = &self.profiler {
+      profiler.print()?;
+    }
+    if let Some(baseline) = &self.baseline {
+      baseline.finish()?;
+    }
+    if let Some(scan_cache) = &self.scan_cache {
+      scan_cache.save()?;
+    }
     let error_count = self.error_count.load(Ordering::Acquire);
     if error_count > 0 {
       Err(anyhow::anyhow!(EC::DiagnosticError(error_count)))
@@ -213,6 +378,9 @@ fn unused_suppression_rule_config(arg: &ScanArg, overwrite: &RuleOverwrite) -> R
 }
 
 impl PathWorker for ScanWithConfig {
+  fn get_input(&self) -> &InputArgs {
+    &self.arg.input
+  }
   fn get_trace(&self) -> &FileTrace {
     &self.trace.inner.file_trace
   }
@@ -221,21 +389,90 @@ impl PathWorker for ScanWithConfig {
     self.configs.for_each_rule(|rule| {
       langs.insert(rule.language);
     });
-    self.arg.input.walk_langs(langs.into_iter())
+    if self.arg.documents {
+      let types = SgLang::file_types_with(langs.into_iter(), DocumentKind::file_types());
+      self.arg.input.walk_types(types)
+    } else {
+      self.arg.input.walk_langs(langs.into_iter())
+    }
   }
   fn produce_item<P: Printer>(
     &self,
     path: &Path,
     processor: &P::Processor,
   ) -> Result<Vec<P::Processed>> {
-    let items = filter_file_rule(path, &self.configs, &self.trace)?;
+    let abs_path = path.canonicalize()?;
+    if let Some(changes) = &self.changes {
+      if !changes.contains_file(&abs_path) {
+        return Ok(vec![]);
+      }
+    }
+    // use path relative to project director
+    let normalized_path = abs_path.strip_prefix(&self.proj_dir).unwrap_or(path);
+    if let Some(kind) = self.document_kind(path) {
+      let file_content = read_file(path)?;
+      let items = filter_document_blocks(path, kind, &file_content, &self.configs, &self.trace)?;
+      let (ret, _) = self.scan_items::<P>(path, &abs_path, normalized_path, items, processor)?;
+      return Ok(ret);
+    }
+    let Some(lang) = SgLang::from_path(path) else {
+      return Ok(vec![]);
+    };
+    let file_content = read_file(path)?;
+    let cache_key = self
+      .scan_cache
+      .as_ref()
+      .map(|cache| cache.key(normalized_path, lang, &file_content));
+    if let (Some(cache), Some(key)) = (&self.scan_cache, &cache_key) {
+      if cache.is_clean(key) {
+        return Ok(vec![]);
+      }
+    }
+    let items = filter_file_content(
+      path,
+      lang,
+      file_content,
+      &self.configs,
+      &self.trace,
+      &self.cache,
+    )?;
+    let (ret, clean) = self.scan_items::<P>(path, &abs_path, normalized_path, items, processor)?;
+    if let (Some(cache), Some(key), true) = (&self.scan_cache, cache_key, clean) {
+      cache.record(key);
+    }
+    Ok(ret)
+  }
+
+  fn should_stop(&self) -> bool {
+    match &self.max_item_counter {
+      Some(max) => max.reached_max(),
+      None => false,
+    }
+  }
+}
+
+impl ScanWithConfig {
+  fn document_kind(&self, path: &Path) -> Option<DocumentKind> {
+    if self.arg.documents {
+      DocumentKind::from_path(path)
+    } else {
+      None
+    }
+  }
+
+  /// Scan the parsed roots of a file, returning the processed items and whether the file is clean.
+  fn scan_items<P: Printer>(
+    &self,
+    path: &Path,
+    abs_path: &Path,
+    normalized_path: &Path,
+    items: impl IntoIterator<Item = AstGrep<StrDoc<SgLang>>>,
+    processor: &P::Processor,
+  ) -> Result<(Vec<P::Processed>, bool)> {
     let mut error_count = 0usize;
+    let mut clean = true;
     let mut ret = vec![];
     for grep in items {
-      let file_content = grep.source();
-      // use path relative to project director
-      let abs_path = path.canonicalize()?;
-      let normalized_path = abs_path.strip_prefix(&self.proj_dir).unwrap_or(path);
       let rules = self
         .configs
         .get_rule_from_lang(normalized_path, *grep.lang());
@@ -243,13 +480,33 @@ impl PathWorker for ScanWithConfig {
       combined.set_unused_suppression_rule(&self.unused_suppression_rule);
       let interactive = self.arg.output.needs_interactive();
       // exclude_fix rule because we already have diff inspection before
-      let scanned = combined.scan(&grep, /* separate_fix*/ interactive);
+      let scanned = match &self.profiler {
+        Some(profiler) => profiler.scan(&combined, &grep, interactive),
+        None => combined.scan(&grep, /* separate_fix*/ interactive),
+      };
+      clean &= scanned.diffs.is_empty() && scanned.matches.iter().all(|(_, m)| m.is_empty());
       if interactive {
-        let diffs = scanned.diffs;
+        let mut diffs = scanned.diffs;
+        if let Some(changes) = &self.changes {
+          diffs.retain(|(_, m)| is_changed(changes, abs_path, m));
+        }
         let processed = match_rule_diff_on_file(path, diffs, processor)?;
         ret.push(processed);
       }
-      for (rule, matches) in scanned.matches {
+      for (rule, mut matches) in scanned.matches {
+        if let Some(changes) = &self.changes {
+          matches.retain(|m| is_changed(changes, abs_path, m));
+        }
+        let matches = match &self.baseline {
+          Some(baseline) => {
+            let path = normalized_path.to_string_lossy();
+            baseline.filter(&rule.id, &path, matches)
+          }
+          None => matches,
+        };
+        if matches.is_empty() {
+          continue;
+        }
         // Atomically reserve slots for matches, truncating if needed
         let matches: Vec<_> = if let Some(counter) = &self.max_item_counter {
           let wanted = matches.len();
@@ -266,23 +523,21 @@ impl PathWorker for ScanWithConfig {
           continue;
         }
         let match_count = matches.len();
-        if matches!(rule.severity, Severity::Error) {
+        if self.arg.fail_on.contains(&rule.severity) {
           error_count = error_count.saturating_add(match_count);
         }
-        let processed = match_rule_on_file(path, matches, rule, file_content, processor)?;
+        let redact = self.arg.redact_secrets;
+        let processed = match_rule_on_file(path, matches, rule, &grep, redact, processor)?;
         ret.push(processed);
       }
     }
     self.error_count.fetch_add(error_count, Ordering::AcqRel);
-    Ok(ret)
+    Ok((ret, clean))
   }
+}
 
-  fn should_stop(&self) -> bool {
-    match &self.max_item_counter {
-      Some(max) => max.reached_max(),
-      None => false,
-    }
-  }
+fn is_changed(changes: &ChangedLines, path: &Path, m: &NodeMatch<StrDoc<SgLang>>) -> bool {
+  changes.overlaps(path, m.start_pos().line(), m.end_pos().line())
 }
 
 struct ScanStdin {
@@ -290,6 +545,9 @@ struct ScanStdin {
   // TODO: remove this
   error_count: AtomicUsize,
   max_diagnostics_shown: Option<usize>,
+  fail_on: Vec<Severity>,
+  redact_secrets: bool,
+  profiler: Option<ScanProfiler>,
 }
 impl ScanStdin {
   fn try_new(arg: ScanArg) -> Result<Self> {
@@ -305,6 +563,9 @@ impl ScanStdin {
       rules,
       error_count: AtomicUsize::new(0),
       max_diagnostics_shown: arg.max_results.map(usize::from),
+      fail_on: arg.fail_on,
+      redact_secrets: arg.redact_secrets,
+      profiler: arg.profile.then(ScanProfiler::new),
     })
   }
 }
@@ -320,6 +581,9 @@ impl Worker for ScanStdin {
       printer.process(item)?;
     }
     printer.after_print()?;
+    if let Some(profiler) = &self.profiler {
+      profiler.print()?;
+    }
     let error_count = self.error_count.load(Ordering::Acquire);
     if error_count > 0 {
       Err(anyhow::anyhow!(EC::DiagnosticError(error_count)))
@@ -340,9 +604,11 @@ impl StdInWorker for ScanStdin {
     let combined = CombinedScan::new(self.rules.iter().collect());
     let grep = lang.ast_grep(src);
     let path = Path::new("STDIN");
-    let file_content = grep.source();
     // do not separate_fix rule in stdin mode
-    let scanned = combined.scan(&grep, false);
+    let scanned = match &self.profiler {
+      Some(profiler) => profiler.scan(&combined, &grep, false),
+      None => combined.scan(&grep, false),
+    };
     let mut error_count = 0usize;
     let mut diagnostic_count = 0usize;
     let mut ret = vec![];
@@ -362,10 +628,11 @@ impl StdInWorker for ScanStdin {
       }
       let match_count = matches.len();
       diagnostic_count += match_count;
-      if matches!(rule.severity, Severity::Error) {
+      if self.fail_on.contains(&rule.severity) {
         error_count = error_count.saturating_add(match_count);
       }
-      let processed = match_rule_on_file(path, matches, rule, file_content, processor)?;
+      let redact = self.redact_secrets;
+      let processed = match_rule_on_file(path, matches, rule, &grep, redact, processor)?;
       ret.push(processed);
     }
     self.error_count.fetch_add(error_count, Ordering::AcqRel);
@@ -390,6 +657,22 @@ fn match_rule_diff_on_file<T>(
 }
 
 fn match_rule_on_file<T>(
+  path: &Path,
+  matches: Vec<NodeMatch<StrDoc<SgLang>>>,
+  rule: &RuleConfig<SgLang>,
+  grep: &AstGrep<StrDoc<SgLang>>,
+  redact: bool,
+  processor: &impl PrintProcessor<T>,
+) -> Result<T> {
+  if redact && is_secret_rule(rule) {
+    let redacted = Redacted::new(grep, &matches);
+    let matches = matches.iter().map(|m| redacted.adopt(m)).collect();
+    return print_rule_on_file(path, matches, rule, redacted.source(), processor);
+  }
+  print_rule_on_file(path, matches, rule, grep.source(), processor)
+}
+
+fn print_rule_on_file<T>(
   path: &Path,
   matches: Vec<NodeMatch<StrDoc<SgLang>>>,
   rule: &RuleConfig<SgLang>,
@@ -446,6 +729,7 @@ rule:
       inline_rules: None,
       report_style: ReportStyle::Rich,
       include_metadata: false,
+      redact_secrets: false,
       input: InputArgs {
         no_ignore: vec![],
         paths: vec![PathBuf::from(".")],
@@ -477,6 +761,16 @@ rule:
       },
       format: None,
       max_results: None,
+      watch: false,
+      baseline: None,
+      baseline_file: PathBuf::from("ast-grep-baseline.json"),
+      cache: false,
+      diff: None,
+      documents: false,
+      fail_on: vec![Severity::Error],
+      findings_exit_code: None,
+      profile: false,
+      error_exit_code: None,
     }
   }
 
@@ -7,6 +7,7 @@ use anyhow::{Context, Result};
 use clap::{Args, ValueEnum};
 use ignore::{
   overrides::{Override, OverrideBuilder},
+  types::Types,
   WalkBuilder, WalkParallel,
 };
 use regex::Regex;
@@ -62,7 +63,7 @@ pub struct InputArgs {
 }
 
 impl InputArgs {
-  fn get_threads(&self) -> usize {
+  pub fn get_threads(&self) -> usize {
     if self.threads == 0 {
       std::thread::available_parallelism()
         .map_or(1, |n| n.get())
@@ -99,7 +100,10 @@ impl InputArgs {
   }
 
   pub fn walk_langs(&self, langs: impl Iterator<Item = SgLang>) -> Result<WalkParallel> {
-    let types = SgLang::file_types_for_langs(langs);
+    self.walk_types(SgLang::file_types_for_langs(langs))
+  }
+
+  pub fn walk_types(&self, types: Types) -> Result<WalkParallel> {
     let mut builder = self.walk_basic()?;
     Ok(builder.types(types).build_parallel())
   }
@@ -0,0 +1,197 @@
+use super::{hex_digest, ErrorContext as EC};
+use crate::lang::SgLang;
+
+use anyhow::{Context, Result};
+use ast_grep_core::{tree_sitter::StrDoc, NodeMatch};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+type Match<'t> = NodeMatch<'t, StrDoc<SgLang>>;
+
+/// The fingerprints of one rule's matches in one file.
+///
+/// A fingerprint hashes the rule id, the path and the matched text, so that it does not
+/// change when unrelated code moves the match, plus the number of earlier matches with the
+/// same text to keep fingerprints unique within the file.
+pub fn match_fingerprints<'a, 't: 'a>(
+  rule_id: &str,
+  path: &str,
+  matches: impl IntoIterator<Item = &'a Match<'t>>,
+) -> Vec<String> {
+  let mut seen = HashMap::new();
+  let mut fingerprints = vec![];
+  for node_match in matches {
+    let text = node_match.text();
+    let occurrence = seen.entry(text.to_string()).or_insert(0usize);
+    let fingerprint = hex_digest([rule_id, path, &text, &occurrence.to_string()]);
+    *occurrence += 1;
+    fingerprints.push(fingerprint);
+  }
+  fingerprints
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BaselineMode {
+  /// Record all findings in the baseline file instead of reporting them.
+  Generate,
+  /// Report only the findings that are not recorded in the baseline file.
+  Compare,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BaselineFile {
+  version: u32,
+  findings: Vec<BaselineEntry>,
+}
+
+/// A recorded finding. Rule id and path are kept to make the file reviewable.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+struct BaselineEntry {
+  rule_id: String,
+  path: String,
+  fingerprint: String,
+}
+
+const BASELINE_VERSION: u32 = 1;
+
+/// Findings known before, to report only new ones on large legacy codebases.
+pub struct Baseline {
+  mode: BaselineMode,
+  path: PathBuf,
+  known: HashSet<String>,
+  recorded: Mutex<Vec<BaselineEntry>>,
+  suppressed: AtomicUsize,
+}
+
+impl Baseline {
+  pub fn try_new(mode: BaselineMode, path: PathBuf) -> Result<Self> {
+    let known = if mode == BaselineMode::Compare {
+      let text = std::fs::read_to_string(&path).with_context(|| EC::ReadBaseline(path.clone()))?;
+      let file: BaselineFile =
+        serde_json::from_str(&text).with_context(|| EC::ParseBaseline(path.clone()))?;
+      file.findings.into_iter().map(|e| e.fingerprint).collect()
+    } else {
+      HashSet::new()
+    };
+    Ok(Self {
+      mode,
+      path,
+      known,
+      recorded: Mutex::new(vec![]),
+      suppressed: AtomicUsize::new(0),
+    })
+  }
+
+  /// Remove the matches of a rule in a file that are in the baseline. When generating,
+  /// all matches are recorded and none is kept.
+  pub fn filter<'t>(&self, rule_id: &str, path: &str, matches: Vec<Match<'t>>) -> Vec<Match<'t>> {
+    let fingerprints = match_fingerprints(rule_id, path, &matches);
+    if self.mode == BaselineMode::Generate {
+      let mut recorded = self.recorded.lock().expect("lock should not be poisoned");
+      recorded.extend(fingerprints.into_iter().map(|fingerprint| BaselineEntry {
+        rule_id: rule_id.to_string(),
+        path: path.to_string(),
+        fingerprint,
+      }));
+      return vec![];
+    }
+    let total = matches.len();
+    let matches: Vec<_> = matches
+      .into_iter()
+      .zip(fingerprints)
+      .filter(|(_, fingerprint)| !self.known.contains(fingerprint))
+      .map(|(node_match, _)| node_match)
+      .collect();
+    let suppressed = total - matches.len();
+    self.suppressed.fetch_add(suppressed, Ordering::AcqRel);
+    matches
+  }
+
+  /// Write the recorded findings when generating, or report how many were suppressed.
+  pub fn finish(&self) -> Result<()> {
+    if self.mode == BaselineMode::Compare {
+      let suppressed = self.suppressed.load(Ordering::Acquire);
+      if suppressed > 0 {
+        eprintln!("{suppressed} finding(s) in the baseline were not reported.");
+      }
+      return Ok(());
+    }
+    let mut findings =
+      std::mem::take(&mut *self.recorded.lock().expect("lock should not be poisoned"));
+    // files are scanned in parallel, sort to keep the file stable
+    findings.sort();
+    let count = findings.len();
+    let file = BaselineFile {
+      version: BASELINE_VERSION,
+      findings,
+    };
+    let text = serde_json::to_string_pretty(&file)?;
+    std::fs::write(&self.path, text + "\n")
+      .with_context(|| EC::WriteBaseline(self.path.clone()))?;
+    eprintln!(
+      "Baseline of {count} finding(s) written to {}.",
+      self.path.display()
+    );
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_language::{LanguageExt, SupportLang};
+
+  fn find_all(src: &str) -> Vec<String> {
+    let grep = SgLang::from(SupportLang::JavaScript).ast_grep(src);
+    let matches: Vec<_> = grep.root().find_all("foo($A)").collect();
+    match_fingerprints("rule", "a.js", &matches)
+  }
+
+  #[test]
+  fn test_match_fingerprints() {
+    let prints = find_all("foo(1); foo(1); foo(2)");
+    assert_eq!(prints.len(), 3);
+    assert_eq!(prints.iter().collect::<HashSet<_>>().len(), 3);
+    // moving matches does not change the fingerprints
+    assert_eq!(find_all("let a = 1;\nfoo(1);\nfoo(1);\nfoo(2)"), prints);
+    assert_eq!(find_all("foo(2)")[0], prints[2]);
+  }
+
+  #[test]
+  fn test_baseline() -> Result<()> {
+    let dir = tempfile::TempDir::new()?;
+    let path = dir.path().join("baseline.json");
+    let grep = SgLang::from(SupportLang::JavaScript).ast_grep("foo(1); foo(2)");
+    let matches: Vec<_> = grep.root().find_all("foo($A)").collect();
+    let generate = Baseline::try_new(BaselineMode::Generate, path.clone())?;
+    assert!(generate
+      .filter("rule", "a.js", matches[..1].to_vec())
+      .is_empty());
+    generate.finish()?;
+    let compare = Baseline::try_new(BaselineMode::Compare, path)?;
+    let new = compare.filter("rule", "a.js", matches.clone());
+    assert_eq!(new.len(), 1);
+    assert_eq!(new[0].text(), "foo(2)");
+    assert_eq!(compare.filter("other", "a.js", matches).len(), 2);
+    assert_eq!(compare.suppressed.load(Ordering::Acquire), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn test_missing_baseline() {
+    let path = PathBuf::from("not-exist.json");
+    let Err(err) = Baseline::try_new(BaselineMode::Compare, path) else {
+      panic!("missing baseline should fail");
+    };
+    assert!(matches!(
+      err.downcast_ref::<EC>(),
+      Some(EC::ReadBaseline(_))
+    ));
+  }
+}
@@ -2,6 +2,7 @@ use ansi_term::{Color, Style};
 use anyhow::{Error, Result};
 
 use crossterm::tty::IsTty;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::path::PathBuf;
 use std::process::ExitCode;
@@ -30,6 +31,9 @@ pub enum ErrorContext {
   ReadConfiguration,
   ParseConfiguration,
   WalkRuleDir(PathBuf),
+  FetchRuleset(String),
+  RulesetDigestMismatch(String, String),
+  UntrustedRuleset(String, String),
   ReadRule(PathBuf),
   ParseRule(PathBuf),
   InvalidRuleId(PathBuf),
@@ -44,12 +48,20 @@ pub enum ErrorContext {
   ParsePattern,
   LanguageNotSpecified,
   StdInIsNotInteractive,
+  StdInIsNotSupported(&'static str),
   PatternHasError,
   // Scan
   DuplicateRuleId(String),
   DiagnosticError(usize),
   RuleNotSpecified,
   RuleNotFound(String),
+  ReadBaseline(PathBuf),
+  ParseBaseline(PathBuf),
+  WriteBaseline(PathBuf),
+  GitDiff(String),
+  WriteScanCache(PathBuf),
+  // Audit
+  AuditFailure(usize, String),
   // LSP
   StartLanguageServer,
   // Edit
@@ -76,15 +88,25 @@ impl ErrorContext {
     use ErrorContext::*;
     // reference: https://mariadb.com/kb/en/operating-system-error-codes/
     match self {
-      DiagnosticError(_) => 1,
+      DiagnosticError(_) | AuditFailure(..) => 1,
       // skip 2 to avoid conflict with clap error code or unexpected error
       ProjectNotExist | LanguageNotSpecified | RuleNotSpecified | RuleNotFound(_) => 3,
       TestFail(_) | TestSnapshotMismatch(_) => 4,
       NoTestDirConfigured | NoUtilDirConfigured => 5,
-      ReadConfiguration | ReadRule(_) | WalkRuleDir(_) | WriteFile(_) => 6,
-      StdInIsNotInteractive => 7,
+      ReadConfiguration
+      | ReadRule(_)
+      | WalkRuleDir(_)
+      | WriteFile(_)
+      | ReadBaseline(_)
+      | WriteBaseline(_)
+      | WriteScanCache(_)
+      | GitDiff(_)
+      | FetchRuleset(_)
+      | RulesetDigestMismatch(..)
+      | UntrustedRuleset(..) => 6,
+      StdInIsNotInteractive | StdInIsNotSupported(_) => 7,
       ParseTest(_) | ParseRule(_) | ParseConfiguration | ParsePattern | InvalidGlobalUtils
-      | LangInjection | DuplicateRuleId(_) | InvalidRuleId(_) => 8,
+      | LangInjection | DuplicateRuleId(_) | InvalidRuleId(_) | ParseBaseline(_) => 8,
       GlobPattern | BuildGlobs => 9,
       CannotInferShell => 10,
       ProjectAlreadyExist | FileAlreadyExist(_) => 17,
@@ -147,6 +169,21 @@ impl ErrorMessage {
         "The rule directory cannot be read or traversed",
         None,
       ),
+      FetchRuleset(url) => Self::new(
+        format!("Cannot fetch ruleset {url}"),
+        "Rulesets in `extends` are fetched with curl and extracted with tar. Please check that both are installed and the URL is reachable.",
+        CONFIG_REFERENCE,
+      ),
+      RulesetDigestMismatch(url, actual) => Self::new(
+        format!("Ruleset {url} does not have the pinned digest."),
+        format!("The fetched archive has the digest sha256:{actual}. Please check the URL, or pin the new digest if the ruleset is trusted."),
+        CONFIG_REFERENCE,
+      ),
+      UntrustedRuleset(url, reason) => Self::new(
+        format!("Ruleset {url} is not signed by a trusted key."),
+        format!("{reason} Rulesets in `extends` must have a minisign signature at their URL with `.minisig` appended, made by one of `trustedKeys`."),
+        CONFIG_REFERENCE,
+      ),
       ReadRule(file) => Self::new(
         format!("Cannot read rule {}", file.display()),
         "The rule file either does not exist or cannot be opened.",
@@ -203,8 +240,38 @@ impl ErrorMessage {
         CONFIG_GUIDE,
       ),
       DiagnosticError(num) => Self::new(
-        format!("{num} error(s) found in code."),
-        "Scan succeeded and found error level diagnostics in the codebase.",
+        format!("{num} diagnostic(s) failing the scan found in code."),
+        "Scan succeeded and found diagnostics of a `--fail-on` severity in the codebase.",
+        None,
+      ),
+      ReadBaseline(file) => Self::new(
+        format!("Cannot read baseline {}", file.display()),
+        "The baseline file either does not exist or cannot be opened. Run `--baseline generate` to create it.",
+        CLI_USAGE,
+      ),
+      ParseBaseline(file) => Self::new(
+        format!("Cannot parse baseline {}", file.display()),
+        "The baseline file is not valid JSON generated by `--baseline generate`.",
+        CLI_USAGE,
+      ),
+      WriteBaseline(file) => Self::new(
+        format!("Cannot write baseline {}", file.display()),
+        "Please check if the directory of the baseline file exists and is writable.",
+        CLI_USAGE,
+      ),
+      WriteScanCache(file) => Self::new(
+        format!("Cannot write scan cache {}", file.display()),
+        "Please check if the cache directory is writable, or scan without `--cache`.",
+        CLI_USAGE,
+      ),
+      GitDiff(base) => Self::new(
+        format!("Cannot get the changes since `{base}` from git."),
+        "`--diff` needs git installed, the current directory in a git repository and a valid base ref.",
+        CLI_USAGE,
+      ),
+      AuditFailure(num, level) => Self::new(
+        format!("{num} security finding(s) of {level} or higher severity."),
+        "Audit succeeded and found security issues at or above the `--fail-on` severity.",
         None,
       ),
       ParsePattern => Self::new(
@@ -222,6 +289,11 @@ impl ErrorMessage {
         "`--interactive` needs StdIn, but it is used as source code. Please use files as input.",
         TOOL_OVERVIEW,
       ),
+      StdInIsNotSupported(command) => Self::new(
+        format!("Command `{command}` cannot parse code from StdIn."),
+        "`--stdin` is not supported by this command. Please use files as input.",
+        CLI_USAGE,
+      ),
       PatternHasError => Self::new(
         "Pattern contains an ERROR node and may cause unexpected results.",
         "ast-grep parsed the pattern but it matched nothing in this run. Try using playground to refine the pattern.",
@@ -306,7 +378,71 @@ impl ErrorMessage {
   }
 }
 
+/// Exit codes configured by users to tell findings from tool errors, like in CI.
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct ExitCodes {
+  /// the exit code when findings fail the command
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub findings: Option<u8>,
+  /// the exit code when the command fails to run
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<u8>,
+}
+
+impl ExitCodes {
+  pub fn is_unset(&self) -> bool {
+    self.findings.is_none() && self.error.is_none()
+  }
+
+  /// The codes of self, or those of `other` if they are not configured.
+  pub fn or(self, other: Self) -> Self {
+    Self {
+      findings: self.findings.or(other.findings),
+      error: self.error.or(other.error),
+    }
+  }
+
+  pub fn apply(&self, result: Result<ExitCode>) -> Result<ExitCode> {
+    let error = match result {
+      Ok(code) => return Ok(code),
+      Err(error) => error,
+    };
+    let code = match error.downcast_ref::<ErrorContext>() {
+      Some(ErrorContext::DiagnosticError(_)) => self.findings,
+      Some(e) if e.is_soft_error() => None,
+      _ => self.error,
+    };
+    match code {
+      Some(code) => Err(Error::new(ConfiguredExit { code, error })),
+      None => Err(error),
+    }
+  }
+}
+
+/// An error to exit with the configured code instead of the code of its context.
+#[derive(Debug)]
+struct ConfiguredExit {
+  code: u8,
+  error: Error,
+}
+
+impl fmt::Display for ConfiguredExit {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.error.fmt(f)
+  }
+}
+
+impl std::error::Error for ConfiguredExit {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    self.error.source()
+  }
+}
+
 pub fn exit_with_error(error: Error) -> Result<ExitCode> {
+  let (error, code) = match error.downcast::<ConfiguredExit>() {
+    Ok(configured) => (configured.error, Some(i32::from(configured.code))),
+    Err(error) => (error, None),
+  };
   if let Some(e) = error.downcast_ref::<clap::Error>() {
     e.exit()
   }
@@ -316,7 +452,12 @@ pub fn exit_with_error(error: Error) -> Result<ExitCode> {
       inner: &error,
     };
     eprintln!("{error_fmt}");
-    std::process::exit(e.exit_code())
+    std::process::exit(code.unwrap_or_else(|| e.exit_code()))
+  }
+  if let Some(code) = code {
+    // same as anyhow's default error reporting
+    eprintln!("Error: {error:?}");
+    std::process::exit(code)
   }
   // use anyhow's default error reporting
   Err(error)
@@ -407,6 +548,41 @@ impl fmt::Display for ErrorFormat<'_> {
 mod test {
   use super::*;
 
+  #[test]
+  fn test_exit_codes() {
+    let codes = ExitCodes {
+      findings: Some(3),
+      error: None,
+    }
+    .or(ExitCodes {
+      findings: Some(4),
+      error: Some(5),
+    });
+    let code_of = |error: Error| {
+      let error = codes.apply(Err(error)).expect_err("should keep error");
+      error.downcast_ref::<ConfiguredExit>().map(|e| e.code)
+    };
+    assert_eq!(
+      code_of(anyhow::anyhow!(ErrorContext::DiagnosticError(1))),
+      Some(3)
+    );
+    assert_eq!(
+      code_of(anyhow::anyhow!(ErrorContext::ReadConfiguration)),
+      Some(5)
+    );
+    assert_eq!(code_of(anyhow::anyhow!("unexpected")), Some(5));
+    assert_eq!(
+      code_of(anyhow::anyhow!(ErrorContext::PatternHasError)),
+      None
+    );
+    let error = codes
+      .apply(Err(anyhow::anyhow!(ErrorContext::ReadConfiguration)))
+      .expect_err("should keep error");
+    assert!(error.downcast_ref::<ErrorContext>().is_none());
+    assert_eq!(error.to_string(), "Cannot read configuration.");
+    assert!(ExitCodes::default().apply(Ok(ExitCode::SUCCESS)).is_ok());
+  }
+
   #[test]
   fn test_display_error() {
     let error = anyhow::anyhow!("test error").context(ErrorContext::ReadConfiguration);
@@ -0,0 +1,247 @@
+use super::ErrorContext as EC;
+
+use anyhow::{anyhow, Context, Result};
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Lines changed in the new version of a file, from one hunk of `git diff`.
+#[derive(Debug, PartialEq, Eq)]
+enum Hunk {
+  /// added or modified lines, zero-based and inclusive
+  Lines(usize, usize),
+  /// lines deleted between the zero-based line and the next one
+  Deletion(usize),
+}
+
+impl Hunk {
+  fn overlaps(&self, start: usize, end: usize) -> bool {
+    match *self {
+      Hunk::Lines(first, last) => start <= last && first <= end,
+      // a match only touches a deletion if it spans both lines around it
+      Hunk::Deletion(line) => start <= line && line < end,
+    }
+  }
+}
+
+/// The lines changed since a git ref, to report only findings in them.
+///
+/// Untracked files are changed as a whole.
+pub struct ChangedLines {
+  /// hunks by the canonical path of the files
+  files: HashMap<PathBuf, Vec<Hunk>>,
+}
+
+impl ChangedLines {
+  pub fn from_git(base: &str) -> Result<Self> {
+    let context = || EC::GitDiff(base.to_string());
+    let root = git(["rev-parse", "--show-toplevel"]).with_context(context)?;
+    let root = PathBuf::from(root.trim_end_matches('\n'))
+      .canonicalize()
+      .with_context(context)?;
+    let diff = git([
+      "-c",
+      "core.quotePath=false",
+      "diff",
+      "--no-color",
+      "--no-ext-diff",
+      "--unified=0",
+      "--src-prefix=a/",
+      "--dst-prefix=b/",
+      base,
+      "--",
+    ])
+    .with_context(context)?;
+    let mut files = parse_diff(&root, &diff);
+    let untracked =
+      git(["ls-files", "--others", "--exclude-standard", "--full-name"]).with_context(context)?;
+    for path in untracked.lines() {
+      files.insert(root.join(path), vec![Hunk::Lines(0, usize::MAX)]);
+    }
+    Ok(Self { files })
+  }
+
+  /// Whether the file at the canonical `path` has changed lines.
+  pub fn contains_file(&self, path: &Path) -> bool {
+    self.files.contains_key(path)
+  }
+
+  /// Whether the zero-based lines from `start` to `end` of the file at the canonical `path`
+  /// overlap its changed lines. A range spanning edited and unedited lines overlaps.
+  pub fn overlaps(&self, path: &Path, start: usize, end: usize) -> bool {
+    self
+      .files
+      .get(path)
+      .is_some_and(|hunks| hunks.iter().any(|h| h.overlaps(start, end)))
+  }
+}
+
+fn git<I, S>(args: I) -> Result<String>
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<OsStr>,
+{
+  let output = Command::new("git").args(args).output()?;
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(anyhow!("{}", stderr.trim_end()));
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn parse_diff(root: &Path, diff: &str) -> HashMap<PathBuf, Vec<Hunk>> {
+  let mut files = HashMap::new();
+  let mut current: Option<&mut Vec<Hunk>> = None;
+  // an added line starting with `++ ` looks like a header in a hunk
+  let mut in_header = false;
+  for line in diff.lines() {
+    if line.starts_with("diff --git ") {
+      in_header = true;
+      current = None;
+    } else if let Some(path) = line.strip_prefix("+++ ").filter(|_| in_header) {
+      // git appends a tab to paths with spaces
+      let path = path.trim_end_matches('\t');
+      // deleted files have no new lines
+      current = path
+        .strip_prefix("b/")
+        .or_else(|| path.strip_prefix("\"b/").map(|p| p.trim_end_matches('"')))
+        .map(|p| files.entry(root.join(unquote(p))).or_default());
+    } else if line.starts_with("@@ ") {
+      in_header = false;
+      if let (Some(hunks), Some(hunk)) = (current.as_mut(), parse_hunk_header(line)) {
+        hunks.push(hunk);
+      }
+    }
+  }
+  files
+}
+
+/// Unescape a path quoted by git because it has special characters.
+fn unquote(path: &str) -> String {
+  let mut ret = String::with_capacity(path.len());
+  let mut chars = path.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      ret.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('t') => ret.push('\t'),
+      Some('n') => ret.push('\n'),
+      Some(c) => ret.push(c),
+      None => ret.push('\\'),
+    }
+  }
+  ret
+}
+
+/// The new lines of a header like `@@ -1,2 +3,4 @@`, where the count is 1 if omitted.
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+  let new = line.split(' ').find_map(|s| s.strip_prefix('+'))?;
+  let (start, count) = match new.split_once(',') {
+    Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+    None => (new.parse().ok()?, 1),
+  };
+  // a deletion is after line `start`, otherwise the hunk starts at line `start`
+  Some(if count == 0 {
+    Hunk::Deletion(start.saturating_sub(1))
+  } else {
+    Hunk::Lines(start - 1, start + count - 2)
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  const DIFF: &str = r#"diff --git a/a.js b/a.js
+index 1234567..89abcde 100644
+--- a/a.js
++++ b/a.js
+@@ -2 +2 @@ function a() {
+-  foo(1)
++  foo(2)
+@@ -5,2 +4,0 @@ function b() {
+-  bar()
+-  bar()
+@@ -10,0 +10,3 @@
++  a
++  b
++  c
+diff --git a/old.js b/old.js
+deleted file mode 100644
+--- a/old.js
++++ /dev/null
+@@ -1 +0,0 @@
+-gone()
+diff --git "a/with\"quote.js" "b/with\"quote.js"
+--- "a/with\"quote.js"
++++ "b/with\"quote.js"
+@@ -1 +1 @@
+-a
++b
+"#;
+
+  #[test]
+  fn test_parse_hunk_header() {
+    let parse = |s| parse_hunk_header(s).expect("should parse");
+    assert_eq!(parse("@@ -2 +2 @@"), Hunk::Lines(1, 1));
+    assert_eq!(parse("@@ -1,2 +3,4 @@ fn a()"), Hunk::Lines(2, 5));
+    assert_eq!(parse("@@ -5,2 +4,0 @@"), Hunk::Deletion(3));
+    assert_eq!(parse("@@ -1 +0,0 @@"), Hunk::Deletion(0));
+    assert!(parse_hunk_header("@@ invalid @@").is_none());
+  }
+
+  #[test]
+  fn test_parse_diff() {
+    let root = Path::new("/repo");
+    let files = parse_diff(root, DIFF);
+    assert_eq!(files.len(), 2);
+    assert_eq!(
+      files[&root.join("a.js")],
+      vec![Hunk::Lines(1, 1), Hunk::Deletion(3), Hunk::Lines(9, 11)]
+    );
+    assert_eq!(files[&root.join("with\"quote.js")], vec![Hunk::Lines(0, 0)]);
+  }
+
+  #[test]
+  fn test_parse_diff_added_plus_lines() {
+    let diff = "diff --git a/a.hs b/a.hs
+--- a/a.hs
++++ b/a.hs
+@@ -1,0 +2,2 @@
++xs = [1]
++++ [2]
+@@ -8 +9 @@
+-a
++b
+";
+    let root = Path::new("/repo");
+    let files = parse_diff(root, diff);
+    assert_eq!(files.len(), 1);
+    assert_eq!(
+      files[&root.join("a.hs")],
+      vec![Hunk::Lines(1, 2), Hunk::Lines(8, 8)]
+    );
+  }
+
+  #[test]
+  fn test_overlaps() {
+    let path = PathBuf::from("/repo/a.js");
+    let changes = ChangedLines {
+      files: parse_diff(Path::new("/repo"), DIFF),
+    };
+    assert!(changes.contains_file(&path));
+    assert!(!changes.contains_file(Path::new("/repo/old.js")));
+    assert!(changes.overlaps(&path, 1, 1));
+    // spans edited and unedited lines
+    assert!(changes.overlaps(&path, 0, 2));
+    assert!(changes.overlaps(&path, 11, 20));
+    assert!(!changes.overlaps(&path, 2, 3));
+    assert!(changes.overlaps(&path, 3, 4));
+    assert!(!changes.overlaps(&path, 4, 8));
+    assert!(!changes.overlaps(Path::new("/repo/b.js"), 0, 100));
+  }
+}
@@ -1,17 +1,33 @@
 mod args;
+mod baseline;
 mod debug_query;
 mod error_context;
+mod git_diff;
 mod inspect;
+mod polyglot;
 mod print_diff;
+mod profile;
+mod redact;
 mod rule_overwrite;
+mod ruleset;
+mod scan_cache;
+mod watch;
 mod worker;
 
 pub use args::{ContextArgs, InputArgs, OutputArgs, OverwriteArgs};
+pub use baseline::{match_fingerprints, Baseline, BaselineMode};
 pub use debug_query::DebugFormat;
-pub use error_context::{exit_with_error, ErrorContext};
+pub use error_context::{exit_with_error, ErrorContext, ExitCodes};
+pub use git_diff::ChangedLines;
 pub use inspect::{FileTrace, Granularity, RuleTrace, RunTrace, ScanTrace};
+pub use polyglot::DocumentKind;
 pub use print_diff::DiffStyles;
+pub use profile::ScanProfiler;
+pub use redact::{is_secret_rule, Redacted};
 pub use rule_overwrite::RuleOverwrite;
+pub use ruleset::RemoteRuleset;
+pub use scan_cache::{hex, hex_digest, rules_hash, ScanCache};
+pub use watch::ParseCache;
 pub use worker::{Items, MaxItemCounter, PathWorker, StdInWorker, Worker};
 
 use crate::lang::SgLang;
@@ -97,7 +113,7 @@ pub fn prompt(prompt_text: &str, letters: &str, default: Option<char>) -> Result
   }
 }
 
-fn read_file(path: &Path) -> Result<String> {
+pub fn read_file(path: &Path) -> Result<String> {
   let file_content =
     read_to_string(path).with_context(|| format!("Cannot read file {}", path.to_string_lossy()))?;
   // skip large files or empty file
@@ -125,12 +141,25 @@ pub fn filter_file_rule(
   path: &Path,
   configs: &RuleCollection<SgLang>,
   trace: &ScanTrace,
+  cache: &ParseCache,
 ) -> Result<SmallVec<[AstGrep; 1]>> {
   let Some(lang) = SgLang::from_path(path) else {
     return Ok(smallvec![]);
   };
   let file_content = read_file(path)?;
-  let grep = lang.ast_grep(file_content);
+  filter_file_content(path, lang, file_content, configs, trace, cache)
+}
+
+/// Like `filter_file_rule`, for the file content read already.
+pub fn filter_file_content(
+  path: &Path,
+  lang: SgLang,
+  file_content: String,
+  configs: &RuleCollection<SgLang>,
+  trace: &ScanTrace,
+  cache: &ParseCache,
+) -> Result<SmallVec<[AstGrep; 1]>> {
+  let grep = cache.parse(path, lang, file_content);
   collect_file_stats(path, lang, configs, trace)?;
   let mut ret = smallvec![grep.clone()];
   if lang.injectable_languages().is_some() {
@@ -143,6 +172,25 @@ pub fn filter_file_rule(
   Ok(ret)
 }
 
+/// Parse the code blocks of a document whose languages have rules.
+pub fn filter_document_blocks(
+  path: &Path,
+  kind: DocumentKind,
+  file_content: &str,
+  configs: &RuleCollection<SgLang>,
+  trace: &ScanTrace,
+) -> Result<Vec<AstGrep>> {
+  let mut ret = vec![];
+  for block in polyglot::extract_blocks(kind, file_content) {
+    if configs.get_rule_from_lang(path, block.lang).is_empty() {
+      continue;
+    }
+    collect_file_stats(path, block.lang, configs, trace)?;
+    ret.push(block.lang.ast_grep(block.source));
+  }
+  Ok(ret)
+}
+
 // sub_matchers are the injected languages
 // e.g. js/css in html
 pub fn filter_file_pattern<'a>(
@@ -150,9 +198,10 @@ pub fn filter_file_pattern<'a>(
   lang: SgLang,
   root_matcher: Option<&'a Pattern>,
   sub_matchers: &'a [(SgLang, Pattern)],
+  cache: &ParseCache,
 ) -> Result<SmallVec<[MatchUnit<&'a Pattern>; 1]>> {
-  let file_content = read_file(path)?;
-  let grep = lang.ast_grep(&file_content);
+  let grep = cache.parse(path, lang, read_file(path)?);
+  let file_content = grep.source();
   let do_match = |ast_grep: AstGrep, matcher: &'a Pattern| {
     let fixed = matcher.fixed_string();
     if !fixed.is_empty() && !file_content.contains(&*fixed) {
@@ -0,0 +1,383 @@
+use crate::lang::SgLang;
+
+use ignore::types::{Types, TypesBuilder};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use std::path::Path;
+use std::str::FromStr;
+
+/// A document embedding code of other languages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentKind {
+  /// Markdown or MDX, with fenced code blocks tagged by language
+  Markdown,
+  /// Jupyter notebook, with code cells in the kernel's language
+  Notebook,
+}
+
+const MARKDOWN_GLOBS: &[&str] = &["*.md", "*.markdown", "*.mdx"];
+const NOTEBOOK_GLOBS: &[&str] = &["*.ipynb"];
+
+impl DocumentKind {
+  pub fn from_path(path: &Path) -> Option<Self> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+      "md" | "markdown" | "mdx" => Some(Self::Markdown),
+      "ipynb" => Some(Self::Notebook),
+      _ => None,
+    }
+  }
+
+  /// The file types of all documents, to walk them with the files of rule languages.
+  pub fn file_types() -> Types {
+    let mut builder = TypesBuilder::new();
+    for (name, globs) in [("markdown", MARKDOWN_GLOBS), ("notebook", NOTEBOOK_GLOBS)] {
+      for glob in globs {
+        builder.add(name, glob).expect("glob should be valid");
+      }
+      builder.select(name);
+    }
+    builder.build().expect("file types must be valid")
+  }
+}
+
+/// One block of code in a document.
+///
+/// The source has the lines of the block at the lines of the document they are in, and
+/// empty lines elsewhere, so that the positions of matches are positions in the document.
+#[derive(Debug)]
+pub struct CodeBlock {
+  pub lang: SgLang,
+  pub source: String,
+}
+
+/// The code blocks of the document with a known language.
+pub fn extract_blocks(kind: DocumentKind, content: &str) -> Vec<CodeBlock> {
+  match kind {
+    DocumentKind::Markdown => markdown_blocks(content),
+    DocumentKind::Notebook => notebook_cells(content),
+  }
+}
+
+/// Lay out a block's lines at the zero-based lines of the document.
+/// A line before the end of the layout goes after it.
+fn layout(lang: SgLang, lines: impl IntoIterator<Item = (usize, String)>) -> CodeBlock {
+  let mut source = String::new();
+  let mut next_line = 0;
+  for (line, text) in lines {
+    while next_line < line {
+      source.push('\n');
+      next_line += 1;
+    }
+    source.push_str(text.trim_end_matches(['\n', '\r']));
+    source.push('\n');
+    next_line += 1;
+  }
+  CodeBlock { lang, source }
+}
+
+/// The language of an info string like `js title="a.js"` or `{.python}`.
+fn tag_lang(info: &str) -> Option<SgLang> {
+  let tag = info
+    .trim_start_matches(['{', '.'])
+    .split(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | ',' | ':'))
+    .next()?;
+  SgLang::from_str(tag).ok()
+}
+
+struct Fence<'a> {
+  marker: char,
+  len: usize,
+  indent: usize,
+  info: &'a str,
+}
+
+/// A fence line indented by at most three spaces, per CommonMark.
+fn parse_fence(line: &str) -> Option<Fence<'_>> {
+  let trimmed = line.trim_start_matches(' ');
+  let indent = line.len() - trimmed.len();
+  let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+  let len = trimmed.len() - trimmed.trim_start_matches(marker).len();
+  if indent > 3 || len < 3 {
+    return None;
+  }
+  let info = trimmed[len..].trim();
+  // the info string of backtick fences cannot have backticks
+  if marker == '`' && info.contains('`') {
+    return None;
+  }
+  Some(Fence {
+    marker,
+    len,
+    indent,
+    info,
+  })
+}
+
+fn markdown_blocks(content: &str) -> Vec<CodeBlock> {
+  let mut blocks = vec![];
+  let mut lines = content.lines().enumerate();
+  while let Some((_, line)) = lines.next() {
+    let Some(open) = parse_fence(line) else {
+      continue;
+    };
+    let lang = tag_lang(open.info);
+    let mut code = vec![];
+    // an unclosed block ends with the document
+    for (i, line) in lines.by_ref() {
+      let is_close = parse_fence(line)
+        .is_some_and(|f| f.marker == open.marker && f.len >= open.len && f.info.is_empty());
+      if is_close {
+        break;
+      }
+      // the indentation of the fence is removed from the code
+      let indent = line.len() - line.trim_start_matches(' ').len();
+      code.push((i, line[indent.min(open.indent)..].to_string()));
+    }
+    if let Some(lang) = lang {
+      blocks.push(layout(lang, code));
+    }
+  }
+  blocks
+}
+
+/// The fields of a notebook to find its code, with the raw JSON of the cell sources to
+/// know where they are in the file.
+#[derive(Deserialize)]
+struct Notebook<'a> {
+  #[serde(borrow, default)]
+  cells: Vec<Cell<'a>>,
+  #[serde(default)]
+  metadata: Value,
+}
+
+#[derive(Deserialize)]
+struct Cell<'a> {
+  #[serde(default)]
+  cell_type: String,
+  #[serde(borrow)]
+  source: Option<&'a RawValue>,
+}
+
+fn notebook_lang(metadata: &Value) -> Option<SgLang> {
+  let name = metadata["kernelspec"]["language"]
+    .as_str()
+    .or_else(|| metadata["language_info"]["name"].as_str())
+    .unwrap_or("python");
+  SgLang::from_str(name).ok()
+}
+
+/// The source lines of a cell, each with the offset in `content` of the JSON string it is
+/// written in. The source is an array of lines or a single string, whose lines all have the
+/// offset of the string, so they are laid out from its line on.
+fn cell_lines(content: &str, source: &RawValue) -> Vec<(usize, String)> {
+  // raw values borrow from the content, so their offset is that of their text
+  let offset = |raw: &RawValue| raw.get().as_ptr() as usize - content.as_ptr() as usize;
+  if let Ok(lines) = serde_json::from_str::<Vec<&RawValue>>(source.get()) {
+    return lines
+      .into_iter()
+      .filter_map(|line| {
+        let text = serde_json::from_str::<String>(line.get()).ok()?;
+        Some((offset(line), text))
+      })
+      .collect();
+  }
+  let Ok(text) = serde_json::from_str::<String>(source.get()) else {
+    return vec![];
+  };
+  let start = offset(source);
+  text
+    .split_inclusive('\n')
+    .map(|line| (start, line.to_string()))
+    .collect()
+}
+
+/// Code cells are mapped to the lines of the notebook file where their source lines are
+/// written.
+fn notebook_cells(content: &str) -> Vec<CodeBlock> {
+  let Ok(notebook) = serde_json::from_str::<Notebook>(content) else {
+    return vec![];
+  };
+  let Some(lang) = notebook_lang(&notebook.metadata) else {
+    return vec![];
+  };
+  let mut blocks = vec![];
+  // offsets only grow, so lines are counted from the previous one
+  let (mut counted, mut line) = (0, 0);
+  for cell in notebook.cells {
+    let Some(source) = cell.source.filter(|_| cell.cell_type == "code") else {
+      continue;
+    };
+    let mut code = vec![];
+    for (offset, text) in cell_lines(content, source) {
+      line += content[counted..offset].matches('\n').count();
+      counted = offset;
+      code.push((line, text));
+    }
+    if !code.is_empty() {
+      blocks.push(layout(lang, code));
+    }
+  }
+  blocks
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_language::SupportLang;
+
+  const MARKDOWN: &str = r#"# Title
+
+```js title="a.js"
+foo(1)
+```
+
+  ~~~~python
+  foo(2)
+    bar()
+  ~~~~
+
+```text
+foo(3)
+```
+
+````
+```ts
+foo(4)
+```
+````
+"#;
+
+  #[test]
+  fn test_document_kind() {
+    let kind = |p| DocumentKind::from_path(Path::new(p));
+    assert_eq!(kind("a.md"), Some(DocumentKind::Markdown));
+    assert_eq!(kind("a.MDX"), Some(DocumentKind::Markdown));
+    assert_eq!(kind("a.ipynb"), Some(DocumentKind::Notebook));
+    assert_eq!(kind("a.js"), None);
+    let types = DocumentKind::file_types();
+    assert!(types.matched("a.markdown", false).is_whitelist());
+    assert!(!types.matched("a.rs", false).is_whitelist());
+  }
+
+  #[test]
+  fn test_markdown_blocks() {
+    let blocks = markdown_blocks(MARKDOWN);
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[0].lang, SupportLang::JavaScript.into());
+    assert_eq!(blocks[0].source, "\n\n\nfoo(1)\n");
+    assert_eq!(blocks[1].lang, SupportLang::Python.into());
+    assert_eq!(blocks[1].source, "\n\n\n\n\n\n\nfoo(2)\n  bar()\n");
+  }
+
+  #[test]
+  fn test_unclosed_block() {
+    let blocks = markdown_blocks("text\n```rust\nfn a() {}\n");
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].source, "\n\nfn a() {}\n");
+  }
+
+  #[test]
+  fn test_tag_lang() {
+    assert_eq!(tag_lang("{.python}"), Some(SupportLang::Python.into()));
+    assert_eq!(
+      tag_lang("ts,linenums"),
+      Some(SupportLang::TypeScript.into())
+    );
+    assert_eq!(tag_lang("not-a-lang"), None);
+    assert_eq!(tag_lang(""), None);
+  }
+
+  const NOTEBOOK: &str = r#"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": [
+    "foo(1)\n"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "metadata": {},
+   "outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "foo(2)\n"
+     ]
+    }
+   ],
+   "source": [
+    "a = \"b\"\n",
+    "foo(2)\n"
+   ]
+  }
+ ],
+ "metadata": {
+  "kernelspec": {
+   "display_name": "Python 3",
+   "language": "python",
+   "name": "python3"
+  }
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}"#;
+
+  #[test]
+  fn test_notebook_cells() {
+    let blocks = notebook_cells(NOTEBOOK);
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].lang, SupportLang::Python.into());
+    let lines: Vec<_> = blocks[0].source.lines().collect();
+    assert_eq!(lines.len(), 25);
+    assert_eq!(lines[23], "a = \"b\"");
+    assert_eq!(lines[24], "foo(2)");
+    assert!(notebook_cells("not json").is_empty());
+  }
+
+  #[test]
+  fn test_notebook_string_source() {
+    let notebook = r#"{
+ "cells": [
+  {"cell_type": "code", "source": "a = 1\nfoo(2)\n"},
+  {"cell_type": "code", "source": ["b = 3"]}
+ ],
+ "metadata": {"kernelspec": {"language": "python"}}
+}"#;
+    let blocks = notebook_cells(notebook);
+    assert_eq!(blocks.len(), 2);
+    // the lines of a string are laid out from the line the string is on
+    assert_eq!(blocks[0].source, "\n\na = 1\nfoo(2)\n");
+    assert_eq!(blocks[1].source, "\n\n\nb = 3\n");
+  }
+
+  #[test]
+  fn test_notebook_escaped_source() {
+    let notebook = r#"{
+ "cells": [
+  {
+   "cell_type": "code",
+   "source": [
+    "url = \"http:\/\/a.b\"\n",
+    "s = \"caf\u00e9\"\n",
+    "foo(2)"
+   ]
+  }
+ ],
+ "metadata": {"kernelspec": {"language": "python"}}
+}"#;
+    let blocks = notebook_cells(notebook);
+    assert_eq!(blocks.len(), 1);
+    let lines: Vec<_> = blocks[0].source.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[5], "url = \"http://a.b\"");
+    assert_eq!(lines[6], "s = \"café\"");
+    assert_eq!(lines[7], "foo(2)");
+  }
+}
@@ -0,0 +1,110 @@
+use crate::lang::SgLang;
+
+use ast_grep_config::{CombinedScan, ScanProfile, ScanResult};
+use ast_grep_core::{tree_sitter::StrDoc, AstGrep};
+
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type Doc = StrDoc<SgLang>;
+
+/// The rule profile of `scan --profile`, summed over the files scanned by every thread.
+pub struct ScanProfiler {
+  start: Instant,
+  total: Mutex<ScanProfile>,
+}
+
+impl ScanProfiler {
+  pub fn new() -> Self {
+    Self {
+      start: Instant::now(),
+      total: Mutex::new(ScanProfile::default()),
+    }
+  }
+
+  /// Scan `grep` like `CombinedScan::scan` and add its profile to the total.
+  pub fn scan<'a, 'r>(
+    &self,
+    combined: &'r CombinedScan<'r, SgLang>,
+    grep: &'a AstGrep<Doc>,
+    separate_fix: bool,
+  ) -> ScanResult<'a, 'r, Doc, SgLang> {
+    let mut profile = ScanProfile::default();
+    let now = || self.start.elapsed();
+    let scanned = combined.scan_profiled(grep, separate_fix, &mut profile, &now);
+    let mut total = self
+      .total
+      .lock()
+      .expect("profile lock should not be poisoned");
+    total.merge(&profile);
+    scanned
+  }
+
+  /// Print the rules to stderr, the slowest first.
+  pub fn print(&self) -> std::io::Result<()> {
+    let total = self
+      .total
+      .lock()
+      .expect("profile lock should not be poisoned");
+    let mut stderr = std::io::stderr().lock();
+    write!(stderr, "{}", report(&total))
+  }
+}
+
+fn report(profile: &ScanProfile) -> String {
+  let mut ret = format!(
+    "Rule profile: {} node(s) visited, {} skipped by the prefilter\n",
+    profile.visited, profile.skipped
+  );
+  ret.push_str(&format!(
+    "{:>10} {:>8} {:>11} {:>10}  rule\n",
+    "time", "matches", "candidates", "prefilter"
+  ));
+  for (id, rule) in profile.slowest() {
+    ret.push_str(&format!(
+      "{:>10} {:>8} {:>11} {:>9.1}%  {id}\n",
+      format_time(rule.time),
+      rule.matches,
+      rule.candidates,
+      rule.prefilter_hit_rate() * 100.0,
+    ));
+  }
+  ret
+}
+
+fn format_time(time: Duration) -> String {
+  format!("{:.2}ms", time.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_config::RuleProfile;
+
+  #[test]
+  fn test_report() {
+    let mut profile = ScanProfile {
+      visited: 10,
+      skipped: 4,
+      ..Default::default()
+    };
+    let rule = |ms, prefiltered| RuleProfile {
+      time: Duration::from_micros(ms),
+      candidates: 4,
+      prefiltered,
+      matches: 1,
+    };
+    profile.rules.insert("fast".into(), rule(10, 3));
+    profile.rules.insert("slow".into(), rule(2500, 0));
+    let report = report(&profile);
+    let lines: Vec<_> = report.lines().collect();
+    assert_eq!(
+      lines[0],
+      "Rule profile: 10 node(s) visited, 4 skipped by the prefilter"
+    );
+    assert!(lines[2].ends_with("0.0%  slow"));
+    assert!(lines[2].trim_start().starts_with("2.50ms"));
+    assert!(lines[3].ends_with("75.0%  fast"));
+  }
+}
@@ -0,0 +1,194 @@
+use crate::lang::SgLang;
+
+use ast_grep_config::{Metadata, RuleConfig};
+use ast_grep_core::meta_var::{MetaVarEnv, MetaVariable};
+use ast_grep_core::tree_sitter::StrDoc;
+use ast_grep_core::{AstGrep, Node, NodeMatch};
+use serde_yaml::Value as YamlValue;
+
+use std::ops::Range;
+
+type Doc = StrDoc<SgLang>;
+
+/// The tag of rules whose findings are secrets, like `metadata: { tags: [secret] }`.
+const SECRET_TAG: &str = "secret";
+/// The number of characters kept at both ends of a masked text.
+const KEPT: usize = 2;
+
+/// If the rule's findings are secrets, so that their text must not be printed.
+pub fn is_secret_rule(rule: &RuleConfig<SgLang>) -> bool {
+  is_secret(rule.metadata.as_ref())
+}
+
+fn is_secret(metadata: Option<&Metadata>) -> bool {
+  match metadata.and_then(|m| m.get("tags")) {
+    Some(YamlValue::Sequence(tags)) => tags.iter().any(|t| t.as_str() == Some(SECRET_TAG)),
+    Some(YamlValue::String(tag)) => tag == SECRET_TAG,
+    _ => false,
+  }
+}
+
+/// The source of a file with the text of secret findings masked.
+///
+/// The masked source has the same bytes at the same offsets except for the masked ones, and
+/// a copy of the tree of the source, so that the matches are moved to it as they are and
+/// every printer shows the masked text instead of the secret.
+pub struct Redacted {
+  root: AstGrep<Doc>,
+}
+
+impl Redacted {
+  /// Mask the matched text and meta variables of `matches`. A meta variable keeps its
+  /// ends even if they are in the middle of the match.
+  pub fn new(grep: &AstGrep<Doc>, matches: &[NodeMatch<Doc>]) -> Self {
+    let doc = grep.root().get_doc();
+    let mut ranges = vec![];
+    for m in matches {
+      ranges.push(m.range());
+      let env = m.get_env();
+      for var in env.get_matched_variables() {
+        ranges.extend(var_nodes(env, &var).iter().map(Node::range));
+      }
+    }
+    // inner ranges are masked last
+    ranges.sort_by_key(|r| std::cmp::Reverse(r.len()));
+    let mut masked = vec![false; doc.src.len()];
+    for range in ranges {
+      mask_range(&doc.src, range, &mut masked);
+    }
+    let src = doc
+      .src
+      .char_indices()
+      .map(|(i, c)| match masked[i] {
+        // the same number of bytes keeps the offsets
+        true => "*".repeat(c.len_utf8()),
+        false => c.to_string(),
+      })
+      .collect();
+    let doc = StrDoc {
+      src,
+      lang: doc.lang,
+      tree: doc.tree.clone(),
+    };
+    Self {
+      root: AstGrep::doc(doc),
+    }
+  }
+
+  pub fn source(&self) -> &str {
+    self.root.source()
+  }
+
+  /// The match of `grep`'s source, in the masked source.
+  pub fn adopt(&self, m: &NodeMatch<Doc>) -> NodeMatch<'_, Doc> {
+    let env = m.get_env();
+    let mut adopted = MetaVarEnv::new();
+    for var in env.get_matched_variables() {
+      match &var {
+        MetaVariable::Capture(name, _) => {
+          if let Some(node) = env.get_match(name) {
+            adopted.insert(name, self.adopt_node(node));
+          } else if let Some(bytes) = env.get_transformed(name) {
+            // a transformation of a secret is a secret too
+            let masked = mask(&String::from_utf8_lossy(bytes)).into_bytes();
+            adopted.insert_transformation(&MetaVariable::Dropped(false), name, masked);
+          }
+        }
+        MetaVariable::MultiCapture(name) => {
+          let nodes = env.get_multiple_matches(name);
+          let nodes = nodes.iter().map(|n| self.adopt_node(n)).collect();
+          adopted.insert_multi(name, nodes);
+        }
+        _ => (),
+      }
+    }
+    NodeMatch::new(self.adopt_node(m.get_node()), adopted)
+  }
+
+  /// The node at the same place in the copy of the tree.
+  fn adopt_node(&self, node: &Node<Doc>) -> Node<'_, Doc> {
+    let mut path = vec![];
+    let mut node = node.clone();
+    while let Some(parent) = node.parent() {
+      let nth = parent
+        .children()
+        .position(|child| child.node_id() == node.node_id())
+        .expect("node should be a child of its parent");
+      path.push(nth);
+      node = parent;
+    }
+    let mut adopted = self.root.root();
+    for nth in path.into_iter().rev() {
+      adopted = adopted.child(nth).expect("trees should be the same");
+    }
+    adopted
+  }
+}
+
+fn var_nodes<'t>(env: &MetaVarEnv<'t, Doc>, var: &MetaVariable) -> Vec<Node<'t, Doc>> {
+  match var {
+    MetaVariable::Capture(name, _) => env.get_match(name).into_iter().cloned().collect(),
+    MetaVariable::MultiCapture(name) => env.get_multiple_matches(name),
+    _ => vec![],
+  }
+}
+
+/// Mask the characters of `range` in `src` but whitespace, so that lines stay as they are,
+/// and the first and last `KEPT` ones if at least as many are masked.
+fn mask_range(src: &str, range: Range<usize>, masked: &mut [bool]) {
+  let text = &src[range.clone()];
+  let len = text.chars().count();
+  let kept = if len >= 4 * KEPT { KEPT } else { 0 };
+  for (n, (i, c)) in text.char_indices().enumerate() {
+    masked[range.start + i] = n >= kept && n + kept < len && !c.is_whitespace();
+  }
+}
+
+/// `text` masked like the text of a finding.
+fn mask(text: &str) -> String {
+  let mut masked = vec![false; text.len()];
+  mask_range(text, 0..text.len(), &mut masked);
+  text
+    .char_indices()
+    .map(|(i, c)| if masked[i] { '*' } else { c })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_core::tree_sitter::LanguageExt;
+  use ast_grep_language::SupportLang;
+
+  fn metadata(yaml: &str) -> Metadata {
+    serde_yaml::from_str(yaml).expect("should parse")
+  }
+
+  #[test]
+  fn test_is_secret() {
+    assert!(is_secret(Some(&metadata("tags: [secret, aws]"))));
+    assert!(is_secret(Some(&metadata("tags: secret"))));
+    assert!(!is_secret(Some(&metadata("tags: [security]"))));
+    assert!(!is_secret(Some(&metadata("cwe: CWE-798"))));
+    assert!(!is_secret(None));
+  }
+
+  #[test]
+  fn test_redact_matches() {
+    let lang = SgLang::from(SupportLang::JavaScript);
+    let grep = lang.ast_grep("const key = 'AKIA1234567890';\nlet a = 1");
+    let matches: Vec<_> = grep.root().find_all("const $K = $V").collect();
+    let redacted = Redacted::new(&grep, &matches);
+    assert_eq!(
+      redacted.source(),
+      "co*** *** * 'A************0';\nlet a = 1"
+    );
+    let m = redacted.adopt(&matches[0]);
+    assert_eq!(m.text(), "co*** *** * 'A************0';");
+    assert_eq!(
+      m.get_env().get_match("V").expect("should match").text(),
+      "'A************0'"
+    );
+    assert_eq!(m.start_pos().line(), 0);
+  }
+}
@@ -13,6 +13,8 @@ use std::collections::HashMap;
 pub struct RuleOverwrite {
   default_severity: Option<Severity>,
   by_rule_id: HashMap<String, Severity>,
+  /// severities in the project config, which the CLI flags take precedence over
+  by_project: HashMap<String, Severity>,
   rule_filter: Option<Regex>,
 }
 
@@ -41,6 +43,7 @@ impl RuleOverwrite {
         None
       },
       by_rule_id: HashMap::new(),
+      by_project: HashMap::new(),
       rule_filter: filter.cloned(),
     }
   }
@@ -80,10 +83,16 @@ impl RuleOverwrite {
     Ok(Self {
       default_severity,
       by_rule_id,
+      by_project: HashMap::new(),
       rule_filter: cli.filter.clone(),
     })
   }
 
+  pub fn with_project_severities(mut self, severities: &HashMap<String, Severity>) -> Self {
+    self.by_project = severities.clone();
+    self
+  }
+
   pub fn process_configs(
     &self,
     configs: Vec<RuleConfig<SgLang>>,
@@ -105,7 +114,8 @@ impl RuleOverwrite {
       .by_rule_id
       .get(id)
       .cloned()
-      .or_else(|| self.default_severity.clone());
+      .or_else(|| self.default_severity.clone())
+      .or_else(|| self.by_project.get(id).cloned());
     OverwriteResult { severity }
   }
 }
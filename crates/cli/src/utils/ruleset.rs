@@ -0,0 +1,327 @@
+use super::{hex, ErrorContext as EC};
+
+use anyhow::{anyhow, Context, Result};
+use ast_grep_config::PublicKey;
+use sha2::{Digest, Sha256};
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+const DIGEST_PREFIX: &str = "@sha256:";
+
+/// A `.tar.gz` archive of rules published at a URL, pinned by the SHA-256 digest of the archive
+/// like `https://example.com/ruleset.tar.gz@sha256:<digest>`.
+///
+/// Archives are downloaded with `curl` and extracted with `tar`, so that ast-grep needs no HTTP
+/// or archive library. An extracted archive is cached by its digest and never fetched again.
+///
+/// With trusted keys, the archive must also have a minisign signature at `URL.minisig` by one
+/// of them. Only the archive and its signature are trusted in the cache: whenever the cache is
+/// used, both the digest and the signature of the archive are verified again, and the rules
+/// are extracted again from the verified archive, replacing the extracted files. A cached
+/// archive that fails this, like one signed by a key that is no longer trusted, is fetched again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteRuleset {
+  pub url: String,
+  /// lowercase hex of the SHA-256 digest
+  pub digest: String,
+}
+
+impl FromStr for RemoteRuleset {
+  type Err = anyhow::Error;
+  fn from_str(s: &str) -> Result<Self> {
+    let Some((url, digest)) = s.rsplit_once(DIGEST_PREFIX) else {
+      return Err(anyhow!(
+        "ruleset `{s}` must be pinned like `URL{DIGEST_PREFIX}<digest>`"
+      ));
+    };
+    if digest.len() != 64 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+      return Err(anyhow!(
+        "ruleset `{s}` must have a SHA-256 digest of 64 hex digits"
+      ));
+    }
+    Ok(Self {
+      url: url.to_string(),
+      digest: digest.to_ascii_lowercase(),
+    })
+  }
+}
+
+impl RemoteRuleset {
+  /// The directory of the extracted ruleset in `cache_dir`, fetching it if it is not cached.
+  /// Unless `trusted_keys` is empty, it must be signed by one of them.
+  pub fn fetch(&self, cache_dir: &Path, trusted_keys: &[PublicKey]) -> Result<PathBuf> {
+    let dir = cache_dir.join(&self.digest);
+    let signature = cache_dir.join(format!("{}.minisig", self.digest));
+    let archive = cache_dir.join(format!("{}.tar.gz", self.digest));
+    let context = || EC::FetchRuleset(self.url.clone());
+    if trusted_keys.is_empty() && dir.is_dir() {
+      return Ok(dir);
+    }
+    if !trusted_keys.is_empty() {
+      if let Some(bytes) = self.read_signed_cache(&archive, &signature, trusted_keys) {
+        // the extracted files are not signed, anyone able to write the cache can change them
+        self.extract(&bytes, cache_dir).with_context(context)?;
+        return Ok(dir);
+      }
+      let _ = fs::remove_dir_all(&dir);
+    }
+    fs::create_dir_all(cache_dir).with_context(context)?;
+    run(curl(&self.url, &archive), None).with_context(context)?;
+    let bytes = fs::read(&archive).with_context(context)?;
+    let actual = hex(&Sha256::digest(&bytes));
+    if actual != self.digest {
+      let _ = fs::remove_file(&archive);
+      return Err(anyhow!(EC::RulesetDigestMismatch(self.url.clone(), actual)));
+    }
+    if !trusted_keys.is_empty() {
+      let verified = self.verify_signature(&bytes, &signature, trusted_keys);
+      if verified.is_err() {
+        let _ = fs::remove_file(&archive);
+        let _ = fs::remove_file(&signature);
+      }
+      verified?;
+    }
+    self.extract(&bytes, cache_dir).with_context(context)?;
+    // a signed archive is kept to verify its signature again when the cache is used
+    if trusted_keys.is_empty() {
+      fs::remove_file(&archive).with_context(context)?;
+    }
+    Ok(dir)
+  }
+
+  /// Extract the archive `bytes` to the ruleset's directory in `cache_dir`, replacing it.
+  /// The bytes are extracted and not the archive file, which could change after it was read.
+  fn extract(&self, bytes: &[u8], cache_dir: &Path) -> Result<()> {
+    // extract to another directory first so that an interrupted fetch is not cached
+    let partial = cache_dir.join(format!("{}.partial", self.digest));
+    let _ = fs::remove_dir_all(&partial);
+    fs::create_dir_all(&partial)?;
+    let mut tar = Command::new("tar");
+    tar.arg("-xzf").arg("-").arg("-C").arg(&partial);
+    run(tar, Some(bytes))?;
+    let dir = cache_dir.join(&self.digest);
+    let _ = fs::remove_dir_all(&dir);
+    fs::rename(&partial, &dir)?;
+    Ok(())
+  }
+
+  /// Fetch the signature of the archive `bytes` to `path` and verify it.
+  fn verify_signature(&self, bytes: &[u8], path: &Path, trusted_keys: &[PublicKey]) -> Result<()> {
+    let url = format!("{}.minisig", self.url);
+    let untrusted = |reason: String| EC::UntrustedRuleset(self.url.clone(), reason);
+    run(curl(&url, path), None).with_context(|| untrusted(format!("Cannot fetch {url}.")))?;
+    let signature = fs::read_to_string(path).with_context(|| EC::FetchRuleset(url.clone()))?;
+    ast_grep_config::verify_signature(bytes, &signature, trusted_keys)
+      .map_err(|e| anyhow!(untrusted(e.to_string())))
+  }
+
+  /// The cached `archive` if it still has the pinned digest and `signature` is a signature
+  /// of it by a trusted key.
+  fn read_signed_cache(
+    &self,
+    archive: &Path,
+    signature: &Path,
+    trusted_keys: &[PublicKey],
+  ) -> Option<Vec<u8>> {
+    let bytes = fs::read(archive).ok()?;
+    let signature = fs::read_to_string(signature).ok()?;
+    let signed = hex(&Sha256::digest(&bytes)) == self.digest
+      && ast_grep_config::verify_signature(&bytes, &signature, trusted_keys).is_ok();
+    signed.then_some(bytes)
+  }
+}
+
+fn curl(url: &str, output: &Path) -> Command {
+  let mut curl = Command::new("curl");
+  curl
+    .args([
+      "--fail",
+      "--silent",
+      "--show-error",
+      "--location",
+      "--output",
+    ])
+    .arg(output)
+    // a URL starting with `-` is not an option
+    .arg("--")
+    .arg(url);
+  curl
+}
+
+/// Run `command` with `input` as its standard input.
+fn run(mut command: Command, input: Option<&[u8]>) -> Result<()> {
+  command.stdout(Stdio::null()).stderr(Stdio::piped());
+  command.stdin(if input.is_some() {
+    Stdio::piped()
+  } else {
+    Stdio::null()
+  });
+  let mut child = command.spawn()?;
+  if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+    // the exit status reports why the input was not read
+    let _ = stdin.write_all(input);
+  }
+  let output = child.wait_with_output()?;
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(anyhow!("{}", stderr.trim_end()));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  const DIGEST: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+  #[test]
+  fn test_parse_ruleset() {
+    let ruleset: RemoteRuleset = format!("https://a.com/r.tar.gz@sha256:{}", DIGEST.to_uppercase())
+      .parse()
+      .expect("should parse");
+    assert_eq!(ruleset.url, "https://a.com/r.tar.gz");
+    assert_eq!(ruleset.digest, DIGEST);
+    assert!("https://a.com/r.tar.gz".parse::<RemoteRuleset>().is_err());
+    assert!("https://a.com/r.tar.gz@sha256:abc"
+      .parse::<RemoteRuleset>()
+      .is_err());
+  }
+
+  #[test]
+  fn test_curl_url_is_not_option() {
+    let curl = curl("-K/etc/passwd", Path::new("out"));
+    let args: Vec<_> = curl.get_args().collect();
+    assert_eq!(args[args.len() - 2..], ["--", "-K/etc/passwd"]);
+  }
+
+  #[test]
+  fn test_fetch_cached() -> Result<()> {
+    let dir = tempfile::TempDir::new()?;
+    fs::create_dir(dir.path().join(DIGEST))?;
+    let ruleset: RemoteRuleset =
+      format!("https://invalid.test/r.tar.gz@sha256:{DIGEST}").parse()?;
+    assert_eq!(ruleset.fetch(dir.path(), &[])?, dir.path().join(DIGEST));
+    Ok(())
+  }
+
+  const KEY: &str = "RWSb/Hxmi63749yYtnZl+v38fXjrQ+Tp/qEENkxyROWc7oRVbryDdHMg";
+  const OTHER_KEY: &str = "RWQ5XSpykpyl7gVYNiYBZ8WcMImyXi82kigi1ZDrBP9CSk1rEX1DyB/9";
+  // signed by KEY
+  const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUSb/Hxmi63740FvRD1X0ThwyM/PmoUbZ08I3uHT12SR2pTOXfZ29+DahtsCcrTPb9QjXCsVlWOu42kEgrbiJNmocrs8hZsZlww=
+trusted comment: timestamp:1760400000\tfile:rule.yml
+6p4AhW4TyxmshT4ulkvu8q/yxfE2Ui8c7tr0sItMheL/4JRKIT6CNuA0ZUKeipZjJQN7pVI1iQmNLYJvAUY1Dw==
+";
+
+  // the content signed by SIGNATURE
+  const SIGNED: &str = "id: no-eval\nlanguage: JavaScript\nrule:\n  pattern: eval($A)\n";
+
+  /// A cache of an archive of `content` with `signature`, and its ruleset.
+  fn signed_cache(content: &str, signature: &str) -> Result<(tempfile::TempDir, RemoteRuleset)> {
+    let dir = tempfile::TempDir::new()?;
+    let digest = hex(&Sha256::digest(content.as_bytes()));
+    fs::create_dir(dir.path().join(&digest))?;
+    fs::write(dir.path().join(format!("{digest}.tar.gz")), content)?;
+    fs::write(dir.path().join(format!("{digest}.minisig")), signature)?;
+    let ruleset = format!("https://invalid.test/r.tar.gz@sha256:{digest}").parse()?;
+    Ok((dir, ruleset))
+  }
+
+  /// A cache of a signed archive of a rule file, not extracted yet, the key signing it and its
+  /// ruleset.
+  fn signed_archive_cache() -> Result<(tempfile::TempDir, PublicKey, RemoteRuleset)> {
+    let dir = tempfile::TempDir::new()?;
+    let rules = dir.path().join("rules");
+    fs::create_dir(&rules)?;
+    fs::write(rules.join("rule.yml"), SIGNED)?;
+    let archive = dir.path().join("r.tar.gz");
+    let mut tar = Command::new("tar");
+    tar.arg("-czf").arg(&archive).arg("-C").arg(&rules).arg(".");
+    run(tar, None)?;
+    let bytes = fs::read(&archive)?;
+    let keys = minisign::KeyPair::generate_unencrypted_keypair()?;
+    let signature = minisign::sign(None, &keys.sk, &bytes[..], None, None)?;
+    let cache = dir.path().join("cache");
+    fs::create_dir(&cache)?;
+    let digest = hex(&Sha256::digest(&bytes));
+    fs::write(cache.join(format!("{digest}.tar.gz")), &bytes)?;
+    fs::write(
+      cache.join(format!("{digest}.minisig")),
+      signature.to_string(),
+    )?;
+    let key = keys.pk.to_box()?.to_string().parse()?;
+    let ruleset = format!("https://invalid.test/r.tar.gz@sha256:{digest}").parse()?;
+    Ok((dir, key, ruleset))
+  }
+
+  #[test]
+  fn test_fetch_cached_signed() -> Result<()> {
+    let (dir, key, ruleset) = signed_archive_cache()?;
+    let cache = dir.path().join("cache");
+    let cached = cache.join(&ruleset.digest);
+    let keys = [key];
+    assert_eq!(ruleset.fetch(&cache, &keys)?, cached);
+    assert_eq!(fs::read_to_string(cached.join("rule.yml"))?, SIGNED);
+    // extracted files changed in the cache are extracted again from the verified archive
+    fs::write(cached.join("rule.yml"), "id: tampered")?;
+    fs::write(cached.join("added.yml"), "id: added")?;
+    assert_eq!(ruleset.fetch(&cache, &keys)?, cached);
+    assert_eq!(fs::read_to_string(cached.join("rule.yml"))?, SIGNED);
+    assert!(!cached.join("added.yml").exists());
+    // signed by a key no longer trusted, so it is fetched again
+    let other: PublicKey = OTHER_KEY.parse()?;
+    assert!(ruleset.fetch(&cache, &[other]).is_err());
+    assert!(!cached.exists());
+    Ok(())
+  }
+
+  #[test]
+  fn test_fetch_cached_forged() -> Result<()> {
+    let keys: [PublicKey; 1] = [KEY.parse()?];
+    // the key id of a trusted key, but the signature of other content
+    let (dir, ruleset) = signed_cache("unsigned rules", SIGNATURE)?;
+    assert!(ruleset.fetch(dir.path(), &keys).is_err());
+    assert!(!dir.path().join(&ruleset.digest).exists());
+    // a signed archive swapped for another one after it was verified
+    let (dir, ruleset) = signed_cache(SIGNED, SIGNATURE)?;
+    fs::write(
+      dir.path().join(format!("{}.tar.gz", ruleset.digest)),
+      "unsigned rules",
+    )?;
+    assert!(ruleset.fetch(dir.path(), &keys).is_err());
+    assert!(!dir.path().join(&ruleset.digest).exists());
+    Ok(())
+  }
+
+  #[test]
+  fn test_fetch_unsigned() -> Result<()> {
+    let dir = tempfile::TempDir::new()?;
+    let archive = dir.path().join("r.tar.gz");
+    fs::write(&archive, "not checked before the signature")?;
+    let digest = hex(&Sha256::digest(fs::read(&archive)?));
+    let url = format!("file://{}", archive.display());
+    let ruleset: RemoteRuleset = format!("{url}@sha256:{digest}").parse()?;
+    let cache = dir.path().join("cache");
+    let keys = [KEY.parse()?];
+    let err = ruleset.fetch(&cache, &keys).unwrap_err();
+    assert!(matches!(
+      err.downcast_ref::<EC>(),
+      Some(EC::UntrustedRuleset(..))
+    ));
+    // a signature of other content
+    fs::write(dir.path().join("r.tar.gz.minisig"), SIGNATURE)?;
+    let err = ruleset.fetch(&cache, &keys).unwrap_err();
+    let Some(EC::UntrustedRuleset(_, reason)) = err.downcast_ref::<EC>() else {
+      panic!("should be untrusted: {err:?}");
+    };
+    assert!(reason.contains("does not match"), "{reason}");
+    assert!(!cache.join(&digest).exists());
+    assert!(!cache.join(format!("{digest}.minisig")).exists());
+    Ok(())
+  }
+}
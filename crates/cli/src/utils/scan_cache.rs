@@ -0,0 +1,178 @@
+use super::ErrorContext as EC;
+use crate::lang::SgLang;
+
+use anyhow::{Context, Result};
+use ast_grep_config::RuleCollection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const CACHE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+  version: u32,
+  /// the hash of the rules the files were scanned with
+  rules: String,
+  /// the keys of the files without findings
+  clean: Vec<String>,
+}
+
+/// The files without findings when they were last scanned with the same rules, to skip
+/// them entirely on repeat runs.
+///
+/// A file is keyed by the hash of its path, language and content. Files with findings
+/// are always scanned again, since reporting needs the matched nodes. The cache keeps only
+/// the clean files of the last run.
+pub struct ScanCache {
+  path: PathBuf,
+  rules_hash: String,
+  clean: HashSet<String>,
+  /// clean files of this run
+  recorded: Mutex<Vec<String>>,
+}
+
+/// `bytes` as lowercase hex, two digits per byte.
+pub fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The SHA-256 digest of `parts` in hex, each part ended by a NUL byte.
+pub fn hex_digest(parts: impl IntoIterator<Item = impl AsRef<[u8]>>) -> String {
+  let mut hasher = Sha256::new();
+  for part in parts {
+    hasher.update(part);
+    hasher.update([0]);
+  }
+  hex(&hasher.finalize())
+}
+
+/// The hash of everything that decides the findings besides the files: the ast-grep version,
+/// the rules after severity overwrites and `extra` inputs like the text of utility rules
+/// and the project config.
+pub fn rules_hash(configs: &RuleCollection<SgLang>, extra: &[String]) -> Result<String> {
+  let mut rules = vec![];
+  let mut error = None;
+  configs.for_each_rule(|rule| match serde_json::to_string(&**rule) {
+    Ok(json) => rules.push(json),
+    Err(e) => error = Some(e),
+  });
+  if let Some(e) = error {
+    return Err(e.into());
+  }
+  rules.sort();
+  let version = env!("CARGO_PKG_VERSION").to_string();
+  Ok(hex_digest(
+    std::iter::once(version)
+      .chain(rules)
+      .chain(extra.iter().cloned()),
+  ))
+}
+
+impl ScanCache {
+  /// Load the cache at `path`. A missing or stale cache is empty.
+  pub fn load(path: PathBuf, rules_hash: String) -> Self {
+    let cached = std::fs::read_to_string(&path)
+      .ok()
+      .and_then(|text| serde_json::from_str::<CacheFile>(&text).ok())
+      .filter(|file| file.version == CACHE_VERSION && file.rules == rules_hash);
+    let clean = cached.map(|file| file.clean.into_iter().collect());
+    Self {
+      path,
+      rules_hash,
+      clean: clean.unwrap_or_default(),
+      recorded: Mutex::new(vec![]),
+    }
+  }
+
+  pub fn key(&self, path: &Path, lang: SgLang, content: &str) -> String {
+    let path = path.to_string_lossy();
+    hex_digest([
+      path.as_bytes(),
+      lang.to_string().as_bytes(),
+      content.as_bytes(),
+    ])
+  }
+
+  /// Whether the file was clean in the last run. A clean file stays in the cache.
+  pub fn is_clean(&self, key: &str) -> bool {
+    let clean = self.clean.contains(key);
+    if clean {
+      self.record(key.to_string());
+    }
+    clean
+  }
+
+  /// Record a file scanned without findings.
+  pub fn record(&self, key: String) {
+    let mut recorded = self.recorded.lock().expect("lock should not be poisoned");
+    recorded.push(key);
+  }
+
+  pub fn save(&self) -> Result<()> {
+    let mut clean =
+      std::mem::take(&mut *self.recorded.lock().expect("lock should not be poisoned"));
+    clean.sort();
+    let file = CacheFile {
+      version: CACHE_VERSION,
+      rules: self.rules_hash.clone(),
+      clean,
+    };
+    let context = || EC::WriteScanCache(self.path.clone());
+    if let Some(dir) = self.path.parent() {
+      std::fs::create_dir_all(dir).with_context(context)?;
+    }
+    let text = serde_json::to_string(&file)?;
+    std::fs::write(&self.path, text).with_context(context)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_config::from_yaml_string;
+  use ast_grep_language::SupportLang;
+
+  fn collection(rule: &str) -> RuleCollection<SgLang> {
+    let rules = from_yaml_string(rule, &Default::default()).expect("should parse");
+    RuleCollection::try_new(rules).expect("should collect")
+  }
+
+  #[test]
+  fn test_rules_hash() -> Result<()> {
+    let rule = "{id: a, language: js, rule: {pattern: foo}}";
+    let hash = rules_hash(&collection(rule), &[])?;
+    assert_eq!(rules_hash(&collection(rule), &[])?, hash);
+    let changed = "{id: a, language: js, rule: {pattern: bar}}";
+    assert_ne!(rules_hash(&collection(changed), &[])?, hash);
+    assert_ne!(rules_hash(&collection(rule), &["utils: {}".into()])?, hash);
+    Ok(())
+  }
+
+  #[test]
+  fn test_scan_cache() -> Result<()> {
+    let dir = tempfile::TempDir::new()?;
+    let path = dir.path().join("cache/scan.json");
+    let lang = SupportLang::JavaScript.into();
+    let cache = ScanCache::load(path.clone(), "rules".into());
+    let clean = cache.key(Path::new("a.js"), lang, "let a = 1");
+    let dirty = cache.key(Path::new("b.js"), lang, "let a = 1");
+    assert_ne!(clean, dirty);
+    assert!(!cache.is_clean(&clean));
+    cache.record(clean.clone());
+    cache.save()?;
+    let cache = ScanCache::load(path.clone(), "rules".into());
+    assert!(cache.is_clean(&clean));
+    assert!(!cache.is_clean(&dirty));
+    assert!(!cache.is_clean(&cache.key(Path::new("a.js"), lang, "let a = 2")));
+    cache.save()?;
+    // the clean file hit in the last run is kept
+    assert!(ScanCache::load(path.clone(), "rules".into()).is_clean(&clean));
+    assert!(!ScanCache::load(path, "other rules".into()).is_clean(&clean));
+    Ok(())
+  }
+}
@@ -0,0 +1,256 @@
+use crate::lang::SgLang;
+
+use ast_grep_core::source::Edit;
+use ast_grep_language::LanguageExt;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::AstGrep;
+
+/// Finds the files that changed by their modification time and size.
+///
+/// File system events only tell which paths were touched. The walk decides which files
+/// are scanned, so it still respects ignore files and `--globs`.
+#[derive(Default)]
+pub struct FileWatcher {
+  files: HashMap<PathBuf, (Option<SystemTime>, u64)>,
+}
+
+impl FileWatcher {
+  /// Record the state of `paths` and return those that are new or changed since the
+  /// last update. Paths that are no longer walked are forgotten.
+  pub fn update(&mut self, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut files = HashMap::with_capacity(paths.len());
+    let mut changed = vec![];
+    for path in paths {
+      let Ok(metadata) = path.metadata() else {
+        continue;
+      };
+      let state = (metadata.modified().ok(), metadata.len());
+      if self.files.get(&path) != Some(&state) {
+        changed.push(path.clone());
+      }
+      files.insert(path, state);
+    }
+    self.files = files;
+    changed
+  }
+
+  /// Whether `path` was found by the last walk.
+  pub fn contains(&self, path: &Path) -> bool {
+    self.files.contains_key(path)
+  }
+
+  /// Record the state of already walked `paths` without walking again, returning those
+  /// that changed. Paths that no longer exist are forgotten.
+  pub fn recheck(&mut self, mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.sort();
+    paths.dedup();
+    let mut changed = vec![];
+    for path in paths {
+      let Ok(metadata) = path.metadata() else {
+        self.files.remove(&path);
+        continue;
+      };
+      let state = (metadata.modified().ok(), metadata.len());
+      if self.files.insert(path.clone(), state) != Some(state) {
+        changed.push(path);
+      }
+    }
+    changed
+  }
+}
+
+/// The most trees kept by an enabled `ParseCache`.
+const CACHE_CAPACITY: usize = 1024;
+
+/// The trees of the files parsed in watch mode, to reparse changed files incrementally.
+///
+/// At most `CACHE_CAPACITY` trees are kept. When the cache is full, the least recently
+/// parsed half is dropped, and those files are parsed from scratch when they change.
+/// The default cache is disabled and parses every file from scratch.
+#[derive(Default)]
+pub struct ParseCache(Option<Mutex<Trees>>);
+
+struct Trees {
+  capacity: usize,
+  /// Counts the parses, to tell which trees were parsed least recently.
+  tick: u64,
+  trees: HashMap<PathBuf, (AstGrep, u64)>,
+}
+
+impl Trees {
+  fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      tick: 0,
+      trees: HashMap::new(),
+    }
+  }
+
+  fn take(&mut self, path: &Path) -> Option<AstGrep> {
+    self.trees.remove(path).map(|(grep, _)| grep)
+  }
+
+  fn insert(&mut self, path: PathBuf, grep: AstGrep) {
+    self.tick += 1;
+    self.trees.insert(path, (grep, self.tick));
+    if self.trees.len() <= self.capacity {
+      return;
+    }
+    let mut ticks: Vec<_> = self.trees.values().map(|(_, tick)| *tick).collect();
+    ticks.sort_unstable();
+    let oldest_kept = ticks[ticks.len() / 2];
+    self.trees.retain(|_, (_, tick)| *tick >= oldest_kept);
+  }
+}
+
+impl ParseCache {
+  pub fn new(enabled: bool) -> Self {
+    Self::with_capacity(enabled, CACHE_CAPACITY)
+  }
+
+  fn with_capacity(enabled: bool, capacity: usize) -> Self {
+    Self(enabled.then(|| Mutex::new(Trees::new(capacity))))
+  }
+
+  pub fn parse(&self, path: &Path, lang: SgLang, src: String) -> AstGrep {
+    let Some(trees) = &self.0 else {
+      return lang.ast_grep(src);
+    };
+    // do not hold the lock while parsing so that files are still parsed in parallel
+    let cached = trees
+      .lock()
+      .expect("lock should not be poisoned")
+      .take(path);
+    let grep = match cached {
+      Some(mut grep) if *grep.lang() == lang => {
+        let edit = diff_edit(grep.source(), &src);
+        if grep.edit(edit).is_ok() {
+          grep
+        } else {
+          lang.ast_grep(src)
+        }
+      }
+      _ => lang.ast_grep(src),
+    };
+    let mut trees = trees.lock().expect("lock should not be poisoned");
+    trees.insert(path.to_path_buf(), grep.clone());
+    grep
+  }
+}
+
+/// The single edit replacing the part of `old` that differs from `new`.
+fn diff_edit(old: &str, new: &str) -> Edit<String> {
+  let mut prefix = old
+    .bytes()
+    .zip(new.bytes())
+    .take_while(|(a, b)| a == b)
+    .count();
+  while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+    prefix -= 1;
+  }
+  let max_suffix = old.len().min(new.len()) - prefix;
+  let mut suffix = old
+    .bytes()
+    .rev()
+    .zip(new.bytes().rev())
+    .take(max_suffix)
+    .take_while(|(a, b)| a == b)
+    .count();
+  while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+    suffix -= 1;
+  }
+  Edit {
+    position: prefix,
+    deleted_length: old.len() - prefix - suffix,
+    inserted_text: new.as_bytes()[prefix..new.len() - suffix].to_vec(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_language::SupportLang;
+
+  fn apply(old: &str, new: &str) -> String {
+    let edit = diff_edit(old, new);
+    let mut text = old.as_bytes().to_vec();
+    let range = edit.position..edit.position + edit.deleted_length;
+    text.splice(range, edit.inserted_text);
+    String::from_utf8(text).expect("edit should keep utf8")
+  }
+
+  #[test]
+  fn test_diff_edit() {
+    let edit = diff_edit("let a = 1;", "let abc = 1;");
+    assert_eq!(edit.position, 5);
+    assert_eq!(edit.deleted_length, 0);
+    assert_eq!(edit.inserted_text, b"bc");
+    for (old, new) in [
+      ("aaa", "aa"),
+      ("aa", "aaa"),
+      ("", "abc"),
+      ("abc", ""),
+      ("a😀b", "a😁b"),
+      ("中文", "中国"),
+      ("same", "same"),
+    ] {
+      assert_eq!(apply(old, new), new);
+    }
+  }
+
+  #[test]
+  fn test_parse_cache() {
+    let lang = SgLang::from(SupportLang::JavaScript);
+    let cache = ParseCache::new(true);
+    let path = Path::new("a.js");
+    cache.parse(path, lang, "let a = 1".into());
+    let grep = cache.parse(path, lang, "let a = foo(1)".into());
+    let root = grep.root();
+    assert_eq!(root.text(), "let a = foo(1)");
+    assert!(root.find("foo($A)").is_some());
+    let disabled = ParseCache::default();
+    let grep = disabled.parse(path, lang, "let b = 2".into());
+    assert_eq!(grep.root().text(), "let b = 2");
+  }
+
+  #[test]
+  fn test_file_watcher() {
+    let dir = tempfile::TempDir::new().expect("should create dir");
+    let path = dir.path().join("a.js");
+    std::fs::write(&path, "a").expect("should write");
+    let mut watcher = FileWatcher::default();
+    assert_eq!(watcher.update(vec![path.clone()]), vec![path.clone()]);
+    assert!(watcher.update(vec![path.clone()]).is_empty());
+    std::fs::write(&path, "ab").expect("should write");
+    assert_eq!(watcher.update(vec![path.clone()]), vec![path.clone()]);
+    assert!(watcher.update(vec![]).is_empty());
+    assert_eq!(watcher.update(vec![path.clone()]), vec![path.clone()]);
+    assert!(watcher.contains(&path));
+    assert!(watcher.recheck(vec![path.clone(), path.clone()]).is_empty());
+    std::fs::write(&path, "abc").expect("should write");
+    assert_eq!(watcher.recheck(vec![path.clone()]), vec![path.clone()]);
+    std::fs::remove_file(&path).expect("should remove");
+    assert!(watcher.recheck(vec![path.clone()]).is_empty());
+    assert!(!watcher.contains(&path));
+  }
+
+  #[test]
+  fn test_parse_cache_capacity() {
+    let lang = SgLang::from(SupportLang::JavaScript);
+    let cache = ParseCache::with_capacity(true, 4);
+    for i in 0..10 {
+      let path = PathBuf::from(format!("{i}.js"));
+      cache.parse(&path, lang, format!("let a = {i}"));
+    }
+    let trees = cache.0.as_ref().expect("should enable");
+    let mut trees = trees.lock().expect("should lock");
+    assert!(trees.trees.len() <= 4);
+    assert!(trees.take(Path::new("9.js")).is_some());
+    assert!(trees.take(Path::new("0.js")).is_none());
+  }
+}
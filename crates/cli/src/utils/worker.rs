@@ -1,13 +1,21 @@
 use crate::print::Printer;
-use crate::utils::FileTrace;
+use crate::utils::{FileTrace, InputArgs};
+
+use super::watch::FileWatcher;
 
 use anyhow::{anyhow, Result};
 use ignore::{DirEntry, WalkParallel, WalkState};
+use notify::{Event, RecursiveMode, Watcher};
 
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// How long `--watch` waits for more file system events before rescanning,
+/// so that saving a file, which can emit several events, rescans only once.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
 
 /// A trait to abstract how ast-grep discovers work Items.
 ///
@@ -32,6 +40,8 @@ pub trait Worker: Sync + Send {
 pub trait PathWorker: Worker {
   /// WalkParallel will determine what files will be processed.
   fn build_walk(&self) -> Result<WalkParallel>;
+  /// The input arguments, for the paths to watch and the number of threads.
+  fn get_input(&self) -> &InputArgs;
   /// Record trace for the worker.
   fn get_trace(&self) -> &FileTrace;
   /// Parse and find_match can be done in `produce_item`.
@@ -53,6 +63,15 @@ pub trait PathWorker: Worker {
   {
     run_worker(Arc::new(self), printer)
   }
+
+  /// Process every file, then every changed file again, until ast-grep is stopped.
+  /// Printing happens in the current thread, so `consume_items` is never called.
+  fn run_watch<P: Printer>(self, printer: P) -> Result<ExitCode>
+  where
+    Self: Sized,
+  {
+    run_watcher(&self, printer)
+  }
 }
 
 pub trait StdInWorker: Worker {
@@ -157,6 +176,119 @@ fn run_worker<W: PathWorker + ?Sized + 'static, P: Printer>(
   worker.consume_items(Items(rx), printer)
 }
 
+fn walk_files(walker: WalkParallel) -> Vec<PathBuf> {
+  let (tx, rx) = mpsc::channel();
+  walker.run(|| {
+    let tx = tx.clone();
+    Box::new(move |result| {
+      if let Some(p) = filter_result(result) {
+        // the receiver lives until the walk is done
+        let _ = tx.send(p);
+      }
+      WalkState::Continue
+    })
+  });
+  drop(tx);
+  let mut paths: Vec<_> = rx.into_iter().collect();
+  paths.sort();
+  paths
+}
+
+/// Process `paths` in parallel, returning the items in the order of `paths`.
+fn produce_items<W: PathWorker + ?Sized, P: Printer>(
+  worker: &W,
+  paths: &[PathBuf],
+  processor: &P::Processor,
+) -> Vec<P::Processed> {
+  let threads = worker.get_input().get_threads();
+  let chunk_size = paths.len().div_ceil(threads).max(1);
+  std::thread::scope(|scope| {
+    let handles: Vec<_> = paths
+      .chunks(chunk_size)
+      .map(|chunk| {
+        scope.spawn(move || {
+          let mut items = vec![];
+          for path in chunk {
+            let stats = worker.get_trace();
+            stats.add_scanned();
+            match worker.produce_item::<P>(path, processor) {
+              Ok(produced) => items.extend(produced),
+              Err(_) => stats.add_skipped(),
+            }
+          }
+          items
+        })
+      })
+      .collect();
+    handles
+      .into_iter()
+      .flat_map(|handle| match handle.join() {
+        Ok(items) => items,
+        Err(panic) => std::panic::resume_unwind(panic),
+      })
+      .collect()
+  })
+}
+
+fn run_watcher<W: PathWorker + ?Sized, P: Printer>(worker: &W, mut printer: P) -> Result<ExitCode> {
+  let processor = printer.get_processor();
+  let (tx, rx) = mpsc::channel();
+  // watch before the first walk so that no change is missed in between
+  let mut notifier = notify::recommended_watcher(tx)?;
+  for path in &worker.get_input().paths {
+    notifier.watch(path, RecursiveMode::Recursive)?;
+  }
+  let mut watcher = FileWatcher::default();
+  printer.before_print()?;
+  let mut changed = watcher.update(walk_files(worker.build_walk()?));
+  loop {
+    for item in produce_items::<W, P>(worker, &changed, &processor) {
+      printer.process(item)?;
+    }
+    eprintln!("Watching for changes. Press Ctrl+C to stop.");
+    changed = vec![];
+    while changed.is_empty() {
+      changed = match recv_changed_paths(&rx)? {
+        // only walk again if a path may be a new file, ignore rules are respected by walking
+        Some(paths) if paths.iter().all(|p| watcher.contains(p)) => watcher.recheck(paths),
+        _ => watcher.update(walk_files(worker.build_walk()?)),
+      };
+    }
+    eprintln!("{} file(s) changed, rescanning...", changed.len());
+  }
+}
+
+/// Wait for file system events and return the paths they touched, collecting every event
+/// until none arrives for `WATCH_DEBOUNCE`. Returns None if all paths must be walked again.
+fn recv_changed_paths(rx: &mpsc::Receiver<notify::Result<Event>>) -> Result<Option<Vec<PathBuf>>> {
+  let mut paths = Some(vec![]);
+  let mut event = rx.recv()?;
+  loop {
+    match event {
+      // reading files to scan them emits access events, skip them or scanning never stops
+      Ok(event) if event.kind.is_access() => (),
+      Ok(event) if event.need_rescan() => paths = None,
+      Ok(event) => {
+        if let Some(paths) = &mut paths {
+          paths.extend(event.paths.into_iter().map(|p| match p.strip_prefix("./") {
+            Ok(p) => p.to_path_buf(),
+            Err(_) => p,
+          }));
+        }
+      }
+      Err(err) => {
+        eprintln!("ERROR: {err}");
+        paths = None;
+      }
+    }
+    event = match rx.recv_timeout(WATCH_DEBOUNCE) {
+      Ok(event) => event,
+      Err(mpsc::RecvTimeoutError::Timeout) => return Ok(paths),
+      Err(err) => return Err(err.into()),
+    };
+  }
+}
+
 pub struct MaxItemCounter(AtomicUsize);
 
 impl MaxItemCounter {
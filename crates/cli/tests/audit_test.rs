@@ -0,0 +1,91 @@
+mod common;
+
+use anyhow::Result;
+use assert_cmd::{cargo_bin, Command};
+use common::create_test_files;
+use predicates::prelude::*;
+use predicates::str::contains;
+use tempfile::TempDir;
+
+const CONFIG: &str = "
+ruleDirs:
+- rules
+";
+const SECURITY_RULE: &str = "
+id: no-danger
+message: danger is used
+severity: warning
+language: Python
+metadata: { category: security }
+rule:
+  pattern: danger($A)
+";
+const STYLE_RULE: &str = "
+id: no-print
+message: print is used
+severity: error
+language: Python
+rule:
+  pattern: print($A)
+";
+
+fn setup() -> Result<TempDir> {
+  let dir = create_test_files([
+    ("sgconfig.yml", CONFIG),
+    ("rules/no-danger.yml", SECURITY_RULE),
+    ("rules/no-print.yml", STYLE_RULE),
+    ("a.js", "eval(code)"),
+    ("b.py", "danger(code)\nprint(1)"),
+  ])?;
+  Ok(dir)
+}
+
+#[test]
+fn test_audit() -> Result<()> {
+  let dir = setup()?;
+  Command::new(cargo_bin!())
+    .current_dir(dir.path())
+    .args(["audit"])
+    .assert()
+    .failure()
+    .code(1)
+    .stdout(contains("HIGH: 1 finding"))
+    .stdout(contains("a.js:1:1 eval: Code is evaluated from a string."))
+    .stdout(contains("b.py:1:1 no-danger: danger is used"))
+    .stdout(contains("no-print").not())
+    .stdout(contains("Findings: 2 in 2 files (1 high, 1 medium, 0 low)"));
+  Ok(())
+}
+
+#[test]
+fn test_audit_project_rules() -> Result<()> {
+  let dir = setup()?;
+  Command::new(cargo_bin!())
+    .current_dir(dir.path())
+    .args(["audit", "--no-builtin"])
+    .assert()
+    .success()
+    .stdout(contains("Rules: 1 (0 builtin, 1 project)"))
+    .stdout(contains("Result: passed"));
+  Command::new(cargo_bin!())
+    .current_dir(dir.path())
+    .args(["audit", "--no-builtin", "--fail-on=medium"])
+    .assert()
+    .failure()
+    .stdout(contains(
+      "Result: failed, 1 finding of medium or higher severity",
+    ));
+  Ok(())
+}
+
+#[test]
+fn test_audit_without_project() -> Result<()> {
+  let dir = create_test_files([("a.js", "const x = 1")])?;
+  Command::new(cargo_bin!())
+    .current_dir(dir.path())
+    .args(["audit"])
+    .assert()
+    .success()
+    .stdout(contains("Findings: 0 in 0 files"));
+  Ok(())
+}
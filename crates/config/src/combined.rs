@@ -1,10 +1,14 @@
 use crate::{RuleConfig, SerializableRule, SerializableRuleConfig, SerializableRuleCore, Severity};
 
+use aho_corasick::AhoCorasick;
 use ast_grep_core::language::Language;
 use ast_grep_core::matcher::{Matcher, MatcherExt};
+use ast_grep_core::source::Content;
 use ast_grep_core::{AstGrep, Doc, Node, NodeMatch};
 
 use std::collections::{HashMap, HashSet};
+use std::ops::{ControlFlow, Range};
+use std::time::Duration;
 
 pub struct ScanResult<'t, 'r, D: Doc, L: Language> {
   pub diffs: Vec<(&'r RuleConfig<L>, NodeMatch<'t, D>)>,
@@ -187,6 +191,162 @@ impl MaySuppressed<'_> {
 
 const IGNORE_TEXT: &str = "ast-grep-ignore";
 
+/// Texts required by the rules, see `RuleCore::required_literals`.
+/// A rule is only tried on nodes containing one of its literals.
+struct Prefilter {
+  searcher: AhoCorasick,
+  /// rule indexes of each literal
+  literal_rules: Vec<Vec<usize>>,
+  /// whether a rule has literals, indexed by rule
+  filtered: Vec<bool>,
+  /// if every rule has literals, subtrees without any literal are skipped
+  all_filtered: bool,
+}
+
+impl Prefilter {
+  fn new<L: Language>(rules: &[&RuleConfig<L>]) -> Option<Self> {
+    let mut literals = vec![];
+    let mut literal_ids = HashMap::new();
+    let mut literal_rules: Vec<Vec<usize>> = vec![];
+    let mut filtered = vec![false; rules.len()];
+    for (idx, rule) in rules.iter().enumerate() {
+      let Some(required) = rule.matcher.required_literals() else {
+        continue;
+      };
+      filtered[idx] = true;
+      for literal in required {
+        let id = *literal_ids.entry(literal).or_insert_with(|| {
+          literals.push(literal);
+          literal_rules.push(vec![]);
+          literals.len() - 1
+        });
+        literal_rules[id].push(idx);
+      }
+    }
+    if !filtered.contains(&true) {
+      return None;
+    }
+    // too many literals to build the searcher, scan without prefilter
+    let searcher = AhoCorasick::new(&literals).ok()?;
+    Some(Self {
+      searcher,
+      literal_rules,
+      all_filtered: !filtered.contains(&false),
+      filtered,
+    })
+  }
+
+  /// Find the literals in the source of `root`.
+  fn search<D: Doc>(&self, root: &Node<'_, D>) -> Occurrences {
+    let text = root.text();
+    let mut found: Vec<_> = self
+      .searcher
+      .find_overlapping_iter(&*text)
+      .map(|m| (m.range(), m.pattern().as_usize()))
+      .collect();
+    found.sort_unstable_by_key(|(range, _)| range.start);
+    let mut occurrences = Occurrences {
+      per_rule: vec![vec![]; self.filtered.len()],
+      all: Vec::with_capacity(found.len()),
+    };
+    // node ranges are in the unit of the doc, not always bytes
+    let (mut byte, mut offset) = (0, root.range().start);
+    for (range, literal) in found {
+      offset += D::Source::decode_str(&text[byte..range.start]).len();
+      byte = range.start;
+      let len = D::Source::decode_str(&text[range]).len();
+      let range = offset..offset + len;
+      for &idx in &self.literal_rules[literal] {
+        occurrences.per_rule[idx].push(range.clone());
+      }
+      occurrences.all.push(range);
+    }
+    occurrences
+  }
+}
+
+/// Where literals of the prefilter occur, sorted by start.
+struct Occurrences {
+  per_rule: Vec<Vec<Range<usize>>>,
+  all: Vec<Range<usize>>,
+}
+
+/// Whether any of the `found` ranges is within `range`.
+fn contains_any(found: &[Range<usize>], range: &Range<usize>) -> bool {
+  let first = found.partition_point(|r| r.start < range.start);
+  found[first..]
+    .iter()
+    .take_while(|r| r.start < range.end)
+    .any(|r| r.end <= range.end)
+}
+
+/// What a rule cost in profiled scans, see `CombinedScan::scan_profiled`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RuleProfile {
+  /// the time spent matching the rule
+  pub time: Duration,
+  /// the visited nodes of the rule's potential kinds
+  pub candidates: usize,
+  /// the candidates skipped since they don't contain a literal the rule requires
+  pub prefiltered: usize,
+  /// the candidates the rule matched, including suppressed matches
+  pub matches: usize,
+}
+
+impl RuleProfile {
+  /// The share of candidates the prefilter skipped, 0 without candidates.
+  pub fn prefilter_hit_rate(&self) -> f64 {
+    if self.candidates == 0 {
+      return 0.0;
+    }
+    self.prefiltered as f64 / self.candidates as f64
+  }
+
+  fn merge(&mut self, other: &Self) {
+    self.time += other.time;
+    self.candidates += other.candidates;
+    self.prefiltered += other.prefiltered;
+    self.matches += other.matches;
+  }
+}
+
+/// Statistics of profiled scans, summed over every scanned file.
+#[derive(Clone, Debug, Default)]
+pub struct ScanProfile {
+  /// the nodes the scans traversed
+  pub visited: usize,
+  /// the traversed nodes in subtrees without a literal any rule requires
+  pub skipped: usize,
+  /// the profile of each rule by id
+  pub rules: HashMap<String, RuleProfile>,
+}
+
+impl ScanProfile {
+  /// Add the statistics of `other`, e.g. of the scans of another thread.
+  pub fn merge(&mut self, other: &Self) {
+    self.visited += other.visited;
+    self.skipped += other.skipped;
+    for (id, rule) in &other.rules {
+      self.rules.entry(id.clone()).or_default().merge(rule);
+    }
+  }
+
+  /// The rules by id, the slowest first.
+  pub fn slowest(&self) -> Vec<(&str, &RuleProfile)> {
+    let mut rules: Vec<_> = self.rules.iter().map(|(id, r)| (&**id, r)).collect();
+    rules.sort_by(|(a_id, a), (b_id, b)| b.time.cmp(&a.time).then(a_id.cmp(b_id)));
+    rules
+  }
+}
+
+/// The profile a scan records to, and the clock it is timed by.
+struct Profiler<'p> {
+  profile: &'p mut ScanProfile,
+  now: &'p dyn Fn() -> Duration,
+  /// the profile of each rule, indexed like `CombinedScan::rules`
+  rules: Vec<RuleProfile>,
+}
+
 /// A struct to group all rules according to their potential kinds.
 /// This can greatly reduce traversal times and skip unmatchable rules.
 /// Rules are referenced by their index in the rules vector.
@@ -196,6 +356,8 @@ pub struct CombinedScan<'r, L: Language> {
   kind_rule_mapping: Vec<Vec<usize>>,
   /// a rule for unused_suppressions
   unused_suppression_rule: Option<&'r RuleConfig<L>>,
+  /// None if no rule requires any text
+  prefilter: Option<Prefilter>,
 }
 
 impl<'r, L: Language> CombinedScan<'r, L> {
@@ -219,10 +381,12 @@ impl<'r, L: Language> CombinedScan<'r, L> {
         mapping[kind].push(idx);
       }
     }
+    let prefilter = Prefilter::new(&rules);
     Self {
       rules,
       kind_rule_mapping: mapping,
       unused_suppression_rule: None,
+      prefilter,
     }
   }
 
@@ -236,6 +400,59 @@ impl<'r, L: Language> CombinedScan<'r, L> {
   pub fn scan<'a, D>(&self, root: &'a AstGrep<D>, separate_fix: bool) -> ScanResult<'a, '_, D, L>
   where
     D: Doc<Lang = L>,
+  {
+    self.scan_with(root, separate_fix, |_, _| ControlFlow::Continue(()))
+  }
+
+  /// Same as `scan`, but calls `visit` on every node before it is matched with the number
+  /// of matches found so far, e.g. to report progress. Nodes are visited in pre-order.
+  /// The scan stops early if `visit` breaks, with the matches found before and no
+  /// unused suppressions.
+  pub fn scan_with<'a, D, F>(
+    &self,
+    root: &'a AstGrep<D>,
+    separate_fix: bool,
+    visit: F,
+  ) -> ScanResult<'a, '_, D, L>
+  where
+    D: Doc<Lang = L>,
+    F: FnMut(&Node<'a, D>, usize) -> ControlFlow<()>,
+  {
+    self.scan_impl(root, separate_fix, visit, None)
+  }
+
+  /// Same as `scan`, but adds what each rule cost to `profile`. `now` is the clock
+  /// matching is timed by, which the caller provides since wasm has no system clock.
+  /// Timing every match slows the scan down, so profiling is opt-in.
+  pub fn scan_profiled<'a, D>(
+    &self,
+    root: &'a AstGrep<D>,
+    separate_fix: bool,
+    profile: &mut ScanProfile,
+    now: &dyn Fn() -> Duration,
+  ) -> ScanResult<'a, '_, D, L>
+  where
+    D: Doc<Lang = L>,
+  {
+    let profiler = Profiler {
+      profile,
+      now,
+      rules: vec![RuleProfile::default(); self.rules.len()],
+    };
+    let visit = |_: &Node<'a, D>, _| ControlFlow::Continue(());
+    self.scan_impl(root, separate_fix, visit, Some(profiler))
+  }
+
+  fn scan_impl<'a, D, F>(
+    &self,
+    root: &'a AstGrep<D>,
+    separate_fix: bool,
+    mut visit: F,
+    mut profiler: Option<Profiler<'_>>,
+  ) -> ScanResult<'a, '_, D, L>
+  where
+    D: Doc<Lang = L>,
+    F: FnMut(&Node<'a, D>, usize) -> ControlFlow<()>,
   {
     let mut result = ScanResultInner {
       diffs: vec![],
@@ -246,18 +463,67 @@ impl<'r, L: Language> CombinedScan<'r, L> {
     let file_sup = suppressions.file_suppression();
     if let MaySuppressed::Yes(s) = file_sup {
       if s.suppressed.is_none() {
+        if let Some(profiler) = profiler {
+          self.record(profiler);
+        }
         return result.into_result(self, separate_fix);
       }
     }
+    let prefilter = self.prefilter.as_ref().map(|p| (p, p.search(&root.root())));
+    // nodes before this offset are in a subtree skipped by the prefilter
+    let mut skip_until = 0;
+    let mut matched = 0;
+    let mut stopped = false;
     for node in root.root().dfs() {
+      if visit(&node, matched).is_break() {
+        stopped = true;
+        break;
+      }
+      let range = node.range();
+      if let Some(profiler) = &mut profiler {
+        profiler.profile.visited += 1;
+      }
+      if range.start < skip_until {
+        if let Some(profiler) = &mut profiler {
+          profiler.profile.skipped += 1;
+        }
+        continue;
+      }
+      if let Some((p, found)) = &prefilter {
+        if p.all_filtered && !contains_any(&found.all, &range) {
+          skip_until = range.end;
+          if let Some(profiler) = &mut profiler {
+            profiler.profile.skipped += 1;
+          }
+          continue;
+        }
+      }
       let kind = node.kind_id() as usize;
       let Some(rule_idx) = self.kind_rule_mapping.get(kind) else {
         continue;
       };
       let line_sup = suppressions.line_suppression(&node);
       for &idx in rule_idx {
+        let stats = profiler.as_mut().map(|p| {
+          p.rules[idx].candidates += 1;
+          (p.now)()
+        });
+        if let Some((p, found)) = &prefilter {
+          if p.filtered[idx] && !contains_any(&found.per_rule[idx], &range) {
+            if let Some(profiler) = &mut profiler {
+              profiler.rules[idx].prefiltered += 1;
+            }
+            continue;
+          }
+        }
         let rule = &self.rules[idx];
-        let Some(ret) = rule.matcher.match_node(node.clone()) else {
+        let ret = rule.matcher.match_node(node.clone());
+        if let (Some(profiler), Some(start)) = (&mut profiler, stats) {
+          let stats = &mut profiler.rules[idx];
+          stats.time += (profiler.now)().saturating_sub(start);
+          stats.matches += usize::from(ret.is_some());
+        }
+        let Some(ret) = ret else {
           continue;
         };
         if let Some(id) = file_sup.suppressed_id(&rule.id) {
@@ -268,6 +534,7 @@ impl<'r, L: Language> CombinedScan<'r, L> {
           suppression_nodes.remove(&id);
           continue;
         }
+        matched += 1;
         if rule.fix.is_none() || !separate_fix {
           let matches = result.matches.entry(idx).or_default();
           matches.push(ret);
@@ -276,13 +543,27 @@ impl<'r, L: Language> CombinedScan<'r, L> {
         }
       }
     }
-    result.unused_suppressions = suppression_nodes
-      .into_values()
-      .map(NodeMatch::from)
-      .collect();
+    if let Some(profiler) = profiler {
+      self.record(profiler);
+    }
+    // suppressions after a stop may suppress nodes that were not visited
+    if !stopped {
+      result.unused_suppressions = suppression_nodes
+        .into_values()
+        .map(NodeMatch::from)
+        .collect();
+    }
     result.into_result(self, separate_fix)
   }
 
+  /// Add the rule profiles of a scan to its profile, by rule id.
+  fn record(&self, profiler: Profiler<'_>) {
+    for (rule, stats) in self.rules.iter().zip(&profiler.rules) {
+      let entry = profiler.profile.rules.entry(rule.id.clone()).or_default();
+      entry.merge(stats);
+    }
+  }
+
   pub fn get_rule(&self, idx: usize) -> &'r RuleConfig<L> {
     self.rules[idx]
   }
@@ -477,4 +758,93 @@ language: Tsx",
       assert_eq!(scanned.len(), 0);
     });
   }
+
+  #[test]
+  fn test_scan_with_visit() {
+    let root = TypeScript::Tsx.ast_grep("console.log(1)");
+    let rule = create_rule();
+    let scan = CombinedScan::new(vec![&rule]);
+    let mut visited = 0;
+    let scanned = scan.scan_with(&root, false, |_, _| {
+      visited += 1;
+      ControlFlow::Continue(())
+    });
+    assert_eq!(scanned.matches.len(), 1);
+    assert_eq!(visited, root.root().dfs().count());
+    // stop at the first node
+    let scanned = scan.scan_with(&root, false, |_, _| ControlFlow::Break(()));
+    assert!(scanned.matches.is_empty());
+  }
+
+  fn rule_with(id: &str, rule: &str) -> RuleConfig<TypeScript> {
+    let src = format!("{{id: {id}, language: Tsx, rule: {rule}}}");
+    let rule: SerializableRuleConfig<TypeScript> = from_str(&src).expect("parse");
+    RuleConfig::try_from(rule, &Default::default()).expect("work")
+  }
+
+  fn scanned_ids(rules: Vec<&RuleConfig<TypeScript>>, source: &str) -> Vec<String> {
+    let root = TypeScript::Tsx.ast_grep(source);
+    let scan = CombinedScan::new(rules);
+    let scanned = scan.scan(&root, false);
+    let mut ids: Vec<_> = scanned
+      .matches
+      .into_iter()
+      .flat_map(|(rule, nms)| {
+        nms
+          .into_iter()
+          .map(|nm| format!("{} {}", rule.id, nm.text()))
+      })
+      .collect();
+    ids.sort();
+    ids
+  }
+
+  #[test]
+  fn test_prefilter() {
+    let log = create_rule();
+    let foo = rule_with("foo", "{any: [{pattern: foo($A)}, {pattern: bar}]}");
+    let source = "function a() { if (x) { console.log(bar) } } let b = () => foo(1)";
+    let ids = scanned_ids(vec![&log, &foo], source);
+    assert_eq!(ids, ["foo bar", "foo foo(1)", "test console.log(bar)"]);
+    // rules without literals still match every node
+    let kind = rule_with("kind", "{kind: number}");
+    let ids = scanned_ids(vec![&log, &kind], "console.log(1); console.warn(2)");
+    assert_eq!(ids, ["kind 1", "kind 2", "test console.log(1)"]);
+    assert!(scanned_ids(vec![&log, &foo], "console.warn(baz)").is_empty());
+  }
+
+  #[test]
+  fn test_prefilter_unused_suppression() {
+    let source = r#"
+    // ast-grep-ignore: test
+    console.warn('no hit')
+    "#;
+    test_scan_unused(source, |scanned| {
+      assert_eq!(scanned.len(), 1);
+      assert_eq!(scanned[0].0.id, "unused-suppression");
+    });
+  }
+
+  #[test]
+  fn test_scan_profiled() {
+    let log = create_rule();
+    let foo = rule_with("foo", "{pattern: foo($A)}");
+    let root = TypeScript::Tsx.ast_grep("console.log(1); foo(2); bar(3)");
+    let scan = CombinedScan::new(vec![&log, &foo]);
+    let mut profile = ScanProfile::default();
+    let scanned = scan.scan_profiled(&root, false, &mut profile, &Duration::default);
+    assert_eq!(scanned.matches.len(), 2);
+    assert_eq!(profile.visited, root.root().dfs().count());
+    // `bar(3)` has no literal of any rule, so its subtree is skipped
+    assert!(profile.skipped > 0);
+    let foo = &profile.rules["foo"];
+    // the other calls are candidates, and only the one with `foo` is matched
+    assert_eq!((foo.candidates, foo.prefiltered, foo.matches), (2, 1, 1));
+    assert_eq!(foo.prefilter_hit_rate(), 0.5);
+    let mut total = profile.clone();
+    total.merge(&profile);
+    assert_eq!(total.rules["test"].matches, 2);
+    assert_eq!(total.visited, 2 * profile.visited);
+    assert_eq!(profile.slowest().len(), 2);
+  }
 }
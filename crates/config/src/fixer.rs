@@ -31,6 +31,28 @@ pub struct SerializableFixConfig {
   expand_start: Maybe<Relation>,
   #[serde(skip_serializing_if = "Option::is_none")]
   title: Option<String>,
+  #[serde(default, skip_serializing_if = "FixIndent::is_default")]
+  indent: FixIndent,
+}
+
+/// How the lines of a multi-line fix are indented.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum FixIndent {
+  /// Indent the lines after the first like the line of the matched node. If that line is
+  /// indented with tabs, so is the template, one tab for each level of its spaces.
+  #[default]
+  Match,
+  /// Like `match`, and always indent the template with tabs.
+  Tabs,
+  /// Insert the lines of the template as written.
+  None,
+}
+
+impl FixIndent {
+  fn is_default(&self) -> bool {
+    *self == Self::Match
+  }
 }
 
 #[derive(Debug, Error)]
@@ -67,9 +89,13 @@ impl Expansion {
 
 pub struct Fixer {
   template: TemplateFix,
+  /// The template indented with tabs, for `FixIndent::Match` on lines indented with tabs.
+  /// `None` if the template has no lines indented with spaces.
+  tab_template: Option<TemplateFix>,
   expand_start: Option<Expansion>,
   expand_end: Option<Expansion>,
   title: Option<String>,
+  indent: FixIndent,
 }
 
 impl Fixer {
@@ -83,20 +109,25 @@ impl Fixer {
       expand_end,
       expand_start,
       title,
+      indent,
     } = serialized;
     let expand_start = Expansion::parse(expand_start, env)?;
     let expand_end = Expansion::parse(expand_end, env)?;
-    let template = if let Some(trans) = transform {
-      let keys: Vec<_> = trans.keys().cloned().collect();
-      TemplateFix::with_transform(fix, &env.lang, &keys)
-    } else {
-      TemplateFix::try_new(fix, &env.lang)?
+    let (template, tab_template) = match indent {
+      FixIndent::Match => parse_templates(fix, env, transform)?,
+      FixIndent::Tabs => (
+        parse_template(&indent_with_tabs(fix), env, transform)?,
+        None,
+      ),
+      FixIndent::None => (parse_template(fix, env, transform)?, None),
     };
     Ok(Self {
       template,
+      tab_template,
       expand_start,
       expand_end,
       title: title.clone(),
+      indent: *indent,
     })
   }
 
@@ -136,27 +167,30 @@ impl Fixer {
     env: &DeserializeEnv<L>,
     transform: &Option<HashMap<String, Transformation>>,
   ) -> Result<Self, FixerError> {
-    let template = if let Some(trans) = transform {
-      let keys: Vec<_> = trans.keys().cloned().collect();
-      TemplateFix::with_transform(fix, &env.lang, &keys)
-    } else {
-      TemplateFix::try_new(fix, &env.lang)?
-    };
+    let (template, tab_template) = parse_templates(fix, env, transform)?;
     Ok(Self {
       template,
+      tab_template,
       expand_end: None,
       expand_start: None,
       title: None,
+      indent: FixIndent::Match,
     })
   }
 
   pub fn from_str<L: Language>(src: &str, lang: &L) -> Result<Self, FixerError> {
     let template = TemplateFix::try_new(src, lang)?;
+    let tabs = indent_with_tabs(src);
+    let tab_template = (tabs != src)
+      .then(|| TemplateFix::try_new(&tabs, lang))
+      .transpose()?;
     Ok(Self {
       template,
+      tab_template,
       expand_start: None,
       expand_end: None,
       title: None,
+      indent: FixIndent::Match,
     })
   }
 
@@ -175,8 +209,12 @@ where
   C: Content,
 {
   fn generate_replacement(&self, nm: &NodeMatch<'_, D>) -> Vec<C::Underlying> {
-    // simple forwarding to template
-    self.template.generate_replacement(nm)
+    match (self.indent, &self.tab_template) {
+      (FixIndent::None, _) => self.template.generate_as_written(nm),
+      // spaces of the template after the tabs of the line would mix the two
+      (FixIndent::Match, Some(tabs)) if indented_with_tabs(nm) => tabs.generate_replacement(nm),
+      _ => self.template.generate_replacement(nm),
+    }
   }
   fn get_replaced_range(&self, nm: &NodeMatch<'_, D>, matcher: impl Matcher) -> Range<usize> {
     let range = nm.range();
@@ -193,6 +231,73 @@ where
   }
 }
 
+fn parse_template<L: Language>(
+  fix: &str,
+  env: &DeserializeEnv<L>,
+  transform: &Option<HashMap<String, Transformation>>,
+) -> Result<TemplateFix, FixerError> {
+  Ok(if let Some(trans) = transform {
+    let keys: Vec<_> = trans.keys().cloned().collect();
+    TemplateFix::with_transform(fix, &env.lang, &keys)
+  } else {
+    TemplateFix::try_new(fix, &env.lang)?
+  })
+}
+
+/// The template of `fix` and, if it differs, the template of `fix` indented with tabs.
+fn parse_templates<L: Language>(
+  fix: &str,
+  env: &DeserializeEnv<L>,
+  transform: &Option<HashMap<String, Transformation>>,
+) -> Result<(TemplateFix, Option<TemplateFix>), FixerError> {
+  let template = parse_template(fix, env, transform)?;
+  let tabs = indent_with_tabs(fix);
+  let tab_template = (tabs != fix)
+    .then(|| parse_template(&tabs, env, transform))
+    .transpose()?;
+  Ok((template, tab_template))
+}
+
+/// Whether the leading whitespace of the line of the matched node has a tab.
+fn indented_with_tabs<D: Doc>(nm: &NodeMatch<'_, D>) -> bool {
+  let decode = |s: &str| D::Source::decode_str(s)[0].clone();
+  let (new_line, space, tab) = (decode("\n"), decode(" "), decode("\t"));
+  let before = nm.get_doc().get_source().get_range(0..nm.range().start);
+  let line_start = before
+    .iter()
+    .rposition(|c| *c == new_line)
+    .map_or(0, |i| i + 1);
+  before[line_start..]
+    .iter()
+    .take_while(|c| **c == space || **c == tab)
+    .any(|c| *c == tab)
+}
+
+/// Replace the leading spaces of the lines after the first with tabs. A level is the smallest
+/// number of leading spaces of those lines, and spaces less than a level are kept.
+fn indent_with_tabs(template: &str) -> String {
+  let leading = |line: &str| line.len() - line.trim_start_matches(' ').len();
+  let level = template
+    .lines()
+    .skip(1)
+    .map(leading)
+    .filter(|n| *n > 0)
+    .min();
+  let Some(level) = level else {
+    return template.to_string();
+  };
+  let mut lines = template.split('\n');
+  let mut ret = lines.next().unwrap_or_default().to_string();
+  for line in lines {
+    let spaces = leading(line);
+    ret.push('\n');
+    ret.push_str(&"\t".repeat(spaces / level));
+    ret.push_str(&" ".repeat(spaces % level));
+    ret.push_str(&line[spaces..]);
+  }
+  ret
+}
+
 fn expand_start<D: Doc>(expansion: Option<&Expansion>, nm: &NodeMatch<'_, D>) -> usize {
   let node = nm.get_node();
   let mut env = std::borrow::Cow::Borrowed(nm.get_env());
@@ -270,6 +375,7 @@ mod test {
       expand_start: Maybe::Absent,
       template: "abcd".to_string(),
       title: None,
+      indent: FixIndent::Match,
     };
     let ret = parse(config)?;
     assert!(ret.expand_start.is_none());
@@ -297,6 +403,7 @@ mod test {
       expand_start: Maybe::Absent,
       template: "var $A = 456".to_string(),
       title: None,
+      indent: FixIndent::Match,
     };
     let fixer = parse(config)?;
     let grep = TypeScript::Tsx.ast_grep("let a = 123");
@@ -315,6 +422,7 @@ mod test {
       expand_start: Maybe::Absent,
       template: "c: 456".to_string(),
       title: None,
+      indent: FixIndent::Match,
     };
     let fixer = parse(config)?;
     let grep = TypeScript::Tsx.ast_grep("var a = { b: 123, }");
@@ -350,4 +458,56 @@ mod test {
     assert!(ret.is_err());
     Ok(())
   }
+
+  fn replace_indented(indent: FixIndent, src: &str) -> String {
+    let config = SerializableFixConfig {
+      expand_end: Maybe::Absent,
+      expand_start: Maybe::Absent,
+      template: "if (a) {\n  $B;\n}".to_string(),
+      title: None,
+      indent,
+    };
+    let fixer = parse(config).expect("should parse");
+    let grep = TypeScript::Tsx.ast_grep(src);
+    let node = grep.root().find("$B;").expect("should found");
+    let edit = fixer.generate_replacement(&node);
+    String::from_utf8_lossy(&edit).to_string()
+  }
+
+  #[test]
+  fn test_fix_indent() {
+    let src = "function f() {\n\tfoo;\n}";
+    // the template is indented with tabs like the line, never with both
+    let ret = replace_indented(FixIndent::Match, src);
+    assert_eq!(ret, "if (a) {\n\t\tfoo;\n\t}");
+    let ret = replace_indented(FixIndent::Tabs, src);
+    assert_eq!(ret, "if (a) {\n\t\tfoo;\n\t}");
+    let ret = replace_indented(FixIndent::None, src);
+    assert_eq!(ret, "if (a) {\n  foo;\n}");
+    let spaces = "function f() {\n    foo;\n}";
+    let ret = replace_indented(FixIndent::Match, spaces);
+    assert_eq!(ret, "if (a) {\n      foo;\n    }");
+    let ret = replace_indented(FixIndent::Tabs, spaces);
+    assert_eq!(ret, "if (a) {\n    \tfoo;\n    }");
+  }
+
+  #[test]
+  fn test_deserialize_indent() {
+    let src = "{template: 'abc', indent: none}";
+    let SerializableFixer::Config(cfg) = from_str(src).expect("should parse") else {
+      panic!("wrong parsing")
+    };
+    assert_eq!(cfg.indent, FixIndent::None);
+    let ret: Result<SerializableFixer, _> = from_str("{template: 'abc', indent: 2}");
+    assert!(ret.is_err());
+  }
+
+  #[test]
+  fn test_indent_with_tabs() {
+    assert_eq!(
+      indent_with_tabs("a\n  b\n    c\n   d"),
+      "a\n\tb\n\t\tc\n\t d"
+    );
+    assert_eq!(indent_with_tabs("a\nb"), "a\nb");
+  }
 }
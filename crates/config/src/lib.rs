@@ -7,6 +7,10 @@ mod rule;
 mod rule_collection;
 mod rule_config;
 mod rule_core;
+mod rule_diagnostic;
+mod sarif;
+mod security;
+mod signature;
 mod transform;
 
 use serde::Deserialize;
@@ -14,15 +18,20 @@ use serde_yaml::{with::singleton_map_recursive::deserialize, Deserializer, Error
 
 use ast_grep_core::language::Language;
 
-pub use combined::CombinedScan;
+pub use combined::{CombinedScan, RuleProfile, ScanProfile, ScanResult};
 pub use fixer::Fixer;
 pub use label::{Label, LabelStyle};
 pub use rule::referent_rule::GlobalRules;
-pub use rule::DeserializeEnv;
+pub use rule::{CustomMatchers, DeserializeEnv};
+pub use rule::{RelationDump, RuleCoreDump, RuleDump, StopByDump, StrictnessStyle};
 pub use rule::{Rule, RuleSerializeError, SerializableRule};
-pub use rule_collection::RuleCollection;
+pub use rule_collection::{RuleCollection, RuleFileGlobs};
 pub use rule_config::{Metadata, RuleConfig, RuleConfigError, SerializableRuleConfig, Severity};
-pub use rule_core::{RuleCore, RuleCoreError, SerializableRuleCore};
+pub use rule_core::{MetaVarUsage, RuleCore, RuleCoreError, SerializableRuleCore};
+pub use rule_diagnostic::{diagnose_rule, GlobalsOf, RuleDiagnostic};
+pub use sarif::{SARIF_COLUMN_KIND, SARIF_SCHEMA, SARIF_VERSION};
+pub use security::{SecurityRuleset, SECURITY_RULESETS};
+pub use signature::{verify_signature, PublicKey, Signature, SignatureError};
 pub use transform::Transformation;
 
 pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T, YamlError> {
@@ -57,6 +66,11 @@ mod test {
     fn kind_to_id(&self, kind: &str) -> u16 {
       TSLanguage::from(tree_sitter_typescript::LANGUAGE_TSX).id_for_node_kind(kind, true)
     }
+    fn kind_names(&self) -> Option<Vec<String>> {
+      Some(ast_grep_core::tree_sitter::kind_names(
+        &self.get_ts_language(),
+      ))
+    }
     fn field_to_id(&self, field: &str) -> Option<u16> {
       TSLanguage::from(tree_sitter_typescript::LANGUAGE_TSX)
         .field_id_for_name(field)
@@ -0,0 +1,96 @@
+use super::Matcher;
+
+use ast_grep_core::{meta_var::MetaVarEnv, Doc, Node};
+
+use bit_set::BitSet;
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+type Predicate<D> = dyn for<'t> Fn(&Node<'t, D>) -> bool + Send + Sync;
+
+/// A predicate of the nodes of one document type. Stored type-erased since rules are not
+/// generic over documents, and only called for nodes of that type.
+struct CustomFn<D: Doc>(Box<Predicate<D>>);
+
+type ErasedFn = Arc<dyn Any + Send + Sync>;
+
+/// Named predicates defined by the host of the rules, e.g. functions of the JS API,
+/// that rules can call with `custom`. They are shared by rules through `GlobalRules`.
+#[derive(Clone, Default)]
+pub struct CustomMatchers(Arc<HashMap<String, ErasedFn>>);
+
+impl CustomMatchers {
+  /// Register `predicate` as `name` for nodes of `D`, replacing the previous one.
+  pub fn insert<D, F>(&mut self, name: &str, predicate: F)
+  where
+    D: Doc,
+    F: for<'t> Fn(&Node<'t, D>) -> bool + Send + Sync + 'static,
+  {
+    let predicate = CustomFn::<D>(Box::new(predicate));
+    Arc::make_mut(&mut self.0).insert(name.to_string(), Arc::new(predicate));
+  }
+
+  pub(crate) fn get(&self, name: &str) -> Option<CustomRule> {
+    let predicate = self.0.get(name)?.clone();
+    Some(CustomRule { predicate })
+  }
+}
+
+/// Matches a node if the custom predicate returns true for it. Nodes of other
+/// document types than the predicate's never match.
+pub struct CustomRule {
+  predicate: ErasedFn,
+}
+
+impl Matcher for CustomRule {
+  fn match_node_with_env<'tree, D: Doc>(
+    &self,
+    node: Node<'tree, D>,
+    _env: &mut Cow<MetaVarEnv<'tree, D>>,
+  ) -> Option<Node<'tree, D>> {
+    let predicate = self.predicate.downcast_ref::<CustomFn<D>>()?;
+    (predicate.0)(&node).then_some(node)
+  }
+
+  fn potential_kinds(&self) -> Option<BitSet> {
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test::TypeScript;
+  use crate::{from_str, DeserializeEnv, GlobalRules};
+  use ast_grep_core::tree_sitter::{LanguageExt, StrDoc};
+
+  fn env(custom: CustomMatchers) -> DeserializeEnv<TypeScript> {
+    let globals = GlobalRules::default().with_custom_matchers(custom);
+    DeserializeEnv::new(TypeScript::Tsx).with_globals(&globals)
+  }
+
+  #[test]
+  fn test_custom_rule() {
+    let mut custom = CustomMatchers::default();
+    custom.insert("isShort", |n: &Node<StrDoc<TypeScript>>| n.text().len() < 3);
+    let rule = from_str("{ kind: identifier, custom: isShort }").unwrap();
+    let rule = env(custom).deserialize_rule(rule).expect("should parse");
+    let grep = TypeScript::Tsx.ast_grep("let ab = abcd");
+    let found: Vec<_> = grep
+      .root()
+      .find_all(&rule)
+      .map(|n| n.text().to_string())
+      .collect();
+    assert_eq!(found, ["ab"]);
+  }
+
+  #[test]
+  fn test_undefined_custom() {
+    let rule = from_str("{ custom: isShort }").unwrap();
+    assert!(env(CustomMatchers::default())
+      .deserialize_rule(rule)
+      .is_err());
+  }
+}
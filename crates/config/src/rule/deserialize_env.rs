@@ -1,7 +1,7 @@
 use super::referent_rule::{GlobalRules, ReferentRuleError, RuleRegistration};
 use crate::check_var::CheckHint;
 use crate::maybe::Maybe;
-use crate::rule::{self, Rule, RuleSerializeError, SerializableRule};
+use crate::rule::{self, CustomMatchers, Rule, RuleSerializeError, SerializableRule};
 use crate::rule_core::{RuleCoreError, SerializableRuleCore};
 use crate::transform::Trans;
 use ast_grep_core::meta_var::MetaVariable;
@@ -174,7 +174,15 @@ impl<L: Language> DeserializeEnv<L> {
   pub fn parse_global_utils(
     utils: Vec<SerializableGlobalRule<L>>,
   ) -> Result<GlobalRules, RuleCoreError> {
-    let registration = GlobalRules::default();
+    Self::parse_global_utils_with(utils, CustomMatchers::default())
+  }
+
+  /// Like `parse_global_utils`, with `custom` for the utils' `custom` rules.
+  pub fn parse_global_utils_with(
+    utils: Vec<SerializableGlobalRule<L>>,
+    custom: CustomMatchers,
+  ) -> Result<GlobalRules, RuleCoreError> {
+    let registration = GlobalRules::default().with_custom_matchers(custom);
     let utils = into_map(utils);
     let order = TopologicalSort::get_order(&utils)
       .map_err(ReferentRuleError::CyclicRule)
@@ -0,0 +1,324 @@
+//! The normalized structure of a rule, for tools visualizing rule logic.
+//!
+//! A rule object with several keys becomes an explicit `all` in the order the keys are
+//! evaluated, `matches` carries the utility rule it refers to, and omitted options are
+//! filled with their defaults.
+
+use super::nth_child::{NthChildSimple, SerializableNthChild};
+use super::range::SerializablePosition;
+use super::relational_rule::Relation;
+use super::stop_by::SerializableStopBy;
+use super::{Categorized, KindStyle, PatternStyle, SerializableRule, Strictness, StrictnessStyle};
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// A relational rule with its defaults filled.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelationDump {
+  pub rule: Box<RuleDump>,
+  pub stop_by: StopByDump,
+  pub field: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum StopByDump {
+  Neighbor,
+  End,
+  Rule(Box<RuleDump>),
+}
+
+/// A rule with exactly one key, tagged by `type`.
+#[derive(Serialize, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RuleDump {
+  #[serde(rename_all = "camelCase")]
+  Pattern {
+    context: String,
+    selector: Option<String>,
+    strictness: StrictnessStyle,
+    parse_as: Option<String>,
+  },
+  Kind {
+    kind: String,
+  },
+  KindRegex {
+    regex: String,
+  },
+  Regex {
+    regex: String,
+  },
+  #[serde(rename_all = "camelCase")]
+  NthChild {
+    position: NthChildSimple,
+    of_rule: Option<Box<RuleDump>>,
+    reverse: bool,
+  },
+  Range {
+    start: SerializablePosition,
+    end: SerializablePosition,
+  },
+  Inside(RelationDump),
+  Has(RelationDump),
+  Precedes(RelationDump),
+  Follows(RelationDump),
+  HasLeadingComment {
+    regex: String,
+  },
+  HasComment {
+    regex: String,
+  },
+  All {
+    rules: Vec<RuleDump>,
+  },
+  Any {
+    rules: Vec<RuleDump>,
+  },
+  Not {
+    rule: Box<RuleDump>,
+  },
+  /// `rule` is the inlined utility rule, unset if it is not a local utility
+  /// or it is already being inlined by an outer `matches`.
+  Matches {
+    id: String,
+    rule: Option<Box<RuleDump>>,
+  },
+  Custom {
+    name: String,
+  },
+}
+
+/// The normalized rule and constraints of a rule core.
+#[derive(Serialize, Clone)]
+pub struct RuleCoreDump {
+  pub rule: RuleDump,
+  pub constraints: BTreeMap<String, RuleDump>,
+}
+
+struct Dumper<'a> {
+  utils: &'a HashMap<String, SerializableRule>,
+  /// utility rules being inlined, to stop at recursive references
+  inlining: Vec<String>,
+}
+
+impl Dumper<'_> {
+  fn dump(&mut self, rule: SerializableRule) -> RuleDump {
+    let Categorized {
+      atomic,
+      relational,
+      composite,
+    } = rule.categorized();
+    // in the order of `deserialize_rule`
+    let mut rules = vec![];
+    if let Some(pattern) = atomic.pattern {
+      rules.push(dump_pattern(pattern));
+    }
+    match atomic.kind {
+      Some(KindStyle::Str(kind)) => rules.push(RuleDump::Kind { kind }),
+      Some(KindStyle::Regex { regex }) => rules.push(RuleDump::KindRegex { regex }),
+      None => (),
+    }
+    if let Some(regex) = atomic.regex {
+      rules.push(RuleDump::Regex { regex });
+    }
+    if let Some(nth_child) = atomic.nth_child {
+      rules.push(self.dump_nth_child(nth_child));
+    }
+    if let Some(range) = atomic.range {
+      rules.push(RuleDump::Range {
+        start: range.start,
+        end: range.end,
+      });
+    }
+    if let Some(all) = composite.all {
+      let all = all.into_iter().map(|r| self.dump(r)).collect();
+      rules.push(RuleDump::All { rules: all });
+    }
+    if let Some(any) = composite.any {
+      let any = any.into_iter().map(|r| self.dump(r)).collect();
+      rules.push(RuleDump::Any { rules: any });
+    }
+    if let Some(not) = composite.not {
+      let rule = Box::new(self.dump(*not));
+      rules.push(RuleDump::Not { rule });
+    }
+    if let Some(id) = composite.matches {
+      rules.push(self.dump_matches(id));
+    }
+    if let Some(name) = composite.custom {
+      rules.push(RuleDump::Custom { name });
+    }
+    if let Some(inside) = relational.inside {
+      rules.push(RuleDump::Inside(self.dump_relation(*inside)));
+    }
+    if let Some(has) = relational.has {
+      rules.push(RuleDump::Has(self.dump_relation(*has)));
+    }
+    if let Some(precedes) = relational.precedes {
+      rules.push(RuleDump::Precedes(self.dump_relation(*precedes)));
+    }
+    if let Some(follows) = relational.follows {
+      rules.push(RuleDump::Follows(self.dump_relation(*follows)));
+    }
+    if let Some(regex) = relational.has_leading_comment {
+      rules.push(RuleDump::HasLeadingComment { regex });
+    }
+    if let Some(regex) = relational.has_comment {
+      rules.push(RuleDump::HasComment { regex });
+    }
+    if rules.len() == 1 {
+      rules.pop().expect("should not be empty")
+    } else {
+      RuleDump::All { rules }
+    }
+  }
+
+  fn dump_nth_child(&mut self, nth_child: SerializableNthChild) -> RuleDump {
+    match nth_child {
+      SerializableNthChild::Simple(position) => RuleDump::NthChild {
+        position,
+        of_rule: None,
+        reverse: false,
+      },
+      SerializableNthChild::Complex {
+        position,
+        of_rule,
+        reverse,
+      } => RuleDump::NthChild {
+        position,
+        of_rule: of_rule.map(|r| Box::new(self.dump(*r))),
+        reverse,
+      },
+    }
+  }
+
+  fn dump_relation(&mut self, relation: Relation) -> RelationDump {
+    let stop_by = match relation.stop_by {
+      SerializableStopBy::Neighbor => StopByDump::Neighbor,
+      SerializableStopBy::End => StopByDump::End,
+      SerializableStopBy::Rule(rule) => StopByDump::Rule(Box::new(self.dump(*rule))),
+    };
+    RelationDump {
+      rule: Box::new(self.dump(relation.rule)),
+      stop_by,
+      field: relation.field,
+    }
+  }
+
+  fn dump_matches(&mut self, id: String) -> RuleDump {
+    let util = self.utils.get(&id).filter(|_| !self.inlining.contains(&id));
+    let rule = util.cloned().map(|util| {
+      self.inlining.push(id.clone());
+      let dumped = self.dump(util);
+      self.inlining.pop();
+      Box::new(dumped)
+    });
+    RuleDump::Matches { id, rule }
+  }
+}
+
+/// Dump `rule` with the local utility rules `utils` inlined.
+pub(crate) fn dump_rule(
+  rule: &SerializableRule,
+  utils: &HashMap<String, SerializableRule>,
+) -> RuleDump {
+  let mut dumper = Dumper {
+    utils,
+    inlining: vec![],
+  };
+  dumper.dump(rule.clone())
+}
+
+fn dump_pattern(pattern: PatternStyle) -> RuleDump {
+  let (context, selector, strictness, parse_as) = match pattern {
+    PatternStyle::Str(context) => (context, None, None, None),
+    PatternStyle::Contextual {
+      context,
+      selector,
+      strictness,
+      parse_as,
+    } => (context, selector, strictness, parse_as),
+  };
+  let strictness = match strictness {
+    None => StrictnessStyle::Level(Strictness::Smart),
+    Some(StrictnessStyle::Custom { base, skip_kinds }) => StrictnessStyle::Custom {
+      base: Some(base.unwrap_or(Strictness::Smart)),
+      skip_kinds,
+    },
+    Some(level) => level,
+  };
+  RuleDump::Pattern {
+    context,
+    selector,
+    strictness,
+    parse_as,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{from_str, SerializableRuleCore};
+
+  fn dump(src: &str) -> RuleCoreDump {
+    let core: SerializableRuleCore = from_str(src).expect("should parse");
+    core.dump()
+  }
+
+  #[test]
+  fn test_dump_implicit_all() {
+    let dumped = dump("{rule: {has: {kind: number}, pattern: foo($A), kind: call_expression}}");
+    let RuleDump::All { rules } = dumped.rule else {
+      panic!("should be all");
+    };
+    assert!(
+      matches!(&rules[0], RuleDump::Pattern { context, selector: None, strictness: StrictnessStyle::Level(Strictness::Smart), parse_as: None } if context == "foo($A)")
+    );
+    assert!(matches!(&rules[1], RuleDump::Kind { kind } if kind == "call_expression"));
+    let RuleDump::Has(has) = &rules[2] else {
+      panic!("should be has");
+    };
+    assert!(matches!(has.stop_by, StopByDump::Neighbor));
+    assert!(matches!(&*has.rule, RuleDump::Kind { .. }));
+    let dumped = dump("{rule: {kind: {regex: _statement$}}}");
+    assert!(matches!(dumped.rule, RuleDump::KindRegex { regex } if regex == "_statement$"));
+  }
+
+  #[test]
+  fn test_dump_inline_utils() {
+    let src = "
+rule: {matches: a}
+constraints: {A: {matches: b}}
+utils:
+  a: {any: [{kind: number}, {matches: a}]}
+  b: {nthChild: 1}
+";
+    let dumped = dump(src);
+    let RuleDump::Matches {
+      id,
+      rule: Some(rule),
+    } = dumped.rule
+    else {
+      panic!("should inline");
+    };
+    assert_eq!(id, "a");
+    let RuleDump::Any { rules } = *rule else {
+      panic!("should be any");
+    };
+    // recursive reference is not inlined again
+    assert!(matches!(&rules[1], RuleDump::Matches { rule: None, .. }));
+    let RuleDump::Matches { rule: Some(b), .. } = &dumped.constraints["A"] else {
+      panic!("should inline");
+    };
+    assert!(matches!(
+      &**b,
+      RuleDump::NthChild {
+        of_rule: None,
+        reverse: false,
+        ..
+      }
+    ));
+  }
+}
@@ -0,0 +1,84 @@
+use super::Matcher;
+
+use ast_grep_core::matcher::{MatcherExt, RegexMatcher, RegexMatcherError};
+use ast_grep_core::{meta_var::MetaVarEnv, Doc, Node};
+
+use bit_set::BitSet;
+use std::borrow::Cow;
+
+/// Matches a node if one of the comments inside it matches the regex, e.g. a `TODO`
+/// in a function body. Patterns skip comments, so this is how rules can look at them.
+pub struct HasComment {
+  regex: RegexMatcher,
+}
+
+impl HasComment {
+  pub fn try_new(regex: &str) -> Result<Self, RegexMatcherError> {
+    Ok(Self {
+      regex: RegexMatcher::try_new(regex)?,
+    })
+  }
+}
+
+impl Matcher for HasComment {
+  fn match_node_with_env<'tree, D: Doc>(
+    &self,
+    node: Node<'tree, D>,
+    _env: &mut Cow<MetaVarEnv<'tree, D>>,
+  ) -> Option<Node<'tree, D>> {
+    let found = node
+      .dfs()
+      .skip(1)
+      .filter(|n| n.kind().contains("comment"))
+      .any(|comment| self.regex.match_node(comment).is_some());
+    found.then_some(node)
+  }
+
+  fn potential_kinds(&self) -> Option<BitSet> {
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test::TypeScript;
+  use ast_grep_core::tree_sitter::LanguageExt;
+
+  fn find_functions(src: &str, regex: &str) -> Vec<String> {
+    let grep = TypeScript::Tsx.ast_grep(src);
+    let matcher = HasComment::try_new(regex).expect("should parse");
+    grep
+      .root()
+      .find_all("function $F() { $$$ }")
+      .filter(|n| matcher.match_node(n.get_node().clone()).is_some())
+      .map(|n| n.get_env().get_match("F").unwrap().text().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_has_comment() {
+    let src = "
+function a() {
+  // TODO: validate
+  run()
+}
+function b() {
+  /* FIXME */ run()
+}
+// TODO: remove
+function c() { run() }
+";
+    assert_eq!(find_functions(src, "TODO"), ["a"]);
+    assert_eq!(find_functions(src, "TODO|FIXME"), ["a", "b"]);
+  }
+
+  #[test]
+  fn test_comment_itself() {
+    let grep = TypeScript::Tsx.ast_grep("// TODO");
+    let matcher = HasComment::try_new("TODO").expect("should parse");
+    let comment = grep.root().child(0).expect("should have comment");
+    assert!(matcher.match_node(comment).is_none());
+    assert!(matcher.match_node(grep.root()).is_some());
+  }
+}
@@ -0,0 +1,159 @@
+use super::Matcher;
+
+use ast_grep_core::matcher::{MatcherExt, RegexMatcher, RegexMatcherError};
+use ast_grep_core::{meta_var::MetaVarEnv, Doc, Node};
+
+use bit_set::BitSet;
+use std::borrow::Cow;
+
+/// Matches a node if one of the comments attached right before it matches the regex.
+/// Attached comments are the consecutive comment siblings preceding the node
+/// without a blank line in between, e.g. a JSDoc block above a function.
+/// A comment after code on its line belongs to that code and is not attached.
+/// The comments of a node wrapped with keywords, e.g. `export function`, precede the wrapper.
+pub struct LeadingComment {
+  regex: RegexMatcher,
+}
+
+impl LeadingComment {
+  pub fn try_new(regex: &str) -> Result<Self, RegexMatcherError> {
+    Ok(Self {
+      regex: RegexMatcher::try_new(regex)?,
+    })
+  }
+
+  fn match_attached<D: Doc>(&self, node: &Node<D>) -> bool {
+    let mut line = node.start_pos().line();
+    let mut prev = node.prev();
+    while let Some(comment) = prev {
+      if !comment.kind().contains("comment")
+        || comment.end_pos().line() + 1 < line
+        || !starts_line(&comment)
+      {
+        return false;
+      }
+      if self.regex.match_node(comment.clone()).is_some() {
+        return true;
+      }
+      line = comment.start_pos().line();
+      prev = comment.prev();
+    }
+    false
+  }
+}
+
+/// Whether no code comes before `node` on its line.
+fn starts_line<D: Doc>(node: &Node<D>) -> bool {
+  let line = node.start_pos().line();
+  let mut current = node.clone();
+  loop {
+    if let Some(prev) = current.prev() {
+      return prev.end_pos().line() < line;
+    }
+    let Some(parent) = current.parent() else {
+      return true;
+    };
+    current = parent;
+  }
+}
+
+/// The parent of `node` if only keywords come before `node` in it, e.g. `export`.
+fn wrapper_of<'t, D: Doc>(node: &Node<'t, D>) -> Option<Node<'t, D>> {
+  let mut prev = node.prev();
+  while let Some(keyword) = prev {
+    let is_keyword = !keyword.is_named() && keyword.kind().chars().all(char::is_alphabetic);
+    if !is_keyword {
+      return None;
+    }
+    prev = keyword.prev();
+  }
+  node.parent()
+}
+
+impl Matcher for LeadingComment {
+  fn match_node_with_env<'tree, D: Doc>(
+    &self,
+    node: Node<'tree, D>,
+    _env: &mut Cow<MetaVarEnv<'tree, D>>,
+  ) -> Option<Node<'tree, D>> {
+    let mut current = node.clone();
+    while !self.match_attached(&current) {
+      current = wrapper_of(&current)?;
+    }
+    Some(node)
+  }
+
+  fn potential_kinds(&self) -> Option<BitSet> {
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test::TypeScript;
+  use ast_grep_core::tree_sitter::LanguageExt;
+
+  fn find_functions(src: &str, regex: &str) -> Vec<String> {
+    let grep = TypeScript::Tsx.ast_grep(src);
+    let matcher = LeadingComment::try_new(regex).expect("should parse");
+    grep
+      .root()
+      .find_all("function $F() {}")
+      .filter(|n| matcher.match_node(n.get_node().clone()).is_some())
+      .map(|n| n.get_env().get_match("F").unwrap().text().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_leading_comment() {
+    let src = "
+/** @deprecated */
+function a() {}
+/** @internal */
+// @deprecated
+function b() {}
+function c() {}
+";
+    assert_eq!(find_functions(src, "@deprecated"), ["a", "b"]);
+    assert_eq!(find_functions(src, "@internal"), ["b"]);
+  }
+
+  #[test]
+  fn test_detached_comment() {
+    let src = "
+// @deprecated
+
+function a() {}
+// @deprecated
+let b = 1
+function c() {}
+";
+    assert!(find_functions(src, "@deprecated").is_empty());
+  }
+
+  #[test]
+  fn test_exported_comment() {
+    let src = "
+/** @deprecated */
+export function a() {}
+/** @deprecated */ export default function b() {}
+function c() {}
+";
+    assert_eq!(find_functions(src, "@deprecated"), ["a", "b"]);
+  }
+
+  #[test]
+  fn test_trailing_comment() {
+    let src = "
+let x = 1; // @deprecated
+function a() {}
+function b() {} /* @deprecated */ function c() {}
+// @internal
+let y = 2; // @deprecated
+function d() {}
+";
+    assert!(find_functions(src, "@deprecated").is_empty());
+    assert!(find_functions(src, "@internal").is_empty());
+  }
+}
@@ -1,4 +1,8 @@
+mod custom_rule;
 mod deserialize_env;
+mod dump;
+mod has_comment;
+mod leading_comment;
 mod nth_child;
 mod range;
 pub mod referent_rule;
@@ -6,28 +10,36 @@ mod relational_rule;
 mod selector;
 mod stop_by;
 
+pub use custom_rule::CustomMatchers;
 pub use deserialize_env::DeserializeEnv;
+pub(crate) use dump::dump_rule;
+pub use dump::{RelationDump, RuleCoreDump, RuleDump, StopByDump};
 pub use relational_rule::Relation;
 use selector::{parse_selector, SelectorError};
 pub use stop_by::StopBy;
 
 use crate::maybe::Maybe;
+use custom_rule::CustomRule;
+use has_comment::HasComment;
+use leading_comment::LeadingComment;
 use nth_child::{NthChild, NthChildError, SerializableNthChild};
 use range::{RangeMatcher, RangeMatcherError, SerializableRange};
 use referent_rule::{ReferentRule, ReferentRuleError};
 use relational_rule::{Follows, Has, Inside, Precedes};
 
 use ast_grep_core::language::Language;
-use ast_grep_core::matcher::{KindMatcher, RegexMatcher, RegexMatcherError};
+use ast_grep_core::matcher::{KindMatcher, KindRegexMatcher, RegexMatcher, RegexMatcherError};
 use ast_grep_core::meta_var::MetaVarEnv;
 use ast_grep_core::{ops as o, Doc, Node};
 use ast_grep_core::{MatchStrictness, Matcher, Pattern, PatternError};
 
 use bit_set::BitSet;
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::HashSet;
+use std::fmt;
 use thiserror::Error;
 
 /// A rule object to find matching AST nodes. We have three categories of rules in ast-grep.
@@ -49,8 +61,9 @@ pub struct SerializableRule {
   #[serde(default, skip_serializing_if = "Maybe::is_absent")]
   pub pattern: Maybe<PatternStyle>,
   /// The kind name of the node to match. You can look up code's kind names in playground.
+  /// Or an object with field `regex` to match every kind whose name matches the regex.
   #[serde(default, skip_serializing_if = "Maybe::is_absent")]
-  pub kind: Maybe<String>,
+  pub kind: Maybe<KindStyle>,
   /// A Rust regular expression to match the node's text. https://docs.rs/regex/latest/regex/#syntax
   #[serde(default, skip_serializing_if = "Maybe::is_absent")]
   pub regex: Maybe<String>,
@@ -80,6 +93,22 @@ pub struct SerializableRule {
   /// the target node must appear after another node matching the `follows` sub-rule.
   #[serde(default, skip_serializing_if = "Maybe::is_absent")]
   pub follows: Maybe<Box<Relation>>,
+  /// A Rust regular expression to match comments attached right before the target node,
+  /// e.g. `@deprecated` in a JSDoc block. Blank lines end the attached comments.
+  #[serde(
+    default,
+    skip_serializing_if = "Maybe::is_absent",
+    rename = "hasLeadingComment"
+  )]
+  pub has_leading_comment: Maybe<String>,
+  /// A Rust regular expression to match comments inside the target node,
+  /// e.g. `TODO` in a function body.
+  #[serde(
+    default,
+    skip_serializing_if = "Maybe::is_absent",
+    rename = "hasComment"
+  )]
+  pub has_comment: Maybe<String>,
   // composite
   /// A list of sub rules and matches a node if all of sub rules match.
   /// The meta variables of the matched node contain all variables from the sub-rules.
@@ -95,6 +124,10 @@ pub struct SerializableRule {
   /// A utility rule id and matches a node if the utility rule matches.
   #[serde(default, skip_serializing_if = "Maybe::is_absent")]
   pub matches: Maybe<String>,
+  /// The name of a predicate registered by the host of the rules, e.g. a JS function,
+  /// and matches a node if the predicate returns true for it.
+  #[serde(default, skip_serializing_if = "Maybe::is_absent")]
+  pub custom: Maybe<String>,
 }
 
 struct Categorized {
@@ -118,12 +151,15 @@ impl SerializableRule {
         has: self.has.into(),
         precedes: self.precedes.into(),
         follows: self.follows.into(),
+        has_leading_comment: self.has_leading_comment.into(),
+        has_comment: self.has_comment.into(),
       },
       composite: CompositeRule {
         all: self.all.into(),
         any: self.any.into(),
         not: self.not.into(),
         matches: self.matches.into(),
+        custom: self.custom.into(),
       },
     }
   }
@@ -131,7 +167,7 @@ impl SerializableRule {
 
 pub struct AtomicRule {
   pub pattern: Option<PatternStyle>,
-  pub kind: Option<String>,
+  pub kind: Option<KindStyle>,
   pub regex: Option<String>,
   pub nth_child: Option<SerializableNthChild>,
   pub range: Option<SerializableRange>,
@@ -183,8 +219,34 @@ impl From<Strictness> for MatchStrictness {
   }
 }
 
+/// A built-in strictness, or one that also skips nodes of the listed kinds.
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(untagged)]
+pub enum StrictnessStyle {
+  Level(Strictness),
+  #[serde(rename_all = "camelCase")]
+  Custom {
+    /// The built-in strictness to extend, `smart` by default.
+    base: Option<Strictness>,
+    /// Kind names of nodes to skip like comments, e.g. `decorator` or `;`.
+    skip_kinds: Vec<String>,
+  },
+}
+
+impl StrictnessStyle {
+  fn apply(self, pattern: Pattern) -> Pattern {
+    match self {
+      StrictnessStyle::Level(level) => pattern.with_strictness(level.into()),
+      StrictnessStyle::Custom { base, skip_kinds } => {
+        let base = base.map_or(MatchStrictness::Smart, Into::into);
+        pattern.with_strictness(base).with_skipped_kinds(skip_kinds)
+      }
+    }
+  }
+}
+
 /// A String pattern will match one single AST node according to pattern syntax.
-/// Or an object with field `context`, `selector` and optionally `strictness`.
+/// Or an object with field `context`, `selector` and optionally `strictness` and `parseAs`.
 #[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(untagged, deny_unknown_fields)]
 pub enum PatternStyle {
@@ -195,15 +257,79 @@ pub enum PatternStyle {
     /// The sub-syntax node kind that is the actual matcher of the pattern.
     selector: Option<String>,
     /// Strictness of the pattern. More strict pattern matches fewer nodes.
-    strictness: Option<Strictness>,
+    strictness: Option<StrictnessStyle>,
+    /// The node kind to parse `context` inside of, like `switch_body` for a `case`.
+    #[serde(default, rename = "parseAs", skip_serializing_if = "Option::is_none")]
+    parse_as: Option<String>,
+  },
+}
+
+/// A String kind will match nodes of that kind, or kinds of a selector like `a > b`.
+/// Or an object with field `regex` matching the kind names, like `_statement$`.
+#[derive(Serialize, Clone, JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum KindStyle {
+  Str(String),
+  Regex {
+    /// A Rust regular expression to match the node's kind name.
+    regex: String,
   },
 }
 
+impl KindStyle {
+  const REGEX_KEY: &str = "regex";
+}
+
+// not derived, an untagged enum reports every mistake as not matching any variant
+struct KindStyleVisitor;
+impl<'de> de::Visitor<'de> for KindStyleVisitor {
+  type Value = KindStyle;
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a kind name or an object with field `regex`")
+  }
+
+  fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+  where
+    E: de::Error,
+  {
+    Ok(KindStyle::Str(value.into()))
+  }
+
+  fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+  where
+    A: de::MapAccess<'de>,
+  {
+    let mut regex = None;
+    while let Some(key) = map.next_key::<String>()? {
+      if key != KindStyle::REGEX_KEY {
+        return Err(de::Error::unknown_field(&key, &[KindStyle::REGEX_KEY]));
+      }
+      if regex.is_some() {
+        return Err(de::Error::duplicate_field(KindStyle::REGEX_KEY));
+      }
+      regex = Some(map.next_value()?);
+    }
+    let regex = regex.ok_or_else(|| de::Error::missing_field(KindStyle::REGEX_KEY))?;
+    Ok(KindStyle::Regex { regex })
+  }
+}
+
+impl<'de> Deserialize<'de> for KindStyle {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    deserializer.deserialize_any(KindStyleVisitor)
+  }
+}
+
 pub struct RelationalRule {
   pub inside: Option<Box<Relation>>,
   pub has: Option<Box<Relation>>,
   pub precedes: Option<Box<Relation>>,
   pub follows: Option<Box<Relation>>,
+  pub has_leading_comment: Option<String>,
+  pub has_comment: Option<String>,
 }
 
 pub struct CompositeRule {
@@ -211,12 +337,14 @@ pub struct CompositeRule {
   pub any: Option<Vec<SerializableRule>>,
   pub not: Option<Box<SerializableRule>>,
   pub matches: Option<String>,
+  pub custom: Option<String>,
 }
 
 pub enum Rule {
   // atomic
   Pattern(Pattern),
   Kind(KindMatcher),
+  KindRegex(KindRegexMatcher),
   Regex(RegexMatcher),
   NthChild(NthChild),
   Range(RangeMatcher),
@@ -225,11 +353,14 @@ pub enum Rule {
   Has(Box<Has>),
   Precedes(Box<Precedes>),
   Follows(Box<Follows>),
+  LeadingComment(LeadingComment),
+  HasComment(HasComment),
   // composite
   All(o::All<Rule>),
   Any(o::Any<Rule>),
   Not(Box<o::Not<Rule>>),
   Matches(ReferentRule),
+  Custom(CustomRule),
 }
 impl Rule {
   /// Check if it has a cyclic referent rule with the id.
@@ -247,6 +378,7 @@ impl Rule {
     match self {
       Rule::Pattern(p) => p.defined_vars(),
       Rule::Kind(_) => HashSet::new(),
+      Rule::KindRegex(_) => HashSet::new(),
       Rule::Regex(_) => HashSet::new(),
       Rule::NthChild(n) => n.defined_vars(),
       Rule::Range(_) => HashSet::new(),
@@ -254,11 +386,49 @@ impl Rule {
       Rule::Inside(p) => p.defined_vars(),
       Rule::Precedes(f) => f.defined_vars(),
       Rule::Follows(f) => f.defined_vars(),
+      Rule::LeadingComment(_) => HashSet::new(),
+      Rule::HasComment(_) => HashSet::new(),
       Rule::All(sub) => sub.inner().iter().flat_map(|r| r.defined_vars()).collect(),
       Rule::Any(sub) => sub.inner().iter().flat_map(|r| r.defined_vars()).collect(),
       Rule::Not(sub) => sub.inner().defined_vars(),
       // TODO: this is not correct, we are collecting util vars else where
       Rule::Matches(_r) => HashSet::new(),
+      Rule::Custom(_) => HashSet::new(),
+    }
+  }
+
+  /// Texts of which every matched node contains at least one, see `Pattern::required_literal`.
+  /// `None` if the rule can match a node without any known text.
+  pub fn required_literals(&self) -> Option<Vec<&str>> {
+    match self {
+      Rule::Pattern(p) => p.required_literal().map(|l| vec![l]),
+      Rule::Has(c) => c.required_literals(),
+      // any sub rule is required, prefer few and long literals
+      Rule::All(sub) => sub
+        .inner()
+        .iter()
+        .filter_map(|r| r.required_literals())
+        .min_by_key(|lits| (lits.len(), Reverse(lits.iter().map(|l| l.len()).min()))),
+      Rule::Any(sub) => {
+        let mut lits = vec![];
+        for rule in sub.inner() {
+          lits.extend(rule.required_literals()?);
+        }
+        Some(lits)
+      }
+      Rule::Kind(_)
+      | Rule::KindRegex(_)
+      | Rule::Regex(_)
+      | Rule::NthChild(_)
+      | Rule::Range(_)
+      | Rule::Inside(_)
+      | Rule::Precedes(_)
+      | Rule::Follows(_)
+      | Rule::LeadingComment(_)
+      | Rule::HasComment(_)
+      | Rule::Not(_)
+      | Rule::Matches(_)
+      | Rule::Custom(_) => None,
     }
   }
 
@@ -267,6 +437,7 @@ impl Rule {
     match self {
       Rule::Pattern(_) => Ok(()),
       Rule::Kind(_) => Ok(()),
+      Rule::KindRegex(_) => Ok(()),
       Rule::Regex(_) => Ok(()),
       Rule::NthChild(n) => n.verify_util(),
       Rule::Range(_) => Ok(()),
@@ -274,10 +445,13 @@ impl Rule {
       Rule::Inside(p) => p.verify_util(),
       Rule::Precedes(f) => f.verify_util(),
       Rule::Follows(f) => f.verify_util(),
+      Rule::LeadingComment(_) => Ok(()),
+      Rule::HasComment(_) => Ok(()),
       Rule::All(sub) => sub.inner().iter().try_for_each(|r| r.verify_util()),
       Rule::Any(sub) => sub.inner().iter().try_for_each(|r| r.verify_util()),
       Rule::Not(sub) => sub.inner().verify_util(),
       Rule::Matches(r) => Ok(r.verify_util()?),
+      Rule::Custom(_) => Ok(()),
     }
   }
 }
@@ -293,6 +467,7 @@ impl Matcher for Rule {
       // atomic
       Pattern(pattern) => pattern.match_node_with_env(node, env),
       Kind(kind) => kind.match_node_with_env(node, env),
+      KindRegex(kind) => kind.match_node_with_env(node, env),
       Regex(regex) => regex.match_node_with_env(node, env),
       NthChild(nth_child) => nth_child.match_node_with_env(node, env),
       Range(range) => range.match_node_with_env(node, env),
@@ -301,11 +476,14 @@ impl Matcher for Rule {
       Has(child) => match_and_add_label(&**child, node, env),
       Precedes(latter) => match_and_add_label(&**latter, node, env),
       Follows(former) => match_and_add_label(&**former, node, env),
+      LeadingComment(comment) => comment.match_node_with_env(node, env),
+      HasComment(comment) => comment.match_node_with_env(node, env),
       // composite
       All(all) => all.match_node_with_env(node, env),
       Any(any) => any.match_node_with_env(node, env),
       Not(not) => not.match_node_with_env(node, env),
       Matches(rule) => rule.match_node_with_env(node, env),
+      Custom(custom) => custom.match_node_with_env(node, env),
     }
   }
 
@@ -315,6 +493,7 @@ impl Matcher for Rule {
       // atomic
       Pattern(pattern) => pattern.potential_kinds(),
       Kind(kind) => kind.potential_kinds(),
+      KindRegex(kind) => kind.potential_kinds(),
       Regex(regex) => regex.potential_kinds(),
       NthChild(nth_child) => nth_child.potential_kinds(),
       Range(range) => range.potential_kinds(),
@@ -323,11 +502,14 @@ impl Matcher for Rule {
       Has(child) => child.potential_kinds(),
       Precedes(latter) => latter.potential_kinds(),
       Follows(former) => former.potential_kinds(),
+      LeadingComment(comment) => comment.potential_kinds(),
+      HasComment(comment) => comment.potential_kinds(),
       // composite
       All(all) => all.potential_kinds(),
       Any(any) => any.potential_kinds(),
       Not(not) => not.potential_kinds(),
       Matches(rule) => rule.potential_kinds(),
+      Custom(custom) => custom.potential_kinds(),
     }
   }
 }
@@ -370,6 +552,10 @@ pub enum RuleSerializeError {
   FieldNotSupported,
   #[error("Relational rule contains invalid field {0}.")]
   InvalidField(String),
+  #[error("Custom matcher `{0}` is not registered.")]
+  UndefinedCustom(String),
+  #[error("Rule can never match because its rules require different kinds.")]
+  NoCommonKind,
 }
 
 // TODO: implement positive/non positive
@@ -378,7 +564,6 @@ pub fn deserialize_rule<L: Language>(
   env: &DeserializeEnv<L>,
 ) -> Result<Rule, RuleSerializeError> {
   let mut rules = Vec::with_capacity(1);
-  use Rule as R;
   let categorized = serialized.categorized();
   // ATTENTION, relational_rule should always come at last
   // after target node is decided by atomic/composite rule
@@ -391,8 +576,17 @@ pub fn deserialize_rule<L: Language>(
   } else if rules.len() == 1 {
     Ok(rules.pop().expect("should not be empty"))
   } else {
-    Ok(R::All(o::All::new(rules)))
+    all_of(rules)
+  }
+}
+
+/// Rules of one node matching together, which cannot require kinds that no node has.
+fn all_of(rules: Vec<Rule>) -> Result<Rule, RuleSerializeError> {
+  let all = o::All::new(rules);
+  if all.potential_kinds().is_some_and(|kinds| kinds.is_empty()) {
+    return Err(RuleSerializeError::NoCommonKind);
   }
+  Ok(Rule::All(all))
 }
 
 fn deserialze_composite_rule<L: Language>(
@@ -409,7 +603,7 @@ fn deserialze_composite_rule<L: Language>(
     Ok(inner)
   };
   if let Some(all) = composite.all {
-    rules.push(R::All(o::All::new(convert_rules(all)?)));
+    rules.push(all_of(convert_rules(all)?)?);
   }
   if let Some(any) = composite.any {
     rules.push(R::Any(o::Any::new(convert_rules(any)?)));
@@ -422,6 +616,12 @@ fn deserialze_composite_rule<L: Language>(
     let matches = ReferentRule::try_new(id, &env.registration)?;
     rules.push(R::Matches(matches));
   }
+  if let Some(name) = composite.custom {
+    let custom = env.registration.get_custom(&name);
+    rules.push(R::Custom(
+      custom.ok_or(RuleSerializeError::UndefinedCustom(name))?,
+    ));
+  }
   Ok(())
 }
 
@@ -444,6 +644,12 @@ fn deserialize_relational_rule<L: Language>(
   if let Some(follows) = relational.follows {
     rules.push(R::Follows(Box::new(Follows::try_new(*follows, env)?)));
   }
+  if let Some(regex) = relational.has_leading_comment {
+    rules.push(R::LeadingComment(LeadingComment::try_new(&regex)?));
+  }
+  if let Some(regex) = relational.has_comment {
+    rules.push(R::HasComment(HasComment::try_new(&regex)?));
+  }
   Ok(())
 }
 
@@ -460,14 +666,16 @@ fn deserialze_atomic_rule<L: Language>(
         context,
         selector,
         strictness,
+        parse_as,
       } => {
-        let pattern = if let Some(selector) = selector {
-          Pattern::contextual(&context, &selector, env.lang.clone())?
-        } else {
-          Pattern::try_new(&context, env.lang.clone())?
+        let lang = env.lang.clone();
+        let pattern = match (parse_as, selector) {
+          (Some(kind), selector) => Pattern::parse_as(&context, &kind, selector.as_deref(), lang)?,
+          (None, Some(selector)) => Pattern::contextual(&context, &selector, lang)?,
+          (None, None) => Pattern::try_new(&context, lang)?,
         };
         let pattern = if let Some(strictness) = strictness {
-          pattern.with_strictness(strictness.into())
+          strictness.apply(pattern)
         } else {
           pattern
         };
@@ -475,9 +683,13 @@ fn deserialze_atomic_rule<L: Language>(
       }
     });
   }
-  if let Some(kind) = atomic.kind {
-    let rule = parse_selector(&kind, env.lang.clone())?;
-    rules.push(rule);
+  match atomic.kind {
+    Some(KindStyle::Str(kind)) => rules.push(parse_selector(&kind, env.lang.clone())?),
+    Some(KindStyle::Regex { regex }) => rules.push(R::KindRegex(KindRegexMatcher::try_new(
+      &regex,
+      env.lang.clone(),
+    )?)),
+    None => (),
   }
   if let Some(regex) = atomic.regex {
     rules.push(R::Regex(RegexMatcher::try_new(&regex)?));
@@ -613,6 +825,62 @@ kind: class_declaration
     assert!(root.root().find(rule).is_some());
   }
 
+  #[test]
+  fn test_deserialize_kind_regex() {
+    let env = DeserializeEnv::new(TypeScript::Tsx);
+    let deserialize = |src: &str| {
+      let rule: SerializableRule = from_str(src).expect("cannot parse rule");
+      deserialize_rule(rule, &env)
+    };
+    let rule = deserialize("kind: { regex: _statement$ }").expect("should deserialize");
+    let root = TypeScript::Tsx.ast_grep("if (a) { return 1 }");
+    let kinds: Vec<_> = root
+      .root()
+      .find_all(&rule)
+      .map(|n| n.kind().to_string())
+      .collect();
+    assert_eq!(kinds, ["if_statement", "return_statement"]);
+    assert!(rule.potential_kinds().is_some());
+    assert!(deserialize("{kind: { regex: ^if }, pattern: 'if (a) {}'}").is_ok());
+    let ret = deserialize("kind: { regex: '(' }");
+    assert!(matches!(ret, Err(RuleSerializeError::WrongRegex(_))));
+    let err = |src: &str| from_str::<SerializableRule>(src).err().unwrap().to_string();
+    assert!(err("kind: { pattern: a }").contains("unknown field `pattern`, expected `regex`"));
+    assert!(err("kind: {}").contains("missing field `regex`"));
+    assert!(err("kind: [a]").contains("a kind name or an object with field `regex`"));
+  }
+
+  #[test]
+  fn test_deserialize_skip_kinds() {
+    let src = r"
+pattern:
+  context: foo($A)
+  strictness:
+    base: relaxed
+    skipKinds: [',']
+";
+    let rule: SerializableRule = from_str(src).expect("cannot parse rule");
+    let env = DeserializeEnv::new(TypeScript::Tsx);
+    let rule = deserialize_rule(rule, &env).expect("should deserialize");
+    let root = TypeScript::Tsx.ast_grep("foo(/* c */ 1,)");
+    assert!(root.root().find(rule).is_some());
+  }
+
+  #[test]
+  fn test_no_common_kind() {
+    let env = DeserializeEnv::new(TypeScript::Tsx);
+    let deserialize = |src: &str| {
+      let rule: SerializableRule = from_str(src).expect("cannot parse rule");
+      deserialize_rule(rule, &env)
+    };
+    let ret = deserialize("all: [{kind: class_declaration}, {kind: identifier}]");
+    assert!(matches!(ret, Err(RuleSerializeError::NoCommonKind)));
+    let ret = deserialize("{kind: identifier, pattern: 'class A {}'}");
+    assert!(matches!(ret, Err(RuleSerializeError::NoCommonKind)));
+    assert!(deserialize("all: [{kind: identifier}, {pattern: $A}]").is_ok());
+    assert!(deserialize("any: [{kind: class_declaration}, {kind: identifier}]").is_ok());
+  }
+
   #[test]
   fn test_deserialize_order() {
     let src = r"
@@ -639,6 +907,24 @@ inside:
     assert_eq!(rule.defined_vars(), ["A", "B"].into_iter().collect());
   }
 
+  #[test]
+  fn test_required_literals() {
+    let literals = |src: &str| {
+      let rule: SerializableRule = from_str(src).expect("cannot parse rule");
+      let env = DeserializeEnv::new(TypeScript::Tsx);
+      let rule = deserialize_rule(rule, &env).expect("should deserialize");
+      rule.required_literals().map(|l| l.join(","))
+    };
+    let src = "{pattern: var $A = 123, inside: {pattern: var $B = 456}}";
+    assert_eq!(literals(src).as_deref(), Some("123"));
+    let src = "{any: [{pattern: foo($A)}, {has: {pattern: bar}}]}";
+    assert_eq!(literals(src).as_deref(), Some("foo,bar"));
+    let src = "{any: [{pattern: foo($A)}, {kind: identifier}]}";
+    assert_eq!(literals(src), None);
+    let src = "{all: [{kind: call_expression}, {not: {pattern: foo}}]}";
+    assert_eq!(literals(src), None);
+  }
+
   #[test]
   fn test_issue_1164() {
     let src = r"
@@ -1,3 +1,4 @@
+use super::custom_rule::{CustomMatchers, CustomRule};
 use crate::{Rule, RuleCore};
 
 use ast_grep_core::meta_var::MetaVarEnv;
@@ -26,10 +27,26 @@ impl<R> Registration<R> {
     unsafe { &mut *(Arc::as_ptr(&self.0) as *mut HashMap<String, R>) }
   }
 }
-pub type GlobalRules = Registration<RuleCore>;
+/// Utility rules shared by all RuleConfigs, with the predicates of `custom` rules.
+#[derive(Clone, Default)]
+pub struct GlobalRules {
+  rules: Registration<RuleCore>,
+  custom: CustomMatchers,
+}
 
 impl GlobalRules {
   pub fn insert(&self, id: &str, rule: RuleCore) -> Result<(), ReferentRuleError> {
+    self.rules.insert(id, rule)
+  }
+
+  /// Make `custom` available to the `custom` rules of RuleConfigs using these globals.
+  pub fn with_custom_matchers(self, custom: CustomMatchers) -> Self {
+    Self { custom, ..self }
+  }
+}
+
+impl Registration<RuleCore> {
+  fn insert(&self, id: &str, rule: RuleCore) -> Result<(), ReferentRuleError> {
     let map = self.write();
     if map.contains_key(id) {
       return Err(ReferentRuleError::DuplicateRule(id.into()));
@@ -59,6 +76,8 @@ pub struct RuleRegistration {
   global: Registration<RuleCore>,
   /// Every RuleConfig has its own rewriters. But sub-rules share parent's rewriters.
   rewriters: Registration<RuleCore>,
+  /// predicates of `custom` rules, shared like global rules.
+  custom: CustomMatchers,
 }
 
 // these are shit code
@@ -70,11 +89,16 @@ impl RuleRegistration {
   pub fn from_globals(global: &GlobalRules) -> Self {
     Self {
       local: Default::default(),
-      global: global.clone(),
+      global: global.rules.clone(),
       rewriters: Default::default(),
+      custom: global.custom.clone(),
     }
   }
 
+  pub(crate) fn get_custom(&self, name: &str) -> Option<CustomRule> {
+    self.custom.get(name)
+  }
+
   fn get_ref(&self) -> RegistrationRef {
     let local = Arc::downgrade(&self.local.0);
     let global = Arc::downgrade(&self.global.0);
@@ -140,6 +140,11 @@ impl Has {
     self.inner.verify_util()?;
     self.stop_by.verify_util()
   }
+
+  /// The inner rule matches a descendant, so the node contains its literals.
+  pub fn required_literals(&self) -> Option<Vec<&str>> {
+    self.inner.required_literals()
+  }
 }
 
 impl Matcher for Has {
@@ -24,8 +24,7 @@ impl<L: Language> RuleBucket<L> {
 
 struct ContingentRule<L: Language> {
   rule: RuleConfig<L>,
-  files_globs: Option<GlobSet>,
-  ignore_globs: Option<GlobSet>,
+  globs: RuleFileGlobs,
 }
 
 fn build_glob_set(configs: &Vec<RuleFileGlob>) -> Result<GlobSet, globset::Error> {
@@ -45,23 +44,23 @@ fn build_glob_set(configs: &Vec<RuleFileGlob>) -> Result<GlobSet, globset::Error
   builder.build()
 }
 
-impl<L> TryFrom<RuleConfig<L>> for ContingentRule<L>
-where
-  L: Language,
-{
-  type Error = globset::Error;
-  fn try_from(rule: RuleConfig<L>) -> Result<Self, Self::Error> {
+/// The compiled `files` and `ignores` globs of a rule.
+pub struct RuleFileGlobs {
+  files_globs: Option<GlobSet>,
+  ignore_globs: Option<GlobSet>,
+}
+
+impl RuleFileGlobs {
+  pub fn try_new<L: Language>(rule: &RuleConfig<L>) -> Result<Self, globset::Error> {
     let files_globs = rule.files.as_ref().map(build_glob_set).transpose()?;
     let ignore_globs = rule.ignores.as_ref().map(build_glob_set).transpose()?;
     Ok(Self {
-      rule,
       files_globs,
       ignore_globs,
     })
   }
-}
 
-impl<L: Language> ContingentRule<L> {
+  /// Whether the rule applies to the file at `path`, relative to the project directory.
   pub fn matches_path<P: AsRef<Path>>(&self, path: P) -> bool {
     if let Some(ignore_globs) = &self.ignore_globs {
       if ignore_globs.is_match(&path) {
@@ -75,6 +74,23 @@ impl<L: Language> ContingentRule<L> {
   }
 }
 
+impl<L> TryFrom<RuleConfig<L>> for ContingentRule<L>
+where
+  L: Language,
+{
+  type Error = globset::Error;
+  fn try_from(rule: RuleConfig<L>) -> Result<Self, Self::Error> {
+    let globs = RuleFileGlobs::try_new(&rule)?;
+    Ok(Self { rule, globs })
+  }
+}
+
+impl<L: Language> ContingentRule<L> {
+  pub fn matches_path<P: AsRef<Path>>(&self, path: P) -> bool {
+    self.globs.matches_path(path)
+  }
+}
+
 /// A collection of rules to run one round of scanning.
 /// Rules will be grouped together based on their language, path globbing and pattern rule.
 pub struct RuleCollection<L: Language + Eq> {
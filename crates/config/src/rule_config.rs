@@ -21,7 +21,7 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
 
-#[derive(Serialize, Deserialize, Clone, Default, JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, Clone, Default, JsonSchema, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum Severity {
   #[default]
@@ -117,6 +117,12 @@ pub enum RuleFileGlob {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Metadata(HashMap<String, serde_yaml::Value>);
 
+impl Metadata {
+  pub fn get(&self, key: &str) -> Option<&serde_yaml::Value> {
+    self.0.get(key)
+  }
+}
+
 impl JsonSchema for Metadata {
   fn schema_name() -> Cow<'static, str> {
     Cow::Borrowed("Metadata")
@@ -145,6 +151,45 @@ impl<L: Language> SerializableRuleConfig<L> {
     Ok(rule)
   }
 
+  /// Returns meta variables referenced by the `fix` or `message` template but
+  /// never defined by the rule, paired with the template's field name.
+  /// Unlike `get_matcher`, undefined variables in `fix` are reported instead of rejected.
+  pub fn undefined_template_vars(
+    &self,
+    globals: &GlobalRules,
+  ) -> Result<Vec<(&'static str, Vec<String>)>, RuleConfigError> {
+    let mut core = self.core.clone();
+    let fix = core.fix.take();
+    let env = DeserializeEnv::new(self.language.clone()).with_globals(globals);
+    let rule = core.get_matcher(env)?;
+    let env = rule.get_env(self.language.clone());
+    let defined = rule.defined_vars();
+    let mut fixers = vec![];
+    if let Some(fix) = &fix {
+      let parsed = Fixer::parse(fix, &env, &self.transform).map_err(RuleCoreError::Fixer)?;
+      fixers.push(("fix", parsed));
+    }
+    let message =
+      Fixer::with_transform(&self.message, &env, &self.transform).map_err(RuleCoreError::Fixer)?;
+    fixers.push(("message", vec![message]));
+    let mut ret = vec![];
+    for (field, fixers) in fixers {
+      let mut undefined: Vec<_> = fixers
+        .iter()
+        .flat_map(|f| f.used_vars())
+        .filter(|v| !defined.contains(v))
+        .map(String::from)
+        .collect();
+      if undefined.is_empty() {
+        continue;
+      }
+      undefined.sort();
+      undefined.dedup();
+      ret.push((field, undefined));
+    }
+    Ok(ret)
+  }
+
   fn check_labels(&self, rule: &RuleCore) -> Result<(), RuleConfigError> {
     let Some(labels) = &self.labels else {
       return Ok(());
@@ -606,6 +651,70 @@ rewriters:
     assert_eq!(String::from_utf8_lossy(b), "1919810");
   }
 
+  #[test]
+  fn test_join_transform_in_fix() {
+    let rule: SerializableRuleConfig<TypeScript> = from_str(
+      r"
+id: test
+rule: {pattern: 'foo($$$ARGS)'}
+language: Tsx
+transform:
+  JOINED: { join: { source: $$$ARGS, joinBy: ', ' } }
+fix: 'bar([$JOINED])'
+    ",
+    )
+    .expect("should parse");
+    let rule = RuleConfig::try_from(rule, &Default::default()).expect("work");
+    let fixer = rule.get_fixer().unwrap().remove(0);
+    let grep = TypeScript::Tsx.ast_grep("foo(a,b,   c)");
+    let nm = grep.root().find(&rule.matcher).unwrap();
+    let replacement = fixer.generate_replacement(&nm);
+    assert_eq!(String::from_utf8_lossy(&replacement), "bar([a, b, c])");
+    let grep = TypeScript::Tsx.ast_grep("foo()");
+    let nm = grep.root().find(&rule.matcher).unwrap();
+    let replacement = fixer.generate_replacement(&nm);
+    assert_eq!(String::from_utf8_lossy(&replacement), "bar([])");
+  }
+
+  #[test]
+  fn test_undefined_template_vars() {
+    let rule: SerializableRuleConfig<TypeScript> = from_str(
+      r"
+id: test
+rule: {pattern: 'foo($A)'}
+language: Tsx
+message: $A is called with $MSG
+fix: 'bar($B, $A, $B, $C)'
+    ",
+    )
+    .expect("should parse");
+    let undefined = rule.undefined_template_vars(&Default::default()).unwrap();
+    assert_eq!(undefined.len(), 2);
+    assert_eq!(undefined[0].0, "fix");
+    assert_eq!(undefined[0].1, ["B", "C"]);
+    assert_eq!(undefined[1].0, "message");
+    assert_eq!(undefined[1].1, ["MSG"]);
+    assert!(RuleConfig::try_from(rule, &Default::default()).is_err());
+  }
+
+  #[test]
+  fn test_defined_template_vars() {
+    let rule: SerializableRuleConfig<TypeScript> = from_str(
+      r"
+id: test
+rule: {pattern: 'foo($A)'}
+language: Tsx
+transform:
+  B: { substring: { source: $A } }
+message: $A
+fix: 'bar($B)'
+    ",
+    )
+    .expect("should parse");
+    let undefined = rule.undefined_template_vars(&Default::default()).unwrap();
+    assert!(undefined.is_empty());
+  }
+
   fn make_undefined_error(src: &str) -> String {
     let rule: SerializableRuleConfig<TypeScript> = from_str(src).expect("should parse");
     let err = RuleConfig::try_from(rule, &Default::default());
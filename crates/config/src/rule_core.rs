@@ -2,7 +2,7 @@ use crate::check_var::{check_rule_with_hint, CheckHint};
 use crate::fixer::{Fixer, FixerError, SerializableFixer};
 use crate::rule::referent_rule::RuleRegistration;
 use crate::rule::Rule;
-use crate::rule::{RuleSerializeError, SerializableRule};
+use crate::rule::{dump_rule, RuleCoreDump, RuleSerializeError, SerializableRule};
 use crate::transform::{Transform, TransformError, Transformation};
 use crate::DeserializeEnv;
 
@@ -17,7 +17,7 @@ use schemars::JsonSchema;
 use thiserror::Error;
 
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Deref;
 
 #[derive(Debug, Error)]
@@ -40,6 +40,22 @@ pub enum RuleCoreError {
 
 type RResult<T> = std::result::Result<T, RuleCoreError>;
 
+/// Where a meta variable is defined and referenced in a rule, see `meta_var_usage`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetaVarUsage {
+  pub name: String,
+  /// captured by the rule, its utils or constraints
+  pub captured: bool,
+  /// defined as a key of `transform`
+  pub transformed: bool,
+  /// filtered by `constraints`
+  pub in_constraints: bool,
+  /// the source of a transformation
+  pub in_transform: bool,
+  /// referenced by `fix`
+  pub in_fix: bool,
+}
+
 /// Used for global rules, rewriters, and pyo3/napi
 #[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct SerializableRuleCore {
@@ -118,6 +134,63 @@ impl SerializableRuleCore {
     self.get_matcher_with_hint(env, CheckHint::Normal)
   }
 
+  /// The normalized rule and constraints with local utility rules inlined, see `RuleDump`.
+  /// The rule is not validated, call `get_matcher` for that.
+  pub fn dump(&self) -> RuleCoreDump {
+    let no_utils = HashMap::new();
+    let utils = self.utils.as_ref().unwrap_or(&no_utils);
+    let constraints = self.constraints.iter().flatten();
+    RuleCoreDump {
+      rule: dump_rule(&self.rule, utils),
+      constraints: constraints
+        .map(|(var, rule)| (var.clone(), dump_rule(rule, utils)))
+        .collect(),
+    }
+  }
+
+  /// The meta variables of the rule sorted by name, for editors that build rules.
+  /// Unlike `get_matcher`, meta variables used in `fix` but never defined are listed
+  /// with `captured` and `transformed` unset, rather than rejected.
+  pub fn meta_var_usage<L: Language>(&self, env: DeserializeEnv<L>) -> RResult<Vec<MetaVarUsage>> {
+    let lang = env.lang.clone();
+    let no_fix = Self {
+      fix: None,
+      ..self.clone()
+    };
+    let core = no_fix.get_matcher(env)?;
+    let fixer = self.get_fixer(&core.get_env(lang))?;
+    let mut usage = BTreeMap::new();
+    fn entry<'a>(
+      usage: &'a mut BTreeMap<String, MetaVarUsage>,
+      name: &str,
+    ) -> &'a mut MetaVarUsage {
+      usage
+        .entry(name.to_string())
+        .or_insert_with_key(|name| MetaVarUsage {
+          name: name.clone(),
+          ..Default::default()
+        })
+    }
+    for var in core.defined_node_vars() {
+      entry(&mut usage, var).captured = true;
+    }
+    for var in core.constraints.keys() {
+      entry(&mut usage, var).in_constraints = true;
+    }
+    if let Some(trans) = &core.transform {
+      for key in trans.keys() {
+        entry(&mut usage, key).transformed = true;
+      }
+      for var in trans.values().map(|t| t.used_vars()) {
+        entry(&mut usage, var).in_transform = true;
+      }
+    }
+    for var in fixer.iter().flat_map(Fixer::used_vars) {
+      entry(&mut usage, var).in_fix = true;
+    }
+    Ok(usage.into_values().collect())
+  }
+
   pub(crate) fn get_matcher_with_hint<L: Language>(
     &self,
     env: DeserializeEnv<L>,
@@ -215,6 +288,11 @@ impl RuleCore {
     ret
   }
 
+  /// Texts of which every matched node contains at least one, see `Rule::required_literals`.
+  pub fn required_literals(&self) -> Option<Vec<&str>> {
+    self.rule.required_literals()
+  }
+
   pub(crate) fn do_match<'tree, D: Doc>(
     &self,
     node: Node<'tree, D>,
@@ -323,6 +401,36 @@ utils: { testa: {kind: bbb} }
     }
   }
 
+  #[test]
+  fn test_meta_var_usage() {
+    let env = DeserializeEnv::new(TypeScript::Tsx);
+    let rule: SerializableRuleCore = from_str(
+      r"
+rule: { pattern: $A($$$ARGS) }
+constraints: { A: { regex: ^log } }
+transform: { B: { substring: { source: $A, startChar: 1 } } }
+fix: $B($$$ARGS, $C)",
+    )
+    .expect("should deser");
+    let usage = rule.meta_var_usage(env).expect("should work");
+    let names: Vec<_> = usage.iter().map(|u| u.name.as_str()).collect();
+    assert_eq!(names, ["A", "ARGS", "B", "C"]);
+    let a = &usage[0];
+    assert!(a.captured && a.in_constraints && a.in_transform && !a.in_fix);
+    let args = &usage[1];
+    assert!(args.captured && args.in_fix && !args.in_constraints);
+    let b = &usage[2];
+    assert!(!b.captured && b.transformed && b.in_fix);
+    assert_eq!(
+      usage[3],
+      MetaVarUsage {
+        name: "C".into(),
+        in_fix: true,
+        ..Default::default()
+      }
+    );
+  }
+
   #[test]
   fn test_cyclic_transform_error() {
     let ret = get_matcher(
@@ -0,0 +1,651 @@
+//! Rule errors located at the field causing them, for editors and the CLI to point at the
+//! offending YAML instead of failing with the error of the whole rule.
+//!
+//! A failing rule is checked field by field, each field added to the fields that passed
+//! before it, and failing composite, relational and utility rules are checked the same way
+//! down to the innermost failing rules. The span of a field is found by walking the YAML
+//! events, since `serde_yaml` locates neither values nor the errors of flattened fields.
+
+use crate::{GlobalRules, RuleConfig, RuleConfigError, RuleCoreError, SerializableRuleConfig};
+
+use ast_grep_core::Language;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_yaml::with::singleton_map_recursive;
+use serde_yaml::{Mapping, Value};
+
+use std::cell::Cell;
+use std::fmt;
+use std::ops::Range;
+
+/// One step of a YAML path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+  Key(String),
+  Index(usize),
+}
+
+/// A path like `rule.all[1].kind`, as `serde_yaml` prints them.
+fn render(path: &[Segment]) -> String {
+  let mut ret = String::new();
+  for segment in path {
+    match segment {
+      Segment::Key(key) if ret.is_empty() => ret.push_str(key),
+      Segment::Key(key) => {
+        ret.push('.');
+        ret.push_str(key);
+      }
+      Segment::Index(i) => ret.push_str(&format!("[{i}]")),
+    }
+  }
+  ret
+}
+
+/// An error of a rule located at the field causing it.
+#[derive(Debug)]
+pub struct RuleDiagnostic {
+  /// the YAML path of the field, empty for errors of the whole rule
+  pub path: String,
+  /// the byte span of the field's key and value
+  pub span: Range<usize>,
+  pub error: RuleConfigError,
+}
+
+/// The utility rules available to the rules of a language.
+pub type GlobalsOf<'a, L> = &'a dyn Fn(&L) -> GlobalRules;
+
+type Located = (Vec<Segment>, RuleConfigError);
+
+/// Parse and check the rule in `yaml`, locating every error at the field causing it.
+pub fn diagnose_rule<L: Language + DeserializeOwned>(
+  yaml: &str,
+  globals: GlobalsOf<L>,
+) -> Result<SerializableRuleConfig<L>, Vec<RuleDiagnostic>> {
+  let value = match parse_yaml(yaml) {
+    Ok(value) => value,
+    Err(e) => {
+      // syntax errors are located by the parser
+      let offset = e.location().map_or(0, |l| l.index());
+      return Err(vec![RuleDiagnostic {
+        path: String::new(),
+        span: offset..offset,
+        error: e.into(),
+      }]);
+    }
+  };
+  let error = match check(&value, false, globals) {
+    Ok(config) => return Ok(config),
+    Err(e) => e,
+  };
+  let located = match &value {
+    Value::Mapping(config) => locate_errors(config, globals),
+    _ => vec![],
+  };
+  let diagnostics = or_here(located, vec![], error)
+    .into_iter()
+    .map(|(path, error)| {
+      let (start, end) = span(yaml, &path);
+      RuleDiagnostic {
+        path: render(&path),
+        span: start..end,
+        error,
+      }
+    })
+    .collect();
+  Err(diagnostics)
+}
+
+fn parse_yaml(yaml: &str) -> Result<Value, serde_yaml::Error> {
+  let mut value: Value = serde_yaml::from_str(yaml)?;
+  value.apply_merge()?;
+  Ok(value)
+}
+
+/// Check a rule like `deserialize_rules`. A lenient check passes errors caused by fields
+/// replaced or left out to check others alone: missing kinds and undefined meta variables.
+fn check<L: Language + DeserializeOwned>(
+  value: &Value,
+  lenient: bool,
+  globals: GlobalsOf<L>,
+) -> Result<SerializableRuleConfig<L>, RuleConfigError> {
+  let config: SerializableRuleConfig<L> = singleton_map_recursive::deserialize(value.clone())?;
+  let globals = globals(&config.language);
+  match RuleConfig::try_from(config.clone(), &globals) {
+    Ok(_) => Ok(config),
+    Err(e) if lenient && is_incomplete(&e) => Ok(config),
+    Err(e) => Err(e),
+  }
+}
+
+fn is_incomplete(e: &RuleConfigError) -> bool {
+  matches!(
+    e,
+    RuleConfigError::MissingPotentialKinds
+      | RuleConfigError::LabelVariable(_)
+      | RuleConfigError::Core(RuleCoreError::UndefinedMetaVar(..))
+  )
+}
+
+/// A rule valid in every language, standing in for rules not checked.
+fn placeholder() -> Value {
+  mapping([("regex", Value::from(".*"))])
+}
+
+fn mapping<'a>(entries: impl IntoIterator<Item = (&'a str, Value)>) -> Value {
+  let map: Mapping = entries
+    .into_iter()
+    .map(|(k, v)| (Value::from(k), v))
+    .collect();
+  Value::Mapping(map)
+}
+
+fn child(path: &[Segment], segment: Segment) -> Vec<Segment> {
+  let mut path = path.to_vec();
+  path.push(segment);
+  path
+}
+
+/// The errors found inside a field, or the field's own error if there is none.
+fn or_here(found: Vec<Located>, path: Vec<Segment>, error: RuleConfigError) -> Vec<Located> {
+  if found.is_empty() {
+    vec![(path, error)]
+  } else {
+    found
+  }
+}
+
+/// A check of a candidate value at one location of the rule, returning the error error.
+type Probe<'a> = &'a dyn Fn(Value) -> Option<RuleConfigError>;
+
+fn locate_errors<L: Language + DeserializeOwned>(
+  config: &Mapping,
+  globals: GlobalsOf<L>,
+) -> Vec<Located> {
+  let Some(language) = config.get("language") else {
+    return vec![];
+  };
+  let mut base = Mapping::new();
+  base.insert("rule".into(), placeholder());
+  // other fields cannot be checked without the language
+  base.insert("language".into(), language.clone());
+  if let Some(error) = failure(&base, true, globals) {
+    return vec![(vec![Segment::Key("language".into())], error)];
+  }
+  // utils before rule, and rule before the fields using its meta variables
+  let mut keys: Vec<&str> = vec!["utils", "rule"];
+  keys.extend(config.keys().filter_map(|k| k.as_str()));
+  let mut checked = vec!["language"];
+  let mut lenient = true;
+  let mut errors = vec![];
+  for key in keys {
+    if checked.contains(&key) {
+      continue;
+    }
+    checked.push(key);
+    let Some(value) = config.get(key) else {
+      continue;
+    };
+    let with = |candidate: Value| {
+      let mut config = base.clone();
+      config.insert(key.into(), candidate);
+      config
+    };
+    // the rule is checked strictly, with only the utils it may use
+    let Some(error) = failure(&with(value.clone()), lenient && key != "rule", globals) else {
+      base = with(value.clone());
+      lenient &= key != "rule";
+      continue;
+    };
+    let probe = |candidate| failure(&with(candidate), true, globals);
+    let path = vec![Segment::Key(key.into())];
+    let found = match key {
+      "rule" => descend_rule(&path, value, &probe, false),
+      "utils" | "constraints" => descend_rule_map(&path, value, &probe),
+      _ => descend_value(&path, value, &probe),
+    };
+    errors.extend(or_here(found, path, error));
+  }
+  errors
+}
+
+fn failure<L: Language + DeserializeOwned>(
+  config: &Mapping,
+  lenient: bool,
+  globals: GlobalsOf<L>,
+) -> Option<RuleConfigError> {
+  let value = Value::Mapping(config.clone());
+  check(&value, lenient, globals).err()
+}
+
+/// Check each field of a rule object alone, descending into the failing ones.
+/// A relational rule's own fields, `stopBy` and `field`, are checked with a placeholder rule.
+fn descend_rule(path: &[Segment], rule: &Value, probe: Probe, relation: bool) -> Vec<Located> {
+  let Value::Mapping(map) = rule else {
+    return vec![];
+  };
+  let mut errors = vec![];
+  for (key, value) in map {
+    let Some(key) = key.as_str() else {
+      continue;
+    };
+    let is_own = relation && matches!(key, "stopBy" | "field");
+    let alone = |candidate: Value| {
+      if is_own {
+        probe(mapping([("regex", Value::from(".*")), (key, candidate)]))
+      } else {
+        probe(mapping([(key, candidate)]))
+      }
+    };
+    let Some(error) = alone(value.clone()) else {
+      continue;
+    };
+    let path = child(path, Segment::Key(key.into()));
+    let found = match key {
+      "all" | "any" => descend_rule_seq(&path, value, &alone),
+      "not" => descend_rule(&path, value, &alone, false),
+      "inside" | "has" | "precedes" | "follows" => descend_rule(&path, value, &alone, true),
+      "stopBy" if is_own => descend_rule(&path, value, &alone, false),
+      _ => vec![],
+    };
+    errors.extend(or_here(found, path, error));
+  }
+  errors
+}
+
+/// Check each rule of `all` or `any` with placeholders for the others.
+fn descend_rule_seq(path: &[Segment], rules: &Value, probe: Probe) -> Vec<Located> {
+  let Value::Sequence(rules) = rules else {
+    return vec![];
+  };
+  let mut errors = vec![];
+  for (i, rule) in rules.iter().enumerate() {
+    let at = |candidate: Value| {
+      let mut rules = vec![placeholder(); rules.len()];
+      rules[i] = candidate;
+      probe(Value::Sequence(rules))
+    };
+    if let Some(error) = at(rule.clone()) {
+      let path = child(path, Segment::Index(i));
+      errors.extend(or_here(descend_rule(&path, rule, &at, false), path, error));
+    }
+  }
+  errors
+}
+
+/// Check each rule of `utils` or `constraints` with placeholders for the others, keeping
+/// their names for `matches` and meta variables.
+fn descend_rule_map(path: &[Segment], rules: &Value, probe: Probe) -> Vec<Located> {
+  let Value::Mapping(rules) = rules else {
+    return vec![];
+  };
+  let mut errors = vec![];
+  for (name, rule) in rules {
+    let Some(key) = name.as_str() else {
+      continue;
+    };
+    let at = |candidate: Value| {
+      let mut rules = rules.clone();
+      for (other, value) in rules.iter_mut() {
+        *value = if other == name {
+          candidate.clone()
+        } else {
+          placeholder()
+        };
+      }
+      probe(Value::Mapping(rules))
+    };
+    if let Some(error) = at(rule.clone()) {
+      let path = child(path, Segment::Key(key.into()));
+      errors.extend(or_here(descend_rule(&path, rule, &at, false), path, error));
+    }
+  }
+  errors
+}
+
+/// Check each entry of a mapping or item of a sequence alone, like those of `transform`.
+fn descend_value(path: &[Segment], value: &Value, probe: Probe) -> Vec<Located> {
+  let mut errors = vec![];
+  match value {
+    Value::Mapping(map) => {
+      for (key, value) in map {
+        let Some(key) = key.as_str() else {
+          continue;
+        };
+        if let Some(error) = probe(mapping([(key, value.clone())])) {
+          errors.push((child(path, Segment::Key(key.into())), error));
+        }
+      }
+    }
+    Value::Sequence(items) => {
+      for (i, item) in items.iter().enumerate() {
+        if let Some(error) = probe(Value::Sequence(vec![item.clone()])) {
+          errors.push((child(path, Segment::Index(i)), error));
+        }
+      }
+    }
+    _ => (),
+  }
+  errors
+}
+
+/// The byte span of the node at `path`, from its key, or of its closest ancestor in the
+/// YAML text. Fields from merge keys are not in the text.
+fn span(yaml: &str, path: &[Segment]) -> (usize, usize) {
+  for len in (1..=path.len()).rev() {
+    let path = &path[..len];
+    if let Some(start) = walk(yaml, path, false) {
+      let end = walk(yaml, path, true).unwrap_or(yaml.len());
+      return (start, trim_end(yaml, start, end));
+    }
+  }
+  let start = yaml.len() - yaml.trim_start().len();
+  (start, trim_end(yaml, start, yaml.len()))
+}
+
+/// The end of a node from the start of the node after it, before the space and the
+/// separators in between.
+fn trim_end(yaml: &str, start: usize, end: usize) -> usize {
+  let mut text = yaml[start..end].trim_end();
+  loop {
+    let trimmed = match text.chars().last() {
+      // the dash of the next sequence item or the comma of the next flow item
+      Some('-') if text[..text.len() - 1].ends_with(char::is_whitespace) => &text[..text.len() - 1],
+      Some(',') => &text[..text.len() - 1],
+      // the end of the flow collection the node is in
+      Some(close @ ('}' | ']')) => {
+        let open = if close == '}' { '{' } else { '[' };
+        if text.matches(close).count() <= text.matches(open).count() {
+          break;
+        }
+        &text[..text.len() - 1]
+      }
+      _ => break,
+    };
+    text = trimmed.trim_end();
+  }
+  start + text.len()
+}
+
+/// Marks the node where walking stops in the messages of errors.
+const FOUND: &str = "the located node";
+
+/// Walk the YAML events to the node at `target`. Walking to its start stops at its key or
+/// at the item, walking to its end stops at the next node after it.
+fn walk(yaml: &str, target: &[Segment], to_end: bool) -> Option<usize> {
+  let walker = Walker {
+    to_end,
+    done: Cell::new(false),
+  };
+  let seed = NodeSeed {
+    walker: &walker,
+    path: Some(target),
+  };
+  let e = seed
+    .deserialize(serde_yaml::Deserializer::from_str(yaml))
+    .err()?;
+  if !e.to_string().contains(FOUND) {
+    return None;
+  }
+  e.location().map(|l| l.index())
+}
+
+struct Walker {
+  to_end: bool,
+  /// whether the target has been walked past
+  done: Cell<bool>,
+}
+
+impl Walker {
+  fn stop<E: de::Error>(&self) -> E {
+    E::custom(FOUND)
+  }
+}
+
+/// Fails at any node, so that the error is located at it.
+struct Stop;
+
+impl Visitor<'_> for Stop {
+  type Value = ();
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(FOUND)
+  }
+}
+
+/// A node, on the way to the target if `path` is the rest of the way.
+struct NodeSeed<'a> {
+  walker: &'a Walker,
+  path: Option<&'a [Segment]>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_> {
+  type Value = ();
+  fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+    let walker = self.walker;
+    if walker.done.get() {
+      return d.deserialize_any(Stop);
+    }
+    match self.path {
+      Some([]) if walker.to_end => {
+        IgnoredAny::deserialize(d)?;
+        walker.done.set(true);
+        Ok(())
+      }
+      // the root or an item, keys stop the walk before their values
+      Some([]) => d.deserialize_any(Stop),
+      path => d.deserialize_any(NodeVisitor { walker, path }),
+    }
+  }
+}
+
+struct NodeVisitor<'a> {
+  walker: &'a Walker,
+  path: Option<&'a [Segment]>,
+}
+
+impl<'de, 'a> Visitor<'de> for NodeVisitor<'a> {
+  type Value = ();
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("any YAML node")
+  }
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+    let key_seed = || KeySeed {
+      walker: self.walker,
+      path: self.path,
+    };
+    while let Some(path) = map.next_key_seed(key_seed())? {
+      map.next_value_seed(NodeSeed {
+        walker: self.walker,
+        path,
+      })?;
+    }
+    Ok(())
+  }
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+    let mut i = 0;
+    loop {
+      let path = match self.path {
+        Some([Segment::Index(index), rest @ ..]) if *index == i => Some(rest),
+        _ => None,
+      };
+      let seed = NodeSeed {
+        walker: self.walker,
+        path,
+      };
+      if seq.next_element_seed(seed)?.is_none() {
+        return Ok(());
+      }
+      i += 1;
+    }
+  }
+  fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> {
+    Ok(())
+  }
+  fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> {
+    Ok(())
+  }
+  fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> {
+    Ok(())
+  }
+  fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> {
+    Ok(())
+  }
+  fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+    Ok(())
+  }
+  fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+    Ok(())
+  }
+  fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+    d.deserialize_any(self)
+  }
+  fn visit_none<E: de::Error>(self) -> Result<(), E> {
+    Ok(())
+  }
+}
+
+/// A key of a mapping on the way, returning the rest of the way for its value.
+struct KeySeed<'a> {
+  walker: &'a Walker,
+  path: Option<&'a [Segment]>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for KeySeed<'a> {
+  type Value = Option<&'a [Segment]>;
+  fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+    if self.walker.done.get() {
+      return d.deserialize_any(Stop).map(|_| None);
+    }
+    d.deserialize_any(self)
+  }
+}
+
+impl<'de, 'a> Visitor<'de> for KeySeed<'a> {
+  type Value = Option<&'a [Segment]>;
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a mapping key")
+  }
+  fn visit_str<E: de::Error>(self, key: &str) -> Result<Self::Value, E> {
+    let Some([Segment::Key(next), rest @ ..]) = self.path else {
+      return Ok(None);
+    };
+    if next != key {
+      return Ok(None);
+    }
+    if rest.is_empty() && !self.walker.to_end {
+      return Err(self.walker.stop());
+    }
+    Ok(Some(rest))
+  }
+  fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+    Ok(None)
+  }
+  fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+    Ok(None)
+  }
+  fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+    Ok(None)
+  }
+  fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+    Ok(None)
+  }
+  fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+    Ok(None)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test::TypeScript;
+
+  fn diagnose(yaml: &str) -> Vec<RuleDiagnostic> {
+    let globals: GlobalsOf<TypeScript> = &|_| GlobalRules::default();
+    diagnose_rule(yaml, globals).err().unwrap_or_default()
+  }
+
+  fn located(yaml: &str) -> Vec<(String, &str)> {
+    diagnose(yaml)
+      .into_iter()
+      .map(|d| (d.path, &yaml[d.span]))
+      .collect()
+  }
+
+  #[test]
+  fn test_valid_rule() {
+    let globals: GlobalsOf<TypeScript> = &|_| GlobalRules::default();
+    let yaml = "id: a\nlanguage: Tsx\nrule: {pattern: foo}";
+    assert!(diagnose_rule(yaml, globals).is_ok());
+  }
+
+  #[test]
+  fn test_composite_error() {
+    let yaml = r"
+id: a
+language: Tsx
+rule:
+  all:
+    - pattern: foo
+    - kind: not_a_kind
+    - inside:
+        kind: class_body
+        stopBy: wrong
+";
+    let errors = located(yaml);
+    assert_eq!(
+      errors,
+      [
+        ("rule.all[1].kind".into(), "kind: not_a_kind"),
+        ("rule.all[2].inside.stopBy".into(), "stopBy: wrong"),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_undefined_util() {
+    let yaml = r"
+id: a
+language: Tsx
+utils:
+  defined: {kind: identifier}
+rule:
+  any:
+    - matches: defined
+    - matches: undefined
+";
+    let errors = located(yaml);
+    assert_eq!(
+      errors,
+      [("rule.any[1].matches".into(), "matches: undefined")]
+    );
+  }
+
+  #[test]
+  fn test_no_common_kind() {
+    let yaml = r"
+id: a
+language: Tsx
+rule:
+  not: {kind: identifier}
+  all: [{kind: class_declaration}, {kind: identifier}]
+";
+    let errors = diagnose(yaml);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path, "rule.all");
+    assert_eq!(
+      &yaml[errors[0].span.clone()],
+      "all: [{kind: class_declaration}, {kind: identifier}]"
+    );
+  }
+
+  #[test]
+  fn test_whole_rule_error() {
+    let errors = located("id: a\nlanguage: NotLang\nrule: {kind: a}");
+    assert_eq!(errors, [("language".into(), "language: NotLang")]);
+    let yaml = "id: a\nlanguage: Tsx\nrule: {kind: [}";
+    let errors = diagnose(yaml);
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].error, RuleConfigError::Yaml(_)));
+    assert!(errors[0].path.is_empty());
+  }
+}
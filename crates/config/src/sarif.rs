@@ -0,0 +1,8 @@
+//! Constants of the SARIF logs written by `ast-grep scan --format sarif` and the wasm
+//! `scanToSarif`, so both declare the same format.
+
+/// The SARIF format version of the log, which is not the ast-grep version.
+pub const SARIF_VERSION: &str = "2.1.0";
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+/// Columns are counted in chars, while SARIF counts UTF-16 code units by default.
+pub const SARIF_COLUMN_KIND: &str = "unicodeCodePoints";
@@ -0,0 +1,30 @@
+//! Security rules bundled with ast-grep, run by `ast-grep audit` and the wasm `scanSecurity`.
+//!
+//! A ruleset is a YAML file of rules without `language`, which is set to each language the
+//! ruleset is for, and with the CWE id of their findings as `metadata.cwe`.
+
+/// A YAML file of bundled security rules.
+pub struct SecurityRuleset {
+  pub name: &'static str,
+  /// names of the languages the rules are written for, including aliases
+  pub languages: &'static [&'static str],
+  pub yaml: &'static str,
+}
+
+pub const SECURITY_RULESETS: &[SecurityRuleset] = &[
+  SecurityRuleset {
+    name: "javascript",
+    languages: &["javascript", "js", "jsx", "typescript", "ts", "tsx"],
+    yaml: include_str!("security/javascript.yml"),
+  },
+  SecurityRuleset {
+    name: "python",
+    languages: &["python", "py"],
+    yaml: include_str!("security/python.yml"),
+  },
+  SecurityRuleset {
+    name: "go",
+    languages: &["go", "golang"],
+    yaml: include_str!("security/go.yml"),
+  },
+];
@@ -0,0 +1,151 @@
+//! Detached [minisign](https://jedisct1.github.io/minisign/) signatures of rulesets, so that
+//! only rules signed by a trusted key are loaded.
+//!
+//! A public key is the base64 line of a `minisign.pub` file, and a signature the content of a
+//! `.minisig` file, produced by `minisign -Sm <file>`. Both the legacy signatures of the file
+//! and the default ones of its BLAKE2b-512 hash are accepted. The trusted comment is signed
+//! too, by the global signature. Signatures are verified by the `minisign-verify` crate.
+
+use minisign_verify::Error;
+use std::str::FromStr;
+use thiserror::Error;
+
+const UNTRUSTED: &str = "untrusted comment:";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SignatureError {
+  #[error("`{0}` is not a minisign public key.")]
+  InvalidKey(String),
+  #[error("Signature is not in the minisign format.")]
+  InvalidSignature,
+  #[error("Signature is not by a trusted key.")]
+  UntrustedKey,
+  #[error("Signature does not match the signed content.")]
+  Mismatch,
+}
+
+/// A minisign ed25519 public key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey(minisign_verify::PublicKey);
+
+/// A detached minisign signature.
+#[derive(Clone)]
+pub struct Signature(minisign_verify::Signature);
+
+impl FromStr for PublicKey {
+  type Err = SignatureError;
+  /// Parse the base64 line of a public key, or a whole `minisign.pub` file.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    // the comment of a `minisign.pub` file is not part of the key
+    let line = s
+      .lines()
+      .map(str::trim)
+      .find(|line| !line.is_empty() && !line.starts_with(UNTRUSTED))
+      .unwrap_or_default();
+    minisign_verify::PublicKey::from_base64(line)
+      .map(Self)
+      .map_err(|_| SignatureError::InvalidKey(s.trim().to_string()))
+  }
+}
+
+impl FromStr for Signature {
+  type Err = SignatureError;
+  /// Parse the content of a `.minisig` file.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    minisign_verify::Signature::decode(s.trim())
+      .map(Self)
+      .map_err(|_| SignatureError::InvalidSignature)
+  }
+}
+
+impl Signature {
+  pub fn trusted_comment(&self) -> &str {
+    self.0.trusted_comment()
+  }
+
+  /// Verify this is a signature of `content` by one of `keys`.
+  pub fn verify(&self, content: &[u8], keys: &[PublicKey]) -> Result<(), SignatureError> {
+    for key in keys {
+      match key.0.verify(content, &self.0, true) {
+        Ok(()) => return Ok(()),
+        // the signature is by another key, try the next one
+        Err(Error::UnexpectedKeyId) => continue,
+        Err(Error::InvalidSignature) => return Err(SignatureError::Mismatch),
+        Err(_) => return Err(SignatureError::InvalidSignature),
+      }
+    }
+    Err(SignatureError::UntrustedKey)
+  }
+}
+
+/// Verify `signature`, the content of a `.minisig` file, is a signature of `content` by one
+/// of `keys`.
+pub fn verify_signature(
+  content: &[u8],
+  signature: &str,
+  keys: &[PublicKey],
+) -> Result<(), SignatureError> {
+  signature.parse::<Signature>()?.verify(content, keys)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  const RULE: &str = "id: no-eval\nlanguage: JavaScript\nrule:\n  pattern: eval($A)\n";
+  const KEY: &str = "RWSb/Hxmi63749yYtnZl+v38fXjrQ+Tp/qEENkxyROWc7oRVbryDdHMg";
+  const OTHER_KEY: &str = "RWQ5XSpykpyl7gVYNiYBZ8WcMImyXi82kigi1ZDrBP9CSk1rEX1DyB/9";
+  const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUSb/Hxmi63740FvRD1X0ThwyM/PmoUbZ08I3uHT12SR2pTOXfZ29+DahtsCcrTPb9QjXCsVlWOu42kEgrbiJNmocrs8hZsZlww=
+trusted comment: timestamp:1760400000\tfile:rule.yml
+6p4AhW4TyxmshT4ulkvu8q/yxfE2Ui8c7tr0sItMheL/4JRKIT6CNuA0ZUKeipZjJQN7pVI1iQmNLYJvAUY1Dw==
+";
+  const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWSb/Hxmi6374xY8fNOxOWrvqD5loOtychhjVVxQEL/yUBmaBXQ5F4E1Ocasm9zvBCM1UpgooSSicVcvLzliDEG1Y+q3m6EiRA4=
+trusted comment: timestamp:1760400000\tfile:legacy.yml
+W4gRoRXYOeuSZCebWcBroNqmDDfe6xFY9QzowZcjQ34DuGMTejKXiuVnfq4ZlxawU7LOe/Ym7YRCfhY0sYOoCQ==
+";
+
+  fn keys(keys: &[&str]) -> Vec<PublicKey> {
+    keys
+      .iter()
+      .map(|k| k.parse().expect("should parse"))
+      .collect()
+  }
+
+  #[test]
+  fn test_verify_signature() {
+    let trusted = keys(&[OTHER_KEY, KEY]);
+    assert_eq!(
+      verify_signature(RULE.as_bytes(), SIGNATURE, &trusted),
+      Ok(())
+    );
+    assert_eq!(
+      verify_signature(RULE.as_bytes(), LEGACY_SIGNATURE, &trusted),
+      Ok(())
+    );
+    let signature: Signature = SIGNATURE.parse().expect("should parse");
+    assert_eq!(
+      signature.trusted_comment(),
+      "timestamp:1760400000\tfile:rule.yml"
+    );
+  }
+
+  #[test]
+  fn test_reject_signature() {
+    let trusted = keys(&[KEY]);
+    let tampered = RULE.replace("eval", "exec");
+    let ret = verify_signature(tampered.as_bytes(), SIGNATURE, &trusted);
+    assert_eq!(ret, Err(SignatureError::Mismatch));
+    let comment = SIGNATURE.replace("rule.yml", "other.yml");
+    let ret = verify_signature(RULE.as_bytes(), &comment, &trusted);
+    assert_eq!(ret, Err(SignatureError::Mismatch));
+    let ret = verify_signature(RULE.as_bytes(), SIGNATURE, &keys(&[OTHER_KEY]));
+    assert_eq!(ret, Err(SignatureError::UntrustedKey));
+    let ret = verify_signature(RULE.as_bytes(), "not a signature", &trusted);
+    assert_eq!(ret, Err(SignatureError::InvalidSignature));
+    assert!("RWQ".parse::<PublicKey>().is_err());
+    let file = format!("untrusted comment: minisign public key\n{KEY}\n");
+    assert_eq!(file.parse::<PublicKey>(), Ok(trusted[0].clone()));
+  }
+}
@@ -1,5 +1,5 @@
 use super::rewrite::Rewrite;
-use super::trans::{Convert, Replace, Substring};
+use super::trans::{regex_str, Convert, Join, Replace, Split, Strip, Substring};
 use super::Trans;
 use serde_yaml::from_str as yaml_from_str;
 use std::str::FromStr;
@@ -28,6 +28,9 @@ impl FromStr for Trans<String> {
       "convert" => Trans::Convert(to_convert(decomposed)?),
       "replace" => Trans::Replace(to_replace(decomposed)?),
       "substring" => Trans::Substring(to_substring(decomposed)?),
+      "strip" => Trans::Strip(to_strip(decomposed)?),
+      "join" => Trans::Join(to_join(decomposed)?),
+      "split" => Trans::Split(to_split(decomposed)?),
       "rewrite" => Trans::Rewrite(to_rewrite(decomposed)?),
       invalid => return Err(ParseTransError::InvalidTransform(invalid.to_string())),
     };
@@ -134,6 +137,55 @@ fn to_substring(decomposed: DecomposedTransString) -> Result<Substring<String>,
     end_char,
   })
 }
+fn to_strip(decomposed: DecomposedTransString) -> Result<Strip<String>, ParseTransError> {
+  debug_assert_eq!(decomposed.func, "strip");
+  let mut prefix = None;
+  let mut suffix = None;
+  for (key, value) in decomposed.args {
+    match key {
+      "prefix" => prefix = Some(value),
+      "suffix" => suffix = Some(value),
+      _ => return Err(ParseTransError::InvalidArg(key.to_string())),
+    }
+  }
+  Ok(Strip {
+    source: decomposed.source.to_string(),
+    prefix: prefix.map(yaml_from_str).transpose()?,
+    suffix: suffix.map(yaml_from_str).transpose()?,
+  })
+}
+fn to_join(decomposed: DecomposedTransString) -> Result<Join<String>, ParseTransError> {
+  debug_assert_eq!(decomposed.func, "join");
+  let mut join_by = None;
+  for (key, value) in decomposed.args {
+    match key {
+      "joinBy" => join_by = Some(value),
+      _ => return Err(ParseTransError::InvalidArg(key.to_string())),
+    }
+  }
+  Ok(Join {
+    source: decomposed.source.to_string(),
+    join_by: join_by.map(yaml_from_str).transpose()?,
+  })
+}
+fn to_split(decomposed: DecomposedTransString) -> Result<Split<String>, ParseTransError> {
+  debug_assert_eq!(decomposed.func, "split");
+  let mut by = None;
+  let mut join_by = None;
+  for (key, value) in decomposed.args {
+    match key {
+      "by" => by = Some(value),
+      "joinBy" => join_by = Some(value),
+      _ => return Err(ParseTransError::InvalidArg(key.to_string())),
+    }
+  }
+  let by = by.ok_or(ParseTransError::RequiredArg("by"))?;
+  Ok(Split {
+    source: decomposed.source.to_string(),
+    by: regex_str::deserialize(serde_yaml::Deserializer::from_str(by))?,
+    join_by: join_by.map(yaml_from_str).transpose()?,
+  })
+}
 fn to_rewrite(decomposed: DecomposedTransString) -> Result<Rewrite<String>, ParseTransError> {
   debug_assert_eq!(decomposed.func, "rewrite");
   let mut rewriters = None;
@@ -174,10 +226,20 @@ mod test {
   const REPLACE_CASE: &str = "replace($A, replace= ^.+, by=', ')";
   const CONVERT_CASE: &str = "convert($A, toCase=camelCase, separatedBy=[underscore, dash])";
   const REWRITE_CASE: &str = "rewrite($A, rewriters=[rule1, rule2], joinBy = ',,,,')";
+  const JOIN_CASE: &str = "join($$$A, joinBy=', ')";
+  const SPLIT_CASE: &str = "split($A, by=',', joinBy=' | ')";
+  const STRIP_CASE: &str = "strip($A, prefix=get, suffix='Async')";
 
   #[test]
   fn test_decompose_cases() {
-    let cases = [SUBSTRING_CASE, REPLACE_CASE, CONVERT_CASE, REWRITE_CASE];
+    let cases = [
+      SUBSTRING_CASE,
+      REPLACE_CASE,
+      CONVERT_CASE,
+      REWRITE_CASE,
+      JOIN_CASE,
+      SPLIT_CASE,
+    ];
     for case in cases {
       let decomposed = decompose_str(case).expect("should parse");
       match decomposed.func {
@@ -185,6 +247,8 @@ mod test {
         "replace" => assert_eq!(decomposed.args.len(), 2),
         "substring" => assert_eq!(decomposed.args.len(), 2),
         "rewrite" => assert_eq!(decomposed.args.len(), 2),
+        "join" => assert_eq!(decomposed.args.len(), 1),
+        "split" => assert_eq!(decomposed.args.len(), 2),
         _ => panic!("Unexpected function: {}", decomposed.func),
       }
     }
@@ -198,6 +262,8 @@ mod test {
       "substring(   $A, startChar=1)",
       "substring(  $A,)",
       "rewrite($A, rewriters=[rule1, rule2])",
+      "join($$$A,)",
+      "split($A, by= \\s+)",
     ];
     for case in cases {
       Trans::from_str(case).expect("should parse convert");
@@ -250,4 +316,36 @@ mod test {
     );
     assert_eq!(rewrite.join_by, Some(",,,,".into()));
   }
+
+  #[test]
+  fn test_parse_join() {
+    let join = Trans::from_str(JOIN_CASE).expect("should parse join");
+    let Trans::Join(join) = join else {
+      panic!("Expected Join transformation");
+    };
+    assert_eq!(join.source, "$$$A");
+    assert_eq!(join.join_by, Some(", ".into()));
+  }
+
+  #[test]
+  fn test_parse_split() {
+    let split = Trans::from_str(SPLIT_CASE).expect("should parse split");
+    let Trans::Split(split) = split else {
+      panic!("Expected Split transformation");
+    };
+    assert_eq!(split.source, "$A");
+    assert_eq!(split.by.as_str(), ",");
+    assert_eq!(split.join_by, Some(" | ".into()));
+  }
+
+  #[test]
+  fn test_parse_strip() {
+    let strip = Trans::from_str(STRIP_CASE).expect("should parse strip");
+    let Trans::Strip(strip) = strip else {
+      panic!("Expected Strip transformation");
+    };
+    assert_eq!(strip.source, "$A");
+    assert_eq!(strip.prefix, Some("get".into()));
+    assert_eq!(strip.suffix, Some("Async".into()));
+  }
 }
@@ -80,6 +80,35 @@ impl Replace<MetaVariable> {
   }
 }
 
+/// Removes a prefix and a suffix from the meta variable's text content.
+///
+/// The text is kept as is where it does not start with `prefix` or end with `suffix`,
+/// e.g. `getFoo` becomes `Foo` with the prefix `get`, but `setFoo` is unchanged.
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Strip<T> {
+  /// source meta variable to be transformed
+  pub source: T,
+  /// optional prefix to remove from the start
+  pub prefix: Option<String>,
+  /// optional suffix to remove from the end
+  pub suffix: Option<String>,
+}
+
+impl Strip<MetaVariable> {
+  fn compute<D: Doc>(&self, ctx: &mut Ctx<'_, '_, D>) -> Option<String> {
+    let text = get_text_from_env(&self.source, ctx)?;
+    let mut text = text.as_str();
+    if let Some(prefix) = &self.prefix {
+      text = text.strip_prefix(prefix.as_str()).unwrap_or(text);
+    }
+    if let Some(suffix) = &self.suffix {
+      text = text.strip_suffix(suffix.as_str()).unwrap_or(text);
+    }
+    Some(text.to_string())
+  }
+}
+
 /// Converts the source meta variable's text content to a specified case format.
 #[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -99,14 +128,91 @@ impl Convert<MetaVariable> {
   }
 }
 
+/// Joins the texts of a multi meta variable's captured nodes with a separator.
+///
+/// Only named nodes are joined, so punctuation like `,` between arguments is dropped.
+/// An empty multi-capture produces an empty string.
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Join<T> {
+  /// source meta variable to be transformed
+  pub source: T,
+  /// optional separator placed between captured nodes, defaults to an empty string.
+  pub join_by: Option<String>,
+}
+
+impl Join<MetaVariable> {
+  fn compute<D: Doc>(&self, ctx: &mut Ctx<'_, '_, D>) -> Option<String> {
+    let MetaVariable::MultiCapture(name) = &self.source else {
+      return get_text_from_env(&self.source, ctx);
+    };
+    let texts: Vec<_> = ctx
+      .env
+      .get_multiple_matches(name)
+      .into_iter()
+      .filter(|n| n.is_named())
+      .map(|n| n.text().into_owned())
+      .collect();
+    Some(texts.join(self.join_by.as_deref().unwrap_or_default()))
+  }
+}
+
+/// Splits the meta variable's text content by a regex and joins the trimmed parts again.
+///
+/// Empty parts are discarded, which makes it handy to reshape lists of items.
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Split<T> {
+  /// source meta variable to be transformed
+  pub source: T,
+  /// a regex to find the separators to split by
+  #[serde(with = "regex_str")]
+  #[schemars(with = "String")]
+  pub by: Regex,
+  /// optional separator placed between the split parts, defaults to a newline.
+  pub join_by: Option<String>,
+}
+
+/// A regex read and written as its pattern string, so it is compiled once with the rule.
+pub(super) mod regex_str {
+  use regex::Regex;
+  use serde::{de, Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(regex.as_str())
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(de::Error::custom)
+  }
+}
+
+impl Split<MetaVariable> {
+  fn compute<D: Doc>(&self, ctx: &mut Ctx<'_, '_, D>) -> Option<String> {
+    let text = get_text_from_env(&self.source, ctx)?;
+    let parts: Vec<_> = self
+      .by
+      .split(&text)
+      .map(str::trim)
+      .filter(|s| !s.is_empty())
+      .collect();
+    Some(parts.join(self.join_by.as_deref().unwrap_or("\n")))
+  }
+}
+
 /// Represents a transformation that can be applied to a matched AST node.
-/// Available transformations are `substring`, `replace` and `convert`.
+/// Available transformations are `substring`, `replace`, `strip`, `convert`, `join`, `split`
+/// and `rewrite`.
 #[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum Trans<T> {
   Substring(Substring<T>),
   Replace(Replace<T>),
+  Strip(Strip<T>),
   Convert(Convert<T>),
+  Join(Join<T>),
+  Split(Split<T>),
   Rewrite(Rewrite<T>),
 }
 
@@ -116,7 +222,10 @@ impl<T> Trans<T> {
     match self {
       T::Replace(r) => &r.source,
       T::Substring(s) => &s.source,
+      T::Strip(s) => &s.source,
       T::Convert(c) => &c.source,
+      T::Join(j) => &j.source,
+      T::Split(s) => &s.source,
       T::Rewrite(r) => &r.source,
     }
   }
@@ -148,11 +257,25 @@ impl Trans<String> {
         start_char: s.start_char,
         end_char: s.end_char,
       }),
+      T::Strip(s) => T::Strip(Strip {
+        source: parse_meta_var(&s.source, lang)?,
+        prefix: s.prefix.clone(),
+        suffix: s.suffix.clone(),
+      }),
       T::Convert(c) => T::Convert(Convert {
         source: parse_meta_var(&c.source, lang)?,
         to_case: c.to_case,
         separated_by: c.separated_by.clone(),
       }),
+      T::Join(j) => T::Join(Join {
+        source: parse_meta_var(&j.source, lang)?,
+        join_by: j.join_by.clone(),
+      }),
+      T::Split(s) => T::Split(Split {
+        source: parse_meta_var(&s.source, lang)?,
+        by: s.by.clone(),
+        join_by: s.join_by.clone(),
+      }),
       T::Rewrite(r) => T::Rewrite(r.parse(lang)?),
     })
   }
@@ -177,7 +300,10 @@ impl Trans<MetaVariable> {
     match self {
       T::Replace(r) => r.compute(ctx),
       T::Substring(s) => s.compute(ctx),
+      T::Strip(s) => s.compute(ctx),
       T::Convert(c) => c.compute(ctx),
+      T::Join(j) => j.compute(ctx),
+      T::Split(s) => s.compute(ctx),
       T::Rewrite(r) => r.compute(ctx),
     }
   }
@@ -187,7 +313,10 @@ impl Trans<MetaVariable> {
     match self {
       T::Replace(_) => &[],
       T::Substring(_) => &[],
+      T::Strip(_) => &[],
       T::Convert(_) => &[],
+      T::Join(_) => &[],
+      T::Split(_) => &[],
       T::Rewrite(r) => &r.rewriters,
     }
   }
@@ -461,5 +590,61 @@ if (true) {
     Ok(())
   }
 
+  #[test]
+  fn test_join_multi_capture() -> R {
+    let trans = parse("join: { source: $$$ARGS, joinBy: ' + ' }")?;
+    let actual = get_transformed("foo(a, b, c)", "foo($$$ARGS)", &trans).ok_or(())?;
+    assert_eq!(actual, "a + b + c");
+    let trans = parse("join: { source: $$$ARGS }")?;
+    let actual = get_transformed("foo(a, b, c)", "foo($$$ARGS)", &trans).ok_or(())?;
+    assert_eq!(actual, "abc");
+    Ok(())
+  }
+
+  #[test]
+  fn test_join_empty_multi_capture() -> R {
+    let trans = parse("join: { source: $$$ARGS, joinBy: ', ' }")?;
+    let actual = get_transformed("foo()", "foo($$$ARGS)", &trans).ok_or(())?;
+    assert_eq!(actual, "");
+    Ok(())
+  }
+
+  #[test]
+  fn test_split() -> R {
+    let trans = parse(
+      r#"
+      split:
+        source: "$A"
+        by: "[,;]"
+        joinBy: " | "
+    "#,
+    )?;
+    let actual = get_transformed("let a = 'x, y;;z'", "let a = $A", &trans).ok_or(())?;
+    assert_eq!(actual, "'x | y | z'");
+    let trans = parse("split: { source: $A, by: '_' }")?;
+    let actual = get_transformed("let a = b_c", "let a = $A", &trans).ok_or(())?;
+    assert_eq!(actual, "b\nc");
+    Ok(())
+  }
+
+  #[test]
+  fn test_strip() -> R {
+    let trans = parse("strip: { source: $A, prefix: get, suffix: Async }")?;
+    let actual = get_transformed("let a = getUserAsync", "let a = $A", &trans).ok_or(())?;
+    assert_eq!(actual, "User");
+    let actual = get_transformed("let a = setUserSync", "let a = $A", &trans).ok_or(())?;
+    assert_eq!(actual, "setUserSync");
+    let trans = parse("strip: { source: $A }")?;
+    let actual = get_transformed("let a = getUser", "let a = $A", &trans).ok_or(())?;
+    assert_eq!(actual, "getUser");
+    Ok(())
+  }
+
+  #[test]
+  fn test_split_invalid_regex() {
+    assert!(parse("split: { source: $A, by: '[' }").is_err());
+    assert!("split($A, by='[')".parse::<Trans<String>>().is_err());
+  }
+
   // TODO: add a symbolic test for Rewrite
 }
@@ -41,6 +41,18 @@ pub trait Language: Clone + 'static {
     None
   }
 
+  /// The code before and after a pattern that parses it inside a node of `kind`,
+  /// e.g. `("switch (_) { ", " }")` for `switch_body` in JavaScript. See `Pattern::parse_as`.
+  fn parse_as_context(&self, _kind: &str) -> Option<(&'static str, &'static str)> {
+    None
+  }
+
+  /// The names of the node kinds, indexed by kind id, to resolve kind regexes.
+  /// `None` if the kinds cannot be listed, then a kind regex does not prefilter nodes.
+  fn kind_names(&self) -> Option<Vec<String>> {
+    None
+  }
+
   fn kind_to_id(&self, kind: &str) -> u16;
   fn field_to_id(&self, field: &str) -> Option<u16>;
   fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError>;
@@ -61,6 +73,9 @@ mod test {
       let ts_lang: TSLanguage = tree_sitter_typescript::LANGUAGE_TSX.into();
       ts_lang.id_for_node_kind(kind, /* named */ true)
     }
+    fn kind_names(&self) -> Option<Vec<String>> {
+      Some(crate::tree_sitter::kind_names(&self.get_ts_language()))
+    }
     fn field_to_id(&self, field: &str) -> Option<u16> {
       self
         .get_ts_language()
@@ -70,6 +85,13 @@ mod test {
     fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError> {
       builder.build(|src| StrDoc::try_new(src, self.clone()))
     }
+    fn parse_as_context(&self, kind: &str) -> Option<(&'static str, &'static str)> {
+      match kind {
+        "switch_body" => Some(("switch (_) { ", " }")),
+        "class_body" => Some(("class _ { ", " }")),
+        _ => None,
+      }
+    }
   }
   impl LanguageExt for Tsx {
     fn get_ts_language(&self) -> TSLanguage {
@@ -22,7 +22,7 @@ mod match_tree;
 mod node;
 
 pub use language::Language;
-pub use match_tree::MatchStrictness;
+pub use match_tree::{Explanation, MatchStrictness, Mismatch};
 pub use matcher::{Matcher, NodeMatch, Pattern, PatternError};
 pub use node::{Node, Position};
 pub use source::Doc;
@@ -0,0 +1,373 @@
+//! Explain why a pattern does not match a node, for rule authors debugging patterns.
+//! The walk follows `match_node_impl` step by step and stops where it would fail.
+
+use super::match_leaf_meta_var;
+use super::match_node::match_node_impl;
+use super::strictness::{MatchOneNode, Strictness};
+use crate::matcher::MatcherExt;
+use crate::matcher::{kind_utils, PatternNode};
+use crate::meta_var::{MetaVarEnv, MetaVariable};
+use crate::{Doc, Node, Pattern};
+
+use std::borrow::Cow;
+use std::iter::Peekable;
+
+/// The first place where a pattern diverges from the candidate tree.
+pub enum Mismatch<'p, 't, D: Doc> {
+  /// The node has another kind than the pattern node.
+  Kind { expected: u16, node: Node<'t, D> },
+  /// The terminal node has the expected kind but another text.
+  Text {
+    expected: &'p str,
+    node: Node<'t, D>,
+  },
+  /// A named meta variable, like `$A`, cannot match an unnamed node.
+  Unnamed { name: String, node: Node<'t, D> },
+  /// A meta variable used twice captured a different node than before.
+  MetaVar {
+    name: String,
+    node: Node<'t, D>,
+    previous: Node<'t, D>,
+  },
+  /// `parent` has no child left for the pattern node `goal`.
+  MissingChild {
+    goal: &'p PatternNode,
+    parent: Node<'t, D>,
+  },
+  /// The node is left over after the pattern's children and cannot be skipped.
+  ExtraChild { node: Node<'t, D> },
+}
+
+/// The result of `Pattern::explain`.
+pub struct Explanation<'p, 't, D: Doc> {
+  /// Whether the pattern matches the node.
+  pub matched: bool,
+  /// Where matching fails. Unset if the pattern matches.
+  pub mismatch: Option<Mismatch<'p, 't, D>>,
+  /// Candidate nodes the strictness skipped before matching stopped.
+  pub skipped: Vec<Node<'t, D>>,
+}
+
+enum Step<'p, 't, D: Doc> {
+  Matched,
+  SkipGoal,
+  SkipCandidate,
+  SkipBoth,
+  Failed(Mismatch<'p, 't, D>),
+}
+
+struct Walk<'p, 't, D: Doc> {
+  strictness: Strictness<'p>,
+  env: Cow<'t, MetaVarEnv<'t, D>>,
+  skipped: Vec<Node<'t, D>>,
+}
+
+fn is_ellipsis(node: &PatternNode) -> bool {
+  matches!(
+    node,
+    PatternNode::MetaVar {
+      meta_var: MetaVariable::Multiple | MetaVariable::MultiCapture(_)
+    }
+  )
+}
+
+impl<'p, 't, D: Doc> Walk<'p, 't, D> {
+  fn step(&mut self, goal: &'p PatternNode, cand: &Node<'t, D>) -> Step<'p, 't, D> {
+    use PatternNode as P;
+    match goal {
+      P::Terminal {
+        text,
+        kind_id,
+        is_named,
+      } => match self
+        .strictness
+        .match_terminal(*is_named, text, *kind_id, cand)
+      {
+        MatchOneNode::MatchedBoth => Step::Matched,
+        MatchOneNode::SkipGoal => Step::SkipGoal,
+        MatchOneNode::SkipCandidate => Step::SkipCandidate,
+        MatchOneNode::SkipBoth => Step::SkipBoth,
+        MatchOneNode::NoMatch if kind_utils::are_kinds_matching(*kind_id, cand.kind_id()) => {
+          Step::Failed(Mismatch::Text {
+            expected: text,
+            node: cand.clone(),
+          })
+        }
+        MatchOneNode::NoMatch => Step::Failed(Mismatch::Kind {
+          expected: *kind_id,
+          node: cand.clone(),
+        }),
+      },
+      P::MetaVar { meta_var } => {
+        if self.strictness.should_skip_cand_for_metavar(cand) {
+          return Step::SkipCandidate;
+        }
+        if is_ellipsis(goal) || match_leaf_meta_var(meta_var, cand, &mut self.env).is_some() {
+          return Step::Matched;
+        }
+        let name = match meta_var {
+          MetaVariable::Capture(name, _) => name.clone(),
+          _ => String::from("_"),
+        };
+        match self.env.get_match(&name) {
+          Some(previous) => Step::Failed(Mismatch::MetaVar {
+            previous: previous.clone(),
+            name,
+            node: cand.clone(),
+          }),
+          None => Step::Failed(Mismatch::Unnamed {
+            name,
+            node: cand.clone(),
+          }),
+        }
+      }
+      P::Internal { kind_id, children } => {
+        let kind_matched = self.strictness.should_skip_kind()
+          || kind_utils::are_kinds_matching(*kind_id, cand.kind_id());
+        if !kind_matched {
+          if self.strictness.should_skip_internal(cand) {
+            return Step::SkipCandidate;
+          }
+          return Step::Failed(Mismatch::Kind {
+            expected: *kind_id,
+            node: cand.clone(),
+          });
+        }
+        match self.children(children, cand) {
+          Ok(()) => Step::Matched,
+          Err(mismatch) => Step::Failed(mismatch),
+        }
+      }
+    }
+  }
+
+  /// Whether `goal` matches `cand`, without recording captures or skipped nodes.
+  fn matches(&self, goal: &PatternNode, cand: &Node<'t, D>) -> bool {
+    let mut env = self.env.clone();
+    matches!(
+      match_node_impl(goal, cand, &mut env, &self.strictness),
+      MatchOneNode::MatchedBoth
+    )
+  }
+
+  fn children(
+    &mut self,
+    goals: &'p [PatternNode],
+    parent: &Node<'t, D>,
+  ) -> Result<(), Mismatch<'p, 't, D>> {
+    let mut goal_children = goals.iter().peekable();
+    let mut cand_children = parent.children().peekable();
+    // only called while a goal is left
+    let missing = |goals: &mut Peekable<std::slice::Iter<'p, PatternNode>>| {
+      let goal = goals.peek().expect("goal is not exhausted");
+      Mismatch::MissingChild {
+        goal,
+        parent: parent.clone(),
+      }
+    };
+    if cand_children.peek().is_none() {
+      return match goal_children.peek() {
+        Some(goal) => Err(Mismatch::MissingChild {
+          goal,
+          parent: parent.clone(),
+        }),
+        None => Ok(()),
+      };
+    }
+    loop {
+      let Some(goal) = goal_children.peek() else {
+        return Ok(());
+      };
+      if is_ellipsis(goal) {
+        goal_children.next();
+        // skip trivial nodes in goal after ellipsis
+        loop {
+          match goal_children.peek() {
+            None => return Ok(()),
+            Some(goal) if goal.is_trivial() => goal_children.next(),
+            Some(_) => break,
+          };
+        }
+        let next = *goal_children.peek().expect("checked above");
+        if is_ellipsis(next) {
+          cand_children.next();
+          if cand_children.peek().is_none() {
+            return Err(missing(&mut goal_children));
+          }
+          continue;
+        }
+        while !self.matches(next, cand_children.peek().expect("checked in loop")) {
+          cand_children.next();
+          if cand_children.peek().is_none() {
+            return Err(missing(&mut goal_children));
+          }
+        }
+      }
+      loop {
+        let Some(cand) = cand_children.peek() else {
+          if self.strictness.should_skip_goal(&mut goal_children) {
+            break;
+          }
+          return Err(missing(&mut goal_children));
+        };
+        let goal = *goal_children.peek().expect("goal is not exhausted");
+        match self.step(goal, cand) {
+          Step::Matched => break,
+          Step::SkipGoal => {
+            goal_children.next();
+            if goal_children.peek().is_none() {
+              break;
+            }
+          }
+          Step::SkipBoth => {
+            self.skipped.push(cand.clone());
+            cand_children.next();
+            goal_children.next();
+            if goal_children.peek().is_none() {
+              break;
+            }
+          }
+          Step::SkipCandidate => {
+            self.skipped.push(cand.clone());
+            cand_children.next();
+          }
+          Step::Failed(mismatch) => return Err(mismatch),
+        }
+      }
+      if goal_children.next().is_some() {
+        cand_children.next();
+      }
+      if goal_children.peek().is_none() {
+        for cand in cand_children {
+          if !self.strictness.should_skip_trailing(&cand) {
+            return Err(Mismatch::ExtraChild { node: cand });
+          }
+          self.skipped.push(cand);
+        }
+        return Ok(());
+      }
+      if cand_children.peek().is_none() {
+        return Err(missing(&mut goal_children));
+      }
+    }
+  }
+}
+
+/// Explain why `goal` does or does not match `node` itself, see `Pattern::explain`.
+pub(crate) fn explain_node<'p, 't, D: Doc>(
+  goal: &'p Pattern,
+  root_kind: Option<u16>,
+  node: Node<'t, D>,
+) -> Explanation<'p, 't, D> {
+  let matched = goal.match_node(node.clone()).is_some();
+  let mut walk = Walk {
+    strictness: goal.match_strictness(),
+    env: Cow::Owned(MetaVarEnv::new()),
+    skipped: vec![],
+  };
+  let mismatch = match root_kind {
+    Some(kind) if node.kind_id() != kind => Some(Mismatch::Kind {
+      expected: kind,
+      node: node.clone(),
+    }),
+    _ => match walk.step(&goal.node, &node) {
+      Step::Failed(mismatch) => Some(mismatch),
+      _ => None,
+    },
+  };
+  Explanation {
+    matched,
+    mismatch: if matched { None } else { mismatch },
+    skipped: walk.skipped,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::language::Tsx;
+  use crate::tree_sitter::LanguageExt;
+  use crate::MatchStrictness;
+
+  fn explain(p: &str, src: &str, kind: &str, strictness: MatchStrictness) -> String {
+    let pattern = Pattern::new(p, Tsx).with_strictness(strictness);
+    let root = Tsx.ast_grep(src);
+    let node = root
+      .root()
+      .dfs()
+      .find(|n| n.kind() == kind)
+      .expect("should find");
+    let explanation = pattern.explain(node);
+    let skipped: Vec<_> = explanation.skipped.iter().map(|n| n.text()).collect();
+    let mismatch = match explanation.mismatch {
+      None => format!("matched {}", explanation.matched),
+      Some(Mismatch::Kind { node, .. }) => format!("kind {}", node.text()),
+      Some(Mismatch::Text { expected, node }) => format!("text {expected} {}", node.text()),
+      Some(Mismatch::Unnamed { name, node }) => format!("unnamed {name} {}", node.text()),
+      Some(Mismatch::MetaVar {
+        name,
+        node,
+        previous,
+      }) => format!("var {name} {} {}", previous.text(), node.text()),
+      Some(Mismatch::MissingChild { parent, .. }) => format!("missing {}", parent.text()),
+      Some(Mismatch::ExtraChild { node }) => format!("extra {}", node.text()),
+    };
+    format!("{mismatch} [{}]", skipped.join(" "))
+  }
+
+  fn smart(p: &str, src: &str, kind: &str) -> String {
+    explain(p, src, kind, MatchStrictness::Smart)
+  }
+
+  #[test]
+  fn test_explain_matched() {
+    assert_eq!(
+      smart("foo($A)", "foo(1)", "call_expression"),
+      "matched true []"
+    );
+    let relaxed = explain(
+      "foo($A)",
+      "foo(/* c */ 1)",
+      "call_expression",
+      MatchStrictness::Relaxed,
+    );
+    assert_eq!(relaxed, "matched true [/* c */]");
+  }
+
+  #[test]
+  fn test_explain_kind_and_text() {
+    assert_eq!(
+      smart("foo($A)", "a + b", "binary_expression"),
+      "kind a + b []"
+    );
+    assert_eq!(smart("foo(1)", "foo(2)", "call_expression"), "text 1 2 []");
+    // the comma is skipped, then `2` is not the closing parenthesis
+    assert_eq!(
+      smart("foo($A)", "foo(1, 2)", "call_expression"),
+      "kind 2 [,]"
+    );
+  }
+
+  #[test]
+  fn test_explain_children() {
+    // the unnamed `)` is skipped while looking for the comma
+    assert_eq!(
+      smart("foo($A, $B)", "foo(1)", "call_expression"),
+      "missing (1) [)]"
+    );
+    assert_eq!(
+      smart("foo($$$, 3)", "foo(1, 2)", "call_expression"),
+      "missing (1, 2) []"
+    );
+    let cst = explain("{ a }", "{ a; }", "statement_block", MatchStrictness::Cst);
+    assert_eq!(cst, "extra ; []");
+  }
+
+  #[test]
+  fn test_explain_meta_var() {
+    assert_eq!(
+      smart("$A + $A", "a + b", "binary_expression"),
+      "var A a b []"
+    );
+  }
+}
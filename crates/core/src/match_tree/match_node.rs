@@ -1,4 +1,4 @@
-use super::strictness::{MatchOneNode, MatchStrictness};
+use super::strictness::{MatchOneNode, Strictness};
 use super::Aggregator;
 use crate::matcher::{kind_utils, PatternNode};
 use crate::meta_var::MetaVariable;
@@ -9,7 +9,7 @@ pub(super) fn match_node_impl<'tree, D: Doc>(
   goal: &PatternNode,
   candidate: &Node<'tree, D>,
   agg: &mut impl Aggregator<'tree, D>,
-  strictness: &MatchStrictness,
+  strictness: &Strictness,
 ) -> MatchOneNode {
   use PatternNode as P;
   match &goal {
@@ -43,6 +43,9 @@ pub(super) fn match_node_impl<'tree, D: Doc>(
       let kind_matched = strictness.should_skip_kind()
         || kind_utils::are_kinds_matching(*kind_id, candidate.kind_id());
       if !kind_matched {
+        if strictness.should_skip_internal(candidate) {
+          return MatchOneNode::SkipCandidate;
+        }
         return MatchOneNode::NoMatch;
       }
       let cand_children = candidate.children();
@@ -58,7 +61,7 @@ fn match_nodes_impl_recursive<'tree, D: Doc>(
   goals: &[PatternNode],
   candidates: impl Iterator<Item = Node<'tree, D>>,
   agg: &mut impl Aggregator<'tree, D>,
-  strictness: &MatchStrictness,
+  strictness: &Strictness,
 ) -> Option<()> {
   let mut goal_children = goals.iter().peekable();
   let mut cand_children = candidates.peekable();
@@ -104,7 +107,7 @@ fn may_match_ellipsis_impl<'p, 't: 'p, D: Doc>(
   goal_children: &mut Peekable<impl Iterator<Item = &'p PatternNode>>,
   cand_children: &mut Peekable<impl Iterator<Item = Node<'t, D>>>,
   agg: &mut impl Aggregator<'t, D>,
-  strictness: &MatchStrictness,
+  strictness: &Strictness,
 ) -> Option<ControlFlow> {
   let Some(curr_node) = goal_children.peek() else {
     // in rare case, an internal node's children is empty
@@ -179,7 +182,7 @@ fn match_single_node_while_skip_trivial<'p, 't: 'p, D: Doc>(
   goal_children: &mut Peekable<impl Iterator<Item = &'p PatternNode>>,
   cand_children: &mut Peekable<impl Iterator<Item = Node<'t, D>>>,
   agg: &mut impl Aggregator<'t, D>,
-  strictness: &MatchStrictness,
+  strictness: &Strictness,
 ) -> Option<ControlFlow> {
   loop {
     let Some(cand) = cand_children.peek() else {
@@ -245,7 +248,7 @@ mod test {
   use super::*;
   use crate::language::Tsx;
   use crate::matcher::KindMatcher;
-  use crate::{meta_var::MetaVarEnv, Matcher, Pattern, Root};
+  use crate::{meta_var::MetaVarEnv, MatchStrictness, Matcher, Pattern, Root};
   use std::borrow::Cow;
   fn match_tree(p: &str, n: &str, strictness: MatchStrictness) -> MatchOneNode {
     let pattern = Pattern::new(p, Tsx);
@@ -254,7 +257,12 @@ mod test {
     let n = Root::str(n, Tsx);
     let n = n.root().find(kind).expect("should find");
     let mut env = Cow::Owned(MetaVarEnv::new());
-    match_node_impl(&pattern.node, &*n, &mut env, &strictness)
+    match_node_impl(
+      &pattern.node,
+      &*n,
+      &mut env,
+      &Strictness::new(&strictness, None),
+    )
   }
   fn matched(p: &str, n: &str, strictness: MatchStrictness) {
     let ret = match_tree(p, n, strictness);
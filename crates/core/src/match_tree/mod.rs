@@ -1,9 +1,13 @@
+mod explain;
 mod match_node;
 mod strictness;
 
+pub(crate) use explain::explain_node;
+pub use explain::{Explanation, Mismatch};
 use match_node::match_node_impl;
 use strictness::MatchOneNode;
 pub use strictness::MatchStrictness;
+pub(crate) use strictness::Strictness;
 
 use crate::meta_var::{MetaVarEnv, MetaVariable};
 use crate::{Doc, Node, Pattern};
@@ -46,7 +50,7 @@ impl<'t, D: Doc> Aggregator<'t, D> for ComputeEnd {
 
 pub fn match_end_non_recursive(goal: &Pattern, candidate: Node<impl Doc>) -> Option<usize> {
   let mut end = ComputeEnd(0);
-  match match_node_impl(&goal.node, &candidate, &mut end, &goal.strictness) {
+  match match_node_impl(&goal.node, &candidate, &mut end, &goal.match_strictness()) {
     MatchOneNode::MatchedBoth => Some(end.0),
     _ => None,
   }
@@ -114,7 +118,7 @@ pub fn match_node_non_recursive<'tree, D: Doc>(
   candidate: Node<'tree, D>,
   env: &mut Cow<MetaVarEnv<'tree, D>>,
 ) -> Option<Node<'tree, D>> {
-  match match_node_impl(&goal.node, &candidate, env, &goal.strictness) {
+  match match_node_impl(&goal.node, &candidate, env, &goal.match_strictness()) {
     MatchOneNode::MatchedBoth => Some(candidate),
     _ => None,
   }
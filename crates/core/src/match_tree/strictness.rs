@@ -2,6 +2,7 @@ use crate::matcher::{kind_utils, PatternNode};
 use crate::meta_var::MetaVariable;
 use crate::node::Node;
 use crate::Doc;
+use std::collections::HashSet;
 use std::iter::Peekable;
 use std::str::FromStr;
 
@@ -122,36 +123,117 @@ impl MatchStrictness {
     }
   }
 
-  pub(crate) fn should_skip_goal<'p>(
-    &self,
-    goal_children: &mut Peekable<impl Iterator<Item = &'p PatternNode>>,
-  ) -> bool {
+  fn should_skip_trailing_goal(&self, pattern: &PatternNode) -> bool {
     use MatchStrictness as M;
-    while let Some(pattern) = goal_children.peek() {
-      let skipped = match self {
-        M::Cst => false,
-        M::Smart | M::Template => match pattern {
-          PatternNode::MetaVar { meta_var } => match meta_var {
-            MetaVariable::Multiple => true,
-            MetaVariable::MultiCapture(_) => true,
-            MetaVariable::Dropped(_) => false,
-            MetaVariable::Capture(..) => false,
-          },
-          PatternNode::Terminal { .. } => false,
-          PatternNode::Internal { .. } => false,
+    match self {
+      M::Cst => false,
+      M::Smart | M::Template => match pattern {
+        PatternNode::MetaVar { meta_var } => match meta_var {
+          MetaVariable::Multiple => true,
+          MetaVariable::MultiCapture(_) => true,
+          MetaVariable::Dropped(_) => false,
+          MetaVariable::Capture(..) => false,
         },
-        M::Ast | M::Relaxed | M::Signature => match pattern {
-          PatternNode::MetaVar { meta_var } => match meta_var {
-            MetaVariable::Multiple => true,
-            MetaVariable::MultiCapture(_) => true,
-            MetaVariable::Dropped(named) => !named,
-            MetaVariable::Capture(_, named) => !named,
-          },
-          PatternNode::Terminal { is_named, .. } => !is_named,
-          PatternNode::Internal { .. } => false,
+        PatternNode::Terminal { .. } => false,
+        PatternNode::Internal { .. } => false,
+      },
+      M::Ast | M::Relaxed | M::Signature => match pattern {
+        PatternNode::MetaVar { meta_var } => match meta_var {
+          MetaVariable::Multiple => true,
+          MetaVariable::MultiCapture(_) => true,
+          MetaVariable::Dropped(named) => !named,
+          MetaVariable::Capture(_, named) => !named,
         },
+        PatternNode::Terminal { is_named, .. } => !is_named,
+        PatternNode::Internal { .. } => false,
+      },
+    }
+  }
+}
+
+/// The strictness a pattern is matched with: its level, and the node kinds it also skips
+/// like `relaxed` skips comments, see `Pattern::with_skipped_kinds`.
+#[derive(Clone, Copy)]
+pub(crate) struct Strictness<'p> {
+  level: &'p MatchStrictness,
+  /// Kind names, so unnamed tokens like `;` can be skipped too. Unnamed tokens of the
+  /// pattern are skipped if their text is listed.
+  skipped_kinds: Option<&'p HashSet<String>>,
+}
+
+impl<'p> Strictness<'p> {
+  pub(crate) fn new(
+    level: &'p MatchStrictness,
+    skipped_kinds: Option<&'p HashSet<String>>,
+  ) -> Self {
+    Self {
+      level,
+      skipped_kinds,
+    }
+  }
+
+  fn skips_candidate(&self, n: &Node<impl Doc>) -> bool {
+    self
+      .skipped_kinds
+      .is_some_and(|kinds| kinds.contains(&*n.kind()))
+  }
+
+  fn skips_goal(&self, is_named: bool, text: &str) -> bool {
+    !is_named && self.skipped_kinds.is_some_and(|kinds| kinds.contains(text))
+  }
+
+  pub(crate) fn should_skip_kind(&self) -> bool {
+    self.level.should_skip_kind()
+  }
+
+  pub(crate) fn match_terminal(
+    &self,
+    is_named: bool,
+    text: &str,
+    goal_kind: u16,
+    candidate: &Node<impl Doc>,
+  ) -> MatchOneNode {
+    let level = self
+      .level
+      .match_terminal(is_named, text, goal_kind, candidate);
+    if matches!(level, MatchOneNode::MatchedBoth) {
+      return level;
+    }
+    // skipped kinds take precedence, the level may skip a candidate the goal matches
+    match (
+      self.skips_goal(is_named, text),
+      self.skips_candidate(candidate),
+    ) {
+      (true, true) => MatchOneNode::SkipBoth,
+      (true, false) => MatchOneNode::SkipGoal,
+      (false, true) => MatchOneNode::SkipCandidate,
+      (false, false) => level,
+    }
+  }
+
+  pub(crate) fn should_skip_cand_for_metavar<D: Doc>(&self, candidate: &Node<D>) -> bool {
+    self.skips_candidate(candidate) || self.level.should_skip_cand_for_metavar(candidate)
+  }
+
+  /// Whether an internal `candidate` of another kind than the goal can be skipped.
+  pub(crate) fn should_skip_internal<D: Doc>(&self, candidate: &Node<D>) -> bool {
+    self.skips_candidate(candidate)
+  }
+
+  pub(crate) fn should_skip_trailing<D: Doc>(&self, candidate: &Node<D>) -> bool {
+    self.skips_candidate(candidate) || self.level.should_skip_trailing(candidate)
+  }
+
+  pub(crate) fn should_skip_goal<'g>(
+    &self,
+    goal_children: &mut Peekable<impl Iterator<Item = &'g PatternNode>>,
+  ) -> bool {
+    while let Some(pattern) = goal_children.peek() {
+      let skipped = match pattern {
+        PatternNode::Terminal { is_named, text, .. } => self.skips_goal(*is_named, text),
+        _ => false,
       };
-      if !skipped {
+      if !skipped && !self.level.should_skip_trailing_goal(pattern) {
         return false;
       }
       goal_children.next();
@@ -230,4 +312,38 @@ mod test {
   fn test_ast_trailing_comma() {
     assert!(test_match("foo(bar)", "foo(bar,)", MatchStrictness::Ast));
   }
+
+  fn skip_match(p: &str, n: &str, strictness: MatchStrictness, kinds: &[&str]) -> bool {
+    let pattern = Pattern::new(p, Tsx)
+      .with_strictness(strictness)
+      .with_skipped_kinds(kinds.iter().copied());
+    let root = Root::str(n, Tsx);
+    let node = root.root();
+    node.find(pattern).is_some()
+  }
+
+  #[test]
+  fn test_custom_skipped_kinds() {
+    let skip = |p, n, kinds| skip_match(p, n, MatchStrictness::Smart, kinds);
+    assert!(!test_match("foo(bar)", "foo(bar,)", MatchStrictness::Cst));
+    assert!(skip_match(
+      "foo(bar)",
+      "foo(bar,)",
+      MatchStrictness::Cst,
+      &[","]
+    ));
+    // unnamed tokens of the pattern are skipped too
+    assert!(!skip("foo(bar,)", "foo(bar)", &[]));
+    assert!(skip("foo(bar,)", "foo(bar)", &[","]));
+    assert!(!skip("foo($A)", "foo(/* c */ 1)", &[]));
+    assert!(skip("foo($A)", "foo(/* c */ 1)", &["comment"]));
+    assert!(skip(
+      "class A { b() {} }",
+      "class A { @dec b() {} }",
+      &["decorator"]
+    ));
+    // the level still applies
+    let relaxed = MatchStrictness::Relaxed;
+    assert!(skip_match("foo(bar)", "foo(/* c */ bar,)", relaxed, &[","]));
+  }
 }
@@ -16,7 +16,7 @@ use crate::{meta_var::MetaVarEnv, Node};
 use bit_set::BitSet;
 use std::borrow::Cow;
 
-pub use kind::{kind_utils, KindMatcher, KindMatcherError};
+pub use kind::{kind_utils, KindMatcher, KindMatcherError, KindRegexMatcher};
 pub use node_match::NodeMatch;
 pub use pattern::{DumpPattern, Pattern, PatternBuilder, PatternError, PatternNode};
 pub use text::{RegexMatcher, RegexMatcherError};
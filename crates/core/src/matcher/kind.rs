@@ -1,4 +1,4 @@
-use super::Matcher;
+use super::{Matcher, RegexMatcherError};
 
 use crate::language::Language;
 use crate::meta_var::MetaVarEnv;
@@ -8,6 +8,7 @@ use crate::{Doc, Node};
 use std::borrow::Cow;
 
 use bit_set::BitSet;
+use regex::Regex;
 use thiserror::Error;
 
 // 0 is symbol_end for not found, 65535 is builtin symbol ERROR
@@ -58,6 +59,47 @@ impl KindMatcher {
   }
 }
 
+/// Matches nodes whose kind name matches a regex, like every `_statement` kind of a grammar.
+/// The regex is resolved against the language's kind names to prefilter nodes by kind.
+#[derive(Clone)]
+pub struct KindRegexMatcher {
+  regex: Regex,
+  kinds: Option<BitSet>,
+}
+
+impl KindRegexMatcher {
+  pub fn try_new<L: Language>(regex: &str, lang: L) -> Result<Self, RegexMatcherError> {
+    let regex = Regex::new(regex)?;
+    let kinds = lang.kind_names().map(|names| {
+      let mut kinds: BitSet = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| regex.is_match(name))
+        .map(|(id, _)| id)
+        .collect();
+      if regex.is_match("ERROR") {
+        kinds.insert(TS_BUILTIN_SYM_ERROR.into());
+      }
+      kinds
+    });
+    Ok(Self { regex, kinds })
+  }
+}
+
+impl Matcher for KindRegexMatcher {
+  fn match_node_with_env<'tree, D: Doc>(
+    &self,
+    node: Node<'tree, D>,
+    _env: &mut Cow<MetaVarEnv<'tree, D>>,
+  ) -> Option<Node<'tree, D>> {
+    self.regex.is_match(&node.kind()).then_some(node)
+  }
+
+  fn potential_kinds(&self) -> Option<BitSet> {
+    self.kinds.clone()
+  }
+}
+
 pub mod kind_utils {
   use super::*;
 
@@ -142,4 +184,23 @@ mod test {
     // should has exactly one potential kind
     assert_eq!(potential_kinds.len(), 1);
   }
+
+  #[test]
+  fn test_kind_regex_match() {
+    let cand = pattern_node("if (a) { return 1 }");
+    let matcher = KindRegexMatcher::try_new("_statement$", Tsx).expect("should parse");
+    let kinds: Vec<_> = cand
+      .root()
+      .find_all(&matcher)
+      .map(|n| n.kind().to_string())
+      .collect();
+    assert_eq!(kinds, ["if_statement", "return_statement"]);
+    let potential_kinds = matcher.potential_kinds().expect("should have kinds");
+    assert!(potential_kinds.contains(Tsx.kind_to_id("if_statement").into()));
+    assert!(!potential_kinds.contains(Tsx.kind_to_id("identifier").into()));
+    let error = KindRegexMatcher::try_new("^ERROR$", Tsx).expect("should parse");
+    let error_kinds = error.potential_kinds().expect("should have kinds");
+    assert!(error_kinds.contains(TS_BUILTIN_SYM_ERROR.into()));
+    assert!(KindRegexMatcher::try_new("(", Tsx).is_err());
+  }
 }
@@ -1,6 +1,9 @@
 use crate::language::Language;
-use crate::match_tree::{match_end_non_recursive, match_node_non_recursive, MatchStrictness};
-use crate::matcher::{kind_utils, KindMatcher, KindMatcherError, Matcher};
+use crate::match_tree::{
+  explain_node, match_end_non_recursive, match_node_non_recursive, Explanation, MatchStrictness,
+  Strictness,
+};
+use crate::matcher::{kind_utils, KindMatcher, KindMatcherError, Matcher, MatcherExt};
 use crate::meta_var::{MetaVarEnv, MetaVariable};
 use crate::source::SgNode;
 use crate::{Doc, Node, Root};
@@ -10,17 +13,24 @@ use thiserror::Error;
 
 use std::borrow::Cow;
 use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Pattern {
   pub node: PatternNode,
   root_kind: Option<u16>,
   pub strictness: MatchStrictness,
+  /// Kinds of nodes skipped on top of `strictness`, see `with_skipped_kinds`.
+  skipped_kinds: Option<Arc<HashSet<String>>>,
 }
 
 pub struct PatternBuilder<'a> {
   selector: Option<&'a str>,
   src: Cow<'a, str>,
+  /// The kind the pattern is parsed in and the pattern's byte range in `src`,
+  /// see `Pattern::parse_as`.
+  parse_as: Option<(&'a str, Range<usize>)>,
 }
 
 impl PatternBuilder<'_> {
@@ -33,6 +43,8 @@ impl PatternBuilder<'_> {
     let root = Root::doc(doc);
     if let Some(selector) = self.selector {
       self.contextual(&root, selector)
+    } else if let Some((kind, range)) = &self.parse_as {
+      self.wrapped(&root, kind, range)
     } else {
       self.single(&root)
     }
@@ -52,7 +64,17 @@ impl PatternBuilder<'_> {
   fn contextual<D: Doc>(&self, root: &Root<D>, selector: &str) -> Result<Pattern, PatternError> {
     let goal = root.root();
     let kind_matcher = KindMatcher::try_new(selector, root.lang().clone())?;
-    let Some(node) = goal.find(&kind_matcher) else {
+    // with `parse_as`, only the pattern is searched, not the code around it
+    let in_pattern = |node: &Node<D>| {
+      self.parse_as.as_ref().map_or(true, |(_, range)| {
+        let node_range = node.range();
+        range.start <= node_range.start && node_range.end <= range.end
+      })
+    };
+    let found = goal
+      .dfs()
+      .find(|node| in_pattern(node) && kind_matcher.match_node(node.clone()).is_some());
+    let Some(node) = found else {
       return Err(PatternError::NoSelectorInContext {
         context: self.src.to_string(),
         selector: selector.into(),
@@ -60,10 +82,31 @@ impl PatternBuilder<'_> {
     };
     Ok(Pattern {
       root_kind: Some(node.kind_id()),
-      node: convert_node_to_pattern(node.get_node().clone()),
+      node: convert_node_to_pattern(node),
       strictness: MatchStrictness::Smart,
+      skipped_kinds: None,
     })
   }
+
+  /// The outermost node spanning exactly `range`, unwrapped like a single pattern.
+  fn wrapped<D: Doc>(
+    &self,
+    root: &Root<D>,
+    kind: &str,
+    range: &Range<usize>,
+  ) -> Result<Pattern, PatternError> {
+    let Some(node) = root.root().dfs().find(|node| node.range() == *range) else {
+      return Err(PatternError::NotParsedAs {
+        pattern: self.src[range.clone()].to_string(),
+        kind: kind.into(),
+      });
+    };
+    let mut inner = node.inner;
+    while is_single_node(&inner) {
+      inner = inner.child(0).unwrap();
+    }
+    Ok(Pattern::from(Node { inner, root }))
+  }
 }
 
 pub struct DumpPattern<'p> {
@@ -194,6 +237,30 @@ impl PatternNode {
       }
     }
   }
+
+  /// The longest text of a named terminal, unlike unnamed ones it is never skipped.
+  fn named_literal(&self) -> &str {
+    match &self {
+      PatternNode::Terminal {
+        text,
+        is_named: true,
+        ..
+      } => text,
+      PatternNode::Terminal { .. } | PatternNode::MetaVar { .. } => "",
+      PatternNode::Internal { children, .. } => {
+        children
+          .iter()
+          .map(|n| n.named_literal())
+          .fold("", |longest, curr| {
+            if longest.len() >= curr.len() {
+              longest
+            } else {
+              curr
+            }
+          })
+      }
+    }
+  }
 }
 impl<'r, D: Doc> From<Node<'r, D>> for PatternNode {
   fn from(node: Node<'r, D>) -> Self {
@@ -207,6 +274,7 @@ impl<'r, D: Doc> From<Node<'r, D>> for Pattern {
       node: convert_node_to_pattern(node),
       root_kind: None,
       strictness: MatchStrictness::Smart,
+      skipped_kinds: None,
     }
   }
 }
@@ -252,6 +320,10 @@ pub enum PatternError {
   InvalidKind(#[from] KindMatcherError),
   #[error("Fails to create Contextual pattern: selector `{selector}` matches no node in the context `{context}`.")]
   NoSelectorInContext { context: String, selector: String },
+  #[error("Patterns cannot be parsed as `{0}` in this language.")]
+  UnknownParseAs(String),
+  #[error("The pattern `{pattern}` is not a single AST node when parsed as `{kind}`.")]
+  NotParsedAs { pattern: String, kind: String },
 }
 
 #[inline]
@@ -290,6 +362,16 @@ impl Pattern {
     self.node.fixed_string()
   }
 
+  /// A text every node matching the pattern contains, used to skip source without it.
+  /// `None` if no text is required, e.g. the `signature` strictness ignores texts.
+  pub fn required_literal(&self) -> Option<&str> {
+    if matches!(&self.strictness, MatchStrictness::Signature) {
+      return None;
+    }
+    let literal = self.node.named_literal();
+    (!literal.is_empty()).then_some(literal)
+  }
+
   /// Get all defined variables in the pattern.
   /// Used for validating rules and report undefined variables.
   pub fn defined_vars(&self) -> HashSet<&str> {
@@ -333,6 +415,29 @@ impl Pattern {
     let builder = PatternBuilder {
       selector: None,
       src: processed,
+      parse_as: None,
+    };
+    lang.build_pattern(&builder)
+  }
+
+  /// Parse `src` inside a node of `kind`, for code that only parses in some constructs,
+  /// like a `case` that needs a `switch`. The code around it is the language's
+  /// `parse_as_context`, and `selector` picks a node of the pattern like in `contextual`.
+  pub fn parse_as<L: Language>(
+    src: &str,
+    kind: &str,
+    selector: Option<&str>,
+    lang: L,
+  ) -> Result<Self, PatternError> {
+    let Some((before, after)) = lang.parse_as_context(kind) else {
+      return Err(PatternError::UnknownParseAs(kind.into()));
+    };
+    let processed = lang.pre_process_pattern(src.trim());
+    let start = before.len();
+    let builder = PatternBuilder {
+      selector,
+      src: format!("{before}{processed}{after}").into(),
+      parse_as: Some((kind, start..start + processed.len())),
     };
     lang.build_pattern(&builder)
   }
@@ -341,11 +446,31 @@ impl Pattern {
     Self::try_new(src, lang).unwrap()
   }
 
+  /// Explain why the pattern does or does not match `node` itself.
+  /// The pattern is walked like in matching, and the first node where matching fails
+  /// is reported together with the nodes the strictness skipped on the way.
+  pub fn explain<'p, 't, D: Doc>(&'p self, node: Node<'t, D>) -> Explanation<'p, 't, D> {
+    explain_node(self, self.root_kind, node)
+  }
+
   pub fn with_strictness(mut self, strictness: MatchStrictness) -> Self {
     self.strictness = strictness;
     self
   }
 
+  /// Also skip nodes of `kinds` when matching, the way `relaxed` skips comments.
+  /// Kinds are node kind names, so unnamed tokens like `;` can be skipped too,
+  /// and unnamed tokens of the pattern are skipped if their text is listed.
+  pub fn with_skipped_kinds<S: Into<String>>(mut self, kinds: impl IntoIterator<Item = S>) -> Self {
+    let kinds: HashSet<_> = kinds.into_iter().map(Into::into).collect();
+    self.skipped_kinds = (!kinds.is_empty()).then(|| Arc::new(kinds));
+    self
+  }
+
+  pub(crate) fn match_strictness(&self) -> Strictness<'_> {
+    Strictness::new(&self.strictness, self.skipped_kinds.as_deref())
+  }
+
   pub fn contextual<L: Language>(
     context: &str,
     selector: &str,
@@ -355,6 +480,7 @@ impl Pattern {
     let builder = PatternBuilder {
       selector: Some(selector),
       src: processed,
+      parse_as: None,
     };
     lang.build_pattern(&builder)
   }
@@ -540,6 +666,33 @@ mod test {
     assert!(pattern.find_node(cand.root()).is_none());
   }
 
+  #[test]
+  fn test_parse_as_pattern() {
+    let pattern = Pattern::parse_as("case $A: $$$B", "switch_body", None, Tsx).expect("test");
+    let cand = pattern_node("switch (x) { case 1: a(); break }");
+    let nm = pattern.find_node(cand.root()).expect("test");
+    assert_eq!(nm.kind(), "switch_case");
+    assert_eq!(nm.get_env().get_match("A").expect("test").text(), "1");
+    let pattern = Pattern::parse_as("$F = $I", "class_body", None, Tsx).expect("test");
+    assert!(pattern
+      .find_node(pattern_node("class B { b = 1 }").root())
+      .is_some());
+    assert!(pattern.find_node(pattern_node("b = 1").root()).is_none());
+    let selected = Pattern::parse_as("a = $I", "class_body", Some("number"), Tsx);
+    assert!(matches!(
+      selected,
+      Err(PatternError::NoSelectorInContext { .. })
+    ));
+    assert!(matches!(
+      Pattern::parse_as("a", "statement_block", None, Tsx),
+      Err(PatternError::UnknownParseAs(_))
+    ));
+    assert!(matches!(
+      Pattern::parse_as("a = 1; b = 2", "class_body", None, Tsx),
+      Err(PatternError::NotParsedAs { .. })
+    ));
+  }
+
   #[test]
   fn test_contextual_match_with_env() {
     let pattern =
@@ -657,6 +810,19 @@ mod test {
     assert!(pattern.fixed_string().is_empty());
   }
 
+  #[test]
+  fn test_pattern_required_literal() {
+    let pattern = Pattern::new("class A { $F = 123 }", Tsx);
+    assert_eq!(pattern.required_literal(), Some("123"));
+    // the unnamed `===` can be skipped by the ast strictness
+    let pattern = Pattern::new("a === $B", Tsx).with_strictness(MatchStrictness::Ast);
+    assert_eq!(pattern.required_literal(), Some("a"));
+    let pattern = Pattern::new("$A($$$)", Tsx);
+    assert_eq!(pattern.required_literal(), None);
+    let pattern = Pattern::new("foo($A)", Tsx).with_strictness(MatchStrictness::Signature);
+    assert_eq!(pattern.required_literal(), None);
+  }
+
   #[test]
   fn test_pattern_error() {
     let pattern = Pattern::try_new("", Tsx);
@@ -124,6 +124,9 @@ fn get_new_line<C: Content>() -> C::Underlying {
 fn get_space<C: Content>() -> C::Underlying {
   C::decode_str(" ")[0].clone()
 }
+fn is_indent<C: Content>(c: &C::Underlying) -> bool {
+  *c == get_space::<C>() || *c == C::decode_str("\t")[0]
+}
 
 const MAX_LOOK_AHEAD: usize = 512;
 
@@ -185,21 +188,55 @@ pub fn indent_lines<C: Content>(
     // need strip old indent
     Ordering::Greater => Cow::Owned(remove_indent::<C>(original_indent - indent, lines)),
     // need add missing indent
-    Ordering::Less => Cow::Owned(indent_lines_impl::<C, _>(
-      indent - original_indent,
-      lines.split(|b| *b == get_new_line::<C>()),
-    )),
+    Ordering::Less => {
+      let leading: Vec<_> = std::iter::repeat(get_space::<C>())
+        .take(indent - original_indent)
+        .collect();
+      Cow::Owned(indent_lines_impl::<C, _>(
+        &leading,
+        lines.split(|b| *b == get_new_line::<C>()),
+      ))
+    }
   }
 }
 
-fn indent_lines_impl<'a, C, Lines>(indent: usize, mut lines: Lines) -> Vec<C::Underlying>
+/// Like `indent_lines`, but indent with `prefix`, the indentation of the line the lines
+/// are inserted at, which can have tabs unlike the indentation `indent_lines` adds.
+/// Lines indented with tabs lose their original indentation before getting the prefix.
+pub fn indent_lines_with<'a, C: Content>(
+  prefix: &[C::Underlying],
+  extract: DeindentedExtract<'a, C>,
+) -> Cow<'a, [C::Underlying]> {
+  let (lines, original_indent) = match extract {
+    DeindentedExtract::SingleLine(line) => return Cow::Borrowed(line),
+    DeindentedExtract::MultiLine(lines, ind) => (lines, ind),
+  };
+  let space = get_space::<C>();
+  let new_line = get_new_line::<C>();
+  let spaced = |line: &[C::Underlying]| line.iter().take(original_indent).all(|c| *c == space);
+  if prefix.iter().all(|c| *c == space) && lines.split(|b| *b == new_line).skip(1).all(spaced) {
+    return indent_lines(prefix.len(), extract);
+  }
+  // the original indentation can be of other characters than the prefix
+  let lines = match original_indent {
+    0 => Cow::Borrowed(lines),
+    _ => Cow::Owned(remove_indent::<C>(original_indent, lines)),
+  };
+  Cow::Owned(indent_lines_impl::<C, _>(
+    prefix,
+    lines.split(|b| *b == new_line),
+  ))
+}
+
+fn indent_lines_impl<'a, C, Lines>(
+  leading: &[C::Underlying],
+  mut lines: Lines,
+) -> Vec<C::Underlying>
 where
   C: Content + 'a,
   Lines: Iterator<Item = &'a [C::Underlying]>,
 {
   let mut ret = vec![];
-  let space = get_space::<C>();
-  let leading: Vec<_> = std::iter::repeat(space).take(indent).collect();
   // first line never got indent
   if let Some(line) = lines.next() {
     ret.extend(line.iter().cloned());
@@ -207,7 +244,7 @@ where
   let new_line = get_new_line::<C>();
   for line in lines {
     ret.push(new_line.clone());
-    ret.extend(leading.clone());
+    ret.extend(leading.iter().cloned());
     ret.extend(line.iter().cloned());
   }
   ret
@@ -216,40 +253,38 @@ where
 /// returns 0 if no indent is found before the offset
 /// either truly no indent exists, or the offset is in a long line
 pub fn get_indent_at_offset<C: Content>(src: &[C::Underlying]) -> usize {
-  let lookahead = src.len().max(MAX_LOOK_AHEAD) - MAX_LOOK_AHEAD;
+  get_indent_prefix::<C>(src).len()
+}
 
-  let mut indent = 0;
+/// The leading spaces and tabs of the line `src` ends in,
+/// empty if the line is too long to look for its start.
+pub fn get_indent_prefix<C: Content>(src: &[C::Underlying]) -> &[C::Underlying] {
+  let lookahead = src.len().max(MAX_LOOK_AHEAD) - MAX_LOOK_AHEAD;
   let new_line = get_new_line::<C>();
-  let space = get_space::<C>();
-  // TODO: support TAB. only whitespace is supported now
-  for c in src[lookahead..].iter().rev() {
-    if *c == new_line {
-      return indent;
-    }
-    if *c == space {
-      indent += 1;
-    } else {
-      indent = 0;
-    }
-  }
-  // lookahead == 0 means we have indentation at first line.
-  if lookahead == 0 && indent != 0 {
-    indent
-  } else {
-    0
-  }
+  let line_start = match src[lookahead..].iter().rposition(|c| *c == new_line) {
+    Some(i) => lookahead + i + 1,
+    // lookahead == 0 means we have indentation at first line.
+    None if lookahead == 0 => 0,
+    None => return &[],
+  };
+  let line = &src[line_start..];
+  let len = line.iter().take_while(|c| is_indent::<C>(c)).count();
+  &line[..len]
 }
 
 // NOTE: we assume input is well indented.
 // following line's should have fewer indentation than initial line
 fn remove_indent<C: Content>(indent: usize, src: &[C::Underlying]) -> Vec<C::Underlying> {
-  let indentation: Vec<_> = std::iter::repeat(get_space::<C>()).take(indent).collect();
   let new_line = get_new_line::<C>();
   let lines: Vec<_> = src
     .split(|b| *b == new_line)
-    .map(|line| match line.strip_prefix(&*indentation) {
-      Some(stripped) => stripped,
-      None => line,
+    .map(|line| {
+      let leading = line.iter().take(indent).take_while(|c| is_indent::<C>(c));
+      if leading.count() == indent {
+        &line[indent..]
+      } else {
+        line
+      }
     })
     .collect();
   lines.join(&new_line).to_vec()
@@ -420,4 +455,32 @@ pass
     let actual = test_replace_with_indent(target, 6, inserted);
     assert_eq!(actual, "def abc():\n    pass");
   }
+
+  fn test_replace_with_prefix(target: &str, start: usize, inserted: &str) -> String {
+    let replace_lines = DeindentedExtract::MultiLine(inserted.as_bytes(), 0);
+    let prefix = get_indent_prefix::<String>(&target.as_bytes()[..start]);
+    let ret = indent_lines_with::<String>(prefix, replace_lines);
+    String::from_utf8(ret.to_vec()).unwrap()
+  }
+
+  #[test]
+  fn test_tab_indent_replace() {
+    let inserted = "def abc():\n  pass";
+    let actual = test_replace_with_prefix("\t\ta = ", 6, inserted);
+    assert_eq!(actual, "def abc():\n\t\t  pass");
+    let actual = test_replace_with_prefix("b\n\t", 3, inserted);
+    assert_eq!(actual, "def abc():\n\t  pass");
+    // spaces only are the same as indent_lines
+    let actual = test_replace_with_prefix("  ", 2, inserted);
+    assert_eq!(actual, "def abc():\n    pass");
+  }
+
+  #[test]
+  fn test_tab_deindent() {
+    let source = "  if a:\n  \tpass".to_string();
+    let extracted = extract_with_deindent(&source, 2..source.len());
+    let ret = indent_lines_with::<String>(b"\t", extracted);
+    assert_eq!(String::from_utf8(ret.to_vec()).unwrap(), "if a:\n\t\tpass");
+    assert_eq!(get_indent_at_offset::<String>(b"\t  a"), 3);
+  }
 }
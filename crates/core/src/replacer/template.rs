@@ -1,4 +1,6 @@
-use super::indent::{extract_with_deindent, get_indent_at_offset, indent_lines, DeindentedExtract};
+use super::indent::{
+  extract_with_deindent, get_indent_prefix, indent_lines_with, DeindentedExtract,
+};
 use super::{split_first_meta_var, MetaVarExtract, Replacer};
 use crate::language::Language;
 use crate::meta_var::{MetaVarEnv, Underlying};
@@ -28,6 +30,12 @@ impl TemplateFix {
     create_template(tpl, lang.meta_var_char(), trans)
   }
 
+  /// The replacement with the lines of the template as written, not indented like the
+  /// line of the matched node. Multi-line meta variables are still indented in the template.
+  pub fn generate_as_written<D: Doc>(&self, nm: &NodeMatch<'_, D>) -> Underlying<D> {
+    replace_fixer(self, nm.get_env())
+  }
+
   pub fn used_vars(&self) -> HashSet<&str> {
     let template = match self {
       TemplateFix::WithMetaVar(t) => t,
@@ -40,14 +48,15 @@ impl TemplateFix {
 impl<D: Doc> Replacer<D> for TemplateFix {
   fn generate_replacement(&self, nm: &NodeMatch<'_, D>) -> Underlying<D> {
     let leading = nm.get_doc().get_source().get_range(0..nm.range().start);
-    let indent = get_indent_at_offset::<D::Source>(leading);
+    let indent = get_indent_prefix::<D::Source>(leading);
     let bytes = replace_fixer(self, nm.get_env());
     let replaced = DeindentedExtract::MultiLine(&bytes, 0);
-    indent_lines::<D::Source>(indent, replaced).to_vec()
+    indent_lines_with::<D::Source>(indent, replaced).to_vec()
   }
 }
 
-type Indent = usize;
+/// The leading spaces and tabs of the line a meta variable is at in the template.
+type Indent = String;
 
 pub struct Template {
   fragments: Vec<String>,
@@ -65,8 +74,8 @@ fn create_template(tmpl: &str, mv_char: char, transforms: &[String]) -> Template
     {
       fragments.push(tmpl[len..len + offset + i].to_string());
       // NB we have to count ident of the full string
-      let indent = get_indent_at_offset::<String>(&tmpl.as_bytes()[..len + offset + i]);
-      vars.push((meta_var, indent));
+      let indent = get_indent_prefix::<String>(&tmpl.as_bytes()[..len + offset + i]);
+      vars.push((meta_var, String::from_utf8_lossy(indent).into_owned()));
       len += skipped + offset + i;
       offset = 0;
       continue;
@@ -108,7 +117,7 @@ fn replace_fixer<D: Doc>(fixer: &TemplateFix, env: &MetaVarEnv<'_, D>) -> Underl
 fn maybe_get_var<'e, 't, C, D>(
   env: &'e MetaVarEnv<'t, D>,
   var: &MetaVarExtract,
-  indent: &usize,
+  indent: &str,
 ) -> Option<Cow<'e, [C::Underlying]>>
 where
   C: Content + 'e,
@@ -119,7 +128,7 @@ where
       // transformed source does not have range, directly return bytes
       let source = env.get_transformed(name)?;
       let de_intended = DeindentedExtract::MultiLine(source, 0);
-      let bytes = indent_lines::<D::Source>(*indent, de_intended);
+      let bytes = indent_lines_with::<D::Source>(&C::decode_str(indent), de_intended);
       return Some(bytes);
     }
     MetaVarExtract::Single(name) => {
@@ -143,7 +152,7 @@ where
     }
   };
   let extracted = extract_with_deindent(source, range);
-  let bytes = indent_lines::<D::Source>(*indent, extracted);
+  let bytes = indent_lines_with::<D::Source>(&C::decode_str(indent), extracted);
   Some(bytes)
 }
 
@@ -352,6 +361,22 @@ if (true) {
     assert_eq!("if (a)\n  if (b)\n    foo", ret);
   }
 
+  #[test]
+  fn test_tab_indented_replace() {
+    let tf = TemplateFix::try_new("if (a) {\n  $B\n}", &Tsx).expect("ok");
+    let grep = Tsx.ast_grep("function f() {\n\tfoo(\n\t\t1\n\t)\n}");
+    let node = grep.root().find("foo($$$)").expect("should find");
+    let mut env = MetaVarEnv::new();
+    env.insert("B", node.get_node().clone());
+    let node_match = NodeMatch::new(node.get_node().clone(), env);
+    let bytes = tf.generate_replacement(&node_match);
+    let ret = String::from_utf8(bytes).expect("replacement must be valid utf-8");
+    assert_eq!(ret, "if (a) {\n\t  foo(\n\t  \t1\n\t  )\n\t}");
+    let bytes = tf.generate_as_written(&node_match);
+    let ret = String::from_utf8(bytes).expect("replacement must be valid utf-8");
+    assert_eq!(ret, "if (a) {\n  foo(\n  \t1\n  )\n}");
+  }
+
   #[test]
   fn test_nested_matching_replace() {
     // TODO impossible, we don't support nested replacement
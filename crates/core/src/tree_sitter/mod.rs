@@ -255,6 +255,14 @@ impl<'r> SgNode<'r> for Node<'r> {
   }
 }
 
+/// The node kind names of a tree-sitter language, indexed by kind id.
+/// See `Language::kind_names`.
+pub fn kind_names(lang: &TSLanguage) -> Vec<String> {
+  (0..lang.node_kind_count() as KindId)
+    .map(|id| lang.node_kind_for_id(id).unwrap_or_default().to_string())
+    .collect()
+}
+
 pub fn perform_edit<S: ContentExt>(tree: &mut Tree, input: &mut S, edit: &Edit<S>) -> InputEdit {
   let edit = input.accept_edit(edit);
   tree.edit(&edit);
@@ -233,6 +233,10 @@ impl Language for DynamicLang {
     self.expando
   }
 
+  fn kind_names(&self) -> Option<Vec<String>> {
+    Some(ast_grep_core::tree_sitter::kind_names(&self.inner().lang))
+  }
+
   fn kind_to_id(&self, kind: &str) -> u16 {
     let inner = self.inner();
     inner.lang.id_for_node_kind(kind, true)
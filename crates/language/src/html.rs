@@ -18,6 +18,11 @@ impl Language for Html {
   fn kind_to_id(&self, kind: &str) -> u16 {
     crate::parsers::language_html().id_for_node_kind(kind, true)
   }
+  fn kind_names(&self) -> Option<Vec<String>> {
+    Some(ast_grep_core::tree_sitter::kind_names(
+      &crate::parsers::language_html(),
+    ))
+  }
   fn field_to_id(&self, field: &str) -> Option<u16> {
     crate::parsers::language_html()
       .field_id_for_name(field)
@@ -71,6 +71,11 @@ macro_rules! impl_lang {
           .get_ts_language()
           .id_for_node_kind(kind, /*named*/ true)
       }
+      fn kind_names(&self) -> Option<Vec<String>> {
+        Some(ast_grep_core::tree_sitter::kind_names(
+          &self.get_ts_language(),
+        ))
+      }
       fn field_to_id(&self, field: &str) -> Option<u16> {
         self
           .get_ts_language()
@@ -122,6 +127,11 @@ macro_rules! impl_lang_expando {
           .get_ts_language()
           .id_for_node_kind(kind, /*named*/ true)
       }
+      fn kind_names(&self) -> Option<Vec<String>> {
+        Some(ast_grep_core::tree_sitter::kind_names(
+          &self.get_ts_language(),
+        ))
+      }
       fn field_to_id(&self, field: &str) -> Option<u16> {
         self
           .get_ts_language()
@@ -456,6 +466,7 @@ macro_rules! impl_lang_method {
 impl Language for SupportLang {
   impl_lang_method!(kind_to_id, (kind: &str) => u16);
   impl_lang_method!(field_to_id, (field: &str) => Option<u16>);
+  impl_lang_method!(kind_names, () => Option<Vec<String>>);
   impl_lang_method!(meta_var_char, () => char);
   impl_lang_method!(expando_char, () => char);
   impl_lang_method!(extract_meta_var, (source: &str) => Option<MetaVariable>);
@@ -461,7 +461,8 @@ impl<L: LSPLang> Backend<L> {
       .get_diagnostics(&uri, &versioned)
       .ok_or(LspError::NoActionableFix)?;
 
-    let mut entries: Vec<_> = fixes.iter().collect();
+    // unsafe fixes are only offered as quickfixes, to be reviewed one by one
+    let mut entries: Vec<_> = fixes.iter().filter(|(_, data)| data.safe).collect();
     entries.sort_by(|((range_a, _), _), ((range_b, _), _)| {
       range_a
         .start
@@ -22,9 +22,19 @@ pub struct OneFix {
 
 pub struct RewriteData {
   pub fixers: Vec<OneFix>,
+  /// Whether the fixes can be applied without review, e.g. by fix-all.
+  /// Rules opt out with `fixSafety: unsafe` in their metadata.
+  pub safe: bool,
   // maybe we should have fixed range
 }
 
+fn is_fix_safe<L: LanguageExt>(rule: &RuleConfig<L>) -> bool {
+  let Some(metadata) = &rule.metadata else {
+    return true;
+  };
+  metadata.get("fixSafety").and_then(|v| v.as_str()) != Some("unsafe")
+}
+
 impl RewriteData {
   pub fn from_node_match<L: LanguageExt>(
     node_match: &NodeMatch<StrDoc<L>>,
@@ -57,7 +67,10 @@ impl RewriteData {
     if fixers.is_empty() {
       None
     } else {
-      Some(Self { fixers })
+      Some(Self {
+        fixers,
+        safe: is_fix_safe(rule),
+      })
     }
   }
 }
@@ -112,6 +125,7 @@ pub fn diagnostic_to_code_action(
 
   let rewrite_data = fixes_cache.get(&(diagnostic.range, id.clone()))?;
 
+  let safe = rewrite_data.safe;
   let actions = rewrite_data
     .fixers
     .clone()
@@ -124,9 +138,12 @@ pub fn diagnostic_to_code_action(
       changes.insert(text_doc.uri.clone(), vec![text_edit]);
 
       let edit = WorkspaceEdit::new(changes);
-      let title = fixer
+      let mut title = fixer
         .title
         .unwrap_or_else(|| format!("Fix `{id}` with ast-grep"));
+      if !safe {
+        title.push_str(" (unsafe)");
+      }
       CodeAction {
         title,
         command: None,
@@ -134,7 +151,8 @@ pub fn diagnostic_to_code_action(
         edit: Some(edit),
         disabled: None,
         kind: Some(CodeActionKind::QUICKFIX),
-        is_preferred: Some(i == 0), // mark the first fix as preferred
+        // mark the first fix as preferred, unless editors should not apply it automatically
+        is_preferred: Some(i == 0 && safe),
         data: None,
       }
     });
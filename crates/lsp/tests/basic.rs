@@ -574,6 +574,83 @@ fix: |-
   assert_eq!(fixed_text, "alert('Hello, world!')\nalert('Another log')");
 }
 
+#[tokio::test]
+async fn test_code_action_skip_unsafe_fix() {
+  let yamls = r"
+id: use-alert
+language: TypeScript
+message: Use alert instead of console.log
+rule:
+  pattern: console.log($$$A)
+fix: |-
+  alert($$$A)
+---
+id: no-debugger
+language: TypeScript
+message: Remove debugger
+rule:
+  pattern: debugger
+fix: ''
+metadata:
+  fixSafety: unsafe";
+  let mut client = create_lsp_framed(yamls).await;
+
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  let file_content = "console.log('Hello, world!')\ndebugger\n";
+  send_did_open_framed(&mut client, file_uri, "typescript", file_content).await;
+  let diagnostics = &wait_for_diagnostics(&mut client)
+    .await
+    .expect("No diagnostics received")
+    .as_array()
+    .expect("Diagnostics should be an array")
+    .to_owned();
+  assert_eq!(diagnostics.len(), 2, "Expected 2 diagnostics");
+
+  // the unsafe fix is still a quickfix, but not a preferred one
+  let unsafe_diagnostic = diagnostics
+    .iter()
+    .find(|d| d["code"] == "no-debugger")
+    .expect("Expected a no-debugger diagnostic");
+  let code_action = request_code_action(&mut client, file_uri, unsafe_diagnostic).await;
+  let code_action = code_action.expect("No code action response");
+  let actions = code_action["result"]
+    .as_array()
+    .expect("Result should be an array");
+  assert_eq!(actions.len(), 1);
+  assert_eq!(
+    actions[0]["title"],
+    "Fix `no-debugger` with ast-grep (unsafe)"
+  );
+  assert_eq!(actions[0]["isPreferred"], false);
+
+  let method_call_id = allocate_method_call_id();
+  let code_action_request = serde_json::json!({
+    "jsonrpc": "2.0",
+    "id": method_call_id,
+    "method": "textDocument/codeAction",
+    "params": {
+      "range": {
+        "start": { "line": 0, "character": 0 },
+        "end": { "line": 1, "character": 8 }
+      },
+      "textDocument": { "uri": file_uri },
+      "context": {
+        "diagnostics": diagnostics,
+        "only": ["source.fixAll"]
+      }
+    }
+  });
+  client.send(code_action_request).await.unwrap();
+  let code_actions = wait_for_response(&mut client, method_call_id).await;
+  let code_action = code_actions.expect("No code action response");
+  let actions = code_action["result"]
+    .as_array()
+    .expect("Result should be an array");
+  assert_eq!(actions.len(), 1, "Expected 1 code action for fix all");
+  let fixed_text = apply_all_code_actions(file_content, actions);
+  assert_eq!(fixed_text, "alert('Hello, world!')\ndebugger");
+}
+
 // Custom LSP Codec for Content-Length framed JSON-RPC
 #[derive(Default)]
 pub struct LspCodec;
@@ -162,6 +162,14 @@ test('find by config', t => {
   })
 })
 
+test('find by kind regex', t => {
+  const sg = parse('console.log("hello world")')
+  const match = sg.root().find({
+    rule: { kind: { regex: '^member_' } },
+  })
+  t.is(match!.kind(), 'member_expression')
+})
+
 test('test find files', async t => {
   await parseMulti(['./__test__/index.spec.ts'], (err, tree) => {
     t.is(err, null)
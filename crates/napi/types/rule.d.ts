@@ -12,6 +12,13 @@ export type PatternStyle<M extends TypesMap = TypesMap> =
   | string
   | PatternObject<M>
 
+/**
+ * A kind name, or an object with field `regex` to match every kind whose name matches the regex.
+ */
+export type KindStyle<M extends TypesMap = TypesMap> =
+  | NamedKinds<M>
+  | { regex: string }
+
 export interface Relation<M extends TypesMap = TypesMap> extends Rule<M> {
   /**
    * Specify how relational rule will stop relative to the target node.
@@ -54,8 +61,10 @@ export interface Range {
 export interface Rule<M extends TypesMap = TypesMap> {
   /** A pattern string or a pattern object. */
   pattern?: PatternStyle<M>
-  /** The kind name of the node to match. You can look up code's kind names in playground. */
-  kind?: NamedKinds<M>
+  /**
+   * The kind name of the node to match. You can look up code's kind names in playground.
+   * Or an object with field `regex` to match every kind whose name matches the regex. */
+  kind?: KindStyle<M>
   /** The exact range of the node in the source code. */
   range?: Range
   /** A Rust regular expression to match the node's text. https://docs.rs/regex/latest/regex/#syntax */
@@ -82,6 +91,14 @@ export interface Rule<M extends TypesMap = TypesMap> {
    * `follows` accepts a relational rule object.
    * the target node must appear after another node matching the `follows` sub-rule. */
   follows?: Relation<M>
+  /**
+   * A Rust regular expression to match comments attached right before the target node.
+   * e.g. `@deprecated` in a JSDoc block. Blank lines end the attached comments. */
+  hasLeadingComment?: string
+  /**
+   * A Rust regular expression to match comments inside the target node,
+   * e.g. `TODO` in a function body. */
+  hasComment?: string
   // composite
   /**
    * A list of sub rules and matches a node if all of sub rules match.
@@ -10,6 +10,9 @@ class Pattern(TypedDict):
     strictness: Optional[Strictness]
     context: str
 
+class KindRegex(TypedDict):
+    regex: str
+
 class NthChild(TypedDict):
     position: int | str
     ofRule: Rule
@@ -26,7 +29,7 @@ class RangeRule(TypedDict):
 class RuleWithoutNot(TypedDict, total=False):
     # atomic rule
     pattern: str | Pattern
-    kind: str
+    kind: str | KindRegex
     regex: str
     nthChild: int | str | NthChild
     range: RangeRule
@@ -36,6 +39,8 @@ class RuleWithoutNot(TypedDict, total=False):
     has: Relation
     precedes: Relation
     follows: Relation
+    hasLeadingComment: str
+    hasComment: str
 
     # composite rule
     all: List[Rule]
@@ -75,6 +80,7 @@ __all__ = [
     "Config",
     "Relation",
     "Pattern",
+    "KindRegex",
     "NthChild",
     "SgNode",
     "SgRoot",
@@ -111,6 +111,12 @@ impl Language for PyLang {
     }
   }
 
+  fn kind_names(&self) -> Option<Vec<String>> {
+    match self {
+      Builtin(b) => b.kind_names(),
+      Custom(c) => c.kind_names(),
+    }
+  }
   fn kind_to_id(&self, kind: &str) -> u16 {
     match self {
       Builtin(b) => b.kind_to_id(kind),
@@ -39,6 +39,11 @@ def test_dict_literal():
     node = root.find(**rule)
     assert node is not None
 
+def test_kind_regex():
+    node = root.find(kind={"regex": "^lexical_"})
+    assert node
+    assert node.kind() == "lexical_declaration"
+
 def test_not_rule():
     rule = {"pattern": "let $A = $B", "not": Rule(pattern="let a = 123")}
     node = root.find(**rule)
@@ -122,6 +122,17 @@ test('findAll', t => {
   t.is(matches[1].range().end.index, 37)
 })
 
+test('findAllIter', async t => {
+  const sg = parse('javascript', 'f(1); g(f(2)); f(3)')
+  const sync = [...sg.root().findAllIter('f($A)')].map(n => n.text())
+  t.deepEqual(sync, ['f(1)', 'f(2)', 'f(3)'])
+  const texts = []
+  for await (const node of sg.root().findAllIter('f($A)')) {
+    texts.push(node.getMatch('A').text())
+  }
+  t.deepEqual(texts, ['1', '2', '3'])
+})
+
 test('find not match', t => {
   const sg = parse('javascript', 'console.log(123)')
   const match = sg.root().find('notExist')
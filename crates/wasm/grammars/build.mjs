@@ -0,0 +1,13 @@
+#!/usr/bin/env node
+// Copies the wasm binaries of the grammars listed in `index.js` from their tree-sitter
+// packages, which publish them prebuilt, so this package ships them without compiling.
+import { copyFileSync } from 'fs'
+import { createRequire } from 'module'
+import { grammars } from './index.js'
+
+const require = createRequire(import.meta.url)
+for (const [name, grammar] of Object.entries(grammars)) {
+  const source = require.resolve(`${grammar.package}/${grammar.file}`)
+  copyFileSync(source, new URL(`./${grammar.file}`, import.meta.url))
+  console.log(`Copied the ${name} grammar from ${grammar.package}.`)
+}
@@ -0,0 +1,14 @@
+export interface BuiltinGrammar {
+  /** The tree-sitter package the binary is copied from. */
+  package: string;
+  /** The file name of the binary in this package. */
+  file: string;
+  /** The `expandoChar` to register the language with, `$` if unset. */
+  expandoChar?: string;
+  extensions: string[];
+}
+
+export declare const grammars: Record<string, BuiltinGrammar>;
+
+/** The URL of the grammar binary of `name`, next to this module. */
+export declare function grammarUrl(name: string): URL;
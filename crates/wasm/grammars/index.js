@@ -0,0 +1,40 @@
+// The prebuilt grammars of `@ast-grep/wasm-grammars`, see `loadBuiltinLanguage` in the
+// README of `@ast-grep/wasm`. Each grammar is the wasm binary published by its tree-sitter
+// package, copied next to this file by `build.mjs`, with the registration options ast-grep
+// uses for the language: an `expandoChar` where `$` cannot start an identifier.
+
+export const grammars = {
+  bash: { package: 'tree-sitter-bash', file: 'tree-sitter-bash.wasm', extensions: ['bash', 'sh', 'zsh'] },
+  c: { package: 'tree-sitter-c', file: 'tree-sitter-c.wasm', expandoChar: '𐀀', extensions: ['c', 'h'] },
+  cpp: {
+    package: 'tree-sitter-cpp',
+    file: 'tree-sitter-cpp.wasm',
+    expandoChar: '𐀀',
+    extensions: ['cc', 'cpp', 'cxx', 'hh', 'hpp', 'hxx'],
+  },
+  css: { package: 'tree-sitter-css', file: 'tree-sitter-css.wasm', expandoChar: '_', extensions: ['css', 'scss'] },
+  go: { package: 'tree-sitter-go', file: 'tree-sitter-go.wasm', expandoChar: 'µ', extensions: ['go'] },
+  html: { package: 'tree-sitter-html', file: 'tree-sitter-html.wasm', expandoChar: 'z', extensions: ['html', 'htm'] },
+  java: { package: 'tree-sitter-java', file: 'tree-sitter-java.wasm', extensions: ['java'] },
+  javascript: {
+    package: 'tree-sitter-javascript',
+    file: 'tree-sitter-javascript.wasm',
+    extensions: ['cjs', 'js', 'mjs', 'jsx'],
+  },
+  json: { package: 'tree-sitter-json', file: 'tree-sitter-json.wasm', extensions: ['json'] },
+  php: { package: 'tree-sitter-php', file: 'tree-sitter-php.wasm', expandoChar: 'µ', extensions: ['php'] },
+  python: { package: 'tree-sitter-python', file: 'tree-sitter-python.wasm', expandoChar: 'µ', extensions: ['py', 'pyi'] },
+  ruby: { package: 'tree-sitter-ruby', file: 'tree-sitter-ruby.wasm', expandoChar: 'µ', extensions: ['rb', 'rbw', 'gemspec'] },
+  rust: { package: 'tree-sitter-rust', file: 'tree-sitter-rust.wasm', expandoChar: 'µ', extensions: ['rs'] },
+  tsx: { package: 'tree-sitter-typescript', file: 'tree-sitter-tsx.wasm', extensions: ['tsx'] },
+  typescript: {
+    package: 'tree-sitter-typescript',
+    file: 'tree-sitter-typescript.wasm',
+    extensions: ['ts', 'cts', 'mts'],
+  },
+}
+
+/** The URL of the grammar binary of `name`, next to this module. */
+export function grammarUrl(name) {
+  return new URL(`./${grammars[name].file}`, import.meta.url)
+}
@@ -0,0 +1,15 @@
+export interface BuiltinLanguageOptions {
+  /** Where the grammar binaries are hosted, instead of next to `@ast-grep/wasm-grammars`. */
+  baseUrl?: string | URL;
+  /** A subresource integrity hash the binary must have, as for `registerDynamicLanguage`. */
+  integrity?: string;
+}
+
+/**
+ * Fetch the prebuilt grammar of `name` and register it like `registerDynamicLanguage`.
+ * Languages are loaded once, later calls resolve when the first one is done.
+ */
+export declare function loadBuiltinLanguage(name: string, options?: BuiltinLanguageOptions): Promise<void>;
+
+/** The names of the languages `loadBuiltinLanguage` can load. */
+export declare function builtinLanguages(): Promise<string[]>;
@@ -0,0 +1,54 @@
+// Lazily loaded builtin languages, see `loadBuiltinLanguage` in the README. The grammars
+// and their registration options are in the optional `@ast-grep/wasm-grammars` package,
+// which is only imported when the first language is loaded.
+import { registerDynamicLanguage } from './wasm.js'
+
+// the pending or finished registration of each language, so it is loaded once
+const loading = new Map()
+
+async function readGrammar(url) {
+  // fetch cannot read local files in Node.js
+  if (url.protocol === 'file:') {
+    const { readFile } = await import('fs/promises')
+    return readFile(url)
+  }
+  const response = await fetch(url)
+  if (!response.ok) {
+    throw new Error(`Cannot fetch the grammar at ${url}: ${response.status} ${response.statusText}`)
+  }
+  return new Uint8Array(await response.arrayBuffer())
+}
+
+async function load(name, { baseUrl, integrity }) {
+  const { grammars, grammarUrl } = await import('@ast-grep/wasm-grammars')
+  const grammar = Object.hasOwn(grammars, name) ? grammars[name] : undefined
+  if (!grammar) {
+    const names = Object.keys(grammars).join(', ')
+    throw new Error(`\`${name}\` is not a builtin language, the builtin languages are ${names}.`)
+  }
+  const url = baseUrl ? new URL(grammar.file, baseUrl) : grammarUrl(name)
+  const libraryBytes = await readGrammar(url)
+  const { expandoChar, extensions } = grammar
+  await registerDynamicLanguage({ [name]: { libraryBytes, expandoChar, extensions, integrity } })
+}
+
+/**
+ * Fetch the prebuilt grammar of `name` and register it like `registerDynamicLanguage`.
+ * Languages are loaded once, later calls resolve when the first one is done.
+ */
+export function loadBuiltinLanguage(name, options = {}) {
+  let registered = loading.get(name)
+  if (!registered) {
+    registered = load(name, options)
+    loading.set(name, registered)
+    // a failed load can be retried, e.g. after a network error
+    registered.catch(() => loading.delete(name))
+  }
+  return registered
+}
+
+/** The names of the languages `loadBuiltinLanguage` can load. */
+export async function builtinLanguages() {
+  const { grammars } = await import('@ast-grep/wasm-grammars')
+  return Object.keys(grammars)
+}
@@ -0,0 +1,24 @@
+// The worker of `createScanner`: answers `init` and `scan` requests with one instance of
+// the module, see `scanner.js` for the other side.
+import * as sg from './wasm.js'
+
+const handlers = {
+  async init({ languages }) {
+    // the module of the `web` target must be instantiated, the `bundler` one already is
+    if (typeof sg.default === 'function') await sg.default()
+    await sg.initializeTreeSitter()
+    await sg.registerDynamicLanguage(languages)
+  },
+  scan({ files, rulesByLang, options }) {
+    return sg.scanMixed(files, rulesByLang, options)
+  },
+}
+
+self.onmessage = async ({ data: { id, type, ...payload } }) => {
+  try {
+    const result = await handlers[type](payload)
+    self.postMessage({ id, result })
+  } catch (e) {
+    self.postMessage({ id, error: String(e?.message ?? e) })
+  }
+}
@@ -0,0 +1,41 @@
+export interface ScannerOptions {
+  /** Number of workers, `navigator.hardwareConcurrency` by default. */
+  workers?: number;
+  /** Languages registered in every worker, as for `registerDynamicLanguage`. */
+  languages?: Record<string, {libraryPath?: string, libraryBytes?: ArrayBuffer | Uint8Array, expandoChar?: string, nodeTypes?: string, extensions?: string[]}>;
+  /** The URL of `scanner-worker.js`, if the bundler moved it. */
+  workerUrl?: string | URL;
+}
+
+export interface ScanFile {
+  name: string;
+  lang: string;
+  src: string;
+}
+
+/** Options of `scanMixed`, and a `signal` to abort the scan. */
+export interface ScannerScanOptions {
+  maxPerRule?: number;
+  unusedSuppression?: "hint" | "info" | "warning" | "error";
+  lazyText?: boolean;
+  signal?: AbortSignal;
+}
+
+/** A `scanMixed` result: the `scan` result of a file, or why it failed. */
+export interface FileScan {
+  name: string;
+  lang: string;
+  rules?: any[];
+  error?: string;
+}
+
+export interface Scanner {
+  /** Yield each file's result as soon as the chunk of files it is in is scanned. */
+  stream(files: ScanFile[], rulesByLang: Record<string, string>, options?: ScannerScanOptions): AsyncIterableIterator<FileScan>;
+  /** Resolve to every file's result, in input order. */
+  scan(files: ScanFile[], rulesByLang: Record<string, string>, options?: ScannerScanOptions): Promise<FileScan[]>;
+  /** Stop every worker. Running scans fail and the scanner cannot be used again. */
+  terminate(): void;
+}
+
+export function createScanner(options?: ScannerOptions): Scanner;
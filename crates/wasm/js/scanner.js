@@ -0,0 +1,137 @@
+// A pool of Web Workers running `scanMixed` off the main thread, see `createScanner`
+// in the README. Each worker loads its own instance of the module and registers the
+// languages, since trees and parsers cannot be shared between threads.
+
+const DEFAULT_WORKERS = 4
+// Files are sent in chunks, a few per worker, so that rules are parsed once per chunk
+// and a worker that finishes early takes over the rest of the work.
+const CHUNKS_PER_WORKER = 4
+
+function spawn(url, languages) {
+  const worker = new Worker(url, { type: 'module' })
+  const calls = new Map()
+  let nextId = 0
+  let failed = null
+  const fail = error => {
+    failed = error
+    for (const call of calls.values()) call.reject(error)
+    calls.clear()
+  }
+  worker.onmessage = ({ data }) => {
+    const call = calls.get(data.id)
+    // the call was rejected when the worker failed
+    if (!call) return
+    calls.delete(data.id)
+    if (data.error === undefined) {
+      call.resolve(data.result)
+    } else {
+      call.reject(new Error(data.error))
+    }
+  }
+  worker.onerror = event => {
+    event.preventDefault()
+    fail(new Error(`The scanner worker failed: ${event.message}`))
+  }
+  const request = (type, payload) =>
+    new Promise((resolve, reject) => {
+      if (failed) return reject(failed)
+      const id = nextId++
+      calls.set(id, { resolve, reject })
+      worker.postMessage({ id, type, ...payload })
+    })
+  const ready = request('init', { languages })
+  // a failed initialization is reported by the scans waiting for it
+  ready.catch(() => {})
+  const terminate = () => {
+    worker.terminate()
+    fail(new Error('The scanner was terminated.'))
+  }
+  return { ready, request, terminate }
+}
+
+/**
+ * Spawn `workers` Web Workers, each initializing the module and registering `languages`
+ * as `registerDynamicLanguage` does. The options of the returned scanner's scans are
+ * those of `scanMixed` plus a `signal` to abort them.
+ */
+export function createScanner({ workers, languages = {}, workerUrl } = {}) {
+  const count = Math.max(
+    1,
+    workers ?? globalThis.navigator?.hardwareConcurrency ?? DEFAULT_WORKERS,
+  )
+  const url = workerUrl ?? new URL('./scanner-worker.js', import.meta.url)
+  const pool = Array.from({ length: count }, () => spawn(url, languages))
+  let terminated = false
+
+  // The results of each chunk of `files` with the chunk's index, in completion order.
+  async function* scanChunks(files, rulesByLang, options = {}) {
+    if (terminated) throw new Error('The scanner was terminated.')
+    // callbacks and signals cannot be sent to workers, the signal is handled here
+    const { signal, onProgress: _, ...workerOptions } = options
+    signal?.throwIfAborted()
+    const size = Math.max(1, Math.ceil(files.length / (pool.length * CHUNKS_PER_WORKER)))
+    const chunks = []
+    for (let start = 0; start < files.length; start += size) {
+      chunks.push(files.slice(start, start + size))
+    }
+    const done = Symbol('done')
+    const queue = []
+    let wake = null
+    const push = item => {
+      queue.push(item)
+      wake?.()
+      wake = null
+    }
+    const onAbort = () => push({ error: signal.reason })
+    signal?.addEventListener('abort', onAbort, { once: true })
+    let next = 0
+    // set once the consumer stops iterating, e.g. with `break`
+    let stopped = false
+    const run = async ({ ready, request }) => {
+      await ready
+      while (next < chunks.length && !signal?.aborted && !stopped) {
+        const index = next++
+        const payload = { files: chunks[index], rulesByLang, options: workerOptions }
+        push({ index, results: await request('scan', payload) })
+      }
+    }
+    Promise.all(pool.map(run)).then(
+      () => push(done),
+      error => push({ error }),
+    )
+    try {
+      while (true) {
+        if (queue.length === 0) await new Promise(resolve => (wake = resolve))
+        const item = queue.shift()
+        if (item === done) return
+        if ('error' in item) throw item.error
+        yield item
+      }
+    } finally {
+      stopped = true
+      signal?.removeEventListener('abort', onAbort)
+    }
+  }
+
+  return {
+    /** Scan like `scanMixed`, yielding the files' results as soon as their chunk is done. */
+    async *stream(files, rulesByLang, options) {
+      for await (const { results } of scanChunks(files, rulesByLang, options)) {
+        yield* results
+      }
+    },
+    /** Scan like `scanMixed` and resolve to the results in input order. */
+    async scan(files, rulesByLang, options) {
+      const chunks = []
+      for await (const { index, results } of scanChunks(files, rulesByLang, options)) {
+        chunks[index] = results
+      }
+      return chunks.flat()
+    },
+    /** Stop every worker. Running scans fail and the scanner cannot be used again. */
+    terminate() {
+      terminated = true
+      for (const worker of pool) worker.terminate()
+    },
+  }
+}
@@ -1,6 +1,8 @@
 #!/usr/bin/env node
-// Patches pkg/package.json generated by wasm-pack to add missing peerDependencies.
-import { readFileSync, writeFileSync } from 'fs'
+// Patches pkg/package.json generated by wasm-pack to add missing peerDependencies,
+// and adds the `createScanner` worker pool and the `loadBuiltinLanguage` loader,
+// which wasm-pack does not know about.
+import { copyFileSync, readFileSync, writeFileSync } from 'fs'
 
 const pkgPath = new URL('../pkg/package.json', import.meta.url).pathname
 const pkg = JSON.parse(readFileSync(pkgPath, 'utf8'))
@@ -11,6 +13,11 @@ const webTreeSitterVersion = rootPkg.peerDependencies['web-tree-sitter']
 
 pkg.peerDependencies = {
   'web-tree-sitter': webTreeSitterVersion,
+  '@ast-grep/wasm-grammars': '*',
+}
+// the grammars are only needed by `loadBuiltinLanguage`
+pkg.peerDependenciesMeta = {
+  '@ast-grep/wasm-grammars': { optional: true },
 }
 
 if (typeof pkg.repository === 'string') {
@@ -21,5 +28,17 @@ if (!repoUrl.startsWith('git+')) {
   pkg.repository.url = 'git+' + repoUrl + (repoUrl.endsWith('.git') ? '' : '.git')
 }
 
+const jsFiles = [
+  'scanner.js',
+  'scanner-worker.js',
+  'scanner.d.ts',
+  'languages.js',
+  'languages.d.ts',
+]
+for (const file of jsFiles) {
+  copyFileSync(new URL(`../js/${file}`, import.meta.url), new URL(`../pkg/${file}`, import.meta.url))
+}
+pkg.files = [...new Set([...(pkg.files ?? []), ...jsFiles])]
+
 writeFileSync(pkgPath, JSON.stringify(pkg, null, 2) + '\n')
-console.log('Patched pkg/package.json with peerDependencies.')
\ No newline at end of file
+console.log('Patched pkg/package.json with peerDependencies, the scanner and the languages.')
\ No newline at end of file
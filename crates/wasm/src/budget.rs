@@ -0,0 +1,151 @@
+//! Limits of `find`, `findAll` and `scan`, so that pathological rules on huge sources
+//! cannot freeze the page.
+//!
+//! Searches run synchronously, so a time limit is checked between nodes rather than
+//! enforced by a timer, and an `AbortSignal` only stops a search if it is aborted while
+//! the search runs JS, e.g. in `onProgress` or a custom matcher, or before it starts.
+
+use crate::search_range::SearchRange;
+
+use js_sys::{Date, Function, Reflect};
+use serde::Deserialize;
+use std::ops::ControlFlow;
+use wasm_bindgen::prelude::*;
+
+/// Number of visited nodes between two checks of the clock and the signal.
+const INTERVAL: usize = 256;
+
+/// Options of `find` and `findAll`.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindOptions {
+  /// Called as `onProgress(processed, total)` during the traversal, see `Progress`.
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub on_progress: JsValue,
+  pub max_nodes: Option<usize>,
+  pub max_matches: Option<usize>,
+  pub timeout_ms: Option<f64>,
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub signal: JsValue,
+  /// Fail before searching if matching would recurse deeper, see `limits`.
+  pub max_pattern_depth: Option<usize>,
+  /// Only search the nodes overlapping this range, see `search_range`.
+  pub range: Option<SearchRange>,
+}
+
+impl FindOptions {
+  /// An options object, or the `onProgress` callback alone.
+  pub fn from_js(value: JsValue) -> Result<Self, JsError> {
+    if value.is_instance_of::<Function>() {
+      return Ok(Self {
+        on_progress: value,
+        ..Self::default()
+      });
+    }
+    let options: Option<Self> = serde_wasm_bindgen::from_value(value)?;
+    Ok(options.unwrap_or_default())
+  }
+
+  pub fn budget(&self) -> Budget {
+    Budget::new(
+      self.max_nodes,
+      self.max_matches,
+      self.timeout_ms,
+      &self.signal,
+    )
+  }
+
+  /// Whether a limit or range is set, so the search needs its own traversal.
+  pub fn is_limited(&self) -> bool {
+    let has_signal = !self.signal.is_undefined() && !self.signal.is_null();
+    self.max_nodes.is_some()
+      || self.max_matches.is_some()
+      || self.timeout_ms.is_some()
+      || has_signal
+      || self.range.is_some()
+  }
+}
+
+/// Counts visited nodes and stops a search once `maxMatches` are found, or with an error
+/// once `maxNodes`, `timeoutMs` or `signal` stops it.
+pub struct Budget {
+  max_nodes: Option<usize>,
+  max_matches: Option<usize>,
+  timeout_ms: Option<f64>,
+  deadline: Option<f64>,
+  signal: Option<JsValue>,
+  visited: usize,
+  error: Option<String>,
+}
+
+impl Budget {
+  pub fn new(
+    max_nodes: Option<usize>,
+    max_matches: Option<usize>,
+    timeout_ms: Option<f64>,
+    signal: &JsValue,
+  ) -> Self {
+    let signal = (!signal.is_undefined() && !signal.is_null()).then(|| signal.clone());
+    Self {
+      max_nodes,
+      max_matches,
+      timeout_ms,
+      deadline: timeout_ms.map(|ms| Date::now() + ms),
+      signal,
+      visited: 0,
+      error: None,
+    }
+  }
+
+  /// Count a visited node with `found` matches so far, and break if the search should
+  /// stop before matching it.
+  pub fn visit(&mut self, found: usize) -> ControlFlow<()> {
+    if self.max_matches.is_some_and(|max| found >= max) {
+      return ControlFlow::Break(());
+    }
+    match self.check() {
+      Ok(()) => ControlFlow::Continue(()),
+      Err(e) => {
+        self.error = Some(e);
+        ControlFlow::Break(())
+      }
+    }
+  }
+
+  /// The clock and the signal are checked for the first node and then every `INTERVAL` nodes.
+  fn check(&mut self) -> Result<(), String> {
+    self.visited += 1;
+    if let Some(max) = self.max_nodes {
+      if self.visited > max {
+        return Err(format!(
+          "The search stopped after visiting {max} nodes, the limit of `maxNodes`."
+        ));
+      }
+    }
+    if self.visited % INTERVAL != 1 {
+      return Ok(());
+    }
+    if let (Some(deadline), Some(ms)) = (self.deadline, self.timeout_ms) {
+      if Date::now() > deadline {
+        return Err(format!(
+          "The search timed out after {ms} ms, the limit of `timeoutMs`."
+        ));
+      }
+    }
+    if let Some(signal) = &self.signal {
+      let aborted = Reflect::get(signal, &"aborted".into()).unwrap_or(JsValue::FALSE);
+      if aborted.is_truthy() {
+        return Err("The search was aborted by its `signal`.".into());
+      }
+    }
+    Ok(())
+  }
+
+  /// Fails with the limit that stopped the search, if it was not `maxMatches`.
+  pub fn finish(self) -> Result<(), String> {
+    match self.error {
+      Some(e) => Err(e),
+      None => Ok(()),
+    }
+  }
+}
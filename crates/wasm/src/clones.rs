@@ -0,0 +1,225 @@
+//! Structural fingerprints of subtrees for `structuralHash` and `findClones`.
+//!
+//! A fingerprint hashes a subtree's kinds and tokens, skipping comments, so code that
+//! differs only in layout hashes equally. With the default options the text of
+//! identifiers and literals is ignored too, so renamed copies of code are clones.
+//! Grammars have no common kind names, so identifiers and literals are found by kind name.
+
+use crate::doc::{error_message, WasmDoc};
+use crate::encoding::EncodingOption;
+use crate::scan::MixedFile;
+use crate::sg_node::{node_range, Range};
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_core::{AstGrep, Node};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+type Hash = [u8; 32];
+
+/// Options of `structuralHash` and `findClones`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FingerprintOptions {
+  /// Hash identifiers by kind only, so renamed variables and functions match.
+  pub ignore_identifiers: bool,
+  /// Hash literals by kind only, e.g. every string the same regardless of its content.
+  pub ignore_literals: bool,
+  /// Skip comments, except for `diffTrees` which reports changed comments.
+  #[serde(skip)]
+  pub ignore_comments: bool,
+  /// Only read by `findClones`, roots keep their own.
+  #[serde(flatten)]
+  pub encoding: EncodingOption,
+}
+
+impl Default for FingerprintOptions {
+  fn default() -> Self {
+    Self {
+      ignore_identifiers: true,
+      ignore_literals: true,
+      ignore_comments: true,
+      encoding: EncodingOption::default(),
+    }
+  }
+}
+
+/// The hash of a subtree and its number of tokens, the leaves other than comments.
+#[derive(Clone, Copy)]
+pub struct Fingerprint {
+  pub hash: Hash,
+  pub tokens: usize,
+}
+
+impl Fingerprint {
+  pub fn hex(&self) -> String {
+    crate::hex(&self.hash)
+  }
+}
+
+fn is_comment(node: &Node<'_, WasmDoc>) -> bool {
+  node.kind().contains("comment")
+}
+
+fn is_identifier(node: &Node<'_, WasmDoc>) -> bool {
+  node.is_named_leaf() && node.kind().contains("identifier")
+}
+
+/// Strings, numbers and other literals, e.g. `template_string` or `integer_literal`.
+fn is_literal(node: &Node<'_, WasmDoc>) -> bool {
+  if !node.is_named() {
+    return false;
+  }
+  let kind = node.kind();
+  ["string", "number", "integer", "float", "literal"]
+    .iter()
+    .any(|k| kind.contains(k))
+}
+
+/// The fingerprints of `root` and its descendants by node id, except comments if they
+/// are ignored. `root` itself is hashed even if it is a comment.
+/// Nodes are hashed after their children without recursion, so deep trees are fine.
+pub fn fingerprints(
+  root: &Node<'_, WasmDoc>,
+  options: &FingerprintOptions,
+) -> HashMap<usize, Fingerprint> {
+  let root_id = root.node_id();
+  let nodes: Vec<_> = root
+    .dfs()
+    .filter(|n| n.node_id() == root_id || !options.ignore_comments || !is_comment(n))
+    .collect();
+  let mut prints: HashMap<usize, Fingerprint> = HashMap::with_capacity(nodes.len());
+  // a node comes after its descendants in reversed pre-order
+  for node in nodes.into_iter().rev() {
+    let mut hasher = Sha256::new();
+    hasher.update(node.kind().as_bytes());
+    hasher.update([0]);
+    let opaque = options.ignore_literals && is_literal(&node);
+    let mut tokens = 0;
+    if node.is_leaf() || opaque {
+      let ignored = opaque || (options.ignore_identifiers && is_identifier(&node));
+      if !ignored {
+        hasher.update(node.text().as_bytes());
+      }
+      tokens = 1;
+    } else {
+      for child in node.children() {
+        let Some(print) = prints.get(&child.node_id()) else {
+          continue;
+        };
+        hasher.update(print.hash);
+        tokens += print.tokens;
+      }
+    }
+    let print = Fingerprint {
+      hash: hasher.finalize().into(),
+      tokens,
+    };
+    prints.insert(node.node_id(), print);
+  }
+  prints
+}
+
+/// A copy of duplicated code found by `findClones`.
+#[derive(Serialize)]
+pub struct CloneInstance {
+  pub file: String,
+  pub range: Range,
+  pub text: String,
+}
+
+/// Subtrees of the same fingerprint, in file order and then position order.
+#[derive(Serialize)]
+pub struct CloneGroup {
+  pub hash: String,
+  pub tokens: usize,
+  pub instances: Vec<CloneInstance>,
+}
+
+/// A subtree large enough to be reported, with its file's index.
+struct Candidate<'r> {
+  file: usize,
+  node: Node<'r, WasmDoc>,
+  tokens: usize,
+}
+
+impl Candidate<'_> {
+  fn contains(&self, other: &Self) -> bool {
+    let (outer, inner) = (self.node.range(), other.node.range());
+    self.file == other.file && outer.start <= inner.start && inner.end <= outer.end
+  }
+}
+
+/// Groups of at least two subtrees of `files` with the same fingerprint and at least
+/// `min_tokens` tokens, largest first. Only maximal clones are reported: a group is
+/// dropped if each of its instances is inside an instance of a larger reported group.
+pub fn find_clones(
+  files: Vec<MixedFile>,
+  min_tokens: usize,
+  options: &FingerprintOptions,
+) -> Result<Vec<CloneGroup>, String> {
+  let mut roots = Vec::with_capacity(files.len());
+  for MixedFile { name, lang, src } in files {
+    let parsed = lang
+      .parse::<WasmLang>()
+      .map_err(|e| error_message(&e))
+      .and_then(|lang| WasmDoc::try_new(src, lang).map_err(|e| error_message(&e)));
+    let doc = parsed.map_err(|e| format!("Cannot parse `{name}`: {e}"))?;
+    let doc = doc.with_encoding(options.encoding.position_encoding);
+    roots.push((name, AstGrep::doc(doc)));
+  }
+  let mut by_hash: HashMap<Hash, Vec<Candidate>> = HashMap::new();
+  for (file, (_, root)) in roots.iter().enumerate() {
+    let prints = fingerprints(&root.root(), options);
+    for node in root.root().dfs().filter(|n| n.is_named()) {
+      let Some(print) = prints.get(&node.node_id()) else {
+        continue;
+      };
+      if print.tokens < min_tokens.max(1) {
+        continue;
+      }
+      let tokens = print.tokens;
+      let candidate = Candidate { file, node, tokens };
+      by_hash.entry(print.hash).or_default().push(candidate);
+    }
+  }
+  let mut groups: Vec<_> = by_hash
+    .into_iter()
+    .filter(|(_, candidates)| candidates.len() > 1)
+    .collect();
+  // larger clones first, and a wrapper before the node it wraps if both have the same tokens
+  groups.sort_by_cached_key(|(_, group)| {
+    let first = &group[0];
+    let range = first.node.range();
+    let size = (first.tokens, range.len());
+    (std::cmp::Reverse(size), first.file, range.start)
+  });
+  let mut reported: Vec<Candidate> = vec![];
+  let mut result = vec![];
+  for (hash, group) in groups {
+    let covered = group.iter().all(|c| reported.iter().any(|r| r.contains(c)));
+    if covered {
+      continue;
+    }
+    let print = Fingerprint {
+      hash,
+      tokens: group[0].tokens,
+    };
+    let instances = group
+      .iter()
+      .map(|c| CloneInstance {
+        file: roots[c.file].0.clone(),
+        range: node_range(&c.node),
+        text: c.node.text().to_string(),
+      })
+      .collect();
+    result.push(CloneGroup {
+      hash: print.hex(),
+      tokens: print.tokens,
+      instances,
+    });
+    reported.extend(group);
+  }
+  Ok(result)
+}
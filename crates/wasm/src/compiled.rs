@@ -0,0 +1,175 @@
+//! Matchers compiled once by `compileMatcher` and reused across roots.
+//!
+//! A handle only holds an id, its matcher lives in a registry until the handle is freed.
+//! Compiling the same source for the same language again reuses the matcher from a
+//! small LRU cache, so handles created in a loop do not recompile the pattern.
+
+use crate::sg_node::{parse_matcher, MatcherType};
+use crate::wasm_lang::{self, WasmLang};
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// Number of compiled sources kept for reuse.
+const CACHE_SIZE: usize = 64;
+
+/// The property identifying a handle passed where a matcher is expected.
+const ID_KEY: &str = "matcherId";
+
+/// The language and the source of a matcher.
+type CacheKey = (WasmLang, String);
+
+struct Compiled {
+  lang: WasmLang,
+  lang_name: String,
+  matcher: Rc<MatcherType>,
+}
+
+thread_local! {
+  // matchers are not `Send`, and JS workers have their own module instance anyway
+  static COMPILED: RefCell<HashMap<u32, Compiled>> = RefCell::new(HashMap::new());
+  static NEXT_ID: Cell<u32> = const { Cell::new(1) };
+  /// Least recently used first.
+  static CACHE: RefCell<VecDeque<(CacheKey, Rc<MatcherType>)>> =
+    const { RefCell::new(VecDeque::new()) };
+}
+
+/// A pattern or rule compiled for one language, see `compileMatcher`.
+/// Call `free()` when it is no longer needed.
+#[wasm_bindgen]
+pub struct CompiledMatcher {
+  id: u32,
+}
+
+#[wasm_bindgen]
+impl CompiledMatcher {
+  /// The language the matcher was compiled for.
+  #[wasm_bindgen(getter)]
+  pub fn lang(&self) -> String {
+    COMPILED.with(|compiled| {
+      let compiled = compiled.borrow();
+      compiled[&self.id].lang_name.clone()
+    })
+  }
+
+  /// Identifies the handle when it is passed as a matcher.
+  #[wasm_bindgen(getter, js_name = matcherId)]
+  pub fn matcher_id(&self) -> u32 {
+    self.id
+  }
+}
+
+impl Drop for CompiledMatcher {
+  fn drop(&mut self) {
+    COMPILED.with(|compiled| compiled.borrow_mut().remove(&self.id));
+  }
+}
+
+/// The cache key of a matcher source, `None` for kind ids which need no compiling.
+fn source_key(source: &JsValue) -> Option<String> {
+  if let Some(pattern) = source.as_string() {
+    return Some(format!("pattern:{pattern}"));
+  }
+  if !source.is_object() {
+    return None;
+  }
+  let json = js_sys::JSON::stringify(source).ok()?;
+  Some(format!("config:{}", String::from(json)))
+}
+
+fn cached(key: &CacheKey) -> Option<Rc<MatcherType>> {
+  CACHE.with(|cache| {
+    let mut cache = cache.borrow_mut();
+    let index = cache.iter().position(|(k, _)| k == key)?;
+    let entry = cache.remove(index).expect("index is in bounds");
+    let matcher = entry.1.clone();
+    cache.push_back(entry);
+    Some(matcher)
+  })
+}
+
+fn cache(key: CacheKey, matcher: Rc<MatcherType>) {
+  CACHE.with(|cache| {
+    let mut cache = cache.borrow_mut();
+    if cache.len() == CACHE_SIZE {
+      cache.pop_front();
+    }
+    cache.push_back((key, matcher));
+  });
+}
+
+/// Number of compiled sources in the cache, see `memoryStats`.
+pub fn cache_len() -> usize {
+  CACHE.with(|cache| cache.borrow().len())
+}
+
+/// Forget cached matchers, e.g. after languages are registered again.
+/// Existing handles keep their matchers.
+pub fn clear_cache() {
+  CACHE.with(|cache| cache.borrow_mut().clear());
+}
+
+/// Compile `source`, a pattern, kind id or rule config, into a reusable handle.
+pub fn compile(lang_name: String, source: JsValue) -> Result<CompiledMatcher, JsError> {
+  let lang: WasmLang = lang_name
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  if is_compiled(&source) {
+    return Err(JsError::new("The matcher is compiled already."));
+  }
+  let key = source_key(&source).map(|key| (lang, key));
+  let matcher = match key.as_ref().and_then(cached) {
+    Some(matcher) => matcher,
+    None => {
+      let matcher = Rc::new(parse_matcher(lang, source)?);
+      if let Some(key) = key {
+        cache(key, matcher.clone());
+      }
+      matcher
+    }
+  };
+  let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+  let entry = Compiled {
+    lang,
+    lang_name,
+    matcher,
+  };
+  COMPILED.with(|compiled| compiled.borrow_mut().insert(id, entry));
+  Ok(CompiledMatcher { id })
+}
+
+fn handle_id(m: &JsValue) -> Option<u32> {
+  if !m.is_object() {
+    return None;
+  }
+  let id = js_sys::Reflect::get(m, &ID_KEY.into()).ok()?;
+  id.as_f64().map(|id| id as u32)
+}
+
+/// Whether `m` is a `CompiledMatcher` handle.
+pub fn is_compiled(m: &JsValue) -> bool {
+  handle_id(m).is_some()
+}
+
+/// The matcher of the handle `m` for a node of `lang`, `None` if `m` is not a handle.
+pub fn lookup(m: &JsValue, lang: WasmLang) -> Option<Result<Rc<MatcherType>, JsError>> {
+  let id = handle_id(m)?;
+  let found = COMPILED.with(|compiled| {
+    let compiled = compiled.borrow();
+    let Some(entry) = compiled.get(&id) else {
+      return Err(JsError::new("The compiled matcher has been freed."));
+    };
+    if entry.lang != lang {
+      entry.lang.check_registered()?;
+      let msg = format!(
+        "The matcher is compiled for `{}` and cannot match another language.",
+        entry.lang_name
+      );
+      return Err(JsError::new(&msg));
+    }
+    Ok(entry.matcher.clone())
+  });
+  Some(found)
+}
@@ -0,0 +1,197 @@
+//! Constant folding for the `values` of rules.
+//!
+//! A node has a value if it is a number, string, boolean or null literal, an
+//! arithmetic or concatenation of values, or a variable whose only declaration
+//! initializes it with a value, like a `const`. Variables are resolved by `scope`.
+//! Like `scope`, expressions are recognized by the kind and field names of common
+//! grammars, e.g. `binary_expression` and Python's `binary_operator`.
+
+use crate::doc::WasmDoc;
+use crate::scope::Scopes;
+
+use ast_grep_core::Node;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+type SgNode<'r> = Node<'r, WasmDoc>;
+
+/// Variables are followed at most this deep, which also stops cycles.
+const MAX_DEPTH: usize = 16;
+
+/// The value of a constant expression, written in YAML as a scalar.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Constant {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+}
+
+/// The `values` of a rule, keyed by meta variable names without `$`.
+pub type ValueConstraints = HashMap<String, Constant>;
+
+/// The value `node` evaluates to, if it is a constant expression.
+pub fn evaluate(scopes: &Scopes<'_>, node: &SgNode<'_>) -> Option<Constant> {
+  Evaluator { scopes, depth: 0 }.evaluate(node)
+}
+
+struct Evaluator<'s, 'r> {
+  scopes: &'s Scopes<'r>,
+  depth: usize,
+}
+
+impl Evaluator<'_, '_> {
+  fn evaluate(&mut self, node: &SgNode<'_>) -> Option<Constant> {
+    let kind = node.kind();
+    match kind.as_ref() {
+      "true" => return Some(Constant::Bool(true)),
+      "false" => return Some(Constant::Bool(false)),
+      "null" | "none" | "nil" | "undefined" => return Some(Constant::Null),
+      "parenthesized_expression" => return self.evaluate(&node.children().find(|n| n.is_named())?),
+      "concatenated_string" => {
+        let mut s = String::new();
+        for part in node.children().filter(|n| n.is_named()) {
+          let Constant::String(part) = self.evaluate(&part)? else {
+            return None;
+          };
+          s.push_str(&part);
+        }
+        return Some(Constant::String(s));
+      }
+      _ => (),
+    }
+    if ["number", "integer", "float", "int_literal", "float_literal"].contains(&kind.as_ref()) {
+      return number(&node.text()).map(Constant::Number);
+    }
+    if kind.contains("string") {
+      return string(node).map(Constant::String);
+    }
+    if kind.contains("binary") {
+      let (left, right) = (node.field("left")?, node.field("right")?);
+      let operator = operator(node)?;
+      return binary(&operator, self.evaluate(&left)?, self.evaluate(&right)?);
+    }
+    if kind.contains("unary") {
+      let operand = node.field("argument").or_else(|| node.field("operand"))?;
+      let operator = operator(node)?;
+      return match (operator.as_str(), self.evaluate(&operand)?) {
+        ("-", Constant::Number(n)) => Some(Constant::Number(-n)),
+        ("+", Constant::Number(n)) => Some(Constant::Number(n)),
+        ("!" | "not", Constant::Bool(b)) => Some(Constant::Bool(!b)),
+        _ => None,
+      };
+    }
+    self.variable(node)
+  }
+
+  /// The value of a variable declared once with a constant initializer.
+  fn variable(&mut self, node: &SgNode<'_>) -> Option<Constant> {
+    if self.depth == MAX_DEPTH {
+      return None;
+    }
+    let definition = self.scopes.definition(node)?;
+    if self.scopes.is_reassigned(node, Some(&definition)) {
+      return None;
+    }
+    let value = initializer(&definition)?;
+    self.depth += 1;
+    let constant = self.evaluate(&value);
+    self.depth -= 1;
+    constant
+  }
+}
+
+/// The value `name` is declared with, e.g. `1` in `const a = 1` or Go's `const a, b = 1, 2`.
+fn initializer<'r>(name: &SgNode<'r>) -> Option<SgNode<'r>> {
+  let declaration = name.parent()?;
+  let names: Vec<_> = ["name", "left"]
+    .iter()
+    .flat_map(|field| declaration.field_children(field))
+    .collect();
+  let index = names.iter().position(|n| n.node_id() == name.node_id())?;
+  let value = declaration
+    .field("value")
+    .or_else(|| declaration.field("right"))?;
+  if !value.kind().contains("list") {
+    return (names.len() == 1).then_some(value);
+  }
+  let values: Vec<_> = value.children().filter(|n| n.is_named()).collect();
+  (values.len() == names.len()).then(|| values[index].clone())
+}
+
+/// The operator of a binary or unary expression, its `operator` field or first token.
+fn operator(node: &SgNode<'_>) -> Option<String> {
+  let operator = node
+    .field("operator")
+    .or_else(|| node.children().find(|n| !n.is_named()))?;
+  Some(operator.text().to_string())
+}
+
+fn binary(operator: &str, left: Constant, right: Constant) -> Option<Constant> {
+  use Constant::{Number, String};
+  let value = match (operator, left, right) {
+    ("+", String(l), String(r)) => String(l + &r),
+    ("+", Number(l), Number(r)) => Number(l + r),
+    ("-", Number(l), Number(r)) => Number(l - r),
+    ("*", Number(l), Number(r)) => Number(l * r),
+    ("/", Number(l), Number(r)) if r != 0.0 => Number(l / r),
+    ("//", Number(l), Number(r)) if r != 0.0 => Number((l / r).floor()),
+    ("%", Number(l), Number(r)) if r != 0.0 => Number(l % r),
+    ("**", Number(l), Number(r)) => Number(l.powf(r)),
+    _ => return None,
+  };
+  Some(value)
+}
+
+/// A decimal, hex, octal or binary literal, with `_` separators.
+fn number(text: &str) -> Option<f64> {
+  let text = text.replace('_', "");
+  let lower = text.to_ascii_lowercase();
+  for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+    if let Some(digits) = lower.strip_prefix(prefix) {
+      return i64::from_str_radix(digits, radix).ok().map(|n| n as f64);
+    }
+  }
+  text.parse().ok()
+}
+
+/// The content of a string literal without interpolations, its escapes decoded.
+fn string(node: &SgNode<'_>) -> Option<String> {
+  let parts: Vec<_> = node.children().filter(|n| n.is_named()).collect();
+  if parts.is_empty() {
+    // `""`, or grammars without content nodes
+    let text = node.text();
+    let content = text.trim_matches(|c| matches!(c, '"' | '\'' | '`'));
+    return (!content.contains('\\')).then(|| content.to_string());
+  }
+  let mut s = String::new();
+  for part in parts {
+    let kind = part.kind();
+    if kind.contains("content") || kind.contains("fragment") {
+      s.push_str(&part.text());
+    } else if kind.contains("escape") {
+      s.push(escape(&part.text())?);
+    } else if !kind.ends_with("_start") && !kind.ends_with("_end") {
+      // interpolations and the like
+      return None;
+    }
+  }
+  Some(s)
+}
+
+fn escape(sequence: &str) -> Option<char> {
+  let c = match sequence.strip_prefix('\\')? {
+    "n" => '\n',
+    "t" => '\t',
+    "r" => '\r',
+    "0" => '\0',
+    s if s.len() == 1 => s.chars().next()?,
+    s => {
+      let hex = s.strip_prefix('x').or_else(|| s.strip_prefix('u'))?;
+      let hex = hex.trim_start_matches('{').trim_end_matches('}');
+      char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+    }
+  };
+  Some(c)
+}
@@ -0,0 +1,86 @@
+//! JS predicates registered by `registerCustomMatcher`, which rules call with `custom`.
+//!
+//! The predicates are shared by every language, like the rules' `custom` field is
+//! language-independent. A rule referencing a name that is not registered is invalid.
+
+use crate::compiled;
+use crate::doc::WasmDoc;
+use crate::sg_node::SgNode;
+
+use ast_grep_config::CustomMatchers;
+use ast_grep_core::{AstGrep, Node};
+use js_sys::{Function, Object};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+  static MATCHERS: RefCell<HashMap<String, Function>> = RefCell::new(HashMap::new());
+  /// The tree of the document the last predicate call was for and the root of its copy,
+  /// to reuse the copy for its other nodes. The copy has a tree of its own, so the tree
+  /// of the original document is kept to compare with.
+  static ROOT: RefCell<Option<(JsValue, Rc<AstGrep<WasmDoc>>)>> = const { RefCell::new(None) };
+  /// Number of documents copied for predicate calls, see `copies`.
+  static COPIES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Register `predicate` as `name`, replacing the predicate registered with that name.
+pub fn register(name: String, predicate: Function) -> Result<(), JsError> {
+  if name.is_empty() {
+    return Err(JsError::new(
+      "The name of a custom matcher cannot be empty.",
+    ));
+  }
+  MATCHERS.with(|m| m.borrow_mut().insert(name, predicate));
+  // cached matchers were compiled before the name was registered
+  compiled::clear_cache();
+  Ok(())
+}
+
+/// The registered predicates, for the `custom` rules of `GlobalRules`.
+pub fn matchers() -> CustomMatchers {
+  let mut custom = CustomMatchers::default();
+  MATCHERS.with(|m| {
+    for name in m.borrow().keys() {
+      let key = name.clone();
+      custom.insert(name, move |node: &Node<'_, WasmDoc>| call(&key, node));
+    }
+  });
+  custom
+}
+
+/// Call the predicate `name` with `node` as an SgNode. A predicate that throws does not match.
+fn call(name: &str, node: &Node<'_, WasmDoc>) -> bool {
+  // looked up again in case it is replaced after the rule is compiled
+  let Some(predicate) = MATCHERS.with(|m| m.borrow().get(name).cloned()) else {
+    return false;
+  };
+  let node = SgNode::readopted(root_of(node), node);
+  predicate
+    .call1(&JsValue::NULL, &node.into())
+    .is_ok_and(|ret| ret.is_truthy())
+}
+
+/// Number of documents copied for predicate calls since the module was loaded.
+pub fn copies() -> usize {
+  COPIES.with(Cell::get)
+}
+
+/// A root of a clone of `node`'s document, cached while the document's tree is the same,
+/// since cloning copies its source and tree.
+fn root_of(node: &Node<'_, WasmDoc>) -> Rc<AstGrep<WasmDoc>> {
+  let doc = node.get_doc();
+  ROOT.with(|root| {
+    let mut root = root.borrow_mut();
+    if let Some((tree, cached)) = &*root {
+      if Object::is(tree, &doc.tree) {
+        return cached.clone();
+      }
+    }
+    let fresh = Rc::new(AstGrep::doc(doc.clone()));
+    COPIES.with(|c| c.set(c.get() + 1));
+    *root = Some((JsValue::from(&doc.tree), fresh.clone()));
+    fresh
+  })
+}
@@ -0,0 +1,106 @@
+//! Descriptions of pattern mismatches for `debugMatch`.
+
+use crate::doc::WasmDoc;
+use crate::wasm_lang::WasmLang;
+use crate::StrictnessOption;
+
+use ast_grep_core::matcher::PatternNode;
+use ast_grep_core::{Language, Mismatch, Node};
+use serde::Deserialize;
+
+/// Options of `debugMatch`, as for `pattern`.
+#[derive(Default, Deserialize)]
+pub struct DebugMatchOptions {
+  pub selector: Option<String>,
+  pub strictness: Option<StrictnessOption>,
+}
+
+/// Why a pattern does not match, with the node where matching stopped.
+pub struct Divergence<'t> {
+  /// One of `kind`, `text`, `unnamed`, `metaVar`, `missingChild` and `extraChild`.
+  pub reason: &'static str,
+  pub message: String,
+  /// The expected kind or text, if the pattern expects a specific node.
+  pub expected: Option<String>,
+  pub node: Node<'t, WasmDoc>,
+}
+
+fn kind_name(lang: &WasmLang, id: u16) -> String {
+  let name = lang.get_ts_language().node_kind_for_id(id);
+  name.unwrap_or_else(|| format!("#{id}"))
+}
+
+/// The expected kind or text of a pattern node, and how a message refers to it.
+fn describe_goal(lang: &WasmLang, goal: &PatternNode) -> (Option<String>, String) {
+  match goal {
+    PatternNode::Terminal { text, .. } => (Some(text.clone()), format!("`{text}`")),
+    PatternNode::Internal { kind_id, .. } => {
+      let kind = kind_name(lang, *kind_id);
+      let described = format!("a `{kind}` node");
+      (Some(kind), described)
+    }
+    PatternNode::MetaVar { .. } => (None, "a meta variable".into()),
+  }
+}
+
+pub fn describe<'t>(mismatch: Mismatch<'_, 't, WasmDoc>, lang: &WasmLang) -> Divergence<'t> {
+  let sigil = lang.meta_var_char();
+  match mismatch {
+    Mismatch::Kind { expected, node } => {
+      let expected = kind_name(lang, expected);
+      Divergence {
+        reason: "kind",
+        message: format!("Expected a `{expected}` node, found `{}`.", node.kind()),
+        expected: Some(expected),
+        node,
+      }
+    }
+    Mismatch::Text { expected, node } => Divergence {
+      reason: "text",
+      message: format!("Expected `{expected}`, found `{}`.", node.text()),
+      expected: Some(expected.into()),
+      node,
+    },
+    Mismatch::Unnamed { name, node } => Divergence {
+      reason: "unnamed",
+      message: format!(
+        "`{sigil}{name}` only matches named nodes, found `{}`. Use `{sigil}{sigil}{name}` to also match unnamed nodes.",
+        node.kind()
+      ),
+      expected: None,
+      node,
+    },
+    Mismatch::MetaVar {
+      name,
+      node,
+      previous,
+    } => Divergence {
+      reason: "metaVar",
+      message: format!(
+        "`{sigil}{name}` matched `{}` before, but here it is `{}`.",
+        previous.text(),
+        node.text()
+      ),
+      expected: Some(previous.text().into()),
+      node,
+    },
+    Mismatch::MissingChild { goal, parent } => {
+      let (expected, described) = describe_goal(lang, goal);
+      Divergence {
+        reason: "missingChild",
+        message: format!("`{}` has no child left for {described}.", parent.kind()),
+        expected,
+        node: parent,
+      }
+    }
+    Mismatch::ExtraChild { node } => Divergence {
+      reason: "extraChild",
+      message: format!(
+        "`{}` is left after the end of the pattern and the strictness does not skip it.",
+        node.text()
+      ),
+      expected: None,
+      node,
+    },
+  }
+}
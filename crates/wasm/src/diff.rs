@@ -0,0 +1,31 @@
+//! Unified diffs of fixes for `unifiedDiff` and the `diff` option of fixes, e.g. for
+//! PR comments or a terminal preview.
+
+use serde::Deserialize;
+use similar::TextDiff;
+
+/// Options of `unifiedDiff`, and the `diff` of `FixOptions`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffOptions {
+  /// Lines of context around each change, 3 by default like `diff -u`.
+  pub context: Option<usize>,
+  /// Path of the file in the `---` and `+++` headers, as `a/path` and `b/path`.
+  pub path: Option<String>,
+}
+
+/// The unified diff from `before` to `after`, empty if they are the same.
+pub fn unified(before: &str, after: &str, options: &DiffOptions) -> String {
+  if before == after {
+    return String::new();
+  }
+  let (old, new) = match &options.path {
+    Some(path) => (format!("a/{path}"), format!("b/{path}")),
+    None => ("before".to_string(), "after".to_string()),
+  };
+  TextDiff::from_lines(before, after)
+    .unified_diff()
+    .context_radius(options.context.unwrap_or(3))
+    .header(&old, &new)
+    .to_string()
+}
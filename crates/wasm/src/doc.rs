@@ -1,49 +1,285 @@
+use crate::encoding::PositionEncoding;
+use crate::global_utils;
+use crate::normalize::Normalization;
+use crate::query::QueryMatcher;
+use crate::source_map::{Segment, SourceMap};
 use crate::ts_types as ts;
-use crate::wasm_lang::{SgWasmError, WasmLang};
+use crate::wasm_lang::{PatternChars, SgWasmError, WasmLang};
 
-use ast_grep_config::{DeserializeEnv, RuleCore, SerializableRuleCore};
+use ast_grep_config::{Fixer, RuleCore, SerializableRuleConfig, SerializableRuleCore, Severity};
+use ast_grep_core::matcher::{Matcher, MatcherExt};
+use ast_grep_core::meta_var::MetaVarEnv;
 use ast_grep_core::source::{Content, Doc, Edit, SgNode};
-use ast_grep_core::Position;
+use ast_grep_core::{Node as CoreNode, Position};
+use bit_set::BitSet;
 use wasm_bindgen::prelude::*;
 
 use std::borrow::Cow;
+use std::cell::OnceCell;
 use std::ops::Range;
+use std::rc::Rc;
 
 /// Rule configuration similar to YAML.
 /// See https://ast-grep.github.io/reference/yaml.html
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct WasmConfig {
+  /// Required unless `query` is set.
+  #[serde(default)]
   pub rule: serde_json::Value,
   pub constraints: Option<serde_json::Value>,
   pub language: Option<String>,
   pub transform: Option<serde_json::Value>,
   pub utils: Option<serde_json::Value>,
+  /// Rules with `fix` that a `rewrite` transformation can apply, as in YAML rules.
+  pub rewriters: Option<serde_json::Value>,
+  /// Meta variable whose node is the relevant part of a match, see `SgNode.primaryCapture`.
+  pub primary: Option<String>,
+  /// A raw tree-sitter query to match instead of `rule`, see `SgNode.findAll`.
+  /// Only the matcher methods of SgNode support it, not `scan` or `fix`.
+  pub query: Option<String>,
+  /// `expandoChar` and `metaVarChar` used by the config's patterns and fix.
+  #[serde(flatten)]
+  pub chars: PatternChars,
 }
 
 impl WasmConfig {
-  pub fn parse_with(self, lang: WasmLang) -> Result<RuleCore, JsError> {
+  pub fn parse_with(self, lang: WasmLang) -> Result<WasmRule, JsError> {
+    self.parse_with_fix(lang, None)
+  }
+
+  /// Like `parse_with`, with `fix` as the rule's fix template. Like in YAML rules,
+  /// the template can use the variables of `transform`.
+  pub fn parse_with_fix(mut self, lang: WasmLang, fix: Option<&str>) -> Result<WasmRule, JsError> {
+    if self.query.is_some() {
+      let msg = "`query` is only supported by `find`, `findAll` and the matcher methods of SgNode.";
+      return Err(JsError::new(msg));
+    }
+    lang.check_registered()?;
+    let lang = lang.with_chars(&self.chars)?;
+    let counts = self.take_counts()?;
     let rule = SerializableRuleCore {
       rule: serde_json::from_value(self.rule)?,
       constraints: self.constraints.map(serde_json::from_value).transpose()?,
       transform: self.transform.map(serde_json::from_value).transpose()?,
       utils: self.utils.map(serde_json::from_value).transpose()?,
-      fix: None,
+      fix: fix.map(|f| serde_json::from_value(f.into())).transpose()?,
+    };
+    let core = match self.rewriters {
+      None => rule
+        .get_matcher(global_utils::env(lang))
+        .map_err(|e| error_chain(&e))?,
+      // rewriters are registered by a full rule config, like in YAML rules
+      Some(rewriters) => SerializableRuleConfig {
+        core: rule.clone(),
+        id: String::new(),
+        language: lang,
+        rewriters: Some(serde_json::from_value(rewriters)?),
+        message: String::new(),
+        note: None,
+        severity: Severity::default(),
+        labels: None,
+        files: None,
+        ignores: None,
+        url: None,
+        metadata: None,
+      }
+      .get_matcher(&global_utils::globals(lang))
+      .map_err(|e| error_chain(&e))?,
+    };
+    let defined = core.defined_vars();
+    if let Some((var, _)) = counts
+      .iter()
+      .find(|(var, _)| !defined.contains(var.as_str()))
+    {
+      let msg = format!("Constraint meta variable `{var}` is not defined in the rule.");
+      return Err(JsError::new(&msg));
+    }
+    let captures = capture_checks(counts, &rule, lang)?;
+    Ok(WasmRule { core, captures })
+  }
+
+  /// Compile `query` if it is set. A query replaces the rule, so it cannot be
+  /// combined with `rule`, `constraints`, `transform`, `utils` or `rewriters`.
+  pub fn parse_query(&self, lang: WasmLang) -> Result<Option<QueryMatcher>, JsError> {
+    let Some(query) = &self.query else {
+      return Ok(None);
+    };
+    let has_rule = !self.rule.is_null()
+      || self.constraints.is_some()
+      || self.transform.is_some()
+      || self.utils.is_some()
+      || self.rewriters.is_some();
+    if has_rule {
+      let msg = "`query` cannot be combined with `rule`, `constraints`, `transform`, `utils` or `rewriters`.";
+      return Err(JsError::new(msg));
+    }
+    QueryMatcher::try_new(lang, query).map(Some)
+  }
+
+  /// Remove `minCount` and `maxCount` from `constraints`, since they are not rules.
+  /// A constraint left empty is dropped.
+  pub(crate) fn take_counts(&mut self) -> Result<Vec<(String, CountBound)>, JsError> {
+    let Some(serde_json::Value::Object(constraints)) = &mut self.constraints else {
+      return Ok(vec![]);
     };
-    let env = DeserializeEnv::new(lang);
-    rule.get_matcher(env).map_err(|e| {
-      let errors: Vec<_> =
-        std::iter::successors(Some(&e as &dyn std::error::Error), |e| e.source())
-          .map(|e| e.to_string())
-          .collect();
-      JsError::new(&errors.join("\n |->"))
+    let mut counts = vec![];
+    for (var, constraint) in constraints.iter_mut() {
+      let serde_json::Value::Object(constraint) = constraint else {
+        continue;
+      };
+      let min = constraint.remove("minCount");
+      let max = constraint.remove("maxCount");
+      if min.is_none() && max.is_none() {
+        continue;
+      }
+      let bound = CountBound {
+        min: min.map(serde_json::from_value).transpose()?,
+        max: max.map(serde_json::from_value).transpose()?,
+      };
+      if let (Some(min), Some(max)) = (bound.min, bound.max) {
+        if min > max {
+          let msg = format!("`minCount` of `{var}` is greater than its `maxCount`.");
+          return Err(JsError::new(&msg));
+        }
+      }
+      counts.push((var.clone(), bound));
+    }
+    constraints.retain(|_, c| !matches!(c, serde_json::Value::Object(c) if c.is_empty()));
+    Ok(counts)
+  }
+}
+
+/// What `constraints` require of meta variables beyond `RuleCore`, which only checks
+/// the rules of single meta variables.
+struct CaptureCheck {
+  var: String,
+  count: Option<CountBound>,
+  /// checked against every named node of a `$$$` capture
+  rule: Option<RuleCore>,
+}
+
+/// Combine the counts with each constraint compiled on its own, with the rule's `utils`.
+fn capture_checks(
+  counts: Vec<(String, CountBound)>,
+  rule: &SerializableRuleCore,
+  lang: WasmLang,
+) -> Result<Vec<CaptureCheck>, JsError> {
+  let mut checks: Vec<_> = counts
+    .into_iter()
+    .map(|(var, count)| CaptureCheck {
+      var,
+      count: Some(count),
+      rule: None,
     })
+    .collect();
+  for (var, constraint) in rule.constraints.iter().flatten() {
+    let core = SerializableRuleCore {
+      rule: constraint.clone(),
+      constraints: None,
+      transform: None,
+      utils: rule.utils.clone(),
+      fix: None,
+    };
+    let matcher = core
+      .get_matcher(global_utils::env(lang))
+      .map_err(|e| error_chain(&e))?;
+    match checks.iter_mut().find(|c| &c.var == var) {
+      Some(check) => check.rule = Some(matcher),
+      None => checks.push(CaptureCheck {
+        var: var.clone(),
+        count: None,
+        rule: Some(matcher),
+      }),
+    }
   }
+  Ok(checks)
+}
+
+/// Bounds on how many nodes a meta variable captures,
+/// set by `minCount` and `maxCount` in `constraints`.
+pub(crate) struct CountBound {
+  min: Option<usize>,
+  max: Option<usize>,
+}
+
+impl CountBound {
+  fn contains(&self, count: usize) -> bool {
+    self.min.map_or(true, |min| count >= min) && self.max.map_or(true, |max| count <= max)
+  }
+}
+
+/// A compiled rule config.
+pub struct WasmRule {
+  core: RuleCore,
+  captures: Vec<CaptureCheck>,
+}
+
+impl WasmRule {
+  /// The fix template of `WasmConfig::parse_with_fix`.
+  pub fn fixer(&self) -> Option<&Fixer> {
+    self.core.fixer.first()
+  }
+}
+
+impl Matcher for WasmRule {
+  fn match_node_with_env<'tree, D: Doc>(
+    &self,
+    node: CoreNode<'tree, D>,
+    env: &mut Cow<MetaVarEnv<'tree, D>>,
+  ) -> Option<CoreNode<'tree, D>> {
+    let ret = self.core.match_node_with_env(node, env)?;
+    for check in &self.captures {
+      if env.get_match(&check.var).is_some() {
+        // a single meta variable counts as one, its rule is checked by the rule core
+        if check.count.as_ref().is_some_and(|bound| !bound.contains(1)) {
+          return None;
+        }
+        continue;
+      }
+      // `$$$` captures also contain separators like `,`, which are neither counted nor checked
+      let captured = env.get_multiple_matches(&check.var);
+      let named: Vec<_> = captured.into_iter().filter(|n| n.is_named()).collect();
+      if check
+        .count
+        .as_ref()
+        .is_some_and(|b| !b.contains(named.len()))
+      {
+        return None;
+      }
+      if let Some(rule) = &check.rule {
+        if !named.into_iter().all(|n| rule.match_node(n).is_some()) {
+          return None;
+        }
+      }
+    }
+    Some(ret)
+  }
+
+  fn potential_kinds(&self) -> Option<BitSet> {
+    self.core.potential_kinds()
+  }
+}
+
+/// Convert an error and all its sources to a JsError message.
+pub(crate) fn error_chain(e: &dyn std::error::Error) -> JsError {
+  JsError::new(&error_message(e))
+}
+
+/// Join an error and all its sources into one message.
+pub(crate) fn error_message(e: &dyn std::error::Error) -> String {
+  let errors: Vec<_> = std::iter::successors(Some(e), |e| e.source())
+    .map(|e| e.to_string())
+    .collect();
+  errors.join("\n |->")
 }
 
 // Content wrapper using Vec<char> encoding for WASM
 #[derive(Clone)]
 pub struct Wrapper {
   inner: Vec<char>,
+  /// UTF-16 offsets of the chars and the end, built on first use by `utf16_offset`.
+  /// `None` if every char is a single code unit, so offsets are the same.
+  utf16: OnceCell<Option<Rc<[u32]>>>,
 }
 
 impl Content for Wrapper {
@@ -63,6 +299,82 @@ impl Content for Wrapper {
 }
 
 impl Wrapper {
+  fn new(src: &str) -> Self {
+    Self {
+      inner: src.chars().collect(),
+      utf16: OnceCell::new(),
+    }
+  }
+
+  /// The whole source text.
+  pub(crate) fn text(&self) -> String {
+    self.inner.iter().collect()
+  }
+
+  /// The chars of the source, which node ranges index.
+  pub(crate) fn chars(&self) -> &[char] {
+    &self.inner
+  }
+
+  /// Number of chars in the source.
+  pub(crate) fn char_count(&self) -> usize {
+    self.inner.len()
+  }
+
+  /// Char offset of the start of the line containing `offset`.
+  pub(crate) fn line_start(&self, offset: usize) -> usize {
+    let before = &self.inner[..offset.min(self.inner.len())];
+    before.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1)
+  }
+
+  /// UTF-8 byte offset of the char offset `offset`.
+  pub(crate) fn byte_offset(&self, offset: usize) -> usize {
+    let before = &self.inner[..offset.min(self.inner.len())];
+    before.iter().map(|c| c.len_utf8()).sum()
+  }
+
+  /// The `[offset, length]` of the char range `range` in UTF-16 code units, so that JS can
+  /// slice the text out of the string it parsed instead of copying it across.
+  pub(crate) fn utf16_span(&self, range: Range<usize>) -> [u32; 2] {
+    let start = self.utf16_offset(range.start);
+    let end = self.utf16_offset(range.end);
+    [start as u32, (end - start) as u32]
+  }
+
+  /// UTF-16 offset of the char offset `offset`, the index of the JS string of the source,
+  /// in constant time after the first call.
+  fn utf16_offset(&self, offset: usize) -> usize {
+    let offset = offset.min(self.inner.len());
+    let table = self.utf16.get_or_init(|| {
+      if self.inner.iter().all(|c| c.len_utf16() == 1) {
+        return None;
+      }
+      let mut units = 0;
+      let mut table = Vec::with_capacity(self.inner.len() + 1);
+      table.push(0);
+      for c in &self.inner {
+        units += c.len_utf16() as u32;
+        table.push(units);
+      }
+      Some(table.into())
+    });
+    table
+      .as_ref()
+      .map_or(offset, |table| table[offset] as usize)
+  }
+
+  /// Char offset of the end of the line containing `offset`, excluding the line break.
+  pub(crate) fn line_end(&self, offset: usize) -> usize {
+    let rest = &self.inner[offset.min(self.inner.len())..];
+    let len = rest.iter().position(|&c| c == '\n').unwrap_or(rest.len());
+    let len = if len > 0 && rest[len - 1] == '\r' {
+      len - 1
+    } else {
+      len
+    };
+    offset + len
+  }
+
   fn accept_edit(&mut self, edit: &Edit<Self>) -> ts::Edit {
     let start_byte = edit.position;
     let old_end_byte = edit.position + edit.deleted_length;
@@ -71,6 +383,7 @@ impl Wrapper {
     let start_position = pos_for_char_offset(input, start_byte);
     let old_end_position = pos_for_char_offset(input, old_end_byte);
     input.splice(start_byte..old_end_byte, edit.inserted_text.clone());
+    self.utf16.take();
     let new_end_position = pos_for_char_offset(input, new_end_byte);
     ts::Edit::new(
       start_byte as u32,
@@ -104,18 +417,125 @@ pub struct WasmDoc {
   lang: WasmLang,
   source: Wrapper,
   pub(crate) tree: ts::Tree,
+  /// Set if only some ranges of the source are parsed, e.g. an injected language.
+  injected: bool,
+  /// Where the source comes from, see `withSourceMap`.
+  pub(crate) source_map: Option<Rc<SourceMap>>,
+  /// How the source was normalized before it was parsed, see `normalize`.
+  pub(crate) normalization: Option<Normalization>,
+  /// The unit of the positions of the doc's nodes and edits, see `positionEncoding`.
+  encoding: PositionEncoding,
 }
 
 impl WasmDoc {
   pub fn try_new(src: String, lang: WasmLang) -> Result<Self, SgWasmError> {
-    let source = Wrapper {
-      inner: src.chars().collect(),
-    };
+    let source = Wrapper::new(&src);
     let parser = lang.get_parser()?;
     let Some(tree) = parser.parse_with_string(&src.into(), None, None)? else {
       return Err(SgWasmError::FailedToParse);
     };
-    Ok(Self { source, lang, tree })
+    Ok(Self {
+      source,
+      lang,
+      tree,
+      injected: false,
+      source_map: None,
+      normalization: None,
+      encoding: PositionEncoding::default(),
+    })
+  }
+
+  /// Wrap `tree`, parsed from `src` by the app's own web-tree-sitter parser, without
+  /// parsing again. The tree is copied, so the app can still edit or delete it.
+  pub fn from_tree(src: String, tree: &ts::Tree, lang: WasmLang) -> Result<Self, JsError> {
+    // web-tree-sitter offsets are UTF-16 code units of the parsed string
+    let len = src.encode_utf16().count();
+    let end = tree.root_node().end_index() as usize;
+    if end > len {
+      let msg = format!("The tree ends at offset {end}, after the end of the source at {len}.");
+      return Err(JsError::new(&msg));
+    }
+    let ranges = tree.get_included_ranges();
+    let whole = ranges.length() == 1 && {
+      let range: ts::Range = ranges.get(0).unchecked_into();
+      range.start_index() == 0 && range.end_index() as usize >= len
+    };
+    Ok(Self {
+      source: Wrapper::new(&src),
+      lang,
+      tree: tree.copy(),
+      injected: !whole,
+      source_map: None,
+      normalization: None,
+      encoding: PositionEncoding::default(),
+    })
+  }
+
+  /// Parse only `ranges` of `source` as `lang`, for languages embedded in another.
+  /// The whole source is kept, so positions are the same as in the host document.
+  pub(crate) fn try_new_in_ranges(
+    source: Wrapper,
+    lang: WasmLang,
+    ranges: &[ts::Range],
+  ) -> Result<Self, SgWasmError> {
+    let parser = lang.get_parser()?;
+    let ranges: js_sys::Array = ranges.iter().collect();
+    let options = ts::ParseOptions::new(Some(&ranges));
+    let src = source.text();
+    let Some(tree) = parser.parse_with_string(&src.into(), None, Some(&options))? else {
+      return Err(SgWasmError::FailedToParse);
+    };
+    Ok(Self {
+      source,
+      lang,
+      tree,
+      injected: true,
+      source_map: None,
+      normalization: None,
+      encoding: PositionEncoding::default(),
+    })
+  }
+
+  /// The unit of the positions of the doc's nodes and edits.
+  pub fn encoding(&self) -> PositionEncoding {
+    self.encoding
+  }
+
+  /// Report positions in `encoding`. Set it before `set_source_map` and `set_normalized`,
+  /// whose segments are in that unit.
+  pub fn with_encoding(self, encoding: PositionEncoding) -> Self {
+    Self { encoding, ..self }
+  }
+
+  /// Report the ranges of the tree's nodes in the original document of `segments`.
+  pub fn set_source_map(&mut self, segments: Vec<Segment>) {
+    let map = SourceMap::new(segments, &self.source.inner, self.encoding);
+    self.source_map = Some(Rc::new(map));
+  }
+
+  /// Report the ranges of the tree's nodes in `original`, the source before it was
+  /// normalized to this doc's source, and then in the document of `segments` if any.
+  pub fn set_normalized(
+    &mut self,
+    normalized: Vec<Segment>,
+    restore: Normalization,
+    original: &str,
+    segments: Option<Vec<Segment>>,
+  ) {
+    let map = SourceMap::new(normalized, &self.source.inner, self.encoding);
+    let map = match segments {
+      Some(segments) => {
+        let original: Vec<char> = original.chars().collect();
+        map.then(SourceMap::new(segments, &original, self.encoding))
+      }
+      None => map,
+    };
+    self.source_map = Some(Rc::new(map));
+    self.normalization = Some(restore);
+  }
+
+  pub(crate) fn source_map(&self) -> Option<&SourceMap> {
+    self.source_map.as_deref()
   }
 }
 
@@ -242,7 +662,11 @@ impl Doc for WasmDoc {
     self.tree.edit(&edit);
     let parser = self.lang.get_parser().map_err(|e| e.to_string())?;
     let src = self.source.inner.iter().collect::<String>();
-    let parse_ret = parser.parse_with_string(&src.into(), Some(&self.tree), None);
+    // the edited tree has the included ranges shifted by the edit
+    let options = self
+      .injected
+      .then(|| ts::ParseOptions::new(Some(&self.tree.get_included_ranges())));
+    let parse_ret = parser.parse_with_string(&src.into(), Some(&self.tree), options.as_ref());
     let Some(tree) = parse_ret.map_err(|e| format!("{e:?}"))? else {
       return Err("Failed to parse".to_string());
     };
@@ -0,0 +1,153 @@
+//! Edits of several matches or rules for `applyEdits`, applied in one pass after their
+//! conflicts are resolved.
+//!
+//! Two edits conflict if they replace overlapping text, or one inserts text strictly
+//! inside the text the other replaces. Insertions at the same offset do not conflict and
+//! are applied in the order given, and an edit identical to an applied one is a duplicate,
+//! like the same fix found by two rules. Edits are kept greedily, by position for the
+//! `first` and `error` strategies and by `priority` and then position for `priority`.
+
+use crate::encoding::EncodingOption;
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use wasm_bindgen::prelude::*;
+
+/// An edit like `WasmEdit`, with the priority of the `priority` strategy.
+#[derive(Deserialize)]
+pub struct EditInput {
+  start_pos: u32,
+  end_pos: u32,
+  inserted_text: String,
+  #[serde(default)]
+  priority: i32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Strategy {
+  /// keep the edit starting first, the default
+  #[default]
+  First,
+  /// keep the edit of the highest priority
+  Priority,
+  /// throw for the first conflict
+  Error,
+}
+
+#[derive(Default, Deserialize)]
+pub struct ApplyOptions {
+  #[serde(default)]
+  strategy: Strategy,
+  #[serde(flatten)]
+  encoding: EncodingOption,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedEdit {
+  /// the index of the edit in the input
+  index: usize,
+  /// the index of the applied edit it conflicts with or duplicates
+  conflicts_with: usize,
+  /// `overlap` or `duplicate`
+  reason: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct AppliedEdits {
+  code: String,
+  /// the indices of the applied edits in the input, in order of position
+  applied: Vec<usize>,
+  skipped: Vec<SkippedEdit>,
+}
+
+fn conflicts(a: &Range<usize>, b: &Range<usize>) -> bool {
+  if a.is_empty() {
+    return b.start < a.start && a.start < b.end;
+  }
+  if b.is_empty() {
+    return a.start < b.start && b.start < a.end;
+  }
+  a.start < b.end && b.start < a.end
+}
+
+/// Apply `edits` to `src` with conflicts resolved by `options`.
+pub fn apply(
+  src: &str,
+  edits: Vec<EditInput>,
+  options: ApplyOptions,
+) -> Result<AppliedEdits, JsError> {
+  let old_content: Vec<char> = src.chars().collect();
+  let encoding = options.encoding.position_encoding;
+  let mut ranges = Vec::with_capacity(edits.len());
+  for (index, edit) in edits.iter().enumerate() {
+    let start = encoding.decode_offset(&old_content, edit.start_pos as usize);
+    let end = encoding.decode_offset(&old_content, edit.end_pos as usize);
+    if start > end || end > old_content.len() {
+      let msg = format!("Edit {index} is out of the bounds of the source.");
+      return Err(JsError::new(&msg));
+    }
+    ranges.push(start..end);
+  }
+  let mut order: Vec<_> = (0..edits.len()).collect();
+  match options.strategy {
+    Strategy::Priority => order.sort_by_key(|i| (Reverse(edits[*i].priority), ranges[*i].start)),
+    _ => order.sort_by_key(|i| ranges[*i].start),
+  }
+  // the applied edits by start, end and index, which do not conflict with each other
+  let mut kept = BTreeSet::new();
+  let mut skipped = vec![];
+  for index in order {
+    let range = &ranges[index];
+    let text = &edits[index].inserted_text;
+    let duplicate = kept
+      .range((range.start, range.end, 0)..=(range.start, range.end, usize::MAX))
+      .find(|(_, _, kept)| edits[*kept].inserted_text == *text);
+    if let Some(&(_, _, kept_index)) = duplicate {
+      skipped.push(SkippedEdit {
+        index,
+        conflicts_with: kept_index,
+        reason: "duplicate",
+      });
+      continue;
+    }
+    // applied edits are disjoint, so their ends grow with their starts
+    let overlap = kept
+      .range(..(range.end, 0, 0))
+      .rev()
+      .take_while(|(_, end, _)| *end > range.start)
+      .find(|(start, end, _)| conflicts(&(*start..*end), range));
+    let Some(&(_, _, kept_index)) = overlap else {
+      kept.insert((range.start, range.end, index));
+      continue;
+    };
+    if matches!(options.strategy, Strategy::Error) {
+      let msg = format!("Edit {index} overlaps edit {kept_index}.");
+      return Err(JsError::new(&msg));
+    }
+    skipped.push(SkippedEdit {
+      index,
+      conflicts_with: kept_index,
+      reason: "overlap",
+    });
+  }
+  skipped.sort_by_key(|s| s.index);
+  let mut new_content: Vec<char> = Vec::with_capacity(old_content.len());
+  let mut start = 0;
+  let mut applied = vec![];
+  for (edit_start, edit_end, index) in kept {
+    new_content.extend(&old_content[start..edit_start]);
+    new_content.extend(edits[index].inserted_text.chars());
+    start = edit_end;
+    applied.push(index);
+  }
+  new_content.extend(&old_content[start..]);
+  Ok(AppliedEdits {
+    code: new_content.into_iter().collect(),
+    applied,
+    skipped,
+  })
+}
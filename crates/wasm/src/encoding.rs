@@ -0,0 +1,90 @@
+//! The unit of columns and offsets exchanged with JS, see `positionEncoding`.
+//!
+//! Positions are char offsets into the source inside the module. They are converted
+//! where they cross the API: node ranges, pattern dumps, edits and the offsets of
+//! `edit`, `subtree`, `commitEdits` and `positionToOffset`. Lines are always zero-based
+//! line numbers. A document keeps the encoding it was parsed with, so its node ranges
+//! and edits use the same unit.
+
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionEncoding {
+  /// UTF-8 bytes, like the byte offsets of the CLI.
+  Utf8,
+  /// UTF-16 code units, like JS string indices and LSP columns.
+  Utf16,
+  /// Unicode code points.
+  #[default]
+  Char,
+}
+
+/// The `positionEncoding` option, flattened into the options of functions that
+/// report positions without a document to take it from.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodingOption {
+  #[serde(default)]
+  pub position_encoding: PositionEncoding,
+}
+
+impl PositionEncoding {
+  fn len(self, chars: &[char]) -> usize {
+    match self {
+      Self::Utf8 => chars.iter().map(|c| c.len_utf8()).sum(),
+      Self::Utf16 => chars.iter().map(|c| c.len_utf16()).sum(),
+      Self::Char => chars.len(),
+    }
+  }
+
+  /// The char offset `offset` of `src` in this encoding.
+  pub fn encode_offset(self, src: &[char], offset: usize) -> usize {
+    self.len(&src[..offset.min(src.len())])
+  }
+
+  /// The column of the char offset `offset` of `src` in this encoding.
+  pub fn encode_column(self, src: &[char], offset: usize) -> usize {
+    let before = &src[..offset.min(src.len())];
+    let line_start = before.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+    self.len(&before[line_start..])
+  }
+
+  /// The char offset of `encoded`, an offset of `src` in this encoding.
+  /// An offset inside a char is the char's start, one past the end stays past the end.
+  pub fn decode_offset(self, src: &[char], encoded: usize) -> usize {
+    if self == Self::Char {
+      return encoded;
+    }
+    let mut units = 0;
+    for (i, c) in src.iter().enumerate() {
+      units += self.len(std::slice::from_ref(c));
+      if units > encoded {
+        return i;
+      }
+    }
+    src.len() + (encoded - units)
+  }
+
+  /// The char offset of `column`, a column in this encoding, on the zero-based
+  /// `line` of `src`. Columns past the end of the line are the line's end, like in LSP.
+  /// Returns `None` if `src` has no such line.
+  pub fn decode_position(self, src: &[char], line: usize, column: usize) -> Option<usize> {
+    let mut line_starts = std::iter::once(0).chain(
+      src
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| c == '\n')
+        .map(|(i, _)| i + 1),
+    );
+    let start = line_starts.nth(line)?;
+    let len = src[start..].iter().position(|&c| c == '\n');
+    let end = len.map_or(src.len(), |len| start + len);
+    Some((start + self.decode_offset(&src[start..end], column)).min(end))
+  }
+}
+
+/// The char offset `offset` of `src` in UTF-8 bytes, whatever the encoding.
+pub fn byte_offset(src: &[char], offset: usize) -> usize {
+  PositionEncoding::Utf8.len(&src[..offset.min(src.len())])
+}
@@ -0,0 +1,293 @@
+//! Plain-language explanations of rules for `explainRule`, e.g. for reviewing rule intent.
+//!
+//! The rule is explained from its normalized form, see `dumpRule`, so a rule object with
+//! several keys reads as an `all` in evaluation order and utility rules are inlined.
+
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_config::{RelationDump, RuleCoreDump, RuleDump, StopByDump, StrictnessStyle};
+use ast_grep_core::matcher::{Matcher, Pattern};
+use ast_grep_core::Language;
+use serde::Serialize;
+
+/// One rule of the explained rule, with the rules it is made of as `children`.
+#[derive(Serialize)]
+pub struct ExplanationNode {
+  /// the rule's `type` in `dumpRule`, or `stopBy` for the rule a relational rule stops at
+  #[serde(rename = "type")]
+  pub kind: &'static str,
+  /// what this rule requires of a node, without its children, e.g. `is inside a node that`
+  pub text: String,
+  /// the whole subtree as one sentence
+  pub summary: String,
+  pub children: Vec<ExplanationNode>,
+}
+
+#[derive(Serialize)]
+pub struct ConstraintExplanation {
+  /// the constrained meta variable, without its `$`
+  pub name: String,
+  pub rule: ExplanationNode,
+}
+
+#[derive(Serialize)]
+pub struct RuleExplanation {
+  /// the rule and its constraints as one sentence
+  pub summary: String,
+  pub rule: ExplanationNode,
+  pub constraints: Vec<ConstraintExplanation>,
+}
+
+pub fn explain(dump: RuleCoreDump, lang: WasmLang) -> RuleExplanation {
+  let explainer = Explainer { lang };
+  let rule = explainer.rule(dump.rule);
+  let constraints: Vec<_> = dump
+    .constraints
+    .into_iter()
+    .map(|(name, rule)| ConstraintExplanation {
+      name,
+      rule: explainer.rule(rule),
+    })
+    .collect();
+  let mut summary = format!("A node that {}", rule.summary);
+  let sigil = lang.meta_var_char();
+  for (i, constraint) in constraints.iter().enumerate() {
+    let joint = if i == 0 { ", where" } else { " and" };
+    let (name, rule) = (&constraint.name, &constraint.rule.summary);
+    summary.push_str(&format!("{joint} {sigil}{name} {rule}"));
+  }
+  summary.push('.');
+  RuleExplanation {
+    summary,
+    rule,
+    constraints,
+  }
+}
+
+struct Explainer {
+  lang: WasmLang,
+}
+
+/// `a` or `an` before `name`, by its spelling.
+fn article(name: &str) -> &'static str {
+  let vowel = name.starts_with(|c: char| "aeiouAEIOU".contains(c));
+  if vowel {
+    "an"
+  } else {
+    "a"
+  }
+}
+
+fn leaf(kind: &'static str, text: String) -> ExplanationNode {
+  ExplanationNode {
+    kind,
+    summary: text.clone(),
+    text,
+    children: vec![],
+  }
+}
+
+/// A rule whose summary is `text` followed by the summaries of its children.
+fn parent(kind: &'static str, text: String, children: Vec<ExplanationNode>) -> ExplanationNode {
+  let rest: Vec<_> = children.iter().map(nested).collect();
+  ExplanationNode {
+    kind,
+    summary: format!("{text} {}", rest.join(" ")),
+    text,
+    children,
+  }
+}
+
+/// A summary of a composite rule, in parentheses if it is part of a larger sentence.
+fn nested(rule: &ExplanationNode) -> String {
+  match rule.kind {
+    "all" | "any" if rule.children.len() > 1 => format!("({})", rule.summary),
+    _ => rule.summary.clone(),
+  }
+}
+
+impl Explainer {
+  fn rule(&self, rule: RuleDump) -> ExplanationNode {
+    match rule {
+      RuleDump::Pattern {
+        context,
+        selector,
+        strictness,
+        parse_as,
+      } => leaf(
+        "pattern",
+        self.pattern(
+          &context,
+          selector.as_deref(),
+          strictness,
+          parse_as.as_deref(),
+        ),
+      ),
+      RuleDump::Kind { kind } => leaf("kind", format!("is {} `{kind}`", article(&kind))),
+      RuleDump::KindRegex { regex } => leaf("kind", format!("has a kind matching /{regex}/")),
+      RuleDump::Regex { regex } => leaf("regex", format!("has text matching /{regex}/")),
+      RuleDump::NthChild {
+        position,
+        of_rule,
+        reverse,
+      } => {
+        let position = match serde_json::to_value(&position) {
+          Ok(serde_json::Value::String(formula)) => format!("a child at position `{formula}`"),
+          Ok(n) => format!("child number {n}"),
+          Err(_) => "a child".into(),
+        };
+        let from = if reverse {
+          ", counting from the last,"
+        } else {
+          ""
+        };
+        match of_rule {
+          None => leaf("nthChild", format!("is {position}{from}")),
+          Some(of_rule) => {
+            let text = format!("is {position}{from} among the siblings that");
+            parent("nthChild", text, vec![self.rule(*of_rule)])
+          }
+        }
+      }
+      RuleDump::Range { start, end } => {
+        let at = |line: usize, column: Option<usize>| match column {
+          Some(column) => format!("line {line}, column {column}"),
+          None => format!("line {line}"),
+        };
+        let (start, end) = (at(start.line, start.column), at(end.line, end.column));
+        leaf("range", format!("spans from {start} to {end}"))
+      }
+      RuleDump::Inside(relation) => self.relation("inside", relation),
+      RuleDump::Has(relation) => self.relation("has", relation),
+      RuleDump::Precedes(relation) => self.relation("precedes", relation),
+      RuleDump::Follows(relation) => self.relation("follows", relation),
+      RuleDump::HasLeadingComment { regex } => leaf(
+        "hasLeadingComment",
+        format!("has a leading comment matching /{regex}/"),
+      ),
+      RuleDump::HasComment { regex } => leaf(
+        "hasComment",
+        format!("contains a comment matching /{regex}/"),
+      ),
+      RuleDump::All { rules } => self.composite("all", "all of", rules, " AND "),
+      RuleDump::Any { rules } => self.composite("any", "any of", rules, " OR "),
+      RuleDump::Not { rule } => {
+        let rule = self.rule(*rule);
+        let summary = format!("NOT {}", nested(&rule));
+        ExplanationNode {
+          kind: "not",
+          text: "not".into(),
+          summary,
+          children: vec![rule],
+        }
+      }
+      RuleDump::Matches { id, rule } => {
+        let text = format!("matches the utility rule `{id}`");
+        match rule {
+          Some(rule) => parent("matches", format!("{text}, which"), vec![self.rule(*rule)]),
+          None => leaf("matches", text),
+        }
+      }
+      RuleDump::Custom { name } => leaf("custom", format!("passes the custom matcher `{name}`")),
+    }
+  }
+
+  fn pattern(
+    &self,
+    context: &str,
+    selector: Option<&str>,
+    strictness: StrictnessStyle,
+    parse_as: Option<&str>,
+  ) -> String {
+    let mut text = match selector {
+      Some(selector) => format!("is the `{selector}` in the pattern `{context}`"),
+      None => format!("matches the pattern `{context}`"),
+    };
+    if let Some(kind) = parse_as {
+      text.push_str(&format!(" parsed in {} `{kind}`", article(kind)));
+    }
+    let compiled = match (parse_as, selector) {
+      (Some(kind), selector) => Pattern::parse_as(context, kind, selector, self.lang),
+      (None, Some(selector)) => Pattern::contextual(context, selector, self.lang),
+      (None, None) => Pattern::try_new(context, self.lang),
+    };
+    let kinds = compiled.ok().and_then(|p| p.potential_kinds());
+    let mut kinds = kinds.iter().flatten();
+    if let (Some(kind), None) = (kinds.next(), kinds.next()) {
+      let name = self.lang.get_ts_language().node_kind_for_id(kind as u16);
+      if let Some(name) = name.filter(|_| selector.is_none()) {
+        text.push_str(&format!(" ({} `{name}`)", article(&name)));
+      }
+    }
+    let (level, skip_kinds) = match strictness {
+      StrictnessStyle::Level(level) => (Some(level), vec![]),
+      StrictnessStyle::Custom { base, skip_kinds } => (base, skip_kinds),
+    };
+    let level = serde_json::to_value(level).unwrap_or_default();
+    if let Some(level) = level.as_str().filter(|l| *l != "smart") {
+      text.push_str(&format!(" with `{level}` strictness"));
+    }
+    if !skip_kinds.is_empty() {
+      let kinds: Vec<_> = skip_kinds.iter().map(|k| format!("`{k}`")).collect();
+      text.push_str(&format!(" skipping {} nodes", kinds.join(", ")));
+    }
+    text
+  }
+
+  fn composite(
+    &self,
+    kind: &'static str,
+    text: &str,
+    rules: Vec<RuleDump>,
+    joint: &str,
+  ) -> ExplanationNode {
+    let children: Vec<_> = rules.into_iter().map(|r| self.rule(r)).collect();
+    let summary = match children.as_slice() {
+      [] if kind == "all" => "is any node".into(),
+      [] => "matches nothing".into(),
+      [only] => only.summary.clone(),
+      _ => children.iter().map(nested).collect::<Vec<_>>().join(joint),
+    };
+    ExplanationNode {
+      kind,
+      text: text.into(),
+      summary,
+      children,
+    }
+  }
+
+  fn relation(&self, kind: &'static str, relation: RelationDump) -> ExplanationNode {
+    let RelationDump {
+      rule,
+      stop_by,
+      field,
+    } = relation;
+    let neighbor = matches!(stop_by, StopByDump::Neighbor);
+    let field = field.map(|f| format!("`{f}`"));
+    let text = match (kind, neighbor, field) {
+      ("inside", true, None) => "is a child of a node that".into(),
+      ("inside", true, Some(field)) => format!("is the {field} of a node that"),
+      ("inside", false, None) => "is inside a node that".into(),
+      ("inside", false, Some(field)) => format!("is inside the {field} of a node that"),
+      ("has", true, None) => "has a child that".into(),
+      ("has", true, Some(field)) => format!("has a {field} that"),
+      ("has", false, None) => "has a descendant that".into(),
+      ("has", false, Some(field)) => format!("has a {field} containing a node that"),
+      ("precedes", true, _) => "is followed by a sibling that".into(),
+      ("precedes", false, _) => "comes before a sibling that".into(),
+      (_, true, _) => "follows a sibling that".into(),
+      (_, false, _) => "comes after a sibling that".into(),
+    };
+    let mut explained = parent(kind, text, vec![self.rule(*rule)]);
+    if let StopByDump::Rule(stop) = stop_by {
+      let stop = parent(
+        "stopBy",
+        "stopping at a node that".into(),
+        vec![self.rule(*stop)],
+      );
+      explained.summary = format!("{}, {}", explained.summary, stop.summary);
+      explained.children.push(stop);
+    }
+    explained
+  }
+}
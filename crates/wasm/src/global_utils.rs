@@ -0,0 +1,78 @@
+//! Utility rules shared by every rule of a language, registered by `registerUtils`.
+//!
+//! Like the CLI's `utilDirs`, a registered util can be referenced by `matches` from any
+//! rule of its language, in addition to the rule's own local `utils`.
+
+use crate::compiled;
+use crate::custom_matcher;
+use crate::doc::error_chain;
+use crate::wasm_lang::{self, WasmLang};
+
+use ast_grep_config::{DeserializeEnv, GlobalRules};
+use serde::Deserialize;
+use serde_yaml::with::singleton_map_recursive;
+use serde_yaml::Value as YamlValue;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+  static GLOBALS: RefCell<HashMap<WasmLang, GlobalRules>> = RefCell::new(HashMap::new());
+}
+
+/// Replace the utils of `lang_name` with those in `utils_yaml`, documents like the CLI's
+/// util files separated by `---`, with `language` defaulting to `lang_name`.
+/// On error, the previously registered utils stay in place.
+pub fn register(lang_name: &str, utils_yaml: &str) -> Result<(), JsError> {
+  let lang: WasmLang = lang_name
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let mut utils = vec![];
+  for document in serde_yaml::Deserializer::from_str(utils_yaml) {
+    let mut value = YamlValue::deserialize(document).map_err(|e| error_chain(&e))?;
+    value.apply_merge().map_err(|e| error_chain(&e))?;
+    let values = match value {
+      YamlValue::Sequence(seq) => seq,
+      YamlValue::Null => vec![],
+      value => vec![value],
+    };
+    for mut value in values {
+      if let YamlValue::Mapping(map) = &mut value {
+        let language = map.entry("language".into()).or_insert(lang_name.into());
+        if language.as_str() != Some(lang_name) {
+          let msg = format!("Utils registered for `{lang_name}` cannot be of another language.");
+          return Err(JsError::new(&msg));
+        }
+      }
+      let util = singleton_map_recursive::deserialize(value).map_err(|e| error_chain(&e))?;
+      utils.push(util);
+    }
+  }
+  let globals = if utils.is_empty() {
+    GlobalRules::default()
+  } else {
+    let custom = custom_matcher::matchers();
+    DeserializeEnv::<WasmLang>::parse_global_utils_with(utils, custom)
+      .map_err(|e| error_chain(&e))?
+  };
+  GLOBALS.with(|g| g.borrow_mut().insert(lang, globals));
+  // cached matchers were compiled against the old utils
+  compiled::clear_cache();
+  Ok(())
+}
+
+/// Drop the utils of `lang`, which were compiled for the grammar it no longer has.
+pub fn forget(lang: WasmLang) {
+  GLOBALS.with(|g| g.borrow_mut().remove(&lang));
+}
+
+/// The utils registered for `lang`, empty if none are, with the registered custom matchers.
+pub fn globals(lang: WasmLang) -> GlobalRules {
+  let globals = GLOBALS.with(|g| g.borrow().get(&lang).cloned().unwrap_or_default());
+  globals.with_custom_matchers(custom_matcher::matchers())
+}
+
+/// A `DeserializeEnv` of `lang` with its registered utils.
+pub fn env(lang: WasmLang) -> DeserializeEnv<WasmLang> {
+  DeserializeEnv::new(lang).with_globals(&globals(lang))
+}
@@ -0,0 +1,454 @@
+//! The module graph of a project scan, for rules with `imports`, see `Project.scanFiles`.
+//!
+//! Every file's imports bind local names to a module and a name it exports. Modules
+//! that are files of the scan are followed through their re-exports, so a rule can
+//! require `exec` to come from `child_process` even if it is imported from a local
+//! module re-exporting it. Imports are recognized by the kinds and fields of the
+//! JavaScript/TypeScript, Python and Go grammars.
+
+use crate::doc::WasmDoc;
+use crate::project::{join, normalize};
+
+use ast_grep_core::{Node, NodeMatch};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+type SgNode<'r> = Node<'r, WasmDoc>;
+
+/// Where a local name comes from.
+#[derive(Clone)]
+struct Binding {
+  /// the path of a file of the scan, or the module as imported
+  module: String,
+  /// the exported name, `None` for the module itself
+  name: Option<String>,
+}
+
+/// A module a meta variable must be imported from, an entry of a rule's `imports`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredImport {
+  from: String,
+  /// the name it must be imported as, any name of the module if unset
+  name: Option<String>,
+}
+
+/// The `imports` of a rule, keyed by meta variable names without `$`.
+pub type Requirements = HashMap<String, RequiredImport>;
+
+#[derive(Default)]
+struct Module {
+  /// names bound by imports
+  bindings: HashMap<String, Binding>,
+  /// names other files import, bound like `bindings`
+  exports: HashMap<String, Binding>,
+  /// modules re-exported by `export * from`
+  star_exports: Vec<String>,
+}
+
+/// The imports of every file of a scan.
+pub struct ModuleGraph {
+  modules: HashMap<String, Module>,
+}
+
+impl ModuleGraph {
+  /// Collect the imports of `files`, pairs of a path and the root of its tree.
+  pub fn new(files: &[(String, SgNode<'_>)]) -> Self {
+    let paths: HashSet<_> = files.iter().map(|(path, _)| normalize(path)).collect();
+    let modules = files
+      .iter()
+      .map(|(path, root)| {
+        let path = normalize(path);
+        let module = collect(&path, root, &paths);
+        (path, module)
+      })
+      .collect();
+    Self { modules }
+  }
+
+  /// Whether every meta variable of `requirements` in `nm` is a name imported from
+  /// its module in the file `path`, e.g. `exec` or `cp.exec` for `child_process`.
+  pub fn satisfies(&self, path: &str, nm: &NodeMatch<'_, WasmDoc>, req: &Requirements) -> bool {
+    let env = nm.get_env();
+    req.iter().all(|(var, required)| {
+      let Some(node) = env.get_match(var) else {
+        return false;
+      };
+      let Some(binding) = self.lookup(&normalize(path), &node.text()) else {
+        return false;
+      };
+      binding.module == required.from
+        && required
+          .name
+          .as_ref()
+          .map_or(true, |name| binding.name.as_ref() == Some(name))
+    })
+  }
+
+  /// The binding of an identifier or a member access like `cp.exec` in `path`.
+  fn lookup(&self, path: &str, text: &str) -> Option<Binding> {
+    let mut segments = text.split('.').map(str::trim);
+    let binding = self.modules.get(path)?.bindings.get(segments.next()?)?;
+    let rest: Vec<_> = segments.collect();
+    if rest.is_empty() {
+      return Some(self.resolve(binding.clone()));
+    }
+    let name = match &binding.name {
+      // the default export of a package is its namespace in CommonJS
+      Some(name) if name != "default" || self.modules.contains_key(&binding.module) => {
+        format!("{name}.{}", rest.join("."))
+      }
+      _ => rest.join("."),
+    };
+    Some(self.resolve(Binding {
+      module: binding.module.clone(),
+      name: Some(name),
+    }))
+  }
+
+  /// Follow `binding` through re-exports to the module it is defined in.
+  fn resolve(&self, binding: Binding) -> Binding {
+    let mut seen = HashSet::new();
+    let mut current = binding;
+    while seen.insert((current.module.clone(), current.name.clone())) {
+      let Some(next) = self.exported(&current, &mut HashSet::new()) else {
+        break;
+      };
+      current = next;
+    }
+    current
+  }
+
+  /// What the module of `binding` re-exports its name as, if it is a file of the scan.
+  fn exported(&self, binding: &Binding, visited: &mut HashSet<String>) -> Option<Binding> {
+    let name = binding.name.as_ref()?;
+    if !visited.insert(binding.module.clone()) {
+      return None;
+    }
+    let module = self.modules.get(&binding.module)?;
+    // `ns.name` of `import * as ns` re-exported under `ns`
+    let head = name.split('.').next().unwrap_or(name);
+    if let Some(exported) = module.exports.get(name) {
+      return Some(exported.clone());
+    }
+    if let Some(exported) = module.exports.get(head).filter(|_| head != name) {
+      let rest = &name[head.len() + 1..];
+      let name = match &exported.name {
+        Some(prefix) => format!("{prefix}.{rest}"),
+        None => rest.to_string(),
+      };
+      return Some(Binding {
+        module: exported.module.clone(),
+        name: Some(name),
+      });
+    }
+    module.star_exports.iter().find_map(|star| {
+      let star = Binding {
+        module: star.clone(),
+        name: Some(name.clone()),
+      };
+      self.exported(&star, visited)
+    })
+  }
+}
+
+/// The imports of the file `path`, resolved to `paths` where they are files of the scan.
+fn collect(path: &str, root: &SgNode<'_>, paths: &HashSet<String>) -> Module {
+  let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+  let mut module = Module::default();
+  let mut is_python = false;
+  // `export { a as b }` of bindings that may be declared later
+  let mut local_exports = vec![];
+  for node in root.dfs() {
+    match node.kind().as_ref() {
+      "import_statement" if node.field("source").is_some() => {
+        js_import(&node, dir, paths, &mut module.bindings);
+      }
+      "import_statement" | "import_from_statement" => {
+        is_python = true;
+        python_import(&node, path, paths, &mut module.bindings);
+      }
+      "export_statement" => {
+        js_export(&node, dir, paths, &mut module, &mut local_exports);
+      }
+      "variable_declarator" => {
+        js_require(&node, dir, paths, &mut module.bindings);
+      }
+      "import_spec" => go_import(&node, &mut module.bindings),
+      _ => (),
+    }
+  }
+  for (local, exported) in local_exports {
+    if let Some(binding) = module.bindings.get(&local) {
+      module.exports.insert(exported, binding.clone());
+    }
+  }
+  // every name imported at the top level of a Python module can be imported from it
+  if is_python {
+    module.exports.extend(module.bindings.clone());
+  }
+  module
+}
+
+fn string_value(node: &SgNode<'_>) -> String {
+  let text = node.text();
+  text
+    .trim_matches(|c| matches!(c, '"' | '\'' | '`'))
+    .to_string()
+}
+
+/// The file a JavaScript specifier refers to, or the specifier of a package.
+fn js_module(specifier: &str, dir: &str, paths: &HashSet<String>) -> String {
+  if !specifier.starts_with('.') {
+    return specifier
+      .strip_prefix("node:")
+      .unwrap_or(specifier)
+      .to_string();
+  }
+  let base = join(dir, specifier);
+  const SUFFIXES: &[&str] = &[
+    "",
+    ".js",
+    ".jsx",
+    ".ts",
+    ".tsx",
+    ".mjs",
+    ".cjs",
+    "/index.js",
+    "/index.ts",
+  ];
+  SUFFIXES
+    .iter()
+    .map(|suffix| format!("{base}{suffix}"))
+    .find(|candidate| paths.contains(candidate))
+    .unwrap_or(base)
+}
+
+fn js_import(
+  node: &SgNode<'_>,
+  dir: &str,
+  paths: &HashSet<String>,
+  bindings: &mut HashMap<String, Binding>,
+) {
+  let Some(source) = node.field("source") else {
+    return;
+  };
+  let module = js_module(&string_value(&source), dir, paths);
+  let Some(clause) = node.children().find(|n| n.kind() == "import_clause") else {
+    return;
+  };
+  let bind = |name: Option<&str>| Binding {
+    module: module.clone(),
+    name: name.map(String::from),
+  };
+  for child in clause.children() {
+    match child.kind().as_ref() {
+      "identifier" => {
+        bindings.insert(child.text().to_string(), bind(Some("default")));
+      }
+      "namespace_import" => {
+        if let Some(local) = child.children().find(|n| n.kind() == "identifier") {
+          bindings.insert(local.text().to_string(), bind(None));
+        }
+      }
+      "named_imports" => {
+        for spec in child.children().filter(|n| n.kind() == "import_specifier") {
+          let Some(name) = spec.field("name") else {
+            continue;
+          };
+          let local = spec.field("alias").unwrap_or_else(|| name.clone());
+          bindings.insert(local.text().to_string(), bind(Some(&name.text())));
+        }
+      }
+      _ => (),
+    }
+  }
+}
+
+fn js_export(
+  node: &SgNode<'_>,
+  dir: &str,
+  paths: &HashSet<String>,
+  module: &mut Module,
+  local_exports: &mut Vec<(String, String)>,
+) {
+  if let Some(declaration) = node.field("declaration") {
+    // `export const { exec } = require('child_process')`
+    let mut bindings = HashMap::new();
+    for declarator in declaration.children() {
+      js_require(&declarator, dir, paths, &mut bindings);
+    }
+    module.exports.extend(bindings);
+    return;
+  }
+  let source = node
+    .field("source")
+    .map(|source| js_module(&string_value(&source), dir, paths));
+  let Some(clause) = node.children().find(|n| n.kind() == "export_clause") else {
+    // `export * from './shell'`
+    if let Some(source) = source {
+      module.star_exports.push(source);
+    }
+    return;
+  };
+  for spec in clause.children().filter(|n| n.kind() == "export_specifier") {
+    let Some(name) = spec.field("name") else {
+      continue;
+    };
+    let exported = spec.field("alias").unwrap_or_else(|| name.clone());
+    let (name, exported) = (name.text().to_string(), exported.text().to_string());
+    match &source {
+      Some(source) => {
+        let binding = Binding {
+          module: source.clone(),
+          name: Some(name),
+        };
+        module.exports.insert(exported, binding);
+      }
+      None => local_exports.push((name, exported)),
+    }
+  }
+}
+
+/// `const cp = require('child_process')` and `const { exec } = require(...)`.
+fn js_require(
+  node: &SgNode<'_>,
+  dir: &str,
+  paths: &HashSet<String>,
+  bindings: &mut HashMap<String, Binding>,
+) {
+  let (Some(name), Some(value)) = (node.field("name"), node.field("value")) else {
+    return;
+  };
+  if value.kind() != "call_expression"
+    || value
+      .field("function")
+      .map_or(true, |f| f.text() != "require")
+  {
+    return;
+  }
+  let Some(specifier) = value
+    .field("arguments")
+    .and_then(|args| args.children().find(|n| n.kind() == "string"))
+  else {
+    return;
+  };
+  let module = js_module(&string_value(&specifier), dir, paths);
+  match name.kind().as_ref() {
+    "identifier" => {
+      let binding = Binding { module, name: None };
+      bindings.insert(name.text().to_string(), binding);
+    }
+    "object_pattern" => {
+      for property in name.children() {
+        let (imported, local) = match property.kind().as_ref() {
+          "shorthand_property_identifier_pattern" => (property.clone(), property),
+          "pair_pattern" => match (property.field("key"), property.field("value")) {
+            (Some(key), Some(value)) if value.kind() == "identifier" => (key, value),
+            _ => continue,
+          },
+          _ => continue,
+        };
+        let binding = Binding {
+          module: module.clone(),
+          name: Some(imported.text().to_string()),
+        };
+        bindings.insert(local.text().to_string(), binding);
+      }
+    }
+    _ => (),
+  }
+}
+
+/// The file a Python module name refers to, or the dotted name of a package.
+/// `module_name` is a `dotted_name` or a `relative_import` like `..pkg`.
+fn python_module(module_name: &SgNode<'_>, path: &str, paths: &HashSet<String>) -> String {
+  if module_name.kind() != "relative_import" {
+    return python_module_of(&module_name.text(), paths);
+  }
+  let dots = module_name
+    .children()
+    .find(|n| n.kind() == "import_prefix")
+    .map_or(0, |prefix| prefix.text().len());
+  let mut base = path.rsplit_once('/').map_or("", |(dir, _)| dir).to_string();
+  for _ in 1..dots {
+    base = join(&base, "..");
+  }
+  if let Some(dotted) = module_name.children().find(|n| n.kind() == "dotted_name") {
+    base = join(&base, &dotted.text().replace('.', "/"));
+  }
+  let candidates = [format!("{base}.py"), join(&base, "__init__.py")];
+  candidates
+    .into_iter()
+    .find(|c| paths.contains(c))
+    .unwrap_or(base)
+}
+
+fn python_import(
+  node: &SgNode<'_>,
+  path: &str,
+  paths: &HashSet<String>,
+  bindings: &mut HashMap<String, Binding>,
+) {
+  let from = node
+    .field("module_name")
+    .map(|module_name| python_module(&module_name, path, paths));
+  for name in node.field_children("name") {
+    let (imported, local) = if name.kind() == "aliased_import" {
+      let (Some(imported), Some(alias)) = (name.field("name"), name.field("alias")) else {
+        continue;
+      };
+      (imported, Some(alias))
+    } else {
+      (name, None)
+    };
+    let imported = imported.text().to_string();
+    let (local, binding) = match &from {
+      // `from subprocess import call as run`
+      Some(module) => {
+        let binding = Binding {
+          module: module.clone(),
+          name: Some(imported.clone()),
+        };
+        (local.map_or(imported, |l| l.text().to_string()), binding)
+      }
+      // `import subprocess as sp`, while `import os.path` binds `os`
+      None => match local {
+        Some(local) => {
+          let module = python_module_of(&imported, paths);
+          (local.text().to_string(), Binding { module, name: None })
+        }
+        None => {
+          let head = imported.split('.').next().unwrap_or(&imported).to_string();
+          let module = python_module_of(&head, paths);
+          (head, Binding { module, name: None })
+        }
+      },
+    };
+    bindings.insert(local, binding);
+  }
+}
+
+/// `python_module` of an absolute dotted name.
+fn python_module_of(dotted: &str, paths: &HashSet<String>) -> String {
+  let base = normalize(&dotted.replace('.', "/"));
+  let candidates = [format!("{base}.py"), join(&base, "__init__.py")];
+  candidates
+    .into_iter()
+    .find(|c| paths.contains(c))
+    .unwrap_or_else(|| dotted.to_string())
+}
+
+/// `import "os/exec"` binds `exec`, `import sh "os/exec"` binds `sh`.
+fn go_import(node: &SgNode<'_>, bindings: &mut HashMap<String, Binding>) {
+  let Some(import_path) = node.field("path") else {
+    return;
+  };
+  let module = string_value(&import_path);
+  let local = match node.field("name") {
+    Some(name) if name.kind() == "package_identifier" => name.text().to_string(),
+    Some(_) => return,
+    None => module.rsplit('/').next().unwrap_or(&module).to_string(),
+  };
+  bindings.insert(local, Binding { module, name: None });
+}
@@ -0,0 +1,156 @@
+//! Patterns generalized from example code for `inferPattern`.
+//!
+//! A single example keeps its code and abstracts its literals, since values are the most
+//! incidental part of a snippet. Two examples are compared in depth: identical subtrees are
+//! kept, named nodes that differ become meta variables, and the differing items between the
+//! same delimiters, like arguments of different counts, become a multi meta variable.
+//! Grammars share no kind names, so like `metrics` literals are recognized by the words
+//! of kind names, e.g. `string` in `string_literal`.
+
+use crate::doc::WasmDoc;
+
+use ast_grep_core::source::Content;
+use ast_grep_core::{Doc, Language, Node};
+use std::collections::HashMap;
+use std::ops::Range;
+
+type SgNode<'r> = Node<'r, WasmDoc>;
+
+/// Words of literal kinds, like `number`, `integer_literal` or `interpreted_string_literal`.
+const LITERALS: &[&str] = &[
+  "string", "number", "integer", "int", "float", "char", "literal", "true", "false", "null", "nil",
+  "none",
+];
+
+/// A part of the first example replaced by a meta variable.
+struct Hole {
+  range: Range<usize>,
+  multi: bool,
+  /// the texts in both examples, so that the same difference gets the same name
+  key: (String, String),
+}
+
+/// Generalize `a`, and `b` if given, into a pattern, or `None` if the examples have
+/// nothing but their root in common.
+pub fn infer(a: &SgNode, b: Option<&SgNode>) -> Option<String> {
+  let mut holes = vec![];
+  match b {
+    Some(b) => {
+      if !align(a, b, &mut holes) {
+        return None;
+      }
+    }
+    None => literals(a, &mut holes),
+  }
+  let single = b.is_none();
+  let chars = a.get_doc().get_source();
+  let root = a.range();
+  let var = a.lang().meta_var_char();
+  let mut names = HashMap::new();
+  let mut count = 0;
+  let mut pattern = String::new();
+  let mut start = root.start;
+  let mut lone = None;
+  for hole in &holes {
+    // the same difference is the same capture, but a single example has no evidence of it
+    let name = match names.get(&hole.key) {
+      Some(name) if !single => name,
+      _ => {
+        count += 1;
+        names.entry(hole.key.clone()).or_insert(format!("V{count}"))
+      }
+    };
+    pattern.extend(chars.get_range(start..hole.range.start));
+    let sigil = if hole.multi { 3 } else { 1 };
+    let var: String = std::iter::repeat(var)
+      .take(sigil)
+      .chain(name.chars())
+      .collect();
+    pattern.push_str(&var);
+    lone = Some(var);
+    start = hole.range.end;
+  }
+  pattern.extend(chars.get_range(start..root.end));
+  let pattern = pattern.trim();
+  // a lone meta variable matches anything
+  (holes.len() != 1 || lone.as_deref() != Some(pattern)).then(|| pattern.to_string())
+}
+
+fn is_literal(kind: &str) -> bool {
+  kind
+    .split('_')
+    .any(|word| LITERALS.contains(&word.to_ascii_lowercase().as_str()))
+}
+
+/// The outermost literals of `node`, in document order.
+fn literals(node: &SgNode, holes: &mut Vec<Hole>) {
+  if node.is_named() && is_literal(&node.kind()) {
+    let text = node.text().to_string();
+    holes.push(Hole {
+      range: node.range(),
+      multi: false,
+      key: (text.clone(), text),
+    });
+    return;
+  }
+  for child in node.children() {
+    literals(&child, holes);
+  }
+}
+
+/// Collect the holes making `a` match `b` too, or return false if `a` as a whole
+/// has to be replaced.
+fn align(a: &SgNode, b: &SgNode, holes: &mut Vec<Hole>) -> bool {
+  if a.text() == b.text() {
+    return true;
+  }
+  // a literal is a value as a whole, not quotes around a meta variable
+  if a.kind() != b.kind() || is_literal(&a.kind()) {
+    return false;
+  }
+  let ca: Vec<_> = a.children().collect();
+  let cb: Vec<_> = b.children().collect();
+  if ca.is_empty() || cb.is_empty() {
+    return false;
+  }
+  if ca.len() == cb.len() {
+    let mut inner = vec![];
+    for (x, y) in ca.iter().zip(&cb) {
+      if align(x, y, &mut inner) {
+        continue;
+      }
+      // unnamed tokens like operators can't be captured by a meta variable
+      if !x.is_named() || !y.is_named() {
+        return false;
+      }
+      inner.push(Hole {
+        range: x.range(),
+        multi: false,
+        key: (x.text().to_string(), y.text().to_string()),
+      });
+    }
+    holes.extend(inner);
+    return true;
+  }
+  // items of different counts between the same delimiters, like arguments
+  let (first, last) = (ca.len() - 1, cb.len() - 1);
+  if ca.len() < 2
+    || cb.len() < 2
+    || ca[0].text() != cb[0].text()
+    || ca[first].text() != cb[last].text()
+  {
+    return false;
+  }
+  let items = |c: &[SgNode]| -> String {
+    c.iter()
+      .map(|n| n.text().to_string())
+      .collect::<Vec<_>>()
+      .join(" ")
+  };
+  holes.push(Hole {
+    range: ca[0].range().end..ca[first].range().start,
+    multi: true,
+    key: (items(&ca[1..first]), items(&cb[1..last])),
+  });
+  true
+}
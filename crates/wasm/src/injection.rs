@@ -0,0 +1,147 @@
+//! Languages embedded in other languages, e.g. JavaScript in HTML, for `registerInjections`.
+//!
+//! Like `languageInjections` in `sgconfig.yml`, a rule of the host language captures each
+//! region as `$CONTENT`, and the injected language is either fixed or read from `$LANG`.
+//! A region is parsed as its own tree over the whole host source with tree-sitter's
+//! included ranges, so positions in the injected tree are positions in the host document.
+
+use crate::doc::{error_chain, WasmDoc};
+use crate::global_utils;
+use crate::ts_types as ts;
+use crate::wasm_lang::{self, SgWasmError, WasmLang};
+
+use ast_grep_config::{RuleCore, SerializableRuleCore};
+use ast_grep_core::{AstGrep, Doc, Node};
+use serde::Deserialize;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+/// The injected language, e.g. `css`, or the languages `$LANG` can name, e.g. `[css, scss]`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Injected {
+  Static(String),
+  Dynamic(Vec<String>),
+}
+
+/// An injection rule in the format of `languageInjections` in `sgconfig.yml`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableInjection {
+  #[serde(flatten)]
+  core: SerializableRuleCore,
+  /// The language containing the others, e.g. html.
+  host_language: String,
+  injected: Injected,
+}
+
+struct Injection {
+  host: WasmLang,
+  rule: RuleCore,
+  injected: Injected,
+}
+
+thread_local! {
+  // rules are not `Send`, and JS workers have their own module instance anyway
+  static INJECTIONS: RefCell<Vec<Injection>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Replace the registered injections. Host languages must be registered already,
+/// injected languages only when a document containing them is parsed.
+pub fn register(injections: Vec<SerializableInjection>) -> Result<(), JsError> {
+  let mut compiled = Vec::with_capacity(injections.len());
+  for injection in injections {
+    let name = injection.host_language;
+    let host: WasmLang = name
+      .parse()
+      .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+    let rule = injection
+      .core
+      .get_matcher(global_utils::env(host))
+      .map_err(|e| error_chain(&e))?;
+    let defined = rule.defined_vars();
+    if !defined.contains("CONTENT") {
+      let msg = format!("Injection rule of `{name}` must capture the region as `$CONTENT`.");
+      return Err(JsError::new(&msg));
+    }
+    if matches!(injection.injected, Injected::Dynamic(_)) && !defined.contains("LANG") {
+      let msg = format!("Injection rule of `{name}` must capture the language as `$LANG`.");
+      return Err(JsError::new(&msg));
+    }
+    compiled.push(Injection {
+      host,
+      rule,
+      injected: injection.injected,
+    });
+  }
+  INJECTIONS.with(|injections| *injections.borrow_mut() = compiled);
+  Ok(())
+}
+
+/// Drop the injections hosted by `host`, whose rules were compiled for the grammar
+/// it no longer has.
+pub fn forget(host: WasmLang) {
+  INJECTIONS.with(|injections| injections.borrow_mut().retain(|i| i.host != host));
+}
+
+/// The embedded regions in `node` with their language names, in document order.
+/// A `$LANG` that is not one of the rule's `injected` languages is skipped.
+pub fn regions<'r>(node: &Node<'r, WasmDoc>) -> Vec<(String, Node<'r, WasmDoc>)> {
+  let host = *node.lang();
+  let mut found = vec![];
+  INJECTIONS.with(|injections| {
+    for injection in injections.borrow().iter().filter(|i| i.host == host) {
+      for nm in node.find_all(&injection.rule) {
+        let env = nm.get_env();
+        let Some(content) = env.get_match("CONTENT") else {
+          continue;
+        };
+        let lang = match &injection.injected {
+          Injected::Static(lang) => lang.clone(),
+          Injected::Dynamic(langs) => {
+            let Some(lang) = env.get_match("LANG").map(|n| n.text().to_string()) else {
+              continue;
+            };
+            if !langs.contains(&lang) {
+              continue;
+            }
+            lang
+          }
+        };
+        found.push((lang, content.clone()));
+      }
+    }
+  });
+  found.sort_by_key(|(_, region)| region.range().start);
+  found
+}
+
+/// Parse every region in `node`, or only those of `lang` if set.
+/// Regions of languages that are not registered are skipped.
+pub fn injected_roots(
+  node: &Node<'_, WasmDoc>,
+  lang: Option<&str>,
+) -> Result<Vec<AstGrep<WasmDoc>>, SgWasmError> {
+  let mut roots = vec![];
+  for (name, region) in regions(node) {
+    if lang.is_some_and(|lang| lang != name) {
+      continue;
+    }
+    let Ok(injected) = name.parse::<WasmLang>() else {
+      continue;
+    };
+    let ts = region.get_inner_node().0;
+    let range = ts::Range::new(
+      ts.start_index(),
+      ts.end_index(),
+      &ts.start_position(),
+      &ts.end_position(),
+    );
+    let source = node.get_doc().get_source().clone();
+    let mut doc = WasmDoc::try_new_in_ranges(source, injected, &[range])?
+      .with_encoding(node.get_doc().encoding());
+    doc.source_map = node.get_doc().source_map.clone();
+    roots.push(AstGrep::doc(doc));
+  }
+  Ok(roots)
+}
@@ -0,0 +1,94 @@
+//! Subresource integrity checks of grammar binaries, see `integrity` of
+//! `registerDynamicLanguage`.
+//!
+//! Like browsers, `integrity` may list several hashes separated by spaces, and the binary
+//! must match one of those of the strongest algorithm. Unlike browsers, an `integrity`
+//! without any supported hash is an error instead of no check.
+
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Algorithm {
+  Sha256,
+  Sha384,
+  Sha512,
+}
+
+impl Algorithm {
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "sha256" => Some(Self::Sha256),
+      "sha384" => Some(Self::Sha384),
+      "sha512" => Some(Self::Sha512),
+      _ => None,
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Self::Sha256 => "sha256",
+      Self::Sha384 => "sha384",
+      Self::Sha512 => "sha512",
+    }
+  }
+
+  fn digest(self, bytes: &[u8]) -> Vec<u8> {
+    match self {
+      Self::Sha256 => Sha256::digest(bytes).to_vec(),
+      Self::Sha384 => Sha384::digest(bytes).to_vec(),
+      Self::Sha512 => Sha512::digest(bytes).to_vec(),
+    }
+  }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8]) -> String {
+  let mut ret = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let n = chunk
+      .iter()
+      .enumerate()
+      .fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+    for i in 0..4 {
+      if i <= chunk.len() {
+        ret.push(BASE64[((n >> (18 - 6 * i)) & 63) as usize] as char);
+      } else {
+        ret.push('=');
+      }
+    }
+  }
+  ret
+}
+
+/// Fail unless the grammar binary of `name` matches `integrity`, e.g. `sha384-<base64>`.
+pub fn verify(name: &str, integrity: &str, bytes: &[u8]) -> Result<(), JsError> {
+  let hashes: Vec<_> = integrity
+    .split_whitespace()
+    .filter_map(|token| {
+      let (algorithm, hash) = token.split_once('-')?;
+      // options after `?` are reserved by the spec
+      let hash = hash.split('?').next().unwrap_or_default();
+      Some((Algorithm::from_name(algorithm)?, hash.trim_end_matches('=')))
+    })
+    .collect();
+  let Some(strongest) = hashes.iter().map(|(algorithm, _)| *algorithm).max() else {
+    let msg = format!(
+      "Invalid integrity of `{name}`, expected a sha256, sha384 or sha512 hash like `sha384-<base64>`."
+    );
+    return Err(JsError::new(&msg));
+  };
+  let actual = base64(&strongest.digest(bytes));
+  let matched = hashes
+    .iter()
+    .any(|(algorithm, hash)| *algorithm == strongest && *hash == actual.trim_end_matches('='));
+  if !matched {
+    let msg = format!(
+      "The grammar of `{name}` does not match its integrity, its hash is `{}-{actual}`.",
+      strongest.name()
+    );
+    return Err(JsError::new(&msg));
+  }
+  Ok(())
+}
@@ -0,0 +1,277 @@
+//! Matches in the shape of the CLI's `--json` output,
+//! so the WASM module can replace the CLI in JSON pipelines.
+
+use crate::doc::{error_chain, WasmDoc, Wrapper};
+use crate::scan::{parse_rules, scan_files, MixedFile};
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_config::{Fixer, LabelStyle, RuleConfig, Severity};
+use ast_grep_core::meta_var::MetaVariable;
+use ast_grep_core::source::{Content, Edit};
+use ast_grep_core::{Doc, Node, NodeMatch};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops;
+use wasm_bindgen::prelude::*;
+
+/// Zero-based character position in a file.
+#[derive(Serialize)]
+struct Position {
+  line: usize,
+  column: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Range {
+  /// UTF-8 byte offsets like the CLI, inclusive start, exclusive end
+  byte_offset: ops::Range<usize>,
+  start: Position,
+  end: Position,
+}
+
+#[derive(Serialize)]
+struct MatchNode {
+  text: String,
+  range: Range,
+}
+
+#[derive(Serialize)]
+struct MatchLabel<'r> {
+  text: String,
+  range: Range,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  message: Option<&'r str>,
+  style: LabelStyle,
+}
+
+#[derive(Serialize)]
+struct CharCount {
+  leading: usize,
+  trailing: usize,
+}
+
+#[derive(Serialize)]
+struct MetaVariables {
+  single: HashMap<String, MatchNode>,
+  multi: HashMap<String, Vec<MatchNode>>,
+  transformed: HashMap<String, String>,
+}
+
+/// One match of `ast-grep run --json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MatchJson<'r> {
+  text: String,
+  range: Range,
+  file: &'r str,
+  lines: String,
+  char_count: CharCount,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  replacement: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  replacement_offsets: Option<ops::Range<usize>>,
+  language: WasmLang,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  meta_variables: Option<MetaVariables>,
+}
+
+/// One match of `ast-grep scan --json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleMatchJson<'r> {
+  #[serde(flatten)]
+  matched: MatchJson<'r>,
+  rule_id: &'r str,
+  severity: Severity,
+  note: Option<String>,
+  message: String,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  labels: Vec<MatchLabel<'r>>,
+}
+
+fn source<'t>(node: &Node<'t, WasmDoc>) -> &'t Wrapper {
+  node.get_doc().get_source()
+}
+
+fn byte_range(src: &Wrapper, chars: ops::Range<usize>) -> ops::Range<usize> {
+  src.byte_offset(chars.start)..src.byte_offset(chars.end)
+}
+
+fn get_range(node: &Node<'_, WasmDoc>) -> Range {
+  let start_pos = node.start_pos();
+  let end_pos = node.end_pos();
+  Range {
+    byte_offset: byte_range(source(node), node.range()),
+    start: Position {
+      line: start_pos.line(),
+      column: start_pos.column(node),
+    },
+    end: Position {
+      line: end_pos.line(),
+      column: end_pos.column(node),
+    },
+  }
+}
+
+fn match_node(node: &Node<'_, WasmDoc>) -> MatchNode {
+  MatchNode {
+    text: node.text().to_string(),
+    range: get_range(node),
+  }
+}
+
+fn from_env(nm: &NodeMatch<'_, WasmDoc>) -> Option<MetaVariables> {
+  let env = nm.get_env();
+  let mut vars = env.get_matched_variables().peekable();
+  vars.peek()?;
+  let mut single = HashMap::new();
+  let mut multi = HashMap::new();
+  let mut transformed = HashMap::new();
+  for var in vars {
+    match var {
+      MetaVariable::Capture(n, _) => {
+        if let Some(node) = env.get_match(&n) {
+          single.insert(n, match_node(node));
+        } else if let Some(chars) = env.get_transformed(&n) {
+          transformed.insert(n, chars.iter().collect());
+        }
+      }
+      MetaVariable::MultiCapture(n) => {
+        let nodes = env.get_multiple_matches(&n);
+        multi.insert(n, nodes.iter().map(match_node).collect());
+      }
+      _ => continue,
+    }
+  }
+  Some(MetaVariables {
+    single,
+    multi,
+    transformed,
+  })
+}
+
+fn get_labels<'r>(
+  rule: &'r RuleConfig<WasmLang>,
+  nm: &NodeMatch<'_, WasmDoc>,
+) -> Vec<MatchLabel<'r>> {
+  let src = source(nm);
+  rule
+    .get_labels(nm)
+    .into_iter()
+    .map(|label| {
+      let chars = label.range();
+      let start_pos = label.start_node.start_pos();
+      let end_pos = label.end_node.end_pos();
+      MatchLabel {
+        text: src.get_range(chars.clone()).iter().collect(),
+        range: Range {
+          byte_offset: byte_range(src, chars),
+          start: Position {
+            line: start_pos.line(),
+            column: start_pos.column(nm),
+          },
+          end: Position {
+            line: end_pos.line(),
+            column: end_pos.column(nm),
+          },
+        },
+        message: label.message,
+        style: label.style,
+      }
+    })
+    .collect()
+}
+
+impl<'r> MatchJson<'r> {
+  /// `edit` is the fix of the match, if any.
+  fn new(nm: &NodeMatch<'_, WasmDoc>, file: &'r str, edit: Option<Edit<Wrapper>>) -> Self {
+    let src = source(nm);
+    let chars = nm.range();
+    let line_start = src.line_start(chars.start);
+    let line_end = src.line_end(chars.end);
+    let text = nm.text().to_string();
+    let leading: String = src.get_range(line_start..chars.start).iter().collect();
+    let trailing: String = src.get_range(chars.end..line_end).iter().collect();
+    let (replacement, replacement_offsets) = match edit {
+      Some(edit) => {
+        let replaced = edit.position..edit.position + edit.deleted_length;
+        let replacement = edit.inserted_text.iter().collect();
+        (Some(replacement), Some(byte_range(src, replaced)))
+      }
+      None => (None, None),
+    };
+    Self {
+      lines: format!("{leading}{text}{trailing}"),
+      char_count: CharCount {
+        leading: leading.chars().count(),
+        trailing: trailing.chars().count(),
+      },
+      text,
+      range: get_range(nm),
+      file,
+      replacement,
+      replacement_offsets,
+      language: *nm.lang(),
+      meta_variables: from_env(nm),
+    }
+  }
+}
+
+impl<'r> RuleMatchJson<'r> {
+  fn new(nm: NodeMatch<'_, WasmDoc>, file: &'r str, rule: &'r RuleConfig<WasmLang>) -> Self {
+    let edit = rule
+      .matcher
+      .fixer
+      .first()
+      .map(|fixer| nm.make_edit(&rule.matcher, fixer));
+    Self {
+      matched: MatchJson::new(&nm, file, edit),
+      rule_id: &rule.id,
+      severity: rule.severity.clone(),
+      note: rule.note.clone(),
+      message: rule.get_message(&nm),
+      labels: get_labels(rule, &nm),
+    }
+  }
+}
+
+/// Options of `matchToJSON`.
+#[derive(Default, Deserialize)]
+pub struct MatchJsonOptions {
+  /// Reported as the match's `file`.
+  file: Option<String>,
+  /// A fix template, reported as `replacement` like `ast-grep run --rewrite`.
+  rewrite: Option<String>,
+}
+
+/// Format a single match like a match of `ast-grep run --json`.
+pub fn match_to_json(
+  nm: &NodeMatch<'_, WasmDoc>,
+  options: MatchJsonOptions,
+) -> Result<JsValue, JsError> {
+  let edit = match &options.rewrite {
+    Some(template) => {
+      let fixer = Fixer::from_str(template, nm.lang()).map_err(|e| error_chain(&e))?;
+      Some(nm.replace_by(fixer))
+    }
+    None => None,
+  };
+  let file = options.file.as_deref().unwrap_or("anonymous");
+  let json = MatchJson::new(nm, file, edit);
+  let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+  json
+    .serialize(&serializer)
+    .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Scan `files` with the YAML rules and format all matches like `ast-grep scan --json`.
+/// Matches are ordered by file, then by rule declaration, then by position.
+pub fn scan_to_json(config_yaml: &str, files: &[MixedFile]) -> Result<String, JsError> {
+  let rules = parse_rules(config_yaml)?;
+  let mut output = vec![];
+  scan_files(&rules, files, |file, rule, nm| {
+    output.push(RuleMatchJson::new(nm, &file.name, rule));
+  })?;
+  serde_json::to_string_pretty(&output).map_err(|e| JsError::new(&e.to_string()))
+}
@@ -1,16 +1,73 @@
+mod budget;
+mod clones;
+mod compiled;
+mod constant;
+mod custom_matcher;
+mod debug_match;
+mod diff;
 mod doc;
+mod edit_conflicts;
+mod encoding;
+mod explain;
+mod global_utils;
+mod imports;
+mod infer;
+mod injection;
+mod integrity;
+mod json_output;
+mod limits;
+mod list_edit;
+mod meta_vars;
+mod metrics;
+mod node_kinds;
+mod normalize;
+mod parse_error;
+mod pattern_diff;
+mod pattern_lint;
+mod pattern_object;
+mod profile;
+mod progress;
+mod project;
+mod query;
+mod rule_errors;
+mod sarif;
+mod scan;
+mod scan_cache;
+mod scope;
+mod search_range;
+mod secrets;
+mod security;
+mod selector;
 mod sg_node;
+mod snapshot;
+mod source_map;
+mod taint;
+mod tree_diff;
 mod ts_types;
+mod type_defs;
+mod verify;
 mod wasm_lang;
 
-pub use sg_node::{SgNode, SgRoot};
+pub use compiled::CompiledMatcher;
+pub use project::Project;
+pub use sg_node::{MatchIterator, SgNode, SgRoot};
 pub use wasm_lang::WasmLangInfo;
 
-use doc::{WasmConfig, WasmDoc};
-use wasm_lang::WasmLang;
+use constant::ValueConstraints;
+use doc::{error_chain, WasmConfig, WasmDoc};
+use encoding::{EncodingOption, PositionEncoding};
+use pattern_object::PatternObject;
+use scan::{FixOptions, ScanOptions};
+use scope::{ScopeConstraints, ScopeFilter};
+use wasm_lang::{PatternChars, WasmLang};
 
+use ast_grep_config::{SerializableRule, SerializableRuleCore, SignatureError};
 use ast_grep_core::matcher::PatternNode;
-use ast_grep_core::{AstGrep, Language, MatchStrictness, Node as CoreNode, Pattern};
+use ast_grep_core::meta_var::MetaVariable;
+use ast_grep_core::{
+  AstGrep, Doc, Language, MatchStrictness, Node as CoreNode, NodeMatch as CoreNodeMatch,
+};
+use serde::Serialize;
 use std::collections::HashMap;
 use ts_types::TreeSitter;
 use wasm_bindgen::prelude::*;
@@ -25,55 +82,1024 @@ pub async fn initialize_tree_sitter() -> Result<(), JsError> {
 // Inject custom TypeScript
 #[wasm_bindgen(typescript_custom_section)]
 const TS_APPEND_CONTENT: &'static str = r#"
-export function registerDynamicLanguage(map: Record<string, {libraryPath: string, expandoChar?: string}>): Promise<void>;
+export function registerDynamicLanguage(map: Record<string, {libraryPath?: string, libraryBytes?: ArrayBuffer | Uint8Array, expandoChar?: string, nodeTypes?: string, extensions?: string[], integrity?: string}>): Promise<void>;
+/** A rule config object, like a YAML rule without the reporting fields. */
+export interface WasmConfig {
+  rule?: SerializableRule;
+  constraints?: Record<string, SerializableRule>;
+  language?: string;
+  transform?: Record<string, Transformation>;
+  utils?: Record<string, SerializableRule>;
+  rewriters?: SerializableRewriter[];
+  primary?: string;
+  query?: string;
+  expandoChar?: string;
+  metaVarChar?: string;
+}
+/** A pattern with options, like the pattern object of YAML rules. */
+export interface PatternObject {
+  context: string;
+  selector?: string;
+  strictness?: StrictnessStyle;
+  parseAs?: string;
+  language?: string;
+}
+export type Matcher = string | number | PatternObject | WasmConfig | CompiledMatcher;
+/** Options of `pattern`. */
+export interface PatternOptions {
+  selector?: string;
+  strictness?: StrictnessStyle;
+  inside?: Relation;
+  has?: Relation;
+  precedes?: Relation;
+  follows?: Relation;
+  nthChild?: SerializableNthChild;
+  constraints?: Record<string, SerializableRule>;
+  utils?: Record<string, SerializableRule>;
+  expandoChar?: string;
+  metaVarChar?: string;
+}
+export function pattern(lang: string, pattern: string | PatternObject, options?: PatternOptions | null): WasmConfig;
+export function pattern(pattern: PatternObject & { language: string }, options?: PatternOptions | null): WasmConfig;
+export function dumpPattern(lang: string, pattern: string | PatternObject, selector?: string | null, strictness?: string | null, options?: { expandoChar?: string, metaVarChar?: string, positionEncoding?: PositionEncoding } | null): any;
+export function dumpPattern(pattern: PatternObject & { language: string }, options?: { expandoChar?: string, metaVarChar?: string, positionEncoding?: PositionEncoding } | null): any;
+/** Options of `find` and `findAll`, whose searches throw once `maxNodes`, `timeoutMs` or `signal` stops them. */
+export interface FindOptions {
+  onProgress?: (processed: number, total: number) => void;
+  /** Stop with the matches found so far once there are this many. */
+  maxMatches?: number;
+  maxNodes?: number;
+  timeoutMs?: number;
+  /** Checked every 256 visited nodes, e.g. `AbortController.signal`. */
+  signal?: { readonly aborted: boolean };
+  /** Only search the nodes overlapping this range. */
+  range?: SearchRange;
+  /** Throw a `LimitExceededError` before searching if matching recurses deeper than this. */
+  maxPatternDepth?: number;
+}
+/** The error of an exceeded hard limit of `parse`, `find` or `findAll`. */
+export interface LimitExceededError extends Error {
+  name: "LimitExceededError";
+  limit: "maxSourceSize" | "maxTreeDepth" | "maxNodes" | "maxPatternDepth";
+  max: number;
+}
+/** Options of `loadProject`. */
+export interface LoadProjectOptions {
+  /** minisign public keys, one of which must have signed every rule and util file. */
+  trustedKeys?: string[];
+}
+/** The unit of columns and offsets: `"char"` (default) for code points, `"utf16"` for JS string indices or `"utf8"` for bytes. */
+export type PositionEncoding = "char" | "utf16" | "utf8";
+/** The `positionEncoding` option of functions reporting positions of a source they parse. */
+export interface EncodingOptions {
+  positionEncoding?: PositionEncoding;
+}
+/** An offset, e.g. `index` of `range()`, or a zero-based line and column, in the root's position encoding. */
+export type SearchPosition = number | { line: number; column: number };
+export interface SearchRange {
+  start: SearchPosition;
+  end: SearchPosition;
+}
+/** Options of `structuralHash` and `findClones`. Both default to `true`. */
+export interface FingerprintOptions extends EncodingOptions {
+  ignoreIdentifiers?: boolean;
+  ignoreLiterals?: boolean;
+}
+type PlainPos = Pick<Pos, "line" | "column" | "index" | "byteOffset">;
+/** A subtree of one of the sources of `diffTrees`. */
+export interface TreeEditNode {
+  kind: string;
+  range: { start: PlainPos; end: PlainPos };
+  text: string;
+}
+export interface TreeDiff {
+  edits: {
+    op: "inserted" | "removed" | "moved" | "updated";
+    before?: TreeEditNode;
+    after?: TreeEditNode;
+  }[];
+}
+/** The labels of the first match of an invalid case and the code after its fix, as `ast-grep test` snapshots. */
+export interface TestSnapshot {
+  fixed?: string;
+  /** `start` and `end` are UTF-8 byte offsets, like in the CLI's snapshot files. */
+  labels: { source: string; message?: string; style: "primary" | "secondary"; start: number; end: number }[];
+}
+export interface RuleTestResult {
+  id: string;
+  passed: boolean;
+  cases: {
+    code: string;
+    /** Whether the case is from `valid` rather than `invalid`. */
+    valid: boolean;
+    status: "validated" | "reported" | "wrong" | "missing" | "noisy" | "error";
+    passed: boolean;
+    snapshot?: TestSnapshot;
+    expected?: TestSnapshot;
+    error?: string;
+  }[];
+  /** The snapshots of this run in the format of the CLI's `__snapshots__` files. */
+  snapshotYaml: string;
+}
+/** An error of `parseRule`, at the field of the rule causing it. */
+export interface RuleError {
+  message: string;
+  /** The YAML path of the field like `rule.all[1].kind`, empty for errors of the whole rule. */
+  path: string;
+  /** The span of the field's key and value, or the position of a YAML syntax error. */
+  range: { start: PlainPos; end: PlainPos };
+}
+export interface ParsedRule {
+  /** The rule with defaults filled in, absent if it has errors. */
+  rule?: RuleConfig;
+  errors: RuleError[];
+}
+/** A mistake of a pattern found by `lintPattern`. */
+export interface PatternLint {
+  code: "multipleRoots" | "invalidMetaVariable" | "unusedMetaVariable" | "matchesEverything";
+  message: string;
+  /** The span in the pattern, absent for lints of the whole pattern. */
+  range?: { start: PlainPos; end: PlainPos };
+}
+/** An edit of `applyEdits`, like a `WasmEdit` of `replace` or `applyFix`. */
+export interface PrioritizedEdit {
+  start_pos: number;
+  end_pos: number;
+  inserted_text: string;
+  /** Higher priorities win conflicts with the `priority` strategy, 0 by default. */
+  priority?: number;
+}
+export interface ApplyEditsOptions extends EncodingOptions {
+  /** How to resolve overlapping edits, `first` by default. */
+  strategy?: "first" | "priority" | "error";
+}
+/** The result of `applyEdits`, with edits as indices in its input. */
+export interface AppliedEdits {
+  code: string;
+  /** The applied edits in order of position. */
+  applied: number[];
+  /** The edits left out, with the applied edit each overlaps or duplicates. */
+  skipped: { index: number; conflictsWith: number; reason: "overlap" | "duplicate" }[];
+}
+/** A meta variable of `listMetaVariables`. */
+export interface MetaVariableInfo {
+  /** The name without meta variable chars, like `ARGS` for `$$$ARGS`. */
+  name: string;
+  /** Whether a pattern captures it as a multi meta variable like `$$$ARGS`. */
+  multi: boolean;
+  /** Whether the rule, its utils or constraints capture it. */
+  captured: boolean;
+  /** Whether it is a key of `transform`. */
+  transformed: boolean;
+  inConstraints: boolean;
+  /** Whether it is the `source` of a transformation. */
+  inTransform: boolean;
+  inFix: boolean;
+}
+/** A kind of `nodeTypeSchema`, with the kinds of its fields and children. */
+export interface KindSchema {
+  kind: string;
+  isNamed: boolean;
+  /** The kinds a supertype like `expression` stands for. */
+  subtypes?: string[];
+  fields: Record<string, { kinds: string[]; required: boolean; multiple: boolean }>;
+  /** The kinds of every child, in a field or not, that `has` can match without `stopBy`. */
+  children: string[];
+}
+/** A rule of `explainRule`'s tree, with the rules it is made of. */
+export interface ExplanationNode {
+  /** The rule's `type` as in `dumpRule`, or `"stopBy"` for the rule a relational rule stops at. */
+  type: string;
+  /** What the rule itself requires, e.g. `is inside a node that`. */
+  text: string;
+  /** The rule with its children as one sentence. */
+  summary: string;
+  children: ExplanationNode[];
+}
+export interface RuleExplanation {
+  summary: string;
+  rule: ExplanationNode;
+  constraints: { name: string; rule: ExplanationNode }[];
+}
+/** Size and complexity of a function's code or of the code outside functions. */
+export interface Metrics {
+  /** Named nodes, excluding nested functions. */
+  nodes: number;
+  /** Deepest nesting of branches and loops, `0` for straight-line code. */
+  maxNesting: number;
+  /** Cyclomatic complexity: one plus the branches, loops, cases, catches and `&&`/`||`. */
+  complexity: number;
+}
+export interface FunctionMetrics extends Metrics {
+  /** The declared name, or the variable or property the function is assigned to. */
+  name?: string;
+  kind: string;
+  range: { start: PlainPos; end: PlainPos };
+}
+export interface CodeMetrics {
+  /** The code outside functions. */
+  file: Metrics;
+  /** Every function including nested ones, in the order of their start. */
+  functions: FunctionMetrics[];
+}
+export interface CloneGroup {
+  hash: string;
+  /** Number of tokens of each copy, excluding comments. */
+  tokens: number;
+  instances: { file: string; range: { start: PlainPos; end: PlainPos }; text: string }[];
+}
+/** Maps the parsed source from `generated` to `original` in the document it was extracted from. */
+export interface SourceMapSegment {
+  generated: number;
+  original: { line: number; column: number; index: number; byteOffset?: number };
+}
+export interface MatchIterator {
+  [Symbol.iterator](): MatchIterator;
+  [Symbol.asyncIterator](): AsyncGenerator<SgNode, void, undefined>;
+}
 "#;
 
+// Types of the YAML rule format, generated from schemas/rule.json by `cargo xtask schema`
+#[wasm_bindgen(typescript_custom_section)]
+const RULE_TYPES: &'static str = include_str!("rule_types.d.ts");
+
+/// The JSON schema of YAML rules, the same as `schemas/rule.json`, e.g. to validate
+/// rules in an editor. `RuleConfig` and the types it references are its TS counterpart.
+#[wasm_bindgen(js_name = ruleSchema)]
+pub fn rule_schema() -> Result<JsValue, JsError> {
+  js_sys::JSON::parse(include_str!("../../../schemas/rule.json"))
+    .map_err(|_| JsError::new("rule schema is not valid JSON"))
+}
+
 /// Register dynamic languages for parsing.
-/// `langs` is a Map of language name to its registration config
-/// (with `libraryPath` and optional `expandoChar`).
+/// `langs` is a Map of language name to its registration config (with either
+/// `libraryPath` or `libraryBytes`, optional `expandoChar` and optional `nodeTypes`).
 /// Can be called multiple times; existing languages are updated.
 #[wasm_bindgen(js_name = registerDynamicLanguage, skip_typescript)]
 pub async fn register_dynamic_language(langs: JsValue) -> Result<(), JsError> {
   let langs: HashMap<String, WasmLangInfo> =
     serde_wasm_bindgen::from_value(langs).map_err(|e| JsError::new(&e.to_string()))?;
-  WasmLang::register(langs).await
+  for replaced in WasmLang::register(langs).await? {
+    retire(replaced);
+  }
+  // cached matchers may be compiled for a grammar that was just replaced
+  compiled::clear_cache();
+  Ok(())
+}
+
+/// Unregister a language added by `registerDynamicLanguage`.
+/// Roots parsed with it keep their tree, but throw when matched or edited.
+/// The utils and injections registered for it are dropped.
+/// Returns `false` if the language is not registered.
+#[wasm_bindgen(js_name = unregisterDynamicLanguage)]
+pub fn unregister_dynamic_language(lang: String) -> bool {
+  let Some(lang) = WasmLang::unregister(&lang) else {
+    return false;
+  };
+  retire(lang);
+  compiled::clear_cache();
+  true
+}
+
+/// Drop the state compiled for the grammar `lang` no longer has.
+fn retire(lang: WasmLang) {
+  global_utils::forget(lang);
+  injection::forget(lang);
+}
+
+/// `bytes` as lowercase hex, two digits per byte.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Register rules that find languages embedded in others, replacing earlier ones.
+/// `injections` is an array of `{ hostLanguage, rule, injected }` in the format of
+/// `languageInjections` in `sgconfig.yml`, plus `constraints`, `utils` and `transform`.
+/// The rule captures each region as `$CONTENT`, and `injected` is the region's language,
+/// or a list of languages one of which the rule captures as `$LANG`.
+#[wasm_bindgen(js_name = registerInjections)]
+pub fn register_injections(injections: JsValue) -> Result<(), JsError> {
+  let injections: serde_json::Value = serde_wasm_bindgen::from_value(injections)?;
+  injection::register(serde_json::from_value(injections)?)
+}
+
+/// Register utility rules that every rule of `lang` can reference with `matches`,
+/// replacing the utils registered for it before.
+/// `utils_yaml` has one util per document, separated by `---`, each with an `id` and a
+/// `rule`, like the util files of `utilDirs` in `sgconfig.yml`.
+#[wasm_bindgen(js_name = registerUtils)]
+pub fn register_utils(lang: String, utils_yaml: String) -> Result<(), JsError> {
+  global_utils::register(&lang, &utils_yaml)
+}
+
+/// Register `predicate` as a matcher that rules of every language can call with
+/// `custom: name`, replacing the one registered as `name` before. The predicate gets the
+/// SgNode to match and returns whether it matches.
+#[wasm_bindgen(js_name = registerCustomMatcher)]
+pub fn register_custom_matcher(
+  name: String,
+  #[wasm_bindgen(unchecked_param_type = "(node: SgNode) => boolean")] predicate: js_sys::Function,
+) -> Result<(), JsError> {
+  custom_matcher::register(name, predicate)
+}
+
+/// Memory usage of the module, see `memoryStats`.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+  registered_languages: usize,
+  cached_parsers: usize,
+  /// Number of sources compiled by `compileMatcher` kept in its cache.
+  cached_patterns: usize,
+  /// Number of grammar binaries loaded. Languages registered with the same
+  /// `libraryPath` share one grammar.
+  grammar_loads: usize,
+  /// Number of documents copied to call custom matchers since the module was loaded.
+  /// A document is copied once, not for every node a matcher is called with.
+  custom_matcher_copies: usize,
+  /// Size of this module's WASM linear memory.
+  heap_bytes: u32,
+}
+
+/// Release cached resources for long-lived sessions.
+/// Cached parsers and compiled matchers are deleted and recreated on demand; registered
+/// languages, existing `SgRoot`s, their nodes and `CompiledMatcher` handles stay valid.
+#[wasm_bindgen(js_name = freeAll)]
+pub fn free_all() {
+  WasmLang::free_parsers();
+  compiled::clear_cache();
+}
+
+/// Report cache sizes and the WASM heap size to monitor memory growth.
+#[wasm_bindgen(js_name = memoryStats)]
+pub fn memory_stats() -> Result<JsValue, JsError> {
+  let (registered_languages, cached_parsers, grammar_loads) = WasmLang::registry_stats();
+  let memory: js_sys::WebAssembly::Memory = wasm_bindgen::memory().unchecked_into();
+  let buffer: js_sys::ArrayBuffer = memory.buffer().unchecked_into();
+  let stats = MemoryStats {
+    registered_languages,
+    cached_parsers,
+    cached_patterns: compiled::cache_len(),
+    grammar_loads,
+    custom_matcher_copies: custom_matcher::copies(),
+    heap_bytes: buffer.byte_length(),
+  };
+  serde_wasm_bindgen::to_value(&stats).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Options of `parse`.
+#[derive(Default, serde::Deserialize)]
+struct ParseOptions {
+  #[serde(flatten)]
+  chars: PatternChars,
+  /// Reject sources with syntax errors.
+  #[serde(default)]
+  strict: bool,
+  /// Report ranges in the document the source was extracted from.
+  #[serde(rename = "withSourceMap")]
+  with_source_map: Option<Vec<source_map::Segment>>,
+  #[serde(flatten)]
+  limits: limits::SourceLimits,
+  /// `normalizeLineEndings` and `stripBom`.
+  #[serde(flatten)]
+  normalize: normalize::NormalizeOptions,
+  /// The unit of the root's positions and edits.
+  #[serde(flatten)]
+  encoding: EncodingOption,
 }
 
 /// Parse a string to an ast-grep instance.
+/// `options` can set `expandoChar` and `metaVarChar` for the patterns used on the tree,
+/// `strict` to throw if the source has syntax errors, `withSourceMap` to report
+/// ranges in the document the source was extracted from, `normalizeLineEndings` and
+/// `stripBom` to parse the source with `\n` line endings and without a BOM,
+/// `maxSourceSize`, `maxTreeDepth` and `maxNodes` to throw a `LimitExceededError` for
+/// untrusted sources too large to handle, and `positionEncoding` for the unit of the
+/// columns and offsets of the root's ranges and edits, and of those passed to it.
 #[wasm_bindgen]
-pub fn parse(lang: String, src: String) -> Result<SgRoot, JsError> {
+pub fn parse(lang: String, src: String, options: JsValue) -> Result<SgRoot, JsError> {
   let lang: WasmLang = lang
     .parse()
     .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
-  let doc = WasmDoc::try_new(src, lang)?;
+  let options: Option<ParseOptions> = serde_wasm_bindgen::from_value(options)?;
+  let options = options.unwrap_or_default();
+  let lang = lang.with_chars(&options.chars)?;
+  options.limits.check_source(&src)?;
+  let encoding = options.encoding.position_encoding;
+  let doc = match normalize::normalize(&src, &options.normalize, encoding) {
+    Some(normalized) => {
+      let mut doc = WasmDoc::try_new(normalized.src, lang)?.with_encoding(encoding);
+      let segments = options.with_source_map;
+      doc.set_normalized(normalized.segments, normalized.restore, &src, segments);
+      doc
+    }
+    None => {
+      let mut doc = WasmDoc::try_new(src, lang)?.with_encoding(encoding);
+      if let Some(segments) = options.with_source_map {
+        doc.set_source_map(segments);
+      }
+      doc
+    }
+  };
+  let root = AstGrep::doc(doc);
+  options.limits.check_tree(&root)?;
+  if options.strict {
+    let errors = parse_error::collect(&root.root());
+    if let Some(first) = errors.first() {
+      let count = errors.len();
+      let msg = format!(
+        "Source has {count} syntax error(s), the first is {}.",
+        first.describe()
+      );
+      return Err(JsError::new(&msg));
+    }
+  }
+  Ok(SgRoot::new(root, "anonymous".into()))
+}
+
+/// Wrap `tree`, a web-tree-sitter `Tree` the app parsed from `src` itself, e.g. for
+/// highlighting, into an SgRoot without parsing `src` again.
+/// `options.positionEncoding` is the unit of the root's positions, like for `parse`.
+/// Throws if the tree is not of the grammar registered as `lang` or longer than `src`.
+#[wasm_bindgen(js_name = fromTree)]
+pub fn from_tree(
+  lang: String,
+  tree: ts_types::Tree,
+  src: String,
+  #[wasm_bindgen(unchecked_param_type = "EncodingOptions")] options: JsValue,
+) -> Result<SgRoot, JsError> {
+  let options: Option<EncodingOption> = serde_wasm_bindgen::from_value(options)?;
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  if !wasm_lang.is_grammar_of(&tree.language()) {
+    let msg = format!("The tree is not parsed with the grammar registered as `{lang}`.");
+    return Err(JsError::new(&msg));
+  }
+  let doc = WasmDoc::from_tree(src, &tree, wasm_lang)?;
+  let doc = doc.with_encoding(options.unwrap_or_default().position_encoding);
   Ok(SgRoot::new(AstGrep::doc(doc), "anonymous".into()))
 }
 
+/// Scan source code with the security rules bundled with the module, e.g. for
+/// hardcoded credentials, `eval`, insecure randomness, weak hashing and command
+/// injection, and for secrets like API keys, tokens and private keys in string
+/// literals and config values. The ruleset is picked by `lang` unless
+/// `options.ruleset` names one, `secrets` for the secret detectors alone.
+/// `options.exclude` lists rule ids to skip, and `options.path` is the file's path,
+/// which detectors of sample secrets skip in tests and fixtures.
+/// Returns the findings in document order, each with its rule's `ruleId` and `cwe`.
+#[wasm_bindgen(js_name = scanSecurity)]
+pub fn scan_security(lang: String, src: String, options: JsValue) -> Result<JsValue, JsError> {
+  let options: Option<security::SecurityOptions> = serde_wasm_bindgen::from_value(options)?;
+  let findings = security::scan(&lang, src, options.unwrap_or_default())?;
+  let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+  findings
+    .serialize(&serializer)
+    .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Scan source code with ast-grep YAML rules.
+/// `config_yaml` can contain multiple rules separated by `---`.
+/// Rules for other languages or with severity `off` are skipped.
+/// `options` can set `maxPerRule` to cap the matches collected per rule, and
+/// `onProgress(processed, total)` to be called periodically during the traversal, and
+/// `withSourceMap` to report ranges in the document the source was extracted from, in
+/// the unit of `positionEncoding`.
+/// `maxNodes`, `maxMatches`, `timeoutMs` and `signal` limit the traversal like for `findAll`.
+/// Returns matches grouped by rule, with each rule's `total` match count.
+#[wasm_bindgen]
+pub fn scan(
+  lang: String,
+  src: String,
+  config_yaml: String,
+  options: JsValue,
+) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let options: Option<ScanOptions> = serde_wasm_bindgen::from_value(options)?;
+  let rules = scan::parse_rules(&config_yaml)?;
+  let scopes: Vec<ScopeConstraints> =
+    scan::rule_fields(&config_yaml, "scopes").map_err(|e| error_chain(&e))?;
+  let values: Vec<ValueConstraints> =
+    scan::rule_fields(&config_yaml, "values").map_err(|e| error_chain(&e))?;
+  let mut options = options.unwrap_or_default();
+  let mut doc = WasmDoc::try_new(src, lang)?.with_encoding(options.encoding.position_encoding);
+  if let Some(segments) = options.with_source_map.take() {
+    doc.set_source_map(segments);
+  }
+  let root = AstGrep::doc(doc);
+  let filter = ScopeFilter::new(&root.root(), &scopes, &values);
+  let keep = |index: usize, nm: &CoreNodeMatch<'_, WasmDoc>| filter.keep(index, nm);
+  let result = scan::scan_root_with(&root, &rules, &options, &|_| true, &keep)
+    .map_err(|e| JsError::new(&e))?;
+  serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Scan source code with ast-grep YAML rules like `scan`, and report what each rule cost
+/// instead of its matches: the time spent matching it, its matches, the nodes of its kinds
+/// visited and how many of them the prefilter skipped, the slowest rule first.
+#[wasm_bindgen]
+pub fn profile(lang: String, src: String, config_yaml: String) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let rules = scan::parse_rules(&config_yaml)?;
+  let root = AstGrep::doc(WasmDoc::try_new(src, lang)?);
+  let result = profile::profile(&root, &rules).map_err(|e| JsError::new(&e))?;
+  serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Compile a pattern string, kind id or rule config for `lang` once.
+/// The returned handle can be passed wherever a matcher is accepted, e.g. `find`,
+/// on any root of `lang`. Compiling the same source again reuses the cached matcher.
+#[wasm_bindgen(js_name = compileMatcher)]
+pub fn compile_matcher(
+  lang: String,
+  #[wasm_bindgen(unchecked_param_type = "string | number | WasmConfig")] matcher: JsValue,
+) -> Result<CompiledMatcher, JsError> {
+  compiled::compile(lang, matcher)
+}
+
+/// Load a project from a virtual file system: `files` maps paths to contents and must
+/// contain the project config at `config_path` (`sgconfig.yml` by default), its
+/// `ruleDirs` and `utilDirs`. Paths are relative to the virtual root.
+/// With `options.trustedKeys`, every rule and util file must be signed by one of them.
+/// Returns a `Project` whose compiled rules can scan many sources.
+#[wasm_bindgen(js_name = loadProject)]
+pub fn load_project(
+  files: JsValue,
+  config_path: Option<String>,
+  #[wasm_bindgen(unchecked_param_type = "LoadProjectOptions")] options: Option<JsValue>,
+) -> Result<Project, JsError> {
+  let files = serde_wasm_bindgen::from_value(files)?;
+  let config_path = config_path.as_deref().unwrap_or("sgconfig.yml");
+  let options = options.unwrap_or(JsValue::UNDEFINED);
+  let options: Option<project::LoadOptions> = serde_wasm_bindgen::from_value(options)?;
+  let trusted_keys = options
+    .unwrap_or_default()
+    .trusted_keys
+    .iter()
+    .map(|key| key.parse())
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e: SignatureError| JsError::new(&e.to_string()))?;
+  Project::load(files, config_path, &trusted_keys).map_err(|e| JsError::new(&e))
+}
+
+/// Scan files of different languages, each with the YAML rules of its language.
+/// `files` is an array of `{ name, lang, src }` and `rules_by_lang` maps a language
+/// to its rules in `scan`'s format. Files of languages without rules are skipped.
+/// Returns `{ name, lang, rules }` per file, or `{ name, lang, error }` if it failed.
+/// `options.cache`, an object with `get(key)` and `set(key, result)`, skips unchanged files.
+#[wasm_bindgen(js_name = scanMixed)]
+pub fn scan_mixed(
+  files: JsValue,
+  rules_by_lang: JsValue,
+  options: JsValue,
+) -> Result<JsValue, JsError> {
+  let files: Vec<scan::MixedFile> = serde_wasm_bindgen::from_value(files)?;
+  let rules_by_lang: HashMap<String, String> = serde_wasm_bindgen::from_value(rules_by_lang)?;
+  let options: Option<ScanOptions> = serde_wasm_bindgen::from_value(options)?;
+  let mut options = options.unwrap_or_default();
+  let cache = std::mem::take(&mut options.cache);
+  if !cache.is_undefined() {
+    return scan_cache::scan_mixed(files, &rules_by_lang, &options, cache);
+  }
+  let result = scan::scan_mixed(files, &rules_by_lang, &options);
+  serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Find duplicated code in files of any languages.
+/// `files` is an array of `{ name, lang, src }`. Returns groups of at least two
+/// subtrees with the same `structuralHash` and at least `min_tokens` tokens,
+/// each `{ hash, tokens, instances }` with the `{ file, range, text }` of every copy.
+#[wasm_bindgen(js_name = findClones, unchecked_return_type = "CloneGroup[]")]
+pub fn find_clones(
+  files: JsValue,
+  min_tokens: usize,
+  #[wasm_bindgen(unchecked_param_type = "FingerprintOptions")] options: JsValue,
+) -> Result<JsValue, JsError> {
+  let files: Vec<scan::MixedFile> = serde_wasm_bindgen::from_value(files)?;
+  let options: Option<clones::FingerprintOptions> = serde_wasm_bindgen::from_value(options)?;
+  let groups = clones::find_clones(files, min_tokens, &options.unwrap_or_default())
+    .map_err(|e| JsError::new(&e))?;
+  serde_wasm_bindgen::to_value(&groups).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Scan files with ast-grep YAML rules and return the matches as a JSON string
+/// in the format of `ast-grep scan --json`.
+/// `files` is an array of `{ name, lang, src }`; `name` is reported as `file`.
+#[wasm_bindgen(js_name = scanToJson)]
+pub fn scan_to_json(config_yaml: String, files: JsValue) -> Result<String, JsError> {
+  let files: Vec<scan::MixedFile> = serde_wasm_bindgen::from_value(files)?;
+  json_output::scan_to_json(&config_yaml, &files)
+}
+
+/// Scan files with ast-grep YAML rules and return the matches as a SARIF 2.1.0 log,
+/// like `ast-grep scan --format sarif`, including the rules' fixes.
+/// `files` is an array of `{ name, lang, src }`; `name` is used as the artifact URI.
+#[wasm_bindgen(js_name = scanToSarif)]
+pub fn scan_to_sarif(config_yaml: String, files: JsValue) -> Result<String, JsError> {
+  let files: Vec<scan::MixedFile> = serde_wasm_bindgen::from_value(files)?;
+  sarif::scan_to_sarif(&config_yaml, &files)
+}
+
+/// Apply the fixes of ast-grep YAML rules to `src` and return the new source.
+/// `config_yaml` can contain multiple rules separated by `---`; rules without `fix`,
+/// for other languages or with severity `off` are skipped.
+/// With `options.validate`, the fixed source is re-parsed and
+/// parse errors introduced by the fixes are reported in `newErrors`.
+#[wasm_bindgen]
+pub fn fix(
+  lang: String,
+  src: String,
+  config_yaml: String,
+  options: JsValue,
+) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let options: Option<FixOptions> = serde_wasm_bindgen::from_value(options)?;
+  let options = options.unwrap_or_default();
+  let rules = scan::parse_rules(&config_yaml)?;
+  let doc = WasmDoc::try_new(src.clone(), lang)?;
+  let root = AstGrep::doc(doc.with_encoding(options.encoding.position_encoding));
+  let result = scan::fix_root(&src, &root, &rules, &options)?;
+  serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Render the change from `before` to `after` as a unified diff, empty if there is none.
+/// `options` can set the lines of `context` (default 3) and the `path` in the headers.
+#[wasm_bindgen(js_name = unifiedDiff)]
+pub fn unified_diff(before: String, after: String, options: JsValue) -> Result<String, JsError> {
+  let options: Option<diff::DiffOptions> = serde_wasm_bindgen::from_value(options)?;
+  Ok(diff::unified(&before, &after, &options.unwrap_or_default()))
+}
+
+/// Check an ast-grep YAML rule for `fix` or `message` templates that use
+/// meta variables not captured by the rule.
+/// Throws if the rule is invalid for other reasons.
+/// Apply edits, e.g. of several rules, to `src` in one pass. Overlapping edits are
+/// resolved by `options.strategy` and the edits left out are reported, see `AppliedEdits`.
+#[wasm_bindgen(js_name = applyEdits, unchecked_return_type = "AppliedEdits")]
+pub fn apply_edits(
+  src: String,
+  #[wasm_bindgen(unchecked_param_type = "PrioritizedEdit[]")] edits: JsValue,
+  #[wasm_bindgen(unchecked_param_type = "ApplyEditsOptions")] options: JsValue,
+) -> Result<JsValue, JsError> {
+  let edits = serde_wasm_bindgen::from_value(edits)?;
+  let options: Option<edit_conflicts::ApplyOptions> = serde_wasm_bindgen::from_value(options)?;
+  let applied = edit_conflicts::apply(&src, edits, options.unwrap_or_default())?;
+  serde_wasm_bindgen::to_value(&applied).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_name = validateRule)]
+pub fn validate_rule(config_yaml: String) -> Result<JsValue, JsError> {
+  let warnings = scan::validate_rule(&config_yaml)?;
+  serde_wasm_bindgen::to_value(&warnings).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Parse and check an ast-grep YAML rule, e.g. for rule editors. Returns `{ rule, errors }`
+/// with the rule and its defaults if it is valid, or every error with the YAML `path`
+/// and the `range` of the field causing it, in `options.positionEncoding`.
+/// Unlike other rule APIs, this never throws.
+#[wasm_bindgen(js_name = parseRule, unchecked_return_type = "ParsedRule")]
+pub fn parse_rule(
+  rule_yaml: String,
+  #[wasm_bindgen(unchecked_param_type = "EncodingOptions")] options: JsValue,
+) -> Result<JsValue, JsError> {
+  let options: Option<EncodingOption> = serde_wasm_bindgen::from_value(options)?;
+  let encoding = options.unwrap_or_default().position_encoding;
+  let parsed = rule_errors::parse_rule(&rule_yaml, encoding);
+  let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+  parsed
+    .serialize(&serializer)
+    .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Dump the rule of an ast-grep YAML rule after normalization, for tools visualizing rules.
+/// Keys of one rule object become an explicit `all` in evaluation order, `matches` embeds
+/// the local utility rule as `rule`, and defaults like `stopBy` are filled in.
+/// Returns `{ rule, constraints }` where each rule is tagged by `type`.
+/// Throws if the rule is invalid for `lang`.
+#[wasm_bindgen(js_name = dumpRule)]
+pub fn dump_rule(lang: String, rule_yaml: String) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let dumped = scan::dump_rule(lang, &rule_yaml)?;
+  let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+  dumped
+    .serialize(&serializer)
+    .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Test rules like `ast-grep test`: each document of `test_yaml` has the `id` of a rule in
+/// `rule_yaml`, `valid` code it must not match and `invalid` code it must match.
+/// With `snapshot_yaml`, the CLI's `__snapshots__` files, the labels and fixed code of
+/// invalid cases must be those of the snapshots. Returns the result of every case per test,
+/// with the snapshots of the run as `snapshotYaml`.
+#[wasm_bindgen(js_name = testRule, unchecked_return_type = "RuleTestResult[]")]
+pub fn test_rule(
+  rule_yaml: String,
+  test_yaml: String,
+  snapshot_yaml: Option<String>,
+) -> Result<JsValue, JsError> {
+  let results = verify::test_rules(&rule_yaml, &test_yaml, snapshot_yaml.as_deref())?;
+  let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+  results
+    .serialize(&serializer)
+    .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Explain an ast-grep YAML rule in plain language, e.g. for reviewing what a rule matches.
+/// Returns `{ summary, rule, constraints }`, where `rule` is a tree of the rules it is made
+/// of, each with its `type`, its own `text` and the `summary` of its subtree.
+/// Throws if the rule is invalid for `lang`.
+#[wasm_bindgen(js_name = explainRule, unchecked_return_type = "RuleExplanation")]
+pub fn explain_rule(lang: String, rule_yaml: String) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let dumped = scan::dump_rule(lang, &rule_yaml)?;
+  let explanation = explain::explain(dumped, lang);
+  serde_wasm_bindgen::to_value(&explanation).map_err(|e| JsError::new(&e.to_string()))
+}
+
 /// Get the `kind` number from its string name.
+/// Throws if `kind_name` is not a named kind of the language.
 #[wasm_bindgen]
 pub fn kind(lang: String, kind_name: String) -> Result<u16, JsError> {
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  match wasm_lang.kind_to_id(&kind_name) {
+    0 => Err(JsError::new(&format!(
+      "`{kind_name}` is not a named kind of `{lang}`."
+    ))),
+    id => Ok(id),
+  }
+}
+
+/// Get the name of the `kind` number `kind_id`, the inverse of `kind`.
+/// Anonymous kinds have names too, e.g. `=`. Throws if the id is not a kind.
+#[wasm_bindgen(js_name = kindName)]
+pub fn kind_name(lang: String, kind_id: u16) -> Result<String, JsError> {
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let ts_lang = wasm_lang.get_ts_language();
+  let name = (kind_id < ts_lang.node_kind_count())
+    .then(|| ts_lang.node_kind_for_id(kind_id))
+    .flatten();
+  name.ok_or_else(|| JsError::new(&format!("{kind_id} is not a kind id of `{lang}`.")))
+}
+
+/// Get the id of the field `field_name`, e.g. to check `field` of relational rules.
+/// Throws if the language has no such field.
+#[wasm_bindgen(js_name = fieldId)]
+pub fn field_id(lang: String, field_name: String) -> Result<u16, JsError> {
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  wasm_lang
+    .field_to_id(&field_name)
+    .ok_or_else(|| JsError::new(&format!("`{field_name}` is not a field of `{lang}`.")))
+}
+
+/// Get the grammar's `node-types.json` schema of a registered language.
+/// Returns `null` if the language was registered without `nodeTypes`.
+#[wasm_bindgen(js_name = nodeTypes)]
+pub fn node_types(lang: String) -> Result<JsValue, JsError> {
   let lang: WasmLang = lang
     .parse()
     .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
-  Ok(lang.kind_to_id(&kind_name))
+  let Some(node_types) = lang.node_types() else {
+    return Ok(JsValue::NULL);
+  };
+  js_sys::JSON::parse(&node_types).map_err(|_| JsError::new("Invalid node types JSON"))
+}
+
+/// Get the schema of every kind in the `nodeTypes` of a registered language: whether it
+/// is named, its fields with the kinds they can have, and the kinds of all its children,
+/// e.g. to autocomplete `kind` and `field` in rule editors. Returns `null` if the
+/// language was registered without `nodeTypes`.
+#[wasm_bindgen(js_name = nodeTypeSchema, unchecked_return_type = "KindSchema[] | null")]
+pub fn node_type_schema(lang: String) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let schema = node_kinds::kind_schema(lang)?;
+  let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+  schema
+    .serialize(&serializer)
+    .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Generate a TypeScript declaration module of typed nodes from the `nodeTypes` of a
+/// registered language: the union of its kinds and a `TypedSgNode` with typed fields.
+/// Throws if the language was registered without `nodeTypes`.
+#[wasm_bindgen(js_name = generateTypes)]
+pub fn generate_types(lang: String) -> Result<String, JsError> {
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let Some(types) = node_kinds::node_types(wasm_lang)? else {
+    let msg = format!("`{lang}` was registered without `nodeTypes`.");
+    return Err(JsError::new(&msg));
+  };
+  Ok(type_defs::declarations(&lang, &types))
+}
+
+/// Get the metadata of a registered language: its pattern chars, file extensions, grammar
+/// ABI version and whether it is builtin.
+#[wasm_bindgen(js_name = languageInfo)]
+pub fn language_info(lang: String) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  serde_wasm_bindgen::to_value(&lang.info()).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// List the kinds of a registered language with their ids, for kind autocompletion.
+/// If the language was registered with `nodeTypes`, kinds also carry their
+/// `subtypes`, `fields` and `children` from the schema.
+#[wasm_bindgen(js_name = listKinds)]
+pub fn list_kinds(lang: String) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let kinds = node_kinds::list_kinds(lang)?;
+  serde_wasm_bindgen::to_value(&kinds).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// A strictness level, one of "cst", "smart", "ast", "relaxed", "signature", "template",
+/// or `{ base?, skipKinds }` for the `base` level (default "smart") that also skips
+/// nodes of the kinds in `skipKinds`, like "relaxed" skips comments.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum StrictnessOption {
+  Level(String),
+  #[serde(rename_all = "camelCase")]
+  Custom {
+    base: Option<String>,
+    skip_kinds: Vec<String>,
+  },
+}
+
+impl StrictnessOption {
+  /// The level and the kinds it also skips.
+  pub(crate) fn to_strictness(&self) -> Result<(MatchStrictness, &[String]), JsError> {
+    let parse = |s: &str| s.parse().map_err(|e: &str| JsError::new(e));
+    match self {
+      Self::Level(level) => Ok((parse(level)?, &[])),
+      Self::Custom { base, skip_kinds } => {
+        let base = base.as_deref().map_or(Ok(MatchStrictness::Smart), parse)?;
+        Ok((base, skip_kinds))
+      }
+    }
+  }
+}
+
+/// Options of `pattern`.
+#[derive(Default, serde::Deserialize)]
+pub struct PatternOptions {
+  /// Kind of the node to match in a contextual pattern.
+  selector: Option<String>,
+  strictness: Option<StrictnessOption>,
+  constraints: Option<serde_json::Value>,
+  utils: Option<serde_json::Value>,
+  /// Relational rules the match must also satisfy, with optional `stopBy` and `field`.
+  inside: Option<serde_json::Value>,
+  has: Option<serde_json::Value>,
+  precedes: Option<serde_json::Value>,
+  follows: Option<serde_json::Value>,
+  /// Position of the match among its siblings, e.g. `2` for a second argument.
+  #[serde(rename = "nthChild")]
+  nth_child: Option<serde_json::Value>,
+  /// `expandoChar` and `metaVarChar` instead of the language's defaults.
+  #[serde(flatten)]
+  chars: PatternChars,
 }
 
 /// Compile a string to ast-grep Pattern config.
-#[wasm_bindgen]
-pub fn pattern(lang: String, pattern_str: String) -> Result<JsValue, JsError> {
+/// `pattern` is a pattern string or object, also accepted without `lang` as the first
+/// argument if it has a `language`.
+/// `options` can set `selector` to make `pattern` a contextual pattern,
+/// `strictness`, the relational rules `inside`, `has`, `precedes` and `follows`,
+/// the positional rule `nthChild`, the `constraints` and `utils` of the config, and `expandoChar` and `metaVarChar`.
+#[wasm_bindgen(skip_typescript)]
+pub fn pattern(lang: JsValue, pattern: JsValue, options: JsValue) -> Result<JsValue, JsError> {
+  let (lang, mut pattern, shifted) = PatternObject::split_args(lang, pattern)?;
+  let options = match shifted {
+    Some(shifted) if options.is_undefined() => shifted,
+    Some(_) => return Err(JsError::new("Too many arguments after the pattern object.")),
+    None => options,
+  };
+  let options: Option<PatternOptions> = serde_wasm_bindgen::from_value(options)?;
+  let options = options.unwrap_or_default();
+  pattern.merge(options.selector, options.strictness)?;
+  if let Some(s) = &pattern.strictness {
+    s.to_strictness()?;
+  }
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  wasm_lang.with_chars(&options.chars)?;
+  pattern.language = None;
+  let pattern = if pattern.is_plain() {
+    serde_json::json!(pattern.context)
+  } else {
+    serde_json::to_value(pattern)?
+  };
+  let mut rule = serde_json::json!({ "pattern": pattern });
+  let relations = [
+    ("inside", options.inside),
+    ("has", options.has),
+    ("precedes", options.precedes),
+    ("follows", options.follows),
+    ("nthChild", options.nth_child),
+  ];
+  for (key, relation) in relations {
+    if let Some(relation) = relation {
+      rule[key] = relation;
+    }
+  }
+  // check the rules' shape, e.g. `stopBy` or `position`, before the config is used
+  serde_json::from_value::<SerializableRule>(rule.clone())
+    .map_err(|e| JsError::new(&format!("Invalid relational or positional rule: {e}")))?;
+  let config = WasmConfig {
+    rule,
+    constraints: options.constraints,
+    language: Some(lang),
+    utils: options.utils,
+    rewriters: None,
+    transform: None,
+    primary: None,
+    query: None,
+    chars: options.chars,
+  };
+  serde_wasm_bindgen::to_value(&config).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Compile a CSS-like selector, e.g. `statement_block call_expression[callee.text="eval"]`,
+/// to a rule config. The rule is validated against `lang`, so unknown kinds fail here.
+#[wasm_bindgen(js_name = selectorMatcher, unchecked_return_type = "WasmConfig")]
+pub fn selector_matcher(lang: String, selector: String) -> Result<JsValue, JsError> {
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let rule = selector::compile(&selector).map_err(|e| JsError::new(&e))?;
+  let serialized = SerializableRuleCore {
+    rule: serde_json::from_value(rule.clone())?,
+    constraints: None,
+    utils: None,
+    transform: None,
+    fix: None,
+  };
+  serialized
+    .get_matcher(global_utils::env(wasm_lang))
+    .map_err(|e| error_chain(&e))?;
+  let config = WasmConfig {
+    rule,
+    constraints: None,
+    language: Some(lang),
+    utils: None,
+    rewriters: None,
+    transform: None,
+    primary: None,
+    query: None,
+    chars: PatternChars::default(),
+  };
+  serde_wasm_bindgen::to_value(&config).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Compile a tree-sitter S-expression query to a rule config, see `WasmConfig::query`.
+/// The query is validated against `lang`, so invalid queries fail here.
+#[wasm_bindgen(js_name = queryMatcher, unchecked_return_type = "WasmConfig")]
+pub fn query_matcher(lang: String, scm_query: String) -> Result<JsValue, JsError> {
+  let wasm_lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  query::QueryMatcher::try_new(wasm_lang, &scm_query)?;
   let config = WasmConfig {
-    rule: serde_json::json!({ "pattern": pattern_str }),
+    rule: serde_json::Value::Null,
     constraints: None,
     language: Some(lang),
     utils: None,
+    rewriters: None,
     transform: None,
+    primary: None,
+    query: Some(scm_query),
+    chars: PatternChars::default(),
   };
   serde_wasm_bindgen::to_value(&config).map_err(|e| JsError::new(&e.to_string()))
 }
 
 // --- Pattern tree types ---
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 enum PatternKind {
   Terminal,
@@ -82,16 +1108,21 @@ enum PatternKind {
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct PatternPos {
   line: u32,
   column: u32,
+  /// offset in UTF-8 bytes
+  byte_offset: u32,
 }
 
-impl From<ts_types::Point> for PatternPos {
-  fn from(p: ts_types::Point) -> Self {
+impl PatternPos {
+  /// The position of `point` at the char offset `offset` of `src` in `encoding`.
+  fn new(src: &[char], point: ts_types::Point, offset: u32, encoding: PositionEncoding) -> Self {
     PatternPos {
-      line: p.row(),
-      column: p.column(),
+      line: point.row(),
+      column: encoding.encode_column(src, offset as usize) as u32,
+      byte_offset: encoding::byte_offset(src, offset as usize) as u32,
     }
   }
 }
@@ -106,46 +1137,388 @@ pub struct PatternTree {
   children: Vec<PatternTree>,
   text: Option<String>,
   pattern: Option<PatternKind>,
+  /// the compiled pattern, only set on the root of `dumpPattern`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  ir: Option<PatternIr>,
+}
+
+impl PatternTree {
+  /// Move positions back by `shift` ASCII chars on the first line, the code before a
+  /// `parseAs` pattern.
+  fn shift_back(&mut self, shift: u32) {
+    for pos in [&mut self.start, &mut self.end] {
+      if pos.line == 0 {
+        pos.column -= shift;
+      }
+      pos.byte_offset -= shift;
+    }
+    for child in &mut self.children {
+      child.shift_back(shift);
+    }
+  }
+}
+
+/// A `PatternNode`, what the matcher compares candidate nodes with.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum PatternIr {
+  /// `name` is unset for `$_` and `$$$`, `ellipsis` is set for `$$$` and `$$$A`.
+  /// `namedOnly` meta variables like `$A` skip unnamed nodes, unlike `$$A`.
+  #[serde(rename_all = "camelCase")]
+  MetaVar {
+    name: Option<String>,
+    ellipsis: bool,
+    named_only: bool,
+  },
+  #[serde(rename_all = "camelCase")]
+  Terminal {
+    kind: String,
+    text: String,
+    is_named: bool,
+  },
+  Internal {
+    kind: String,
+    children: Vec<PatternIr>,
+  },
+}
+
+impl PatternIr {
+  fn new(pattern: &PatternNode, lang: &ts_types::Language) -> Self {
+    let kind = |id: u16| lang.node_kind_for_id(id).unwrap_or_default();
+    match pattern {
+      PatternNode::MetaVar { meta_var } => {
+        let (name, ellipsis, named_only) = match meta_var {
+          MetaVariable::Capture(name, named) => (Some(name.clone()), false, *named),
+          MetaVariable::Dropped(named) => (None, false, *named),
+          MetaVariable::Multiple => (None, true, false),
+          MetaVariable::MultiCapture(name) => (Some(name.clone()), true, false),
+        };
+        PatternIr::MetaVar {
+          name,
+          ellipsis,
+          named_only,
+        }
+      }
+      PatternNode::Terminal {
+        text,
+        is_named,
+        kind_id,
+      } => PatternIr::Terminal {
+        kind: kind(*kind_id),
+        text: text.clone(),
+        is_named: *is_named,
+      },
+      PatternNode::Internal { kind_id, children } => PatternIr::Internal {
+        kind: kind(*kind_id),
+        children: children.iter().map(|c| PatternIr::new(c, lang)).collect(),
+      },
+    }
+  }
 }
 
 /// Dump a pattern's internal structure for inspection.
+/// `pattern` is a pattern string or object, or the first argument as for `pattern`.
 /// `selector` is an optional kind name for contextual patterns.
 /// `strictness` is one of: "cst", "smart", "ast", "relaxed", "signature", "template".
+/// `options` can set `expandoChar` and `metaVarChar`, as for `pattern`, and the
+/// `positionEncoding` of the columns.
 /// Returns a tree structure showing how ast-grep parses the pattern, including source positions.
-#[wasm_bindgen(js_name = dumpPattern)]
+#[wasm_bindgen(js_name = dumpPattern, skip_typescript)]
 pub fn dump_pattern(
-  lang: String,
-  pattern_str: String,
+  lang: JsValue,
+  pattern: JsValue,
   selector: Option<String>,
   strictness: Option<String>,
+  options: JsValue,
+) -> Result<JsValue, JsError> {
+  let (lang, mut pattern, shifted) = PatternObject::split_args(lang, pattern)?;
+  let options = match shifted {
+    Some(shifted) if selector.is_none() && strictness.is_none() => shifted,
+    Some(_) => return Err(JsError::new("Too many arguments after the pattern object.")),
+    None => options,
+  };
+  pattern.merge(selector, strictness.map(StrictnessOption::Level))?;
+  let options: Option<DumpPatternOptions> = serde_wasm_bindgen::from_value(options)?;
+  let options = options.unwrap_or_default();
+  let (mut tree, ir) = build_pattern_tree(lang, &pattern, &options)?;
+  tree.ir = Some(ir);
+  serde_wasm_bindgen::to_value(&tree).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// A node of `dumpSyntaxTree`'s output.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyntaxTree {
+  kind: String,
+  /// name of the field of the parent this node is in
+  field: Option<String>,
+  start: PatternPos,
+  end: PatternPos,
+  is_named: bool,
+  children: Vec<SyntaxTree>,
+  /// source text, only set for leaf nodes
+  text: Option<String>,
+}
+
+/// Dump the syntax tree of `src`, for tree viewers.
+/// `format` is `"cst"` (default) for every node, or `"ast"` for named nodes only.
+/// Columns are in `options.positionEncoding`.
+#[wasm_bindgen(js_name = dumpSyntaxTree)]
+pub fn dump_syntax_tree(
+  lang: String,
+  src: String,
+  format: Option<String>,
+  #[wasm_bindgen(unchecked_param_type = "EncodingOptions")] options: JsValue,
+) -> Result<JsValue, JsError> {
+  let options: Option<EncodingOption> = serde_wasm_bindgen::from_value(options)?;
+  let encoding = options.unwrap_or_default().position_encoding;
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let named_only = match format.as_deref() {
+    None | Some("cst") => false,
+    Some("ast") => true,
+    Some(f) => {
+      return Err(JsError::new(&format!(
+        "Unknown format `{f}`, expected cst or ast."
+      )))
+    }
+  };
+  let doc = WasmDoc::try_new(src, lang)?;
+  let cursor = doc.tree.root_node().walk();
+  let tree = dump_syntax_node(&cursor, doc.get_source().chars(), named_only, encoding);
+  cursor.delete();
+  serde_wasm_bindgen::to_value(&tree).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn dump_syntax_node(
+  cursor: &ts_types::TreeCursor,
+  src: &[char],
+  named_only: bool,
+  encoding: PositionEncoding,
+) -> SyntaxTree {
+  let node = cursor.current_node();
+  let field = cursor.current_field_name().map(String::from);
+  let mut children = vec![];
+  if cursor.goto_first_child() {
+    loop {
+      if !named_only || cursor.node_is_named() {
+        children.push(dump_syntax_node(cursor, src, named_only, encoding));
+      }
+      if !cursor.goto_next_sibling() {
+        break;
+      }
+    }
+    cursor.goto_parent();
+  }
+  let kind = String::from(node.type_());
+  let kind = if node.is_missing() {
+    format!("MISSING {kind}")
+  } else {
+    kind
+  };
+  let text = (node.child_count() == 0).then(|| String::from(node.text()));
+  SyntaxTree {
+    kind,
+    field,
+    start: PatternPos::new(src, node.start_position(), node.start_index(), encoding),
+    end: PatternPos::new(src, node.end_position(), node.end_index(), encoding),
+    is_named: node.is_named(),
+    children,
+    text,
+  }
+}
+
+/// Diff the syntax trees of two versions of a source, e.g. for a semantic code review.
+/// Returns `{ edits }`, where each edit has an `op`, `inserted`, `removed`, `moved` or
+/// `updated`, and the `{ kind, range, text }` of the subtree `before` and/or `after`.
+/// Ranges are in `options.positionEncoding`.
+#[wasm_bindgen(js_name = diffTrees, unchecked_return_type = "TreeDiff")]
+pub fn diff_trees(
+  lang: String,
+  old_src: String,
+  new_src: String,
+  #[wasm_bindgen(unchecked_param_type = "EncodingOptions")] options: JsValue,
+) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let options: Option<EncodingOption> = serde_wasm_bindgen::from_value(options)?;
+  let encoding = options.unwrap_or_default().position_encoding;
+  let old = AstGrep::doc(WasmDoc::try_new(old_src, lang)?.with_encoding(encoding));
+  let new = AstGrep::doc(WasmDoc::try_new(new_src, lang)?.with_encoding(encoding));
+  let diff = tree_diff::diff_trees(&old.root(), &new.root());
+  serde_wasm_bindgen::to_value(&diff).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Compute the size, nesting depth and cyclomatic complexity of each function of a source,
+/// e.g. to flag functions too complex to review. A nested function is measured on its own
+/// and not as part of the function containing it. Ranges are in `options.positionEncoding`.
+#[wasm_bindgen(js_name = computeMetrics, unchecked_return_type = "CodeMetrics")]
+pub fn compute_metrics(
+  lang: String,
+  src: String,
+  #[wasm_bindgen(unchecked_param_type = "EncodingOptions")] options: JsValue,
+) -> Result<JsValue, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let options: Option<EncodingOption> = serde_wasm_bindgen::from_value(options)?;
+  let encoding = options.unwrap_or_default().position_encoding;
+  let root = AstGrep::doc(WasmDoc::try_new(src, lang)?.with_encoding(encoding));
+  let metrics = metrics::compute(&root.root());
+  serde_wasm_bindgen::to_value(&metrics).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Generalize one or two code snippets into a pattern to start a rule from.
+/// A single example keeps its code with every literal replaced by a meta variable.
+/// With two examples the code they share is kept and what differs becomes meta variables,
+/// the same difference the same one. Throws if the examples have nothing in common.
+#[wasm_bindgen(js_name = inferPattern)]
+pub fn infer_pattern(
+  lang: String,
+  example_a: String,
+  example_b: Option<String>,
+) -> Result<String, JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let a = AstGrep::doc(WasmDoc::try_new(example_a, lang)?);
+  let b = example_b
+    .map(|src| WasmDoc::try_new(src, lang).map(AstGrep::doc))
+    .transpose()?;
+  let b = b.as_ref().map(|b| b.root());
+  infer::infer(&a.root(), b.as_ref())
+    .ok_or_else(|| JsError::new("The examples have no code in common to generalize."))
+}
+
+/// Diff the dumped trees of two patterns.
+/// Children are aligned by kind, and every added, removed or changed subtree is reported
+/// with its `PatternTree` from `patternA` as `before` and/or from `patternB` as `after`.
+/// Columns are in `options.positionEncoding`.
+#[wasm_bindgen(js_name = diffPatternTrees)]
+pub fn diff_pattern_trees(
+  lang: String,
+  #[wasm_bindgen(unchecked_param_type = "string | PatternObject")] pattern_a: JsValue,
+  #[wasm_bindgen(unchecked_param_type = "string | PatternObject")] pattern_b: JsValue,
+  #[wasm_bindgen(unchecked_param_type = "EncodingOptions")] options: JsValue,
+) -> Result<JsValue, JsError> {
+  let encoding: Option<EncodingOption> = serde_wasm_bindgen::from_value(options)?;
+  let options = DumpPatternOptions {
+    chars: PatternChars::default(),
+    encoding: encoding.unwrap_or_default(),
+  };
+  let (pattern_a, pattern_b) = (
+    PatternObject::from_js(pattern_a)?,
+    PatternObject::from_js(pattern_b)?,
+  );
+  pattern_a.check_language(&lang)?;
+  pattern_b.check_language(&lang)?;
+  let (before, _) = build_pattern_tree(lang.clone(), &pattern_a, &options)?;
+  let (after, _) = build_pattern_tree(lang, &pattern_b, &options)?;
+  let diff = pattern_diff::diff_trees(&before, &after);
+  serde_wasm_bindgen::to_value(&diff).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Check a pattern for common mistakes that still compile: several root nodes, meta
+/// variables that cannot capture because of their casing, a pattern that is only a meta
+/// variable and so matches every node, and with `fix`, meta variables the fix does not use.
+/// Returns an empty array for a clean pattern, with ranges in `options.positionEncoding`.
+/// Throws if the pattern is invalid for other reasons.
+#[wasm_bindgen(js_name = lintPattern, unchecked_return_type = "PatternLint[]")]
+pub fn lint_pattern(
+  lang: String,
+  #[wasm_bindgen(unchecked_param_type = "string | PatternObject")] pattern: JsValue,
+  fix: Option<String>,
+  #[wasm_bindgen(unchecked_param_type = "EncodingOptions")] options: JsValue,
 ) -> Result<JsValue, JsError> {
+  let options: Option<EncodingOption> = serde_wasm_bindgen::from_value(options)?;
+  let encoding = options.unwrap_or_default().position_encoding;
+  let pattern = PatternObject::from_js(pattern)?;
+  pattern.check_language(&lang)?;
   let lang: WasmLang = lang
     .parse()
     .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let lints = pattern_lint::lint(lang, &pattern, fix.as_deref(), encoding)?;
+  serde_wasm_bindgen::to_value(&lints).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// The meta variables of a pattern or rule config and of `fix`, sorted by name.
+/// Meta variables used in `fix` but never defined are listed, not rejected.
+#[wasm_bindgen(js_name = listMetaVariables, unchecked_return_type = "MetaVariableInfo[]")]
+pub fn list_meta_variables(
+  lang: String,
+  #[wasm_bindgen(unchecked_param_type = "string | PatternObject | WasmConfig")] matcher: JsValue,
+  fix: Option<String>,
+) -> Result<JsValue, JsError> {
+  let config: WasmConfig = if matcher.is_string() || PatternObject::is_object(&matcher) {
+    let mut pattern = PatternObject::from_js(matcher)?;
+    pattern.check_language(&lang)?;
+    pattern.language = None;
+    serde_json::from_value(serde_json::json!({ "rule": { "pattern": pattern } }))?
+  } else {
+    serde_wasm_bindgen::from_value(matcher)?
+  };
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let vars = meta_vars::list(lang, config, fix.as_deref())?;
+  serde_wasm_bindgen::to_value(&vars).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Options of `dumpPattern`.
+#[derive(Default, serde::Deserialize)]
+struct DumpPatternOptions {
+  #[serde(flatten)]
+  chars: PatternChars,
+  #[serde(flatten)]
+  encoding: EncodingOption,
+}
+
+fn build_pattern_tree(
+  lang: String,
+  pattern: &PatternObject,
+  options: &DumpPatternOptions,
+) -> Result<(PatternTree, PatternIr), JsError> {
+  let lang: WasmLang = lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let lang = lang.with_chars(&options.chars)?;
   // Pre-process the pattern string so tree-sitter can parse it as valid code.
   // Pattern::try_new also calls pre_process_pattern internally, but we need a
   // separate WasmDoc so we can look up positions from the actual parsed tree.
-  let processed = lang.pre_process_pattern(&pattern_str);
-  let doc = WasmDoc::try_new(processed.to_string(), lang)?;
-  let root = AstGrep::doc(doc);
-  let mut pat = if let Some(sel) = &selector {
-    Pattern::contextual(&pattern_str, sel, lang).map_err(|e| JsError::new(&e.to_string()))?
-  } else {
-    Pattern::try_new(&pattern_str, lang).map_err(|e| JsError::new(&e.to_string()))?
+  let pat = pattern.compile(lang)?;
+  // a `parseAs` pattern is parsed after the code `before` it, see `Pattern::parse_as`
+  let (pattern_str, before, after) = match &pattern.parse_as {
+    Some(kind) => {
+      let (before, after) = lang.parse_as_context(kind).unwrap_or_default();
+      (pattern.context.trim(), before, after)
+    }
+    None => (pattern.context.as_str(), "", ""),
   };
-  if let Some(s) = &strictness {
-    let strict: MatchStrictness = s.parse().map_err(|e: &str| JsError::new(e))?;
-    pat = pat.with_strictness(strict);
-  }
+  let processed = lang.pre_process_pattern(pattern_str);
+  let doc = WasmDoc::try_new(format!("{before}{processed}{after}"), lang)?;
+  let root = AstGrep::doc(doc);
   let found = root
     .root()
-    .find(&pat)
+    .find_all(&pat)
+    .find(|found| found.range().start >= before.len())
     .ok_or_else(|| JsError::new("Pattern has no root node"))?;
-  let tree = dump_pattern_node(found.into(), &pat.node);
-  serde_wasm_bindgen::to_value(&tree).map_err(|e| JsError::new(&e.to_string()))
+  // pre-processing replaces chars one by one, so offsets are the same in `pattern_str`
+  let src: Vec<char> = before.chars().chain(pattern_str.chars()).collect();
+  let encoding = options.encoding.position_encoding;
+  let mut tree = dump_pattern_node(found.into(), &pat.node, &src, encoding);
+  tree.shift_back(before.len() as u32);
+  let ir = PatternIr::new(&pat.node, &lang.get_ts_language());
+  Ok((tree, ir))
 }
 
-fn dump_pattern_node<'r>(node: CoreNode<'r, WasmDoc>, pattern: &PatternNode) -> PatternTree {
+fn dump_pattern_node<'r>(
+  node: CoreNode<'r, WasmDoc>,
+  pattern: &PatternNode,
+  src: &[char],
+  encoding: PositionEncoding,
+) -> PatternTree {
   use PatternNode as PN;
   let ts = node.get_inner_node().0;
   let kind = if ts.is_missing() {
@@ -155,41 +1528,47 @@ fn dump_pattern_node<'r>(node: CoreNode<'r, WasmDoc>, pattern: &PatternNode) ->
   };
   match pattern {
     PN::MetaVar { .. } => {
-      let expando = node.lang().expando_char();
-      let text = node.text().to_string().replace(expando, "$");
+      let lang = node.lang();
+      let text = node
+        .text()
+        .to_string()
+        .replace(lang.expando_char(), &lang.meta_var_char().to_string());
       PatternTree {
         kind,
-        start: ts.start_position().into(),
-        end: ts.end_position().into(),
+        start: PatternPos::new(src, ts.start_position(), ts.start_index(), encoding),
+        end: PatternPos::new(src, ts.end_position(), ts.end_index(), encoding),
         is_named: true,
         children: vec![],
         text: Some(text),
         pattern: Some(PatternKind::MetaVar),
+        ir: None,
       }
     }
     PN::Terminal { is_named, .. } => PatternTree {
       kind,
-      start: ts.start_position().into(),
-      end: ts.end_position().into(),
+      start: PatternPos::new(src, ts.start_position(), ts.start_index(), encoding),
+      end: PatternPos::new(src, ts.end_position(), ts.end_index(), encoding),
       is_named: *is_named,
       children: vec![],
       text: Some(node.text().into_owned()),
       pattern: Some(PatternKind::Terminal),
+      ir: None,
     },
     PN::Internal { children, .. } => {
       let children = children
         .iter()
         .zip(node.children())
-        .map(|(pn, n)| dump_pattern_node(n, pn))
+        .map(|(pn, n)| dump_pattern_node(n, pn, src, encoding))
         .collect();
       PatternTree {
         kind,
-        start: ts.start_position().into(),
-        end: ts.end_position().into(),
+        start: PatternPos::new(src, ts.start_position(), ts.start_index(), encoding),
+        end: PatternPos::new(src, ts.end_position(), ts.end_index(), encoding),
         is_named: true,
         children,
         text: None,
         pattern: Some(PatternKind::Internal),
+        ir: None,
       }
     }
   }
@@ -0,0 +1,182 @@
+//! Hard limits of `parse`, `find` and `findAll` for untrusted input.
+//!
+//! A source over `maxSourceSize` is rejected before it is parsed, a tree deeper than
+//! `maxTreeDepth` or with more than `maxNodes` nodes after, and a matcher whose matching
+//! would recurse deeper than `maxPatternDepth` before it is used. Each fails with a
+//! `LimitExceededError` carrying the `limit` and its `max`, so callers can tell them
+//! from other errors.
+
+use crate::doc::{WasmConfig, WasmDoc};
+use crate::sg_node::MatcherType;
+use crate::ts_types::TreeCursor;
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_core::matcher::PatternNode;
+use ast_grep_core::{AstGrep, Pattern};
+use js_sys::Reflect;
+use serde::Deserialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+/// Limits of `parse` on the source and its tree.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceLimits {
+  /// the number of characters of the source
+  pub max_source_size: Option<usize>,
+  /// the number of nodes on the longest path from the root, the root included
+  pub max_tree_depth: Option<usize>,
+  /// the number of nodes of the tree, named or not
+  pub max_nodes: Option<usize>,
+}
+
+/// A `LimitExceededError` of `limit`.
+fn exceeded(limit: &str, max: usize, msg: &str) -> JsError {
+  let error = JsError::new(msg);
+  let value = JsValue::from(error.clone());
+  let props = [
+    ("name", JsValue::from("LimitExceededError")),
+    ("limit", JsValue::from(limit)),
+    ("max", JsValue::from(max)),
+  ];
+  for (key, prop) in props {
+    let _ = Reflect::set(&value, &key.into(), &prop);
+  }
+  error
+}
+
+impl SourceLimits {
+  pub fn check_source(&self, src: &str) -> Result<(), JsError> {
+    let Some(max) = self.max_source_size else {
+      return Ok(());
+    };
+    // a char is at least one byte
+    if src.len() > max && src.chars().count() > max {
+      let msg =
+        format!("The source is longer than {max} characters, the limit of `maxSourceSize`.");
+      return Err(exceeded("maxSourceSize", max, &msg));
+    }
+    Ok(())
+  }
+
+  /// Walk the tree until it is found to be too deep or too large.
+  pub fn check_tree(&self, root: &AstGrep<WasmDoc>) -> Result<(), JsError> {
+    if self.max_tree_depth.is_none() && self.max_nodes.is_none() {
+      return Ok(());
+    }
+    let cursor = root.root().get_inner_node().0.walk();
+    let checked = self.walk(&cursor);
+    cursor.delete();
+    checked
+  }
+
+  fn walk(&self, cursor: &TreeCursor) -> Result<(), JsError> {
+    let (mut depth, mut nodes) = (1, 1);
+    loop {
+      if let Some(max) = self.max_tree_depth.filter(|max| depth > *max) {
+        let msg = format!("The tree is deeper than {max} nodes, the limit of `maxTreeDepth`.");
+        return Err(exceeded("maxTreeDepth", max, &msg));
+      }
+      if let Some(max) = self.max_nodes.filter(|max| nodes > *max) {
+        let msg = format!("The tree has more than {max} nodes, the limit of `maxNodes`.");
+        return Err(exceeded("maxNodes", max, &msg));
+      }
+      if cursor.goto_first_child() {
+        depth += 1;
+        nodes += 1;
+        continue;
+      }
+      loop {
+        if cursor.goto_next_sibling() {
+          nodes += 1;
+          break;
+        }
+        if !cursor.goto_parent() {
+          return Ok(());
+        }
+        depth -= 1;
+      }
+    }
+  }
+}
+
+fn node_depth(node: &PatternNode) -> usize {
+  match node {
+    PatternNode::MetaVar { .. } | PatternNode::Terminal { .. } => 1,
+    PatternNode::Internal { children, .. } => {
+      1 + children.iter().map(node_depth).max().unwrap_or(0)
+    }
+  }
+}
+
+/// The depth of a pattern string's tree. Patterns that fail to compile are reported when
+/// the matcher is compiled.
+fn source_depth(src: &str, lang: WasmLang) -> usize {
+  Pattern::try_new(src, lang).map_or(0, |p| node_depth(&p.node))
+}
+
+/// The nesting of rules in a rule object, plus the depth of the patterns in it.
+/// The context of a contextual pattern stands in for the selected pattern, which is
+/// never deeper.
+fn rule_depth(value: &Value, lang: WasmLang) -> usize {
+  match value {
+    Value::Object(map) => {
+      let inner = map.iter().map(|(key, value)| match (key.as_str(), value) {
+        ("pattern", Value::String(src)) => source_depth(src, lang),
+        ("pattern", Value::Object(pattern)) => pattern
+          .get("context")
+          .and_then(Value::as_str)
+          .map_or(0, |src| source_depth(src, lang)),
+        _ => rule_depth(value, lang),
+      });
+      1 + inner.max().unwrap_or(0)
+    }
+    Value::Array(items) => items
+      .iter()
+      .map(|item| rule_depth(item, lang))
+      .max()
+      .unwrap_or(0),
+    _ => 0,
+  }
+}
+
+/// How deep matching a node with `matcher`, compiled from `m`, recurses: the depth of
+/// its pattern, or the nesting of its rules and their patterns, with utils and
+/// constraints each on their own.
+fn matcher_depth(m: &JsValue, matcher: &MatcherType, lang: WasmLang) -> usize {
+  let config = match matcher {
+    MatcherType::Pattern(p) => return node_depth(&p.node),
+    MatcherType::Kind(_) | MatcherType::Query(..) => return 1,
+    MatcherType::Rule(..) => match serde_wasm_bindgen::from_value::<WasmConfig>(m.clone()) {
+      Ok(config) => config,
+      // a handle of `compileMatcher`, whose rules are not kept
+      Err(_) => return 0,
+    },
+  };
+  let mut depth = rule_depth(&config.rule, lang);
+  for rules in [&config.utils, &config.constraints].into_iter().flatten() {
+    if let Value::Object(rules) = rules {
+      let inner = rules.values().map(|rule| rule_depth(rule, lang));
+      depth = depth.max(inner.max().unwrap_or(0));
+    }
+  }
+  depth
+}
+
+/// Fails if matching with `matcher` recurses deeper than `max`.
+/// Rules compiled by `compileMatcher` are not checked, only patterns.
+pub fn check_matcher(
+  m: &JsValue,
+  matcher: &MatcherType,
+  lang: WasmLang,
+  max: Option<usize>,
+) -> Result<(), JsError> {
+  let Some(max) = max else {
+    return Ok(());
+  };
+  if matcher_depth(m, matcher, lang) > max {
+    let msg = format!("The matcher is deeper than {max} levels, the limit of `maxPatternDepth`.");
+    return Err(exceeded("maxPatternDepth", max, &msg));
+  }
+  Ok(())
+}
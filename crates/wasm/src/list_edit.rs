@@ -0,0 +1,167 @@
+//! Edits of separated lists for `SgNode.removeFromList` and `SgNode.insertIntoList`.
+//!
+//! A list is any node with named children, like arguments, imports or object fields.
+//! Its items are the named children but comments, and its separators are the `,` and `;`
+//! tokens between them, so lists with a trailing separator keep it and lists separated
+//! by line breaks only, like Go imports, work as well. New items are separated like the
+//! first two items of the list, with the same separator and the same space or indentation.
+
+use crate::doc::WasmDoc;
+
+use ast_grep_core::{Doc, Node};
+use std::ops::Range;
+
+type SgNode<'r> = Node<'r, WasmDoc>;
+
+const SEPARATORS: &[&str] = &[",", ";"];
+
+struct List<'r> {
+  items: Vec<SgNode<'r>>,
+  /// the separator after each item, if any
+  separators: Vec<Option<SgNode<'r>>>,
+  /// a child before the first item, like an opening bracket
+  opening: Option<SgNode<'r>>,
+}
+
+impl<'r> List<'r> {
+  fn new(list: &SgNode<'r>) -> Self {
+    let mut items = vec![];
+    let mut separators: Vec<Option<SgNode>> = vec![];
+    let mut opening = None;
+    for child in list.children() {
+      if is_item(&child) {
+        items.push(child);
+        separators.push(None);
+      } else if let Some(last) = separators.last_mut() {
+        if last.is_none() && SEPARATORS.contains(&&*child.text()) {
+          *last = Some(child);
+        }
+      } else if opening.is_none() && !child.kind().contains("comment") {
+        opening = Some(child);
+      }
+    }
+    Self {
+      items,
+      separators,
+      opening,
+    }
+  }
+
+  /// Where the item at `nth` ends with its separator.
+  fn item_end(&self, nth: usize) -> usize {
+    match &self.separators[nth] {
+      Some(sep) => sep.range().end,
+      None => self.items[nth].range().end,
+    }
+  }
+
+  /// The text of the separator and what follows it up to the next item.
+  fn separation(&self) -> (String, String) {
+    let sep = match self.separators.iter().flatten().next() {
+      Some(sep) => sep.text().to_string(),
+      // items only separated by line breaks
+      None if self.items.len() > 1 => String::new(),
+      None => ",".to_string(),
+    };
+    // the first items with a separator between them, if any
+    let pairs = 1..self.items.len();
+    let nth = pairs.clone().find(|i| self.separators[i - 1].is_some());
+    if let Some(nth) = nth.or(pairs.min()) {
+      let src = self.items[0].get_doc().get_source().chars();
+      let next = self.items[nth].range().start;
+      let gap = src[self.item_end(nth - 1)..next].iter().collect();
+      return (sep, gap);
+    }
+    let gap = match (&self.opening, self.items.first()) {
+      // a list of items on their own lines
+      (Some(open), Some(item)) if item.start_pos().line() > open.start_pos().line() => {
+        let src = item.get_doc().get_source().chars();
+        let line_start = src[..item.range().start]
+          .iter()
+          .rposition(|c| *c == '\n')
+          .map_or(0, |i| i + 1);
+        let indent: String = src[line_start..item.range().start].iter().collect();
+        format!("\n{indent}")
+      }
+      _ => " ".to_string(),
+    };
+    (sep, gap)
+  }
+}
+
+fn is_item(node: &SgNode) -> bool {
+  node.is_named() && !node.kind().contains("comment")
+}
+
+/// The start of the first sibling after `node` that is not only whitespace.
+fn next_start(node: &SgNode) -> Option<usize> {
+  node
+    .next_all()
+    .find(|n| !n.text().trim().is_empty())
+    .map(|n| n.range().start)
+}
+
+/// The range to delete to remove `node` and a separator from the list it is an item of.
+pub fn remove(node: &SgNode) -> Result<Range<usize>, String> {
+  let not_item = || "The node is not an item of a list.".to_string();
+  let mut node = node.clone();
+  let mut parent = node.parent().ok_or_else(not_item)?;
+  // the item of an identifier is its node of the same text, like an import specifier
+  while parent.range() == node.range() {
+    node = parent;
+    parent = node.parent().ok_or_else(not_item)?;
+  }
+  let node = &node;
+  let list = List::new(&parent);
+  let nth = list
+    .items
+    .iter()
+    .position(|item| item.node_id() == node.node_id())
+    .ok_or_else(not_item)?;
+  let count = list.items.len();
+  let range = node.range();
+  let end = list.item_end(nth);
+  if nth + 1 < count {
+    // up to the next item, or a comment before it
+    let after = list.separators[nth].as_ref().unwrap_or(node);
+    let next = next_start(after).unwrap_or(list.items[nth + 1].range().start);
+    return Ok(range.start..next);
+  }
+  if nth > 0 {
+    let prev = &list.items[nth - 1];
+    // the last item keeps the trailing separator of the list, if it has one
+    return Ok(match (&list.separators[nth - 1], &list.separators[nth]) {
+      (Some(sep), Some(_)) => sep.range().end..end,
+      _ => prev.range().end..range.end,
+    });
+  }
+  // the only item leaves an empty list like `()`
+  let start = list.opening.map_or(range.start, |o| o.range().end);
+  let after = list.separators[nth].as_ref().unwrap_or(node);
+  Ok(start..next_start(after).unwrap_or(end))
+}
+
+/// The offset and text to insert to add `text` to `list` as its `nth` item.
+pub fn insert(list: &SgNode, nth: usize, text: &str) -> Result<(usize, String), String> {
+  let parsed = List::new(list);
+  let count = parsed.items.len();
+  if nth > count {
+    return Err(format!("The list has only {count} items."));
+  }
+  let (sep, gap) = parsed.separation();
+  if nth < count {
+    let start = parsed.items[nth].range().start;
+    return Ok((start, format!("{text}{sep}{gap}")));
+  }
+  if count == 0 {
+    let open = parsed
+      .opening
+      .ok_or_else(|| "The node is not a list.".to_string())?;
+    return Ok((open.range().end, text.to_string()));
+  }
+  let last = count - 1;
+  Ok(match parsed.separators[last] {
+    Some(_) => (parsed.item_end(last), format!("{gap}{text}{sep}")),
+    None => (parsed.items[last].range().end, format!("{sep}{gap}{text}")),
+  })
+}
@@ -0,0 +1,99 @@
+//! Meta variables of patterns and rules for `listMetaVariables`, so editors that build
+//! rules can offer the variables for constraints, transformations and fixes.
+//!
+//! Where variables are defined and used comes from the compiled rule. Whether a variable
+//! is multi, like `$$$ARGS`, is read from the text of the rule's patterns, as in
+//! `lintPattern`, since compiled patterns only keep the names.
+
+use crate::doc::{error_chain, WasmConfig};
+use crate::global_utils;
+use crate::pattern_lint::tokens;
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_config::SerializableRuleCore;
+use ast_grep_core::Language;
+use serde::Serialize;
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaVariable {
+  name: String,
+  multi: bool,
+  captured: bool,
+  transformed: bool,
+  in_constraints: bool,
+  in_transform: bool,
+  in_fix: bool,
+}
+
+/// Names of the multi meta variables in the `pattern` strings and `context`s of `value`.
+fn multi_vars(value: &serde_json::Value, meta_char: char, multi: &mut HashSet<String>) {
+  match value {
+    serde_json::Value::Object(map) => {
+      for (key, value) in map {
+        match value {
+          serde_json::Value::String(src) if key == "pattern" || key == "context" => {
+            let src: Vec<char> = src.chars().collect();
+            let found = tokens(&src, meta_char).into_iter();
+            multi.extend(
+              found
+                .filter(|t| t.prefix == 3 && t.is_valid())
+                .map(|t| t.name),
+            );
+          }
+          _ => multi_vars(value, meta_char, multi),
+        }
+      }
+    }
+    serde_json::Value::Array(values) => {
+      for value in values {
+        multi_vars(value, meta_char, multi);
+      }
+    }
+    _ => (),
+  }
+}
+
+/// The meta variables of `config` and `fix`, sorted by name.
+pub fn list(
+  lang: WasmLang,
+  mut config: WasmConfig,
+  fix: Option<&str>,
+) -> Result<Vec<MetaVariable>, JsError> {
+  if config.query.is_some() {
+    return Err(JsError::new(
+      "Meta variables of a `query` cannot be listed.",
+    ));
+  }
+  lang.check_registered()?;
+  let lang = lang.with_chars(&config.chars)?;
+  let mut multi = HashSet::new();
+  let rules = [config.utils.as_ref(), config.constraints.as_ref()];
+  for value in std::iter::once(&config.rule).chain(rules.into_iter().flatten()) {
+    multi_vars(value, lang.meta_var_char(), &mut multi);
+  }
+  // `minCount` and `maxCount` constrain a variable without being a rule
+  let counted: Vec<_> = config.take_counts()?.into_iter().map(|c| c.0).collect();
+  let rule = SerializableRuleCore {
+    rule: serde_json::from_value(config.rule)?,
+    constraints: config.constraints.map(serde_json::from_value).transpose()?,
+    transform: config.transform.map(serde_json::from_value).transpose()?,
+    utils: config.utils.map(serde_json::from_value).transpose()?,
+    fix: fix.map(|f| serde_json::from_value(f.into())).transpose()?,
+  };
+  let usage = rule
+    .meta_var_usage(global_utils::env(lang))
+    .map_err(|e| error_chain(&e))?;
+  let vars = usage.into_iter().map(|usage| MetaVariable {
+    multi: multi.contains(&usage.name),
+    in_constraints: usage.in_constraints || counted.contains(&usage.name),
+    name: usage.name,
+    captured: usage.captured,
+    transformed: usage.transformed,
+    in_transform: usage.in_transform,
+    in_fix: usage.in_fix,
+  });
+  Ok(vars.collect())
+}
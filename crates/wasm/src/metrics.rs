@@ -0,0 +1,180 @@
+//! Code metrics for `computeMetrics`: node counts, nesting depth and cyclomatic
+//! complexity per function and for the code outside functions.
+//!
+//! Grammars share no kind names, so like `taint` functions and control flow are
+//! recognized by the words of kind names, e.g. `if` in `if_statement` and `elif_clause`.
+//! Each node is measured for the innermost function containing it only.
+
+use crate::doc::WasmDoc;
+use crate::sg_node::{node_range, Range};
+use crate::taint;
+
+use ast_grep_core::Node;
+use serde::Serialize;
+
+type SgNode<'r> = Node<'r, WasmDoc>;
+
+/// Words of branch and loop kinds, each adding one path through the code.
+const DECISIONS: &[&str] = &[
+  "if",
+  "elif",
+  "for",
+  "foreach",
+  "while",
+  "do",
+  "loop",
+  "until",
+  "case",
+  "catch",
+  "except",
+  "rescue",
+  "conditional",
+  "ternary",
+  "arm",
+];
+/// Words of the kinds whose bodies are nested one level deeper.
+const NESTING: &[&str] = &[
+  "if", "for", "foreach", "while", "do", "loop", "until", "switch", "match", "try", "with",
+];
+/// Short-circuit operators, which branch like an `if`.
+const LOGICAL: &[&str] = &["&&", "||", "??", "and", "or"];
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+  /// named nodes, excluding nested functions
+  pub nodes: usize,
+  /// deepest nesting of control flow, 0 for straight-line code
+  pub max_nesting: usize,
+  /// one plus the number of branches, loops, cases, catches and short-circuit operators
+  pub complexity: usize,
+}
+
+#[derive(Serialize)]
+pub struct FunctionMetrics {
+  /// the declared name, or the name it is assigned to, like `f` in `const f = () => {}`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  pub kind: String,
+  pub range: Range,
+  #[serde(flatten)]
+  pub metrics: Metrics,
+}
+
+#[derive(Serialize)]
+pub struct CodeMetrics {
+  /// the code outside functions
+  pub file: Metrics,
+  /// in the order of their start
+  pub functions: Vec<FunctionMetrics>,
+}
+
+fn words(node: &SgNode<'_>) -> Vec<String> {
+  node.kind().split('_').map(str::to_string).collect()
+}
+
+fn is_function(node: &SgNode<'_>) -> bool {
+  taint::is_scope(node) && node.field("body").is_some()
+}
+
+/// A clause of its parent's construct, like Go's `for_clause` in a `for_statement`,
+/// is not counted again.
+fn is_clause_of_parent(node: &SgNode<'_>, words: &[String]) -> bool {
+  if words.last().map(String::as_str) != Some("clause") {
+    return false;
+  }
+  let Some(parent) = node.parent() else {
+    return false;
+  };
+  parent.kind().split('_').next() == words.first().map(String::as_str)
+}
+
+fn is_decision(node: &SgNode<'_>) -> bool {
+  if !node.is_named() || node.is_leaf() {
+    return false;
+  }
+  let words = words(node);
+  let has = |list: &[&str]| words.iter().any(|w| list.contains(&w.as_str()));
+  if has(DECISIONS) && !has(&["default", "block"]) && !is_clause_of_parent(node, &words) {
+    return true;
+  }
+  node
+    .children()
+    .any(|c| !c.is_named() && LOGICAL.contains(&&*c.text()))
+}
+
+fn is_nesting(node: &SgNode<'_>) -> bool {
+  // Rust's `try_expression` is the `?` operator
+  if !node.is_named() || node.is_leaf() || node.kind() == "try_expression" {
+    return false;
+  }
+  let words = words(node);
+  let has = |list: &[&str]| words.iter().any(|w| list.contains(&w.as_str()));
+  if !has(NESTING) || has(&["clause", "case", "arm", "default", "block"]) {
+    return false;
+  }
+  // an `else if` continues the chain of its `if` rather than nesting in it
+  let in_else = node.parent().is_some_and(|p| p.kind().contains("else"));
+  !(in_else && has(&["if"]))
+}
+
+/// The name of a function, or of the variable, property or key it is assigned to.
+fn function_name(node: &SgNode<'_>) -> Option<String> {
+  if let Some(name) = node.field("name") {
+    return Some(name.text().to_string());
+  }
+  let parent = node.parent()?;
+  ["name", "left", "key"]
+    .iter()
+    .filter_map(|field| parent.field(field))
+    .find(|n| n.node_id() != node.node_id())
+    .map(|n| n.text().to_string())
+}
+
+/// Measure `owner`'s subtree except functions nested in it, which are returned.
+fn measure<'r>(owner: &SgNode<'r>) -> (Metrics, Vec<SgNode<'r>>) {
+  let mut metrics = Metrics {
+    complexity: 1,
+    ..Metrics::default()
+  };
+  let mut nested = vec![];
+  let mut stack: Vec<_> = owner.children().map(|c| (c, 0)).collect();
+  stack.reverse();
+  while let Some((node, depth)) = stack.pop() {
+    if is_function(&node) {
+      nested.push(node);
+      continue;
+    }
+    if node.is_named() {
+      metrics.nodes += 1;
+    }
+    if is_decision(&node) {
+      metrics.complexity += 1;
+    }
+    let depth = if is_nesting(&node) { depth + 1 } else { depth };
+    metrics.max_nesting = metrics.max_nesting.max(depth);
+    let start = stack.len();
+    stack.extend(node.children().map(|c| (c, depth)));
+    stack[start..].reverse();
+  }
+  (metrics, nested)
+}
+
+pub fn compute(root: &SgNode<'_>) -> CodeMetrics {
+  let (file, mut pending) = measure(root);
+  let mut functions = vec![];
+  while let Some(function) = pending.pop() {
+    let (mut metrics, nested) = measure(&function);
+    // the function node itself
+    metrics.nodes += 1;
+    pending.extend(nested);
+    functions.push(FunctionMetrics {
+      name: function_name(&function),
+      kind: function.kind().to_string(),
+      range: node_range(&function),
+      metrics,
+    });
+  }
+  functions.sort_by_key(|f| (f.range.start.index, std::cmp::Reverse(f.range.end.index)));
+  CodeMetrics { file, functions }
+}
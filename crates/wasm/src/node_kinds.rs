@@ -0,0 +1,213 @@
+//! Kinds of a registered language for `listKinds`.
+
+use crate::wasm_lang::WasmLang;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use wasm_bindgen::prelude::*;
+
+/// A kind of the grammar, with its schema from `node-types.json` if registered.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindInfo {
+  id: u16,
+  kind: String,
+  is_named: bool,
+  /// kinds a supertype like `expression` stands for
+  #[serde(skip_serializing_if = "Option::is_none")]
+  subtypes: Option<Vec<String>>,
+  /// field names, sorted
+  #[serde(skip_serializing_if = "Option::is_none")]
+  fields: Option<Vec<String>>,
+  /// kinds of the named children that are not in a field
+  #[serde(skip_serializing_if = "Option::is_none")]
+  children: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TypeRef {
+  #[serde(rename = "type")]
+  pub kind: String,
+  pub named: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ChildTypes {
+  #[serde(default)]
+  pub required: bool,
+  #[serde(default)]
+  pub multiple: bool,
+  pub types: Vec<TypeRef>,
+}
+
+/// An entry of `node-types.json`.
+#[derive(Deserialize)]
+pub(crate) struct NodeType {
+  #[serde(rename = "type")]
+  pub kind: String,
+  pub named: bool,
+  #[serde(default)]
+  pub root: bool,
+  pub subtypes: Option<Vec<TypeRef>>,
+  pub fields: Option<BTreeMap<String, ChildTypes>>,
+  pub children: Option<ChildTypes>,
+}
+
+/// The parsed `node-types.json` of `lang`, if registered.
+pub(crate) fn node_types(lang: WasmLang) -> Result<Option<Vec<NodeType>>, JsError> {
+  let Some(json) = lang.node_types() else {
+    return Ok(None);
+  };
+  serde_json::from_str(&json)
+    .map(Some)
+    .map_err(|e| JsError::new(&format!("Invalid node types: {e}")))
+}
+
+/// The subtypes of each named supertype like `expression`.
+pub(crate) fn supertypes(types: &[NodeType]) -> HashMap<&str, &[TypeRef]> {
+  types
+    .iter()
+    .filter(|t| t.named)
+    .filter_map(|t| Some((t.kind.as_str(), t.subtypes.as_deref()?)))
+    .collect()
+}
+
+/// The concrete kinds of the types, with supertypes replaced by the kinds they stand for.
+pub(crate) fn resolve(
+  types: &[TypeRef],
+  supertypes: &HashMap<&str, &[TypeRef]>,
+) -> BTreeSet<String> {
+  fn walk(
+    types: &[TypeRef],
+    supertypes: &HashMap<&str, &[TypeRef]>,
+    seen: &mut Vec<String>,
+    kinds: &mut BTreeSet<String>,
+  ) {
+    for t in types {
+      match supertypes.get(t.kind.as_str()) {
+        Some(subtypes) if t.named => {
+          if seen.contains(&t.kind) {
+            continue;
+          }
+          seen.push(t.kind.clone());
+          walk(subtypes, supertypes, seen, kinds);
+          seen.pop();
+        }
+        _ => {
+          kinds.insert(t.kind.clone());
+        }
+      }
+    }
+  }
+  let mut kinds = BTreeSet::new();
+  walk(types, supertypes, &mut vec![], &mut kinds);
+  kinds
+}
+
+fn type_names(types: Vec<TypeRef>) -> Vec<String> {
+  types.into_iter().map(|t| t.kind).collect()
+}
+
+/// List the visible kinds of `lang` in id order, each name and namedness once,
+/// plus the supertypes declared in its node types.
+pub fn list_kinds(lang: WasmLang) -> Result<Vec<KindInfo>, JsError> {
+  let mut schema: HashMap<(String, bool), NodeType> = node_types(lang)?
+    .unwrap_or_default()
+    .into_iter()
+    .map(|t| ((t.kind.clone(), t.named), t))
+    .collect();
+  let ts_lang = lang.get_ts_language();
+  let mut kinds: Vec<KindInfo> = vec![];
+  for id in 0..ts_lang.node_kind_count() {
+    let Some(kind) = ts_lang.node_kind_for_id(id) else {
+      continue;
+    };
+    let is_named = ts_lang.node_kind_is_named(id);
+    let key = (kind, is_named);
+    let is_supertype = schema.get(&key).is_some_and(|t| t.subtypes.is_some());
+    if !ts_lang.node_kind_is_visible(id) && !is_supertype {
+      continue;
+    }
+    if kinds.iter().any(|k| k.kind == key.0 && k.is_named == key.1) {
+      continue;
+    }
+    let node_type = schema.remove(&key);
+    let (kind, is_named) = key;
+    let mut info = KindInfo {
+      id,
+      kind,
+      is_named,
+      subtypes: None,
+      fields: None,
+      children: None,
+    };
+    if let Some(node_type) = node_type {
+      info.subtypes = node_type.subtypes.map(type_names);
+      info.fields = node_type.fields.map(|f| f.into_keys().collect());
+      info.children = node_type.children.map(|c| type_names(c.types));
+    }
+    kinds.push(info);
+  }
+  Ok(kinds)
+}
+
+/// The kinds a field can have, with supertypes resolved.
+#[derive(Serialize)]
+pub struct FieldSchema {
+  kinds: Vec<String>,
+  required: bool,
+  multiple: bool,
+}
+
+/// The schema of a kind for `nodeTypeSchema`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindSchema {
+  kind: String,
+  is_named: bool,
+  /// kinds a supertype like `expression` stands for
+  #[serde(skip_serializing_if = "Option::is_none")]
+  subtypes: Option<Vec<String>>,
+  fields: BTreeMap<String, FieldSchema>,
+  /// kinds of every child, in a field or not, that `has` can match without `stopBy`
+  children: Vec<String>,
+}
+
+/// The schema of every kind in the node types of `lang`, `None` if it has none.
+pub fn kind_schema(lang: WasmLang) -> Result<Option<Vec<KindSchema>>, JsError> {
+  let Some(types) = node_types(lang)? else {
+    return Ok(None);
+  };
+  let supertypes = supertypes(&types);
+  let schema = types
+    .iter()
+    .map(|t| {
+      let fields: BTreeMap<_, _> = t
+        .fields
+        .iter()
+        .flatten()
+        .map(|(name, field)| {
+          let schema = FieldSchema {
+            kinds: resolve(&field.types, &supertypes).into_iter().collect(),
+            required: field.required,
+            multiple: field.multiple,
+          };
+          (name.clone(), schema)
+        })
+        .collect();
+      let mut children: BTreeSet<_> = fields.values().flat_map(|f| f.kinds.clone()).collect();
+      if let Some(c) = &t.children {
+        children.extend(resolve(&c.types, &supertypes));
+      }
+      let subtypes = t.subtypes.as_deref().map(|s| resolve(s, &supertypes));
+      KindSchema {
+        kind: t.kind.clone(),
+        is_named: t.named,
+        subtypes: subtypes.map(|s| s.into_iter().collect()),
+        fields,
+        children: children.into_iter().collect(),
+      }
+    })
+    .collect();
+  Ok(Some(schema))
+}
@@ -0,0 +1,133 @@
+//! Source normalization of `parse`'s `normalizeLineEndings` and `stripBom`, for files
+//! authored on Windows or saved with a byte order mark.
+//!
+//! The normalized source is parsed, and a source map with a segment after every removed
+//! char maps its positions back to the source passed in, so ranges can be used on the
+//! file as read. Lines are counted like editors do, with `\r\n`, `\r` and `\n` each ending
+//! one. Code made from the tree, like by `commitEdits` or `replaceAll`, has the normalized
+//! line endings, and `SgRoot.denormalize` restores those of the source.
+
+use crate::encoding::{self, PositionEncoding};
+use crate::source_map::{OriginalPos, Segment};
+
+use serde::Deserialize;
+
+const BOM: char = '\u{feff}';
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizeOptions {
+  /// Parse `\r\n` and `\r` as `\n`.
+  #[serde(default)]
+  normalize_line_endings: bool,
+  /// Parse the source without a leading byte order mark.
+  #[serde(default)]
+  strip_bom: bool,
+}
+
+/// How to turn code of the normalized source back into code like the source.
+#[derive(Clone, Copy)]
+pub struct Normalization {
+  /// the most common line ending of the source, if it is not `\n`
+  line_ending: Option<&'static str>,
+  bom: bool,
+}
+
+impl Normalization {
+  pub fn restore(&self, code: &str) -> String {
+    let mut restored = String::with_capacity(code.len());
+    if self.bom && !code.starts_with(BOM) {
+      restored.push(BOM);
+    }
+    match self.line_ending {
+      Some(ending) => restored.push_str(&code.replace('\n', ending)),
+      None => restored.push_str(code),
+    }
+    restored
+  }
+}
+
+pub struct Normalized {
+  pub src: String,
+  /// positions of `src` in the source, see `withSourceMap`
+  pub segments: Vec<Segment>,
+  pub restore: Normalization,
+}
+
+/// `src` normalized by `options`, or `None` if they leave it unchanged.
+/// The segments are in `encoding`, the position encoding of the parsed doc.
+pub fn normalize(
+  src: &str,
+  options: &NormalizeOptions,
+  encoding: PositionEncoding,
+) -> Option<Normalized> {
+  let chars: Vec<char> = src.chars().collect();
+  let bom = options.strip_bom && chars.first() == Some(&BOM);
+  let has_cr = options.normalize_line_endings && chars.contains(&'\r');
+  if !bom && !has_cr {
+    return None;
+  }
+  let mut normalized = Vec::with_capacity(chars.len());
+  // char offsets in `normalized` and `chars` where a segment starts, with its line
+  let mut starts = vec![];
+  let mut counts = [0usize; 3];
+  let mut line = 0;
+  let mut i = usize::from(bom);
+  if bom {
+    starts.push((0, 1, 0));
+  }
+  while i < chars.len() {
+    let c = chars[i];
+    i += 1;
+    if c != '\r' || !has_cr {
+      if c == '\n' {
+        counts[2] += 1;
+        line += 1;
+      }
+      normalized.push(c);
+      continue;
+    }
+    normalized.push('\n');
+    line += 1;
+    if chars.get(i) == Some(&'\n') {
+      // `\r\n` becomes a single `\n`
+      counts[0] += 1;
+      i += 1;
+      starts.push((normalized.len(), i, line));
+    } else {
+      counts[1] += 1;
+    }
+  }
+  let segments = starts
+    .into_iter()
+    .map(|(generated, original, line)| {
+      let column = if line == 0 {
+        encoding.encode_offset(&chars, original)
+      } else {
+        0
+      };
+      Segment {
+        generated: encoding.encode_offset(&normalized, generated) as u32,
+        original: OriginalPos {
+          line: line as u32,
+          column: column as u32,
+          index: encoding.encode_offset(&chars, original) as u32,
+          byte_offset: Some(encoding::byte_offset(&chars, original) as u32),
+        },
+      }
+    })
+    .collect();
+  let [crlf, cr, lf] = counts;
+  let line_ending = if crlf >= cr.max(lf) && crlf > 0 {
+    Some("\r\n")
+  } else if cr > lf {
+    Some("\r")
+  } else {
+    None
+  };
+  Some(Normalized {
+    src: normalized.into_iter().collect(),
+    segments,
+    restore: Normalization { line_ending, bom },
+  })
+}
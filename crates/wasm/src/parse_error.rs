@@ -0,0 +1,80 @@
+//! Syntax errors of a parsed tree, see `SgRoot.parseErrors` and the `strict` option of `parse`.
+
+use crate::doc::WasmDoc;
+use crate::sg_node::{node_range, Range};
+
+use ast_grep_core::{Doc, Node};
+use serde::Serialize;
+
+/// An ERROR or MISSING node of the tree.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseError {
+  /// `ERROR`, or the kind of the token a MISSING node stands in for
+  kind: String,
+  missing: bool,
+  /// the skipped text of an ERROR node, empty for MISSING nodes
+  text: String,
+  range: Range,
+  /// kinds the parser could have accepted where the error starts, sorted
+  expected: Vec<String>,
+}
+
+impl ParseError {
+  /// A one line description for error messages, with one-based line and column.
+  pub fn describe(&self) -> String {
+    let start = &self.range.start;
+    let (line, column) = (start.line + 1, start.column + 1);
+    if self.missing {
+      format!("missing `{}` at line {line}, column {column}", self.kind)
+    } else {
+      format!("unexpected `{}` at line {line}, column {column}", self.text)
+    }
+  }
+}
+
+/// Every ERROR and MISSING node under `root`, in document order.
+pub fn collect(root: &Node<'_, WasmDoc>) -> Vec<ParseError> {
+  if !root.get_inner_node().0.has_error() {
+    return vec![];
+  }
+  root
+    .dfs()
+    .filter(|n| n.is_error() || n.is_missing())
+    .map(|n| ParseError {
+      kind: n.kind().to_string(),
+      missing: n.is_missing(),
+      text: n.text().to_string(),
+      range: node_range(&n),
+      expected: expected(&n),
+    })
+    .collect()
+}
+
+fn expected(node: &Node<'_, WasmDoc>) -> Vec<String> {
+  if node.is_missing() {
+    return vec![node.kind().to_string()];
+  }
+  // tree-sitter recommends the state of an ERROR node's first leaf for its lookahead
+  let mut leaf = node.get_inner_node().0;
+  while let Some(child) = leaf.first_child() {
+    leaf = child;
+  }
+  let lang = node.get_doc().get_lang().get_ts_language();
+  let Some(lookahead) = lang.lookahead_iterator(leaf.parse_state()) else {
+    return vec![];
+  };
+  let mut kinds: Vec<String> = js_sys::try_iter(&lookahead)
+    .ok()
+    .flatten()
+    .into_iter()
+    .flatten()
+    .filter_map(|kind| kind.ok()?.as_string())
+    // hidden rules and supertypes are not kinds of the tree
+    .filter(|kind| !kind.starts_with('_') && kind != "ERROR")
+    .collect();
+  lookahead.delete();
+  kinds.sort();
+  kinds.dedup();
+  kinds
+}
@@ -0,0 +1,90 @@
+use crate::PatternTree;
+
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeOp {
+  Added,
+  Removed,
+  Changed,
+}
+
+/// A subtree that differs between two pattern trees.
+#[derive(Serialize)]
+pub struct PatternChange<'a> {
+  pub op: ChangeOp,
+  /// the subtree in the first pattern, absent if added
+  pub before: Option<&'a PatternTree>,
+  /// the subtree in the second pattern, absent if removed
+  pub after: Option<&'a PatternTree>,
+}
+
+#[derive(Serialize)]
+pub struct PatternTreeDiff<'a> {
+  pub changes: Vec<PatternChange<'a>>,
+}
+
+pub fn diff_trees<'a>(before: &'a PatternTree, after: &'a PatternTree) -> PatternTreeDiff<'a> {
+  let mut changes = vec![];
+  diff_node(before, after, &mut changes);
+  PatternTreeDiff { changes }
+}
+
+/// Nodes of the same kind are aligned and then compared in depth.
+fn same_kind(a: &PatternTree, b: &PatternTree) -> bool {
+  a.kind == b.kind && a.pattern == b.pattern
+}
+
+fn diff_node<'a>(a: &'a PatternTree, b: &'a PatternTree, changes: &mut Vec<PatternChange<'a>>) {
+  if !same_kind(a, b) || a.text != b.text || a.is_named != b.is_named {
+    changes.push(PatternChange {
+      op: ChangeOp::Changed,
+      before: Some(a),
+      after: Some(b),
+    });
+    return;
+  }
+  diff_children(&a.children, &b.children, changes);
+}
+
+/// Align children by the longest common subsequence of their kinds.
+fn diff_children<'a>(
+  a: &'a [PatternTree],
+  b: &'a [PatternTree],
+  changes: &mut Vec<PatternChange<'a>>,
+) {
+  // lcs[i][j] is the LCS length of a[i..] and b[j..]
+  let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+  for i in (0..a.len()).rev() {
+    for j in (0..b.len()).rev() {
+      lcs[i][j] = if same_kind(&a[i], &b[j]) {
+        lcs[i + 1][j + 1] + 1
+      } else {
+        lcs[i + 1][j].max(lcs[i][j + 1])
+      };
+    }
+  }
+  let (mut i, mut j) = (0, 0);
+  while i < a.len() || j < b.len() {
+    if i < a.len() && j < b.len() && same_kind(&a[i], &b[j]) {
+      diff_node(&a[i], &b[j], changes);
+      i += 1;
+      j += 1;
+    } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+      changes.push(PatternChange {
+        op: ChangeOp::Removed,
+        before: Some(&a[i]),
+        after: None,
+      });
+      i += 1;
+    } else {
+      changes.push(PatternChange {
+        op: ChangeOp::Added,
+        before: None,
+        after: Some(&b[j]),
+      });
+      j += 1;
+    }
+  }
+}
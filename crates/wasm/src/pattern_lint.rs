@@ -0,0 +1,172 @@
+//! Lints of patterns for `lintPattern`, for mistakes that still compile but make a
+//! pattern match something else than its author meant, or nothing at all.
+//!
+//! Meta variables are found in the pattern's text like tokens: a run of one to three
+//! meta variable chars followed by a name, at the start of a word. So `$foo` is reported
+//! as a meta variable that cannot capture, while `$` alone, like jQuery's, is not.
+
+use crate::encoding::PositionEncoding;
+use crate::pattern_object::PatternObject;
+use crate::sg_node::{pos, Range};
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_core::matcher::PatternNode;
+use ast_grep_core::replacer::TemplateFix;
+use ast_grep_core::{Language, Pattern, PatternError};
+use serde::Serialize;
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+#[derive(Serialize)]
+pub struct PatternLint {
+  /// `multipleRoots`, `invalidMetaVariable`, `unusedMetaVariable` or `matchesEverything`
+  pub code: &'static str,
+  pub message: String,
+  /// the span in the pattern, absent for lints of the whole pattern
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub range: Option<Range>,
+}
+
+/// A meta variable of the pattern's text, at the char offsets `start..end`.
+pub(crate) struct Token {
+  start: usize,
+  end: usize,
+  /// the number of leading meta variable chars
+  pub prefix: usize,
+  pub name: String,
+}
+
+impl Token {
+  /// Whether ast-grep reads the token as a meta variable, see `extract_meta_var`.
+  pub fn is_valid(&self) -> bool {
+    let is_valid_char = |c: char| matches!(c, 'A'..='Z' | '_') || c.is_ascii_digit();
+    if self.prefix == 3 {
+      return self.name.chars().all(is_valid_char);
+    }
+    self
+      .name
+      .starts_with(|c: char| matches!(c, 'A'..='Z' | '_'))
+      && self.name.chars().all(is_valid_char)
+  }
+}
+
+pub(crate) fn tokens(src: &[char], meta_char: char) -> Vec<Token> {
+  let is_word = |c: char| c.is_alphanumeric() || c == '_';
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < src.len() {
+    if src[i] != meta_char || (i > 0 && is_word(src[i - 1])) {
+      i += 1;
+      continue;
+    }
+    let start = i;
+    while i < src.len() && src[i] == meta_char {
+      i += 1;
+    }
+    let prefix = i - start;
+    let name_start = i;
+    while i < src.len() && is_word(src[i]) {
+      i += 1;
+    }
+    let name: String = src[name_start..i].iter().collect();
+    if prefix <= 3 && (!name.is_empty() || prefix == 3) {
+      tokens.push(Token {
+        start,
+        end: i,
+        prefix,
+        name,
+      });
+    }
+  }
+  tokens
+}
+
+fn range(src: &[char], start: usize, end: usize, encoding: PositionEncoding) -> Range {
+  let at = |offset: usize| {
+    let line = src[..offset].iter().filter(|c| **c == '\n').count();
+    pos(src, line, offset, encoding)
+  };
+  Range {
+    start: at(start),
+    end: at(end),
+  }
+}
+
+/// The lints of `pattern`, and of its meta variables unused by `fix` if given,
+/// with ranges in `encoding`.
+/// Fails if the pattern does not compile for another reason than having several roots.
+pub fn lint(
+  lang: WasmLang,
+  pattern: &PatternObject,
+  fix: Option<&str>,
+  encoding: PositionEncoding,
+) -> Result<Vec<PatternLint>, JsError> {
+  let src: Vec<char> = pattern.context.chars().collect();
+  let meta_char = lang.meta_var_char();
+  let tokens = tokens(&src, meta_char);
+  let mut lints = vec![];
+  for token in tokens.iter().filter(|t| !t.is_valid()) {
+    let text: String = src[token.start..token.end].iter().collect();
+    let prefix = meta_char.to_string().repeat(token.prefix);
+    let upper = format!("{prefix}{}", token.name.to_uppercase());
+    let hint = if upper != text && !upper[prefix.len()..].starts_with(|c: char| c.is_ascii_digit())
+    {
+      format!(" Did you mean `{upper}`?")
+    } else {
+      String::new()
+    };
+    lints.push(PatternLint {
+      code: "invalidMetaVariable",
+      message: format!(
+        "`{text}` is not a meta variable, so it only matches the text `{text}`. Meta \
+         variables are named with uppercase letters, digits and `_`.{hint}"
+      ),
+      range: Some(range(&src, token.start, token.end, encoding)),
+    });
+  }
+  if pattern.selector.is_none() && pattern.parse_as.is_none() {
+    if let Err(PatternError::MultipleNode(_)) = Pattern::try_new(&pattern.context, lang) {
+      lints.push(PatternLint {
+        code: "multipleRoots",
+        message: "The pattern has several root nodes, so it cannot match a single node. \
+                  Match one of them, or write the code as a `context` and pick the node to \
+                  match with a `selector`."
+          .into(),
+        range: None,
+      });
+      return Ok(lints);
+    }
+  }
+  let compiled = pattern.compile(lang)?;
+  if pattern.selector.is_none() && matches!(compiled.node, PatternNode::MetaVar { .. }) {
+    lints.push(PatternLint {
+      code: "matchesEverything",
+      message: format!(
+        "The pattern is only the meta variable `{}`, so it matches every node. Add the \
+         code around it, or use a `kind` rule to match nodes of one kind.",
+        pattern.context.trim()
+      ),
+      range: None,
+    });
+  }
+  let Some(fix) = fix else {
+    return Ok(lints);
+  };
+  let template = TemplateFix::with_transform(fix, &lang, &[]);
+  let used = template.used_vars();
+  let defined = compiled.defined_vars();
+  let mut reported = HashSet::new();
+  for token in &tokens {
+    let name = token.name.as_str();
+    if !defined.contains(name) || used.contains(name) || !reported.insert(name) {
+      continue;
+    }
+    let text: String = src[token.start..token.end].iter().collect();
+    lints.push(PatternLint {
+      code: "unusedMetaVariable",
+      message: format!("`{text}` is not used in the fix, so the code it captures is dropped."),
+      range: Some(range(&src, token.start, token.end, encoding)),
+    });
+  }
+  Ok(lints)
+}
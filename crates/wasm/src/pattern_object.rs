@@ -0,0 +1,148 @@
+//! Pattern objects `{ context, selector?, strictness?, parseAs?, language? }`, accepted in place of
+//! pattern strings like the pattern object of YAML rules.
+//!
+//! A string is the same as an object with only `context`. `language` is optional where the
+//! call already has a language, and must name the same one.
+
+use crate::wasm_lang::{self, WasmLang};
+use crate::StrictnessOption;
+
+use ast_grep_core::Pattern;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternObject {
+  pub context: String,
+  /// Kind of the node to match in the `context`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub selector: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub strictness: Option<StrictnessOption>,
+  /// Kind of the node to parse the `context` inside of, see `Pattern::parse_as`.
+  #[serde(default, rename = "parseAs", skip_serializing_if = "Option::is_none")]
+  pub parse_as: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub language: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatternArg {
+  String(String),
+  Object(PatternObject),
+}
+
+impl PatternObject {
+  /// A pattern string or object.
+  pub fn from_js(value: JsValue) -> Result<Self, JsError> {
+    let arg: PatternArg = serde_wasm_bindgen::from_value(value).map_err(|_| {
+      JsError::new(
+        "Expected a pattern string or `{ context, selector?, strictness?, parseAs?, language? }`.",
+      )
+    })?;
+    Ok(match arg {
+      PatternArg::String(context) => Self {
+        context,
+        ..Self::default()
+      },
+      PatternArg::Object(object) => object,
+    })
+  }
+
+  /// Whether `value` is a pattern object rather than a rule config or compiled matcher.
+  pub fn is_object(value: &JsValue) -> bool {
+    value.is_object() && js_sys::Reflect::has(value, &"context".into()).unwrap_or(false)
+  }
+
+  /// The arguments of `f(lang, pattern, rest)` or `f({ language, ... }, rest)`: the language,
+  /// the pattern and, for the latter, the argument after the object.
+  pub fn split_args(
+    first: JsValue,
+    second: JsValue,
+  ) -> Result<(String, Self, Option<JsValue>), JsError> {
+    if let Some(lang) = first.as_string() {
+      let pattern = Self::from_js(second)?;
+      pattern.check_language(&lang)?;
+      return Ok((lang, pattern, None));
+    }
+    let pattern = Self::from_js(first)?;
+    let Some(lang) = pattern.language.clone() else {
+      return Err(JsError::new("The pattern object needs a `language`."));
+    };
+    Ok((lang, pattern, Some(second)))
+  }
+
+  /// Set `selector` and `strictness` given outside the object, which cannot set them too.
+  pub fn merge(
+    &mut self,
+    selector: Option<String>,
+    strictness: Option<StrictnessOption>,
+  ) -> Result<(), JsError> {
+    let conflict = |name: &str| {
+      JsError::new(&format!(
+        "`{name}` is set both in the pattern object and separately."
+      ))
+    };
+    if selector.is_some() {
+      if self.selector.is_some() {
+        return Err(conflict("selector"));
+      }
+      self.selector = selector;
+    }
+    if strictness.is_some() {
+      if self.strictness.is_some() {
+        return Err(conflict("strictness"));
+      }
+      self.strictness = strictness;
+    }
+    Ok(())
+  }
+
+  pub fn check_language(&self, lang: &str) -> Result<(), JsError> {
+    match &self.language {
+      Some(language) if parse_lang(language)? != parse_lang(lang)? => Err(JsError::new(&format!(
+        "The pattern is for `{language}` but is used with `{lang}`."
+      ))),
+      _ => Ok(()),
+    }
+  }
+
+  /// Whether the `selector`, `strictness` and `parseAs` are unset, so the pattern is its `context`.
+  pub fn is_plain(&self) -> bool {
+    self.selector.is_none() && self.strictness.is_none() && self.parse_as.is_none()
+  }
+
+  /// `context` parsed for `lang`, with the `selector` and `strictness`.
+  pub fn compile(&self, lang: WasmLang) -> Result<Pattern, JsError> {
+    if let Some(language) = &self.language {
+      if parse_lang(language)? != lang {
+        let msg = format!("The pattern is for `{language}` and cannot match another language.");
+        return Err(JsError::new(&msg));
+      }
+    }
+    let selector = self.selector.as_deref();
+    let pattern = match (&self.parse_as, selector) {
+      (Some(kind), selector) => Pattern::parse_as(&self.context, kind, selector, lang),
+      (None, Some(selector)) => Pattern::contextual(&self.context, selector, lang),
+      (None, None) => Pattern::try_new(&self.context, lang),
+    };
+    let pattern = pattern.map_err(|e| JsError::new(&e.to_string()))?;
+    let Some(strictness) = &self.strictness else {
+      return Ok(pattern);
+    };
+    let (level, skip_kinds) = strictness.to_strictness()?;
+    Ok(
+      pattern
+        .with_strictness(level)
+        .with_skipped_kinds(skip_kinds),
+    )
+  }
+}
+
+fn parse_lang(lang: &str) -> Result<WasmLang, JsError> {
+  lang
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))
+}
@@ -0,0 +1,70 @@
+//! Rule profiles for `profile`, to find the rules that make a scan slow.
+//!
+//! The source is scanned like by `scan`, embedded regions included, with every match
+//! attempt timed by `performance.now()`, since wasm has no clock of its own. Browsers
+//! coarsen the timer, so a single attempt may take 0ms, but the sum over a file
+//! still ranks the rules.
+
+use crate::doc::WasmDoc;
+use crate::injection;
+use crate::scan::applicable_rules;
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_config::{CombinedScan, RuleConfig, ScanProfile};
+use ast_grep_core::AstGrep;
+use serde::Serialize;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+  #[wasm_bindgen(js_namespace = performance, js_name = now)]
+  fn performance_now() -> f64;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+  visited: usize,
+  skipped: usize,
+  rules: Vec<RuleProfile>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleProfile {
+  rule_id: String,
+  time_ms: f64,
+  matches: usize,
+  candidates: usize,
+  prefiltered: usize,
+  prefilter_hit_rate: f64,
+}
+
+/// Scan `root` with `rules` and report what each rule cost, the slowest first.
+pub fn profile(root: &AstGrep<WasmDoc>, rules: &[RuleConfig<WasmLang>]) -> Result<Profile, String> {
+  let injected = injection::injected_roots(&root.root(), None).map_err(|e| e.to_string())?;
+  let now = || Duration::from_secs_f64(performance_now().max(0.0) / 1000.0);
+  let mut profile = ScanProfile::default();
+  for doc in std::iter::once(root).chain(&injected) {
+    let combined = CombinedScan::new(applicable_rules(rules, *doc.lang()));
+    combined.scan_profiled(doc, false, &mut profile, &now);
+  }
+  let rules = profile
+    .slowest()
+    .into_iter()
+    .map(|(id, rule)| RuleProfile {
+      rule_id: id.to_string(),
+      time_ms: rule.time.as_secs_f64() * 1000.0,
+      matches: rule.matches,
+      candidates: rule.candidates,
+      prefiltered: rule.prefiltered,
+      prefilter_hit_rate: rule.prefilter_hit_rate(),
+    })
+    .collect();
+  Ok(Profile {
+    visited: profile.visited,
+    skipped: profile.skipped,
+    rules,
+  })
+}
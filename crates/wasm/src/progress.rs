@@ -0,0 +1,85 @@
+//! Progress callbacks of `findAll` and `scan`.
+
+use crate::doc::WasmDoc;
+
+use ast_grep_core::Node;
+use js_sys::{Error, Function, JsString};
+use std::ops::Range;
+use wasm_bindgen::prelude::*;
+
+/// Number of visited nodes between two callback calls.
+const INTERVAL: usize = 1024;
+
+/// Calls `callback(processed, total)` periodically during a traversal and once at the end.
+/// Both are character offsets relative to the start of the traversed node or range.
+/// The callback runs synchronously, so it cannot yield to the event loop.
+pub struct Progress {
+  callback: Function,
+  start: usize,
+  total: usize,
+  visited: usize,
+  error: Option<JsValue>,
+}
+
+impl Progress {
+  pub fn new(callback: Function, range: Range<usize>) -> Self {
+    Self {
+      callback,
+      start: range.start,
+      total: range.len(),
+      visited: 0,
+      error: None,
+    }
+  }
+
+  /// Count a visited node and report its start every `INTERVAL` nodes.
+  pub fn visit(&mut self, node: &Node<'_, WasmDoc>) {
+    self.visited += 1;
+    if self.visited % INTERVAL == 0 {
+      // ancestors of a range start before it
+      let processed = node
+        .range()
+        .start
+        .saturating_sub(self.start)
+        .min(self.total);
+      self.report(processed);
+    }
+  }
+
+  fn report(&mut self, processed: usize) {
+    if self.error.is_some() {
+      return;
+    }
+    let processed = JsValue::from(processed as u32);
+    let total = JsValue::from(self.total as u32);
+    if let Err(e) = self.callback.call2(&JsValue::NULL, &processed, &total) {
+      self.error = Some(e);
+    }
+  }
+
+  /// Report completion. Returns the message of the first error thrown by the callback.
+  pub fn finish(mut self) -> Result<(), String> {
+    self.report(self.total);
+    match self.error {
+      None => Ok(()),
+      Some(err) => {
+        let message = match err.dyn_into::<Error>() {
+          Ok(error) => error.message(),
+          Err(value) => JsString::from(value),
+        };
+        Err(String::from(message))
+      }
+    }
+  }
+}
+
+/// Read an optional `onProgress` option, which must be a function if set.
+pub fn callback(value: &JsValue) -> Result<Option<Function>, String> {
+  if value.is_undefined() || value.is_null() {
+    return Ok(None);
+  }
+  match value.dyn_ref::<Function>() {
+    Some(f) => Ok(Some(f.clone())),
+    None => Err("`onProgress` must be a function".into()),
+  }
+}
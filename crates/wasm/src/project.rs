@@ -0,0 +1,316 @@
+//! Project configs (`sgconfig.yml`) loaded from a virtual file system for `loadProject`.
+
+use crate::constant::ValueConstraints;
+use crate::custom_matcher;
+use crate::doc::{error_message, WasmDoc};
+use crate::imports::{ModuleGraph, Requirements};
+use crate::scan::{self, FileScan, MixedFile, RuleMatches, ScanOptions};
+use crate::scope::{ScopeConstraints, ScopeFilter};
+use crate::wasm_lang::{self, WasmLang};
+
+use ast_grep_config::{
+  from_str, verify_signature, DeserializeEnv, GlobalRules, PublicKey, RuleConfig, RuleFileGlobs,
+};
+use ast_grep_core::{AstGrep, NodeMatch};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+/// The fields of `sgconfig.yml` used to find rules. Languages must be registered
+/// with `registerDynamicLanguage`, so `customLanguages` and the like are ignored.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectConfig {
+  #[serde(default)]
+  rule_dirs: Vec<String>,
+  util_dirs: Option<Vec<String>>,
+}
+
+/// Resolve `.` and `..` and drop leading and repeated slashes.
+pub fn normalize(path: &str) -> String {
+  let mut parts: Vec<&str> = vec![];
+  for part in path.split('/') {
+    match part {
+      "" | "." => (),
+      ".." => {
+        parts.pop();
+      }
+      part => parts.push(part),
+    }
+  }
+  parts.join("/")
+}
+
+pub fn join(dir: &str, path: &str) -> String {
+  normalize(&format!("{dir}/{path}"))
+}
+
+/// The options of `loadProject`.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadOptions {
+  /// minisign public keys, one of which must have signed every rule and util file
+  #[serde(default)]
+  pub trusted_keys: Vec<String>,
+}
+
+/// Fail unless `keys` is empty or `files` has a signature of the file at `path` by one of
+/// them, at `path` with `.minisig` appended like `minisign -Sm` writes it.
+fn check_signature(
+  files: &BTreeMap<String, String>,
+  path: &str,
+  content: &str,
+  keys: &[PublicKey],
+) -> Result<(), String> {
+  if keys.is_empty() {
+    return Ok(());
+  }
+  let Some(signature) = files.get(&format!("{path}.minisig")) else {
+    return Err(format!(
+      "`{path}` is not signed, `{path}.minisig` is not in the files."
+    ));
+  };
+  verify_signature(content.as_bytes(), signature, keys)
+    .map_err(|e| format!("`{path}` is not signed by a trusted key: {e}"))
+}
+
+fn is_yaml(path: &str) -> bool {
+  path.ends_with(".yml") || path.ends_with(".yaml")
+}
+
+/// YAML files under any of `dirs`, in path order.
+fn yaml_files<'a>(
+  files: &'a BTreeMap<String, String>,
+  dirs: &'a [String],
+) -> impl Iterator<Item = (&'a String, &'a String)> {
+  files.iter().filter(move |(path, _)| {
+    is_yaml(path)
+      && dirs
+        .iter()
+        .any(|dir| dir.is_empty() || path.starts_with(&format!("{dir}/")))
+  })
+}
+
+/// The rules of a project, compiled once and reused for every scan.
+#[wasm_bindgen]
+pub struct Project {
+  rules: Vec<RuleConfig<WasmLang>>,
+  /// the `imports` of each rule, see `imports`
+  imports: Vec<Requirements>,
+  /// the `scopes` of each rule, see `scope`
+  scopes: Vec<ScopeConstraints>,
+  /// the `values` of each rule, see `constant`
+  values: Vec<ValueConstraints>,
+  /// the `files` and `ignores` globs of each rule
+  globs: Vec<RuleFileGlobs>,
+  /// the directory of the config file, which the globs are relative to
+  base: String,
+}
+
+impl Project {
+  /// Compile the rules in `ruleDirs` with the utils in `utilDirs`, both relative to
+  /// the config file. Rules without `id` are named after their file like the CLI does.
+  /// Their `files` and `ignores` globs match paths relative to the config file too.
+  /// Unless `trusted_keys` is empty, the rule and util files must be signed by one of them.
+  pub fn load(
+    files: BTreeMap<String, String>,
+    config_path: &str,
+    trusted_keys: &[PublicKey],
+  ) -> Result<Self, String> {
+    let files: BTreeMap<_, _> = files
+      .into_iter()
+      .map(|(path, content)| (normalize(&path), content))
+      .collect();
+    let config_path = normalize(config_path);
+    let Some(config_yaml) = files.get(&config_path) else {
+      return Err(format!(
+        "Project config `{config_path}` is not in the files."
+      ));
+    };
+    let config: ProjectConfig = from_str(config_yaml)
+      .map_err(|e| format!("Cannot parse `{config_path}`: {}", error_message(&e)))?;
+    let base = config_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let resolve = |dirs: &[String]| -> Vec<String> { dirs.iter().map(|d| join(base, d)).collect() };
+
+    let util_dirs = resolve(config.util_dirs.as_deref().unwrap_or_default());
+    let mut utils = vec![];
+    for (path, yaml) in yaml_files(&files, &util_dirs) {
+      check_signature(&files, path, yaml, trusted_keys)?;
+      let util =
+        from_str(yaml).map_err(|e| format!("Cannot parse util `{path}`: {}", error_message(&e)))?;
+      utils.push(util);
+    }
+    let custom = custom_matcher::matchers();
+    let globals = if utils.is_empty() {
+      GlobalRules::default().with_custom_matchers(custom)
+    } else {
+      DeserializeEnv::<WasmLang>::parse_global_utils_with(utils, custom)
+        .map_err(|e| format!("Invalid utils: {}", error_message(&e)))?
+    };
+
+    let rule_dirs = resolve(&config.rule_dirs);
+    let mut rules = vec![];
+    let mut imports = vec![];
+    let mut scopes = vec![];
+    let mut values = vec![];
+    let mut globs = vec![];
+    for (path, yaml) in yaml_files(&files, &rule_dirs) {
+      check_signature(&files, path, yaml, trusted_keys)?;
+      let mut configs = scan::deserialize_rules(yaml, Some(&globals))
+        .map_err(|e| format!("Cannot parse rule `{path}`: {}", error_message(&e)))?;
+      let requirements = scan::rule_fields::<Requirements>(yaml, "imports")
+        .map_err(|e| format!("Invalid imports of rule `{path}`: {}", error_message(&e)))?;
+      imports.extend(requirements);
+      let constraints = scan::rule_fields::<ScopeConstraints>(yaml, "scopes")
+        .map_err(|e| format!("Invalid scopes of rule `{path}`: {}", error_message(&e)))?;
+      scopes.extend(constraints);
+      let constants = scan::rule_fields::<ValueConstraints>(yaml, "values")
+        .map_err(|e| format!("Invalid values of rule `{path}`: {}", error_message(&e)))?;
+      values.extend(constants);
+      let stem = path.rsplit('/').next().unwrap_or(path);
+      let stem = stem.rsplit_once('.').map_or(stem, |(stem, _)| stem);
+      let has_multiple = configs.len() > 1;
+      for (i, rule) in configs.iter_mut().enumerate() {
+        if rule.id.is_empty() {
+          rule.id = if has_multiple {
+            format!("{stem}-{i}")
+          } else {
+            stem.into()
+          };
+        }
+      }
+      for rule in &configs {
+        let rule_globs = RuleFileGlobs::try_new(rule)
+          .map_err(|e| format!("Invalid files or ignores of rule `{path}`: {e}"))?;
+        globs.push(rule_globs);
+      }
+      rules.extend(configs);
+    }
+    let mut seen = HashSet::new();
+    if let Some(rule) = rules.iter().find(|r| !seen.insert(&r.id)) {
+      return Err(format!("Duplicate rule id `{}`.", rule.id));
+    }
+    Ok(Self {
+      rules,
+      imports,
+      scopes,
+      values,
+      globs,
+      base: base.to_string(),
+    })
+  }
+
+  /// Scan `root` of the file `path` with the rules whose globs match it, or every rule
+  /// if `path` is empty, keeping the matches of rules with `imports` only
+  /// if `graph` resolves their meta variables to the required modules, and of rules
+  /// with `scopes` or `values` only if their variables are declared or evaluate as
+  /// required.
+  fn scan_root(
+    &self,
+    root: &AstGrep<WasmDoc>,
+    path: &str,
+    graph: &ModuleGraph,
+    options: &ScanOptions,
+  ) -> Result<Vec<RuleMatches>, String> {
+    let scopes = ScopeFilter::new(&root.root(), &self.scopes, &self.values);
+    let keep = |index: usize, nm: &NodeMatch<'_, WasmDoc>| {
+      let imported = match self.imports.get(index) {
+        Some(required) if !required.is_empty() => graph.satisfies(path, nm, required),
+        _ => true,
+      };
+      imported && scopes.keep(index, nm)
+    };
+    let path = normalize(path);
+    let relative = match path.strip_prefix(&self.base) {
+      Some(rest) if !self.base.is_empty() => rest.strip_prefix('/').unwrap_or(&path),
+      _ => &path,
+    };
+    let applies = |index: usize| path.is_empty() || self.globs[index].matches_path(relative);
+    scan::scan_root_with(root, &self.rules, options, &applies, &keep)
+  }
+
+  fn check_registered(&self) -> Result<(), JsError> {
+    // rules compiled for a replaced grammar would silently stop matching
+    for rule in &self.rules {
+      rule.language.check_registered()?;
+    }
+    Ok(())
+  }
+}
+
+#[wasm_bindgen]
+impl Project {
+  /// Ids of the project's rules, ordered by file path.
+  #[wasm_bindgen(js_name = ruleIds)]
+  pub fn rule_ids(&self) -> Vec<String> {
+    self.rules.iter().map(|r| r.id.clone()).collect()
+  }
+
+  /// Scan source code with the project's rules for `lang`.
+  /// `options` and the result are the same as for the top level `scan`.
+  pub fn scan(&self, lang: String, src: String, options: JsValue) -> Result<JsValue, JsError> {
+    let lang: WasmLang = lang
+      .parse()
+      .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+    self.check_registered()?;
+    let options: Option<ScanOptions> = serde_wasm_bindgen::from_value(options)?;
+    let options = options.unwrap_or_default();
+    let doc = WasmDoc::try_new(src, lang)?.with_encoding(options.encoding.position_encoding);
+    let root = AstGrep::doc(doc);
+    // imports of a single file can still be resolved to packages
+    let graph = ModuleGraph::new(&[(String::new(), root.root())]);
+    let result = self
+      .scan_root(&root, "", &graph, &options)
+      .map_err(|e| JsError::new(&e))?;
+    serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+  }
+
+  /// Scan files of a project together, an array of `{ name, lang, src }` where `name`
+  /// is the file's path. Rules only scan the files matched by their `files` and `ignores`
+  /// globs. Rules with `imports` see what each file imports across the
+  /// files, see `imports`. Returns `{ name, lang, rules }` per file like `scanMixed`,
+  /// or `{ name, lang, error }` if the file cannot be parsed.
+  #[wasm_bindgen(js_name = scanFiles)]
+  pub fn scan_files(&self, files: JsValue, options: JsValue) -> Result<JsValue, JsError> {
+    self.check_registered()?;
+    let files: Vec<MixedFile> = serde_wasm_bindgen::from_value(files)?;
+    let options: Option<ScanOptions> = serde_wasm_bindgen::from_value(options)?;
+    let options = options.unwrap_or_default();
+    let parsed: Vec<_> = files
+      .into_iter()
+      .map(|MixedFile { name, lang, src }| {
+        let root = lang
+          .parse()
+          .map_err(|e: wasm_lang::NotSupport| e.to_string())
+          .and_then(|lang| WasmDoc::try_new(src, lang).map_err(|e| error_message(&e)))
+          .map(|doc| AstGrep::doc(doc.with_encoding(options.encoding.position_encoding)));
+        (name, lang, root)
+      })
+      .collect();
+    let roots: Vec<_> = parsed
+      .iter()
+      .filter_map(|(name, _, root)| Some((name.clone(), root.as_ref().ok()?.root())))
+      .collect();
+    let graph = ModuleGraph::new(&roots);
+    let results: Vec<_> = parsed
+      .iter()
+      .map(|(name, lang, root)| {
+        let result = root
+          .as_ref()
+          .map_err(Clone::clone)
+          .and_then(|root| self.scan_root(root, name, &graph, &options));
+        let (rules, error) = match result {
+          Ok(rules) => (Some(rules), None),
+          Err(error) => (None, Some(error)),
+        };
+        FileScan {
+          name: name.clone(),
+          lang: lang.clone(),
+          rules,
+          error,
+        }
+      })
+      .collect();
+    serde_wasm_bindgen::to_value(&results).map_err(|e| JsError::new(&e.to_string()))
+  }
+}
@@ -0,0 +1,242 @@
+//! Raw tree-sitter queries used as rules, see `WasmConfig::query`.
+//!
+//! Queries match the SgNode methods only. `scan` and `fix` run YAML rules compiled to
+//! `RuleCore`s of ast-grep-config, whose only hook for matchers from outside the crate,
+//! `custom`, tells whether a node matches without meta variables. A query's captures
+//! could not be used by `message`, `fix` or `constraints` there.
+
+use crate::doc::{self, WasmDoc};
+use crate::ts_types::{self as ts, JsValueExt};
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_core::meta_var::MetaVarEnv;
+use ast_grep_core::{Node, NodeMatch};
+use wasm_bindgen::prelude::*;
+
+/// The capture added to the root of every pattern that can have one, see `anchor_roots`.
+const ROOT: &str = "__sg_root";
+
+/// A compiled tree-sitter query. A node matches if a query pattern matches
+/// starting at the node itself, and the pattern's captures become meta variables.
+pub struct QueryMatcher(ts::Query);
+
+/// The end of the string literal starting at `start`.
+fn string_end(src: &[char], start: usize) -> Option<usize> {
+  let mut i = start + 1;
+  while i < src.len() {
+    match src[i] {
+      '"' => return Some(i + 1),
+      '\\' => i += 2,
+      _ => i += 1,
+    }
+  }
+  None
+}
+
+/// The end of the parenthesized or bracketed form starting at `start`, skipping strings
+/// and comments.
+fn form_end(src: &[char], start: usize) -> Option<usize> {
+  let mut depth = 0;
+  let mut i = start;
+  while i < src.len() {
+    match src[i] {
+      '(' | '[' => depth += 1,
+      ')' | ']' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(i + 1);
+        }
+      }
+      '"' => {
+        i = string_end(src, i)?;
+        continue;
+      }
+      ';' => {
+        while i < src.len() && src[i] != '\n' {
+          i += 1;
+        }
+      }
+      _ => {}
+    }
+    i += 1;
+  }
+  None
+}
+
+/// The end of the comment starting at `start`.
+fn comment_end(src: &[char], start: usize) -> usize {
+  let mut i = start;
+  while i < src.len() && src[i] != '\n' {
+    i += 1;
+  }
+  i
+}
+
+/// The end of the pattern starting at `start`, `None` if no pattern starts there.
+fn pattern_end(src: &[char], start: usize) -> Option<usize> {
+  match src[start] {
+    '(' | '[' => form_end(src, start),
+    '"' => string_end(src, start),
+    '_' => Some(start + 1),
+    _ => None,
+  }
+}
+
+/// Whether the form from `start` to `end` groups patterns, e.g. siblings or a pattern and
+/// its predicates, instead of being a node.
+fn is_grouping(src: &[char], start: usize, end: usize) -> bool {
+  let inner = src[start + 1..end].iter().find(|c| !c.is_whitespace());
+  src[start] == '(' && matches!(inner, Some('(' | '[' | '"' | '.' | ';'))
+}
+
+/// Copy the pattern from `start` to `end` to `out` with the `ROOT` capture after it. A
+/// grouping is anchored at its first pattern, where its matches start.
+fn anchor_pattern(src: &[char], start: usize, end: usize, out: &mut String) -> Option<()> {
+  if !is_grouping(src, start, end) {
+    out.extend(&src[start..end]);
+    out.push_str(" @");
+    out.push_str(ROOT);
+    return Some(());
+  }
+  out.push('(');
+  let mut i = start + 1;
+  loop {
+    match src.get(i)? {
+      c if c.is_whitespace() || *c == '.' => {
+        out.push(*c);
+        i += 1;
+      }
+      ';' => {
+        let comment = comment_end(src, i);
+        out.extend(&src[i..comment]);
+        i = comment;
+      }
+      _ => break,
+    }
+  }
+  let first = i;
+  let first_end = pattern_end(src, first)?;
+  i = first_end;
+  // a quantifier goes before the captures
+  let quantified = src.get(i).is_some_and(|c| "*+?".contains(*c));
+  if quantified {
+    i += 1;
+  }
+  if quantified || !is_grouping(src, first, first_end) {
+    out.extend(&src[first..i]);
+    out.push_str(" @");
+    out.push_str(ROOT);
+  } else {
+    anchor_pattern(src, first, first_end, out)?;
+  }
+  out.extend(&src[i..end]);
+  Some(())
+}
+
+/// The query with the `ROOT` capture at the root of every top level pattern, so that a
+/// match can be checked to start at the node it was run on. `None` for a query this does
+/// not understand.
+fn anchor_roots(source: &str) -> Option<String> {
+  let src: Vec<char> = source.chars().collect();
+  let mut out = String::new();
+  let mut i = 0;
+  while i < src.len() {
+    let start = i;
+    match src[i] {
+      c if c.is_whitespace() => {
+        out.push(c);
+        i += 1;
+        continue;
+      }
+      ';' => {
+        i = comment_end(&src, i);
+        out.extend(&src[start..i]);
+        continue;
+      }
+      // captures of the previous pattern
+      '@' => {
+        i += 1;
+        while i < src.len() && (src[i].is_alphanumeric() || "_.-".contains(src[i])) {
+          i += 1;
+        }
+        out.extend(&src[start..i]);
+        continue;
+      }
+      _ => {}
+    }
+    let end = pattern_end(&src, start)?;
+    i = end;
+    if i < src.len() && "*+?".contains(src[i]) {
+      // the captures of a quantified pattern are the repeated nodes
+      i += 1;
+      out.extend(&src[start..i]);
+      out.push_str(" @");
+      out.push_str(ROOT);
+    } else {
+      anchor_pattern(&src, start, end, &mut out)?;
+    }
+  }
+  Some(out)
+}
+
+impl QueryMatcher {
+  pub fn try_new(lang: WasmLang, source: &str) -> Result<Self, JsError> {
+    let ts_lang = lang.get_ts_language();
+    // errors are reported for the query as it was written
+    let query = ts::Query::new(&ts_lang, source).lift_error()?;
+    query.delete();
+    let unanchored = || JsError::new("Cannot find where the patterns of the query start.");
+    let anchored = anchor_roots(source).ok_or_else(unanchored)?;
+    let query = ts::Query::new(&ts_lang, &anchored).map_err(|_| unanchored())?;
+    Ok(Self(query))
+  }
+
+  /// Captures become single meta variables named after the capture, without `@`.
+  /// A name captured more than once becomes a multi meta variable.
+  pub fn match_node<'r>(&self, node: Node<'r, WasmDoc>) -> Option<NodeMatch<'r, WasmDoc>> {
+    let inner = node.get_inner_node().0;
+    // patterns may only start at the node, but their captures can be anywhere in it
+    let matches = self.0.matches(&inner, &ts::QueryOptions::new(0));
+    let root = node.root();
+    let captures = matches.iter().find_map(|m| {
+      let m: &ts::QueryMatch = m.unchecked_ref();
+      let captures: Vec<_> = m
+        .captures()
+        .iter()
+        .map(|c| {
+          let c: &ts::QueryCapture = c.unchecked_ref();
+          (c.name(), root.adopt(doc::Node(c.node())))
+        })
+        .collect();
+      let at_node = captures
+        .iter()
+        .any(|(name, n)| name == ROOT && n.node_id() == node.node_id());
+      at_node.then_some(captures)
+    })?;
+    let mut captured: Vec<(String, Vec<Node<'r, WasmDoc>>)> = vec![];
+    for (name, captured_node) in captures {
+      if name == ROOT {
+        continue;
+      }
+      match captured.iter_mut().find(|(n, _)| *n == name) {
+        Some((_, nodes)) => nodes.push(captured_node),
+        None => captured.push((name, vec![captured_node])),
+      }
+    }
+    let mut env = MetaVarEnv::new();
+    for (name, mut nodes) in captured {
+      if nodes.len() == 1 {
+        env.insert(&name, nodes.remove(0));
+      } else {
+        env.insert_multi(&name, nodes);
+      }
+    }
+    Some(NodeMatch::new(node, env))
+  }
+}
+
+impl Drop for QueryMatcher {
+  fn drop(&mut self) {
+    self.0.delete();
+  }
+}
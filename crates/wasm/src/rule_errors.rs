@@ -0,0 +1,63 @@
+//! Rule errors located at the field causing them, for `parseRule` in rule editors.
+
+use crate::doc::error_message;
+use crate::encoding::PositionEncoding;
+use crate::global_utils;
+use crate::sg_node::{pos, Range};
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_config::{diagnose_rule, SerializableRuleConfig};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct RuleError {
+  pub message: String,
+  /// the YAML path of the field, empty for errors of the whole rule
+  pub path: String,
+  /// the span of the field's key and value
+  pub range: Range,
+}
+
+#[derive(Serialize)]
+pub struct ParsedRule {
+  /// the rule with defaults filled in, absent if the rule has errors
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub rule: Option<SerializableRuleConfig<WasmLang>>,
+  pub errors: Vec<RuleError>,
+}
+
+/// Parse and check the rule in `yaml`, collecting every error with its location
+/// in `encoding`.
+pub fn parse_rule(yaml: &str, encoding: PositionEncoding) -> ParsedRule {
+  let diagnostics = match diagnose_rule(yaml, &|lang| global_utils::globals(*lang)) {
+    Ok(config) => {
+      return ParsedRule {
+        rule: Some(config),
+        errors: vec![],
+      }
+    }
+    Err(diagnostics) => diagnostics,
+  };
+  let errors = diagnostics
+    .into_iter()
+    .map(|d| RuleError {
+      message: error_message(&d.error),
+      path: d.path,
+      range: range(yaml, d.span.start, d.span.end, encoding),
+    })
+    .collect();
+  ParsedRule { rule: None, errors }
+}
+
+fn range(yaml: &str, start: usize, end: usize, encoding: PositionEncoding) -> Range {
+  let chars: Vec<char> = yaml.chars().collect();
+  let pos_at = |byte: usize| {
+    let before = &yaml[..byte.min(yaml.len())];
+    let line = before.matches('\n').count();
+    pos(&chars, line, before.chars().count(), encoding)
+  };
+  Range {
+    start: pos_at(start),
+    end: pos_at(end),
+  }
+}
@@ -0,0 +1,596 @@
+// Generated by `cargo xtask schema` from schemas/rule.json. DO NOT EDIT.
+
+/**
+ * Used for global rules, rewriters, and pyo3/napi
+ */
+export interface RuleConfig {
+  /**
+   * A rule object to find matching AST nodes
+   */
+  rule: SerializableRule;
+  /**
+   * Additional meta variables pattern to filter matching
+   */
+  constraints?: Record<string, SerializableRule> | null;
+  /**
+   * Utility rules that can be used in `matches`
+   */
+  utils?: Record<string, SerializableRule> | null;
+  /**
+   * A dictionary for metavariable manipulation. Dict key is the new variable name.
+   * Dict value is a [transformation] that specifies how meta var is processed.
+   * See [transformation doc](https://ast-grep.github.io/reference/yaml/transformation.html).
+   */
+  transform?: Record<string, Transformation> | null;
+  /**
+   * A pattern string or a FixConfig object to auto fix the issue.
+   * It can reference metavariables appeared in rule.
+   * See details in fix [object reference](https://ast-grep.github.io/reference/yaml/fix.html#fixconfig).
+   */
+  fix?: SerializableFixer | null;
+  /**
+   * Unique, descriptive identifier, e.g., no-unused-variable
+   */
+  id?: string;
+  /**
+   * Specify the language to parse and the file extension to include in matching.
+   */
+  language: Language;
+  /**
+   * Rewrite rules for `rewrite` transformation
+   */
+  rewriters?: SerializableRewriter[] | null;
+  /**
+   * Main message highlighting why this rule fired. It should be single line and concise,
+   * but specific enough to be understood without additional context.
+   */
+  message?: string;
+  /**
+   * Additional notes to elaborate the message and provide potential fix to the issue.
+   * `notes` can contain markdown syntax, but it cannot reference meta-variables.
+   */
+  note?: string | null;
+  /**
+   * One of: hint, info, warning, or error
+   */
+  severity?: Severity;
+  /**
+   * Custom label dictionary to configure reporting. Key is the meta-variable name and
+   * value is the label message and label style.
+   */
+  labels?: Record<string, LabelConfig> | null;
+  /**
+   * Glob patterns to specify that the rule only applies to matching files
+   */
+  files?: RuleFileGlob[] | null;
+  /**
+   * Glob patterns that exclude rules from applying to files
+   */
+  ignores?: RuleFileGlob[] | null;
+  /**
+   * Documentation link to this rule
+   */
+  url?: string | null;
+  /**
+   * Extra information for the rule
+   */
+  metadata?: Metadata | null;
+}
+
+/**
+ * A rule object to find matching AST nodes. We have three categories of rules in ast-grep.
+ *
+ * * Atomic: the most basic rule to match AST. We have two variants: Pattern and Kind.
+ *
+ * * Relational: filter matched target according to their position relative to other nodes.
+ *
+ * * Composite: use logic operation all/any/not to compose the above rules to larger rules.
+ *
+ * Every rule has it's unique name so we can combine several rules in one object.
+ */
+export interface SerializableRule {
+  /**
+   * A pattern string or a pattern object.
+   */
+  pattern?: PatternStyle;
+  /**
+   * The kind name of the node to match. You can look up code's kind names in playground.
+   * Or an object with field `regex` to match every kind whose name matches the regex.
+   */
+  kind?: KindStyle;
+  /**
+   * A Rust regular expression to match the node's text. https://docs.rs/regex/latest/regex/#syntax
+   */
+  regex?: string;
+  /**
+   * `nth_child` accepts number, string or object.
+   * It specifies the position in nodes' sibling list.
+   */
+  nthChild?: SerializableNthChild;
+  /**
+   * `range` accepts a range object.
+   * the target node must exactly appear in the range.
+   */
+  range?: SerializableRange;
+  /**
+   * `inside` accepts a relational rule object.
+   * the target node must appear inside of another node matching the `inside` sub-rule.
+   */
+  inside?: Relation;
+  /**
+   * `has` accepts a relational rule object.
+   * the target node must has a descendant node matching the `has` sub-rule.
+   */
+  has?: Relation;
+  /**
+   * `precedes` accepts a relational rule object.
+   * the target node must appear before another node matching the `precedes` sub-rule.
+   */
+  precedes?: Relation;
+  /**
+   * `follows` accepts a relational rule object.
+   * the target node must appear after another node matching the `follows` sub-rule.
+   */
+  follows?: Relation;
+  /**
+   * A Rust regular expression to match comments attached right before the target node,
+   * e.g. `@deprecated` in a JSDoc block. Blank lines end the attached comments.
+   */
+  hasLeadingComment?: string;
+  /**
+   * A Rust regular expression to match comments inside the target node,
+   * e.g. `TODO` in a function body.
+   */
+  hasComment?: string;
+  /**
+   * A list of sub rules and matches a node if all of sub rules match.
+   * The meta variables of the matched node contain all variables from the sub-rules.
+   */
+  all?: SerializableRule[];
+  /**
+   * A list of sub rules and matches a node if any of sub rules match.
+   * The meta variables of the matched node only contain those of the matched sub-rule.
+   */
+  any?: SerializableRule[];
+  /**
+   * A single sub-rule and matches a node if the sub rule does not match.
+   */
+  not?: SerializableRule;
+  /**
+   * A utility rule id and matches a node if the utility rule matches.
+   */
+  matches?: string;
+  /**
+   * The name of a predicate registered by the host of the rules, e.g. a JS function,
+   * and matches a node if the predicate returns true for it.
+   */
+  custom?: string;
+}
+
+/**
+ * A String pattern will match one single AST node according to pattern syntax.
+ * Or an object with field `context`, `selector` and optionally `strictness` and `parseAs`.
+ */
+export type PatternStyle = string | {
+  /**
+   * The surrounding code that helps to resolve any ambiguity in the syntax.
+   */
+  context: string;
+  /**
+   * The sub-syntax node kind that is the actual matcher of the pattern.
+   */
+  selector?: string | null;
+  /**
+   * Strictness of the pattern. More strict pattern matches fewer nodes.
+   */
+  strictness?: StrictnessStyle | null;
+  /**
+   * The node kind to parse `context` inside of, like `switch_body` for a `case`.
+   */
+  parseAs?: string | null;
+};
+
+/**
+ * A built-in strictness, or one that also skips nodes of the listed kinds.
+ */
+export type StrictnessStyle = Strictness | {
+  /**
+   * The built-in strictness to extend, `smart` by default.
+   */
+  base?: Strictness | null;
+  /**
+   * Kind names of nodes to skip like comments, e.g. `decorator` or `;`.
+   */
+  skipKinds: string[];
+};
+
+export type Strictness = "cst" | "smart" | "ast" | "relaxed" | "signature" | "template";
+
+/**
+ * A String kind will match nodes of that kind, or kinds of a selector like `a > b`.
+ * Or an object with field `regex` matching the kind names, like `_statement$`.
+ */
+export type KindStyle = string | {
+  /**
+   * A Rust regular expression to match the node's kind name.
+   */
+  regex: string;
+};
+
+/**
+ * `nthChild` accepts either a number, a string or an object.
+ */
+export type SerializableNthChild = NthChildSimple | {
+  /**
+   * nth-child syntax
+   */
+  position: NthChildSimple;
+  /**
+   * select the nth node that matches the rule, like CSS's of syntax
+   */
+  ofRule?: SerializableRule | null;
+  /**
+   * matches from the end instead like CSS's nth-last-child
+   */
+  reverse?: boolean;
+};
+
+/**
+ * A string or number describing the indices of matching nodes in a list of siblings.
+ */
+export type NthChildSimple = number | string;
+
+/**
+ * Represents a position in source code using 0-based line and column numbers
+ */
+export interface SerializableRange {
+  /**
+   * start position in the source code
+   */
+  start: SerializablePosition;
+  /**
+   * end position in the source code
+   */
+  end: SerializablePosition;
+}
+
+/**
+ * Represents a zero-based character-wise position in a document
+ */
+export interface SerializablePosition {
+  /**
+   * 0-based line number in the source code
+   */
+  line: number;
+  /**
+   * 0-based column number in the source code
+   */
+  column?: number | null;
+}
+
+/**
+ * A relational rule object, which is a Rule object with two additional fields stopBy and field.
+ */
+export interface Relation {
+  /**
+   * A pattern string or a pattern object.
+   */
+  pattern?: PatternStyle;
+  /**
+   * The kind name of the node to match. You can look up code's kind names in playground.
+   * Or an object with field `regex` to match every kind whose name matches the regex.
+   */
+  kind?: KindStyle;
+  /**
+   * A Rust regular expression to match the node's text. https://docs.rs/regex/latest/regex/#syntax
+   */
+  regex?: string;
+  /**
+   * `nth_child` accepts number, string or object.
+   * It specifies the position in nodes' sibling list.
+   */
+  nthChild?: SerializableNthChild;
+  /**
+   * `range` accepts a range object.
+   * the target node must exactly appear in the range.
+   */
+  range?: SerializableRange;
+  /**
+   * `inside` accepts a relational rule object.
+   * the target node must appear inside of another node matching the `inside` sub-rule.
+   */
+  inside?: Relation;
+  /**
+   * `has` accepts a relational rule object.
+   * the target node must has a descendant node matching the `has` sub-rule.
+   */
+  has?: Relation;
+  /**
+   * `precedes` accepts a relational rule object.
+   * the target node must appear before another node matching the `precedes` sub-rule.
+   */
+  precedes?: Relation;
+  /**
+   * `follows` accepts a relational rule object.
+   * the target node must appear after another node matching the `follows` sub-rule.
+   */
+  follows?: Relation;
+  /**
+   * A Rust regular expression to match comments attached right before the target node,
+   * e.g. `@deprecated` in a JSDoc block. Blank lines end the attached comments.
+   */
+  hasLeadingComment?: string;
+  /**
+   * A Rust regular expression to match comments inside the target node,
+   * e.g. `TODO` in a function body.
+   */
+  hasComment?: string;
+  /**
+   * A list of sub rules and matches a node if all of sub rules match.
+   * The meta variables of the matched node contain all variables from the sub-rules.
+   */
+  all?: SerializableRule[];
+  /**
+   * A list of sub rules and matches a node if any of sub rules match.
+   * The meta variables of the matched node only contain those of the matched sub-rule.
+   */
+  any?: SerializableRule[];
+  /**
+   * A single sub-rule and matches a node if the sub rule does not match.
+   */
+  not?: SerializableRule;
+  /**
+   * A utility rule id and matches a node if the utility rule matches.
+   */
+  matches?: string;
+  /**
+   * The name of a predicate registered by the host of the rules, e.g. a JS function,
+   * and matches a node if the predicate returns true for it.
+   */
+  custom?: string;
+  stopBy?: SerializableStopBy;
+  field?: string | null;
+}
+
+/**
+ * Control how the relational rule search should stop
+ */
+export type SerializableStopBy = "neighbor" | "end" | SerializableRule;
+
+export type Transformation = string | Trans;
+
+/**
+ * Represents a transformation that can be applied to a matched AST node.
+ * Available transformations are `substring`, `replace`, `strip`, `convert`, `join`, `split`
+ * and `rewrite`.
+ */
+export type Trans = {
+  substring: Substring;
+} | {
+  replace: Replace;
+} | {
+  strip: Strip;
+} | {
+  convert: Convert;
+} | {
+  join: Join;
+} | {
+  split: Split;
+} | {
+  rewrite: Rewrite;
+};
+
+/**
+ * Extracts a substring from the meta variable's text content.
+ *
+ * Both `start_char` and `end_char` support negative indexing,
+ * which counts character from the end of an array, moving backwards.
+ */
+export interface Substring {
+  /**
+   * source meta variable to be transformed
+   */
+  source: string;
+  /**
+   * optional starting character index of the substring, defaults to 0.
+   */
+  startChar?: number | null;
+  /**
+   * optional ending character index of the substring, defaults to the end of the string.
+   */
+  endChar?: number | null;
+}
+
+/**
+ * Replaces a substring in the meta variable's text content with another string.
+ */
+export interface Replace {
+  /**
+   * source meta variable to be transformed
+   */
+  source: string;
+  /**
+   * a regex to find substring to be replaced
+   */
+  replace: string;
+  /**
+   * the replacement string
+   */
+  by: string;
+}
+
+/**
+ * Removes a prefix and a suffix from the meta variable's text content.
+ *
+ * The text is kept as is where it does not start with `prefix` or end with `suffix`,
+ * e.g. `getFoo` becomes `Foo` with the prefix `get`, but `setFoo` is unchanged.
+ */
+export interface Strip {
+  /**
+   * source meta variable to be transformed
+   */
+  source: string;
+  /**
+   * optional prefix to remove from the start
+   */
+  prefix?: string | null;
+  /**
+   * optional suffix to remove from the end
+   */
+  suffix?: string | null;
+}
+
+/**
+ * Converts the source meta variable's text content to a specified case format.
+ */
+export interface Convert {
+  /**
+   * source meta variable to be transformed
+   */
+  source: string;
+  /**
+   * the target case format to convert the text content to
+   */
+  toCase: StringCase;
+  /**
+   * optional separators to specify how to separate word
+   */
+  separatedBy?: Separator[] | null;
+}
+
+/**
+ * An enumeration representing different cases for strings.
+ */
+export type StringCase = "lowerCase" | "upperCase" | "capitalize" | "camelCase" | "snakeCase" | "kebabCase" | "pascalCase";
+
+/**
+ * Separator to split string. e.g. `user_accountName` -> `user`, `accountName`
+ * It will be rejoin according to `StringCase`.
+ */
+export type Separator = "caseChange" | "dash" | "dot" | "slash" | "space" | "underscore";
+
+/**
+ * Joins the texts of a multi meta variable's captured nodes with a separator.
+ *
+ * Only named nodes are joined, so punctuation like `,` between arguments is dropped.
+ * An empty multi-capture produces an empty string.
+ */
+export interface Join {
+  /**
+   * source meta variable to be transformed
+   */
+  source: string;
+  /**
+   * optional separator placed between captured nodes, defaults to an empty string.
+   */
+  joinBy?: string | null;
+}
+
+/**
+ * Splits the meta variable's text content by a regex and joins the trimmed parts again.
+ *
+ * Empty parts are discarded, which makes it handy to reshape lists of items.
+ */
+export interface Split {
+  /**
+   * source meta variable to be transformed
+   */
+  source: string;
+  /**
+   * a regex to find the separators to split by
+   */
+  by: string;
+  /**
+   * optional separator placed between the split parts, defaults to a newline.
+   */
+  joinBy?: string | null;
+}
+
+export interface Rewrite {
+  source: string;
+  rewriters: string[];
+  joinBy?: string | null;
+}
+
+/**
+ * A pattern string or fix object to auto fix the issue.
+ * It can reference metavariables appeared in rule.
+ */
+export type SerializableFixer = string | SerializableFixConfig | SerializableFixConfig[];
+
+export interface SerializableFixConfig {
+  template: string;
+  expandEnd?: Relation;
+  expandStart?: Relation;
+  title?: string | null;
+  indent?: FixIndent;
+}
+
+/**
+ * How the lines of a multi-line fix are indented.
+ */
+export type FixIndent = "match" | "tabs" | "none";
+
+/**
+ * Placeholder for language, used in JSON schema only.
+ */
+export type Language = string;
+
+/**
+ * Used for global rules, rewriters, and pyo3/napi
+ */
+export interface SerializableRewriter {
+  /**
+   * A rule object to find matching AST nodes
+   */
+  rule: SerializableRule;
+  /**
+   * Additional meta variables pattern to filter matching
+   */
+  constraints?: Record<string, SerializableRule> | null;
+  /**
+   * Utility rules that can be used in `matches`
+   */
+  utils?: Record<string, SerializableRule> | null;
+  /**
+   * A dictionary for metavariable manipulation. Dict key is the new variable name.
+   * Dict value is a [transformation] that specifies how meta var is processed.
+   * See [transformation doc](https://ast-grep.github.io/reference/yaml/transformation.html).
+   */
+  transform?: Record<string, Transformation> | null;
+  /**
+   * A pattern string or a FixConfig object to auto fix the issue.
+   * It can reference metavariables appeared in rule.
+   * See details in fix [object reference](https://ast-grep.github.io/reference/yaml/fix.html#fixconfig).
+   */
+  fix?: SerializableFixer | null;
+  /**
+   * Unique, descriptive identifier, e.g., no-unused-variable
+   */
+  id: string;
+}
+
+export type Severity = "hint" | "info" | "warning" | "error" | "off";
+
+export interface LabelConfig {
+  style: LabelStyle;
+  message?: string | null;
+}
+
+export type LabelStyle = "primary" | "secondary";
+
+export type RuleFileGlob = string | {
+  /**
+   * A glob pattern string
+   */
+  glob: string;
+  /**
+   * Whether the glob matching is case insensitive
+   */
+  caseInsensitive?: boolean;
+};
+
+/**
+ * Additional metadata for the rule, can be used to store extra information.
+ */
+export type Metadata = Record<string, unknown>;
@@ -0,0 +1,152 @@
+//! Scan results as a SARIF 2.1.0 log, the format of `ast-grep scan --format sarif`,
+//! so findings can be uploaded to code scanning dashboards.
+
+use crate::doc::{WasmDoc, Wrapper};
+use crate::scan::{parse_rules, scan_files, MixedFile};
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_config::{RuleConfig, Severity, SARIF_COLUMN_KIND, SARIF_SCHEMA, SARIF_VERSION};
+use ast_grep_core::{Doc, NodeMatch};
+use serde_sarif::sarif;
+use wasm_bindgen::prelude::*;
+
+fn severity_to_level(severity: &Severity) -> sarif::ResultLevel {
+  match severity {
+    Severity::Error => sarif::ResultLevel::Error,
+    Severity::Warning => sarif::ResultLevel::Warning,
+    Severity::Info => sarif::ResultLevel::Note,
+    Severity::Hint => sarif::ResultLevel::Note,
+    Severity::Off => sarif::ResultLevel::None,
+  }
+}
+
+fn source<'t>(nm: &NodeMatch<'t, WasmDoc>) -> &'t Wrapper {
+  nm.get_doc().get_source()
+}
+
+/// Region of the match with one-based lines, char columns and UTF-8 byte offsets.
+fn match_region(nm: &NodeMatch<'_, WasmDoc>) -> sarif::Region {
+  let src = source(nm);
+  let range = nm.range();
+  let start = src.byte_offset(range.start);
+  let end = src.byte_offset(range.end);
+  let start_pos = nm.start_pos();
+  let end_pos = nm.end_pos();
+  sarif::Region::builder()
+    .start_line((start_pos.line() + 1) as i64)
+    .start_column((start_pos.column(nm) + 1) as i64)
+    .end_line((end_pos.line() + 1) as i64)
+    .end_column((end_pos.column(nm) + 1) as i64)
+    .byte_offset(start as i64)
+    .byte_length((end - start) as i64)
+    .snippet(
+      sarif::ArtifactContent::builder()
+        .text(nm.text().to_string())
+        .build(),
+    )
+    .build()
+}
+
+fn artifact_location(file: &str) -> sarif::ArtifactLocation {
+  sarif::ArtifactLocation::builder()
+    .uri(file.to_string())
+    .build()
+}
+
+/// The rule's first fix as a SARIF fix. The deleted region only has lines and columns
+/// if it is the match itself, since `expandStart`/`expandEnd` can widen it.
+fn make_fix(
+  nm: &NodeMatch<'_, WasmDoc>,
+  file: &str,
+  rule: &RuleConfig<WasmLang>,
+) -> Option<sarif::Fix> {
+  let fixer = rule.matcher.fixer.first()?;
+  let edit = nm.make_edit(&rule.matcher, fixer);
+  let src = source(nm);
+  let deleted = edit.position..edit.position + edit.deleted_length;
+  let start = src.byte_offset(deleted.start);
+  let end = src.byte_offset(deleted.end);
+  let mut deleted_region = sarif::Region::builder()
+    .byte_offset(start as i64)
+    .byte_length((end - start) as i64)
+    .build();
+  if deleted == nm.range() {
+    let region = match_region(nm);
+    deleted_region.start_line = region.start_line;
+    deleted_region.start_column = region.start_column;
+    deleted_region.end_line = region.end_line;
+    deleted_region.end_column = region.end_column;
+  }
+  let replacement = sarif::Replacement {
+    deleted_region,
+    inserted_content: Some(
+      sarif::ArtifactContent::builder()
+        .text(edit.inserted_text.iter().collect::<String>())
+        .build(),
+    ),
+    properties: None,
+  };
+  let artifact_change = sarif::ArtifactChange {
+    artifact_location: artifact_location(file),
+    replacements: vec![replacement],
+    properties: None,
+  };
+  Some(sarif::Fix {
+    description: Some(
+      sarif::Message::builder()
+        .text("Apply suggested fix".to_string())
+        .build(),
+    ),
+    artifact_changes: vec![artifact_change],
+    properties: None,
+  })
+}
+
+fn make_result(
+  nm: NodeMatch<'_, WasmDoc>,
+  file: &str,
+  rule: &RuleConfig<WasmLang>,
+) -> sarif::Result {
+  let physical_location = sarif::PhysicalLocation::builder()
+    .artifact_location(artifact_location(file))
+    .region(match_region(&nm))
+    .build();
+  let location = sarif::Location::builder()
+    .physical_location(physical_location)
+    .build();
+  let message = rule.get_message(&nm);
+  let mut result = sarif::Result::builder()
+    .message(sarif::Message::builder().text(message).build())
+    .build();
+  result.rule_id = Some(rule.id.clone());
+  result.level = Some(severity_to_level(&rule.severity));
+  result.locations = Some(vec![location]);
+  result.fixes = make_fix(&nm, file, rule).map(|fix| vec![fix]);
+  result
+}
+
+/// Scan `files` with the YAML rules and report all matches as a SARIF 2.1.0 log
+/// with a single run. A file's `name` is used as its artifact URI.
+pub fn scan_to_sarif(config_yaml: &str, files: &[MixedFile]) -> Result<String, JsError> {
+  let rules = parse_rules(config_yaml)?;
+  let mut results = vec![];
+  scan_files(&rules, files, |file, rule, nm| {
+    results.push(make_result(nm, &file.name, rule));
+  })?;
+  let driver = sarif::ToolComponent::builder()
+    .name("ast-grep")
+    .version(env!("CARGO_PKG_VERSION"))
+    .build();
+  let tool = sarif::Tool::builder().driver(driver).build();
+  let mut run = sarif::Run::builder()
+    .tool(tool)
+    .column_kind(serde_json::json!(SARIF_COLUMN_KIND))
+    .build();
+  run.results = Some(results);
+  let log = sarif::Sarif::builder()
+    .schema(SARIF_SCHEMA)
+    .version(serde_json::json!(SARIF_VERSION))
+    .runs(vec![run])
+    .build();
+  serde_json::to_string_pretty(&log).map_err(|e| JsError::new(&e.to_string()))
+}
@@ -0,0 +1,961 @@
+use crate::budget::Budget;
+use crate::diff::{self, DiffOptions};
+use crate::doc::{error_chain, error_message, WasmDoc, Wrapper};
+use crate::encoding::{EncodingOption, PositionEncoding};
+use crate::global_utils;
+use crate::injection;
+use crate::progress::{self, Progress};
+use crate::project;
+use crate::sg_node::{node_range, Range, WasmEdit};
+use crate::source_map::Segment;
+use crate::wasm_lang::{self, WasmLang};
+
+use ast_grep_config::{
+  from_str, CombinedScan, GlobalRules, LabelStyle, Metadata, RuleConfig, RuleConfigError,
+  RuleCoreDump, RuleFileGlobs, SerializableRuleConfig, SerializableRuleCore, Severity,
+};
+use ast_grep_core::meta_var::MetaVariable;
+use ast_grep_core::source::{Content, Edit};
+use ast_grep_core::{AstGrep, Doc, Node, NodeMatch};
+use serde::de::DeserializeOwned;
+use serde::ser::Error as _;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_yaml::with::singleton_map_recursive;
+use serde_yaml::Value as YamlValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use wasm_bindgen::prelude::*;
+
+/// Options to control `scan` output.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanOptions {
+  /// Collect at most this many matches per rule. `total` still counts every match.
+  pub max_per_rule: Option<usize>,
+  /// Called as `onProgress(processed, total)` during the traversal, see `Progress`.
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub on_progress: JsValue,
+  /// Report `ast-grep-ignore` comments that suppress nothing with this severity,
+  /// as matches of the rule `unused-suppression`.
+  pub unused_suppression: Option<Severity>,
+  /// Only read by the top-level `scan`, see `source_map`.
+  pub with_source_map: Option<Vec<Segment>>,
+  /// Limits of each source's traversal, including its embedded regions, see `Budget`.
+  pub max_nodes: Option<usize>,
+  pub max_matches: Option<usize>,
+  pub timeout_ms: Option<f64>,
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub signal: JsValue,
+  /// Report the `textSpan` of matches and labels instead of copying their `text`.
+  pub lazy_text: bool,
+  /// Only read by `scanMixed`, see `scan_cache`.
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub cache: JsValue,
+  /// Only read where the source is parsed, roots keep their own.
+  #[serde(flatten)]
+  pub encoding: EncodingOption,
+}
+
+/// A single match reported by a rule.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+  /// absent with `lazyText`, which reports the `text_span` instead
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub text: Option<String>,
+  /// `[offset, length]` of the text in UTF-16 code units of the scanned source
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub text_span: Option<[u32; 2]>,
+  pub range: Range,
+  /// the rule's message with meta variables substituted
+  pub message: String,
+  /// replacement of the matched text if the rule has a `fix`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub fix: Option<String>,
+  /// the match and related ranges, from the rule's `labels` or the default labels
+  pub labels: Vec<FindingLabel>,
+  /// the captures of the match, absent if it captured nothing
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    serialize_with = "serialize_object"
+  )]
+  pub meta_variables: Option<FindingVariables>,
+}
+
+/// A node captured by a meta variable of a finding.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capture {
+  pub kind: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub text: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub text_span: Option<[u32; 2]>,
+  pub range: Range,
+}
+
+/// The meta variables of a finding, like the `metaVariables` of `matchToJSON`.
+/// A multi meta variable like `$$$ARGS` is the list of its nodes, not their joined text.
+#[derive(Serialize)]
+pub struct FindingVariables {
+  pub single: BTreeMap<String, Capture>,
+  pub multi: BTreeMap<String, Vec<Capture>>,
+  pub transformed: BTreeMap<String, String>,
+}
+
+/// A range of a finding to highlight, like a label of the CLI's diagnostics.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindingLabel {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub text: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub text_span: Option<[u32; 2]>,
+  pub range: Range,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub message: Option<String>,
+  pub style: LabelStyle,
+}
+
+impl Finding {
+  fn new(nm: &NodeMatch<'_, WasmDoc>, rule: &RuleConfig<WasmLang>, lazy_text: bool) -> Self {
+    let fix = rule.matcher.fixer.first().map(|fixer| {
+      let edit = nm.make_edit(&rule.matcher, fixer);
+      edit.inserted_text.iter().collect()
+    });
+    let src = nm.get_doc().get_source();
+    let text = |range: std::ops::Range<usize>| -> (Option<String>, Option<[u32; 2]>) {
+      if lazy_text {
+        (None, Some(src.utf16_span(range)))
+      } else {
+        (Some(src.get_range(range).iter().collect()), None)
+      }
+    };
+    let mut labels: Vec<_> = rule
+      .get_labels(nm)
+      .into_iter()
+      .map(|label| {
+        let (text, text_span) = text(label.range());
+        FindingLabel {
+          text,
+          text_span,
+          range: Range {
+            start: node_range(&label.start_node).start,
+            end: node_range(&label.end_node).end,
+          },
+          message: label.message.map(String::from),
+          style: label.style,
+        }
+      })
+      .collect();
+    // labels from the config come in no particular order
+    labels.sort_by_key(|l| (l.range.start.index, l.range.end.index));
+    let capture = |node: &Node<'_, WasmDoc>| {
+      let (text, text_span) = text(node.range());
+      Capture {
+        kind: node.kind().to_string(),
+        text,
+        text_span,
+        range: node_range(node),
+      }
+    };
+    let env = nm.get_env();
+    let mut vars = FindingVariables {
+      single: BTreeMap::new(),
+      multi: BTreeMap::new(),
+      transformed: BTreeMap::new(),
+    };
+    for var in env.get_matched_variables() {
+      match var {
+        MetaVariable::Capture(name, _) => {
+          if let Some(node) = env.get_match(&name) {
+            vars.single.insert(name, capture(node));
+          } else if let Some(chars) = env.get_transformed(&name) {
+            vars.transformed.insert(name, chars.iter().collect());
+          }
+        }
+        MetaVariable::MultiCapture(name) => {
+          let nodes = env.get_multiple_matches(&name);
+          vars.multi.insert(name, nodes.iter().map(capture).collect());
+        }
+        _ => continue,
+      }
+    }
+    let is_empty = vars.single.is_empty() && vars.multi.is_empty() && vars.transformed.is_empty();
+    let (text, text_span) = text(nm.range());
+    Self {
+      text,
+      text_span,
+      range: node_range(nm),
+      message: rule.get_message(nm),
+      fix,
+      labels,
+      meta_variables: (!is_empty).then_some(vars),
+    }
+  }
+}
+
+/// Matches reported by one rule.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleMatches {
+  pub rule_id: String,
+  pub severity: Severity,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub note: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub url: Option<String>,
+  /// the rule's `metadata` as is, e.g. CWE ids or OWASP tags
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    serialize_with = "serialize_object"
+  )]
+  pub metadata: Option<Metadata>,
+  /// Number of all matches, which can exceed `matches.len()` if `maxPerRule` is set.
+  pub total: usize,
+  pub matches: Vec<Finding>,
+}
+
+/// Serialize maps like `metadata` as plain objects, not the `Map` `serde_wasm_bindgen` makes of maps.
+fn serialize_object<T: Serialize, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+  let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+  let object = value
+    .serialize(&serializer)
+    .map_err(|e| S::Error::custom(e.to_string()))?;
+  serde_wasm_bindgen::preserve::serialize(&object, s)
+}
+
+/// Parse one or more YAML rules separated by `---`.
+/// A document can also be a list of rules, so they can share anchors.
+/// Anchors, aliases and merge keys (`<<:`) are resolved before parsing a rule.
+/// Rules can use the utils registered for their language by `registerUtils`.
+pub fn parse_rules(config_yaml: &str) -> Result<Vec<RuleConfig<WasmLang>>, JsError> {
+  deserialize_rules(config_yaml, None).map_err(|e| error_chain(&e))
+}
+
+/// Like `parse_rules`, with `globals` as the utility rules of a project
+/// instead of the registered utils.
+pub fn deserialize_rules(
+  config_yaml: &str,
+  globals: Option<&GlobalRules>,
+) -> Result<Vec<RuleConfig<WasmLang>>, RuleConfigError> {
+  let mut rules = vec![];
+  for document in serde_yaml::Deserializer::from_str(config_yaml) {
+    let mut value = YamlValue::deserialize(document)?;
+    value.apply_merge()?;
+    let values = match value {
+      YamlValue::Sequence(seq) => seq,
+      value => vec![value],
+    };
+    for value in values {
+      let config: SerializableRuleConfig<WasmLang> = singleton_map_recursive::deserialize(value)?;
+      let rule = match globals {
+        Some(globals) => RuleConfig::try_from(config, globals)?,
+        None => {
+          let globals = global_utils::globals(config.language);
+          RuleConfig::try_from(config, &globals)?
+        }
+      };
+      rules.push(rule);
+    }
+  }
+  Ok(rules)
+}
+
+/// The field `key` of each rule in `config_yaml`, in the order of `deserialize_rules`,
+/// for fields handled outside of `RuleConfig` like `imports` and `scopes`.
+pub fn rule_fields<T>(config_yaml: &str, key: &str) -> Result<Vec<T>, serde_yaml::Error>
+where
+  T: DeserializeOwned + Default,
+{
+  let mut fields = vec![];
+  for document in serde_yaml::Deserializer::from_str(config_yaml) {
+    let mut value = YamlValue::deserialize(document)?;
+    value.apply_merge()?;
+    let values = match value {
+      YamlValue::Sequence(seq) => seq,
+      value => vec![value],
+    };
+    for value in values {
+      let field = match value.get(key) {
+        Some(field) => T::deserialize(field.clone())?,
+        None => T::default(),
+      };
+      fields.push(field);
+    }
+  }
+  Ok(fields)
+}
+
+/// A source file passed to `scanMixed`.
+#[derive(Deserialize)]
+pub struct MixedFile {
+  pub name: String,
+  pub lang: String,
+  pub src: String,
+}
+
+/// The `scanMixed` result of one file.
+/// Exactly one of `rules` and `error` is set.
+#[derive(Serialize)]
+pub struct FileScan {
+  pub name: String,
+  pub lang: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub rules: Option<Vec<RuleMatches>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+/// Scan every file with the YAML rules of its language.
+/// Files of languages without rules are skipped. A file that fails, e.g. because its
+/// language is not registered or its rules are invalid, reports an error instead of
+/// aborting the others. Each language's rules are parsed once.
+pub fn scan_mixed(
+  files: Vec<MixedFile>,
+  rules_by_lang: &HashMap<String, String>,
+  options: &ScanOptions,
+) -> Vec<FileScan> {
+  let mut parsed = HashMap::new();
+  let mut results = vec![];
+  for MixedFile { name, lang, src } in files {
+    let Some(config_yaml) = rules_by_lang.get(&lang) else {
+      continue;
+    };
+    let rules = parsed
+      .entry(lang.clone())
+      .or_insert_with(|| parse_rules_with_globs(config_yaml));
+    let result = rules
+      .as_ref()
+      .map_err(Clone::clone)
+      .and_then(|(rules, globs)| {
+        let wasm_lang: WasmLang = lang.parse().map_err(|e| error_message(&e))?;
+        let doc = WasmDoc::try_new(src, wasm_lang).map_err(|e| error_message(&e))?;
+        let doc = doc.with_encoding(options.encoding.position_encoding);
+        let path = project::normalize(&name);
+        let applies = |i: usize| globs[i].matches_path(&path);
+        scan_root_with(&AstGrep::doc(doc), rules, options, &applies, &|_, _| true)
+      });
+    let (rules, error) = match result {
+      Ok(rules) => (Some(rules), None),
+      Err(error) => (None, Some(error)),
+    };
+    results.push(FileScan {
+      name,
+      lang,
+      rules,
+      error,
+    });
+  }
+  results
+}
+
+/// Options to control `fix` output.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixOptions {
+  /// Re-parse the fixed source and report parse errors the fixes introduced.
+  #[serde(default)]
+  pub validate: bool,
+  #[serde(default)]
+  pub edit_format: EditFormat,
+  /// Report the change as a unified diff in `diff`.
+  pub diff: Option<DiffOptions>,
+  /// Only read by the top-level `fix`, roots keep their own.
+  #[serde(flatten)]
+  pub encoding: EncodingOption,
+}
+
+/// Format of the edits in a `FixResult`.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditFormat {
+  /// `WasmEdit`s in offsets of the root's `positionEncoding`.
+  #[default]
+  Offset,
+  /// LSP `TextEdit`s with UTF-16 columns.
+  Lsp,
+}
+
+/// A position in LSP's format: zero-based line and UTF-16 column.
+#[derive(Serialize)]
+pub struct LspPosition {
+  pub line: u32,
+  pub character: u32,
+}
+
+#[derive(Serialize)]
+pub struct LspRange {
+  pub start: LspPosition,
+  pub end: LspPosition,
+}
+
+/// An LSP `TextEdit`, the element of a `TextDocumentEdit`'s `edits`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEdit {
+  pub range: LspRange,
+  pub new_text: String,
+}
+
+/// An applied edit of a `FixResult`, see `EditFormat`.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum FixEdit {
+  Offset(WasmEdit),
+  Lsp(TextEdit),
+}
+
+/// Convert `edits` of `src` to `format`, with offsets in `encoding`.
+pub fn format_edits(
+  src: &str,
+  edits: Vec<WasmEdit>,
+  format: EditFormat,
+  encoding: PositionEncoding,
+) -> Vec<FixEdit> {
+  match format {
+    EditFormat::Offset => {
+      let chars: Vec<char> = src.chars().collect();
+      let encode = |offset: u32| encoding.encode_offset(&chars, offset as usize) as u32;
+      edits
+        .into_iter()
+        .map(|edit| {
+          FixEdit::Offset(WasmEdit {
+            start_pos: encode(edit.start_pos),
+            end_pos: encode(edit.end_pos),
+            inserted_text: edit.inserted_text,
+          })
+        })
+        .collect()
+    }
+    EditFormat::Lsp => {
+      let chars: Vec<char> = src.chars().collect();
+      // edits are sorted, so positions are computed in one pass over the source
+      let mut cursor = (
+        0,
+        LspPosition {
+          line: 0,
+          character: 0,
+        },
+      );
+      let mut position = |offset: usize| {
+        let (start, pos) = &mut cursor;
+        for &c in &chars[*start..offset] {
+          if c == '\n' {
+            pos.line += 1;
+            pos.character = 0;
+          } else {
+            pos.character += c.len_utf16() as u32;
+          }
+        }
+        *start = offset;
+        LspPosition {
+          line: pos.line,
+          character: pos.character,
+        }
+      };
+      edits
+        .into_iter()
+        .map(|edit| {
+          let start = position(edit.start_pos as usize);
+          let end = position(edit.end_pos as usize);
+          FixEdit::Lsp(TextEdit {
+            range: LspRange { start, end },
+            new_text: edit.inserted_text,
+          })
+        })
+        .collect()
+    }
+  }
+}
+
+/// Source code after applying rule fixes.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixResult {
+  pub code: String,
+  /// Number of fixes applied. Fixes overlapping an earlier fix are skipped.
+  pub applied: usize,
+  /// The applied edits, in positions of the original source.
+  pub edits: Vec<FixEdit>,
+  /// Ranges in `code` of parse errors not present in the original source.
+  /// Only reported if `validate` is set.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub new_errors: Option<Vec<Range>>,
+  /// The unified diff from the original source to `code`, if `diff` is set.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diff: Option<String>,
+}
+
+/// Result of `SgRoot.replaceAll`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceAll {
+  pub code: String,
+  /// The applied edits in order of position, in positions of the original source.
+  pub applied: Vec<RuleEdit>,
+  /// The edits overlapping an applied edit, which are left out of `code`.
+  pub skipped: Vec<RuleEdit>,
+  /// Ranges in `code` of parse errors not present in the original source.
+  /// Only reported if `validate` is set.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub new_errors: Option<Vec<Range>>,
+  /// The unified diff from the original source to `code`, if `diff` is set.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diff: Option<String>,
+}
+
+/// The edit of a rule's fix, see `EditFormat`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleEdit {
+  pub rule_id: String,
+  #[serde(flatten)]
+  pub edit: FixEdit,
+}
+
+/// Result of `applyFixesIteratively`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IterativeFix {
+  pub fixed: String,
+  /// number of passes that changed the code
+  pub passes: usize,
+  /// number of fixes applied over all passes
+  pub applied: usize,
+  /// false if fixes still changed the code when `maxPasses` was reached,
+  /// or changed it back to the code of an earlier pass
+  pub converged: bool,
+}
+
+/// A template referencing meta variables the rule never captures.
+/// Such variables are silently substituted with empty text.
+#[derive(Serialize)]
+pub struct RuleWarning {
+  /// template field, either `fix` or `message`
+  pub field: &'static str,
+  /// names of the undefined meta variables, without `$`
+  pub variables: Vec<String>,
+  pub message: String,
+}
+
+/// Check a single YAML rule for templates using undefined meta variables.
+pub fn validate_rule(config_yaml: &str) -> Result<Vec<RuleWarning>, JsError> {
+  let config: SerializableRuleConfig<WasmLang> =
+    from_str(config_yaml).map_err(|e| error_chain(&e))?;
+  let undefined = config
+    .undefined_template_vars(&global_utils::globals(config.language))
+    .map_err(|e| error_chain(&e))?;
+  let warnings = undefined
+    .into_iter()
+    .map(|(field, variables)| {
+      let names: Vec<_> = variables.iter().map(|v| format!("`${v}`")).collect();
+      let message = format!("Undefined meta var {} used in `{field}`.", names.join(", "));
+      RuleWarning {
+        field,
+        variables,
+        message,
+      }
+    })
+    .collect();
+  Ok(warnings)
+}
+
+/// The normalized structure of a rule, see `dumpRule`.
+pub fn dump_rule(lang: WasmLang, rule_yaml: &str) -> Result<RuleCoreDump, JsError> {
+  let core: SerializableRuleCore = from_str(rule_yaml).map_err(|e| error_chain(&e))?;
+  // only dump rules which compile
+  core
+    .get_matcher(global_utils::env(lang))
+    .map_err(|e| error_chain(&e))?;
+  Ok(core.dump())
+}
+
+pub(crate) fn applicable_rules(
+  rules: &[RuleConfig<WasmLang>],
+  lang: WasmLang,
+) -> Vec<&RuleConfig<WasmLang>> {
+  rules
+    .iter()
+    .filter(|r| r.language == lang && !matches!(r.severity, Severity::Off))
+    .collect()
+}
+
+/// Scan every file with the rules of its language and call `f` on each match.
+/// Matches are visited by file, then by rule declaration, then by position.
+/// Fails on the first file whose language is not registered or cannot be parsed.
+pub(crate) fn scan_files<'r, F>(
+  rules: &'r [RuleConfig<WasmLang>],
+  files: &'r [MixedFile],
+  mut f: F,
+) -> Result<(), JsError>
+where
+  F: FnMut(&'r MixedFile, &'r RuleConfig<WasmLang>, NodeMatch<'_, WasmDoc>),
+{
+  for file in files {
+    let lang: WasmLang = file
+      .lang
+      .parse()
+      .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+    let root = AstGrep::doc(WasmDoc::try_new(file.src.clone(), lang)?);
+    let combined = CombinedScan::new(applicable_rules(rules, lang));
+    // resolve rules to `rules` since the scan result borrows from `combined`
+    let mut matches: Vec<_> = combined
+      .scan(&root, false)
+      .matches
+      .into_iter()
+      .filter_map(|(rule, nodes)| Some((rules.iter().position(|r| std::ptr::eq(r, rule))?, nodes)))
+      .collect();
+    matches.sort_by_key(|(index, _)| *index);
+    for (index, nodes) in matches {
+      for nm in nodes {
+        f(file, &rules[index], nm);
+      }
+    }
+  }
+  Ok(())
+}
+
+type RulesWithGlobs = (Vec<RuleConfig<WasmLang>>, Vec<RuleFileGlobs>);
+
+/// The rules of `config_yaml` with their compiled `files` and `ignores` globs.
+fn parse_rules_with_globs(config_yaml: &str) -> Result<RulesWithGlobs, String> {
+  let rules = deserialize_rules(config_yaml, None).map_err(|e| error_message(&e))?;
+  let globs = rules
+    .iter()
+    .map(|rule| {
+      RuleFileGlobs::try_new(rule)
+        .map_err(|e| format!("Invalid files or ignores of rule `{}`: {e}", rule.id))
+    })
+    .collect::<Result<_, _>>()?;
+  Ok((rules, globs))
+}
+
+/// Run all rules of `lang` over the root in one traversal, and the rules of embedded
+/// languages over their regions, see `registerInjections`.
+/// Results are grouped by rule and ordered as the rules are declared.
+/// Fails if `onProgress` is not a function or throws, or a limit of `Budget` is exceeded.
+pub fn scan_root(
+  root: &AstGrep<WasmDoc>,
+  rules: &[RuleConfig<WasmLang>],
+  options: &ScanOptions,
+) -> Result<Vec<RuleMatches>, String> {
+  scan_root_with(root, rules, options, &|_| true, &|_, _| true)
+}
+
+/// `scan_root` with only the rules whose index in `rules` passes `applies`, like the
+/// rules whose `files` and `ignores` globs match the scanned file, reporting only the
+/// matches that pass `keep`, which is called with the index of the match's rule,
+/// or `rules.len()` for unused suppressions.
+pub fn scan_root_with(
+  root: &AstGrep<WasmDoc>,
+  rules: &[RuleConfig<WasmLang>],
+  options: &ScanOptions,
+  applies: &dyn Fn(usize) -> bool,
+  keep: &dyn Fn(usize, &NodeMatch<'_, WasmDoc>) -> bool,
+) -> Result<Vec<RuleMatches>, String> {
+  let injected = injection::injected_roots(&root.root(), None).map_err(|e| e.to_string())?;
+  let unused = options
+    .unused_suppression
+    .clone()
+    .map(|severity| CombinedScan::unused_config(severity, *root.lang()));
+  let new_scan = |lang| {
+    let mut applicable = applicable_rules(rules, lang);
+    applicable.retain(|rule| {
+      let index = rules.iter().position(|r| std::ptr::eq(r, *rule));
+      index.is_some_and(applies)
+    });
+    let mut combined = CombinedScan::new(applicable);
+    if let Some(unused) = &unused {
+      combined.set_unused_suppression_rule(unused);
+    }
+    combined
+  };
+  let mut budget = Budget::new(
+    options.max_nodes,
+    options.max_matches,
+    options.timeout_ms,
+    &options.signal,
+  );
+  let mut progress =
+    progress::callback(&options.on_progress)?.map(|f| Progress::new(f, root.root().range()));
+  let combined = new_scan(*root.lang());
+  let scanned = combined.scan_with(root, false, |node, found| {
+    if let Some(progress) = &mut progress {
+      progress.visit(node);
+    }
+    budget.visit(found)
+  });
+  if let Some(progress) = progress {
+    progress.finish()?;
+  }
+  // resolve rules to `rules` since the scan results borrow from their `CombinedScan`,
+  // unused suppressions come after the declared rules
+  let resolve = |rule: &RuleConfig<WasmLang>| {
+    let is_unused = unused.as_ref().is_some_and(|u| std::ptr::eq(u, rule));
+    if is_unused {
+      return Some(rules.len());
+    }
+    rules.iter().position(|r| std::ptr::eq(r, rule))
+  };
+  let mut scanned: Vec<_> = scanned
+    .matches
+    .into_iter()
+    .filter_map(|(rule, nodes)| Some((resolve(rule)?, nodes)))
+    .collect();
+  // matches of embedded regions count towards `maxMatches` after those of the root
+  let mut found: usize = scanned.iter().map(|(_, nodes)| nodes.len()).sum();
+  for doc in &injected {
+    let combined = new_scan(*doc.lang());
+    let matches = combined
+      .scan_with(doc, false, |_, in_doc| budget.visit(found + in_doc))
+      .matches;
+    for (rule, nodes) in matches {
+      found += nodes.len();
+      let Some(index) = resolve(rule) else {
+        continue;
+      };
+      match scanned.iter_mut().find(|(i, _)| *i == index) {
+        Some((_, found)) => found.extend(nodes),
+        None => scanned.push((index, nodes)),
+      }
+    }
+  }
+  budget.finish()?;
+  let mut grouped: Vec<_> = scanned
+    .into_iter()
+    .filter_map(|(order, mut nodes)| {
+      nodes.retain(|nm| keep(order, nm));
+      if nodes.is_empty() {
+        return None;
+      }
+      let rule = rules
+        .get(order)
+        .or(unused.as_ref())
+        .expect("rule is resolved");
+      nodes.sort_by_key(|nm| nm.range().start);
+      let limit = options.max_per_rule.unwrap_or(usize::MAX);
+      let matches = nodes
+        .iter()
+        .take(limit)
+        .map(|nm| Finding::new(nm, rule, options.lazy_text))
+        .collect();
+      let result = RuleMatches {
+        rule_id: rule.id.clone(),
+        severity: rule.severity.clone(),
+        note: rule.note.clone(),
+        url: rule.url.clone(),
+        metadata: rule.metadata.clone(),
+        total: nodes.len(),
+        matches,
+      };
+      Some((order, result))
+    })
+    .collect();
+  grouped.sort_by_key(|(order, _)| *order);
+  Ok(grouped.into_iter().map(|(_, r)| r).collect())
+}
+
+/// Apply fixes and re-parse the result until no fix changes the code or `max_passes` is reached.
+/// Fixes can produce code that the rules match again, e.g. in nested matches,
+/// so one pass may not apply every fix. Fixes that keep the code as it is have converged,
+/// and fixes that change it back to the code of an earlier pass never converge.
+pub fn fix_iteratively(
+  root: &AstGrep<WasmDoc>,
+  rules: &[RuleConfig<WasmLang>],
+  max_passes: usize,
+) -> Result<IterativeFix, JsError> {
+  let lang = *root.lang();
+  let options = FixOptions::default();
+  let mut code = root.root().get_doc().get_source().text();
+  let mut reparsed = None;
+  let mut passes = 0;
+  let mut applied = 0;
+  let mut seen = HashSet::new();
+  let converged = loop {
+    let current = reparsed.as_ref().unwrap_or(root);
+    let result = fix_root(&code, current, rules, &options)?;
+    if result.applied == 0 || result.code == code {
+      break true;
+    }
+    seen.insert(hash_code(&code));
+    if passes == max_passes || seen.contains(&hash_code(&result.code)) {
+      break false;
+    }
+    passes += 1;
+    applied += result.applied;
+    code = result.code;
+    reparsed = Some(AstGrep::doc(WasmDoc::try_new(code.clone(), lang)?));
+  };
+  Ok(IterativeFix {
+    fixed: code,
+    passes,
+    applied,
+    converged,
+  })
+}
+
+fn hash_code(code: &str) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  code.hash(&mut hasher);
+  hasher.finish()
+}
+
+/// Apply the first fix of every matching rule of `lang` to `src`.
+pub fn fix_root(
+  src: &str,
+  root: &AstGrep<WasmDoc>,
+  rules: &[RuleConfig<WasmLang>],
+  options: &FixOptions,
+) -> Result<FixResult, JsError> {
+  let lang = *root.lang();
+  let combined = CombinedScan::new(applicable_rules(rules, lang));
+  let edits: Vec<_> = combined
+    .scan(root, true)
+    .diffs
+    .into_iter()
+    .filter_map(|(rule, nm)| Some(nm.make_edit(&rule.matcher, rule.matcher.fixer.first()?)))
+    .collect();
+  fix_result(src, root, edits, options)
+}
+
+/// Apply the first fix of every match of the rules of `lang` to `src`, keeping the fix
+/// of the outermost match where matches overlap.
+pub fn replace_all(
+  src: &str,
+  root: &AstGrep<WasmDoc>,
+  rules: &[RuleConfig<WasmLang>],
+  options: &FixOptions,
+) -> Result<ReplaceAll, JsError> {
+  let lang = *root.lang();
+  let combined = CombinedScan::new(applicable_rules(rules, lang));
+  let edits: Vec<_> = combined
+    .scan(root, true)
+    .diffs
+    .into_iter()
+    .filter_map(|(rule, nm)| {
+      let edit = nm.make_edit(&rule.matcher, rule.matcher.fixer.first()?);
+      Some((rule.id.as_str(), edit))
+    })
+    .collect();
+  let (code, applied, skipped) = apply_tagged_edits(src, edits);
+  let new_errors = if options.validate {
+    let fixed = AstGrep::doc(WasmDoc::try_new(code.clone(), lang)?);
+    Some(new_parse_errors(root, &fixed))
+  } else {
+    None
+  };
+  let encoding = root.root().get_doc().encoding();
+  let format = |edits: Vec<(&str, WasmEdit)>| -> Vec<RuleEdit> {
+    let (ids, edits): (Vec<_>, Vec<_>) = edits.into_iter().unzip();
+    ids
+      .into_iter()
+      .zip(format_edits(src, edits, options.edit_format, encoding))
+      .map(|(id, edit)| RuleEdit {
+        rule_id: id.to_string(),
+        edit,
+      })
+      .collect()
+  };
+  let diff = options.diff.as_ref().map(|o| diff::unified(src, &code, o));
+  Ok(ReplaceAll {
+    code,
+    applied: format(applied),
+    skipped: format(skipped),
+    new_errors,
+    diff,
+  })
+}
+
+/// Apply `edits` of `root` to its source `src` and report the result as `options` ask.
+pub fn fix_result(
+  src: &str,
+  root: &AstGrep<WasmDoc>,
+  edits: Vec<Edit<Wrapper>>,
+  options: &FixOptions,
+) -> Result<FixResult, JsError> {
+  let (code, edits) = apply_edits(src, edits);
+  let new_errors = if options.validate {
+    let fixed = AstGrep::doc(WasmDoc::try_new(code.clone(), *root.lang())?);
+    Some(new_parse_errors(root, &fixed))
+  } else {
+    None
+  };
+  let diff = options.diff.as_ref().map(|o| diff::unified(src, &code, o));
+  Ok(FixResult {
+    code,
+    applied: edits.len(),
+    edits: format_edits(
+      src,
+      edits,
+      options.edit_format,
+      root.root().get_doc().encoding(),
+    ),
+    new_errors,
+    diff,
+  })
+}
+
+/// Apply `edits` to `src` in order of position. Edits overlapping an earlier one are skipped.
+/// Returns the new source and the applied edits.
+pub fn apply_edits(src: &str, edits: Vec<Edit<Wrapper>>) -> (String, Vec<WasmEdit>) {
+  let edits = edits.into_iter().map(|edit| ((), edit)).collect();
+  let (code, applied, _) = apply_tagged_edits(src, edits);
+  (code, applied.into_iter().map(|(_, edit)| edit).collect())
+}
+
+type Tagged<T> = Vec<(T, WasmEdit)>;
+
+/// Like `apply_edits`, keeping the tag of each edit. Also returns the skipped edits.
+fn apply_tagged_edits<T>(
+  src: &str,
+  mut edits: Vec<(T, Edit<Wrapper>)>,
+) -> (String, Tagged<T>, Tagged<T>) {
+  edits.sort_by_key(|(_, e)| e.position);
+  let old_content = Wrapper::decode_str(src);
+  let mut new_content: Vec<char> = Vec::with_capacity(old_content.len());
+  let mut applied = vec![];
+  let mut skipped = vec![];
+  let mut start = 0;
+  for (tag, edit) in edits {
+    let wasm_edit = WasmEdit {
+      start_pos: edit.position as u32,
+      end_pos: (edit.position + edit.deleted_length) as u32,
+      inserted_text: edit.inserted_text.iter().collect(),
+    };
+    if start > edit.position {
+      skipped.push((tag, wasm_edit));
+      continue;
+    }
+    new_content.extend(&old_content[start..edit.position]);
+    new_content.extend(&edit.inserted_text);
+    start = edit.position + edit.deleted_length;
+    applied.push((tag, wasm_edit));
+  }
+  new_content.extend(&old_content[start..]);
+  (new_content.into_iter().collect(), applied, skipped)
+}
+
+/// Kind, text and range of every error or missing node.
+fn parse_errors(root: &AstGrep<WasmDoc>) -> Vec<(String, String, Range)> {
+  let root = root.root();
+  root
+    .dfs()
+    .filter(|n| n.is_error() || n.is_missing())
+    .map(|n| (n.kind().to_string(), n.text().to_string(), node_range(&n)))
+    .collect()
+}
+
+/// Errors in `fixed` without a same kind and text counterpart in `original`.
+/// Positions shift after fixing, so errors are compared by content.
+fn new_parse_errors(original: &AstGrep<WasmDoc>, fixed: &AstGrep<WasmDoc>) -> Vec<Range> {
+  let mut existing = parse_errors(original);
+  parse_errors(fixed)
+    .into_iter()
+    .filter(|(kind, text, _)| {
+      let found = existing.iter().position(|(k, t, _)| k == kind && t == text);
+      found.map(|i| existing.swap_remove(i)).is_none()
+    })
+    .map(|(_, _, range)| range)
+    .collect()
+}
@@ -0,0 +1,122 @@
+//! The pluggable cache of `scanMixed`, mapping a file and its rules to the file's result.
+//!
+//! The cache is any JS object with `get(key)` and `set(key, value)`, like a `Map`, or a
+//! wrapper of IndexedDB or the file system that loads entries before the scan. A key is the
+//! SHA-256 hex digest of the ast-grep version, the file's name, language and source, its
+//! language's rules and the options that change results, so an unchanged file is not
+//! parsed again. Only results without `error` are cached.
+
+use crate::scan::{self, MixedFile, ScanOptions};
+
+use js_sys::{Array, Function, Reflect};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+struct ScanCache {
+  cache: JsValue,
+  get: Function,
+  set: Function,
+}
+
+impl ScanCache {
+  fn new(cache: JsValue) -> Result<Self, JsError> {
+    let method = |name: &str| -> Result<Function, JsError> {
+      Reflect::get(&cache, &name.into())
+        .ok()
+        .and_then(|f| f.dyn_into::<Function>().ok())
+        .ok_or_else(|| JsError::new(&format!("`cache` must have a `{name}` method.")))
+    };
+    Ok(Self {
+      get: method("get")?,
+      set: method("set")?,
+      cache,
+    })
+  }
+
+  fn get(&self, key: &str) -> Result<Option<JsValue>, JsValue> {
+    let value = self.get.call1(&self.cache, &key.into())?;
+    Ok((!value.is_undefined()).then_some(value))
+  }
+
+  fn set(&self, key: &str, value: &JsValue) -> Result<(), JsValue> {
+    self.set.call2(&self.cache, &key.into(), value)?;
+    Ok(())
+  }
+}
+
+fn cache_key(file: &MixedFile, rules: &str, options: &ScanOptions) -> String {
+  let options = format!(
+    "{:?} {:?} {} {:?}",
+    options.max_per_rule,
+    options.unused_suppression,
+    options.lazy_text,
+    options.encoding.position_encoding
+  );
+  let parts = [
+    env!("CARGO_PKG_VERSION"),
+    &file.name,
+    &file.lang,
+    &file.src,
+    rules,
+    &options,
+  ];
+  let mut hasher = Sha256::new();
+  for part in parts {
+    hasher.update(part.as_bytes());
+    hasher.update([0]);
+  }
+  crate::hex(&hasher.finalize())
+}
+
+/// `scanMixed` reusing the results of `cache`, and storing the results of the files missed.
+pub fn scan_mixed(
+  files: Vec<MixedFile>,
+  rules_by_lang: &HashMap<String, String>,
+  options: &ScanOptions,
+  cache: JsValue,
+) -> Result<JsValue, JsError> {
+  let cache = ScanCache::new(cache)?;
+  // the cached result of each file, or the key to store its result with
+  let mut slots = vec![];
+  let mut missed = vec![];
+  for file in files {
+    // files of languages without rules are skipped
+    let Some(rules) = rules_by_lang.get(&file.lang) else {
+      continue;
+    };
+    let key = cache_key(&file, rules, options);
+    match cache.get(&key).map_err(js_error)? {
+      Some(value) => slots.push(Ok(value)),
+      None => {
+        slots.push(Err(key));
+        missed.push(file);
+      }
+    }
+  }
+  let mut scanned = scan::scan_mixed(missed, rules_by_lang, options).into_iter();
+  let results = Array::new();
+  for slot in slots {
+    let value = match slot {
+      Ok(value) => value,
+      Err(key) => {
+        let result = scanned.next().expect("every missed file should be scanned");
+        let value = serde_wasm_bindgen::to_value(&result)?;
+        if result.error.is_none() {
+          cache.set(&key, &value).map_err(js_error)?;
+        }
+        value
+      }
+    };
+    results.push(&value);
+  }
+  Ok(results.into())
+}
+
+fn js_error(e: JsValue) -> JsError {
+  let message = e
+    .dyn_ref::<js_sys::Error>()
+    .map(|e| String::from(e.message()))
+    .unwrap_or_else(|| format!("{e:?}"));
+  JsError::new(&format!("`cache` failed: {message}"))
+}
@@ -0,0 +1,341 @@
+//! Intra-file symbol resolution for `SgNode.goToDefinition` and `SgNode.findReferences`.
+//!
+//! A name is declared by a parameter, a function or class name, a declarator, an
+//! import, a loop variable or a caught error, and is visible in the scope it is
+//! declared in. `let`, `const` and Go's short variable declarations are scoped to
+//! the enclosing block, other declarations to the enclosing function, class or file,
+//! like `var` in JavaScript and assignments in Python. A name refers to the first
+//! declaration of the innermost enclosing scope declaring it. Grammars share no kind
+//! names, so like `taint` the declarations are recognized by kind and field names.
+
+use crate::constant::{evaluate, ValueConstraints};
+use crate::doc::WasmDoc;
+use crate::taint::{is_scope as is_function, is_variable};
+
+use ast_grep_core::{Node, NodeMatch};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+type SgNode<'r> = Node<'r, WasmDoc>;
+
+fn is_class(node: &SgNode<'_>) -> bool {
+  let kind = node.kind();
+  kind == "class" || kind.ends_with("class_declaration") || kind.ends_with("class_definition")
+}
+
+/// A scope a `let`/`const` declaration can be confined to, other than a function.
+fn is_block(node: &SgNode<'_>) -> bool {
+  if node.is_leaf() {
+    return false;
+  }
+  let kind = node.kind();
+  kind.contains("block") || kind.starts_with("for") || kind == "catch_clause"
+}
+
+fn is_scope(node: &SgNode<'_>) -> bool {
+  node.parent().is_none() || is_function(node) || is_class(node) || is_block(node)
+}
+
+/// The closest ancestor of `node` declarations of functions, classes and `var` go to.
+fn function_scope<'r>(node: &SgNode<'r>) -> Option<SgNode<'r>> {
+  node
+    .ancestors()
+    .find(|n| n.parent().is_none() || is_function(n) || is_class(n))
+}
+
+/// An identifier naming a variable, rather than a type, property or keyword argument.
+fn is_name(node: &SgNode<'_>) -> bool {
+  if !is_variable(node) {
+    return false;
+  }
+  let kind = node.kind();
+  if kind.contains("type")
+    || (!kind.starts_with("shorthand") && (kind.contains("property") || kind.contains("field")))
+  {
+    return false;
+  }
+  node.parent().map_or(true, |p| {
+    p.kind() != "keyword_argument" || !in_field(&p, node, "name")
+  })
+}
+
+fn in_field(parent: &SgNode<'_>, child: &SgNode<'_>, field: &str) -> bool {
+  parent
+    .field_children(field)
+    .any(|n| n.node_id() == child.node_id())
+}
+
+/// The scope `name` is declared in, if it is a name being declared.
+fn declared_scope<'r>(name: &SgNode<'r>) -> Option<SgNode<'r>> {
+  let mut child = name.clone();
+  for parent in name.ancestors() {
+    let kind = parent.kind();
+    let in_any = |fields: &[&str]| fields.iter().any(|f| in_field(&parent, &child, f));
+    // defaults, values and types in a declaration are references, but not the
+    // `value` of a destructuring pattern like `{ key: value }`
+    let fields = [
+      "value",
+      "right",
+      "type",
+      "body",
+      "module_name",
+      "source",
+      "path",
+    ];
+    if !kind.contains("pattern") && in_any(&fields) {
+      return None;
+    }
+    if parent.field("alias").is_some() && !in_field(&parent, &child, "alias") {
+      // `exec` in `import { exec as run }`
+      return None;
+    }
+    if kind == "dotted_name" && parent.children().next()?.node_id() != child.node_id() {
+      // `path` in `import os.path`
+      return None;
+    }
+    if is_function(&parent) || is_class(&parent) {
+      if in_field(&parent, &child, "name") {
+        return function_scope(&parent);
+      }
+      return in_any(&["parameters", "parameter", "receiver"]).then_some(parent);
+    }
+    if kind.ends_with("declarator") || kind == "assignment" || kind.ends_with("_spec") {
+      if !in_any(&["name", "left"]) {
+        return None;
+      }
+      let lexical = parent
+        .parent()
+        .is_some_and(|p| p.kind() == "lexical_declaration");
+      return if lexical {
+        parent.ancestors().find(is_scope)
+      } else {
+        function_scope(&parent)
+      };
+    }
+    if kind == "short_var_declaration" {
+      return in_field(&parent, &child, "left")
+        .then(|| parent.ancestors().find(is_scope))
+        .flatten();
+    }
+    if kind == "catch_clause" {
+      return in_field(&parent, &child, "parameter").then_some(parent);
+    }
+    if kind.starts_with("for") || kind == "range_clause" {
+      return in_field(&parent, &child, "left")
+        .then(|| function_scope(&parent))
+        .flatten();
+    }
+    if kind.contains("import") && (kind.ends_with("statement") || kind.ends_with("declaration")) {
+      return parent.ancestors().last();
+    }
+    // `obj.prop = 1` or `f(x)` declares nothing
+    if is_scope(&parent)
+      || [
+        "statement",
+        "member",
+        "attribute",
+        "subscript",
+        "call",
+        "selector",
+      ]
+      .iter()
+      .any(|k| kind.contains(k))
+    {
+      return None;
+    }
+    child = parent;
+  }
+  None
+}
+
+/// Whether `name` is the target of an assignment or an update like `i++`.
+fn is_assigned(name: &SgNode<'_>) -> bool {
+  let mut child = name.clone();
+  for parent in name.ancestors() {
+    let kind = parent.kind();
+    if kind.contains("assignment") {
+      return in_field(&parent, &child, "left");
+    }
+    if kind.contains("update") || kind == "inc_statement" || kind == "dec_statement" {
+      return true;
+    }
+    // `[a, b] = ...` or Go's `a, b = ...`
+    if !kind.contains("pattern") && !kind.contains("list") && !kind.contains("parenthesized") {
+      return false;
+    }
+    child = parent;
+  }
+  false
+}
+
+/// Where the variable of a meta variable is declared, see `ScopeConstraint`.
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Declared {
+  /// in the function enclosing the match, parameters included
+  Local,
+  /// as a parameter of the function enclosing the match
+  Parameter,
+  /// at the top level of the file
+  File,
+  /// nowhere in the file, like a global of the runtime
+  Undeclared,
+}
+
+/// An entry of a rule's `scopes`, checking the variable a meta variable refers to.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScopeConstraint {
+  declared: Option<Declared>,
+  /// whether the variable is assigned again after its first declaration
+  reassigned: Option<bool>,
+}
+
+/// The `scopes` of a rule, keyed by meta variable names without `$`.
+pub type ScopeConstraints = HashMap<String, ScopeConstraint>;
+
+/// The declarations of a file, keyed by the scope and the declared name.
+pub struct Scopes<'r> {
+  root: SgNode<'r>,
+  declarations: HashMap<(usize, String), SgNode<'r>>,
+}
+
+impl<'r> Scopes<'r> {
+  pub fn new(root: &SgNode<'r>) -> Self {
+    let mut declarations = HashMap::new();
+    for node in root.dfs().filter(is_name) {
+      if let Some(scope) = declared_scope(&node) {
+        let key = (scope.node_id(), node.text().to_string());
+        declarations.entry(key).or_insert(node);
+      }
+    }
+    Self {
+      root: root.clone(),
+      declarations,
+    }
+  }
+
+  /// The declaration `name` refers to, `None` if it is not a name or a global.
+  pub fn definition(&self, name: &SgNode<'_>) -> Option<SgNode<'r>> {
+    if !is_name(name) {
+      return None;
+    }
+    let text = name.text().to_string();
+    name
+      .ancestors()
+      .filter(is_scope)
+      .find_map(|scope| self.declarations.get(&(scope.node_id(), text.clone())))
+      .cloned()
+  }
+
+  /// The names of the file that refer to the same declaration as `name`, or to no
+  /// declaration like `name`, in document order.
+  pub fn references(&self, name: &SgNode<'_>) -> Vec<SgNode<'r>> {
+    if !is_name(name) {
+      return vec![];
+    }
+    let text = name.text();
+    let definition = self.definition(name).map(|d| d.node_id());
+    self
+      .root
+      .dfs()
+      .filter(|n| is_name(n) && n.text() == text)
+      .filter(|n| self.definition(n).map(|d| d.node_id()) == definition)
+      .collect()
+  }
+
+  /// Whether the variables the meta variables of `nm` refer to satisfy `constraints`.
+  /// A meta variable that captured no variable fails every constraint.
+  pub fn satisfies(&self, nm: &NodeMatch<'_, WasmDoc>, constraints: &ScopeConstraints) -> bool {
+    let env = nm.get_env();
+    constraints.iter().all(|(var, constraint)| {
+      let Some(name) = env.get_match(var).filter(|n| is_name(n)) else {
+        return false;
+      };
+      let definition = self.definition(name);
+      let declared = constraint.declared.map_or(true, |declared| {
+        is_declared(nm, definition.as_ref(), declared)
+      });
+      declared
+        && constraint.reassigned.map_or(true, |reassigned| {
+          self.is_reassigned(name, definition.as_ref()) == reassigned
+        })
+    })
+  }
+
+  /// Whether the variable of `name` is declared or assigned again after `definition`.
+  pub fn is_reassigned(&self, name: &SgNode<'_>, definition: Option<&SgNode<'r>>) -> bool {
+    let first = definition.map(|d| d.node_id());
+    self
+      .references(name)
+      .iter()
+      .filter(|r| Some(r.node_id()) != first)
+      .any(|r| declared_scope(r).is_some() || is_assigned(r))
+  }
+}
+
+/// Keeps the matches of rules whose `scopes` and `values` they satisfy, see
+/// `scan::scan_root_with`.
+pub struct ScopeFilter<'r> {
+  /// built only if a rule has `scopes` or `values`
+  scopes: Option<Scopes<'r>>,
+  constraints: &'r [ScopeConstraints],
+  values: &'r [ValueConstraints],
+}
+
+impl<'r> ScopeFilter<'r> {
+  /// A filter for the file of `root`, given the `scopes` and `values` of each rule.
+  pub fn new(
+    root: &SgNode<'r>,
+    constraints: &'r [ScopeConstraints],
+    values: &'r [ValueConstraints],
+  ) -> Self {
+    let needed = constraints.iter().any(|c| !c.is_empty()) || values.iter().any(|v| !v.is_empty());
+    Self {
+      scopes: needed.then(|| Scopes::new(root)),
+      constraints,
+      values,
+    }
+  }
+
+  /// Whether `nm` of the rule at `index` satisfies the rule's `scopes` and `values`.
+  pub fn keep(&self, index: usize, nm: &NodeMatch<'_, WasmDoc>) -> bool {
+    let Some(scopes) = &self.scopes else {
+      return true;
+    };
+    let env = nm.get_env();
+    let values = self.values.get(index).map_or(true, |values| {
+      values.iter().all(|(var, expected)| {
+        env
+          .get_match(var)
+          .is_some_and(|node| evaluate(scopes, node).as_ref() == Some(expected))
+      })
+    });
+    values
+      && self
+        .constraints
+        .get(index)
+        .map_or(true, |constraints| scopes.satisfies(nm, constraints))
+  }
+}
+
+/// Whether `definition` is declared where `declared` requires relative to `node`.
+fn is_declared(node: &SgNode<'_>, definition: Option<&SgNode<'_>>, declared: Declared) -> bool {
+  let Some(definition) = definition else {
+    return declared == Declared::Undeclared;
+  };
+  let range = definition.range();
+  let contains = |n: &SgNode<'_>| n.range().start <= range.start && range.end <= n.range().end;
+  let function = node.ancestors().find(is_function);
+  match declared {
+    Declared::Local => function.is_some_and(|f| contains(&f)),
+    Declared::Parameter => function.is_some_and(|f| {
+      ["parameters", "parameter"]
+        .iter()
+        .filter_map(|field| f.field(field))
+        .any(|params| contains(&params))
+    }),
+    Declared::File => declared_scope(definition).is_some_and(|s| s.parent().is_none()),
+    Declared::Undeclared => false,
+  }
+}
@@ -0,0 +1,81 @@
+//! The `range` of `find`, `findAll` and `findInRange`, e.g. an editor's viewport or selection.
+//!
+//! A search in a range only visits the nodes overlapping it and skips every other subtree,
+//! so its cost is about the size of the range rather than of the tree.
+
+use crate::doc::WasmDoc;
+use crate::encoding::PositionEncoding;
+
+use ast_grep_core::Node;
+use serde::Deserialize;
+use std::ops::Range;
+
+/// An offset in the position encoding of the root, or a zero-based line and column.
+/// Positions returned by `range()` can be passed back as they are.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Position {
+  Index(usize),
+  LineColumn { line: usize, column: usize },
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct SearchRange {
+  pub start: Position,
+  pub end: Position,
+}
+
+impl Position {
+  /// The char offset in `src`. Columns past the end of their line are the line's end,
+  /// lines past the last one are the end of `src`.
+  fn resolve(self, src: &[char], encoding: PositionEncoding) -> usize {
+    let (line, column) = match self {
+      Self::Index(index) => return encoding.decode_offset(src, index).min(src.len()),
+      Self::LineColumn { line, column } => (line, column),
+    };
+    encoding
+      .decode_position(src, line, column)
+      .unwrap_or(src.len())
+  }
+}
+
+impl SearchRange {
+  /// The char range in `src`, whose positions are in `encoding`.
+  /// Fails if it ends before it starts.
+  pub fn resolve(&self, src: &[char], encoding: PositionEncoding) -> Result<Range<usize>, String> {
+    let start = self.start.resolve(src, encoding);
+    let end = self.end.resolve(src, encoding);
+    if start > end {
+      return Err("The search range ends before it starts.".into());
+    }
+    Ok(start..end)
+  }
+}
+
+/// Whether a node spanning `node` overlaps `range`. An empty range overlaps the nodes
+/// containing its position, including those starting or ending there.
+fn overlaps(node: &Range<usize>, range: &Range<usize>) -> bool {
+  if range.is_empty() {
+    node.start <= range.start && range.start <= node.end
+  } else {
+    node.start < range.end && range.start < node.end
+  }
+}
+
+/// The nodes of `root`'s subtree overlapping `range`, in pre-order like `dfs`.
+pub fn overlapping<'t>(
+  root: Node<'t, WasmDoc>,
+  range: Range<usize>,
+) -> impl Iterator<Item = Node<'t, WasmDoc>> {
+  let mut stack = vec![];
+  if overlaps(&root.range(), &range) {
+    stack.push(root);
+  }
+  std::iter::from_fn(move || {
+    let node = stack.pop()?;
+    let start = stack.len();
+    stack.extend(node.children().filter(|c| overlaps(&c.range(), &range)));
+    stack[start..].reverse();
+    Some(node)
+  })
+}
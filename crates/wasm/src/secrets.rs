@@ -0,0 +1,272 @@
+//! Secrets bundled with `scanSecurity`, detected in string literals and config values.
+//!
+//! Unlike the YAML rulesets, a detector is a regex on the text of a value plus a minimum
+//! Shannon entropy, which tells keys and tokens from placeholders like `xxxx`. Only the
+//! outermost string and scalar nodes of the tree are checked, so comments never are.
+
+use crate::doc::WasmDoc;
+use crate::scan::{Finding, FindingLabel};
+use crate::sg_node::node_range;
+
+use ast_grep_config::{LabelStyle, Severity};
+use ast_grep_core::Node;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+
+/// Paths of test code, where sample tokens are expected.
+const TESTS: &[&str] = &[
+  "**/test/**",
+  "**/tests/**",
+  "**/__tests__/**",
+  "**/*.test.*",
+  "**/*.spec.*",
+  "**/*_test.*",
+  "**/test_*",
+];
+/// Paths of test data, where sample keys and certificates are expected.
+const FIXTURES: &[&str] = &["**/fixtures/**", "**/__fixtures__/**", "**/testdata/**"];
+
+pub struct Detector {
+  pub id: &'static str,
+  pub cwe: &'static str,
+  pub severity: Severity,
+  message: &'static str,
+  pub note: &'static str,
+  /// matched against the value without its quotes
+  pattern: &'static str,
+  /// bits per char the match must have at least
+  min_entropy: f64,
+  /// only report values of keys, variables or arguments named like credentials
+  needs_credential_name: bool,
+  /// paths the detector skips, see `SecurityOptions.path`
+  ignores: &'static [&'static [&'static str]],
+}
+
+pub const DETECTORS: &[Detector] = &[
+  Detector {
+    id: "aws-access-key",
+    cwe: "CWE-798",
+    severity: Severity::Error,
+    message: "AWS access key id in a string.",
+    note: "Revoke the key and load it from the environment or a secret store.",
+    pattern: r"\b(AKIA|ASIA)[0-9A-Z]{16}\b",
+    min_entropy: 3.0,
+    needs_credential_name: false,
+    ignores: &[],
+  },
+  Detector {
+    id: "github-token",
+    cwe: "CWE-798",
+    severity: Severity::Error,
+    message: "GitHub token in a string.",
+    note: "Revoke the token and load it from the environment or a secret store.",
+    pattern: r"\bgh[pousr]_[0-9A-Za-z]{36}\b",
+    min_entropy: 3.0,
+    needs_credential_name: false,
+    ignores: &[],
+  },
+  Detector {
+    id: "slack-token",
+    cwe: "CWE-798",
+    severity: Severity::Error,
+    message: "Slack token in a string.",
+    note: "Revoke the token and load it from the environment or a secret store.",
+    pattern: r"\bxox[abposr]-[0-9A-Za-z-]{10,}",
+    min_entropy: 3.0,
+    needs_credential_name: false,
+    ignores: &[],
+  },
+  Detector {
+    id: "stripe-secret-key",
+    cwe: "CWE-798",
+    severity: Severity::Error,
+    message: "Stripe live secret key in a string.",
+    note: "Roll the key and load it from the environment or a secret store.",
+    pattern: r"\b[rs]k_live_[0-9A-Za-z]{24,}\b",
+    min_entropy: 3.0,
+    needs_credential_name: false,
+    ignores: &[],
+  },
+  Detector {
+    id: "google-api-key",
+    cwe: "CWE-798",
+    severity: Severity::Error,
+    message: "Google API key in a string.",
+    note: "Restrict or regenerate the key and load it from the environment or a secret store.",
+    pattern: r"\bAIza[0-9A-Za-z_-]{35}",
+    min_entropy: 3.0,
+    needs_credential_name: false,
+    ignores: &[],
+  },
+  Detector {
+    id: "private-key",
+    cwe: "CWE-321",
+    severity: Severity::Error,
+    message: "Private key in a string.",
+    note: "Replace the key and load it from a file or secret store outside the repository.",
+    pattern: r"-----BEGIN ([A-Z0-9]+ )*PRIVATE KEY( BLOCK)?-----",
+    min_entropy: 0.0,
+    needs_credential_name: false,
+    ignores: &[FIXTURES],
+  },
+  Detector {
+    id: "jwt",
+    cwe: "CWE-798",
+    severity: Severity::Warning,
+    message: "JSON Web Token in a string.",
+    note: "Tokens grant access until they expire; issue them at runtime instead.",
+    pattern: r"\beyJ[0-9A-Za-z_-]{8,}\.eyJ[0-9A-Za-z_-]{8,}\.[0-9A-Za-z_-]{8,}",
+    min_entropy: 3.0,
+    needs_credential_name: false,
+    ignores: &[TESTS, FIXTURES],
+  },
+  Detector {
+    id: "high-entropy-secret",
+    cwe: "CWE-798",
+    severity: Severity::Warning,
+    message: "Random-looking string assigned to a credential.",
+    note: "Load credentials from the environment or a secret store instead.",
+    pattern: r"^[0-9A-Za-z+/=_.~-]{20,}$",
+    min_entropy: 4.0,
+    needs_credential_name: true,
+    ignores: &[TESTS, FIXTURES],
+  },
+];
+
+/// Names of keys, variables and arguments holding credentials.
+const CREDENTIAL_NAME: &str =
+  r"(?i)(passw(or)?d|secret|token|credential|api[_-]?key|access[_-]?key|private[_-]?key)";
+
+/// A detector compiled for a scan.
+pub struct Compiled {
+  pub detector: &'static Detector,
+  regex: Regex,
+  ignores: GlobSet,
+}
+
+/// Compile `detectors`, dropping those that ignore `path`.
+pub fn compile(
+  detectors: impl Iterator<Item = &'static Detector>,
+  path: Option<&str>,
+) -> Vec<Compiled> {
+  detectors
+    .map(|detector| {
+      let mut builder = GlobSetBuilder::new();
+      for glob in detector.ignores.iter().copied().flatten() {
+        builder.add(Glob::new(glob).expect("bundled globs must be valid"));
+      }
+      Compiled {
+        detector,
+        regex: Regex::new(detector.pattern).expect("bundled patterns must be valid"),
+        ignores: builder.build().expect("bundled globs must be valid"),
+      }
+    })
+    .filter(|compiled| path.map_or(true, |p| !compiled.ignores.is_match(p)))
+    .collect()
+}
+
+/// The findings of `detectors` in the values under `root`, each with its detector.
+pub fn scan<'c>(
+  root: &Node<'_, WasmDoc>,
+  detectors: &'c [Compiled],
+) -> Vec<(&'c Detector, Finding)> {
+  let credential_name = Regex::new(CREDENTIAL_NAME).expect("must be valid");
+  let mut findings = vec![];
+  let mut stack = vec![root.clone()];
+  while let Some(node) = stack.pop() {
+    if !is_value(&node) {
+      let children: Vec<_> = node.children().collect();
+      stack.extend(children.into_iter().rev());
+      continue;
+    }
+    if is_docstring(&node) {
+      continue;
+    }
+    let text = node.text();
+    let value = text.trim_matches(|c| matches!(c, '"' | '\'' | '`'));
+    for compiled in detectors {
+      let detector = compiled.detector;
+      let Some(found) = compiled.regex.find(value) else {
+        continue;
+      };
+      if entropy(found.as_str()) < detector.min_entropy {
+        continue;
+      }
+      if detector.needs_credential_name
+        && !credential_name_of(&node).is_some_and(|name| credential_name.is_match(&name))
+      {
+        continue;
+      }
+      findings.push((detector, finding(&node, detector)));
+    }
+  }
+  findings
+}
+
+/// String literals and config scalars of any grammar, e.g. `string`,
+/// `template_string`, `interpreted_string_literal` or YAML's `plain_scalar`.
+fn is_value(node: &Node<'_, WasmDoc>) -> bool {
+  if !node.is_named() || node.is_error() {
+    return false;
+  }
+  let kind = node.kind();
+  (kind.contains("string") && !kind.contains("comment")) || kind.ends_with("_scalar")
+}
+
+/// A string that is a statement of its own, like a Python docstring or `'use strict'`.
+fn is_docstring(node: &Node<'_, WasmDoc>) -> bool {
+  let Some(parent) = node.parent() else {
+    return false;
+  };
+  parent.kind() == "expression_statement" && parent.children().filter(|n| n.is_named()).count() == 1
+}
+
+/// The name a value is bound to: the closest named node before it in its parent, or
+/// in an ancestor for values wrapped like YAML's `flow_node` or Go's `expression_list`.
+fn credential_name_of(node: &Node<'_, WasmDoc>) -> Option<String> {
+  let mut current = node.clone();
+  for _ in 0..3 {
+    if let Some(prev) = current.prev_all().find(|n| n.is_named()) {
+      return Some(prev.text().to_string());
+    }
+    current = current.parent()?;
+  }
+  None
+}
+
+/// Shannon entropy of `s` in bits per char.
+fn entropy(s: &str) -> f64 {
+  let mut counts = std::collections::HashMap::new();
+  let mut len = 0.0;
+  for c in s.chars() {
+    *counts.entry(c).or_insert(0.0) += 1.0;
+    len += 1.0;
+  }
+  counts
+    .values()
+    .map(|count: &f64| {
+      let p = count / len;
+      -p * p.log2()
+    })
+    .sum()
+}
+
+fn finding(node: &Node<'_, WasmDoc>, detector: &Detector) -> Finding {
+  let text = node.text().to_string();
+  let range = node_range(node);
+  Finding {
+    labels: vec![FindingLabel {
+      text: Some(text.clone()),
+      text_span: None,
+      range: range.clone(),
+      message: None,
+      style: LabelStyle::Primary,
+    }],
+    text: Some(text),
+    text_span: None,
+    range,
+    message: detector.message.to_string(),
+    fix: None,
+    meta_variables: None,
+  }
+}
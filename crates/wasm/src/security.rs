@@ -0,0 +1,147 @@
+//! The security rules bundled with the module, see `scanSecurity`.
+//!
+//! The rulesets are those of `ast-grep audit`, see `SECURITY_RULESETS`, with `language`
+//! set to the scanned language. Every scan also looks for secrets, see `secrets`, and the
+//! ruleset `secrets` runs only those.
+
+use crate::doc::{error_chain, WasmDoc};
+use crate::encoding::EncodingOption;
+use crate::global_utils;
+use crate::scan::{self, Finding, ScanOptions};
+use crate::secrets;
+use crate::wasm_lang::{self, WasmLang};
+
+use ast_grep_config::{RuleConfig, SerializableRuleConfig, Severity, SECURITY_RULESETS};
+use ast_grep_core::AstGrep;
+use serde::{Deserialize, Serialize};
+use serde_yaml::with::singleton_map_recursive;
+use serde_yaml::Value as YamlValue;
+use wasm_bindgen::prelude::*;
+
+/// The ruleset of only the secret detectors, for languages without bundled rules.
+const SECRETS: &str = "secrets";
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityOptions {
+  /// The bundled ruleset to scan with, by default the one of the language's name.
+  ruleset: Option<String>,
+  /// Ids of rules to skip.
+  #[serde(default)]
+  exclude: Vec<String>,
+  /// Path of the scanned file, for detectors that skip e.g. test fixtures.
+  path: Option<String>,
+  #[serde(flatten)]
+  encoding: EncodingOption,
+}
+
+/// A match of a bundled security rule.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityFinding {
+  rule_id: String,
+  /// the weakness found, e.g. `CWE-798`
+  cwe: String,
+  severity: Severity,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  note: Option<String>,
+  #[serde(flatten)]
+  finding: Finding,
+}
+
+/// Scan `src` with the bundled rules for `lang_name`, findings ordered by position.
+pub fn scan(
+  lang_name: &str,
+  src: String,
+  options: SecurityOptions,
+) -> Result<Vec<SecurityFinding>, JsError> {
+  let lang: WasmLang = lang_name
+    .parse()
+    .map_err(|e: wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  let name = options.ruleset.as_deref().unwrap_or(lang_name);
+  let mut rules = if name == SECRETS {
+    vec![]
+  } else {
+    rules_for(lang_name, lang, ruleset(name)?)?
+  };
+  rules.retain(|rule| !options.exclude.contains(&rule.id));
+  let detectors = secrets::DETECTORS
+    .iter()
+    .filter(|detector| !options.exclude.iter().any(|id| id == detector.id));
+  let detectors = secrets::compile(detectors, options.path.as_deref());
+  let doc = WasmDoc::try_new(src, lang)?.with_encoding(options.encoding.position_encoding);
+  let root = AstGrep::doc(doc);
+  let scanned =
+    scan::scan_root(&root, &rules, &ScanOptions::default()).map_err(|e| JsError::new(&e))?;
+  let mut findings = vec![];
+  for matches in scanned {
+    let Some(rule) = rules.iter().find(|r| r.id == matches.rule_id) else {
+      continue;
+    };
+    let cwe = rule
+      .metadata
+      .as_ref()
+      .and_then(|metadata| serde_json::to_value(metadata).ok())
+      .and_then(|metadata| Some(metadata.get("cwe")?.as_str()?.to_string()))
+      .unwrap_or_default();
+    findings.extend(matches.matches.into_iter().map(|finding| SecurityFinding {
+      rule_id: rule.id.clone(),
+      cwe: cwe.clone(),
+      severity: rule.severity.clone(),
+      note: rule.note.clone(),
+      finding,
+    }));
+  }
+  let secrets = secrets::scan(&root.root(), &detectors);
+  findings.extend(
+    secrets
+      .into_iter()
+      .map(|(detector, finding)| SecurityFinding {
+        rule_id: detector.id.to_string(),
+        cwe: detector.cwe.to_string(),
+        severity: detector.severity.clone(),
+        note: Some(detector.note.to_string()),
+        finding,
+      }),
+  );
+  findings.sort_by_key(|f| (f.finding.range.start.index, f.finding.range.end.index));
+  Ok(findings)
+}
+
+fn ruleset(name: &str) -> Result<&'static str, JsError> {
+  let found = SECURITY_RULESETS
+    .iter()
+    .find(|ruleset| ruleset.name == name || ruleset.languages.contains(&name));
+  match found {
+    Some(ruleset) => Ok(ruleset.yaml),
+    None => {
+      let mut names: Vec<_> = SECURITY_RULESETS.iter().map(|r| r.name).collect();
+      names.push(SECRETS);
+      let msg = format!(
+        "No bundled security rules for `{name}`. Set `ruleset` to one of {}.",
+        names.join(", ")
+      );
+      Err(JsError::new(&msg))
+    }
+  }
+}
+
+/// The rules of `yaml` compiled for `lang`, with the utils registered for it.
+fn rules_for(
+  lang_name: &str,
+  lang: WasmLang,
+  yaml: &str,
+) -> Result<Vec<RuleConfig<WasmLang>>, JsError> {
+  let globals = global_utils::globals(lang);
+  let mut rules = vec![];
+  for document in serde_yaml::Deserializer::from_str(yaml) {
+    let mut value = YamlValue::deserialize(document).map_err(|e| error_chain(&e))?;
+    if let YamlValue::Mapping(map) = &mut value {
+      map.insert("language".into(), lang_name.into());
+    }
+    let config: SerializableRuleConfig<WasmLang> =
+      singleton_map_recursive::deserialize(value).map_err(|e| error_chain(&e))?;
+    rules.push(RuleConfig::try_from(config, &globals).map_err(|e| error_chain(&e))?);
+  }
+  Ok(rules)
+}
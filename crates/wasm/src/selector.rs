@@ -0,0 +1,365 @@
+//! CSS-like selectors for `selectorMatcher`, compiled to rule configs.
+//!
+//! A selector is a list of compound selectors joined by combinators, like in CSS. Each
+//! compound is a node kind or `*` followed by attribute and pseudo-class filters, and
+//! becomes an `all` rule of them. Combinators become relational rules of the compound
+//! to their right, so `a > b c` is a `c` inside a `b` whose parent is an `a`:
+//! - ` ` is `inside` with `stopBy: end`, `>` is `inside` the parent
+//! - `~` is `follows` with `stopBy: end`, `+` is `follows` the previous sibling
+//!
+//! Attributes test the text of the node or of its child in a field, like
+//! `[text^="use"]` or `[callee.text="eval"]`, with the CSS operators `=`, `^=`, `$=` and
+//! `*=`, and `~=` for a regex instead of whole words. `[callee]` requires the field and
+//! `[callee.kind=identifier]` its kind. The pseudo-classes are `:not()`, `:is()`,
+//! `:has()`, `:nth-child()`, `:nth-last-child()`, `:first-child` and `:last-child`.
+
+use serde_json::{json, Value};
+
+/// The rule that every node matches, for `*` and attributes like `[callee]`.
+fn any_node() -> Value {
+  json!({ "regex": "" })
+}
+
+struct Parser {
+  src: Vec<char>,
+  pos: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Combinator {
+  Descendant,
+  Child,
+  Sibling,
+  Adjacent,
+}
+
+impl Combinator {
+  /// The relational rule of the right compound to `rule` of the left one.
+  fn relation(self, mut rule: Value) -> Value {
+    let (key, stop_by_end) = match self {
+      Self::Descendant => ("inside", true),
+      Self::Child => ("inside", false),
+      Self::Sibling => ("follows", true),
+      Self::Adjacent => ("follows", false),
+    };
+    if stop_by_end {
+      rule["stopBy"] = "end".into();
+    }
+    json!({ key: rule })
+  }
+
+  /// The relational rule of `:has()` for a relative selector starting with the combinator.
+  fn relative(self, mut rule: Value) -> Value {
+    let (key, stop_by_end) = match self {
+      Self::Descendant => ("has", true),
+      Self::Child => ("has", false),
+      Self::Sibling => ("precedes", true),
+      Self::Adjacent => ("precedes", false),
+    };
+    if stop_by_end {
+      rule["stopBy"] = "end".into();
+    }
+    json!({ key: rule })
+  }
+}
+
+fn all(mut rules: Vec<Value>) -> Value {
+  if rules.len() == 1 {
+    rules.pop().expect("should have a rule")
+  } else {
+    json!({ "all": rules })
+  }
+}
+
+fn is_name_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl Parser {
+  fn peek(&self) -> Option<char> {
+    self.src.get(self.pos).copied()
+  }
+
+  fn error<T>(&self, msg: &str) -> Result<T, String> {
+    match self.peek() {
+      Some(c) => Err(format!("{msg}, found `{c}` at offset {}.", self.pos)),
+      None => Err(format!("{msg}, found the end of the selector.")),
+    }
+  }
+
+  fn skip_space(&mut self) -> bool {
+    let start = self.pos;
+    while self.peek().is_some_and(char::is_whitespace) {
+      self.pos += 1;
+    }
+    self.pos > start
+  }
+
+  fn eat(&mut self, c: char) -> bool {
+    if self.peek() == Some(c) {
+      self.pos += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), String> {
+    if self.eat(c) {
+      Ok(())
+    } else {
+      self.error(&format!("Expected `{c}`"))
+    }
+  }
+
+  fn name(&mut self) -> Option<String> {
+    let start = self.pos;
+    while self.peek().is_some_and(is_name_char) {
+      self.pos += 1;
+    }
+    let name: String = self.src[start..self.pos].iter().collect();
+    (!name.is_empty()).then_some(name)
+  }
+
+  /// `a, b`, as an `any` rule of the selectors.
+  fn list(&mut self, relative: bool) -> Result<Value, String> {
+    let mut rules = vec![];
+    loop {
+      self.skip_space();
+      rules.push(self.complex(relative)?);
+      self.skip_space();
+      if !self.eat(',') {
+        break;
+      }
+    }
+    Ok(if rules.len() == 1 {
+      rules.pop().expect("should have a rule")
+    } else {
+      json!({ "any": rules })
+    })
+  }
+
+  fn combinator(&mut self) -> Option<Combinator> {
+    let spaced = self.skip_space();
+    let combinator = match self.peek() {
+      Some('>') => Combinator::Child,
+      Some('+') => Combinator::Adjacent,
+      Some('~') => Combinator::Sibling,
+      Some(',' | ')') | None => return None,
+      Some(_) if spaced => return Some(Combinator::Descendant),
+      Some(_) => return None,
+    };
+    self.pos += 1;
+    self.skip_space();
+    Some(combinator)
+  }
+
+  /// Compounds joined by combinators. A relative selector of `:has()` may start with a
+  /// combinator, and is then returned as the relational rule of it.
+  fn complex(&mut self, relative: bool) -> Result<Value, String> {
+    let leading = match self.peek() {
+      Some('>') if relative => Some(Combinator::Child),
+      Some('+') if relative => Some(Combinator::Adjacent),
+      Some('~') if relative => Some(Combinator::Sibling),
+      _ => None,
+    };
+    if leading.is_some() {
+      self.pos += 1;
+      self.skip_space();
+    }
+    let mut rule = all(self.compound()?);
+    while let Some(combinator) = self.combinator() {
+      let mut rules = self.compound()?;
+      rules.push(combinator.relation(rule));
+      rule = all(rules);
+    }
+    Ok(match (relative, leading) {
+      (true, leading) => leading.unwrap_or(Combinator::Descendant).relative(rule),
+      (false, _) => rule,
+    })
+  }
+
+  fn compound(&mut self) -> Result<Vec<Value>, String> {
+    let mut rules = vec![];
+    let start = self.pos;
+    if !self.eat('*') {
+      if let Some(kind) = self.name() {
+        rules.push(json!({ "kind": kind }));
+      }
+    }
+    loop {
+      match self.peek() {
+        Some('[') => rules.push(self.attribute()?),
+        Some(':') => rules.push(self.pseudo_class()?),
+        _ => break,
+      }
+    }
+    if self.pos == start {
+      return self.error("Expected a node kind, `*`, `[` or `:`");
+    }
+    if rules.is_empty() {
+      rules.push(any_node());
+    }
+    Ok(rules)
+  }
+
+  fn value(&mut self) -> Result<String, String> {
+    let Some(quote @ ('"' | '\'')) = self.peek() else {
+      let start = self.pos;
+      while self.peek().is_some_and(|c| !c.is_whitespace() && c != ']') {
+        self.pos += 1;
+      }
+      if self.pos == start {
+        return self.error("Expected a value");
+      }
+      return Ok(self.src[start..self.pos].iter().collect());
+    };
+    self.pos += 1;
+    let mut value = String::new();
+    loop {
+      match self.peek() {
+        Some(c) if c == quote => break,
+        // only quotes and backslashes are escaped, so regexes keep theirs like `\d`
+        Some('\\') if self.src.get(self.pos + 1) == Some(&quote) => {
+          self.pos += 1;
+          value.push(quote);
+        }
+        Some('\\') if self.src.get(self.pos + 1) == Some(&'\\') => {
+          self.pos += 1;
+          value.push('\\');
+        }
+        Some(c) => value.push(c),
+        None => return self.error(&format!("Expected the closing `{quote}`")),
+      }
+      self.pos += 1;
+    }
+    self.pos += 1;
+    Ok(value)
+  }
+
+  /// `[text op value]`, `[field]`, `[field.text op value]` or `[field.kind=value]`.
+  fn attribute(&mut self) -> Result<Value, String> {
+    self.expect('[')?;
+    self.skip_space();
+    let Some(name) = self.name() else {
+      return self.error("Expected `text` or a field name");
+    };
+    let (field, attr) = if self.eat('.') {
+      let attr = match self.name() {
+        Some(attr) if attr == "text" || attr == "kind" => attr,
+        _ => return self.error("Expected `text` or `kind` after the field name"),
+      };
+      (Some(name), attr)
+    } else if name == "text" {
+      (None, name)
+    } else {
+      (Some(name), String::new())
+    };
+    self.skip_space();
+    let op_start = self.pos;
+    while self.peek().is_some_and(|c| "^$*~=".contains(c)) {
+      self.pos += 1;
+    }
+    let op: String = self.src[op_start..self.pos].iter().collect();
+    let rule = if op.is_empty() {
+      if field.is_none() || !attr.is_empty() {
+        return self.error("Expected an operator like `=`");
+      }
+      any_node()
+    } else if attr.is_empty() && field.is_some() {
+      self.pos = op_start;
+      return self.error("Expected `]`, or `.text` or `.kind` before the operator");
+    } else {
+      self.skip_space();
+      let value = self.value()?;
+      if attr == "kind" {
+        if op != "=" {
+          self.pos = op_start;
+          return self.error("Expected `=` for a kind");
+        }
+        json!({ "kind": value })
+      } else {
+        let escaped = regex::escape(&value);
+        let regex = match op.as_str() {
+          "=" => format!("^{escaped}$"),
+          "^=" => format!("^{escaped}"),
+          "$=" => format!("{escaped}$"),
+          "*=" => escaped,
+          "~=" => value,
+          _ => {
+            self.pos = op_start;
+            return self.error("Expected one of `=`, `^=`, `$=`, `*=` or `~=`");
+          }
+        };
+        json!({ "regex": regex })
+      }
+    };
+    self.skip_space();
+    self.expect(']')?;
+    Ok(match field {
+      Some(field) => {
+        let mut rule = rule;
+        rule["field"] = field.into();
+        json!({ "has": rule })
+      }
+      None => rule,
+    })
+  }
+
+  fn pseudo_class(&mut self) -> Result<Value, String> {
+    self.expect(':')?;
+    let start = self.pos;
+    while self.peek().is_some_and(|c| is_name_char(c) || c == '-') {
+      self.pos += 1;
+    }
+    let name: String = self.src[start..self.pos].iter().collect();
+    let rule = match name.as_str() {
+      "first-child" => return Ok(json!({ "nthChild": 1 })),
+      "last-child" => return Ok(json!({ "nthChild": { "position": 1, "reverse": true } })),
+      "not" | "is" | "has" => {
+        self.expect('(')?;
+        let rule = self.list(name == "has")?;
+        match name.as_str() {
+          "not" => json!({ "not": rule }),
+          _ => rule,
+        }
+      }
+      "nth-child" | "nth-last-child" => {
+        self.expect('(')?;
+        let arg_start = self.pos;
+        while self.peek().is_some_and(|c| c != ')') {
+          self.pos += 1;
+        }
+        let arg: String = self.src[arg_start..self.pos].iter().collect();
+        let arg = arg.trim();
+        let position = match arg.parse::<usize>() {
+          Ok(n) => json!(n),
+          Err(_) => json!(arg),
+        };
+        match name.as_str() {
+          "nth-child" => json!({ "nthChild": position }),
+          _ => json!({ "nthChild": { "position": position, "reverse": true } }),
+        }
+      }
+      _ => {
+        self.pos = start;
+        return self.error("Expected `not`, `is`, `has`, `nth-child`, `nth-last-child`, `first-child` or `last-child`");
+      }
+    };
+    self.expect(')')?;
+    Ok(rule)
+  }
+}
+
+/// The rule of `selector`. Kinds and fields are not checked against a language here.
+pub fn compile(selector: &str) -> Result<Value, String> {
+  let mut parser = Parser {
+    src: selector.chars().collect(),
+    pos: 0,
+  };
+  let rule = parser.list(false)?;
+  if parser.pos < parser.src.len() {
+    return parser.error("Expected a combinator or `,`");
+  }
+  Ok(rule)
+}
@@ -1,34 +1,61 @@
 use std::rc::Rc;
 
+use crate::budget::FindOptions;
+use crate::clones::{self, FingerprintOptions};
+use crate::compiled;
+use crate::debug_match::{self, DebugMatchOptions};
+use crate::global_utils;
+use crate::injection;
+use crate::json_output;
+use crate::limits;
+use crate::list_edit;
+use crate::parse_error;
+use crate::pattern_object::PatternObject;
+use crate::progress::{self, Progress};
+use crate::query::QueryMatcher;
+use crate::scan;
+use crate::scope::Scopes;
+use crate::search_range;
+use crate::snapshot;
+use crate::taint::{self, TaintSpec};
 use crate::ts_types as ts;
-use ast_grep_core::matcher::KindMatcher;
-use ast_grep_core::source::Content;
-use ast_grep_core::{AstGrep, NodeMatch, Pattern};
+use ast_grep_core::matcher::{KindMatcher, MatcherExt};
+use ast_grep_core::meta_var::MetaVariable;
+use ast_grep_core::source::{Content, Edit};
+use ast_grep_core::{AstGrep, Doc, Language, Node, NodeMatch, Pattern};
 use wasm_bindgen::prelude::*;
 
-use crate::doc::{WasmConfig, WasmDoc, Wrapper};
+use crate::doc::{error_chain, WasmConfig, WasmDoc, WasmRule, Wrapper};
+use crate::encoding::{self, PositionEncoding};
+use crate::wasm_lang::{TextKind, WasmLang};
+use ast_grep_config::{Fixer, SerializableRule};
 #[derive(serde::Serialize, serde::Deserialize)]
 #[wasm_bindgen(getter_with_clone)]
 pub struct WasmEdit {
-  /// The start position of the edit (character offset)
+  /// The start position of the edit, an offset in the unit of `positionEncoding`
   pub start_pos: u32,
-  /// The end position of the edit (character offset)
+  /// The end position of the edit
   pub end_pos: u32,
   /// The text to be inserted
   pub inserted_text: String,
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 #[wasm_bindgen(getter_with_clone)]
 pub struct Pos {
   /// line number starting from 0
   pub line: u32,
-  /// column number starting from 0
+  /// column number starting from 0, in the unit of `positionEncoding`
   pub column: u32,
-  /// character offset of the position
+  /// offset of the position in the source, in the same unit
   pub index: u32,
+  /// offset of the position in the source in UTF-8 bytes
+  #[wasm_bindgen(js_name = byteOffset)]
+  pub byte_offset: u32,
 }
 
+#[derive(Clone, serde::Serialize)]
 #[wasm_bindgen(getter_with_clone)]
 pub struct Range {
   /// starting position of the range
@@ -42,6 +69,8 @@ pub struct Range {
 pub struct SgRoot {
   inner: Rc<AstGrep<WasmDoc>>,
   filename: String,
+  /// The node used as root if the SgRoot is a subtree of another SgRoot.
+  subtree: Option<Node<'static, WasmDoc>>,
 }
 
 #[wasm_bindgen]
@@ -53,10 +82,155 @@ impl SgRoot {
     // AstGrep alive as long as any SgNode references it.
     let root_ref: &'static AstGrep<WasmDoc> =
       unsafe { &*(Rc::as_ptr(&self.inner) as *const AstGrep<WasmDoc>) };
-    let node_match: NodeMatch<'static, WasmDoc> = root_ref.root().into();
+    let root = match &self.subtree {
+      Some(node) => node.clone(),
+      None => root_ref.root(),
+    };
     SgNode {
       _root: self.inner.clone(),
-      inner: node_match,
+      inner: root.into(),
+      primary: None,
+    }
+  }
+
+  /// Replace the text from `start` to `end` (offsets, see `positionEncoding`) with `new_text` and
+  /// reparse incrementally, reusing the unchanged parts of the old tree.
+  /// Nodes obtained before the edit keep describing the old source.
+  /// Throws if the range is out of bounds or this is a subtree.
+  pub fn edit(&mut self, start: u32, end: u32, new_text: String) -> Result<(), JsError> {
+    if self.subtree.is_some() {
+      return Err(JsError::new(
+        "Cannot edit a subtree, edit the SgRoot it was created from.",
+      ));
+    }
+    let doc = self.inner.root().get_doc();
+    let source = doc.get_source();
+    let start = doc.encoding().decode_offset(source.chars(), start as usize);
+    let end = doc.encoding().decode_offset(source.chars(), end as usize);
+    let len = source.char_count();
+    if start > end || end > len {
+      return Err(JsError::new(&format!(
+        "Invalid edit range {start}..{end} for source of length {len}."
+      )));
+    }
+    // edit a copy, the tree of the old doc is still used by existing nodes
+    let mut root = AstGrep::doc(self.inner.root().get_doc().clone());
+    let edit = Edit {
+      position: start,
+      deleted_length: end - start,
+      inserted_text: new_text.chars().collect(),
+    };
+    root.edit(edit).map_err(|e| JsError::new(&e))?;
+    self.inner = Rc::new(root);
+    Ok(())
+  }
+
+  /// Returns the smallest node spanning the range from `start` to `end`
+  /// (offsets like `range().start.index`) as its own SgRoot.
+  /// The subtree shares the source of this root, so positions are unchanged.
+  /// Returns `null` if no node spans the range.
+  pub fn subtree(&self, start: u32, end: u32) -> Option<SgRoot> {
+    let doc = self.inner.root().get_doc();
+    let src = doc.get_source().chars();
+    let start = doc.encoding().decode_offset(src, start as usize);
+    let end = doc.encoding().decode_offset(src, end as usize);
+    let mut node = self.root().inner.get_node().clone();
+    let range = node.range();
+    if start > end || start < range.start || end > range.end {
+      return None;
+    }
+    let spans = |n: &Node<'static, WasmDoc>| {
+      let r = n.range();
+      r.start <= start && end <= r.end
+    };
+    loop {
+      let child = node.children().find(spans);
+      let Some(child) = child else {
+        break;
+      };
+      node = child;
+    }
+    Some(SgRoot {
+      inner: self.inner.clone(),
+      filename: self.filename.clone(),
+      subtree: Some(node),
+    })
+  }
+
+  /// The position of `offset`, an offset in the unit of `positionEncoding`, with its
+  /// zero-based line and column. An offset inside a character is the character's start.
+  /// Throws if the offset is past the end of the source.
+  #[wasm_bindgen(js_name = offsetToPosition)]
+  pub fn offset_to_position(&self, offset: u32) -> Result<Pos, JsError> {
+    let doc = self.inner.root().get_doc();
+    let src = doc.get_source().chars();
+    let offset = doc.encoding().decode_offset(src, offset as usize);
+    if offset > src.len() {
+      return Err(JsError::new("The offset is past the end of the source."));
+    }
+    let line = src[..offset].iter().filter(|&&c| c == '\n').count();
+    Ok(pos(src, line, offset, doc.encoding()))
+  }
+
+  /// The offset, in the unit of `positionEncoding`, of the zero-based `line` and `column`.
+  /// A column past the end of the line is the line's end, like in LSP.
+  /// Throws if the source has no such line.
+  #[wasm_bindgen(js_name = positionToOffset)]
+  pub fn position_to_offset(&self, line: u32, column: u32) -> Result<u32, JsError> {
+    let doc = self.inner.root().get_doc();
+    let src = doc.get_source().chars();
+    let encoding = doc.encoding();
+    let offset = encoding
+      .decode_position(src, line as usize, column as usize)
+      .ok_or_else(|| JsError::new(&format!("The source has no line {line}.")))?;
+    Ok(encoding.encode_offset(src, offset) as u32)
+  }
+
+  /// Names of the languages embedded in this document by `registerInjections`,
+  /// each once, in the order their first region appears.
+  #[wasm_bindgen(js_name = injectedLanguages)]
+  pub fn injected_languages(&self) -> Vec<String> {
+    let mut langs: Vec<String> = vec![];
+    for (lang, _) in injection::regions(self.root().inner.get_node()) {
+      if !langs.contains(&lang) {
+        langs.push(lang);
+      }
+    }
+    langs
+  }
+
+  /// Returns an SgRoot for every region of `lang` embedded in this document, in order.
+  /// Each region is parsed on its own, but over the host source, so positions and
+  /// ranges are those of the host document. Unregistered languages have no regions.
+  #[wasm_bindgen(js_name = getInjections)]
+  pub fn get_injections(&self, lang: String) -> Result<Vec<SgRoot>, JsError> {
+    let roots = injection::injected_roots(self.root().inner.get_node(), Some(&lang))?;
+    let roots = roots
+      .into_iter()
+      .map(|root| SgRoot {
+        inner: Rc::new(root),
+        filename: self.filename.clone(),
+        subtree: None,
+      })
+      .collect();
+    Ok(roots)
+  }
+
+  /// Returns the ERROR and MISSING nodes of the tree in document order, each with
+  /// its `kind`, `text`, `range` and the kinds the parser `expected` there.
+  #[wasm_bindgen(js_name = parseErrors)]
+  pub fn parse_errors(&self) -> Result<JsValue, JsError> {
+    let errors = parse_error::collect(self.root().inner.get_node());
+    serde_wasm_bindgen::to_value(&errors).map_err(|e| JsError::new(&e.to_string()))
+  }
+
+  /// Convert `code` of this tree's source, e.g. of `commitEdits`, back to the line endings
+  /// and BOM of the source passed to `parse` with `normalizeLineEndings` or `stripBom`.
+  /// Every line ends like most lines of that source. Returns `code` as is otherwise.
+  pub fn denormalize(&self, code: String) -> String {
+    match self.inner.root().get_doc().normalization {
+      Some(normalization) => normalization.restore(&code),
+      None => code,
     }
   }
 
@@ -66,6 +240,137 @@ impl SgRoot {
     self.filename.clone()
   }
 
+  /// A compact binary snapshot of the whole tree, with the language, the filename and
+  /// a hash of the source, for `SgRoot.deserialize`.
+  pub fn serialize(&self) -> Vec<u8> {
+    snapshot::serialize(&self.inner, &self.filename)
+  }
+
+  /// Restore the SgRoot of a snapshot of `src`. web-tree-sitter cannot load a tree, so `src`
+  /// is parsed again. Throws if the snapshot is not of `src`, or of another grammar than
+  /// the one registered for its language now, so a cache knows a stored result is stale.
+  pub fn deserialize(bytes: &[u8], src: String) -> Result<SgRoot, JsError> {
+    let (root, filename) = snapshot::deserialize(bytes, src)?;
+    Ok(SgRoot::new(root, filename))
+  }
+
+  /// Resolve a path of `SgNode.path` or `SgNode.fieldPath` to its node, or `undefined`
+  /// if the tree has no node there, e.g. since the source changed. Paths start at the
+  /// root of the whole tree, so this throws for a subtree.
+  #[wasm_bindgen(js_name = nodeAtPath)]
+  pub fn node_at_path(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "number[] | string")] path: JsValue,
+  ) -> Result<Option<SgNode>, JsError> {
+    if self.subtree.is_some() {
+      return Err(JsError::new("A subtree cannot resolve node paths."));
+    }
+    let mut node = self.root();
+    let steps = match path.as_string() {
+      Some(field_path) => {
+        let (kind, steps) = parse_field_path(&field_path)?;
+        if kind != node.kind() {
+          return Ok(None);
+        }
+        steps
+      }
+      None => {
+        let indices: Vec<u32> = serde_wasm_bindgen::from_value(path)
+          .map_err(|_| JsError::new("Expected a path of child indices or a field path."))?;
+        indices.into_iter().map(PathStep::Index).collect()
+      }
+    };
+    for step in steps {
+      let (index, then) = match step {
+        PathStep::Index(index) => (index, None),
+        PathStep::Field(field, nth) => {
+          let children = child_fields(&node.inner.get_inner_node().0);
+          let in_field: Vec<_> = (0..children.len() as u32)
+            .filter(|i| children[*i as usize].1.as_ref() == Some(&field))
+            .collect();
+          // `.body[1]` of a single `body` child is its child at 1, see `path_step`
+          match (in_field.as_slice(), nth) {
+            ([index], nth) => (*index, nth),
+            (in_field, nth) => match in_field.get(nth.unwrap_or(0) as usize) {
+              Some(index) => (*index, None),
+              None => return Ok(None),
+            },
+          }
+        }
+      };
+      for index in std::iter::once(index).chain(then) {
+        let Some(child) = node.child_node(index) else {
+          return Ok(None);
+        };
+        node = child;
+      }
+    }
+    Ok(Some(node))
+  }
+
+  /// Run several rule configs over the tree in one traversal.
+  /// `rules` is an array of `{ id, config }` where `config` is a rule config object.
+  /// Returns an object mapping every rule id to its matches in document order.
+  #[wasm_bindgen(js_name = matchAllRules)]
+  pub fn match_all_rules(&self, rules: JsValue) -> Result<js_sys::Object, JsError> {
+    let rules: Vec<NamedRule> = serde_wasm_bindgen::from_value(rules)?;
+    let root = self.root();
+    let lang = *root.inner.lang();
+    let mut compiled = Vec::with_capacity(rules.len());
+    for NamedRule { id, config } in rules {
+      if compiled.iter().any(|(i, _)| *i == id) {
+        return Err(JsError::new(&format!("Duplicate rule id `{id}`.")));
+      }
+      compiled.push((id, config.parse_with(lang)?));
+    }
+    let mut grouped: Vec<Vec<SgNode>> = compiled.iter().map(|_| vec![]).collect();
+    for node in root.inner.dfs() {
+      for ((_, rule), matches) in compiled.iter().zip(grouped.iter_mut()) {
+        if let Some(nm) = rule.match_node(node.clone()) {
+          matches.push(root.make_node(unsafe { SgNode::cast_match(nm) }));
+        }
+      }
+    }
+    let result = js_sys::Object::new();
+    for ((id, _), matches) in compiled.into_iter().zip(grouped) {
+      let matches: js_sys::Array = matches.into_iter().map(JsValue::from).collect();
+      js_sys::Reflect::set(&result, &id.into(), &matches)
+        .map_err(|_| JsError::new("Failed to set rule matches"))?;
+    }
+    Ok(result)
+  }
+
+  /// Apply the fixes of ast-grep YAML rules repeatedly, re-parsing between passes,
+  /// until the fixes no longer change the code or `max_passes` passes have run.
+  /// The whole source is fixed, also if this is a subtree.
+  /// Returns `{ fixed, passes, applied, converged }`.
+  #[wasm_bindgen(js_name = applyFixesIteratively)]
+  pub fn apply_fixes_iteratively(
+    &self,
+    config_yaml: String,
+    max_passes: u32,
+  ) -> Result<JsValue, JsError> {
+    self.inner.lang().check_registered()?;
+    let rules = scan::parse_rules(&config_yaml)?;
+    let result = scan::fix_iteratively(&self.inner, &rules, max_passes as usize)?;
+    serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+  }
+
+  /// Apply the fix of every match of ast-grep YAML rules in one pass. Where matches
+  /// overlap, the fix of the one starting first is applied and the others are skipped.
+  /// The whole source is fixed, also if this is a subtree.
+  /// Returns `{ code, applied, skipped, newErrors? }` with edits tagged by `ruleId`.
+  /// `options` are the same as for the top level `fix`.
+  #[wasm_bindgen(js_name = replaceAll)]
+  pub fn replace_all(&self, config_yaml: String, options: JsValue) -> Result<JsValue, JsError> {
+    self.inner.lang().check_registered()?;
+    let options: Option<scan::FixOptions> = serde_wasm_bindgen::from_value(options)?;
+    let rules = scan::parse_rules(&config_yaml)?;
+    let src = self.inner.root().get_doc().get_source().text();
+    let result = scan::replace_all(&src, &self.inner, &rules, &options.unwrap_or_default())?;
+    serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+  }
+
   /// This method is mainly for debugging tree parsing result.
   #[wasm_bindgen(js_name = getInnerTree)]
   pub fn get_inner_tree(&self) -> ts::Tree {
@@ -73,11 +378,19 @@ impl SgRoot {
   }
 }
 
+/// A rule config object with its id, used by `matchAllRules`.
+#[derive(serde::Deserialize)]
+struct NamedRule {
+  id: String,
+  config: WasmConfig,
+}
+
 impl SgRoot {
   pub fn new(inner: AstGrep<WasmDoc>, filename: String) -> Self {
     Self {
       inner: Rc::new(inner),
       filename,
+      subtree: None,
     }
   }
 }
@@ -88,30 +401,209 @@ pub struct SgNode {
   // Prevent the AstGrep from being dropped while SgNode is alive
   _root: Rc<AstGrep<WasmDoc>>,
   inner: NodeMatch<'static, WasmDoc>,
+  /// Meta variable designated by the rule config's `primary` field.
+  primary: Option<Rc<str>>,
+}
+
+/// Lazily yields the matches of `findAllIter` in the order of `findAll`.
+#[wasm_bindgen]
+pub struct MatchIterator {
+  /// The searched node, which also keeps the tree alive.
+  root: SgNode,
+  root_id: usize,
+  /// Next node to visit in pre-order, `None` when the traversal is done.
+  next: Option<Node<'static, WasmDoc>>,
+  matcher: Rc<MatcherType>,
+}
+
+#[wasm_bindgen(inline_js = r#"
+const BATCH_SIZE = 256
+async function* batches() {
+  let batch
+  while ((batch = this.nextBatch(BATCH_SIZE)).length > 0) {
+    yield* batch
+    await new Promise(resolve => setTimeout(resolve, 0))
+  }
+}
+export function makeIterable(iter) {
+  const proto = Object.getPrototypeOf(iter)
+  if (!Object.hasOwn(proto, Symbol.asyncIterator)) {
+    proto[Symbol.iterator] = function () { return this }
+    proto[Symbol.asyncIterator] = batches
+  }
+  return iter
+}
+"#)]
+extern "C" {
+  #[wasm_bindgen(js_name = makeIterable)]
+  fn make_iterable(iter: JsValue) -> JsValue;
+}
+
+impl MatchIterator {
+  /// The node after `node` in a pre-order traversal of the searched node.
+  fn successor(&self, node: &Node<'static, WasmDoc>) -> Option<Node<'static, WasmDoc>> {
+    if let Some(child) = node.child(0) {
+      return Some(child);
+    }
+    let mut node = node.clone();
+    loop {
+      if node.node_id() == self.root_id {
+        return None;
+      }
+      if let Some(next) = node.next() {
+        return Some(next);
+      }
+      node = node.parent()?;
+    }
+  }
+
+  fn next_match(&mut self) -> Option<SgNode> {
+    while let Some(node) = self.next.take() {
+      self.next = self.successor(&node);
+      if let Some(nm) = self.matcher.match_node(node) {
+        let primary = self.matcher.primary();
+        return Some(self.root.make_node(nm).with_primary(primary));
+      }
+    }
+    None
+  }
+}
+
+#[wasm_bindgen]
+impl MatchIterator {
+  /// The next match as an iterator result `{ done, value }`.
+  #[wasm_bindgen(js_name = next, unchecked_return_type = "IteratorResult<SgNode, undefined>")]
+  pub fn next_result(&mut self) -> Result<JsValue, JsError> {
+    let value = self.next_match();
+    let result = js_sys::Object::new();
+    let done = JsValue::from_bool(value.is_none());
+    let value = value.map_or(JsValue::UNDEFINED, JsValue::from);
+    js_sys::Reflect::set(&result, &"done".into(), &done)
+      .and_then(|_| js_sys::Reflect::set(&result, &"value".into(), &value))
+      .map_err(|_| JsError::new("Failed to create iterator result"))?;
+    Ok(result.into())
+  }
+
+  /// Up to `size` next matches. Returns an empty array once all matches are yielded.
+  #[wasm_bindgen(js_name = nextBatch)]
+  pub fn next_batch(&mut self, size: u32) -> Vec<SgNode> {
+    std::iter::from_fn(|| self.next_match())
+      .take(size as usize)
+      .collect()
+  }
+
+  /// Whether all matches have been yielded.
+  #[wasm_bindgen(getter)]
+  pub fn done(&self) -> bool {
+    self.next.is_none()
+  }
 }
 
 impl SgNode {
+  /// An edit of `range`, in offsets of the source, with offsets in the position encoding.
+  fn make_edit(&self, range: std::ops::Range<usize>, text: String) -> WasmEdit {
+    let doc = self.inner.get_doc();
+    let src = doc.get_source().chars();
+    WasmEdit {
+      start_pos: doc.encoding().encode_offset(src, range.start) as u32,
+      end_pos: doc.encoding().encode_offset(src, range.end) as u32,
+      inserted_text: text,
+    }
+  }
+
+  /// Whether the node or an ancestor is one of the language's `text` kinds.
+  /// The walk stops at interpolated code, e.g. `${x}` in a template string.
+  fn is_inside_kind(&self, text: TextKind) -> Result<bool, JsError> {
+    let node: &Node<'static, WasmDoc> = &self.inner;
+    let lang = node.lang();
+    let (kinds, stops) = lang.text_kinds(text).ok_or_else(|| {
+      JsError::new(&format!(
+        "Language `{}` has no known string and comment kinds.",
+        lang.name()
+      ))
+    })?;
+    for n in std::iter::once(node.clone()).chain(node.ancestors()) {
+      let id = n.kind_id() as usize;
+      if kinds.contains(id) {
+        return Ok(true);
+      }
+      if stops.contains(id) {
+        return Ok(false);
+      }
+    }
+    Ok(false)
+  }
+
+  fn tree_root(&self) -> Node<'static, WasmDoc> {
+    let node: &Node<'static, WasmDoc> = &self.inner;
+    node.ancestors().last().unwrap_or_else(|| node.clone())
+  }
+
+  /// `node` as an SgNode of `root`, a root of a clone of its document, for callbacks
+  /// that get nodes without their SgRoot like custom matchers.
+  pub(crate) fn readopted(root: Rc<AstGrep<WasmDoc>>, node: &Node<'_, WasmDoc>) -> SgNode {
+    // SAFETY: as in `SgRoot::root`, the Rc keeps the AstGrep alive, and the clone
+    // shares the tree of the node's document so the node is in its lineage.
+    let root_ref: &'static AstGrep<WasmDoc> = unsafe { &*Rc::as_ptr(&root) };
+    let mut node: Node<'static, WasmDoc> = unsafe { std::mem::transmute(node.clone()) };
+    unsafe { root_ref.readopt(&mut node) };
+    SgNode {
+      _root: root,
+      inner: node.into(),
+      primary: None,
+    }
+  }
+
   fn make_node(&self, nm: NodeMatch<'static, WasmDoc>) -> SgNode {
     SgNode {
       _root: self._root.clone(),
       inner: nm,
+      primary: None,
     }
   }
 
-  fn parse_matcher(&self, m: JsValue) -> Result<MatcherType, JsError> {
-    if let Some(s) = m.as_string() {
-      let lang = *self.inner.lang();
-      let pattern = Pattern::try_new(&s, lang).map_err(|e| JsError::new(&e.to_string()))?;
-      return Ok(MatcherType::Pattern(pattern));
-    }
-    if let Some(n) = m.as_f64() {
-      return Ok(MatcherType::Kind(KindMatcher::from_id(n as u16)));
+  fn with_primary(mut self, primary: Option<Rc<str>>) -> Self {
+    self.primary = primary;
+    self
+  }
+
+  /// `nodes` that match `filter` with their captures, or all of them if it is unset.
+  fn filtered<'a>(
+    &'a self,
+    nodes: impl Iterator<Item = Node<'static, WasmDoc>> + 'a,
+    filter: JsValue,
+  ) -> Result<impl Iterator<Item = SgNode> + 'a, JsError> {
+    let matcher = if filter.is_undefined() || filter.is_null() {
+      None
+    } else {
+      Some(self.parse_matcher(filter)?)
+    };
+    let primary = matcher.as_ref().and_then(|m| m.primary());
+    Ok(nodes.filter_map(move |n| {
+      let nm = match &matcher {
+        Some(matcher) => matcher.match_node(n)?,
+        None => NodeMatch::from(n),
+      };
+      let node = self.make_node(unsafe { Self::cast_match(nm) });
+      Some(node.with_primary(primary.clone()))
+    }))
+  }
+
+  fn parse_matcher(&self, m: JsValue) -> Result<Rc<MatcherType>, JsError> {
+    let lang = *self.inner.lang();
+    lang.check_registered()?;
+    if let Some(compiled) = compiled::lookup(&m, lang) {
+      return compiled;
     }
-    // Treat as WasmConfig object
-    let config: WasmConfig = serde_wasm_bindgen::from_value(m)?;
+    parse_matcher(lang, m).map(Rc::new)
+  }
+
+  /// `parse_matcher` failing if matching would recurse past `maxPatternDepth`.
+  fn parse_limited(&self, m: JsValue, options: &FindOptions) -> Result<Rc<MatcherType>, JsError> {
+    let matcher = self.parse_matcher(m.clone())?;
     let lang = *self.inner.lang();
-    let rule_core = config.parse_with(lang)?;
-    Ok(MatcherType::RuleCore(rule_core))
+    limits::check_matcher(&m, &matcher, lang, options.max_pattern_depth)?;
+    Ok(matcher)
   }
 
   // SAFETY helper: transmute NodeMatch lifetime from 'tree to 'static.
@@ -121,10 +613,82 @@ impl SgNode {
   }
 }
 
-enum MatcherType {
+/// Compile a pattern string or object, kind id or rule config for `lang`.
+pub(crate) fn parse_matcher(lang: WasmLang, m: JsValue) -> Result<MatcherType, JsError> {
+  lang.check_registered()?;
+  if m.is_string() || PatternObject::is_object(&m) {
+    let pattern = PatternObject::from_js(m)?.compile(lang)?;
+    return Ok(MatcherType::Pattern(pattern));
+  }
+  if let Some(n) = m.as_f64() {
+    return Ok(MatcherType::Kind(KindMatcher::from_id(n as u16)));
+  }
+  // Treat as WasmConfig object
+  let config: WasmConfig = serde_wasm_bindgen::from_value(m)?;
+  let primary = config
+    .primary
+    .as_deref()
+    .map(|p| p.trim_start_matches('$').into());
+  if let Some(query) = config.parse_query(lang)? {
+    return Ok(MatcherType::Query(query, primary));
+  }
+  let rule = config.parse_with(lang)?;
+  Ok(MatcherType::Rule(Box::new(rule), primary))
+}
+
+/// The range of `node`, in the original document if its source has `withSourceMap`.
+pub(crate) fn node_range(node: &Node<'_, WasmDoc>) -> Range {
+  let doc = node.get_doc();
+  let src = doc.get_source().chars();
+  let range = node.range();
+  let range = Range {
+    start: pos(src, node.start_pos().line(), range.start, doc.encoding()),
+    end: pos(src, node.end_pos().line(), range.end, doc.encoding()),
+  };
+  original_range(node.get_doc(), range)
+}
+
+fn original_range(doc: &WasmDoc, range: Range) -> Range {
+  match doc.source_map() {
+    Some(map) => map.map_range(range),
+    None => range,
+  }
+}
+
+/// The position of the char offset `offset` on `line` in `encoding`.
+pub(crate) fn pos(src: &[char], line: usize, offset: usize, encoding: PositionEncoding) -> Pos {
+  Pos {
+    line: line as u32,
+    column: encoding.encode_column(src, offset) as u32,
+    index: encoding.encode_offset(src, offset) as u32,
+    byte_offset: encoding::byte_offset(src, offset) as u32,
+  }
+}
+
+pub(crate) enum MatcherType {
   Pattern(Pattern),
   Kind(KindMatcher),
-  RuleCore(ast_grep_config::RuleCore),
+  Rule(Box<WasmRule>, Option<Rc<str>>),
+  Query(QueryMatcher, Option<Rc<str>>),
+}
+
+impl MatcherType {
+  pub(crate) fn match_node<'r>(&self, node: Node<'r, WasmDoc>) -> Option<NodeMatch<'r, WasmDoc>> {
+    match self {
+      MatcherType::Pattern(p) => p.match_node(node),
+      MatcherType::Kind(k) => k.match_node(node),
+      MatcherType::Rule(r, _) => r.match_node(node),
+      MatcherType::Query(q, _) => q.match_node(node),
+    }
+  }
+
+  /// The rule config's `primary` meta variable, without `$`.
+  pub(crate) fn primary(&self) -> Option<Rc<str>> {
+    match self {
+      MatcherType::Rule(_, p) | MatcherType::Query(_, p) => p.clone(),
+      _ => None,
+    }
+  }
 }
 
 /// Position and info methods
@@ -132,21 +696,48 @@ enum MatcherType {
 impl SgNode {
   #[wasm_bindgen(js_name = range)]
   pub fn range(&self) -> Range {
-    let byte_range = self.inner.range();
-    let start_pos = self.inner.start_pos();
-    let end_pos = self.inner.end_pos();
-    Range {
-      start: Pos {
-        line: start_pos.line() as u32,
-        column: start_pos.column(self.inner.get_node()) as u32,
-        index: byte_range.start as u32,
-      },
-      end: Pos {
-        line: end_pos.line() as u32,
-        column: end_pos.column(self.inner.get_node()) as u32,
-        index: byte_range.end as u32,
-      },
+    node_range(&self.inner)
+  }
+
+  /// The node's range expanded to whole lines, from the start of its first line
+  /// to the end of its last line (excluding the line break).
+  #[wasm_bindgen(js_name = fullLineRange)]
+  pub fn full_line_range(&self) -> Range {
+    let doc = self.inner.get_doc();
+    let source = doc.get_source();
+    let range = self.inner.range();
+    let src = source.chars();
+    let line_start = source.line_start(range.start);
+    let line_end = source.line_end(range.end);
+    let range = Range {
+      start: pos(
+        src,
+        self.inner.start_pos().line(),
+        line_start,
+        doc.encoding(),
+      ),
+      end: pos(src, self.inner.end_pos().line(), line_end, doc.encoding()),
+    };
+    original_range(self.inner.get_doc(), range)
+  }
+
+  /// The node's text with formatting normalized, for comparing code regardless of layout.
+  /// The text is rebuilt from the leaf tokens with one spacing rule, whatever the source's
+  /// whitespace, see `normalized_tokens`. Strings are kept verbatim.
+  #[wasm_bindgen(js_name = normalizedText)]
+  pub fn normalized_text(&self) -> String {
+    let mut tokens = vec![];
+    normalized_tokens(&self.inner, &mut tokens);
+    let mut text = String::new();
+    let mut prev: Option<&Token> = None;
+    for token in &tokens {
+      if let Some(prev) = prev {
+        text.push_str(token_spacing(prev, token));
+      }
+      text.push_str(&token.text);
+      prev = Some(token);
     }
+    text
   }
 
   #[wasm_bindgen(js_name = isLeaf)]
@@ -176,67 +767,390 @@ impl SgNode {
     self.inner.text().to_string()
   }
 
+  /// The `[offset, length]` of the node's text in the source it was parsed from, in UTF-16
+  /// code units whatever the position encoding, so `src.substr(offset, length)` is `text()`
+  /// without copying the text out of the module.
+  #[wasm_bindgen(js_name = textSpan)]
+  pub fn text_span(&self) -> Vec<u32> {
+    let src = self.inner.get_doc().get_source();
+    src.utf16_span(self.inner.range()).to_vec()
+  }
+
+  /// Generalize the node's text into a pattern by replacing every node of the given
+  /// kinds with a fresh meta variable, numbered `$V1`, `$V2`, ... in document order.
+  /// Nodes inside a replaced node are not replaced separately.
+  #[wasm_bindgen(js_name = toPatternWithHoles)]
+  pub fn to_pattern_with_holes(&self, kinds_to_abstract: Vec<String>) -> String {
+    let source = self.inner.get_doc().get_source();
+    let range = self.inner.range();
+    let mut pattern = String::new();
+    let mut start = range.start;
+    let mut count = 0;
+    for node in self.inner.dfs() {
+      let hole = node.range();
+      if hole.start < start || !kinds_to_abstract.iter().any(|k| k == &*node.kind()) {
+        continue;
+      }
+      count += 1;
+      pattern.extend(source.get_range(start..hole.start));
+      pattern.push_str(&format!("$V{count}"));
+      start = hole.end;
+    }
+    pattern.extend(source.get_range(start..range.end));
+    pattern
+  }
+
+  /// The node's text on a single line for result lists. Line breaks and the
+  /// indentation around them become one space, and text longer than `max_len`
+  /// characters is cut to `max_len` characters ending with `…`.
+  pub fn snippet(&self, max_len: u32) -> String {
+    let text = self.inner.text();
+    let single_line = text
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty())
+      .collect::<Vec<_>>()
+      .join(" ");
+    let max_len = max_len as usize;
+    if single_line.chars().count() <= max_len {
+      return single_line;
+    }
+    let mut snippet: String = single_line
+      .chars()
+      .take(max_len.saturating_sub(1))
+      .collect();
+    if max_len > 0 {
+      snippet.push('…');
+    }
+    snippet
+  }
+
   pub fn id(&self) -> u32 {
     self.inner.node_id() as u32
   }
+
+  /// Returns the hex-encoded SHA-256 hash of the node's source text (UTF-8).
+  /// Only the text is hashed, so identical code hashes equally across roots
+  /// regardless of its kind or position.
+  #[wasm_bindgen(js_name = sourceHash)]
+  pub fn source_hash(&self) -> String {
+    use sha2::{Digest, Sha256};
+    crate::hex(&Sha256::digest(self.text().as_bytes()))
+  }
+
+  /// Returns the hex-encoded SHA-256 fingerprint of the node's structure, the same for
+  /// subtrees that differ only in layout and comments, and by default in the text of
+  /// identifiers and literals. See `findClones`.
+  #[wasm_bindgen(js_name = structuralHash)]
+  pub fn structural_hash(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "FingerprintOptions")] options: JsValue,
+  ) -> Result<String, JsError> {
+    let options: Option<FingerprintOptions> = serde_wasm_bindgen::from_value(options)?;
+    let node = self.inner.get_node();
+    let prints = clones::fingerprints(node, &options.unwrap_or_default());
+    Ok(prints[&node.node_id()].hex())
+  }
+
+  /// Returns a human-readable path from the root to this node,
+  /// e.g. `program[0].body[1]`. Each step uses the node's field name in its parent,
+  /// indexed among children sharing that field if there are several.
+  /// Nodes without a field fall back to their child index.
+  #[wasm_bindgen(js_name = fieldPath)]
+  pub fn field_path(&self) -> String {
+    let mut node = self.inner.get_inner_node().0.clone();
+    let mut steps = vec![];
+    while let Some(parent) = node.parent() {
+      steps.push(path_step(&parent, &node));
+      node = parent;
+    }
+    steps.push(String::from(node.type_()));
+    steps.reverse();
+    steps.concat()
+  }
+
+  /// Returns the indices of the nodes from the root of the tree to this node, each
+  /// among all children of its parent including anonymous ones, e.g. `[0, 3, 1]`.
+  /// Unlike a node, the path can be kept and sent anywhere, and resolved again by
+  /// `SgRoot.nodeAtPath` for the same source, also parsed again like by `SgRoot.deserialize`.
+  #[wasm_bindgen(unchecked_return_type = "number[]")]
+  pub fn path(&self) -> Result<JsValue, JsError> {
+    let mut node = self.inner.get_inner_node().0.clone();
+    let mut steps = vec![];
+    while let Some(parent) = node.parent() {
+      let id = node.id();
+      let index = child_fields(&parent).iter().position(|(i, _)| *i == id);
+      steps.push(index.unwrap_or_default() as u32);
+      node = parent;
+    }
+    steps.reverse();
+    serde_wasm_bindgen::to_value(&steps).map_err(|e| JsError::new(&e.to_string()))
+  }
+
+  /// Returns the name of the field this node is in within its parent, e.g. `body`,
+  /// or `undefined` if it is in no field or is the root.
+  #[wasm_bindgen(js_name = fieldNameInParent)]
+  pub fn field_name_in_parent(&self) -> Option<String> {
+    let node = self.inner.get_inner_node().0.clone();
+    let parent = node.parent()?;
+    let id = node.id();
+    child_fields(&parent)
+      .into_iter()
+      .find(|(i, _)| *i == id)
+      .and_then(|(_, field)| field)
+  }
+}
+
+/// The ids of the children of `parent`, including anonymous ones, with their fields.
+fn child_fields(parent: &ts::SyntaxNode) -> Vec<(u32, Option<String>)> {
+  let cursor = parent.walk();
+  let mut children = vec![];
+  if cursor.goto_first_child() {
+    loop {
+      let field = cursor.current_field_name().map(String::from);
+      children.push((cursor.current_node().id(), field));
+      if !cursor.goto_next_sibling() {
+        break;
+      }
+    }
+  }
+  cursor.delete();
+  children
+}
+
+fn path_step(parent: &ts::SyntaxNode, child: &ts::SyntaxNode) -> String {
+  let children = child_fields(parent);
+  let id = child.id();
+  let Some(index) = children.iter().position(|(i, _)| *i == id) else {
+    return String::new();
+  };
+  let Some(field) = &children[index].1 else {
+    return format!("[{index}]");
+  };
+  let same_field: Vec<_> = children
+    .iter()
+    .filter(|(_, f)| f.as_ref() == Some(field))
+    .collect();
+  if same_field.len() == 1 {
+    return format!(".{field}");
+  }
+  let nth = same_field.iter().position(|(i, _)| *i == id).unwrap_or(0);
+  format!(".{field}[{nth}]")
+}
+
+/// A step of a path of `SgRoot.nodeAtPath` from a node to one of its children.
+enum PathStep {
+  /// the child at the index among all children
+  Index(u32),
+  /// the child in the field, or the nth if there are several
+  Field(String, Option<u32>),
+}
+
+/// The root kind and the steps of a path of `SgNode.fieldPath`, like `program[0].body[1]`.
+fn parse_field_path(path: &str) -> Result<(&str, Vec<PathStep>), JsError> {
+  let invalid = || JsError::new(&format!("Invalid field path `{path}`."));
+  let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+  let kind_end = path.find(|c| !is_name(c)).unwrap_or(path.len());
+  let (kind, mut rest) = path.split_at(kind_end);
+  // `[n]` at the start of `rest`, with its length
+  let index = |rest: &str| -> Option<(u32, usize)> {
+    let end = rest.strip_prefix('[')?.find(']')? + 1;
+    Some((rest[1..end].parse().ok()?, end + 1))
+  };
+  let mut steps = vec![];
+  while !rest.is_empty() {
+    if let Some((nth, len)) = index(rest) {
+      steps.push(PathStep::Index(nth));
+      rest = &rest[len..];
+      continue;
+    }
+    let field = rest.strip_prefix('.').ok_or_else(invalid)?;
+    let field_end = field.find(|c| !is_name(c)).unwrap_or(field.len());
+    if field_end == 0 {
+      return Err(invalid());
+    }
+    rest = &field[field_end..];
+    let nth = index(rest);
+    steps.push(PathStep::Field(
+      field[..field_end].to_string(),
+      nth.map(|n| n.0),
+    ));
+    rest = &rest[nth.map_or(0, |n| n.1)..];
+  }
+  Ok((kind, steps))
+}
+
+/// A token of `normalizedText`.
+struct Token {
+  text: String,
+  /// a named leaf like an identifier, as opposed to a keyword or punctuation
+  named: bool,
+  comment: bool,
+}
+
+/// If the node is a literal whose text must be kept as is, even though it has tokens.
+fn is_verbatim(kind: &str) -> bool {
+  ["string", "regex", "char", "heredoc"]
+    .iter()
+    .any(|k| kind.contains(k))
+}
+
+/// The leaf tokens of `node`, with strings and other literals as one token.
+fn normalized_tokens(node: &Node<'static, WasmDoc>, tokens: &mut Vec<Token>) {
+  let kind = node.kind();
+  if node.is_leaf() || (node.is_named() && is_verbatim(&kind)) {
+    let text = node.text().to_string();
+    if !text.is_empty() {
+      tokens.push(Token {
+        text,
+        named: node.is_named(),
+        comment: kind.contains("comment"),
+      });
+    }
+    return;
+  }
+  for child in node.children() {
+    normalized_tokens(&child, tokens);
+  }
+}
+
+/// The whitespace between two tokens. A line comment ends at a line break, and other
+/// tokens are separated by a single space, which is dropped after opening and before
+/// closing brackets, around `.`, before `,` and `;`, and before the brackets of calls
+/// and subscripts.
+fn token_spacing(prev: &Token, next: &Token) -> &'static str {
+  let (prev_text, next_text) = (prev.text.as_str(), next.text.as_str());
+  if prev.comment {
+    return "\n";
+  }
+  let opens = matches!(prev_text, "(" | "[" | "{");
+  let closes = matches!(next_text, ")" | "]" | "}" | "," | ";");
+  let member = matches!(prev_text, "." | "?.") || matches!(next_text, "." | "?.");
+  let applied = matches!(next_text, "(" | "[") && (prev.named || matches!(prev_text, ")" | "]"));
+  if opens || closes || member || applied {
+    ""
+  } else {
+    " "
+  }
 }
 
 /// Matcher methods
 #[wasm_bindgen]
 impl SgNode {
-  pub fn matches(&self, m: JsValue) -> Result<bool, JsError> {
-    Ok(match self.parse_matcher(m)? {
+  pub fn matches(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] m: JsValue,
+  ) -> Result<bool, JsError> {
+    Ok(match &*self.parse_matcher(m)? {
       MatcherType::Pattern(p) => self.inner.matches(p),
       MatcherType::Kind(k) => self.inner.matches(k),
-      MatcherType::RuleCore(r) => self.inner.matches(r),
+      MatcherType::Rule(r, _) => self.inner.matches(&**r),
+      MatcherType::Query(q, _) => q.match_node(self.inner.get_node().clone()).is_some(),
     })
   }
 
-  pub fn inside(&self, m: JsValue) -> Result<bool, JsError> {
-    Ok(match self.parse_matcher(m)? {
+  pub fn inside(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] m: JsValue,
+  ) -> Result<bool, JsError> {
+    Ok(match &*self.parse_matcher(m)? {
       MatcherType::Pattern(p) => self.inner.inside(p),
       MatcherType::Kind(k) => self.inner.inside(k),
-      MatcherType::RuleCore(r) => self.inner.inside(r),
+      MatcherType::Rule(r, _) => self.inner.inside(&**r),
+      MatcherType::Query(q, _) => self.inner.ancestors().any(|n| q.match_node(n).is_some()),
     })
   }
 
-  pub fn has(&self, m: JsValue) -> Result<bool, JsError> {
-    Ok(match self.parse_matcher(m)? {
+  pub fn has(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] m: JsValue,
+  ) -> Result<bool, JsError> {
+    Ok(match &*self.parse_matcher(m)? {
       MatcherType::Pattern(p) => self.inner.has(p),
       MatcherType::Kind(k) => self.inner.has(k),
-      MatcherType::RuleCore(r) => self.inner.has(r),
+      MatcherType::Rule(r, _) => self.inner.has(&**r),
+      MatcherType::Query(q, _) => self.inner.dfs().skip(1).any(|n| q.match_node(n).is_some()),
     })
   }
 
-  pub fn precedes(&self, m: JsValue) -> Result<bool, JsError> {
-    Ok(match self.parse_matcher(m)? {
+  pub fn precedes(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] m: JsValue,
+  ) -> Result<bool, JsError> {
+    Ok(match &*self.parse_matcher(m)? {
       MatcherType::Pattern(p) => self.inner.precedes(p),
       MatcherType::Kind(k) => self.inner.precedes(k),
-      MatcherType::RuleCore(r) => self.inner.precedes(r),
+      MatcherType::Rule(r, _) => self.inner.precedes(&**r),
+      MatcherType::Query(q, _) => self.inner.next_all().any(|n| q.match_node(n).is_some()),
     })
   }
 
-  pub fn follows(&self, m: JsValue) -> Result<bool, JsError> {
-    Ok(match self.parse_matcher(m)? {
+  pub fn follows(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] m: JsValue,
+  ) -> Result<bool, JsError> {
+    Ok(match &*self.parse_matcher(m)? {
       MatcherType::Pattern(p) => self.inner.follows(p),
       MatcherType::Kind(k) => self.inner.follows(k),
-      MatcherType::RuleCore(r) => self.inner.follows(r),
+      MatcherType::Rule(r, _) => self.inner.follows(&**r),
+      MatcherType::Query(q, _) => self.inner.prev_all().any(|n| q.match_node(n).is_some()),
     })
   }
 
+  /// True if a comment attached right before the node matches the regex,
+  /// same as the `hasLeadingComment` rule.
+  #[wasm_bindgen(js_name = hasLeadingComment)]
+  pub fn has_leading_comment(&self, regex: String) -> Result<bool, JsError> {
+    let rule = serde_json::json!({ "hasLeadingComment": regex });
+    let rule: SerializableRule = serde_json::from_value(rule)?;
+    let env = global_utils::env(*self.inner.lang());
+    let rule = env.deserialize_rule(rule).map_err(|e| error_chain(&e))?;
+    Ok(self.inner.matches(rule))
+  }
+
+  /// True if a comment inside the node matches the regex, same as the `hasComment` rule.
+  #[wasm_bindgen(js_name = hasComment)]
+  pub fn has_comment(&self, regex: String) -> Result<bool, JsError> {
+    let rule = serde_json::json!({ "hasComment": regex });
+    let rule: SerializableRule = serde_json::from_value(rule)?;
+    let env = global_utils::env(*self.inner.lang());
+    let rule = env.deserialize_rule(rule).map_err(|e| error_chain(&e))?;
+    Ok(self.inner.matches(rule))
+  }
+
+  /// Returns the match in the shape of a match of the CLI's `run --json` output.
+  /// `options.file` is reported as its `file`, and `options.rewrite` is a fix template
+  /// reported as its `replacement`, like `--rewrite`.
+  #[wasm_bindgen(js_name = matchToJSON)]
+  pub fn match_to_json(&self, options: JsValue) -> Result<JsValue, JsError> {
+    let options: Option<json_output::MatchJsonOptions> = serde_wasm_bindgen::from_value(options)?;
+    json_output::match_to_json(&self.inner, options.unwrap_or_default())
+  }
+
+  /// Returns the node captured by a meta variable. `m` is the name without `$`,
+  /// though a leading `$` is accepted too, e.g. `A` or `$A` for `$A`.
   #[wasm_bindgen(js_name = getMatch)]
   pub fn get_match(&self, m: String) -> Option<SgNode> {
-    let node = self.inner.get_env().get_match(&m).cloned()?;
+    let node = self.inner.get_env().get_match(var_name(&m)).cloned()?;
     let nm = NodeMatch::from(node);
     Some(self.make_node(unsafe { Self::cast_match(nm) }))
   }
 
+  /// Returns the node bound to the meta variable named by the rule config's `primary` field.
+  /// It is often narrower than the whole matched node, e.g. a name inside a declaration.
+  #[wasm_bindgen(js_name = primaryCapture)]
+  pub fn primary_capture(&self) -> Option<SgNode> {
+    let name = self.primary.as_deref()?;
+    self.get_match(name.to_string())
+  }
+
+  /// Returns the nodes captured by a multi meta variable, e.g. `ARGS` or `$$$ARGS`.
   #[wasm_bindgen(js_name = getMultipleMatches)]
   pub fn get_multiple_matches(&self, m: String) -> Vec<SgNode> {
     self
       .inner
       .get_env()
-      .get_multiple_matches(&m)
+      .get_multiple_matches(var_name(&m))
       .into_iter()
       .map(|node| {
         let nm = NodeMatch::from(node);
@@ -247,9 +1161,31 @@ impl SgNode {
 
   #[wasm_bindgen(js_name = getTransformed)]
   pub fn get_transformed(&self, m: String) -> Option<String> {
-    let bytes = self.inner.get_env().get_transformed(&m)?;
+    let bytes = self.inner.get_env().get_transformed(var_name(&m))?;
     Some(Wrapper::encode_bytes(bytes).to_string())
   }
+
+  /// Returns the names, without `$`, of the meta variables captured by the match,
+  /// including multi meta variables and transformed variables.
+  #[wasm_bindgen(js_name = matchedVariables)]
+  pub fn matched_variables(&self) -> Vec<String> {
+    let mut names = vec![];
+    for var in self.inner.get_env().get_matched_variables() {
+      let name = match var {
+        MetaVariable::Capture(name, _) | MetaVariable::MultiCapture(name) => name,
+        _ => continue,
+      };
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+    names
+  }
+}
+
+/// Strip the `$` sigils, so `$A` and `$$$ARGS` name the variables `A` and `ARGS`.
+fn var_name(name: &str) -> &str {
+  name.trim_start_matches('$')
 }
 
 /// Tree traversal methods
@@ -279,10 +1215,31 @@ impl SgNode {
     Some(self.make_node(unsafe { Self::cast_match(nm) }))
   }
 
-  pub fn ancestors(&self) -> Vec<SgNode> {
+  /// Returns the ancestors from parent to root.
+  /// `filter` is an optional matcher, e.g. a pattern, kind id or rule config, that the
+  /// returned nodes must match. Matched nodes carry their captures like `find` results.
+  pub fn ancestors(&self, filter: JsValue) -> Result<Vec<SgNode>, JsError> {
+    Ok(self.filtered(self.inner.ancestors(), filter)?.collect())
+  }
+
+  /// Returns this node and its descendants in pre-order, optionally filtered as `ancestors`.
+  pub fn dfs(&self, filter: JsValue) -> Result<Vec<SgNode>, JsError> {
+    Ok(self.filtered(self.inner.dfs(), filter)?.collect())
+  }
+
+  /// Returns all descendants of the given kind at any depth, in document order.
+  /// Returns an empty array if the language has no such kind.
+  #[wasm_bindgen(js_name = descendantsOfKind)]
+  pub fn descendants_of_kind(&self, kind: String) -> Vec<SgNode> {
+    let kind_id = self.inner.lang().kind_to_id(&kind);
+    if kind_id == 0 {
+      return vec![];
+    }
     self
       .inner
-      .ancestors()
+      .dfs()
+      .skip(1)
+      .filter(|n| n.kind_id() == kind_id)
       .map(|n| {
         let nm = NodeMatch::from(n);
         self.make_node(unsafe { Self::cast_match(nm) })
@@ -290,68 +1247,323 @@ impl SgNode {
       .collect()
   }
 
+  /// Returns the kinds of this node and all its ancestors, node first and root last.
+  #[wasm_bindgen(js_name = kindAncestry)]
+  pub fn kind_ancestry(&self) -> Vec<String> {
+    let ancestors = self.inner.ancestors().map(|n| n.kind().to_string());
+    std::iter::once(self.kind()).chain(ancestors).collect()
+  }
+
+  /// True if the node is a string literal or inside one.
+  /// Code in interpolations, e.g. `${x}` in a template string, is not inside the string.
+  /// Throws for languages without known string kinds, see the README.
+  #[wasm_bindgen(js_name = isInsideString)]
+  pub fn is_inside_string(&self) -> Result<bool, JsError> {
+    self.is_inside_kind(TextKind::String)
+  }
+
+  /// True if the node is a comment or inside one.
+  /// Throws for languages without known comment kinds, see the README.
+  #[wasm_bindgen(js_name = isInsideComment)]
+  pub fn is_inside_comment(&self) -> Result<bool, JsError> {
+    self.is_inside_kind(TextKind::Comment)
+  }
+
+  /// Returns the next sibling, or with `filter` the first following sibling matching it.
   #[wasm_bindgen(js_name = next)]
-  pub fn next_node(&self) -> Option<SgNode> {
-    let node = self.inner.next()?;
-    let nm = NodeMatch::from(node);
-    Some(self.make_node(unsafe { Self::cast_match(nm) }))
+  pub fn next_node(&self, filter: JsValue) -> Result<Option<SgNode>, JsError> {
+    Ok(self.filtered(self.inner.next_all(), filter)?.next())
   }
 
+  /// Returns the following siblings, optionally filtered as `ancestors`.
   #[wasm_bindgen(js_name = nextAll)]
-  pub fn next_all(&self) -> Vec<SgNode> {
-    self
-      .inner
-      .next_all()
-      .map(|n| {
-        let nm = NodeMatch::from(n);
-        self.make_node(unsafe { Self::cast_match(nm) })
-      })
-      .collect()
+  pub fn next_all(&self, filter: JsValue) -> Result<Vec<SgNode>, JsError> {
+    Ok(self.filtered(self.inner.next_all(), filter)?.collect())
   }
 
+  /// Returns the previous sibling, or with `filter` the nearest preceding sibling matching it.
   #[wasm_bindgen(js_name = prev)]
-  pub fn prev_node(&self) -> Option<SgNode> {
-    let node = self.inner.prev()?;
-    let nm = NodeMatch::from(node);
-    Some(self.make_node(unsafe { Self::cast_match(nm) }))
+  pub fn prev_node(&self, filter: JsValue) -> Result<Option<SgNode>, JsError> {
+    Ok(self.filtered(self.inner.prev_all(), filter)?.next())
   }
 
+  /// Returns the preceding siblings, nearest first, optionally filtered as `ancestors`.
   #[wasm_bindgen(js_name = prevAll)]
-  pub fn prev_all(&self) -> Vec<SgNode> {
-    self
-      .inner
-      .prev_all()
-      .map(|n| {
-        let nm = NodeMatch::from(n);
-        self.make_node(unsafe { Self::cast_match(nm) })
-      })
-      .collect()
+  pub fn prev_all(&self, filter: JsValue) -> Result<Vec<SgNode>, JsError> {
+    Ok(self.filtered(self.inner.prev_all(), filter)?.collect())
   }
 
-  pub fn find(&self, matcher: JsValue) -> Result<Option<SgNode>, JsError> {
-    let node_match = match self.parse_matcher(matcher)? {
-      MatcherType::Pattern(p) => self.inner.find(p),
-      MatcherType::Kind(k) => self.inner.find(k),
-      MatcherType::RuleCore(r) => self.inner.find(r),
+  /// `options` limits the search as in `findAll`.
+  pub fn find(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] matcher: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "FindOptions")] options: Option<js_sys::Object>,
+  ) -> Result<Option<SgNode>, JsError> {
+    let options = FindOptions::from_js(options.into())?;
+    let matcher = self.parse_limited(matcher, &options)?;
+    let primary = matcher.primary();
+    let node_match = if options.is_limited() {
+      let (nodes, _) = self.search_nodes(&options)?;
+      let mut budget = options.budget();
+      let found = nodes
+        .take_while(|_| budget.visit(0).is_continue())
+        .find_map(|n| matcher.match_node(n));
+      budget.finish().map_err(|e| JsError::new(&e))?;
+      found
+    } else {
+      match &*matcher {
+        MatcherType::Pattern(p) => self.inner.find(p),
+        MatcherType::Kind(k) => self.inner.find(k),
+        MatcherType::Rule(r, _) => self.inner.find(&**r),
+        MatcherType::Query(q, _) => self.inner.dfs().find_map(|n| q.match_node(n)),
+      }
     };
-    Ok(node_match.map(|nm| self.make_node(unsafe { Self::cast_match(nm) })))
+    Ok(node_match.map(|nm| {
+      let node = self.make_node(unsafe { Self::cast_match(nm) });
+      node.with_primary(primary)
+    }))
   }
 
+  /// `options` is an `onProgress` callback or `FindOptions`.
+  /// `onProgress(processed, total)` is called periodically during the traversal
+  /// and once when it is done. Both are character offsets relative to this node.
+  /// The callback runs synchronously and cannot pause the search. Throwing in it makes
+  /// `findAll` throw, and so does exceeding `maxNodes` or `timeoutMs`, or aborting
+  /// `signal`. The search stops with the matches found once there are `maxMatches`.
   #[wasm_bindgen(js_name = findAll)]
-  pub fn find_all(&self, matcher: JsValue) -> Result<Vec<SgNode>, JsError> {
-    let matches: Vec<_> = match self.parse_matcher(matcher)? {
-      MatcherType::Pattern(p) => self.inner.find_all(p).collect(),
-      MatcherType::Kind(k) => self.inner.find_all(k).collect(),
-      MatcherType::RuleCore(r) => self.inner.find_all(r).collect(),
+  pub fn find_all(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] matcher: JsValue,
+    #[wasm_bindgen(
+      unchecked_param_type = "((processed: number, total: number) => void) | FindOptions"
+    )]
+    options: Option<js_sys::Object>,
+  ) -> Result<Vec<SgNode>, JsError> {
+    self.find_all_with(matcher, FindOptions::from_js(options.into())?)
+  }
+
+  /// `findAll` with the `range` option, e.g. for the visible lines of an editor.
+  /// Returns the matches overlapping `range`, including ancestors spanning it.
+  #[wasm_bindgen(js_name = findInRange)]
+  pub fn find_in_range(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] matcher: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "SearchRange")] range: JsValue,
+  ) -> Result<Vec<SgNode>, JsError> {
+    let options = FindOptions {
+      range: Some(serde_wasm_bindgen::from_value(range)?),
+      ..FindOptions::default()
+    };
+    self.find_all_with(matcher, options)
+  }
+}
+
+/// Nodes visited by a search, see `SgNode::search_nodes`.
+type SearchNodes<'a> = Box<dyn Iterator<Item = Node<'static, WasmDoc>> + 'a>;
+
+impl SgNode {
+  fn find_all_with(&self, matcher: JsValue, options: FindOptions) -> Result<Vec<SgNode>, JsError> {
+    let on_progress = progress::callback(&options.on_progress).map_err(|e| JsError::new(&e))?;
+    let matcher = self.parse_limited(matcher, &options)?;
+    let primary = matcher.primary();
+    let plain = on_progress.is_none() && !options.is_limited();
+    let matches: Vec<_> = match (&*matcher, plain) {
+      (MatcherType::Pattern(p), true) => self.inner.find_all(p).collect(),
+      (MatcherType::Kind(k), true) => self.inner.find_all(k).collect(),
+      (MatcherType::Rule(r, _), true) => self.inner.find_all(&**r).collect(),
+      (MatcherType::Query(q, _), true) => {
+        self.inner.dfs().filter_map(|n| q.match_node(n)).collect()
+      }
+      // a plain traversal so every node is counted, the result is the same
+      (matcher, false) => {
+        let (nodes, range) = self.search_nodes(&options)?;
+        let mut progress = on_progress.map(|f| Progress::new(f, range));
+        let mut budget = options.budget();
+        let mut matches = vec![];
+        for n in nodes {
+          if let Some(progress) = &mut progress {
+            progress.visit(&n);
+          }
+          if budget.visit(matches.len()).is_break() {
+            break;
+          }
+          matches.extend(matcher.match_node(n));
+        }
+        if let Some(progress) = progress {
+          progress.finish().map_err(|e| JsError::new(&e))?;
+        }
+        budget.finish().map_err(|e| JsError::new(&e))?;
+        matches
+      }
     };
     Ok(
       matches
         .into_iter()
-        .map(|nm| self.make_node(unsafe { Self::cast_match(nm) }))
+        .map(|nm| {
+          let node = self.make_node(unsafe { Self::cast_match(nm) });
+          node.with_primary(primary.clone())
+        })
         .collect(),
     )
   }
 
+  /// The nodes a search with `options` visits in pre-order, and the range they cover:
+  /// this node's subtree, or only its nodes overlapping the `range` option.
+  fn search_nodes(
+    &self,
+    options: &FindOptions,
+  ) -> Result<(SearchNodes<'_>, std::ops::Range<usize>), JsError> {
+    let node = self.inner.get_node().clone();
+    let Some(range) = &options.range else {
+      return Ok((Box::new(self.inner.dfs()), node.range()));
+    };
+    let doc = node.get_doc();
+    let range = range.resolve(doc.get_source().chars(), doc.encoding());
+    let range = range.map_err(|e| JsError::new(&e))?;
+    Ok((
+      Box::new(search_range::overlapping(node, range.clone())),
+      range,
+    ))
+  }
+}
+
+#[wasm_bindgen]
+impl SgNode {
+  /// Like `findAll`, but returns an iterator that searches only as far as the next
+  /// match is requested, so matches are never all materialized at once.
+  /// The iterator is also async iterable: `for await` yields control to the event
+  /// loop between batches of matches.
+  #[wasm_bindgen(js_name = findAllIter, unchecked_return_type = "MatchIterator")]
+  pub fn find_all_iter(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] matcher: JsValue,
+  ) -> Result<JsValue, JsError> {
+    let matcher = self.parse_matcher(matcher)?;
+    let node = (*self.inner).clone();
+    let iter = MatchIterator {
+      root: self.make_node(NodeMatch::from(node.clone())),
+      root_id: node.node_id(),
+      next: Some(node),
+      matcher,
+    };
+    Ok(make_iterable(iter.into()))
+  }
+
+  /// Explain why `pattern`, a pattern string or object, does or does not match this node
+  /// itself. `options` can set `selector` and `strictness` as for `pattern`.
+  /// Returns `{ matched, skipped }` plus, if it does not match, the first divergence as
+  /// `{ reason, message, expected?, node }`. `skipped` are the nodes the strictness
+  /// skipped before matching stopped.
+  #[wasm_bindgen(js_name = debugMatch)]
+  pub fn debug_match(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "string | PatternObject")] pattern: JsValue,
+    options: JsValue,
+  ) -> Result<js_sys::Object, JsError> {
+    let options: Option<DebugMatchOptions> = serde_wasm_bindgen::from_value(options)?;
+    let options = options.unwrap_or_default();
+    let mut pattern = PatternObject::from_js(pattern)?;
+    pattern.merge(options.selector, options.strictness)?;
+    let lang = *self.inner.lang();
+    let pattern = pattern.compile(lang)?;
+    let explanation = pattern.explain(self.inner.get_node().clone());
+    let skipped: js_sys::Array = explanation
+      .skipped
+      .into_iter()
+      .map(|n| JsValue::from(self.make_node(NodeMatch::from(n))))
+      .collect();
+    let mut fields = vec![
+      ("matched", JsValue::from(explanation.matched)),
+      ("skipped", skipped.into()),
+    ];
+    if let Some(mismatch) = explanation.mismatch {
+      let divergence = debug_match::describe(mismatch, &lang);
+      fields.push(("reason", divergence.reason.into()));
+      fields.push(("message", divergence.message.into()));
+      if let Some(expected) = divergence.expected {
+        fields.push(("expected", expected.into()));
+      }
+      let node = self.make_node(NodeMatch::from(divergence.node));
+      fields.push(("node", node.into()));
+    }
+    let result = js_sys::Object::new();
+    for (key, value) in fields {
+      js_sys::Reflect::set(&result, &key.into(), &value)
+        .map_err(|_| JsError::new("Failed to create match explanation"))?;
+    }
+    Ok(result)
+  }
+
+  /// Report dataflows from `sources` to `sinks` within each function body.
+  /// `rule` is `{ sources, sinks, sanitizers? }`, each an array of matchers.
+  /// A sink with a `primary` meta variable only checks the captured node.
+  /// Returns an array of `{ source, sink, path }` in document order of the sinks,
+  /// where `path` lists the variables the tainted value was assigned to.
+  #[wasm_bindgen(js_name = findTaintFlows)]
+  pub fn find_taint_flows(&self, rule: JsValue) -> Result<js_sys::Array, JsError> {
+    let matchers = |key: &str, required: bool| -> Result<Vec<Rc<MatcherType>>, JsError> {
+      let value = js_sys::Reflect::get(&rule, &key.into())
+        .map_err(|_| JsError::new("Taint rule must be an object"))?;
+      if value.is_undefined() && !required {
+        return Ok(vec![]);
+      }
+      if !js_sys::Array::is_array(&value) || js_sys::Array::from(&value).length() == 0 {
+        return Err(JsError::new(&format!(
+          "Taint rule `{key}` must be a non-empty array of matchers"
+        )));
+      }
+      js_sys::Array::from(&value)
+        .iter()
+        .map(|m| self.parse_matcher(m))
+        .collect()
+    };
+    let spec = TaintSpec {
+      sources: matchers("sources", true)?,
+      sinks: matchers("sinks", true)?,
+      sanitizers: matchers("sanitizers", false)?,
+    };
+    let result = js_sys::Array::new();
+    for flow in taint::find_flows(&self.inner, &spec) {
+      let source = self.make_node(NodeMatch::from(flow.source));
+      let sink = self.make_node(flow.sink).with_primary(flow.primary);
+      let path: js_sys::Array = flow.path.into_iter().map(JsValue::from).collect();
+      let entry = js_sys::Object::new();
+      for (key, value) in [
+        ("source", JsValue::from(source)),
+        ("sink", sink.into()),
+        ("path", path.into()),
+      ] {
+        js_sys::Reflect::set(&entry, &key.into(), &value)
+          .map_err(|_| JsError::new("Failed to create taint flow"))?;
+      }
+      result.push(&entry);
+    }
+    Ok(result)
+  }
+
+  /// The identifier declaring the variable this identifier refers to, e.g. the name
+  /// in its `const` declaration, its parameter or its import, found by the scoping
+  /// rules of common languages. `null` for globals and nodes that are not variables.
+  #[wasm_bindgen(js_name = goToDefinition)]
+  pub fn go_to_definition(&self) -> Option<SgNode> {
+    let root = self.tree_root();
+    let definition = Scopes::new(&root).definition(&self.inner)?;
+    Some(self.make_node(NodeMatch::from(definition)))
+  }
+
+  /// The identifiers in the file that refer to the same variable as this one,
+  /// including its declarations, in document order. Names of undeclared globals
+  /// refer to the same global. Empty for nodes that are not variables.
+  #[wasm_bindgen(js_name = findReferences)]
+  pub fn find_references(&self) -> Vec<SgNode> {
+    let root = self.tree_root();
+    Scopes::new(&root)
+      .references(&self.inner)
+      .into_iter()
+      .map(|n| self.make_node(NodeMatch::from(n)))
+      .collect()
+  }
+
   #[wasm_bindgen(js_name = field)]
   pub fn field_node(&self, name: String) -> Option<SgNode> {
     let node = self.inner.field(&name)?;
@@ -370,23 +1582,131 @@ impl SgNode {
       })
       .collect()
   }
+
+  /// Returns the names of the fields that have children in this node,
+  /// each once, in the order they first appear.
+  #[wasm_bindgen(js_name = fieldNames)]
+  pub fn field_names(&self) -> Vec<String> {
+    let cursor = self.inner.get_inner_node().0.walk();
+    let mut names: Vec<String> = vec![];
+    if cursor.goto_first_child() {
+      loop {
+        if let Some(name) = cursor.current_field_name().map(String::from) {
+          if !names.contains(&name) {
+            names.push(name);
+          }
+        }
+        if !cursor.goto_next_sibling() {
+          break;
+        }
+      }
+    }
+    cursor.delete();
+    names
+  }
 }
 
 /// Edit methods
 #[wasm_bindgen]
 impl SgNode {
   pub fn replace(&self, text: String) -> WasmEdit {
-    let range = self.inner.range();
-    WasmEdit {
-      start_pos: range.start as u32,
-      end_pos: range.end as u32,
-      inserted_text: text,
-    }
+    self.make_edit(self.inner.range(), text)
+  }
+
+  /// Creates an edit removing this node from the list it is an item of, like an argument,
+  /// an import or an object field, together with one separator, so that no dangling `,`
+  /// or `;` is left. A trailing separator of the list is kept.
+  #[wasm_bindgen(js_name = removeFromList)]
+  pub fn remove_from_list(&self) -> Result<WasmEdit, JsError> {
+    let range = list_edit::remove(&self.inner).map_err(|e| JsError::new(&e))?;
+    Ok(self.make_edit(range, String::new()))
+  }
+
+  /// Creates an edit inserting `text` as the `nth` item of this list node, e.g. the
+  /// arguments of a call. The item is separated like the existing ones, with the same
+  /// separator, spacing and line breaks, and `nth` equal to the count of items appends it.
+  #[wasm_bindgen(js_name = insertIntoList)]
+  pub fn insert_into_list(&self, nth: u32, text: String) -> Result<WasmEdit, JsError> {
+    let (offset, text) =
+      list_edit::insert(&self.inner, nth as usize, &text).map_err(|e| JsError::new(&e))?;
+    Ok(self.make_edit(offset..offset, text))
+  }
+
+  /// Replace every match of `matcher` in this node with `template`, which interpolates
+  /// meta variables like a rule's `fix`, including the variables of a rule config's
+  /// `transform`. Matches overlapping an earlier one are skipped.
+  /// Returns the new source of the whole file and the applied edits as
+  /// `{ code, applied, edits }`. `options` are the same as for the top level `fix`.
+  #[wasm_bindgen(js_name = applyFix)]
+  pub fn apply_fix(
+    &self,
+    #[wasm_bindgen(unchecked_param_type = "Matcher")] matcher: JsValue,
+    template: String,
+    options: JsValue,
+  ) -> Result<JsValue, JsError> {
+    let options: Option<scan::FixOptions> = serde_wasm_bindgen::from_value(options)?;
+    let lang = *self.inner.lang();
+    // a rule config compiles the template as its `fix`, so it knows the transforms
+    let is_config = matcher.is_object() && !compiled::is_compiled(&matcher);
+    let matcher = if is_config {
+      let config: WasmConfig = serde_wasm_bindgen::from_value(matcher)?;
+      if config.query.is_some() {
+        return Err(JsError::new("`query` is not supported by `applyFix`."));
+      }
+      let rule = config.parse_with_fix(lang, Some(&template))?;
+      Rc::new(MatcherType::Rule(Box::new(rule), None))
+    } else {
+      self.parse_matcher(matcher)?
+    };
+    let parsed;
+    let fixer = match &*matcher {
+      MatcherType::Rule(r, _) if is_config => r.fixer().expect("fix is set"),
+      _ => {
+        parsed = Fixer::from_str(&template, &lang).map_err(|e| error_chain(&e))?;
+        &parsed
+      }
+    };
+    let edits: Vec<_> = match &*matcher {
+      MatcherType::Pattern(p) => self
+        .inner
+        .find_all(p)
+        .map(|nm| nm.make_edit(p, fixer))
+        .collect(),
+      MatcherType::Kind(k) => self
+        .inner
+        .find_all(k)
+        .map(|nm| nm.make_edit(k, fixer))
+        .collect(),
+      MatcherType::Rule(r, _) => self
+        .inner
+        .find_all(&**r)
+        .map(|nm| nm.make_edit(&**r, fixer))
+        .collect(),
+      MatcherType::Query(..) => {
+        return Err(JsError::new("`query` is not supported by `applyFix`."))
+      }
+    };
+    let src = self.inner.get_doc().get_source().text();
+    let result = scan::fix_result(
+      &src,
+      self._root.as_ref(),
+      edits,
+      &options.unwrap_or_default(),
+    )?;
+    serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
   }
 
+  /// Applies edits to this node's text and returns the new source.
+  /// The tree is left untouched, so existing nodes still describe the original source.
   #[wasm_bindgen(js_name = commitEdits)]
   pub fn commit_edits(&self, edits: JsValue) -> Result<String, JsError> {
     let mut edits: Vec<WasmEdit> = serde_wasm_bindgen::from_value(edits)?;
+    let doc = self.inner.get_doc();
+    let src = doc.get_source().chars();
+    for edit in &mut edits {
+      edit.start_pos = doc.encoding().decode_offset(src, edit.start_pos as usize) as u32;
+      edit.end_pos = doc.encoding().decode_offset(src, edit.end_pos as usize) as u32;
+    }
     edits.sort_by_key(|edit| edit.start_pos);
     let mut new_content = Vec::new();
     let text = self.text();
@@ -0,0 +1,122 @@
+//! Binary snapshots of parsed trees for `SgRoot.serialize` and `SgRoot.deserialize`.
+//!
+//! web-tree-sitter can only create trees by parsing, so a snapshot cannot be loaded as a
+//! tree. It records what the tree was parsed from instead: the language, its grammar's ABI
+//! version, a SHA-256 of the source and the nodes in pre-order, each as LEB128 numbers of
+//! its kind id, its child count, its start after the previous node's start and its length.
+//! Restoring parses the source again and checks the result is the tree of the snapshot,
+//! so a cache knows a stored result is stale when the source or the grammar changed.
+
+use crate::doc::WasmDoc;
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_core::{AstGrep, Doc, Node};
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::*;
+
+const MAGIC: &[u8; 4] = b"SGT\x01";
+
+pub fn serialize(root: &AstGrep<WasmDoc>, filename: &str) -> Vec<u8> {
+  let node = root.root();
+  let lang = node.lang();
+  let mut out = MAGIC.to_vec();
+  write_str(&mut out, &lang.name());
+  write_str(&mut out, filename);
+  write_uint(&mut out, lang.get_ts_language().abi_version() as u64);
+  out.extend(source_digest(node.get_doc()));
+  write_nodes(&mut out, &node);
+  out
+}
+
+/// The filename recorded in `bytes` and the tree of `src` parsed again, if it is the
+/// tree of the snapshot.
+pub fn deserialize(bytes: &[u8], src: String) -> Result<(AstGrep<WasmDoc>, String), JsError> {
+  let mut reader = Reader(bytes);
+  if reader.take(MAGIC.len())? != MAGIC {
+    return Err(JsError::new(
+      "The bytes are not a tree snapshot of this version.",
+    ));
+  }
+  let name = reader.read_str()?;
+  let filename = reader.read_str()?;
+  let abi = reader.read_uint()?;
+  let digest = reader.take(32)?;
+  let lang: WasmLang = name
+    .parse()
+    .map_err(|e: crate::wasm_lang::NotSupport| JsError::new(&e.to_string()))?;
+  if lang.get_ts_language().abi_version() as u64 != abi {
+    let msg = format!("The snapshot is of another grammar than the one registered as `{name}`.");
+    return Err(JsError::new(&msg));
+  }
+  if Sha256::digest(src.as_bytes()).as_slice() != digest {
+    return Err(JsError::new("The snapshot is of another source."));
+  }
+  let root = AstGrep::doc(WasmDoc::try_new(src, lang)?);
+  let mut nodes = vec![];
+  write_nodes(&mut nodes, &root.root());
+  if nodes != reader.0 {
+    let msg = format!("The tree of the snapshot is not the one `{name}` parses now.");
+    return Err(JsError::new(&msg));
+  }
+  Ok((root, filename))
+}
+
+fn source_digest(doc: &WasmDoc) -> [u8; 32] {
+  Sha256::digest(doc.get_source().text().as_bytes()).into()
+}
+
+fn write_nodes(out: &mut Vec<u8>, root: &Node<'_, WasmDoc>) {
+  let mut previous = 0;
+  for node in root.dfs() {
+    let range = node.range();
+    write_uint(out, node.kind_id() as u64);
+    write_uint(out, node.children().count() as u64);
+    write_uint(out, (range.start - previous) as u64);
+    write_uint(out, range.len() as u64);
+    previous = range.start;
+  }
+}
+
+fn write_uint(out: &mut Vec<u8>, mut n: u64) {
+  while n >= 0x80 {
+    out.push(n as u8 | 0x80);
+    n >>= 7;
+  }
+  out.push(n as u8);
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+  write_uint(out, s.len() as u64);
+  out.extend(s.as_bytes());
+}
+
+struct Reader<'b>(&'b [u8]);
+
+impl<'b> Reader<'b> {
+  fn take(&mut self, len: usize) -> Result<&'b [u8], JsError> {
+    if self.0.len() < len {
+      return Err(JsError::new("The tree snapshot is truncated."));
+    }
+    let (taken, rest) = self.0.split_at(len);
+    self.0 = rest;
+    Ok(taken)
+  }
+
+  fn read_uint(&mut self) -> Result<u64, JsError> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+      let byte = self.take(1)?[0];
+      n |= ((byte & 0x7f) as u64) << shift;
+      if byte < 0x80 {
+        return Ok(n);
+      }
+    }
+    Err(JsError::new("The tree snapshot is corrupted."))
+  }
+
+  fn read_str(&mut self) -> Result<String, JsError> {
+    let len = self.read_uint()? as usize;
+    let bytes = self.take(len)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| JsError::new("The tree snapshot is corrupted."))
+  }
+}
@@ -0,0 +1,106 @@
+//! Position mappings of `withSourceMap`, for sources extracted from a larger document
+//! like the script of a Vue SFC or a markdown code fence.
+//!
+//! A mapping is a list of segments, each mapping the parsed source from its
+//! `generated` offset up to the next segment to the text at its `original` position.
+//! Text inside a segment is assumed to be copied verbatim, so positions are shifted.
+
+use crate::encoding::PositionEncoding;
+use crate::sg_node::{pos, Pos, Range};
+
+use serde::Deserialize;
+
+/// A position in the original document, in the unit of `positionEncoding`.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OriginalPos {
+  pub line: u32,
+  pub column: u32,
+  pub index: u32,
+  /// defaults to `index`, which is right for ASCII documents
+  pub byte_offset: Option<u32>,
+}
+
+/// An element of `withSourceMap`.
+#[derive(Clone, Deserialize)]
+pub struct Segment {
+  /// offset in the parsed source, in the unit of `positionEncoding`
+  pub generated: u32,
+  pub original: OriginalPos,
+}
+
+/// Segments of a parsed source, with the position of each `generated` offset.
+pub struct SourceMap {
+  segments: Vec<(Pos, OriginalPos)>,
+  /// The map of the original document to another one, applied after this map.
+  next: Option<Box<SourceMap>>,
+}
+
+impl SourceMap {
+  pub fn new(mut segments: Vec<Segment>, src: &[char], encoding: PositionEncoding) -> Self {
+    segments.sort_by_key(|s| s.generated);
+    let segments = segments
+      .into_iter()
+      .map(|segment| {
+        let offset = encoding
+          .decode_offset(src, segment.generated as usize)
+          .min(src.len());
+        let line = src[..offset].iter().filter(|&&c| c == '\n').count();
+        (pos(src, line, offset, encoding), segment.original)
+      })
+      .collect();
+    Self {
+      segments,
+      next: None,
+    }
+  }
+
+  /// Map positions with this map and then with `next`, whose parsed source is this
+  /// map's original document.
+  pub fn then(self, next: SourceMap) -> Self {
+    Self {
+      next: Some(Box::new(next)),
+      ..self
+    }
+  }
+
+  /// `pos` of the parsed source in the original document. Positions before the
+  /// first segment are not mapped.
+  pub fn map(&self, pos: Pos) -> Pos {
+    let mapped = self.map_segment(pos);
+    match &self.next {
+      Some(next) => next.map(mapped),
+      None => mapped,
+    }
+  }
+
+  fn map_segment(&self, pos: Pos) -> Pos {
+    let Some((generated, original)) = self
+      .segments
+      .iter()
+      .rev()
+      .find(|(generated, _)| generated.index <= pos.index)
+    else {
+      return pos;
+    };
+    let column = if pos.line == generated.line {
+      original.column + (pos.column - generated.column)
+    } else {
+      pos.column
+    };
+    let byte_offset = original.byte_offset.unwrap_or(original.index);
+    Pos {
+      line: original.line + (pos.line - generated.line),
+      column,
+      index: original.index + (pos.index - generated.index),
+      byte_offset: byte_offset + (pos.byte_offset - generated.byte_offset),
+    }
+  }
+
+  pub fn map_range(&self, range: Range) -> Range {
+    Range {
+      start: self.map(range.start),
+      end: self.map(range.end),
+    }
+  }
+}
@@ -0,0 +1,240 @@
+//! Intraprocedural taint tracking for `findTaintFlows`.
+//!
+//! Every function body, and the top level, is analyzed on its own in document order.
+//! A value is tainted if it contains a source match or a tainted variable outside of
+//! sanitizer matches. Assignments and declarations taint or clear their targets, and
+//! a flow is reported when a sink match contains a tainted value. Grammars share no
+//! kind names, so functions, assignments and identifiers are recognized by name.
+
+use crate::doc::WasmDoc;
+use crate::sg_node::MatcherType;
+
+use ast_grep_core::{Node, NodeMatch};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type SgNode<'r> = Node<'r, WasmDoc>;
+
+/// Matchers of a taint rule.
+pub struct TaintSpec {
+  pub sources: Vec<Rc<MatcherType>>,
+  pub sinks: Vec<Rc<MatcherType>>,
+  pub sanitizers: Vec<Rc<MatcherType>>,
+}
+
+/// A tainted value reaching a sink.
+pub struct Flow<'r> {
+  pub source: SgNode<'r>,
+  pub sink: NodeMatch<'r, WasmDoc>,
+  /// The sink's `primary` meta variable.
+  pub primary: Option<Rc<str>>,
+  /// Variables the value was assigned to on its way, in order.
+  pub path: Vec<String>,
+}
+
+#[derive(Clone)]
+struct Origin<'r> {
+  source: SgNode<'r>,
+  path: Vec<String>,
+}
+
+/// An assignment that takes effect once the traversal is past its end.
+struct Update<'r> {
+  end: usize,
+  names: Vec<String>,
+  origin: Option<Origin<'r>>,
+  /// A plain variable target is overwritten, other targets only gain taint.
+  strong: bool,
+}
+
+pub fn is_scope(node: &SgNode<'_>) -> bool {
+  if !node.is_named() || node.is_leaf() {
+    return false;
+  }
+  let kind = node.kind();
+  kind.contains("function") || kind.contains("method") || kind.contains("lambda")
+}
+
+/// Assignment target and value, e.g. `left`/`right` or a declarator's `name`/`value`.
+fn assignment<'r>(node: &SgNode<'r>) -> Option<(SgNode<'r>, SgNode<'r>, bool)> {
+  let kind = node.kind();
+  if !kind.contains("assignment") && !kind.contains("declarator") {
+    return None;
+  }
+  let augmented = kind.contains("augmented");
+  if let (Some(left), Some(right)) = (node.field("left"), node.field("right")) {
+    return Some((left, right, augmented));
+  }
+  let (name, value) = (node.field("name")?, node.field("value")?);
+  Some((name, value, augmented))
+}
+
+/// A variable reference, but not the property in `obj.prop`.
+pub fn is_variable(node: &SgNode<'_>) -> bool {
+  if !node.is_named_leaf() || !node.kind().contains("identifier") {
+    return false;
+  }
+  let Some(parent) = node.parent() else {
+    return true;
+  };
+  ["property", "attribute"]
+    .iter()
+    .filter_map(|field| parent.field(field))
+    .all(|n| n.node_id() != node.node_id())
+}
+
+fn variables(node: &SgNode<'_>, names: &mut Vec<String>) {
+  if is_variable(node) {
+    let name = node.text().to_string();
+    if !names.contains(&name) {
+      names.push(name);
+    }
+    return;
+  }
+  for child in node.children() {
+    variables(&child, names);
+  }
+}
+
+fn matches_any(matchers: &[Rc<MatcherType>], node: &SgNode<'_>) -> bool {
+  matchers
+    .iter()
+    .any(|m| m.match_node(node.clone()).is_some())
+}
+
+struct Analysis<'s, 'r> {
+  spec: &'s TaintSpec,
+  tainted: HashMap<String, Origin<'r>>,
+  pending: Vec<Update<'r>>,
+  /// Nested functions, analyzed after the current one.
+  scopes: Vec<SgNode<'r>>,
+  flows: Vec<Flow<'r>>,
+}
+
+impl<'r> Analysis<'_, 'r> {
+  /// The first taint found in `node`, skipping sanitized subtrees.
+  fn taint_of(&self, node: &SgNode<'r>) -> Option<Origin<'r>> {
+    if matches_any(&self.spec.sanitizers, node) {
+      return None;
+    }
+    if matches_any(&self.spec.sources, node) {
+      return Some(Origin {
+        source: node.clone(),
+        path: vec![],
+      });
+    }
+    if is_variable(node) {
+      return self.tainted.get(node.text().as_ref()).cloned();
+    }
+    node.children().find_map(|child| self.taint_of(&child))
+  }
+
+  fn apply_pending(&mut self, pos: usize) {
+    let (done, rest) = std::mem::take(&mut self.pending)
+      .into_iter()
+      .partition(|u| u.end <= pos);
+    self.pending = rest;
+    for update in done {
+      self.apply(update);
+    }
+  }
+
+  fn apply(&mut self, update: Update<'r>) {
+    for name in update.names {
+      match &update.origin {
+        Some(origin) => {
+          let mut origin = origin.clone();
+          origin.path.push(name.clone());
+          self.tainted.insert(name, origin);
+        }
+        None if update.strong => {
+          self.tainted.remove(&name);
+        }
+        None => (),
+      }
+    }
+  }
+
+  fn check_sink(&mut self, node: &SgNode<'r>) {
+    for sink in &self.spec.sinks {
+      let Some(nm) = sink.match_node(node.clone()) else {
+        continue;
+      };
+      let primary = sink.primary();
+      let checked = match &primary {
+        Some(name) => nm.get_env().get_match(name).cloned(),
+        None => Some(node.clone()),
+      };
+      if let Some(origin) = checked.and_then(|n| self.taint_of(&n)) {
+        let Origin { source, path } = origin;
+        self.flows.push(Flow {
+          source,
+          sink: nm,
+          primary,
+          path,
+        });
+      }
+    }
+  }
+
+  fn visit(&mut self, node: &SgNode<'r>, scope_id: usize) {
+    if node.node_id() != scope_id && is_scope(node) {
+      self.scopes.push(node.clone());
+      return;
+    }
+    self.apply_pending(node.range().start);
+    self.check_sink(node);
+    // a source that is a variable itself, e.g. a parameter, taints the variable
+    if is_variable(node) && matches_any(&self.spec.sources, node) {
+      self.pending.push(Update {
+        end: node.range().end,
+        names: vec![node.text().to_string()],
+        origin: Some(Origin {
+          source: node.clone(),
+          path: vec![],
+        }),
+        strong: true,
+      });
+    } else if let Some((target, value, augmented)) = assignment(node) {
+      let mut names = vec![];
+      variables(&target, &mut names);
+      self.pending.push(Update {
+        end: node.range().end,
+        names,
+        origin: self.taint_of(&value),
+        strong: !augmented && is_variable(&target),
+      });
+    }
+    for child in node.children() {
+      self.visit(&child, scope_id);
+    }
+  }
+}
+
+/// Find the flows from sources to sinks in `root`, in the order sinks are reached.
+/// A source reaching one sink through different paths is reported once.
+pub fn find_flows<'r>(root: &SgNode<'r>, spec: &TaintSpec) -> Vec<Flow<'r>> {
+  let mut analysis = Analysis {
+    spec,
+    tainted: HashMap::new(),
+    pending: vec![],
+    scopes: vec![root.clone()],
+    flows: vec![],
+  };
+  let mut flows: Vec<Flow<'r>> = vec![];
+  while let Some(scope) = analysis.scopes.pop() {
+    analysis.tainted.clear();
+    analysis.pending.clear();
+    analysis.visit(&scope, scope.node_id());
+    for flow in analysis.flows.drain(..) {
+      let duplicate = flows
+        .iter()
+        .any(|f| f.source.range() == flow.source.range() && f.sink.range() == flow.sink.range());
+      if !duplicate {
+        flows.push(flow);
+      }
+    }
+  }
+  flows.sort_by_key(|f| f.sink.range().start);
+  flows
+}
@@ -0,0 +1,277 @@
+//! Structural diffs of two sources for `diffTrees`, as an edit script of subtrees.
+//!
+//! Subtrees are compared by their exact fingerprints, see `clones`. Identical children are
+//! aligned in order first, then identical children out of order are moves, and the rest
+//! is aligned by kind and compared in depth, so a change is reported at the largest subtree
+//! it replaces. A removed subtree identical to an inserted one elsewhere is a move too.
+
+use crate::clones::{self, Fingerprint, FingerprintOptions};
+use crate::doc::WasmDoc;
+use crate::sg_node::{node_range, Range};
+
+use ast_grep_core::Node;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Children lists whose alignment would need more table cells are not aligned,
+/// their differing children are all removed and inserted.
+const MAX_ALIGNMENT: usize = 1 << 22;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EditOp {
+  Inserted,
+  Removed,
+  Moved,
+  Updated,
+}
+
+/// A node of one of the sources in a `TreeEdit`.
+#[derive(Serialize)]
+pub struct EditNode {
+  pub kind: String,
+  pub range: Range,
+  pub text: String,
+}
+
+impl EditNode {
+  fn new(node: &Node<'_, WasmDoc>) -> Self {
+    Self {
+      kind: node.kind().to_string(),
+      range: node_range(node),
+      text: node.text().to_string(),
+    }
+  }
+}
+
+/// One step of the edit script from the old source to the new one.
+#[derive(Serialize)]
+pub struct TreeEdit {
+  pub op: EditOp,
+  /// the subtree in the old source, absent if inserted
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub before: Option<EditNode>,
+  /// the subtree in the new source, absent if removed
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub after: Option<EditNode>,
+}
+
+#[derive(Serialize)]
+pub struct TreeDiff {
+  pub edits: Vec<TreeEdit>,
+}
+
+struct Edit<'t> {
+  op: EditOp,
+  before: Option<Node<'t, WasmDoc>>,
+  after: Option<Node<'t, WasmDoc>>,
+}
+
+struct Differ<'t> {
+  old: HashMap<usize, Fingerprint>,
+  new: HashMap<usize, Fingerprint>,
+  edits: Vec<Edit<'t>>,
+}
+
+/// The edit script turning the tree of `old` into the tree of `new`. Edits follow a
+/// traversal of the aligned trees, so the edits of one parent's children are adjacent.
+pub fn diff_trees<'t>(old: &Node<'t, WasmDoc>, new: &Node<'t, WasmDoc>) -> TreeDiff {
+  let exact = FingerprintOptions {
+    ignore_identifiers: false,
+    ignore_literals: false,
+    ignore_comments: false,
+    ..Default::default()
+  };
+  let mut differ = Differ {
+    old: clones::fingerprints(old, &exact),
+    new: clones::fingerprints(new, &exact),
+    edits: vec![],
+  };
+  differ.diff_node(old.clone(), new.clone());
+  let edits = differ.pair_moves();
+  let edits = edits
+    .into_iter()
+    .map(|e| TreeEdit {
+      op: e.op,
+      before: e.before.as_ref().map(EditNode::new),
+      after: e.after.as_ref().map(EditNode::new),
+    })
+    .collect();
+  TreeDiff { edits }
+}
+
+impl<'t> Differ<'t> {
+  fn same(&self, a: &Node<'t, WasmDoc>, b: &Node<'t, WasmDoc>) -> bool {
+    self.old[&a.node_id()].hash == self.new[&b.node_id()].hash
+  }
+
+  fn push(
+    &mut self,
+    op: EditOp,
+    before: Option<Node<'t, WasmDoc>>,
+    after: Option<Node<'t, WasmDoc>>,
+  ) {
+    self.edits.push(Edit { op, before, after });
+  }
+
+  fn diff_node(&mut self, a: Node<'t, WasmDoc>, b: Node<'t, WasmDoc>) {
+    if self.same(&a, &b) {
+      return;
+    }
+    if a.kind_id() != b.kind_id() {
+      self.push(EditOp::Removed, Some(a), None);
+      self.push(EditOp::Inserted, None, Some(b));
+    } else if a.is_leaf() && b.is_leaf() {
+      self.push(EditOp::Updated, Some(a), Some(b));
+    } else {
+      self.diff_children(a.children().collect(), b.children().collect());
+    }
+  }
+
+  /// Align identical children in order, pair the other identical named children as moves,
+  /// and compare the rest.
+  fn diff_children(&mut self, a: Vec<Node<'t, WasmDoc>>, b: Vec<Node<'t, WasmDoc>>) {
+    let anchors = align(a.len(), b.len(), |i, j| self.same(&a[i], &b[j]));
+    let mut a_rest = vec![true; a.len()];
+    let mut b_rest = vec![true; b.len()];
+    for (i, j) in anchors {
+      (a_rest[i], b_rest[j]) = (false, false);
+    }
+    for i in 0..a.len() {
+      if !a_rest[i] || !a[i].is_named() {
+        continue;
+      }
+      let moved = (0..b.len()).find(|&j| b_rest[j] && self.same(&a[i], &b[j]));
+      if let Some(j) = moved {
+        (a_rest[i], b_rest[j]) = (false, false);
+        self.push(EditOp::Moved, Some(a[i].clone()), Some(b[j].clone()));
+      }
+    }
+    let rest = |nodes: Vec<Node<'t, WasmDoc>>, keep: Vec<bool>| -> Vec<_> {
+      nodes
+        .into_iter()
+        .zip(keep)
+        .filter(|(_, k)| *k)
+        .map(|(n, _)| n)
+        .collect()
+    };
+    self.diff_rest(&rest(a, a_rest), &rest(b, b_rest));
+  }
+
+  /// Children without an identical counterpart: nodes of the same kind are aligned and
+  /// compared in depth. A single leaf replaced by another, like an operator, is an update.
+  fn diff_rest(&mut self, a: &[Node<'t, WasmDoc>], b: &[Node<'t, WasmDoc>]) {
+    let pairs = align(a.len(), b.len(), |i, j| a[i].kind_id() == b[j].kind_id());
+    let (mut i, mut j) = (0, 0);
+    for (ai, bj) in pairs.into_iter().chain([(a.len(), b.len())]) {
+      match (&a[i..ai], &b[j..bj]) {
+        ([old], [new]) if old.is_leaf() && new.is_leaf() => {
+          self.push(EditOp::Updated, Some(old.clone()), Some(new.clone()));
+        }
+        (removed, inserted) => {
+          for old in removed {
+            self.push(EditOp::Removed, Some(old.clone()), None);
+          }
+          for new in inserted {
+            self.push(EditOp::Inserted, None, Some(new.clone()));
+          }
+        }
+      }
+      if ai < a.len() {
+        self.diff_node(a[ai].clone(), b[bj].clone());
+      }
+      (i, j) = (ai + 1, bj + 1);
+    }
+  }
+
+  /// Replace each removed named subtree identical to an inserted one elsewhere, e.g. in
+  /// another parent, by a move at the position of the removal.
+  fn pair_moves(self) -> Vec<Edit<'t>> {
+    let Self {
+      old,
+      new,
+      mut edits,
+    } = self;
+    let mut inserted: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, edit) in edits.iter().enumerate().rev() {
+      if let (EditOp::Inserted, Some(node)) = (edit.op, &edit.after) {
+        if node.is_named() {
+          inserted
+            .entry(new[&node.node_id()].hash)
+            .or_default()
+            .push(index);
+        }
+      }
+    }
+    let mut moved = vec![false; edits.len()];
+    for index in 0..edits.len() {
+      let edit = &edits[index];
+      let Some(node) = edit.before.as_ref().filter(|_| edit.op == EditOp::Removed) else {
+        continue;
+      };
+      if !node.is_named() {
+        continue;
+      }
+      let Some(target) = inserted
+        .get_mut(&old[&node.node_id()].hash)
+        .and_then(|indices| indices.pop())
+      else {
+        continue;
+      };
+      moved[target] = true;
+      let after = edits[target].after.take();
+      let edit = &mut edits[index];
+      edit.op = EditOp::Moved;
+      edit.after = after;
+    }
+    edits
+      .into_iter()
+      .zip(moved)
+      .filter(|(_, moved)| !moved)
+      .map(|(edit, _)| edit)
+      .collect()
+  }
+}
+
+/// The index pairs of the longest common subsequence of two lists by `eq`, after their
+/// common prefix and suffix. Lists too long to align only keep that prefix and suffix.
+fn align(n: usize, m: usize, eq: impl Fn(usize, usize) -> bool) -> Vec<(usize, usize)> {
+  let mut prefix = 0;
+  while prefix < n && prefix < m && eq(prefix, prefix) {
+    prefix += 1;
+  }
+  let mut suffix = 0;
+  while suffix < n - prefix && suffix < m - prefix && eq(n - 1 - suffix, m - 1 - suffix) {
+    suffix += 1;
+  }
+  let (a_end, b_end) = (n - suffix, m - suffix);
+  let mut pairs: Vec<_> = (0..prefix).map(|i| (i, i)).collect();
+  let (rows, cols) = (a_end - prefix, b_end - prefix);
+  if rows > 0 && cols > 0 && rows * cols <= MAX_ALIGNMENT {
+    // lcs[i][j] is the LCS length of the rest of the lists from `prefix + i` and `prefix + j`
+    let mut lcs = vec![vec![0u32; cols + 1]; rows + 1];
+    for i in (0..rows).rev() {
+      for j in (0..cols).rev() {
+        lcs[i][j] = if eq(prefix + i, prefix + j) {
+          lcs[i + 1][j + 1] + 1
+        } else {
+          lcs[i + 1][j].max(lcs[i][j + 1])
+        };
+      }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < rows && j < cols {
+      if eq(prefix + i, prefix + j) {
+        pairs.push((prefix + i, prefix + j));
+        i += 1;
+        j += 1;
+      } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+        i += 1;
+      } else {
+        j += 1;
+      }
+    }
+  }
+  pairs.extend((0..suffix).map(|k| (a_end + k, b_end + k)));
+  pairs
+}
@@ -162,6 +162,20 @@ extern "C" {
 
   #[wasm_bindgen(method, js_name = nodeTypeIsVisible)]
   pub fn node_kind_is_visible(this: &Language, kind_id: u16) -> bool;
+
+  #[wasm_bindgen(method, js_name = lookaheadIterator)]
+  pub fn lookahead_iterator(this: &Language, state_id: u16) -> Option<LookaheadIterator>;
+}
+
+#[wasm_bindgen(module = "web-tree-sitter")]
+extern "C" {
+  /// The newest grammar ABI version web-tree-sitter can parse with.
+  #[wasm_bindgen(thread_local_v2, js_name = LANGUAGE_VERSION)]
+  pub static LANGUAGE_VERSION: u32;
+
+  /// The oldest grammar ABI version web-tree-sitter can parse with.
+  #[wasm_bindgen(thread_local_v2, js_name = MIN_COMPATIBLE_VERSION)]
+  pub static MIN_COMPATIBLE_VERSION: u32;
 }
 
 impl Language {
@@ -217,6 +231,109 @@ impl Default for ParseOptions {
   }
 }
 
+#[wasm_bindgen]
+extern "C" {
+  #[derive(Clone, Debug)]
+  #[wasm_bindgen(extends = Object)]
+  pub type Range;
+
+  #[wasm_bindgen(method, getter, js_name = startIndex)]
+  pub fn start_index(this: &Range) -> u32;
+
+  #[wasm_bindgen(method, getter, js_name = endIndex)]
+  pub fn end_index(this: &Range) -> u32;
+}
+
+impl Range {
+  pub fn new(
+    start_index: u32,
+    end_index: u32,
+    start_position: &Point,
+    end_position: &Point,
+  ) -> Self {
+    let obj = Object::new();
+    Reflect::set(&obj, &"startIndex".into(), &start_index.into()).unwrap();
+    Reflect::set(&obj, &"endIndex".into(), &end_index.into()).unwrap();
+    Reflect::set(&obj, &"startPosition".into(), &start_position.into()).unwrap();
+    Reflect::set(&obj, &"endPosition".into(), &end_position.into()).unwrap();
+    JsCast::unchecked_into(obj)
+  }
+}
+
+#[wasm_bindgen]
+extern "C" {
+  /// Iterates the names of the symbols valid in a parse state.
+  #[derive(Clone, Debug)]
+  pub type LookaheadIterator;
+
+  #[wasm_bindgen(method)]
+  pub fn delete(this: &LookaheadIterator);
+}
+
+#[wasm_bindgen(module = "web-tree-sitter")]
+extern "C" {
+  #[derive(Clone, Debug)]
+  pub type Query;
+
+  // Constructor
+
+  #[wasm_bindgen(catch, constructor)]
+  pub fn new(language: &Language, source: &str) -> Result<Query, JsValue>;
+
+  // Instance Methods
+
+  #[wasm_bindgen(method)]
+  pub fn delete(this: &Query);
+
+  // -> QueryMatch[]
+  #[wasm_bindgen(method)]
+  pub fn matches(this: &Query, node: &SyntaxNode, options: &QueryOptions) -> Box<[JsValue]>;
+}
+
+#[wasm_bindgen]
+extern "C" {
+  #[derive(Clone, Debug)]
+  #[wasm_bindgen(extends = Object)]
+  pub type QueryOptions;
+}
+
+impl QueryOptions {
+  /// Only report matches starting at most `max_start_depth` levels below the node.
+  pub fn new(max_start_depth: u32) -> Self {
+    let obj = Object::new();
+    Reflect::set(&obj, &"maxStartDepth".into(), &max_start_depth.into()).unwrap();
+    JsCast::unchecked_into(obj)
+  }
+}
+
+#[wasm_bindgen]
+extern "C" {
+  #[derive(Clone, Debug)]
+  #[wasm_bindgen(extends = Object)]
+  pub type QueryMatch;
+
+  // Instance Properties
+
+  // -> QueryCapture[]
+  #[wasm_bindgen(method, getter)]
+  pub fn captures(this: &QueryMatch) -> Box<[JsValue]>;
+}
+
+#[wasm_bindgen]
+extern "C" {
+  #[derive(Clone, Debug)]
+  #[wasm_bindgen(extends = Object)]
+  pub type QueryCapture;
+
+  // Instance Properties
+
+  #[wasm_bindgen(method, getter)]
+  pub fn name(this: &QueryCapture) -> String;
+
+  #[wasm_bindgen(method, getter)]
+  pub fn node(this: &QueryCapture) -> SyntaxNode;
+}
+
 #[wasm_bindgen]
 extern "C" {
   #[derive(Clone, Debug)]
@@ -374,6 +491,9 @@ extern "C" {
   #[wasm_bindgen(method, getter, js_name = isError)]
   pub fn is_error(this: &SyntaxNode) -> bool;
 
+  #[wasm_bindgen(method, getter, js_name = parseState)]
+  pub fn parse_state(this: &SyntaxNode) -> u16;
+
   // Instance Methods
 
   #[wasm_bindgen(method)]
@@ -501,6 +621,10 @@ extern "C" {
   // -> Range[]
   #[wasm_bindgen(method, js_name = getChangedRanges)]
   pub fn get_changed_ranges(this: &Tree, other: &Tree) -> Box<[JsValue]>;
+
+  // -> Range[]
+  #[wasm_bindgen(method, js_name = getIncludedRanges)]
+  pub fn get_included_ranges(this: &Tree) -> Array;
 }
 
 impl Clone for Tree {
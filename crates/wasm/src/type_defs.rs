@@ -0,0 +1,115 @@
+//! TypeScript declarations of a language's nodes for `generateTypes`, generated from its
+//! `node-types.json` so that kinds and fields are checked at compile time.
+
+use crate::node_kinds::{resolve, supertypes, NodeType};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The helper types, the same for every language.
+const TYPED_NODE: &str = r#"
+type FieldsOf<K> = K extends keyof Fields ? Fields[K] : {}
+type FieldName<K> = keyof FieldsOf<K> & string
+type FieldOf<K, F> = F extends keyof FieldsOf<K> ? FieldsOf<K>[F] : never
+type FieldKinds<K, F> = FieldOf<K, F> extends { kinds: infer T } ? T : never
+/** A node for each kind of a union. */
+export type NodeOf<K> = K extends string ? TypedSgNode<K> : never
+
+/**
+ * An `SgNode` whose kind is one of `K`, with typed `kind`, `is`, `field` and
+ * `fieldChildren`. Cast an `SgNode` to it, e.g. `sgRoot.root() as Root`.
+ */
+export interface TypedSgNode<K extends string = Kind>
+  extends Omit<SgNode, 'kind' | 'is' | 'field' | 'fieldChildren'> {
+  kind(): K
+  is<T extends K>(kind: T): this is TypedSgNode<T>
+  is(kind: string): boolean
+  field<F extends FieldName<K>>(
+    name: F,
+  ): FieldOf<K, F> extends { required: true } ? NodeOf<FieldKinds<K, F>>
+    : NodeOf<FieldKinds<K, F>> | undefined
+  fieldChildren<F extends FieldName<K>>(name: F): NodeOf<FieldKinds<K, F>>[]
+}
+
+/** The root node of a parsed source. */
+export type Root = TypedSgNode<RootKind>
+"#;
+
+fn literal(s: &str) -> String {
+  serde_json::to_string(s).expect("string should serialize")
+}
+
+fn union<'a>(kinds: impl IntoIterator<Item = &'a String>) -> String {
+  let kinds: Vec<_> = kinds.into_iter().map(|k| literal(k)).collect();
+  if kinds.is_empty() {
+    "never".into()
+  } else {
+    kinds.join(" | ")
+  }
+}
+
+/// The declaration module of the language `lang` with the node types `types`.
+pub fn declarations(lang: &str, types: &[NodeType]) -> String {
+  let supertypes = supertypes(types);
+  let resolved = |types| resolve(types, &supertypes);
+  let named: BTreeSet<_> = types
+    .iter()
+    .filter(|t| t.named && t.subtypes.is_none())
+    .map(|t| &t.kind)
+    .collect();
+  let roots: Vec<_> = types.iter().filter(|t| t.root).map(|t| &t.kind).collect();
+  let mut out = String::new();
+  out.push_str(&format!(
+    "// Generated by ast-grep from the node types of `{lang}`.\n"
+  ));
+  out.push_str("import type { SgNode } from '@ast-grep/wasm'\n");
+  out.push_str("\n/** The kinds of named nodes. */\n");
+  out.push_str("export type Kind =\n");
+  for kind in &named {
+    out.push_str(&format!("  | {}\n", literal(kind)));
+  }
+  if named.is_empty() {
+    out.push_str("  never\n");
+  }
+  let root = if roots.is_empty() {
+    "Kind".into()
+  } else {
+    union(roots)
+  };
+  out.push_str("\n/** The kind of the root node. */\n");
+  out.push_str(&format!("export type RootKind = {root}\n"));
+  out.push_str("\n/** The kinds each supertype stands for. */\n");
+  out.push_str("export interface Supertypes {\n");
+  let sorted: BTreeMap<_, _> = supertypes.iter().collect();
+  for (supertype, subtypes) in sorted {
+    out.push_str(&format!(
+      "  {}: {}\n",
+      literal(supertype),
+      union(&resolved(subtypes))
+    ));
+  }
+  out.push_str("}\n");
+  out.push_str(
+    "\n/** The kinds of the nodes in the fields of each kind, and if the field is required. */\n",
+  );
+  out.push_str("export interface Fields {\n");
+  let with_fields: BTreeMap<_, _> = types
+    .iter()
+    .filter(|t| t.named)
+    .filter_map(|t| Some((&t.kind, t.fields.as_ref().filter(|f| !f.is_empty())?)))
+    .collect();
+  for (kind, fields) in with_fields {
+    out.push_str(&format!("  {}: {{\n", literal(kind)));
+    for (name, field) in fields {
+      let kinds = union(&resolved(&field.types));
+      let required = field.required;
+      out.push_str(&format!(
+        "    {}: {{ kinds: {kinds}; required: {required} }}\n",
+        literal(name)
+      ));
+    }
+    out.push_str("  }\n");
+  }
+  out.push_str("}\n");
+  out.push_str(TYPED_NODE);
+  out
+}
@@ -0,0 +1,264 @@
+//! Rule tests for `testRule`, like the CLI's `ast-grep test`.
+//!
+//! A test has `valid` code the rule must not match and `invalid` code it must match.
+//! The snapshot of an invalid case is the first match's labels and the code with the
+//! rule's fix applied, in the format of the CLI's `__snapshots__` files so that they can
+//! be shared with `ast-grep test`. Label offsets are UTF-8 bytes like in those files.
+
+use crate::doc::{error_chain, WasmDoc};
+use crate::scan;
+use crate::wasm_lang::WasmLang;
+
+use ast_grep_config::{LabelStyle, RuleConfig};
+use ast_grep_core::source::Content;
+use ast_grep_core::{AstGrep, Doc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use wasm_bindgen::JsError;
+
+/// One rule-test YAML document, as in the CLI.
+#[derive(Deserialize)]
+pub struct TestCase {
+  pub id: String,
+  #[serde(default)]
+  pub valid: Vec<String>,
+  #[serde(default)]
+  pub invalid: Vec<String>,
+}
+
+/// The snapshots of one rule's invalid cases by their code, as in the CLI.
+#[derive(Serialize, Deserialize)]
+pub struct TestSnapshots {
+  pub id: String,
+  pub snapshots: BTreeMap<String, TestSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSnapshot {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub fixed: Option<String>,
+  pub labels: Vec<LabelSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelSnapshot {
+  pub source: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub message: Option<String>,
+  pub style: LabelStyle,
+  pub start: usize,
+  pub end: usize,
+}
+
+/// How a case went, named like the CLI's case statuses.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CaseStatus {
+  /// valid code the rule does not match
+  Validated,
+  /// invalid code the rule matches, with the expected snapshot if snapshots are given
+  Reported,
+  /// invalid code the rule matches with another snapshot than expected
+  Wrong,
+  /// invalid code the rule does not match
+  Missing,
+  /// valid code the rule matches
+  Noisy,
+  /// the rule's fix could not be applied
+  Error,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseResult {
+  pub code: String,
+  /// whether the case is from `valid` rather than `invalid`
+  pub valid: bool,
+  pub status: CaseStatus,
+  pub passed: bool,
+  /// the snapshot of an invalid case the rule matches
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub snapshot: Option<TestSnapshot>,
+  /// the snapshot a `wrong` case was expected to have, absent if it had none
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub expected: Option<TestSnapshot>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleTestResult {
+  pub id: String,
+  pub passed: bool,
+  pub cases: Vec<CaseResult>,
+  /// the snapshots of this run as a `__snapshots__` file, to save once they are right
+  pub snapshot_yaml: String,
+}
+
+/// Run every test of `test_yaml` against its rule in `rule_yaml`, comparing the snapshots
+/// of invalid cases with those of `snapshot_yaml` if it is given.
+pub fn test_rules(
+  rule_yaml: &str,
+  test_yaml: &str,
+  snapshot_yaml: Option<&str>,
+) -> Result<Vec<RuleTestResult>, JsError> {
+  let rules = scan::deserialize_rules(rule_yaml, None).map_err(|e| error_chain(&e))?;
+  let tests: Vec<TestCase> =
+    documents(test_yaml).map_err(|e| JsError::new(&format!("Invalid test: {e}")))?;
+  let snapshots = match snapshot_yaml {
+    Some(yaml) => {
+      let snapshots: Vec<TestSnapshots> =
+        documents(yaml).map_err(|e| JsError::new(&format!("Invalid snapshots: {e}")))?;
+      Some(
+        snapshots
+          .into_iter()
+          .map(|s| (s.id, s.snapshots))
+          .collect::<HashMap<_, _>>(),
+      )
+    }
+    None => None,
+  };
+  let mut results = vec![];
+  for test in tests {
+    let Some(rule) = rules.iter().find(|r| r.id == test.id) else {
+      let msg = format!("No rule has the id `{}` of a test.", test.id);
+      return Err(JsError::new(&msg));
+    };
+    let expected = snapshots
+      .as_ref()
+      .map(|snapshots| snapshots.get(&test.id).cloned().unwrap_or_default());
+    results.push(test_rule(rule, test, expected.as_ref())?);
+  }
+  Ok(results)
+}
+
+/// The YAML documents of `yaml`, skipping empty ones like after a trailing `---`.
+fn documents<T: serde::de::DeserializeOwned>(yaml: &str) -> Result<Vec<T>, serde_yaml::Error> {
+  let mut values = vec![];
+  for document in serde_yaml::Deserializer::from_str(yaml) {
+    let value = serde_yaml::Value::deserialize(document)?;
+    if !value.is_null() {
+      values.push(serde_yaml::from_value(value)?);
+    }
+  }
+  Ok(values)
+}
+
+fn test_rule(
+  rule: &RuleConfig<WasmLang>,
+  test: TestCase,
+  expected: Option<&BTreeMap<String, TestSnapshot>>,
+) -> Result<RuleTestResult, JsError> {
+  let mut cases = vec![];
+  for code in test.valid {
+    let root = parse(rule, &code)?;
+    let status = if root.root().find(&rule.matcher).is_some() {
+      CaseStatus::Noisy
+    } else {
+      CaseStatus::Validated
+    };
+    cases.push(CaseResult::new(code, true, status));
+  }
+  let mut actual = BTreeMap::new();
+  for code in test.invalid {
+    let root = parse(rule, &code)?;
+    let case = match generate(rule, root) {
+      Ok(None) => CaseResult::new(code, false, CaseStatus::Missing),
+      Err(e) => CaseResult {
+        error: Some(e),
+        ..CaseResult::new(code, false, CaseStatus::Error)
+      },
+      Ok(Some(snapshot)) => {
+        let expected = expected.map(|e| e.get(&code));
+        let status = match expected {
+          Some(Some(e)) if *e != snapshot => CaseStatus::Wrong,
+          Some(None) => CaseStatus::Wrong,
+          _ => CaseStatus::Reported,
+        };
+        actual.insert(code.clone(), snapshot.clone());
+        CaseResult {
+          snapshot: Some(snapshot),
+          expected: expected
+            .flatten()
+            .filter(|_| status == CaseStatus::Wrong)
+            .cloned(),
+          ..CaseResult::new(code, false, status)
+        }
+      }
+    };
+    cases.push(case);
+  }
+  let snapshots = TestSnapshots {
+    id: test.id,
+    snapshots: actual,
+  };
+  let snapshot_yaml = serde_yaml::to_string(&snapshots)?;
+  Ok(RuleTestResult {
+    passed: cases.iter().all(|c| c.passed),
+    id: snapshots.id,
+    cases,
+    snapshot_yaml,
+  })
+}
+
+impl CaseResult {
+  fn new(code: String, valid: bool, status: CaseStatus) -> Self {
+    Self {
+      code,
+      valid,
+      status,
+      passed: matches!(status, CaseStatus::Validated | CaseStatus::Reported),
+      snapshot: None,
+      expected: None,
+      error: None,
+    }
+  }
+}
+
+fn parse(rule: &RuleConfig<WasmLang>, code: &str) -> Result<AstGrep<WasmDoc>, JsError> {
+  let doc = WasmDoc::try_new(code.to_string(), rule.language)?;
+  Ok(AstGrep::doc(doc))
+}
+
+/// The snapshot of the rule's first match in `root`, `None` if it does not match,
+/// or why its fix failed.
+fn generate(
+  rule: &RuleConfig<WasmLang>,
+  mut root: AstGrep<WasmDoc>,
+) -> Result<Option<TestSnapshot>, String> {
+  let labels = {
+    let node = root.root();
+    let Some(matched) = node.find(&rule.matcher) else {
+      return Ok(None);
+    };
+    let src = matched.get_doc().get_source();
+    rule
+      .get_labels(&matched)
+      .into_iter()
+      .map(|label| {
+        let range = label.range();
+        LabelSnapshot {
+          source: src.get_range(range.clone()).iter().collect(),
+          message: label.message.map(String::from),
+          style: label.style,
+          start: src.byte_offset(range.start),
+          end: src.byte_offset(range.end),
+        }
+      })
+      .collect()
+  };
+  let Some(fixer) = rule.matcher.fixer.first() else {
+    return Ok(Some(TestSnapshot {
+      fixed: None,
+      labels,
+    }));
+  };
+  root.replace(&rule.matcher, fixer)?;
+  Ok(Some(TestSnapshot {
+    fixed: Some(root.root().get_doc().get_source().text()),
+    labels,
+  }))
+}
@@ -4,32 +4,56 @@ use std::str::FromStr;
 
 use ast_grep_core::language::Language;
 use ast_grep_core::matcher::{Pattern, PatternBuilder, PatternError};
+use bit_set::BitSet;
+use js_sys::{ArrayBuffer, Promise, Uint8Array};
 use serde::{de, Deserialize, Deserializer, Serialize};
 use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 
 use crate::doc::WasmDoc;
-use crate::ts_types as ts;
+use crate::integrity;
+use crate::ts_types::{self as ts, JsValueExt};
 
 type LangIndex = u32;
 
 /// Represents a dynamically registered language in WASM.
 /// Languages are not predefined — they must be registered at runtime via `register`.
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+/// Two values are the same language even if their pattern chars differ.
+#[derive(Copy, Clone)]
 pub struct WasmLang {
   index: LangIndex,
   // inline expando char since it is used frequently
   expando: char,
+  meta: char,
+}
+
+impl PartialEq for WasmLang {
+  fn eq(&self, other: &Self) -> bool {
+    self.index == other.index
+  }
+}
+
+impl Eq for WasmLang {}
+
+impl std::hash::Hash for WasmLang {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.index.hash(state);
+  }
 }
 
 impl fmt::Debug for WasmLang {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    let langs = LANGS.lock().expect("debug lock");
-    if let Some(inner) = langs.get(self.index as usize) {
-      write!(f, "WasmLang({})", inner.name)
-    } else {
-      write!(f, "WasmLang(#{})", self.index)
+    // debug formatting can happen while the registry is locked, e.g. in a panic message
+    let name = LANGS
+      .try_lock()
+      .ok()
+      .and_then(|langs| Some(langs.get(self.index as usize)?.name.clone()));
+    match name {
+      Some(name) => write!(f, "WasmLang({name})"),
+      None => write!(f, "WasmLang(#{})", self.index),
     }
   }
 }
@@ -54,10 +78,11 @@ impl FromStr for WasmLang {
   fn from_str(s: &str) -> Result<Self, Self::Err> {
     let langs = LANGS.lock().expect_throw("from_str lock error");
     for (i, inner) in langs.iter().enumerate() {
-      if inner.name == s {
+      if inner.name == s && !inner.retired {
         return Ok(WasmLang {
           index: i as LangIndex,
           expando: inner.expando_char,
+          meta: '$',
         });
       }
     }
@@ -95,78 +120,529 @@ struct TsParser(ts::Parser);
 unsafe impl Send for TsParser {}
 unsafe impl Sync for TsParser {}
 
+#[derive(Clone)]
+struct TsLanguage(ts::Language);
+
+unsafe impl Send for TsLanguage {}
+unsafe impl Sync for TsLanguage {}
+
 struct Inner {
   name: String,
-  parser: TsParser,
+  /// Identifies the grammar binary, see `Grammar::id`.
+  grammar_id: String,
+  language: TsLanguage,
+  /// Created on first use and dropped by `free_parsers`.
+  parser: Option<TsParser>,
   expando_char: char,
+  node_types: Option<String>,
+  extensions: Vec<String>,
+  /// Set when the language is unregistered or its grammar replaced. The slot is kept
+  /// so trees parsed with the old grammar still resolve it, see `check_registered`.
+  retired: bool,
+}
+
+impl Inner {
+  fn retire(&mut self) {
+    if let Some(parser) = self.parser.take() {
+      parser.0.delete();
+    }
+    self.retired = true;
+  }
 }
 
 /// Registration info for a custom WASM language, mirroring napi/pyo3's CustomLang.
+/// The grammar is loaded from exactly one of `library_path` and `library_bytes`.
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WasmLangInfo {
-  pub library_path: String,
+  /// Path or URL of the grammar's WASM binary.
+  #[serde(default)]
+  pub library_path: Option<String>,
+  /// The grammar's WASM binary as an `ArrayBuffer` or `Uint8Array`,
+  /// for environments that cannot fetch it at runtime.
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub library_bytes: JsValue,
+  #[serde(default, deserialize_with = "deserialize_expando")]
   pub expando_char: Option<char>,
+  /// Content of the grammar's `node-types.json`, if available.
+  pub node_types: Option<String>,
+  /// File extensions of the language without the leading dot, reported by `languageInfo`.
+  #[serde(default)]
+  pub extensions: Vec<String>,
+  /// Subresource integrity hash the grammar's binary must match, e.g. `sha384-<base64>`.
+  #[serde(default)]
+  pub integrity: Option<String>,
+}
+
+/// Metadata of a registered language, see `languageInfo`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageInfo {
+  name: String,
+  expando_char: char,
+  /// The default prefix of meta variables, patterns can override it per call.
+  meta_var_char: char,
+  extensions: Vec<String>,
+  /// The tree-sitter ABI version the grammar was generated for.
+  abi_version: u32,
+  /// Languages of the WASM module are all registered at runtime, so this is always
+  /// `false`. Kept so UIs can share the shape with other bindings.
+  builtin: bool,
+}
+
+/// Per-call overrides of the chars used by patterns, see `WasmLang::with_chars`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatternChars {
+  /// Replaces the language's `expandoChar` from registration.
+  #[serde(
+    default,
+    deserialize_with = "deserialize_expando",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub expando_char: Option<char>,
+  /// Prefix of meta variables in patterns and fix templates, `$` by default.
+  #[serde(
+    default,
+    deserialize_with = "deserialize_meta_var_char",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub meta_var_char: Option<char>,
+}
+
+fn deserialize_expando<'de, D>(deserializer: D) -> Result<Option<char>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  deserialize_char(deserializer, "expandoChar")
+}
+
+fn deserialize_meta_var_char<'de, D>(deserializer: D) -> Result<Option<char>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let c = deserialize_char(deserializer, "metaVarChar")?;
+  match c {
+    Some(c) if c.is_alphanumeric() || c == '_' => Err(de::Error::custom(format!(
+      "metaVarChar cannot be a letter, digit or `_`, got `{c}`"
+    ))),
+    c => Ok(c),
+  }
+}
+
+fn deserialize_char<'de, D>(deserializer: D, field: &str) -> Result<Option<char>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let Some(s) = Option::<String>::deserialize(deserializer)? else {
+    return Ok(None);
+  };
+  let mut chars = s.chars();
+  match (chars.next(), chars.next()) {
+    (Some(c), None) if !c.is_whitespace() && !c.is_control() => Ok(Some(c)),
+    _ => Err(de::Error::custom(format!(
+      "{field} must be exactly one non-whitespace character, got `{s}`"
+    ))),
+  }
 }
 
 /// Stores all registered languages.
+/// WASM is single threaded, but async registrations can interleave with other calls
+/// (and each JS worker has its own module instance), so the lock is only held for
+/// short synchronous sections, never across an `.await`.
+/// Indices are stable because slots are never removed: a language is updated in place,
+/// unless its grammar changes, which retires the old slot like unregistering does.
 static LANGS: Mutex<Vec<Inner>> = Mutex::new(Vec::new());
 
+/// Number of grammar binaries loaded so far. Grammars are shared by `Grammar::id`,
+/// so registering aliases or re-registering a language does not load it again.
+static GRAMMAR_LOADS: AtomicUsize = AtomicUsize::new(0);
+
 impl WasmLang {
   /// Register languages from a HashMap of name -> WasmLangInfo.
   /// Can be called multiple times; existing languages are updated.
-  pub async fn register(langs: HashMap<String, WasmLangInfo>) -> Result<(), JsError> {
+  /// Either all languages are registered or, on error, none of them.
+  /// Returns the languages whose grammar was replaced, as they were before.
+  pub async fn register(langs: HashMap<String, WasmLangInfo>) -> Result<Vec<Self>, JsError> {
+    // load every grammar before locking so the lock is never held across an await
+    let mut loaded = Vec::with_capacity(langs.len());
     for (name, custom) in langs {
-      let parser = create_parser(&custom.library_path).await?;
-      let expando = custom.expando_char.unwrap_or('$');
-      let mut registered = LANGS.lock().expect_throw("register lock error");
-      if let Some(entry) = registered.iter_mut().find(|inner| inner.name == name) {
-        entry.parser = parser;
-        entry.expando_char = expando;
-      } else {
-        registered.push(Inner {
-          name,
-          parser,
-          expando_char: expando,
-        });
+      if let Some(node_types) = &custom.node_types {
+        serde_json::from_str::<de::IgnoredAny>(node_types)
+          .map_err(|e| JsError::new(&format!("Invalid node types for `{name}`: {e}")))?;
+      }
+      let mut grammar = Grammar::new(&name, &custom)?;
+      if let Some(integrity) = &custom.integrity {
+        grammar = grammar.verified(&name, integrity).await?;
       }
+      let grammar_id = grammar.id();
+      let shared = loaded
+        .iter()
+        .find(|inner: &&Inner| inner.grammar_id == grammar_id)
+        .map(|inner| inner.language.0.clone())
+        .or_else(|| find_loaded(&grammar_id));
+      let language = match shared {
+        Some(language) => language,
+        None => {
+          let language = grammar.load().await.map_err(|e| load_error(&name, e))?;
+          check_abi(&name, &language)?;
+          language
+        }
+      };
+      let parser = create_parser(&language)?;
+      if let Some(expando) = custom.expando_char {
+        check_expando(&name, expando, &language)?;
+      }
+      loaded.push(Inner {
+        name,
+        grammar_id,
+        language: TsLanguage(language),
+        parser: Some(parser),
+        expando_char: custom.expando_char.unwrap_or('$'),
+        node_types: custom.node_types,
+        extensions: custom.extensions,
+        retired: false,
+      });
     }
-    Ok(())
+    let mut registered = LANGS.lock().expect_throw("register lock error");
+    let mut replaced = vec![];
+    for inner in loaded {
+      let index = registered
+        .iter()
+        .position(|e| e.name == inner.name && !e.retired);
+      match index {
+        Some(i) if registered[i].grammar_id == inner.grammar_id => {
+          if let Some(old) = registered[i].parser.take() {
+            old.0.delete();
+          }
+          registered[i] = inner;
+        }
+        // trees of the old grammar keep its slot, so their kind ids stay meaningful
+        Some(i) => {
+          registered[i].retire();
+          replaced.push(Self::of(i, &registered[i]));
+          registered.push(inner);
+        }
+        None => registered.push(inner),
+      }
+    }
+    Ok(replaced)
   }
 
-  pub(crate) fn get_parser(&self) -> Result<ts::Parser, SgWasmError> {
-    let langs = LANGS.lock().expect_throw("get parser error");
+  /// Unregister the language `name`, returning it or `None` if it is not registered.
+  pub fn unregister(name: &str) -> Option<Self> {
+    let mut langs = LANGS.lock().expect_throw("unregister lock error");
+    let i = langs.iter().position(|e| e.name == name && !e.retired)?;
+    langs[i].retire();
+    Some(Self::of(i, &langs[i]))
+  }
+
+  fn of(index: usize, inner: &Inner) -> Self {
+    WasmLang {
+      index: index as LangIndex,
+      expando: inner.expando_char,
+      meta: '$',
+    }
+  }
+
+  /// Fails if the language was unregistered or its grammar replaced, e.g. for a tree
+  /// parsed before that. Such trees keep working with the old grammar, but their
+  /// nodes cannot be matched or reparsed.
+  pub(crate) fn check_registered(&self) -> Result<(), SgWasmError> {
+    let langs = LANGS.lock().expect_throw("check registered lock error");
     match langs.get(self.index as usize) {
-      Some(inner) => Ok(inner.parser.0.clone()),
-      None => {
-        let name = format!("lang#{}", self.index);
-        Err(SgWasmError::LanguageNotLoaded(name))
-      }
+      Some(inner) if inner.retired => Err(SgWasmError::LanguageRetired(inner.name.clone())),
+      Some(_) => Ok(()),
+      None => Err(SgWasmError::LanguageNotLoaded(format!(
+        "lang#{}",
+        self.index
+      ))),
+    }
+  }
+
+  /// The same language with the pattern chars of `chars` overridden.
+  /// An `expandoChar` override is checked against the grammar like at registration.
+  pub(crate) fn with_chars(self, chars: &PatternChars) -> Result<Self, JsError> {
+    let mut lang = self;
+    if let Some(expando) = chars.expando_char {
+      let langs = LANGS.lock().expect_throw("with chars lock error");
+      let inner = langs
+        .get(self.index as usize)
+        .expect_throw("language is not loaded, call registerDynamicLanguage first");
+      check_expando(&inner.name, expando, &inner.language.0)?;
+      lang.expando = expando;
+    }
+    if let Some(meta) = chars.meta_var_char {
+      lang.meta = meta;
+    }
+    Ok(lang)
+  }
+
+  pub(crate) fn get_parser(&self) -> Result<ts::Parser, SgWasmError> {
+    let mut langs = LANGS.lock().expect_throw("get parser error");
+    let Some(inner) = langs.get_mut(self.index as usize) else {
+      let name = format!("lang#{}", self.index);
+      return Err(SgWasmError::LanguageNotLoaded(name));
+    };
+    if inner.retired {
+      return Err(SgWasmError::LanguageRetired(inner.name.clone()));
+    }
+    if let Some(parser) = &inner.parser {
+      return Ok(parser.0.clone());
+    }
+    let parser = create_parser(&inner.language.0)?;
+    inner.parser = Some(parser.clone());
+    Ok(parser.0)
+  }
+
+  /// Whether `other` is the grammar of the language, either the same object or one
+  /// loaded from the same binary, which has the same kinds and fields.
+  pub(crate) fn is_grammar_of(&self, other: &ts::Language) -> bool {
+    let ours = self.get_ts_language();
+    if ours == *other {
+      return true;
+    }
+    ours.abi_version() == other.abi_version()
+      && ours.node_kind_count() == other.node_kind_count()
+      && ours.field_count() == other.field_count()
+      && (0..ours.node_kind_count()).all(|id| {
+        ours.node_kind_for_id(id) == other.node_kind_for_id(id)
+          && ours.node_kind_is_named(id) == other.node_kind_is_named(id)
+      })
+      && (1..=ours.field_count())
+        .all(|id| ours.field_name_for_id(id) == other.field_name_for_id(id))
+  }
+
+  /// Returns the raw `node-types.json` provided at registration.
+  pub(crate) fn node_types(&self) -> Option<String> {
+    let langs = LANGS.lock().expect_throw("node types lock error");
+    langs.get(self.index as usize)?.node_types.clone()
+  }
+
+  /// Returns the name the language is registered as.
+  pub(crate) fn name(&self) -> String {
+    let langs = LANGS.lock().expect_throw("language name lock error");
+    let inner = langs
+      .get(self.index as usize)
+      .expect_throw("language is not loaded, call registerDynamicLanguage first");
+    inner.name.clone()
+  }
+
+  /// Returns the registration metadata of the language.
+  pub(crate) fn info(&self) -> LanguageInfo {
+    let langs = LANGS.lock().expect_throw("language info lock error");
+    let inner = langs
+      .get(self.index as usize)
+      .expect_throw("language is not loaded, call registerDynamicLanguage first");
+    LanguageInfo {
+      name: inner.name.clone(),
+      expando_char: inner.expando_char,
+      meta_var_char: '$',
+      extensions: inner.extensions.clone(),
+      abi_version: inner.language.0.abi_version(),
+      builtin: false,
     }
   }
 
   pub(crate) fn get_ts_language(&self) -> ts::Language {
-    self
-      .get_parser()
-      .expect_throw("language is not loaded, call registerDynamicLanguage first")
-      .language()
-      .expect_throw("parser has no language set")
+    let langs = LANGS.lock().expect_throw("get language error");
+    let inner = langs
+      .get(self.index as usize)
+      .expect_throw("language is not loaded, call registerDynamicLanguage first");
+    inner.language.0.clone()
+  }
+
+  /// Delete all cached parsers to release their memory.
+  /// Languages stay registered and parsers are recreated on demand.
+  pub(crate) fn free_parsers() {
+    let mut langs = LANGS.lock().expect_throw("free parsers lock error");
+    for inner in langs.iter_mut() {
+      if let Some(parser) = inner.parser.take() {
+        parser.0.delete();
+      }
+    }
+  }
+
+  /// Returns the number of registered languages, of cached parsers and of grammar loads.
+  pub(crate) fn registry_stats() -> (usize, usize, usize) {
+    let langs = LANGS.lock().expect_throw("registry stats lock error");
+    let registered = langs.iter().filter(|inner| !inner.retired).count();
+    let parsers = langs.iter().filter(|inner| inner.parser.is_some()).count();
+    (registered, parsers, GRAMMAR_LOADS.load(Ordering::Relaxed))
   }
 }
 
-async fn create_parser(parser_path: &str) -> Result<TsParser, SgWasmError> {
+/// Grammars are generated for an ABI version of tree-sitter, and web-tree-sitter parses
+/// with a range of them, so older grammars within the range are accepted as they are.
+/// Checked at registration, since parsers of other versions fail only when they are created.
+fn check_abi(name: &str, lang: &ts::Language) -> Result<(), JsError> {
+  let abi = lang.abi_version();
+  let min = ts::MIN_COMPATIBLE_VERSION.with(|v| *v);
+  let max = ts::LANGUAGE_VERSION.with(|v| *v);
+  let hint = if abi < min {
+    "Regenerate it with a newer tree-sitter CLI"
+  } else if abi > max {
+    "Use a newer web-tree-sitter, or regenerate the grammar"
+  } else {
+    return Ok(());
+  };
+  let msg = format!(
+    "The grammar of `{name}` has ABI version {abi}, but web-tree-sitter supports {min} through {max}. {hint}, e.g. with `tree-sitter generate --abi {max}`."
+  );
+  Err(JsError::new(&msg))
+}
+
+/// Binaries built for another version of web-tree-sitter can fail to link, with errors
+/// that do not name the grammar.
+fn load_error(name: &str, err: SgWasmError) -> JsError {
+  let msg = format!(
+    "Failed to load the grammar of `{name}`: {err}. Grammars built for another version of web-tree-sitter may not load, rebuild it with `tree-sitter build --wasm`."
+  );
+  JsError::new(&msg)
+}
+
+/// An expando char that is also a token of the grammar would make
+/// pattern pre-processing produce ambiguous code.
+fn check_expando(name: &str, expando: char, lang: &ts::Language) -> Result<(), JsError> {
+  if lang.id_for_node_kind(&expando.to_string(), false) != 0 {
+    let msg = format!("expandoChar `{expando}` is a token in language `{name}`. Use another char.");
+    return Err(JsError::new(&msg));
+  }
+  Ok(())
+}
+
+fn create_parser(lang: &ts::Language) -> Result<TsParser, SgWasmError> {
   let parser = ts::Parser::new()?;
-  let lang = get_lang(parser_path).await?;
-  parser.set_language(Some(&lang))?;
+  parser.set_language(Some(lang))?;
   Ok(TsParser(parser))
 }
 
-async fn get_lang(parser_path: &str) -> Result<ts::Language, SgWasmError> {
-  let lang = ts::Language::load_path(parser_path).await?;
-  Ok(lang)
+/// Returns the grammar of an already registered language with the same `Grammar::id`.
+fn find_loaded(grammar_id: &str) -> Option<ts::Language> {
+  let langs = LANGS.lock().expect_throw("find loaded lock error");
+  let inner = langs.iter().find(|inner| inner.grammar_id == grammar_id)?;
+  Some(inner.language.0.clone())
+}
+
+#[wasm_bindgen(inline_js = r#"
+export async function readGrammar(path) {
+  const isNode = typeof process !== 'undefined' && !!process.versions?.node
+  if (isNode && !/^https?:/i.test(path)) {
+    const fs = await import('node:fs/promises')
+    return new Uint8Array(await fs.readFile(path.startsWith('file:') ? new URL(path) : path))
+  }
+  const response = await fetch(path)
+  if (!response.ok) {
+    throw new Error(`Failed to fetch the grammar ${path}: ${response.status} ${response.statusText}`)
+  }
+  return new Uint8Array(await response.arrayBuffer())
+}
+"#)]
+extern "C" {
+  /// Fetch a grammar binary like web-tree-sitter's `Language.load`.
+  #[wasm_bindgen(js_name = readGrammar)]
+  fn read_grammar(path: &str) -> Promise;
+}
+
+/// Where a grammar's WASM binary is loaded from.
+enum Grammar {
+  Path(String),
+  Bytes(Uint8Array),
+}
+
+impl Grammar {
+  fn new(name: &str, info: &WasmLangInfo) -> Result<Self, JsError> {
+    let bytes = &info.library_bytes;
+    let has_bytes = !bytes.is_undefined() && !bytes.is_null();
+    match (&info.library_path, has_bytes) {
+      (Some(path), false) => Ok(Grammar::Path(path.clone())),
+      (None, true) => {
+        if let Some(array) = bytes.dyn_ref::<Uint8Array>() {
+          Ok(Grammar::Bytes(array.clone()))
+        } else if let Some(buffer) = bytes.dyn_ref::<ArrayBuffer>() {
+          Ok(Grammar::Bytes(Uint8Array::new(buffer)))
+        } else {
+          let msg = format!("libraryBytes of `{name}` must be an ArrayBuffer or Uint8Array.");
+          Err(JsError::new(&msg))
+        }
+      }
+      _ => {
+        let msg = format!("Language `{name}` needs exactly one of libraryPath and libraryBytes.");
+        Err(JsError::new(&msg))
+      }
+    }
+  }
+
+  /// The grammar with the bytes of its binary checked against `integrity`. A path is
+  /// fetched once and its checked bytes loaded, so the binary cannot change in between.
+  async fn verified(self, name: &str, integrity: &str) -> Result<Self, JsError> {
+    let bytes = match self {
+      Grammar::Bytes(bytes) => bytes.to_vec(),
+      Grammar::Path(path) => {
+        let fetched = JsFuture::from(read_grammar(&path)).await.lift_error()?;
+        fetched.unchecked_into::<Uint8Array>().to_vec()
+      }
+    };
+    integrity::verify(name, integrity, &bytes)?;
+    Ok(Grammar::Bytes(Uint8Array::from(bytes.as_slice())))
+  }
+
+  /// The path, or a hash of the bytes, so identical binaries are loaded once.
+  fn id(&self) -> String {
+    match self {
+      Grammar::Path(path) => path.clone(),
+      Grammar::Bytes(bytes) => {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(bytes.to_vec());
+        format!("sha256:{}", crate::hex(&digest))
+      }
+    }
+  }
+
+  async fn load(&self) -> Result<ts::Language, SgWasmError> {
+    let lang = match self {
+      Grammar::Path(path) => ts::Language::load_path(path).await?,
+      Grammar::Bytes(bytes) => ts::Language::load_bytes(bytes).await?,
+    };
+    GRAMMAR_LOADS.fetch_add(1, Ordering::Relaxed);
+    Ok(lang)
+  }
+}
+
+/// Which text nodes `WasmLang::text_kinds` looks up.
+#[derive(Clone, Copy)]
+pub enum TextKind {
+  String,
+  Comment,
+}
+
+impl WasmLang {
+  /// The kind ids of the string or comment nodes of this language, and of the
+  /// interpolations a string walk stops at. `None` if the language has no known kinds.
+  pub fn text_kinds(&self, text: TextKind) -> Option<(BitSet, BitSet)> {
+    let name = self.name();
+    let entry = TEXT_KINDS
+      .iter()
+      .find(|entry| entry.langs.contains(&name.as_str()))?;
+    let ids = |kinds: &[&str]| {
+      kinds
+        .iter()
+        .map(|kind| self.kind_to_id(kind) as usize)
+        .filter(|id| *id != 0)
+        .collect::<BitSet>()
+    };
+    Some(match text {
+      TextKind::String => (ids(entry.strings), ids(entry.interpolations)),
+      TextKind::Comment => (ids(entry.comments), BitSet::new()),
+    })
+  }
 }
 
 impl Language for WasmLang {
+  fn meta_var_char(&self) -> char {
+    self.meta
+  }
+
   fn expando_char(&self) -> char {
     self.expando
   }
@@ -179,7 +655,21 @@ impl Language for WasmLang {
   }
 
   fn pre_process_pattern<'q>(&self, query: &'q str) -> Cow<'q, str> {
-    pre_process_pattern(self.expando_char(), query)
+    pre_process_pattern(self.expando_char(), self.meta_var_char(), query)
+  }
+
+  fn parse_as_context(&self, kind: &str) -> Option<(&'static str, &'static str)> {
+    let name = self.name();
+    PARSE_AS
+      .iter()
+      .find(|(langs, k, _, _)| *k == kind && langs.contains(&name.as_str()))
+      .map(|(_, _, before, after)| (*before, *after))
+  }
+
+  fn kind_names(&self) -> Option<Vec<String>> {
+    let lang = self.get_ts_language();
+    let names = (0..lang.node_kind_count()).map(|id| lang.node_kind_for_id(id).unwrap_or_default());
+    Some(names.collect())
   }
 
   fn kind_to_id(&self, kind: &str) -> u16 {
@@ -193,21 +683,251 @@ impl Language for WasmLang {
   }
 }
 
-fn pre_process_pattern(expando: char, query: &str) -> Cow<'_, str> {
+const JS: &[&str] = &["javascript", "typescript", "tsx"];
+const TS: &[&str] = &["typescript", "tsx"];
+
+/// The languages, the node kind and the code around a pattern parsed inside that kind,
+/// for `parseAs`. The code before a pattern is a single ASCII line, see `dumpPattern`.
+const PARSE_AS: &[(&[&str], &str, &str, &str)] = &[
+  (JS, "switch_body", "switch (x) { ", " }"),
+  (JS, "class_body", "class T { ", " }"),
+  (JS, "object", "({ ", " })"),
+  (JS, "formal_parameters", "function f(", ") {}"),
+  (JS, "arguments", "f(", ")"),
+  (JS, "statement_block", "function f() { ", " }"),
+  (TS, "interface_body", "interface T { ", " }"),
+  (TS, "object_type", "type T = { ", " }"),
+  (&["python"], "argument_list", "f(", ")"),
+  (&["python"], "parameters", "def f(", "): pass"),
+  (&["python"], "dictionary", "{", "}"),
+  (&["rust"], "match_block", "match x { ", " }"),
+  (&["rust"], "declaration_list", "impl T { ", " }"),
+  (&["rust"], "field_declaration_list", "struct T { ", " }"),
+  (&["rust"], "arguments", "fn f() { f(", ") }"),
+  (&["rust"], "parameters", "fn f(", ") {}"),
+  (&["go"], "expression_switch_statement", "switch x { ", " }"),
+  (&["go"], "field_declaration_list", "type T struct { ", " }"),
+  (&["go"], "literal_value", "T{", "}"),
+  (&["go"], "parameter_list", "func f(", ") {}"),
+  (&["go"], "argument_list", "func f() { f(", ") }"),
+  (
+    &["java"],
+    "switch_block",
+    "class T { void f() { switch (x) { ",
+    " } } }",
+  ),
+  (&["java"], "class_body", "class T { ", " }"),
+  (
+    &["java"],
+    "formal_parameters",
+    "class T { void f(",
+    ") {} }",
+  ),
+  (
+    &["java"],
+    "argument_list",
+    "class T { void f() { f(",
+    "); } }",
+  ),
+];
+
+/// The string, interpolation and comment kinds of a language, for `isInsideString` and
+/// `isInsideComment`. Interpolations are code inside a string, like `${x}` in a template.
+struct TextKinds {
+  langs: &'static [&'static str],
+  strings: &'static [&'static str],
+  interpolations: &'static [&'static str],
+  comments: &'static [&'static str],
+}
+
+const TEXT_KINDS: &[TextKinds] = &[
+  TextKinds {
+    langs: JS,
+    strings: &["string", "template_string"],
+    interpolations: &["template_substitution"],
+    comments: &["comment", "html_comment"],
+  },
+  TextKinds {
+    langs: &["python"],
+    strings: &["string", "concatenated_string"],
+    interpolations: &["interpolation"],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["rust"],
+    strings: &["string_literal", "raw_string_literal"],
+    interpolations: &[],
+    comments: &["line_comment", "block_comment"],
+  },
+  TextKinds {
+    langs: &["go"],
+    strings: &["interpreted_string_literal", "raw_string_literal"],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["java"],
+    strings: &["string_literal"],
+    interpolations: &["string_interpolation"],
+    comments: &["line_comment", "block_comment"],
+  },
+  TextKinds {
+    langs: &["c"],
+    strings: &["string_literal", "concatenated_string", "system_lib_string"],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["cpp"],
+    strings: &[
+      "string_literal",
+      "raw_string_literal",
+      "concatenated_string",
+      "system_lib_string",
+    ],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["csharp"],
+    strings: &[
+      "string_literal",
+      "verbatim_string_literal",
+      "raw_string_literal",
+      "interpolated_string_expression",
+    ],
+    interpolations: &["interpolation"],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["kotlin"],
+    strings: &["string_literal"],
+    interpolations: &["interpolated_expression", "interpolated_identifier"],
+    comments: &["line_comment", "multiline_comment"],
+  },
+  TextKinds {
+    langs: &["ruby"],
+    strings: &["string", "chained_string", "heredoc_body"],
+    interpolations: &["interpolation"],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["bash"],
+    strings: &[
+      "string",
+      "raw_string",
+      "ansi_c_string",
+      "translated_string",
+      "heredoc_body",
+    ],
+    interpolations: &["command_substitution", "expansion", "simple_expansion"],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["php"],
+    strings: &["string", "encapsed_string", "heredoc", "nowdoc"],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["scala"],
+    strings: &["string", "interpolated_string_expression"],
+    interpolations: &["interpolation"],
+    comments: &["comment", "block_comment"],
+  },
+  TextKinds {
+    langs: &["swift"],
+    strings: &[
+      "line_string_literal",
+      "multi_line_string_literal",
+      "raw_string_literal",
+    ],
+    interpolations: &["interpolated_expression", "raw_str_interpolation"],
+    comments: &["comment", "multiline_comment"],
+  },
+  TextKinds {
+    langs: &["lua"],
+    strings: &["string"],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["elixir"],
+    strings: &["string", "charlist", "sigil"],
+    interpolations: &["interpolation"],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["haskell"],
+    strings: &["string"],
+    interpolations: &[],
+    comments: &["comment", "haddock"],
+  },
+  TextKinds {
+    langs: &["css"],
+    strings: &["string_value"],
+    interpolations: &[],
+    comments: &["comment", "js_comment"],
+  },
+  TextKinds {
+    langs: &["html"],
+    strings: &["quoted_attribute_value"],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["json"],
+    strings: &["string"],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["yaml"],
+    strings: &[
+      "double_quote_scalar",
+      "single_quote_scalar",
+      "block_scalar",
+      "string_scalar",
+    ],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["nix"],
+    strings: &["string_expression", "indented_string_expression"],
+    interpolations: &["interpolation"],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["solidity"],
+    strings: &["string", "hex_string_literal", "unicode_string_literal"],
+    interpolations: &[],
+    comments: &["comment"],
+  },
+  TextKinds {
+    langs: &["hcl"],
+    strings: &["quoted_template", "heredoc_template"],
+    interpolations: &["template_interpolation", "template_directive"],
+    comments: &["comment"],
+  },
+];
+
+/// Replace the `meta` sigils of meta variables with `expando`, keeping other `meta` chars.
+fn pre_process_pattern(expando: char, meta: char, query: &str) -> Cow<'_, str> {
   let mut ret = Vec::with_capacity(query.len());
   let mut dollar_count = 0;
   for c in query.chars() {
-    if c == '$' {
+    if c == meta {
       dollar_count += 1;
       continue;
     }
     let need_replace = matches!(c, 'A'..='Z' | '_') || dollar_count == 3;
-    let sigil = if need_replace { expando } else { '$' };
+    let sigil = if need_replace { expando } else { meta };
     ret.extend(std::iter::repeat(sigil).take(dollar_count));
     dollar_count = 0;
     ret.push(c);
   }
-  let sigil = if dollar_count == 3 { expando } else { '$' };
+  let sigil = if dollar_count == 3 { expando } else { meta };
   ret.extend(std::iter::repeat(sigil).take(dollar_count));
   Cow::Owned(ret.into_iter().collect())
 }
@@ -219,6 +939,7 @@ pub enum SgWasmError {
   ParserError(ts::ParserError),
   LanguageError(ts::LanguageError),
   LanguageNotLoaded(String),
+  LanguageRetired(String),
   FailedToParse,
 }
 
@@ -226,7 +947,7 @@ impl fmt::Display for SgWasmError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       SgWasmError::ParserError(err) => write!(f, "Parser error: {}", err.message()),
-      SgWasmError::LanguageError(err) => write!(f, "Language error: {:?}", err),
+      SgWasmError::LanguageError(err) => write!(f, "Language error: {}", err.message()),
       SgWasmError::LanguageNotLoaded(name) => {
         write!(
           f,
@@ -234,6 +955,10 @@ impl fmt::Display for SgWasmError {
           name
         )
       }
+      SgWasmError::LanguageRetired(name) => write!(
+        f,
+        "Language `{name}` was unregistered or its grammar replaced after this was created. Parse the source or compile the rules again."
+      ),
       SgWasmError::FailedToParse => write!(f, "Failed to parse"),
     }
   }
@@ -1,5 +1,15 @@
 const path = require("path");
+const fs = require("fs");
 
 exports.parserPath = function (lang) {
   return require.resolve(`tree-sitter-${lang}/tree-sitter-${lang}.wasm`);
 };
+
+exports.parserBytes = function (lang) {
+  return new Uint8Array(fs.readFileSync(exports.parserPath(lang)));
+};
+
+exports.nodeTypesJson = function (lang) {
+  const file = require.resolve(`tree-sitter-${lang}/src/node-types.json`);
+  return fs.readFileSync(file, "utf8");
+};
@@ -21,7 +21,15 @@ pub fn generate_schema() -> Result<()> {
   let xtask_path = std::env::var("CARGO_MANIFEST_DIR")?;
   let rule_path = std::fs::canonicalize(format!("{xtask_path}/../schemas/rule.json"))?;
   let mut file = File::create(rule_path)?;
-  to_writer_pretty(&mut file, &schema).context("cannot print JSON schema")
+  to_writer_pretty(&mut file, &schema).context("cannot print JSON schema")?;
+  generate_ts_types(&schema)
+}
+
+/// Write the TypeScript counterpart of rule.json, shipped in the d.ts of the wasm package.
+fn generate_ts_types(schema: &Schema) -> Result<()> {
+  let xtask_path = std::env::var("CARGO_MANIFEST_DIR")?;
+  let ts_path = format!("{xtask_path}/../crates/wasm/src/rule_types.d.ts");
+  std::fs::write(ts_path, schema_to_ts(schema.as_value())).context("cannot print TS types")
 }
 
 fn generate_lang_schemas() -> Result<()> {
@@ -87,13 +95,11 @@ fn add_lang_info_to_schema<T: LanguageExt + Alias>(
     Value::Array(get_fields(&lang.get_ts_language())),
   );
 
-  // insert kind to relation and rule
-  insert_kind(relation_props, &lang)?;
-  let rule = definitions
-    .get_mut("SerializableRule")
-    .context("must have SerializableRule")?;
-  let rule_props = rule.get_mut("properties").context("must have properties")?;
-  insert_kind(rule_props, &lang)?;
+  // insert kind to the kind names of relation and rule
+  let kind_style = definitions
+    .get_mut("KindStyle")
+    .context("must have KindStyle")?;
+  insert_kind(kind_style, &lang)?;
 
   // insert language
   let language = definitions
@@ -116,9 +122,11 @@ fn add_lang_info_to_schema<T: LanguageExt + Alias>(
 
 fn insert_kind<L: LanguageExt>(schema: &mut Value, lang: &L) -> Result<()> {
   let named_nodes = get_named_nodes(&lang.get_ts_language());
+  // the string variant, the regex object matches any kind name
   let kind = schema
-    .get_mut("kind")
-    .context("must have kind")?
+    .get_mut("anyOf")
+    .and_then(|variants| variants.get_mut(0))
+    .context("must have kind string")?
     .as_object_mut()
     .context("kind must be an object")?;
   kind.insert("enum".to_string(), Value::Array(named_nodes));
@@ -155,6 +163,124 @@ fn get_fields(lang: &TSLanguage) -> Vec<Value> {
     .collect()
 }
 
+fn schema_to_ts(schema: &Value) -> String {
+  let mut out =
+    String::from("// Generated by `cargo xtask schema` from schemas/rule.json. DO NOT EDIT.\n");
+  push_decl(&mut out, "RuleConfig", schema);
+  if let Some(Value::Object(defs)) = schema.get("$defs") {
+    for (name, def) in defs {
+      push_decl(&mut out, name, def);
+    }
+  }
+  out
+}
+
+fn push_decl(out: &mut String, name: &str, schema: &Value) {
+  out.push('\n');
+  push_doc(out, schema, "");
+  if schema.get("properties").is_some() && schema["type"] == "object" {
+    out.push_str(&format!(
+      "export interface {name} {}\n",
+      object_to_ts(schema, "")
+    ));
+  } else {
+    out.push_str(&format!("export type {name} = {};\n", to_ts(schema, "")));
+  }
+}
+
+fn push_doc(out: &mut String, schema: &Value, indent: &str) {
+  let Some(description) = schema.get("description").and_then(Value::as_str) else {
+    return;
+  };
+  out.push_str(&format!("{indent}/**\n"));
+  for line in description.replace("*/", "*\\/").lines() {
+    let line = format!("{indent} * {line}");
+    out.push_str(line.trim_end());
+    out.push('\n');
+  }
+  out.push_str(&format!("{indent} */\n"));
+}
+
+/// The TS type of `schema`, with `indent` the indentation of the line it starts on.
+fn to_ts(schema: &Value, indent: &str) -> String {
+  if let Some(Value::String(reference)) = schema.get("$ref") {
+    return reference.trim_start_matches("#/$defs/").to_string();
+  }
+  if let Some(value) = schema.get("const") {
+    return value.to_string();
+  }
+  if let Some(Value::Array(values)) = schema.get("enum") {
+    return union(values.iter().map(Value::to_string));
+  }
+  if let Some(Value::Array(variants)) = schema.get("anyOf").or_else(|| schema.get("oneOf")) {
+    return union(variants.iter().map(|v| to_ts(v, indent)));
+  }
+  match schema.get("type") {
+    Some(Value::String(ty)) => type_to_ts(ty, schema, indent),
+    Some(Value::Array(types)) => union(
+      types
+        .iter()
+        .filter_map(Value::as_str)
+        .map(|ty| type_to_ts(ty, schema, indent)),
+    ),
+    _ => "unknown".into(),
+  }
+}
+
+fn union(variants: impl Iterator<Item = String>) -> String {
+  let mut seen = BTreeSet::new();
+  let variants: Vec<_> = variants.filter(|v| seen.insert(v.clone())).collect();
+  variants.join(" | ")
+}
+
+fn type_to_ts(ty: &str, schema: &Value, indent: &str) -> String {
+  match ty {
+    "integer" | "number" => "number".into(),
+    "string" | "boolean" | "null" => ty.into(),
+    "array" => match schema.get("items") {
+      Some(items) => {
+        let item = to_ts(items, indent);
+        if item.contains(' ') {
+          format!("({item})[]")
+        } else {
+          format!("{item}[]")
+        }
+      }
+      None => "unknown[]".into(),
+    },
+    "object" => object_to_ts(schema, indent),
+    _ => "unknown".into(),
+  }
+}
+
+fn object_to_ts(schema: &Value, indent: &str) -> String {
+  let Some(Value::Object(props)) = schema.get("properties") else {
+    return match schema.get("additionalProperties") {
+      Some(value @ Value::Object(_)) => format!("Record<string, {}>", to_ts(value, indent)),
+      _ => "Record<string, unknown>".into(),
+    };
+  };
+  let required: Vec<_> = match schema.get("required") {
+    Some(Value::Array(required)) => required.iter().filter_map(Value::as_str).collect(),
+    _ => vec![],
+  };
+  let inner = format!("{indent}  ");
+  let mut out = String::from("{\n");
+  for (name, prop) in props {
+    push_doc(&mut out, prop, &inner);
+    let optional = if required.contains(&name.as_str()) {
+      ""
+    } else {
+      "?"
+    };
+    let ty = to_ts(prop, &inner);
+    out.push_str(&format!("{inner}{name}{optional}: {ty};\n"));
+  }
+  out.push_str(indent);
+  out.push('}');
+  out
+}
+
 #[derive(Clone)]
 struct PlaceholderLang;
 // reference: https://github.com/GREsau/schemars/blob/9415fcb57b85f12e07afeb1dd16184bab0e26a84/schemars/src/json_schema_impls/primitives.rs#L8
@@ -200,4 +326,27 @@ mod test {
     let ret = generate_schema();
     assert!(ret.is_ok());
   }
+
+  #[test]
+  fn test_schema_to_ts() {
+    let schema = json_schema!({
+      "type": "object",
+      "properties": {
+        "id": { "description": "rule id", "type": "string" },
+        "fix": { "anyOf": [{ "$ref": "#/$defs/Fixer" }, { "type": "null" }] },
+        "labels": { "type": ["object", "null"], "additionalProperties": { "type": "integer" } },
+      },
+      "required": ["id"],
+      "$defs": {
+        "Fixer": { "type": "array", "items": { "enum": ["a", "b"] } },
+      }
+    });
+    let ts = schema_to_ts(schema.as_value());
+    assert!(
+      ts.contains("export interface RuleConfig {\n  /**\n   * rule id\n   */\n  id: string;\n")
+    );
+    assert!(ts.contains("  fix?: Fixer | null;\n"));
+    assert!(ts.contains("  labels?: Record<string, number> | null;\n}"));
+    assert!(ts.contains("export type Fixer = (\"a\" | \"b\")[];"));
+  }
 }